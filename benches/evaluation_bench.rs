@@ -4,7 +4,6 @@ use ferrite::evaluation::evaluate;
 use criterion::{criterion_group, criterion_main, Criterion};
 
 fn bench_evaluation(c: &mut Criterion) {
-    ferrite::board::init();
     let positions = vec![
         ("startpos", Board::default()),
         ("middlegame", Board::from_str("r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4").unwrap()),
@@ -1,18 +1,25 @@
 use std::str::FromStr;
 use ferrite::board::{Board, MoveGen};
-use ferrite::search::{search, SearchState};
+use ferrite::search::{search, SearchState, SearchTables};
+use ferrite::SearchLimits;
 use criterion::{criterion_group, criterion_main, Criterion};
 
+/// Search `board` from scratch, reusing `state`'s tables instead of allocating new ones
+fn fresh_search(state: &mut SearchState, board: &Board, depth: u8) -> u64 {
+    state.new_game();
+    state.reset();
+    search(board, state, &SearchLimits::depth(depth)).nodes
+}
+
 fn bench_search(c: &mut Criterion) {
-    ferrite::board::init();
     let board = Board::default();
+    // Small enough to clear on every iteration, big enough for these searches
+    let mut state = SearchState::with_tables(SearchTables::new(1));
+    state.silent = true;
+    state.set_deterministic(true);
 
     c.bench_function("search_depth_3_startpos", |b| {
-        b.iter(|| {
-            let mut state = SearchState::new();
-            state.silent = true;
-            search(&board, &mut state, 3)
-        })
+        b.iter(|| fresh_search(&mut state, &board, 3))
     });
 
     let kiwipete = Board::from_str(
@@ -20,24 +27,15 @@ fn bench_search(c: &mut Criterion) {
     ).unwrap();
 
     c.bench_function("search_depth_3_kiwipete", |b| {
-        b.iter(|| {
-            let mut state = SearchState::new();
-            state.silent = true;
-            search(&kiwipete, &mut state, 3)
-        })
+        b.iter(|| fresh_search(&mut state, &kiwipete, 3))
     });
 
     c.bench_function("search_depth_4_startpos", |b| {
-        b.iter(|| {
-            let mut state = SearchState::new();
-            state.silent = true;
-            search(&board, &mut state, 4)
-        })
+        b.iter(|| fresh_search(&mut state, &board, 4))
     });
 }
 
 fn bench_movegen(c: &mut Criterion) {
-    ferrite::board::init();
     let board = Board::default();
     c.bench_function("movegen_startpos", |b| {
         b.iter(|| { let moves: Vec<_> = MoveGen::new_legal(&board).collect(); moves.len() })
@@ -0,0 +1,16 @@
+#![no_main]
+
+use std::str::FromStr;
+
+use ferrite::board::{Board, FenString};
+use libfuzzer_sys::fuzz_target;
+
+// Any text parses to a board or an error; a board that parses writes back a FEN that parses to it
+fuzz_target!(|fen: FenString| {
+    if let Ok(board) = Board::from_str(&fen.0) {
+        assert_eq!(Board::from_str(&board.to_string()).as_ref(), Ok(&board));
+    }
+    if let Ok(board) = Board::from_fen_lenient(&fen.0) {
+        assert_eq!(Board::from_str(&board.to_string()).as_ref(), Ok(&board));
+    }
+});
@@ -0,0 +1,19 @@
+#![no_main]
+
+use std::str::FromStr;
+
+use ferrite::board::{Board, ChessMove};
+use libfuzzer_sys::fuzz_target;
+
+// Any 16-bit move is applied or refused without a panic, and a move that is applied leaves a
+// board whose incremental hash matches one computed from scratch
+fuzz_target!(|input: (Board, ChessMove)| {
+    let (board, mv) = input;
+    match board.try_make_move(mv) {
+        Ok(child) => {
+            assert!(board.legal(board.normalize_move(mv)));
+            assert_eq!(Board::from_str(&child.to_string()).map(|b| b.get_hash()), Ok(child.get_hash()));
+        }
+        Err(_) => assert!(!board.legal(board.normalize_move(mv))),
+    }
+});
@@ -0,0 +1,14 @@
+#![no_main]
+
+use ferrite::board::Board;
+use ferrite::pgn::{move_to_san, parse_san};
+use libfuzzer_sys::fuzz_target;
+
+// SAN parsing returns only legal moves, and the SAN written for a move reads back as that move
+fuzz_target!(|input: (Board, &str)| {
+    let (board, text) = input;
+    if let Ok(mv) = parse_san(&board, text) {
+        assert!(board.legal(mv), "{} {}", board, text);
+        assert_eq!(parse_san(&board, &move_to_san(&board, mv)), Ok(mv));
+    }
+});
@@ -0,0 +1,14 @@
+#![no_main]
+
+use ferrite::board::{Board, ChessMove};
+use libfuzzer_sys::fuzz_target;
+
+// A UCI string is a move or an error, and applying it succeeds exactly when it is legal
+fuzz_target!(|input: (Board, &str)| {
+    let (board, text) = input;
+    if let Ok(mv) = text.parse::<ChessMove>() {
+        assert_eq!(mv.to_string().parse::<ChessMove>(), Ok(mv));
+        let legal = board.legal(board.normalize_move(mv));
+        assert_eq!(board.try_make_move(mv).is_ok(), legal, "{} {}", board, text);
+    }
+});
@@ -0,0 +1,141 @@
+#!/usr/bin/env python3
+"""Fit the WDL model in src/wdl.rs to `ferrite datagen` output.
+
+    ferrite datagen wdl.txt --games 4000 --nodes 5000 --sample-rate 0.25
+    python3 scripts/fit_wdl.py wdl.txt
+
+Reads `fen | score | result` lines (score in centipawns and result 1.0/0.5/0.0, both from
+White's point of view) and fits, by maximum likelihood of the game results,
+
+    win  = 1 / (1 + exp((a - score) / b))
+    loss = 1 / (1 + exp((a + score) / b))
+    a = a0 + a1 * phase,  b = b0 + b1 * phase
+
+with phase the material phase of the position (0 = kings and pawns, 24 = all pieces). Prints
+the coefficients for `wdl::model` and how well they predict the results, per phase band.
+Only the standard library is needed.
+"""
+
+import math
+import sys
+from collections import Counter
+
+PHASE_WEIGHT = {"n": 1, "b": 1, "r": 2, "q": 4}
+TOTAL_PHASE = 24
+# Scores past this are clamped: a won position is won by 15 pawns as much as by 30
+MAX_SCORE = 1500
+
+
+def phase(fen):
+    placement = fen.split()[0].lower()
+    return min(sum(PHASE_WEIGHT.get(c, 0) for c in placement), TOTAL_PHASE)
+
+
+def read_samples(paths):
+    """Counts of (phase, score, result) triples, result 2 = white win, 1 = draw, 0 = loss"""
+    counts = Counter()
+    for path in paths:
+        with open(path) as f:
+            for line in f:
+                parts = [p.strip() for p in line.split("|")]
+                if len(parts) != 3:
+                    continue
+                score = max(-MAX_SCORE, min(MAX_SCORE, int(parts[1])))
+                result = round(float(parts[2]) * 2)
+                counts[(phase(parts[0]), score, result)] += 1
+    return counts
+
+
+def sigmoid(x):
+    if x >= 0:
+        return 1.0 / (1.0 + math.exp(-x))
+    e = math.exp(x)
+    return e / (1.0 + e)
+
+
+def probabilities(params, m, score):
+    a0, a1, b0, b1 = params
+    a = a0 + a1 * m
+    b = max(b0 + b1 * m, 1.0)
+    win = sigmoid((score - a) / b)
+    loss = sigmoid((-score - a) / b)
+    return win, max(1.0 - win - loss, 1e-9), loss
+
+
+def log_likelihood(params, counts):
+    total = 0.0
+    for (m, score, result), n in counts.items():
+        p = probabilities(params, m, score)[2 - result]
+        total += n * math.log(max(p, 1e-12))
+    return total
+
+
+def fit(counts, params):
+    """Maximize the log likelihood by coordinate-wise Newton steps on numeric derivatives"""
+    params = list(params)
+    steps = [1.0, 0.1, 1.0, 0.1]
+    best = log_likelihood(params, counts)
+    for _ in range(200):
+        improved = False
+        for i, h in enumerate(steps):
+            up = params.copy()
+            up[i] += h
+            down = params.copy()
+            down[i] -= h
+            f_up, f_down = log_likelihood(up, counts), log_likelihood(down, counts)
+            curvature = (f_up - 2 * best + f_down) / (h * h)
+            slope = (f_up - f_down) / (2 * h)
+            step = -slope / curvature if curvature < 0 else math.copysign(h, slope)
+            # Halve the step until it helps
+            for _ in range(20):
+                trial = params.copy()
+                trial[i] += step
+                f_trial = log_likelihood(trial, counts)
+                if f_trial > best + 1e-9:
+                    params, best, improved = trial, f_trial, True
+                    break
+                step /= 2
+        if not improved:
+            break
+    return params, best
+
+
+def report(params, counts):
+    bands = [(0, 6), (7, 12), (13, 18), (19, 24)]
+    print("phase   positions   draws (seen / model)   white wins (seen / model)")
+    for low, high in bands:
+        n = seen_draws = seen_wins = model_draws = model_wins = 0
+        for (m, score, result), k in counts.items():
+            if not low <= m <= high:
+                continue
+            win, draw, _ = probabilities(params, m, score)
+            n += k
+            seen_draws += k * (result == 1)
+            seen_wins += k * (result == 2)
+            model_draws += k * draw
+            model_wins += k * win
+        if n:
+            print(f"{low:2}-{high:<2}  {n:11}   {seen_draws / n:6.3f} / {model_draws / n:6.3f}"
+                  f"        {seen_wins / n:6.3f} / {model_wins / n:6.3f}")
+
+
+def main():
+    if len(sys.argv) < 2:
+        sys.exit(__doc__)
+    counts = read_samples(sys.argv[1:])
+    positions = sum(counts.values())
+    if positions == 0:
+        sys.exit("no `fen | score | result` lines found")
+    # Start from the coefficients in src/wdl.rs
+    start = [346.2, -12.06, 247.8, -3.15]
+    params, ll = fit(counts, start)
+    print(f"{positions} positions, log likelihood {ll / positions:.4f} per position "
+          f"(current model: {log_likelihood(start, counts) / positions:.4f})")
+    report(params, counts)
+    a0, a1, b0, b1 = params
+    sign = lambda x: "-" if x < 0 else "+"
+    print(f"\n    ({a0:.1f} {sign(a1)} {abs(a1):.2f} * m, {b0:.1f} {sign(b1)} {abs(b1):.2f} * m)")
+
+
+if __name__ == "__main__":
+    main()
@@ -0,0 +1,152 @@
+use crate::board::{BoardStatus, ChessMove, Color};
+use crate::engine::Engine;
+use crate::game::Game;
+use crate::pgn::{move_to_san, Annotation, PgnGame};
+use crate::types::{Score, SearchLimits, SCORE_MATE};
+
+/// NAGs for a move that loses at least the matching `AnnotateConfig` threshold
+pub const NAG_MISTAKE: u8 = 2;
+pub const NAG_BLUNDER: u8 = 4;
+pub const NAG_INACCURACY: u8 = 6;
+
+/// Losses are measured on scores clamped to this, so a missed mate counts as a large loss
+/// rather than thousands of centipawns
+const LOSS_CLAMP: Score = 1000;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnnotateConfig {
+    /// Search for every position of the game
+    pub limits: SearchLimits,
+    /// Centipawns a move must lose, for the side that played it, to be marked `?!`, `?` or `??`
+    pub inaccuracy: Score,
+    pub mistake: Score,
+    pub blunder: Score,
+}
+
+impl Default for AnnotateConfig {
+    fn default() -> Self {
+        AnnotateConfig { limits: SearchLimits::depth(12), inaccuracy: 50, mistake: 100, blunder: 200 }
+    }
+}
+
+impl AnnotateConfig {
+    /// The NAG for a move that lost `loss` centipawns, if it is bad enough to get one
+    pub fn nag(&self, loss: Score) -> Option<u8> {
+        match loss {
+            l if l >= self.blunder => Some(NAG_BLUNDER),
+            l if l >= self.mistake => Some(NAG_MISTAKE),
+            l if l >= self.inaccuracy => Some(NAG_INACCURACY),
+            _ => None,
+        }
+    }
+}
+
+/// `game` with every move commented with the evaluation after it (White's view) and NAGs on
+/// inaccuracies, mistakes and blunders, which also name the engine's choice. Each position is
+/// searched with `config.limits` from a fresh game, with the earlier moves as repetition history.
+pub fn annotate_game(engine: &mut Engine, game: &PgnGame, config: &AnnotateConfig) -> PgnGame {
+    engine.new_game();
+    let mut history = Game::new(game.start);
+    // White's score and the engine's move for every position, the final one included
+    let mut evals = Vec::with_capacity(game.moves.len() + 1);
+    for i in 0..=game.moves.len() {
+        evals.push(evaluate_position(engine, &history, &config.limits));
+        if let Some(&mv) = game.moves.get(i) {
+            history.make_move(mv).expect("PGN moves are legal");
+        }
+    }
+
+    let mut annotations = Vec::with_capacity(game.moves.len());
+    for (i, (board, &mv)) in game.positions().zip(&game.moves).enumerate() {
+        let ((before, best), (after, _)) = (evals[i], evals[i + 1]);
+        let sign = if board.side_to_move() == Color::White { 1 } else { -1 };
+        let loss = sign * (before.clamp(-LOSS_CLAMP, LOSS_CLAMP) - after.clamp(-LOSS_CLAMP, LOSS_CLAMP));
+        // A search that disagrees with itself across a ply is no reason to fault the engine's own move
+        let nag = if best == Some(mv) { None } else { config.nag(loss) };
+
+        let mut comment = (after.abs() != SCORE_MATE).then(|| format_eval(after));
+        if nag.is_some()
+            && let (Some(best), Some(text)) = (best, comment.as_mut())
+        {
+            text.push_str(&format!("; best {} ({})", move_to_san(&board, best), format_eval(before)));
+        }
+        annotations.push(Annotation { nag, comment });
+    }
+
+    let mut tags: Vec<(String, String)> = game.tags.iter().filter(|(name, _)| name != "Annotator").cloned().collect();
+    tags.push(("Annotator".to_string(), "ferrite".to_string()));
+    PgnGame { tags, start: game.start, moves: game.moves.clone(), result: game.result.clone(), annotations }
+}
+
+/// White's score of the game's current position and the engine's move there. Mate, stalemate and
+/// rule draws are scored without a search.
+fn evaluate_position(engine: &mut Engine, game: &Game, limits: &SearchLimits) -> (Score, Option<ChessMove>) {
+    let board = game.board();
+    let sign = if board.side_to_move() == Color::White { 1 } else { -1 };
+    match board.status() {
+        BoardStatus::Checkmate => return (-sign * SCORE_MATE, None),
+        BoardStatus::Stalemate => return (0, None),
+        BoardStatus::Ongoing if game.draw_reason().is_some() => return (0, None),
+        BoardStatus::Ongoing => {}
+    }
+    engine.set_position(game.clone());
+    engine.go(limits.clone());
+    let result = engine.wait_best_move().expect("engine worker exited");
+    (sign * result.score, result.best_move)
+}
+
+/// A White-view score for people: pawns (`+0.43`), or `#3` / `#-3` for mates
+pub fn format_eval(white_score: Score) -> String {
+    if white_score.abs() > SCORE_MATE - 100 {
+        let moves = (SCORE_MATE - white_score.abs() + 1) / 2;
+        format!("#{}", if white_score > 0 { moves } else { -moves })
+    } else {
+        format!("{:+.2}", white_score as f64 / 100.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_thresholds_and_format() {
+        let config = AnnotateConfig::default();
+        assert_eq!([30, 50, 150, 900].map(|loss| config.nag(loss)), [None, Some(NAG_INACCURACY), Some(NAG_MISTAKE), Some(NAG_BLUNDER)]);
+        assert_eq!(format_eval(43), "+0.43");
+        assert_eq!(format_eval(-250), "-2.50");
+        assert_eq!(format_eval(SCORE_MATE - 5), "#3");
+        assert_eq!(format_eval(-(SCORE_MATE - 2)), "#-1");
+    }
+
+    #[test]
+    fn test_annotate_blunder() {
+        let game = PgnGame::parse("[White \"a\"]\n[Annotator \"someone\"]\n\n1. e4 e5 2. Qh5 Nc6 3. Bc4 Nf6 4. Qxf7# 1-0").unwrap();
+        let mut engine = Engine::new();
+        let config = AnnotateConfig { limits: SearchLimits::depth(4), ..Default::default() };
+        let annotated = annotate_game(&mut engine, &game, &config);
+
+        assert_eq!(annotated.moves, game.moves);
+        assert_eq!(annotated.annotations.len(), game.moves.len());
+        assert_eq!(annotated.tag("Annotator"), Some("ferrite"));
+        assert_eq!(annotated.tags.iter().filter(|(name, _)| name == "Annotator").count(), 1);
+
+        // 3...Nf6?? allows mate in one; the mate itself needs no eval
+        let blunder = &annotated.annotations[5];
+        assert_eq!(blunder.nag, Some(NAG_BLUNDER));
+        let comment = blunder.comment.as_deref().unwrap();
+        assert!(comment.starts_with("#1; best "), "{}", comment);
+        assert_eq!(annotated.annotations[6], Annotation::default());
+
+        let text = annotated.to_string();
+        assert!(text.contains("Nf6 $4 {#1; best"), "{}", text);
+        assert_eq!(PgnGame::parse(&text).unwrap().moves, game.moves);
+    }
+}
+
+// Each position gets its own search rather than reusing the score from the previous one: the
+// best-move score of position N and the score after the played move are then both the engine's
+// best guess at the same depth, and the difference is the cost of the move. Scores are clamped
+// before taking it, so going from mate-in-3 to mate-in-5 is free while allowing a mate is a
+// blunder. Thresholds are in centipawns rather than win probability; they are the common
+// 50/100/200 split and are config fields for anyone who wants a stricter tool.
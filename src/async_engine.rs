@@ -0,0 +1,141 @@
+use tokio::sync::{broadcast, mpsc};
+use tokio_stream::wrappers::BroadcastStream;
+use tokio_stream::{Stream, StreamExt};
+use tokio_util::sync::CancellationToken;
+
+use crate::engine::{Engine, EngineEvent};
+use crate::game::Game;
+use crate::types::{SearchLimits, SearchResult};
+
+/// Events kept for slow `events()` subscribers before the oldest are dropped
+const EVENT_BUFFER: usize = 256;
+
+/// `Engine` for tokio code: `go` is an async fn that resolves to the search result, events are a
+/// `Stream`, and searches can be cancelled with a `CancellationToken`. The search itself still
+/// runs on the engine's worker thread, so it never blocks the runtime.
+pub struct AsyncEngine {
+    engine: Engine,
+    results: mpsc::UnboundedReceiver<SearchResult>,
+    events: broadcast::Sender<EngineEvent>,
+    /// Results of searches whose `go` future was dropped before they arrived
+    abandoned: usize,
+}
+
+impl Default for AsyncEngine {
+    fn default() -> Self {
+        AsyncEngine::new()
+    }
+}
+
+impl AsyncEngine {
+    pub fn new() -> Self {
+        let (result_tx, results) = mpsc::unbounded_channel();
+        let (events, _) = broadcast::channel(EVENT_BUFFER);
+        let event_tx = events.clone();
+        let engine = Engine::with_handler(move |event| {
+            if let EngineEvent::BestMove(result) = &event {
+                let _ = result_tx.send(result.clone());
+            }
+            // No subscribers is fine
+            let _ = event_tx.send(event);
+        });
+        AsyncEngine { engine, results, events, abandoned: 0 }
+    }
+
+    pub fn set_position(&self, game: Game) {
+        self.engine.set_position(game);
+    }
+
+    pub fn new_game(&self) {
+        self.engine.new_game();
+    }
+
+    pub fn set_option(&self, name: &str, value: &str) {
+        self.engine.set_option(name, value);
+    }
+
+    /// Search the current position. Cancelling `cancel` stops the search early and still
+    /// returns its best move; dropping the future stops it too.
+    pub async fn go(&mut self, limits: SearchLimits, cancel: CancellationToken) -> SearchResult {
+        while self.abandoned > 0 {
+            self.results.recv().await.expect("engine worker exited");
+            self.abandoned -= 1;
+        }
+
+        self.engine.go(limits);
+        // Counted as abandoned until it arrives, so a dropped future leaves the count right
+        self.abandoned += 1;
+        let stop_on_drop = StopOnDrop(&self.engine);
+        let result = tokio::select! {
+            result = self.results.recv() => result,
+            _ = cancel.cancelled() => {
+                stop_on_drop.0.stop();
+                self.results.recv().await
+            }
+        };
+        std::mem::forget(stop_on_drop);
+        self.abandoned -= 1;
+        result.expect("engine worker exited")
+    }
+
+    /// Stop the running search, if any
+    pub fn stop(&self) {
+        self.engine.stop();
+    }
+
+    /// Every event from now on, including `Info` for each iteration. A subscriber that falls
+    /// more than a few hundred events behind skips the oldest.
+    pub fn events(&self) -> impl Stream<Item = EngineEvent> + Send + 'static {
+        BroadcastStream::new(self.events.subscribe()).filter_map(Result::ok)
+    }
+}
+
+/// Stops the engine's search unless forgotten, for `go` futures dropped mid-search
+struct StopOnDrop<'a>(&'a Engine);
+
+impl Drop for StopOnDrop<'_> {
+    fn drop(&mut self) {
+        self.0.stop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_go_and_event_stream() {
+        let mut engine = AsyncEngine::new();
+        let mut events = Box::pin(engine.events());
+        engine.set_position(Game::from_str("r1bqkb1r/pppp1ppp/2n2n2/4p2Q/2B1P3/8/PPPP1PPP/RNB1K1NR w KQkq - 4 4").unwrap());
+
+        let result = engine.go(SearchLimits::depth(3), CancellationToken::new()).await;
+        assert_eq!(result.best_move.unwrap().to_string(), "h5f7");
+        assert!(matches!(events.next().await, Some(EngineEvent::Info(info)) if info.depth == 1));
+    }
+
+    #[tokio::test]
+    async fn test_cancel_and_dropped_go() {
+        let mut engine = AsyncEngine::new();
+
+        let cancel = CancellationToken::new();
+        cancel.cancel();
+        let limits = SearchLimits { depth: Some(64), infinite: true, ..Default::default() };
+        let result = engine.go(limits.clone(), cancel).await;
+        assert!(result.depth < 64);
+
+        // A go dropped mid-search is stopped, and its result doesn't leak into the next one
+        let dropped = tokio::time::timeout(Duration::from_millis(1), engine.go(limits, CancellationToken::new())).await;
+        assert!(dropped.is_err());
+        let result = engine.go(SearchLimits::depth(1), CancellationToken::new()).await;
+        assert_eq!(result.depth, 1);
+    }
+}
+
+// AsyncEngine adds no threads of its own: the engine's worker calls a handler that forwards
+// each BestMove into a channel the pending `go` awaits, and every event into a broadcast channel
+// behind `events()`. A `go` future can be dropped at any await point, so results are counted
+// as outstanding until received and the next `go` first drains any left over from abandoned
+// searches, which were told to stop when their future was dropped.
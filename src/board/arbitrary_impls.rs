@@ -0,0 +1,112 @@
+use arbitrary::{Arbitrary, Result, Unstructured};
+
+use super::board::Board;
+use super::chessmove::ChessMove;
+use super::movegen::MoveGen;
+
+/// Longest random game an arbitrary `Board` comes from
+const MAX_PLIES: u32 = 200;
+
+/// Characters FENs are made of, which mutations draw from so that most results still look like
+/// a FEN and get past the first checks
+const FEN_CHARS: &[u8] = b"pnbrqkPNBRQK12345678/ wb-KQkqABCDEFGHabcdefgh0123456789";
+
+/// Any 16-bit encoding, including ones no legal move has: what a corrupt TT entry or a
+/// hand-built move can hold
+impl<'a> Arbitrary<'a> for ChessMove {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(ChessMove::from_raw(u.arbitrary()?))
+    }
+
+    fn size_hint(_depth: usize) -> (usize, Option<usize>) {
+        (2, Some(2))
+    }
+}
+
+/// A position reached by random legal moves from the start position, so it is always valid
+impl<'a> Arbitrary<'a> for Board {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let mut board = Board::default();
+        for _ in 0..u.int_in_range(0..=MAX_PLIES)? {
+            let moves: Vec<ChessMove> = MoveGen::new_legal(&board).collect();
+            if moves.is_empty() || u.is_empty() {
+                break;
+            }
+            board = board.make_move_new(*u.choose(&moves)?);
+        }
+        Ok(board)
+    }
+}
+
+/// Text for FEN parsers to choke on: a valid FEN with a few characters replaced, inserted or
+/// removed, or (now and then) arbitrary text
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FenString(pub String);
+
+impl<'a> Arbitrary<'a> for FenString {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        if u.ratio(1, 8)? {
+            return Ok(FenString(u.arbitrary()?));
+        }
+        let mut fen = Board::arbitrary(u)?.to_string().into_bytes();
+        for _ in 0..u.int_in_range(0..=4)? {
+            let c = *u.choose(FEN_CHARS)?;
+            let at = u.choose_index(fen.len() + 1)?;
+            match u.int_in_range(0..=2)? {
+                0 if at < fen.len() => fen[at] = c,
+                1 if at < fen.len() => {
+                    fen.remove(at);
+                }
+                _ => fen.insert(at, c),
+            }
+        }
+        Ok(FenString(String::from_utf8(fen).expect("FEN_CHARS is ASCII")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    /// Deterministic bytes for Unstructured
+    fn bytes(seed: u64, len: usize) -> Vec<u8> {
+        let mut state = seed | 1;
+        (0..len)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                state as u8
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_arbitrary_boards_and_fens() {
+        for seed in 0..200 {
+            let data = bytes(seed, 512);
+            let mut u = Unstructured::new(&data);
+            let board = Board::arbitrary(&mut u).unwrap();
+            assert_eq!(Board::from_str(&board.to_string()).unwrap(), board);
+
+            // Parsers answer every mutation with a board or an error, never a panic
+            let FenString(fen) = FenString::arbitrary(&mut u).unwrap();
+            if let Ok(parsed) = Board::from_str(&fen) {
+                assert_eq!(Board::from_str(&parsed.to_string()).unwrap(), parsed, "{}", fen);
+            }
+            let _ = Board::from_fen_lenient(&fen);
+
+            // try_make_move accepts exactly the legal moves
+            let mv = ChessMove::arbitrary(&mut u).unwrap();
+            assert_eq!(board.try_make_move(mv).is_ok(), board.legal(board.normalize_move(mv)), "{} {:?}", board, mv);
+        }
+    }
+}
+
+// Arbitrary support (optional `arbitrary` feature) for cargo-fuzz: the targets in fuzz/ take
+// these types straight from the fuzzer's bytes. Boards come from random games rather than random
+// bitboards, since the rest of the engine trusts a Board to be legal and only the parsers have
+// to cope with anything; those get FenString, which is mostly near-valid FEN so the fuzzer spends
+// its time past the first `/` rather than on rejected garbage.
@@ -1,78 +1,93 @@
-use std::sync::Once;
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+
+use once_cell::race::OnceBox;
 
 use super::bitboard::BitBoard;
 use super::piece::Color;
 use super::square::Square;
-use super::magic::{BISHOP_BITS, ROOK_BITS, MagicEntry, Rng, find_magic};
+use super::magic::{BISHOP_BITS, BISHOP_MAGICS, ROOK_BITS, ROOK_MAGICS, MagicEntry};
 
 // --- static tables ---
 
-static INIT: Once = Once::new();
+/// Leaper tables are computed at compile time
+static KNIGHT_ATTACKS: [BitBoard; 64] = leaper_table(&[(-2, -1), (-2, 1), (-1, -2), (-1, 2), (1, -2), (1, 2), (2, -1), (2, 1)]);
+static KING_ATTACKS: [BitBoard; 64] = leaper_table(&[(-1, -1), (-1, 0), (-1, 1), (0, -1), (0, 1), (1, -1), (1, 0), (1, 1)]);
+static PAWN_ATTACKS: [[BitBoard; 64]; 2] = [leaper_table(&[(1, -1), (1, 1)]), leaper_table(&[(-1, -1), (-1, 1)])];
+
+/// Slider attack tables: one magic entry per square pointing into a shared attack array
+struct SliderTables {
+    bishop_table: Vec<BitBoard>,
+    rook_table: Vec<BitBoard>,
+    bishop_entries: [MagicEntry; 64],
+    rook_entries: [MagicEntry; 64],
+    /// Indexed with PEXT instead of magic multiplication (the CPU has BMI2)
+    #[cfg(all(feature = "pext", target_arch = "x86_64"))]
+    use_pext: bool,
+}
 
-static mut KNIGHT_ATTACKS: [BitBoard; 64] = [BitBoard(0); 64];
-static mut KING_ATTACKS: [BitBoard; 64] = [BitBoard(0); 64];
-static mut PAWN_ATTACKS: [[BitBoard; 64]; 2] = [[BitBoard(0); 64]; 2];
+/// Built on first slider lookup
+static SLIDERS: OnceBox<SliderTables> = OnceBox::new();
 
-static mut BISHOP_TABLE: Vec<BitBoard> = Vec::new();
-static mut ROOK_TABLE: Vec<BitBoard> = Vec::new();
-static mut BISHOP_ENTRIES: [MagicEntry; 64] = unsafe { std::mem::zeroed() };
-static mut ROOK_ENTRIES: [MagicEntry; 64] = unsafe { std::mem::zeroed() };
+#[inline]
+fn sliders() -> &'static SliderTables {
+    SLIDERS.get_or_init(|| Box::new(SliderTables::new()))
+}
 
-/// Init all attack tables. Must be called once before any lookup.
+/// Force-init the slider tables. Can be called at startup, but they are also
+/// lazily initialized on first access.
 pub fn init_attacks() {
-    INIT.call_once(|| {
-        unsafe {
-            init_knight_attacks();
-            init_king_attacks();
-            init_pawn_attacks();
-            init_slider_attacks();
-        }
-    });
+    sliders();
 }
 
 // --- Leaper lookup functions ---
 
 #[inline]
 pub fn knight_attacks(sq: Square) -> BitBoard {
-    unsafe { KNIGHT_ATTACKS[sq.to_index()] }
+    KNIGHT_ATTACKS[sq.to_index()]
 }
 
 #[inline]
 pub fn king_attacks(sq: Square) -> BitBoard {
-    unsafe { KING_ATTACKS[sq.to_index()] }
+    KING_ATTACKS[sq.to_index()]
 }
 
 #[inline]
 pub fn pawn_attacks(color: Color, sq: Square) -> BitBoard {
-    unsafe { PAWN_ATTACKS[color.to_index()][sq.to_index()] }
+    PAWN_ATTACKS[color.to_index()][sq.to_index()]
 }
 
 // --- Slider lookup functions ---
 
 #[inline]
 pub fn bishop_attacks(sq: Square, occupied: BitBoard) -> BitBoard {
-    unsafe {
-        let entry = &BISHOP_ENTRIES[sq.to_index()];
-        let idx = magic_index(entry, occupied);
-        BISHOP_TABLE[idx]
-    }
+    let tables = sliders();
+    tables.bishop_table[tables.index(&tables.bishop_entries[sq.to_index()], occupied)]
 }
 
 #[inline]
 pub fn rook_attacks(sq: Square, occupied: BitBoard) -> BitBoard {
-    unsafe {
-        let entry = &ROOK_ENTRIES[sq.to_index()];
-        let idx = magic_index(entry, occupied);
-        ROOK_TABLE[idx]
-    }
+    let tables = sliders();
+    tables.rook_table[tables.index(&tables.rook_entries[sq.to_index()], occupied)]
 }
 
 #[inline]
-#[allow(dead_code)] // Public API, used in tests
 pub fn queen_attacks(sq: Square, occupied: BitBoard) -> BitBoard {
     bishop_attacks(sq, occupied) | rook_attacks(sq, occupied)
 }
 
+/// Whether slider lookups use PEXT (`pext` feature on a BMI2 CPU)
+pub fn pext_enabled() -> bool {
+    #[cfg(all(feature = "pext", target_arch = "x86_64"))]
+    {
+        sliders().use_pext
+    }
+    #[cfg(not(all(feature = "pext", target_arch = "x86_64")))]
+    {
+        false
+    }
+}
+
 /// Compute magic table index for a given occupancy.
 #[inline]
 fn magic_index(entry: &MagicEntry, occupied: BitBoard) -> usize {
@@ -81,151 +96,100 @@ fn magic_index(entry: &MagicEntry, occupied: BitBoard) -> usize {
     entry.offset as usize + (hash >> entry.shift) as usize
 }
 
-// --- Initialization ---
-
-unsafe fn init_knight_attacks() {
-    unsafe {
-        let offsets: [(i8, i8); 8] = [
-            (-2, -1), (-2, 1), (-1, -2), (-1, 2),
-            (1, -2), (1, 2), (2, -1), (2, 1),
-        ];
-        for sq in 0..64u8 {
-            let r = (sq >> 3) as i8;
-            let f = (sq & 7) as i8;
-            let mut bb = 0u64;
-            for (dr, df) in offsets {
-                let nr = r + dr;
-                let nf = f + df;
-                if (0..8).contains(&nr) && (0..8).contains(&nf) {
-                    bb |= 1u64 << (nr * 8 + nf);
-                }
-            }
-            KNIGHT_ATTACKS[sq as usize] = BitBoard(bb);
-        }
-    }
+/// Gather the bits of `occupied` selected by `mask` into the low bits: a perfect index into
+/// the 2^popcnt(mask) subsets, so no magic is needed
+#[cfg(all(feature = "pext", target_arch = "x86_64"))]
+#[target_feature(enable = "bmi2")]
+unsafe fn pext(occupied: u64, mask: u64) -> u64 {
+    core::arch::x86_64::_pext_u64(occupied, mask)
 }
 
-unsafe fn init_king_attacks() {
-    unsafe {
-        let offsets: [(i8, i8); 8] = [
-            (-1, -1), (-1, 0), (-1, 1),
-            (0, -1),           (0, 1),
-            (1, -1),  (1, 0),  (1, 1),
-        ];
-        for sq in 0..64u8 {
-            let r = (sq >> 3) as i8;
-            let f = (sq & 7) as i8;
-            let mut bb = 0u64;
-            for (dr, df) in offsets {
-                let nr = r + dr;
-                let nf = f + df;
-                if (0..8).contains(&nr) && (0..8).contains(&nf) {
-                    bb |= 1u64 << (nr * 8 + nf);
-                }
-            }
-            KING_ATTACKS[sq as usize] = BitBoard(bb);
-        }
-    }
-}
+// --- Initialization ---
 
-unsafe fn init_pawn_attacks() {
-    unsafe {
-        for sq in 0..64u8 {
-            let r = (sq >> 3) as i8;
-            let f = (sq & 7) as i8;
-            let mut white = 0u64;
-            let mut black = 0u64;
-
-            // white: rank+1
-            if r + 1 < 8 {
-                if f > 0 { white |= 1u64 << ((r + 1) * 8 + (f - 1)); }
-                if f + 1 < 8 { white |= 1u64 << ((r + 1) * 8 + (f + 1)); }
-            }
-            // black: rank-1
-            if r > 0 {
-                if f > 0 { black |= 1u64 << ((r - 1) * 8 + (f - 1)); }
-                if f + 1 < 8 { black |= 1u64 << ((r - 1) * 8 + (f + 1)); }
+/// Attack sets for a leaper stepping by each (rank, file) offset, from every square
+const fn leaper_table(offsets: &[(i8, i8)]) -> [BitBoard; 64] {
+    let mut table = [BitBoard(0); 64];
+    let mut sq = 0;
+    while sq < 64 {
+        let r = (sq / 8) as i8;
+        let f = (sq % 8) as i8;
+        let mut bb = 0u64;
+        let mut i = 0;
+        while i < offsets.len() {
+            let nr = r + offsets[i].0;
+            let nf = f + offsets[i].1;
+            if nr >= 0 && nr < 8 && nf >= 0 && nf < 8 {
+                bb |= 1u64 << (nr * 8 + nf);
             }
-
-            PAWN_ATTACKS[0][sq as usize] = BitBoard(white);
-            PAWN_ATTACKS[1][sq as usize] = BitBoard(black);
+            i += 1;
         }
+        table[sq] = BitBoard(bb);
+        sq += 1;
     }
+    table
 }
 
-unsafe fn init_slider_attacks() {
-    unsafe {
-        let mut rng = Rng(0x12345678_9ABCDEF0); // fixed seed for deterministic init
-
-        // Compute total table sizes
-        let mut bishop_total = 0usize;
-        let mut rook_total = 0usize;
+impl SliderTables {
+    fn new() -> Self {
+        let empty = || MagicEntry { mask: BitBoard(0), magic: 0, shift: 0, offset: 0 };
+        let mut tables = SliderTables {
+            bishop_table: Vec::new(),
+            rook_table: Vec::new(),
+            bishop_entries: core::array::from_fn(|_| empty()),
+            rook_entries: core::array::from_fn(|_| empty()),
+            #[cfg(all(feature = "pext", target_arch = "x86_64", feature = "std"))]
+            use_pext: std::arch::is_x86_feature_detected!("bmi2"),
+            // No runtime detection without std: PEXT only if the build targets BMI2
+            #[cfg(all(feature = "pext", target_arch = "x86_64", not(feature = "std")))]
+            use_pext: cfg!(target_feature = "bmi2"),
+        };
+
+        let mut bishop_table = Vec::new();
+        let mut rook_table = Vec::new();
         for sq in 0..64 {
-            bishop_total += 1 << BISHOP_BITS[sq];
-            rook_total += 1 << ROOK_BITS[sq];
-        }
-
-        BISHOP_TABLE = vec![BitBoard(0); bishop_total];
-        ROOK_TABLE = vec![BitBoard(0); rook_total];
-
-        // Init bishop entries ... find magic for each square
-        let mut offset = 0u32;
-        for sq in 0..64 {
-            let mask = bishop_mask(sq);
-            let bits = BISHOP_BITS[sq];
-            let shift = 64 - bits;
-
-            let magic = find_magic(mask, bits, &|occ| bishop_attacks_slow(sq, occ), &mut rng);
-
-            BISHOP_ENTRIES[sq] = MagicEntry {
-                mask: BitBoard(mask),
-                magic,
-                shift,
-                offset,
+            tables.bishop_entries[sq] = MagicEntry {
+                mask: BitBoard(bishop_mask(sq)),
+                magic: BISHOP_MAGICS[sq],
+                shift: 64 - BISHOP_BITS[sq],
+                offset: bishop_table.len() as u32,
             };
-
-            // Fill table for all occupancy subsets
-            let mut occ = 0u64;
-            loop {
-                let idx = offset as usize
-                    + ((occ.wrapping_mul(magic)) >> shift) as usize;
-                BISHOP_TABLE[idx] = BitBoard(bishop_attacks_slow(sq, occ));
-
-                occ = occ.wrapping_sub(mask) & mask;
-                if occ == 0 { break; }
-            }
-
-            offset += 1u32 << bits;
-        }
-
-        // Init rook entries ... find magic for each square
-        offset = 0;
-        for sq in 0..64 {
-            let mask = rook_mask(sq);
-            let bits = ROOK_BITS[sq];
-            let shift = 64 - bits;
-
-            let magic = find_magic(mask, bits, &|occ| rook_attacks_slow(sq, occ), &mut rng);
-
-            ROOK_ENTRIES[sq] = MagicEntry {
-                mask: BitBoard(mask),
-                magic,
-                shift,
-                offset,
+            bishop_table.resize(bishop_table.len() + (1 << BISHOP_BITS[sq]), BitBoard(0));
+            tables.fill(&tables.bishop_entries[sq], &mut bishop_table, |occ| bishop_attacks_slow(sq, occ));
+
+            tables.rook_entries[sq] = MagicEntry {
+                mask: BitBoard(rook_mask(sq)),
+                magic: ROOK_MAGICS[sq],
+                shift: 64 - ROOK_BITS[sq],
+                offset: rook_table.len() as u32,
             };
+            rook_table.resize(rook_table.len() + (1 << ROOK_BITS[sq]), BitBoard(0));
+            tables.fill(&tables.rook_entries[sq], &mut rook_table, |occ| rook_attacks_slow(sq, occ));
+        }
+        tables.bishop_table = bishop_table;
+        tables.rook_table = rook_table;
+        tables
+    }
 
-            let mut occ = 0u64;
-            loop {
-                let idx = offset as usize
-                    + ((occ.wrapping_mul(magic)) >> shift) as usize;
-                ROOK_TABLE[idx] = BitBoard(rook_attacks_slow(sq, occ));
-
-                occ = occ.wrapping_sub(mask) & mask;
-                if occ == 0 { break; }
-            }
+    /// Write the attack set for every occupancy subset of the entry's mask
+    fn fill(&self, entry: &MagicEntry, table: &mut [BitBoard], slow_attacks: impl Fn(u64) -> u64) {
+        let mask = entry.mask.0;
+        let mut occ = 0u64;
+        loop {
+            table[self.index(entry, BitBoard(occ))] = BitBoard(slow_attacks(occ));
+            occ = occ.wrapping_sub(mask) & mask;
+            if occ == 0 { break; }
+        }
+    }
 
-            offset += 1u32 << bits;
+    /// Table index for a given occupancy: PEXT when enabled, magic multiplication otherwise.
+    #[inline]
+    fn index(&self, entry: &MagicEntry, occupied: BitBoard) -> usize {
+        #[cfg(all(feature = "pext", target_arch = "x86_64"))]
+        if self.use_pext {
+            // SAFETY: use_pext is only set when the CPU reports BMI2
+            return entry.offset as usize + unsafe { pext(occupied.0, entry.mask.0) } as usize;
         }
+        magic_index(entry, occupied)
     }
 }
 
@@ -321,63 +285,54 @@ mod tests {
 
     #[test]
     fn test_knight_attacks_corner() {
-        init_attacks();
         let attacks = knight_attacks(sq(0, 0)); // A1
         assert_eq!(attacks.popcnt(), 2); // B3, C2
     }
 
     #[test]
     fn test_knight_attacks_center() {
-        init_attacks();
         let attacks = knight_attacks(sq(3, 3)); // D4
         assert_eq!(attacks.popcnt(), 8);
     }
 
     #[test]
     fn test_king_attacks_corner() {
-        init_attacks();
         let attacks = king_attacks(sq(0, 0)); // A1
         assert_eq!(attacks.popcnt(), 3);
     }
 
     #[test]
     fn test_king_attacks_center() {
-        init_attacks();
         let attacks = king_attacks(sq(3, 3)); // D4
         assert_eq!(attacks.popcnt(), 8);
     }
 
     #[test]
     fn test_pawn_attacks_white() {
-        init_attacks();
         let attacks = pawn_attacks(Color::White, sq(1, 4)); // E2
         assert_eq!(attacks.popcnt(), 2); // D3, F3
     }
 
     #[test]
     fn test_pawn_attacks_edge() {
-        init_attacks();
         let attacks = pawn_attacks(Color::White, sq(1, 0)); // A2
         assert_eq!(attacks.popcnt(), 1); // B3 only
     }
 
     #[test]
     fn test_rook_attacks_empty_board() {
-        init_attacks();
         let attacks = rook_attacks(sq(3, 3), BitBoard(0)); // D4 on empty board
         assert_eq!(attacks.popcnt(), 14); // full rank + file minus self
     }
 
     #[test]
     fn test_bishop_attacks_empty_board() {
-        init_attacks();
         let attacks = bishop_attacks(sq(3, 3), BitBoard(0)); // D4 on empty board
         assert_eq!(attacks.popcnt(), 13);
     }
 
     #[test]
     fn test_queen_equals_bishop_or_rook() {
-        init_attacks();
         let occ = BitBoard(0x0000_0010_0800_0000); // some blockers
         let s = sq(4, 4); // E5
         let q = queen_attacks(s, occ);
@@ -388,7 +343,6 @@ mod tests {
 
     #[test]
     fn test_rook_attacks_with_blockers() {
-        init_attacks();
         // Rook on A1, blocker on A4 and D1
         let blocker = BitBoard::from_square(sq(3, 0)) | BitBoard::from_square(sq(0, 3));
         let attacks = rook_attacks(sq(0, 0), blocker);
@@ -398,7 +352,6 @@ mod tests {
 
     #[test]
     fn test_bishop_attacks_with_blockers() {
-        init_attacks();
         // Bishop on D4, blocker on F6
         let blocker = BitBoard::from_square(sq(5, 5)); // F6
         let attacks = bishop_attacks(sq(3, 3), blocker);
@@ -409,9 +362,52 @@ mod tests {
         assert_eq!(attacks.popcnt(), 11);
     }
 
+    #[test]
+    fn test_hardcoded_magics_match_search() {
+        use super::super::magic::{find_magic, Rng};
+        // Rerun the offline search with its fixed seed: same order (bishops, then rooks)
+        let mut rng = Rng(0x12345678_9ABCDEF0);
+        for s in 0..64 {
+            let magic = find_magic(bishop_mask(s), BISHOP_BITS[s], &|occ| bishop_attacks_slow(s, occ), &mut rng);
+            assert_eq!(magic, BISHOP_MAGICS[s], "bishop magic for square {}", s);
+        }
+        for s in 0..64 {
+            let magic = find_magic(rook_mask(s), ROOK_BITS[s], &|occ| rook_attacks_slow(s, occ), &mut rng);
+            assert_eq!(magic, ROOK_MAGICS[s], "rook magic for square {}", s);
+        }
+    }
+
+    #[test]
+    fn test_slider_tables_match_slow_generators() {
+        for s in 0..64 {
+            let sq = Square::new(s as u8);
+            for bishop in [true, false] {
+                let mask = if bishop { bishop_mask(s) } else { rook_mask(s) };
+                let mut occ = 0u64;
+                loop {
+                    let (fast, slow) = if bishop {
+                        (bishop_attacks(sq, BitBoard(occ)).0, bishop_attacks_slow(s, occ))
+                    } else {
+                        (rook_attacks(sq, BitBoard(occ)).0, rook_attacks_slow(s, occ))
+                    };
+                    assert_eq!(fast, slow, "square {} occupancy {:#x}", s, occ);
+                    occ = occ.wrapping_sub(mask) & mask;
+                    if occ == 0 { break; }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_pext_follows_cpu_support() {
+        #[cfg(all(feature = "pext", target_arch = "x86_64"))]
+        assert_eq!(pext_enabled(), std::arch::is_x86_feature_detected!("bmi2"));
+        #[cfg(not(all(feature = "pext", target_arch = "x86_64")))]
+        assert!(!pext_enabled());
+    }
+
     #[test]
     fn test_rook_attacks_all_squares_empty_board() {
-        init_attacks();
         // Every square on an empty board should have exactly 14 rook attacks
         for r in 0..8 {
             for f in 0..8 {
@@ -424,8 +420,17 @@ mod tests {
     }
 }
 
-// precompute attack tables for every piece type on every squre, stored in static arrays
-// initialized once via `std::sync::Once`
+// precompute attack tables for every piece type on every squre. Leaper tables are `const fn` output
+// baked into the binary; the slider tables (~800 KB) are built from the hardcoded magics in a
+// `OnceBox` on first use, so no `static mut` is left. OnceBox (a pointer set by compare-and-swap)
+// works without std; two threads racing to build the tables both build them and one copy is
+// dropped, which only ever happens at startup
+
+// PEXT (`pext` feature): BMI2's parallel bit extract packs the masked occupancy bits straight into an
+// index, replacing multiply + shift. Each square's slot count (2^relevant bits) is the same either
+// way, so the tables keep their layout and only the fill order changes. The choice is made once at
+// init from runtime CPU detection. Zen 1/2 implement PEXT in microcode and are much slower with it,
+// which is why it is opt-in rather than the default.
 
 // Excluding edges from the mask... for magic bitboards, only care abt relevant blockers (pieces
 // btwn slider and edge). Piece on edge doesn't affect ray (ray ends there regardless), so exclude
@@ -1,5 +1,10 @@
-use std::fmt;
-use std::str::FromStr;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+use core::hash::{Hash, Hasher};
+use core::str::FromStr;
+
+use thiserror::Error;
 
 use super::attacks::{bishop_attacks, rook_attacks, knight_attacks, king_attacks, pawn_attacks};
 use super::bitboard::{BitBoard, EMPTY};
@@ -7,6 +12,8 @@ use super::chessmove::ChessMove;
 use super::piece::{Color, Piece};
 use super::square::{Square, Rank, File};
 use super::zobrist;
+use crate::pst::{self, PHASE_WEIGHT};
+use crate::types::Score;
 
 /// Castling rights stored as a 4-bit mask
 /// Bit 0: White kingside, Bit 1: White queenside
@@ -16,6 +23,39 @@ pub const WQ: u8 = 2;
 pub const BK: u8 = 4;
 pub const BQ: u8 = 8;
 
+/// Rook squares for each right in the classical start position (h1, a1, h8, a8)
+const DEFAULT_CASTLE_ROOKS: [Square; 4] = [Square::H1, Square::A1, Square::H8, Square::A8];
+
+/// King of `color` on its back rank, if any (castling is impossible otherwise)
+fn back_rank_king(pieces: &[BitBoard; 6], colors: &[BitBoard; 2], color: Color) -> Option<Square> {
+    let back_rank = if color == Color::White { 0 } else { 7 };
+    (pieces[Piece::King.to_index()] & colors[color.to_index()])
+        .iter()
+        .find(|k| k.rank().to_index() == back_rank)
+}
+
+/// Outermost rook on the back rank on the given side of the king, if any
+fn find_castle_rook(pieces: &[BitBoard; 6], colors: &[BitBoard; 2], color: Color, kingside: bool) -> Option<(Square, bool)> {
+    let king = back_rank_king(pieces, colors, color)?;
+    let mut rooks = (pieces[Piece::Rook.to_index()] & colors[color.to_index()])
+        .iter()
+        .filter(|r| r.rank() == king.rank());
+    let rook = if kingside {
+        rooks.filter(|r| r.to_index() > king.to_index()).last()
+    } else {
+        rooks.find(|r| r.to_index() < king.to_index())
+    };
+    rook.map(|r| (r, kingside))
+}
+
+/// Back-rank rook on `file` (Shredder / X-FEN castling letter); the side follows from the king
+fn castle_rook_on_file(pieces: &[BitBoard; 6], colors: &[BitBoard; 2], color: Color, file: usize) -> Option<(Square, bool)> {
+    let king = back_rank_king(pieces, colors, color)?;
+    let rook = Square::make_square(king.rank(), File::from_index(file));
+    let is_rook = !(pieces[Piece::Rook.to_index()] & colors[color.to_index()] & BitBoard::from_square(rook)).is_empty();
+    (is_rook && file != king.file().to_index()).then(|| (rook, file > king.file().to_index()))
+}
+
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub enum BoardStatus {
     Ongoing,
@@ -23,6 +63,48 @@ pub enum BoardStatus {
     Stalemate,
 }
 
+/// Why a FEN didn't parse
+#[derive(Clone, PartialEq, Eq, Debug, Error)]
+pub enum ParseFenError {
+    #[error("FEN needs at least {needed} fields, got {found}")]
+    MissingFields { needed: usize, found: usize },
+    #[error("Invalid piece char: {0}")]
+    BadPiece(char),
+    /// A rank with more than 8 squares, or more than 8 ranks
+    #[error("FEN rank/file out of bounds")]
+    BadPlacement,
+    #[error("Invalid side to move: {0}")]
+    BadSideToMove(String),
+    #[error("Invalid castling char: {0}")]
+    BadCastling(char),
+    #[error("Invalid en passant square: {0}")]
+    BadEnPassant(String),
+    /// Halfmove clock or fullmove number that isn't a number
+    #[error("Invalid move counter: {0}")]
+    BadCounter(String),
+    /// Well-formed, but not a position a game could reach (see `Board::validate`)
+    #[error("{0}")]
+    Inconsistent(String),
+}
+
+/// Lets FEN errors flow through `?` into the crate's `String` errors
+impl From<ParseFenError> for String {
+    fn from(e: ParseFenError) -> String {
+        e.to_string()
+    }
+}
+
+/// Why `Board::try_make_move` refused a move
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Error)]
+pub enum MoveError {
+    /// The side to move has no piece on the source square
+    #[error("No piece of the side to move on {0}")]
+    NoPiece(Square),
+    /// The piece can't make this move, or it would leave its king in check
+    #[error("Illegal move {0}")]
+    Illegal(ChessMove),
+}
+
 /// The board representation. Copy
 #[derive(Clone, Copy, Debug)]
 pub struct Board {
@@ -30,10 +112,16 @@ pub struct Board {
     colors: [BitBoard; 2], // per color
     side_to_move: Color,
     castling: u8, // 4-bit castling rights
+    castle_rooks: [Square; 4], // rook start square per castling right (WK, WQ, BK, BQ bit order)
+    chess960: bool, // print castling as king-takes-rook (set for non-standard castling setups)
     ep_square: Option<Square>,
     halfmove_clock: u8,
+    fullmove_number: u16, // starts at 1, incremented after each Black move
     hash: u64,
     checkers: BitBoard, // cached: enemy pieces giving check
+    psq_mg: Score, // incremental material + PST (white-relative), midgame
+    psq_eg: Score, // incremental material + PST (white-relative), endgame
+    phase: i32,    // incremental sum of PHASE_WEIGHT over all pieces
 }
 
 impl Board {
@@ -65,6 +153,16 @@ impl Board {
         self.ep_square
     }
 
+    /// Plies since the last capture or pawn move (FEN field 5)
+    pub fn halfmove_clock(&self) -> u8 {
+        self.halfmove_clock
+    }
+
+    /// Fullmove counter as in FEN field 6
+    pub fn fullmove_number(&self) -> u16 {
+        self.fullmove_number
+    }
+
     pub fn color_combined(&self, color: Color) -> BitBoard {
         self.colors[color.to_index()]
     }
@@ -77,14 +175,164 @@ impl Board {
         self.colors[0] | self.colors[1]
     }
 
+    /// Square of `color`'s king. Every board has one king per side: FEN parsing checks it, and
+    /// legal moves never capture a king.
+    pub fn king_square(&self, color: Color) -> Square {
+        let king = self.pieces[Piece::King.to_index()] & self.colors[color.to_index()];
+        debug_assert!(!king.is_empty(), "{:?} has no king", color);
+        Square::new(king.0.trailing_zeros() as u8)
+    }
+
     pub fn get_hash(&self) -> u64 {
         self.hash
     }
 
+    /// The position's Zobrist key, covering the pieces, side to move, castling rights and a
+    /// capturable en passant square. Keys come from a fixed seed, so a position has the same
+    /// key in every run; it's what `Hash` feeds a `HashMap`. Same as `get_hash`.
+    pub fn zobrist(&self) -> u64 {
+        self.hash
+    }
+
     pub fn castling_rights(&self) -> u8 {
         self.castling
     }
 
+    /// Starting square of the rook for a single castling right (WK, WQ, BK or BQ)
+    pub fn castle_rook(&self, right: u8) -> Square {
+        self.castle_rooks[right.trailing_zeros() as usize]
+    }
+
+    /// Whether moves are written in Chess960 notation (castling as king-takes-rook)
+    pub fn is_chess960(&self) -> bool {
+        self.chess960
+    }
+
+    /// Force Chess960 notation (UCI_Chess960). Turning it off keeps it for positions that
+    /// can't be written in classical notation.
+    pub fn set_chess960(&mut self, on: bool) {
+        self.chess960 = on || self.needs_chess960();
+    }
+
+    /// Anything but the classical e-file king and corner rooks needs Chess960 notation
+    fn needs_chess960(&self) -> bool {
+        (0..4).filter(|i| self.castling & (1 << i) != 0).any(|i| {
+            let color = if i < 2 { Color::White } else { Color::Black };
+            let king_file = back_rank_king(&self.pieces, &self.colors, color).map(|k| k.file().to_index());
+            king_file != Some(4) || self.castle_rooks[i] != DEFAULT_CASTLE_ROOKS[i]
+        })
+    }
+
+    /// Castling moves are encoded king-takes-own-rook, which works for any Chess960 setup.
+    /// Also recognizes moves that don't carry the castling kind (see `normalize_move`).
+    pub fn is_castling(&self, mv: ChessMove) -> bool {
+        if mv.is_castling() {
+            return true;
+        }
+        let src = BitBoard::from_square(mv.get_source());
+        let us = self.colors[self.side_to_move.to_index()];
+        !(self.pieces[Piece::King.to_index()] & src & us).is_empty()
+            && !(self.pieces[Piece::Rook.to_index()] & us & BitBoard::from_square(mv.get_dest())).is_empty()
+    }
+
+    /// Pawn move onto the en passant square (the captured pawn is not on the destination)
+    pub fn is_en_passant(&self, mv: ChessMove) -> bool {
+        if mv.is_en_passant() {
+            return true;
+        }
+        self.ep_square == Some(mv.get_dest())
+            && !(self.pieces[Piece::Pawn.to_index()] & BitBoard::from_square(mv.get_source())).is_empty()
+    }
+
+    /// Piece removed by the move: the pawn for en passant, nothing for castling
+    pub fn captured_piece(&self, mv: ChessMove) -> Option<Piece> {
+        if self.is_en_passant(mv) {
+            Some(Piece::Pawn)
+        } else if self.color_on(mv.get_dest()) == Some(!self.side_to_move) {
+            self.piece_on(mv.get_dest())
+        } else {
+            None
+        }
+    }
+
+    pub fn is_capture(&self, mv: ChessMove) -> bool {
+        self.captured_piece(mv).is_some()
+    }
+
+    /// Final king and rook squares for a castling move
+    fn castling_targets(mv: ChessMove) -> (Square, Square) {
+        let rank = mv.get_source().rank();
+        if mv.get_dest().file().to_index() > mv.get_source().file().to_index() {
+            (Square::make_square(rank, File::from_index(6)), Square::make_square(rank, File::from_index(5)))
+        } else {
+            (Square::make_square(rank, File::from_index(2)), Square::make_square(rank, File::from_index(3)))
+        }
+    }
+
+    /// UCI string for a move: castling is written as the king's two-square move unless in
+    /// Chess960 mode, where it stays king-takes-rook
+    pub fn move_to_uci(&self, mv: ChessMove) -> String {
+        if !self.chess960 && mv.is_castling() {
+            let (king_to, _) = Self::castling_targets(mv);
+            return ChessMove::new(mv.get_source(), king_to, None).to_string();
+        }
+        mv.to_string()
+    }
+
+    /// Give a move built from squares alone (UCI input, tests) its en passant or castling kind.
+    /// Standard-notation castling (king two squares sideways) is mapped to the internal
+    /// king-takes-rook encoding unless in Chess960 mode. Other moves pass through.
+    pub fn normalize_move(&self, mv: ChessMove) -> ChessMove {
+        if mv.is_castling() || mv.is_en_passant() {
+            return mv;
+        }
+        let (src, dst) = (mv.get_source(), mv.get_dest());
+        if self.is_en_passant(mv) {
+            return ChessMove::new_en_passant(src, dst);
+        }
+        if self.is_castling(mv) {
+            return ChessMove::new_castling(src, dst);
+        }
+        if self.chess960 || self.piece_on(src) != Some(Piece::King) {
+            return mv;
+        }
+        if src.rank() != dst.rank() || src.file().to_index().abs_diff(dst.file().to_index()) != 2 {
+            return mv;
+        }
+        let white = self.side_to_move == Color::White;
+        let right = match (dst.file().to_index() > src.file().to_index(), white) {
+            (true, true) => WK,
+            (false, true) => WQ,
+            (true, false) => BK,
+            (false, false) => BQ,
+        };
+        if self.castling & right == 0 {
+            return mv;
+        }
+        ChessMove::new_castling(src, self.castle_rook(right))
+    }
+
+    /// Material + PST score (mg, eg) from White's perspective, maintained incrementally
+    #[inline]
+    pub fn psq_score(&self) -> (Score, Score) {
+        (self.psq_mg, self.psq_eg)
+    }
+
+    /// Sum of PHASE_WEIGHT over all pieces on the board (unclamped), maintained incrementally
+    #[inline]
+    pub fn material_phase(&self) -> i32 {
+        self.phase
+    }
+
+    /// Add (sign = 1) or remove (sign = -1) a piece's contribution to the eval accumulators
+    #[inline]
+    fn update_psq(&mut self, piece: usize, color: usize, sq: usize, sign: Score) {
+        let (mg, eg) = pst::psq_value(piece, color, sq);
+        self.psq_mg += sign * mg;
+        self.psq_eg += sign * eg;
+        self.phase += sign * PHASE_WEIGHT[piece];
+    }
+
     /// Compute board status by checking if any legal move exists
     pub fn status(&self) -> BoardStatus {
         // Quick check: generate pseudo-legal moves and test legality
@@ -116,20 +364,115 @@ impl Board {
         false
     }
 
-    /// Make move and return resulting board. Does not validate legality
+    /// Check that the position is one a legal game could reach, in the ways move generation
+    /// relies on: one king each, no pawns on the back ranks, castling rights backed by a king
+    /// and rook in place, an en passant square behind a just-pushed pawn, and the side that
+    /// just moved not left in check
+    pub fn validate(&self) -> Result<(), String> {
+        let mut seen = EMPTY;
+        for bb in self.pieces {
+            if !(seen & bb).is_empty() {
+                return Err("Two pieces on one square".to_string());
+            }
+            seen |= bb;
+        }
+        if seen != self.colors[0] | self.colors[1] || !(self.colors[0] & self.colors[1]).is_empty() {
+            return Err("Piece and color bitboards disagree".to_string());
+        }
+
+        let kings = self.pieces[Piece::King.to_index()];
+        for color in [Color::White, Color::Black] {
+            let n = (kings & self.colors[color.to_index()]).popcnt();
+            if n != 1 {
+                return Err(format!("{:?} has {} kings", color, n));
+            }
+        }
+
+        let back_ranks = BitBoard(0xFF00_0000_0000_00FF);
+        if !(self.pieces[Piece::Pawn.to_index()] & back_ranks).is_empty() {
+            return Err("Pawn on rank 1 or 8".to_string());
+        }
+
+        for i in 0..4 {
+            if self.castling & (1 << i) == 0 {
+                continue;
+            }
+            let color = if i < 2 { Color::White } else { Color::Black };
+            let rook = self.castle_rooks[i];
+            let Some(king) = back_rank_king(&self.pieces, &self.colors, color) else {
+                return Err(format!("{:?} castling right without a king on the back rank", color));
+            };
+            let own_rooks = self.pieces[Piece::Rook.to_index()] & self.colors[color.to_index()];
+            let kingside = i % 2 == 0;
+            if (own_rooks & BitBoard::from_square(rook)).is_empty()
+                || rook.rank() != king.rank()
+                || (rook.to_index() > king.to_index()) != kingside
+            {
+                return Err(format!("Castling right without a rook on {}", rook));
+            }
+        }
+
+        if let Some(ep) = self.ep_square
+            && !self.ep_consistent(ep)
+        {
+            return Err(format!("Inconsistent en passant square {}", ep));
+        }
+
+        let their_king = kings & self.colors[(!self.side_to_move).to_index()];
+        let king_sq = their_king.iter().next().expect("king count checked above");
+        if !(self.attackers_to(king_sq, seen) & self.colors[self.side_to_move.to_index()]).is_empty() {
+            return Err("Side not to move is in check".to_string());
+        }
+
+        Ok(())
+    }
+
+    /// Whether `ep` could follow a double push: the pawn that made it sits in front of the
+    /// square, with the square it passed and the one it came from empty
+    fn ep_consistent(&self, ep: Square) -> bool {
+        let ep_rank = if self.side_to_move == Color::White { 5 } else { 2 };
+        if ep.rank().to_index() != ep_rank {
+            return false;
+        }
+        let pawn_sq = ep.forward(!self.side_to_move).expect("rank checked above");
+        let from_sq = ep.forward(self.side_to_move).expect("rank checked above");
+        let their_pawns = self.pieces[Piece::Pawn.to_index()] & self.colors[(!self.side_to_move).to_index()];
+        let occupied = self.colors[0] | self.colors[1];
+        !(their_pawns & BitBoard::from_square(pawn_sq)).is_empty()
+            && (occupied & (BitBoard::from_square(ep) | BitBoard::from_square(from_sq))).is_empty()
+    }
+
+    /// Make move after checking it is legal, for moves from outside the engine. Moves built
+    /// from squares alone are normalized first, so UCI castling and en passant work.
+    pub fn try_make_move(&self, mv: ChessMove) -> Result<Board, MoveError> {
+        let src = mv.get_source();
+        if (self.color_combined(self.side_to_move) & BitBoard::from_square(src)).is_empty() {
+            return Err(MoveError::NoPiece(src));
+        }
+        let mv = self.normalize_move(mv);
+        if !self.legal(mv) {
+            return Err(MoveError::Illegal(mv));
+        }
+        Ok(self.make_move_new(mv))
+    }
+
+    /// Make move and return resulting board. Does not validate legality (`try_make_move` does),
+    /// and reads en passant and castling from the move kind (moves from `MoveGen` or
+    /// `normalize_move` carry it)
     ///
     /// Handles:
     /// 1. Remove piece from source square
     /// 2. Remove captured piece (if any)
     /// 3. Handle en passant capture (captured pawn is NOT on destination)
     /// 4. Place piece/promoted piece on destination
-    /// 5. Handle castling (move the rook too)
-    /// 6. Update castling rights via CASTLING_MASK[64]
+    /// 5. Handle castling (king-takes-own-rook: move the rook, king lands on the g/c file)
+    /// 6. Update castling rights (king moved, or a castling rook's square touched)
     /// 7. Set new en passant square (double pawn push)
     /// 8. Update halfmove clock
     /// 9. Flip side to move + update hash
     /// 10. Recompute checkers
     pub fn make_move_new(&self, mv: ChessMove) -> Board {
+        debug_assert_eq!(mv, self.normalize_move(mv), "move without its castling or en passant kind");
         let mut b = *self;
         let src = mv.get_source();
         let dst = mv.get_dest();
@@ -139,7 +482,8 @@ impl Board {
         let them_idx = them.to_index();
 
         let piece = self.piece_on(src).expect("no piece on source square");
-        let captured = self.piece_on(dst);
+        let castling = mv.is_castling();
+        let captured = if castling { None } else { self.piece_on(dst) };
 
         // Remove old hash components
         b.hash ^= zobrist::castling_key(b.castling);
@@ -153,69 +497,69 @@ impl Board {
         b.pieces[piece.to_index()] ^= src_bb;
         b.colors[us_idx] ^= src_bb;
         b.hash ^= zobrist::piece_key(piece.to_index(), us_idx, src.to_index());
+        b.update_psq(piece.to_index(), us_idx, src.to_index(), -1);
 
         // Handle capture (regular)
         if let Some(cap) = captured {
             b.pieces[cap.to_index()] ^= dst_bb;
             b.colors[them_idx] ^= dst_bb;
             b.hash ^= zobrist::piece_key(cap.to_index(), them_idx, dst.to_index());
+            b.update_psq(cap.to_index(), them_idx, dst.to_index(), -1);
         }
 
         // Handle en passant capture
-        let is_ep = piece == Piece::Pawn
-            && self.ep_square == Some(dst)
-            && captured.is_none();
+        let is_ep = mv.is_en_passant();
 
         if is_ep {
-            let ep_pawn_sq = match us {
-                Color::White => Square::new(dst.to_index() as u8 - 8),
-                Color::Black => Square::new(dst.to_index() as u8 + 8),
-            };
+            // The captured pawn sits just behind the ep square
+            let ep_pawn_sq = dst.forward(them).expect("ep square on rank 3 or 6");
             let ep_bb = BitBoard::from_square(ep_pawn_sq);
             b.pieces[Piece::Pawn.to_index()] ^= ep_bb;
             b.colors[them_idx] ^= ep_bb;
             b.hash ^= zobrist::piece_key(Piece::Pawn.to_index(), them_idx, ep_pawn_sq.to_index());
+            b.update_psq(Piece::Pawn.to_index(), them_idx, ep_pawn_sq.to_index(), -1);
         }
 
+        // Castling: the rook moves first, since the king may land on its start square
+        let piece_dst = if castling {
+            let (king_to, rook_to) = Self::castling_targets(mv);
+            Self::move_piece(&mut b, Piece::Rook, us, dst, rook_to);
+            king_to
+        } else {
+            dst
+        };
+
         // Place piece (or promoted piece) on destination
         let placed_piece = mv.get_promotion().unwrap_or(piece);
-        b.pieces[placed_piece.to_index()] ^= dst_bb;
-        b.colors[us_idx] ^= dst_bb;
-        b.hash ^= zobrist::piece_key(placed_piece.to_index(), us_idx, dst.to_index());
+        let piece_dst_bb = BitBoard::from_square(piece_dst);
+        b.pieces[placed_piece.to_index()] ^= piece_dst_bb;
+        b.colors[us_idx] ^= piece_dst_bb;
+        b.hash ^= zobrist::piece_key(placed_piece.to_index(), us_idx, piece_dst.to_index());
+        b.update_psq(placed_piece.to_index(), us_idx, piece_dst.to_index(), 1);
 
-        // Handle castling (move the rook)
-        if piece == Piece::King {
-            let from_file = src.file().to_index();
-            let to_file = dst.file().to_index();
-
-            if from_file == 4 && to_file == 6 {
-                // Kingside castle
-                let rook_src = Square::make_square(src.rank(), File::from_index(7));
-                let rook_dst = Square::make_square(src.rank(), File::from_index(5));
-                Self::move_piece(&mut b, Piece::Rook, us, rook_src, rook_dst);
-            } else if from_file == 4 && to_file == 2 {
-                // Queenside castle
-                let rook_src = Square::make_square(src.rank(), File::from_index(0));
-                let rook_dst = Square::make_square(src.rank(), File::from_index(3));
-                Self::move_piece(&mut b, Piece::Rook, us, rook_src, rook_dst);
+        // Update castling rights
+        if b.castling != 0 {
+            if piece == Piece::King {
+                b.castling &= if us == Color::White { !(WK | WQ) } else { !(BK | BQ) };
+            }
+            for (i, &rook_sq) in self.castle_rooks.iter().enumerate() {
+                if rook_sq == src || rook_sq == dst {
+                    b.castling &= !(1 << i);
+                }
             }
         }
 
-        // Update castling rights
-        b.castling &= CASTLING_MASK[src.to_index()];
-        b.castling &= CASTLING_MASK[dst.to_index()];
-
-        // Update en passant square
+        // Update en passant square (only if an enemy pawn can actually capture onto it)
         b.ep_square = None;
         if piece == Piece::Pawn {
             let src_rank = src.rank().to_index();
             let dst_rank = dst.rank().to_index();
             if src_rank.abs_diff(dst_rank) == 2 {
                 let ep_rank = (src_rank + dst_rank) / 2;
-                b.ep_square = Some(Square::make_square(
-                    Rank::from_index(ep_rank),
-                    src.file(),
-                ));
+                let ep = Square::make_square(Rank::from_index(ep_rank), src.file());
+                if b.ep_capturable(ep, them) {
+                    b.ep_square = Some(ep);
+                }
             }
         }
 
@@ -223,7 +567,12 @@ impl Board {
         if piece == Piece::Pawn || captured.is_some() || is_ep {
             b.halfmove_clock = 0;
         } else {
-            b.halfmove_clock = self.halfmove_clock + 1;
+            b.halfmove_clock = self.halfmove_clock.saturating_add(1);
+        }
+
+        // Fullmove number advances once Black has moved
+        if us == Color::Black {
+            b.fullmove_number = self.fullmove_number.saturating_add(1);
         }
 
         // Hash in new castling + EP
@@ -242,6 +591,12 @@ impl Board {
         b
     }
 
+    /// Whether a pawn of `capturer` attacks `ep`, i.e. the en passant square is usable
+    fn ep_capturable(&self, ep: Square, capturer: Color) -> bool {
+        let pawns = self.pieces[Piece::Pawn.to_index()] & self.colors[capturer.to_index()];
+        !(pawn_attacks(!capturer, ep) & pawns).is_empty()
+    }
+
     /// Helper to move a piece (for castling rook moves)
     fn move_piece(b: &mut Board, piece: Piece, color: Color, from: Square, to: Square) {
         let from_bb = BitBoard::from_square(from);
@@ -252,6 +607,8 @@ impl Board {
         b.colors[ci] ^= from_bb | to_bb;
         b.hash ^= zobrist::piece_key(pi, ci, from.to_index());
         b.hash ^= zobrist::piece_key(pi, ci, to.to_index());
+        b.update_psq(pi, ci, from.to_index(), -1);
+        b.update_psq(pi, ci, to.to_index(), 1);
     }
 
     /// Null move: flip side, clear EP. Returns None if in check
@@ -275,36 +632,174 @@ impl Board {
         Some(b)
     }
 
+    /// Same position with colors swapped and ranks flipped (a1 <-> a8), other side to move.
+    /// A correct evaluation scores both identically from the side to move's view.
+    pub fn mirror(&self) -> Board {
+        let mut b = *self;
+        for bb in b.pieces.iter_mut() {
+            *bb = BitBoard(bb.0.swap_bytes());
+        }
+        b.colors = [BitBoard(self.colors[1].0.swap_bytes()), BitBoard(self.colors[0].0.swap_bytes())];
+        b.side_to_move = !self.side_to_move;
+        // White's rights become Black's and vice versa: bit order WK, WQ, BK, BQ
+        b.castling = (self.castling >> 2) | ((self.castling & 3) << 2);
+        let flip = |sq: Square| Square::new(sq.to_index() as u8 ^ 56);
+        let r = self.castle_rooks;
+        b.castle_rooks = [flip(r[2]), flip(r[3]), flip(r[0]), flip(r[1])];
+        b.ep_square = self.ep_square.map(flip);
+        b.refresh();
+        b
+    }
+
+    /// Same position reflected left to right (a-file <-> h-file). Castling rights are
+    /// dropped, since the reflected king and rooks can't castle by the usual rules.
+    pub fn flip_horizontal(&self) -> Board {
+        let mut b = *self;
+        let flip_files = |bb: BitBoard| {
+            let mut out = 0u64;
+            for (rank, byte) in bb.0.to_le_bytes().into_iter().enumerate() {
+                out |= (byte.reverse_bits() as u64) << (rank * 8);
+            }
+            BitBoard(out)
+        };
+        for bb in b.pieces.iter_mut().chain(b.colors.iter_mut()) {
+            *bb = flip_files(*bb);
+        }
+        b.castling = 0;
+        b.castle_rooks = DEFAULT_CASTLE_ROOKS;
+        b.ep_square = self.ep_square.map(|sq| Square::new(sq.to_index() as u8 ^ 7));
+        b.refresh();
+        b
+    }
+
+    /// Same position with the piece on `sq` taken off, for setting up odds games. Castling
+    /// rights that used a removed rook are dropped. None for an empty square or a king.
+    pub fn without_piece(&self, sq: Square) -> Option<Board> {
+        let piece = self.piece_on(sq)?;
+        if piece == Piece::King {
+            return None;
+        }
+        let mut b = *self;
+        let keep = !BitBoard::from_square(sq);
+        for bb in b.pieces.iter_mut().chain(b.colors.iter_mut()) {
+            *bb &= keep;
+        }
+        for i in 0..4 {
+            if self.castle_rooks[i] == sq {
+                b.castling &= !(1 << i);
+            }
+        }
+        b.ep_square = None;
+        b.refresh();
+        Some(b)
+    }
+
+    /// Recompute everything derived from the piece placement and state fields
+    fn refresh(&mut self) {
+        self.hash = self.compute_hash();
+        self.checkers = self.compute_checkers();
+        (self.psq_mg, self.psq_eg, self.phase) = self.compute_psq();
+        self.chess960 = self.chess960 || self.needs_chess960();
+    }
+
     /// Compute which enemy pieces are giving check to the current side's king
     fn compute_checkers(&self) -> BitBoard {
         let us = self.side_to_move;
         let them = !us;
-        let our_king_bb = self.pieces[Piece::King.to_index()] & self.colors[us.to_index()];
-        if our_king_bb.is_empty() {
-            return EMPTY;
-        }
-        let king_sq = Square::new(our_king_bb.0.trailing_zeros() as u8);
-        let occupied = self.combined();
-        let enemy = self.colors[them.to_index()];
-
-        let mut checkers = EMPTY;
-
-        // Knight checks
-        checkers |= knight_attacks(king_sq) & self.pieces[Piece::Knight.to_index()] & enemy;
-        // Pawn checks
-        checkers |= pawn_attacks(us, king_sq) & self.pieces[Piece::Pawn.to_index()] & enemy;
-        // Bishop/Queen checks (diagonal)
-        checkers |= bishop_attacks(king_sq, occupied)
-            & (self.pieces[Piece::Bishop.to_index()] | self.pieces[Piece::Queen.to_index()])
-            & enemy;
-        // Rook/Queen checks (straight)
-        checkers |= rook_attacks(king_sq, occupied)
-            & (self.pieces[Piece::Rook.to_index()] | self.pieces[Piece::Queen.to_index()])
-            & enemy;
-        // King checks (theoretically impossible but keep for consistency)
-        checkers |= king_attacks(king_sq) & self.pieces[Piece::King.to_index()] & enemy;
-
-        checkers
+        self.attackers_to(self.king_square(us), self.combined()) & self.colors[them.to_index()]
+    }
+
+    /// All pieces of both colors attacking `sq`, with sliders seeing through everything not in
+    /// `occupied` (pass a reduced occupancy for x-rays, e.g. in SEE)
+    pub fn attackers_to(&self, sq: Square, occupied: BitBoard) -> BitBoard {
+        let pawns = self.pieces[Piece::Pawn.to_index()];
+        let diagonal = self.pieces[Piece::Bishop.to_index()] | self.pieces[Piece::Queen.to_index()];
+        let straight = self.pieces[Piece::Rook.to_index()] | self.pieces[Piece::Queen.to_index()];
+
+        // A white pawn attacks sq from where a black pawn on sq would attack, and vice versa
+        (pawn_attacks(Color::Black, sq) & pawns & self.colors[Color::White.to_index()])
+            | (pawn_attacks(Color::White, sq) & pawns & self.colors[Color::Black.to_index()])
+            | (knight_attacks(sq) & self.pieces[Piece::Knight.to_index()])
+            | (king_attacks(sq) & self.pieces[Piece::King.to_index()])
+            | (bishop_attacks(sq, occupied) & diagonal)
+            | (rook_attacks(sq, occupied) & straight)
+    }
+
+    /// Static exchange evaluation: the material `mv` wins, in MVV values (centipawns), once both
+    /// sides have made every capture on its destination that pays, least valuable attacker first.
+    /// X-rays are seen, pins are not; a promotion counts the promoted piece and castling is 0.
+    pub fn see(&self, mv: ChessMove) -> Score {
+        if self.is_castling(mv) {
+            return 0;
+        }
+        let value = |piece: Piece| pst::MVV_VALUE[piece.to_index()];
+        let (from, to) = (mv.get_source(), mv.get_dest());
+        let Some(mut on_square) = self.piece_on(from) else {
+            return 0;
+        };
+
+        // gains[d]: what the side making capture d nets if the exchange stops after it
+        let mut gains = [0 as Score; 32];
+        gains[0] = self.captured_piece(mv).map_or(0, value);
+        let mut occupied = self.combined() ^ BitBoard::from_square(from);
+        if self.is_en_passant(mv) {
+            occupied ^= BitBoard::from_square(Square::make_square(from.rank(), to.file()));
+        }
+        if let Some(promotion) = mv.get_promotion() {
+            gains[0] += value(promotion) - value(Piece::Pawn);
+            on_square = promotion;
+        }
+
+        let diagonal = self.pieces[Piece::Bishop.to_index()] | self.pieces[Piece::Queen.to_index()];
+        let straight = self.pieces[Piece::Rook.to_index()] | self.pieces[Piece::Queen.to_index()];
+        let mut attackers = self.attackers_to(to, occupied) & occupied;
+        let mut side = !self.side_to_move;
+        let mut depth = 0;
+        loop {
+            let ours = attackers & self.colors[side.to_index()];
+            let Some(piece) = Piece::ALL.into_iter().find(|&p| !(ours & self.pieces[p.to_index()]).is_empty()) else {
+                break;
+            };
+            // The king can't take on a square the other side still attacks
+            if piece == Piece::King && !(attackers & self.colors[(!side).to_index()]).is_empty() {
+                break;
+            }
+            depth += 1;
+            gains[depth] = value(on_square) - gains[depth - 1];
+            let attacker = (ours & self.pieces[piece.to_index()]).iter().next().expect("attacker found above");
+            occupied ^= BitBoard::from_square(attacker);
+            // Sliders lined up behind the attacker join in
+            attackers |= (bishop_attacks(to, occupied) & diagonal) | (rook_attacks(to, occupied) & straight);
+            attackers &= occupied;
+            on_square = piece;
+            side = !side;
+        }
+
+        // Either side may stop capturing when going on would cost it
+        while depth > 0 {
+            gains[depth - 1] = -(-gains[depth - 1]).max(gains[depth]);
+            depth -= 1;
+        }
+        gains[0]
+    }
+
+    /// Compute eval accumulators (mg, eg, phase) from scratch (for FEN parsing)
+    fn compute_psq(&self) -> (Score, Score, i32) {
+        let mut mg = 0;
+        let mut eg = 0;
+        let mut phase = 0;
+        for piece in Piece::ALL {
+            for color in [Color::White, Color::Black] {
+                let bb = self.pieces[piece.to_index()] & self.colors[color.to_index()];
+                for sq in bb.iter() {
+                    let (m, e) = pst::psq_value(piece.to_index(), color.to_index(), sq.to_index());
+                    mg += m;
+                    eg += e;
+                    phase += PHASE_WEIGHT[piece.to_index()];
+                }
+            }
+        }
+        (mg, eg, phase)
     }
 
     /// Compute hash from scratch (for FEN parsing)
@@ -331,26 +826,6 @@ impl Board {
     }
 }
 
-// --- Castling rights update mask ---
-// When piece moves from or to square, AND castling rights with this mask
-// This handles rook captures and king/rook moves
-const CASTLING_MASK: [u8; 64] = {
-    let mut mask = [0xFFu8; 64];
-    // A1 (index 0) = White queenside rook
-    mask[0] = 0xFF ^ WQ;
-    // H1 (index 7) = White kingside rook
-    mask[7] = 0xFF ^ WK;
-    // E1 (index 4) = White king
-    mask[4] = 0xFF ^ (WK | WQ);
-    // A8 (index 56) = Black queenside rook
-    mask[56] = 0xFF ^ BQ;
-    // H8 (index 63) = Black kingside rook
-    mask[63] = 0xFF ^ BK;
-    // E8 (index 60) = Black king
-    mask[60] = 0xFF ^ (BK | BQ);
-    mask
-};
-
 // --- Default (starting position) ---
 
 impl Default for Board {
@@ -372,18 +847,37 @@ impl PartialEq for Board {
 
 impl Eq for Board {}
 
+/// Hashes the Zobrist key, which depends on exactly the fields `==` compares
+impl Hash for Board {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_u64(self.hash);
+    }
+}
+
 // --- FEN parsing ---
 
 impl FromStr for Board {
-    type Err = String;
+    type Err = ParseFenError;
 
-    fn from_str(fen: &str) -> Result<Self, String> {
-        super::attacks::init_attacks();
-        super::zobrist::init_zobrist();
+    /// Parse a complete six-field FEN, rejecting anything malformed or impossible
+    fn from_str(fen: &str) -> Result<Self, ParseFenError> {
+        Board::parse_fen(fen, false)
+    }
+}
 
+impl Board {
+    /// Parse a FEN the way positions turn up in the wild: the halfmove and fullmove fields may
+    /// be missing or garbled (EPD has neither) and default to 0 and 1, and an en passant square
+    /// that doesn't fit the position is dropped instead of rejected
+    pub fn from_fen_lenient(fen: &str) -> Result<Board, ParseFenError> {
+        Board::parse_fen(fen, true)
+    }
+
+    fn parse_fen(fen: &str, lenient: bool) -> Result<Board, ParseFenError> {
         let parts: Vec<&str> = fen.split_whitespace().collect();
-        if parts.len() < 4 {
-            return Err(format!("FEN needs at least 4 fields, got {}", parts.len()));
+        let needed = if lenient { 4 } else { 6 };
+        if parts.len() < needed {
+            return Err(ParseFenError::MissingFields { needed, found: parts.len() });
         }
 
         let mut pieces = [EMPTY; 6];
@@ -411,11 +905,11 @@ impl FromStr for Board {
                 'r' => Piece::Rook,
                 'q' => Piece::Queen,
                 'k' => Piece::King,
-                _ => return Err(format!("Invalid piece char: {}", ch)),
+                _ => return Err(ParseFenError::BadPiece(ch)),
             };
 
             if !(0..=7).contains(&rank) || !(0..=7).contains(&file) {
-                return Err("FEN rank/file out of bounds".to_string());
+                return Err(ParseFenError::BadPlacement);
             }
 
             let sq = Square::make_square(Rank::from_index(rank as usize), File::from_index(file as usize));
@@ -429,60 +923,87 @@ impl FromStr for Board {
         let side_to_move = match parts[1] {
             "w" => Color::White,
             "b" => Color::Black,
-            _ => return Err(format!("Invalid side to move: {}", parts[1])),
+            other => return Err(ParseFenError::BadSideToMove(other.to_string())),
         };
 
-        // Castling rights
+        // Castling rights: K/Q name the outermost rook on each side of the king, so the
+        // same letters work for Chess960 start positions. Shredder-FEN / X-FEN file letters
+        // (A-H, a-h) name the rook's file directly. Rights without a rook are dropped.
         let mut castling = 0u8;
+        let mut castle_rooks = DEFAULT_CASTLE_ROOKS;
         for ch in parts[2].chars() {
-            match ch {
-                'K' => castling |= WK,
-                'Q' => castling |= WQ,
-                'k' => castling |= BK,
-                'q' => castling |= BQ,
-                '-' => {}
-                _ => return Err(format!("Invalid castling char: {}", ch)),
+            let color = if ch.is_ascii_uppercase() { Color::White } else { Color::Black };
+            let rook_sq = match ch.to_ascii_lowercase() {
+                'k' => find_castle_rook(&pieces, &colors, color, true),
+                'q' => find_castle_rook(&pieces, &colors, color, false),
+                'a'..='h' => {
+                    let file = (ch.to_ascii_lowercase() as u8 - b'a') as usize;
+                    castle_rook_on_file(&pieces, &colors, color, file)
+                }
+                '-' => continue,
+                _ => return Err(ParseFenError::BadCastling(ch)),
+            };
+            if let Some((rook_sq, kingside)) = rook_sq {
+                let right = match (color, kingside) {
+                    (Color::White, true) => WK,
+                    (Color::White, false) => WQ,
+                    (Color::Black, true) => BK,
+                    (Color::Black, false) => BQ,
+                };
+                castling |= right;
+                castle_rooks[right.trailing_zeros() as usize] = rook_sq;
             }
         }
 
         // En passant square
-        let ep_square = if parts[3] == "-" {
-            None
-        } else {
-            let bytes = parts[3].as_bytes();
-            if bytes.len() >= 2 {
-                let f = bytes[0].wrapping_sub(b'a');
-                let r = bytes[1].wrapping_sub(b'1');
-                if f < 8 && r < 8 {
-                    Some(Square::make_square(Rank::from_index(r as usize), File::from_index(f as usize)))
-                } else {
-                    None
-                }
-            } else {
-                None
+        let ep_square = match parts[3].as_bytes() {
+            b"-" => None,
+            &[f @ b'a'..=b'h', r @ b'1'..=b'8'] => {
+                Some(Square::make_square(Rank::from_index((r - b'1') as usize), File::from_index((f - b'a') as usize)))
             }
+            _ if lenient => None,
+            _ => return Err(ParseFenError::BadEnPassant(parts[3].to_string())),
         };
 
-        // Halfmove clock (optional)
-        let halfmove_clock = if parts.len() > 4 {
-            parts[4].parse().unwrap_or(0)
-        } else {
-            0
+        // Halfmove clock and fullmove number (at least 1)
+        let counter = |i: usize, default: u16| match parts.get(i).map(|s| s.parse::<u16>()) {
+            Some(Ok(n)) => Ok(n),
+            _ if lenient => Ok(default),
+            _ => Err(ParseFenError::BadCounter(parts[i].to_string())),
         };
+        let halfmove_clock = u8::try_from(counter(4, 0)?).unwrap_or(u8::MAX);
+        let fullmove_number = counter(5, 1)?.max(1);
 
         let mut board = Board {
             pieces,
             colors,
             side_to_move,
             castling,
+            castle_rooks,
+            chess960: false,
             ep_square,
             halfmove_clock,
+            fullmove_number,
             hash: 0,
             checkers: EMPTY,
+            psq_mg: 0,
+            psq_eg: 0,
+            phase: 0,
         };
 
+        if lenient && board.ep_square.is_some_and(|ep| !board.ep_consistent(ep)) {
+            board.ep_square = None;
+        }
+        board.validate().map_err(ParseFenError::Inconsistent)?;
+        // A FEN may name the ep square after any double push; drop it when no capture is possible
+        // so the position hashes like the same one reached by other move orders
+        if board.ep_square.is_some_and(|ep| !board.ep_capturable(ep, side_to_move)) {
+            board.ep_square = None;
+        }
+        board.chess960 = board.needs_chess960();
         board.hash = board.compute_hash();
         board.checkers = board.compute_checkers();
+        (board.psq_mg, board.psq_eg, board.phase) = board.compute_psq();
 
         Ok(board)
     }
@@ -525,10 +1046,23 @@ impl fmt::Display for Board {
         if self.castling == 0 {
             write!(f, "-")?;
         } else {
-            if self.castling & WK != 0 { write!(f, "K")?; }
-            if self.castling & WQ != 0 { write!(f, "Q")?; }
-            if self.castling & BK != 0 { write!(f, "k")?; }
-            if self.castling & BQ != 0 { write!(f, "q")?; }
+            // X-FEN: K/Q when the castling rook is the outermost one on its side, else its file
+            for (i, (color, kingside, letter)) in
+                [(Color::White, true, 'K'), (Color::White, false, 'Q'), (Color::Black, true, 'k'), (Color::Black, false, 'q')]
+                    .into_iter()
+                    .enumerate()
+            {
+                if self.castling & (1 << i) == 0 {
+                    continue;
+                }
+                let rook = self.castle_rooks[i];
+                if find_castle_rook(&self.pieces, &self.colors, color, kingside).map(|(r, _)| r) == Some(rook) {
+                    write!(f, "{}", letter)?;
+                } else {
+                    let file = (b'a' + rook.file().to_index() as u8) as char;
+                    write!(f, "{}", if color == Color::White { file.to_ascii_uppercase() } else { file })?;
+                }
+            }
         }
 
         // En passant
@@ -540,7 +1074,7 @@ impl fmt::Display for Board {
         }
 
         // Halfmove clock and fullmove number
-        write!(f, " {} 1", self.halfmove_clock)?;
+        write!(f, " {} {}", self.halfmove_clock, self.fullmove_number)?;
 
         Ok(())
     }
@@ -558,17 +1092,175 @@ fn piece_to_char(piece: Piece, color: Color) -> char {
     if color == Color::White { ch.to_ascii_uppercase() } else { ch }
 }
 
+fn piece_to_unicode(piece: Piece, color: Color) -> char {
+    const WHITE: [char; 6] = ['♙', '♘', '♗', '♖', '♕', '♔'];
+    const BLACK: [char; 6] = ['♟', '♞', '♝', '♜', '♛', '♚'];
+    if color == Color::White { WHITE[piece.to_index()] } else { BLACK[piece.to_index()] }
+}
+
+// --- Diagram output (UCI `d`) ---
+
+impl Board {
+    /// 8x8 board diagram (rank 8 at the top) followed by the FEN, state fields, hash and
+    /// checkers. `unicode` draws pieces as chess glyphs instead of FEN letters.
+    pub fn diagram(&self, unicode: bool) -> String {
+        let separator = " +---+---+---+---+---+---+---+---+\n";
+        let mut out = String::from(separator);
+        for rank in (0..8).rev() {
+            for file in 0..8 {
+                let sq = Square::make_square(Rank::from_index(rank), File::from_index(file));
+                let ch = match (self.piece_on(sq), self.color_on(sq)) {
+                    (Some(piece), Some(color)) if unicode => piece_to_unicode(piece, color),
+                    (Some(piece), Some(color)) => piece_to_char(piece, color),
+                    _ => ' ',
+                };
+                out.push_str(&format!(" | {}", ch));
+            }
+            out.push_str(&format!(" | {}\n{}", rank + 1, separator));
+        }
+        out.push_str("   a   b   c   d   e   f   g   h\n\n");
+
+        let fen = self.to_string();
+        let fields: Vec<&str> = fen.split(' ').collect();
+        let checkers: Vec<String> = self.checkers.iter().map(|sq| sq.to_string()).collect();
+        out.push_str(&format!("Fen: {}\n", fen));
+        out.push_str(&format!("Side to move: {:?}\n", self.side_to_move));
+        out.push_str(&format!("Castling: {}\n", fields[2]));
+        out.push_str(&format!("En passant: {}\n", fields[3]));
+        out.push_str(&format!("Halfmove clock: {}\n", self.halfmove_clock));
+        out.push_str(&format!("Key: {:016X}\n", self.hash));
+        out.push_str(&format!("Checkers: {}", if checkers.is_empty() { "-".to_string() } else { checkers.join(" ") }));
+        out
+    }
+
+    /// Parse a diagram as `diagram` prints it (FEN letters or chess glyphs, rank 8 at the top),
+    /// or a plain 8x8 grid of letters with `.` for empty squares. The `Side to move:`,
+    /// `Castling:`, `En passant:` and `Halfmove clock:` lines are optional and default to White,
+    /// `-`, `-` and 0; other lines (`Fen:` included) are ignored, so an edited grid is never
+    /// overruled by a stale FEN below it.
+    pub fn from_diagram(diagram: &str) -> Result<Board, ParseFenError> {
+        let mut ranks: Vec<String> = Vec::new();
+        let (mut side, mut castling, mut ep, mut halfmove) = ("w", "-", "-", "0");
+        for line in diagram.lines() {
+            if let Some((key, value)) = line.split_once(':') {
+                let value = value.trim();
+                if value.is_empty() {
+                    continue;
+                }
+                match key.trim().to_ascii_lowercase().as_str() {
+                    "side to move" => {
+                        side = match value.to_ascii_lowercase().as_str() {
+                            "white" | "w" => "w",
+                            "black" | "b" => "b",
+                            _ => return Err(ParseFenError::BadSideToMove(value.to_string())),
+                        }
+                    }
+                    "castling" => castling = value,
+                    "en passant" => ep = value,
+                    "halfmove clock" => halfmove = value,
+                    _ => {}
+                }
+            } else if let Some(rank) = diagram_rank(line)? {
+                ranks.push(rank);
+            }
+        }
+        if ranks.len() != 8 {
+            return Err(ParseFenError::BadPlacement);
+        }
+        Board::from_str(&format!("{} {} {} {} {} 1", ranks.join("/"), side, castling, ep, halfmove))
+    }
+}
+
+/// One diagram line as a FEN rank, or None for lines that aren't ranks (borders, file letters,
+/// blank lines). A `|`-separated line is always a rank, so a bad square in it is an error.
+fn diagram_rank(line: &str) -> Result<Option<String>, ParseFenError> {
+    let squares: Vec<Option<char>> = if line.contains('|') {
+        let cells: Vec<&str> = line.split('|').skip(1).map(str::trim).take(8).collect();
+        if cells.len() < 8 {
+            return Err(ParseFenError::BadPlacement);
+        }
+        let mut squares = Vec::with_capacity(8);
+        for cell in cells {
+            let mut chars = cell.chars();
+            squares.push(match (chars.next(), chars.next()) {
+                (None | Some('.'), None) => None,
+                (Some(ch), None) => Some(diagram_piece(ch).ok_or(ParseFenError::BadPiece(ch))?),
+                _ => return Err(ParseFenError::BadPlacement),
+            });
+        }
+        squares
+    } else {
+        // Rank numbers may label either end of a plain grid row
+        let text: String = line.chars().filter(|ch| !ch.is_whitespace()).collect();
+        let text = text.trim_start_matches(|ch| ('1'..='8').contains(&ch)).trim_end_matches(|ch| ('1'..='8').contains(&ch));
+        if text.chars().count() != 8 {
+            return Ok(None);
+        }
+        let squares: Option<Vec<Option<char>>> =
+            text.chars().map(|ch| if ch == '.' { Some(None) } else { diagram_piece(ch).map(Some) }).collect();
+        match squares {
+            Some(squares) => squares,
+            None => return Ok(None),
+        }
+    };
+
+    let mut rank = String::new();
+    let mut empty = 0;
+    for square in squares {
+        match square {
+            Some(piece) => {
+                if empty > 0 {
+                    rank.push_str(&empty.to_string());
+                    empty = 0;
+                }
+                rank.push(piece);
+            }
+            None => empty += 1,
+        }
+    }
+    if empty > 0 {
+        rank.push_str(&empty.to_string());
+    }
+    Ok(Some(rank))
+}
+
+/// The FEN letter for a diagram square: a FEN letter itself or a chess glyph
+fn diagram_piece(ch: char) -> Option<char> {
+    const GLYPHS: [(char, char); 12] = [
+        ('♙', 'P'), ('♘', 'N'), ('♗', 'B'), ('♖', 'R'), ('♕', 'Q'), ('♔', 'K'),
+        ('♟', 'p'), ('♞', 'n'), ('♝', 'b'), ('♜', 'r'), ('♛', 'q'), ('♚', 'k'),
+    ];
+    if "pnbrqkPNBRQK".contains(ch) {
+        return Some(ch);
+    }
+    GLYPHS.iter().find(|&&(glyph, _)| glyph == ch).map(|&(_, letter)| letter)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use super::super::movegen::MoveGen;
 
-    fn init() {
-        super::super::init();
+
+    #[test]
+    fn test_try_make_move() {
+        let board = Board::default();
+        let e4 = ChessMove::from_uci("e2e4").unwrap();
+        assert_eq!(board.try_make_move(e4).unwrap(), board.make_move_new(e4));
+        assert_eq!(board.try_make_move(ChessMove::from_uci("e3e4").unwrap()), Err(MoveError::NoPiece(Square::E3)));
+        assert_eq!(board.try_make_move(ChessMove::from_uci("e7e5").unwrap()), Err(MoveError::NoPiece(Square::E7)));
+        let err = board.try_make_move(ChessMove::from_uci("e2e5").unwrap()).unwrap_err();
+        assert_eq!(err.to_string(), "Illegal move e2e5");
+
+        // Castling given as the king's two-square step is normalized before the legality check
+        let board = Board::from_str("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+        let after = board.try_make_move(ChessMove::from_uci("e1g1").unwrap()).unwrap();
+        assert_eq!(after.piece_on(Square::G1), Some(Piece::King));
+        assert_eq!(after.piece_on(Square::F1), Some(Piece::Rook));
     }
 
     #[test]
     fn test_default_board() {
-        init();
         let board = Board::default();
         assert_eq!(board.side_to_move(), Color::White);
         assert_eq!(board.castling, WK | WQ | BK | BQ);
@@ -577,15 +1269,82 @@ mod tests {
 
     #[test]
     fn test_fen_roundtrip_startpos() {
-        init();
         let board = Board::default();
         let fen = board.to_string();
         assert!(fen.starts_with("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq -"));
     }
 
+    #[test]
+    fn test_fullmove_number() {
+        let fen = "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3";
+        let board = Board::from_str(fen).unwrap();
+        assert_eq!(board.fullmove_number(), 3);
+        assert_eq!(board.to_string(), fen);
+
+        // White's move keeps the number, Black's reply advances it
+        let mut moves = MoveGen::new_legal(&board);
+        let after_white = board.make_move_new(moves.next().unwrap());
+        assert_eq!(after_white.fullmove_number(), 3);
+        let after_black = after_white.make_move_new(MoveGen::new_legal(&after_white).next().unwrap());
+        assert_eq!(after_black.fullmove_number(), 4);
+
+        // Missing field defaults to 1 in lenient mode only
+        let short = Board::from_fen_lenient("4k3/8/8/8/8/8/8/4K3 w - -").unwrap();
+        assert_eq!(short.fullmove_number(), 1);
+        assert!(Board::from_str("4k3/8/8/8/8/8/8/4K3 w - -").is_err());
+    }
+
+    #[test]
+    fn test_king_square() {
+        let board = Board::default();
+        assert_eq!(board.king_square(Color::White), Square::E1);
+        assert_eq!(board.king_square(Color::Black), Square::E8);
+        let board = Board::from_str("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+        let castled = board.make_move_new(board.normalize_move(ChessMove::from_uci("e1c1").unwrap()));
+        assert_eq!(castled.king_square(Color::White), Square::C1);
+    }
+
+    #[test]
+    fn test_board_as_hash_key() {
+        use std::collections::HashSet;
+
+        // Transpositions are the same key; the move counters don't matter
+        let play = |moves: &[&str]| {
+            moves.iter().fold(Board::default(), |b, m| b.make_move_new(ChessMove::from_uci(m).unwrap()))
+        };
+        let a = play(&["g1f3", "g8f6", "b1c3"]);
+        let b = play(&["b1c3", "g8f6", "g1f3"]);
+        let mut seen = HashSet::new();
+        assert!(seen.insert(a));
+        assert!(!seen.insert(b));
+        assert!(seen.insert(play(&["g1f3", "g8f6"])));
+        assert_eq!(a.zobrist(), b.zobrist());
+        assert_eq!(a.zobrist(), a.get_hash());
+        assert_eq!(Board::default().zobrist(), Board::from_str("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 5 9").unwrap().zobrist());
+    }
+
+    #[test]
+    fn test_fen_errors_and_lenient_mode() {
+        let err = |fen: &str| Board::from_str(fen).unwrap_err();
+        assert_eq!(err("4k3/8/8/8/8/8/8/4K3 w -"), ParseFenError::MissingFields { needed: 6, found: 3 });
+        assert_eq!(err("4k3/8/8/8/8/8/8/4X3 w - - 0 1"), ParseFenError::BadPiece('X'));
+        assert_eq!(err("4k3/8/8/8/8/8/8/4K3 w KX - 0 1"), ParseFenError::BadCastling('X'));
+        assert_eq!(err("4k3/8/8/8/8/8/8/4K3 w - z9 0 1"), ParseFenError::BadEnPassant("z9".to_string()));
+        assert_eq!(err("4k3/8/8/8/8/8/8/4K3 w - - x 1"), ParseFenError::BadCounter("x".to_string()));
+        assert!(matches!(err("4k3/8/8/8/8/8/8/4K3 w - e6 0 1"), ParseFenError::Inconsistent(_)));
+        assert_eq!(err("8/8/8/8/8/8/8/4K3 w - - 0 1").to_string(), "Black has 0 kings");
+
+        // Lenient mode defaults the counters and drops an en passant square that can't be right,
+        // but still rejects what it can't make sense of
+        let board = Board::from_fen_lenient("4k3/8/8/8/8/8/8/4K3 w - e6 x").unwrap();
+        assert_eq!(board, Board::from_str("4k3/8/8/8/8/8/8/4K3 w - - 0 1").unwrap());
+        assert_eq!(Board::from_fen_lenient("4k3/8/8/8/8/8/8/4K3 w - z9").unwrap().en_passant(), None);
+        assert_eq!(Board::from_fen_lenient("4k3/8/8/8/8/8/8/4X3 w - -"), Err(ParseFenError::BadPiece('X')));
+        assert!(matches!(Board::from_fen_lenient("8/8/8/8/8/8/8/4K3 w - -"), Err(ParseFenError::Inconsistent(_))));
+    }
+
     #[test]
     fn test_fen_parse_complex() {
-        init();
         let fen = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
         let board = Board::from_str(fen).unwrap();
         assert_eq!(board.side_to_move(), Color::White);
@@ -594,7 +1353,6 @@ mod tests {
 
     #[test]
     fn test_piece_on() {
-        init();
         let board = Board::default();
         let e1 = Square::make_square(Rank::from_index(0), File::from_index(4));
         assert_eq!(board.piece_on(e1), Some(Piece::King));
@@ -603,7 +1361,6 @@ mod tests {
 
     #[test]
     fn test_make_move_basic() {
-        init();
         let board = Board::default();
         let e2 = Square::make_square(Rank::from_index(1), File::from_index(4));
         let e4 = Square::make_square(Rank::from_index(3), File::from_index(4));
@@ -613,12 +1370,27 @@ mod tests {
         assert_eq!(new_board.piece_on(e4), Some(Piece::Pawn));
         assert_eq!(new_board.piece_on(e2), None);
         assert_eq!(new_board.side_to_move(), Color::Black);
-        assert!(new_board.en_passant().is_some()); // e3
+        assert!(new_board.en_passant().is_none()); // e3 only if a black pawn could take
+    }
+
+    #[test]
+    fn test_ep_square_only_when_capturable() {
+        let board = Board::from_str("4k3/8/8/8/3p4/8/4P1P1/4K3 w - - 0 1").unwrap();
+        let e4 = board.make_move_new(ChessMove::new(Square::E2, Square::E4, None));
+        assert_eq!(e4.en_passant(), Some(Square::E3));
+        let g4 = board.make_move_new(ChessMove::new(Square::G2, Square::G4, None));
+        assert_eq!(g4.en_passant(), None);
+        assert_eq!(g4.to_string(), "4k3/8/8/8/3p2P1/8/4P3/4K3 b - - 0 1");
+
+        // A phantom ep square in a FEN is dropped, so transpositions hash alike
+        let with_ep = Board::from_str("4k3/8/8/8/3p2P1/8/4P3/4K3 b - g3 0 1").unwrap();
+        assert_eq!(with_ep.en_passant(), None);
+        assert_eq!(with_ep.get_hash(), g4.get_hash());
+        assert_eq!(with_ep, g4);
     }
 
     #[test]
     fn test_make_move_capture() {
-        init();
         let fen = "rnbqkbnr/ppp1pppp/8/3p4/4P3/8/PPPP1PPP/RNBQKBNR w KQkq d6 0 2";
         let board = Board::from_str(fen).unwrap();
         let e4 = Square::make_square(Rank::from_index(3), File::from_index(4));
@@ -631,27 +1403,205 @@ mod tests {
 
     #[test]
     fn test_castling_kingside() {
-        init();
         let fen = "r3k2r/pppppppp/8/8/8/8/PPPPPPPP/R3K2R w KQkq - 0 1";
         let board = Board::from_str(fen).unwrap();
         let e1 = Square::make_square(Rank::from_index(0), File::from_index(4));
         let g1 = Square::make_square(Rank::from_index(0), File::from_index(6));
-        let mv = ChessMove::new(e1, g1, None);
+        let h1 = Square::make_square(Rank::from_index(0), File::from_index(7));
+        // Castling is encoded king-takes-rook; UCI input e1g1 maps onto it
+        let mv = board.normalize_move(ChessMove::new(e1, g1, None));
+        assert_eq!(mv, ChessMove::new_castling(e1, h1));
+        assert_eq!(board.move_to_uci(mv), "e1g1");
         let new_board = board.make_move_new(mv);
         assert_eq!(new_board.piece_on(g1), Some(Piece::King));
         let f1 = Square::make_square(Rank::from_index(0), File::from_index(5));
         assert_eq!(new_board.piece_on(f1), Some(Piece::Rook));
+        assert!(new_board.piece_on(h1).is_none());
+        assert_eq!(new_board.castling_rights(), BK | BQ);
+        assert_eq!(new_board.get_hash(), new_board.compute_hash());
+    }
+
+    #[test]
+    fn test_mirror_and_flip() {
+        let board = Board::from_str("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1").unwrap();
+        let mirrored = board.mirror();
+        assert_eq!(mirrored.to_string(), "r3k2r/pppbbppp/2n2q1P/1P2p3/3pn3/BN2PNP1/P1PPQPB1/R3K2R b KQkq - 0 1");
+        assert_eq!(mirrored.get_hash(), Board::from_str(&mirrored.to_string()).unwrap().get_hash());
+        assert_eq!(mirrored.mirror(), board);
+
+        let ep = Board::from_str("rnbqkbnr/ppp1pppp/8/3pP3/8/8/PPPP1PPP/RNBQKBNR w KQkq d6 0 1").unwrap();
+        assert_eq!(ep.mirror().to_string(), "rnbqkbnr/pppp1ppp/8/8/3Pp3/8/PPP1PPPP/RNBQKBNR b KQkq d3 0 1");
+        assert_eq!(ep.flip_horizontal().to_string(), "rnbkqbnr/pppp1ppp/8/3Pp3/8/8/PPP1PPPP/RNBKQBNR w - e6 0 1");
+        assert_eq!(ep.flip_horizontal().flip_horizontal().to_string(), "rnbqkbnr/ppp1pppp/8/3pP3/8/8/PPPP1PPP/RNBQKBNR w - d6 0 1");
+    }
+
+    #[test]
+    fn test_without_piece() {
+        let board = Board::default();
+        let a1 = Square::make_square(Rank::from_index(0), File::from_index(0));
+        let no_rook = board.without_piece(a1).unwrap();
+        assert_eq!(no_rook.to_string(), "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/1NBQKBNR w Kkq - 0 1");
+        assert_eq!(no_rook.get_hash(), Board::from_str(&no_rook.to_string()).unwrap().get_hash());
+        assert_eq!(no_rook.material_phase(), board.material_phase() - Board::from_str("4k3/8/8/8/8/8/8/R3K3 w - - 0 1").unwrap().material_phase());
+        assert!(board.without_piece(board.king_square(Color::White)).is_none());
+        assert!(board.without_piece(Square::make_square(Rank::from_index(3), File::from_index(3))).is_none());
+    }
+
+    #[test]
+    fn test_diagram() {
+        let board = Board::from_str("4k3/8/8/8/8/8/8/4K2r w - - 0 1").unwrap();
+        let diagram = board.diagram(false);
+        let lines: Vec<&str> = diagram.lines().collect();
+        assert_eq!(lines[0], " +---+---+---+---+---+---+---+---+");
+        assert_eq!(lines[1], " |   |   |   |   | k |   |   |   | 8");
+        assert_eq!(lines[15], " |   |   |   |   | K |   |   | r | 1");
+        assert_eq!(lines[17], "   a   b   c   d   e   f   g   h");
+        assert!(diagram.contains("Fen: 4k3/8/8/8/8/8/8/4K2r w - - 0 1\n"));
+        assert!(diagram.contains("Side to move: White\n"));
+        assert!(diagram.ends_with("Checkers: h1"));
+
+        let unicode = Board::default().diagram(true);
+        assert!(unicode.contains(" | ♜ | ♞ | ♝ | ♛ | ♚ | ♝ | ♞ | ♜ | 8"));
+        assert!(unicode.contains("Castling: KQkq\n"));
+        assert!(unicode.ends_with("Checkers: -"));
+    }
+
+    #[test]
+    fn test_see() {
+        let see = |fen: &str, uci: &str| {
+            let board = Board::from_str(fen).unwrap();
+            board.see(board.normalize_move(ChessMove::from_uci(uci).unwrap()))
+        };
+        // Undefended pawn, then defended by a pawn: the knight is lost for it
+        assert_eq!(see("4k3/8/3p4/8/4N3/8/8/4K3 w - - 0 1", "e4d6"), 100);
+        assert_eq!(see("4k3/2p5/3p4/8/4N3/8/8/4K3 w - - 0 1", "e4d6"), -200);
+        // A defended pawn costs the rook, unless a second rook x-rays through the first
+        assert_eq!(see("3rk3/8/8/3p4/8/8/8/3RK3 w - - 0 1", "d1d5"), -400);
+        assert_eq!(see("3rk3/8/8/3p4/8/8/3R4/3RK3 w - - 0 1", "d2d5"), 100);
+        assert_eq!(see("3rk3/3r4/8/3p4/8/8/3R4/3RK3 w - - 0 1", "d2d5"), -400);
+        // The king only recaptures a piece nothing else defends
+        assert_eq!(see("8/8/8/8/8/4k3/3p4/3R3K w - - 0 1", "d1d2"), -400);
+        assert_eq!(see("8/8/8/8/8/4k3/3p4/1N1R3K w - - 0 1", "d1d2"), 100);
+        // Quiet moves: onto a square a pawn guards, or not
+        assert_eq!(see("4k3/8/2p5/8/8/8/8/1Q2K3 w - - 0 1", "b1b5"), -900);
+        assert_eq!(see("4k3/8/8/8/8/8/8/1Q2K3 w - - 0 1", "b1b5"), 0);
+        // Promotion gains the new piece; en passant and castling
+        assert_eq!(see("4k3/1P6/8/8/8/8/8/4K3 w - - 0 1", "b7b8q"), 800);
+        assert_eq!(see("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1", "e5d6"), 100);
+        assert_eq!(see("4k3/8/8/8/8/8/8/4K2R w K - 0 1", "e1g1"), 0);
+    }
+
+    #[test]
+    fn test_from_diagram() {
+        // The `d` output reads back in both styles, state lines included
+        for fen in ["r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1", "4k3/8/8/3pP3/8/8/8/4K3 w - d6 7 1"] {
+            let board = Board::from_str(fen).unwrap();
+            assert_eq!(Board::from_diagram(&board.diagram(false)), Ok(board));
+            assert_eq!(Board::from_diagram(&board.diagram(true)), Ok(board));
+        }
+
+        let grid = "
+            8  . . . . k . . .
+            7  . . . . . . . .
+            6  . . . . . . . .
+            5  . . . . . . . .
+            4  . . . . . . . .
+            3  . . . . . . . .
+            2  . . . . P . . .
+            1  R . . . K . . .
+               a b c d e f g h
+            Side to move: black
+            Castling: Q
+        ";
+        let board = Board::from_diagram(grid).unwrap();
+        assert_eq!(board.to_string(), "4k3/8/8/8/8/8/4P3/R3K3 b Q - 0 1");
+        // Without annotations: White to move, no castling
+        let bare: String = grid.lines().filter(|line| !line.contains(':')).collect::<Vec<_>>().join("\n");
+        assert_eq!(Board::from_diagram(&bare).unwrap().to_string(), "4k3/8/8/8/8/8/4P3/R3K3 w - - 0 1");
+
+        assert_eq!(Board::from_diagram(&grid.replace("1  R", "")), Err(ParseFenError::BadPlacement));
+        assert_eq!(Board::from_diagram(" | x |   |   |   | k |   |   |   | 8"), Err(ParseFenError::BadPiece('x')));
+        assert!(Board::from_diagram(&grid.replace("black", "nobody")).is_err());
+    }
+
+    #[test]
+    fn test_validate_rejects_bad_positions() {
+        for fen in [
+            "8/8/8/8/8/8/8/4K3 w - - 0 1",           // no black king
+            "4k3/8/8/8/8/8/8/3KK3 w - - 0 1",        // two white kings
+            "4k3/8/8/8/8/8/8/P3K3 w - - 0 1",        // pawn on rank 1
+            "4k3/8/8/8/8/8/8/4K3 w - e6 0 1",        // ep square with no pawn in front
+            "4k3/8/8/3pP3/8/8/8/4K3 w - d3 0 1",     // ep square on the wrong rank
+            "4k3/8/8/8/8/8/8/4R1K1 w - - 0 1",       // black in check, white to move
+        ] {
+            assert!(Board::from_str(fen).is_err(), "{}", fen);
+        }
+
+        assert!(Board::from_str("rnbqkbnr/ppp1pppp/8/3pP3/8/8/PPPP1PPP/RNBQKBNR w KQkq d6 0 1").is_ok());
+        assert!(Board::from_str("4k3/8/8/8/8/8/8/5K1R w K - 0 1").is_ok());
+        assert!(Board::default().validate().is_ok());
+        // Rights without a rook are dropped while parsing rather than rejected
+        assert_eq!(Board::from_str("4k3/8/8/8/8/8/8/4K2R w KQ - 0 1").unwrap().castling_rights(), WK);
+
+        // Castling rights whose rook has gone are caught on an already-built board
+        let mut board = Board::from_str("4k3/8/8/8/8/8/8/4K2R w K - 0 1").unwrap();
+        board.castle_rooks[0] = Square::G1;
+        assert!(board.validate().is_err());
+    }
+
+    #[test]
+    fn test_shredder_and_xfen_castling() {
+        // Shredder-FEN file letters mean the same as KQkq when the rooks are outermost
+        let shredder = Board::from_str("bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9").unwrap();
+        let xfen = Board::from_str("bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w KQkq - 2 9").unwrap();
+        assert_eq!(shredder, xfen);
+        assert_eq!(shredder.castle_rook(WQ), Square::F1);
+        assert_eq!(shredder.to_string(), "bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w KQkq - 2 9");
+
+        // X-FEN: an inner rook is named by its file, and written back the same way
+        let board = Board::from_str("4k3/8/8/8/8/8/8/RR2K2R w BK - 0 1").unwrap();
+        assert_eq!(board.castle_rook(WQ), Square::B1);
+        assert_eq!(board.to_string(), "4k3/8/8/8/8/8/8/RR2K2R w KB - 0 1");
+        assert!(board.is_chess960());
+
+        // A letter with no rook behind it drops the right
+        let board = Board::from_str("4k3/8/8/8/8/8/8/4K2R w KC - 0 1").unwrap();
+        assert_eq!(board.castling_rights(), WK);
+    }
+
+    #[test]
+    fn test_chess960_castling() {
+        // King on b1 with rooks on a1 and h1: queenside castling moves the king right
+        let board = Board::from_str("4r1kr/8/8/8/8/8/8/RK5R w KQk - 0 1").unwrap();
+        assert!(board.is_chess960());
+        assert_eq!(board.castling_rights(), WK | WQ | BK);
+        let mv = board.normalize_move(ChessMove::new(Square::B1, Square::A1, None));
+        assert_eq!(mv, ChessMove::new_castling(Square::B1, Square::A1));
+        assert!(board.legal(mv));
+        assert_eq!(board.move_to_uci(mv), "b1a1");
+        let new_board = board.make_move_new(mv);
+        assert_eq!(new_board.piece_on(Square::C1), Some(Piece::King));
+        assert_eq!(new_board.piece_on(Square::D1), Some(Piece::Rook));
+        assert_eq!(new_board.castling_rights(), BK);
+
+        // Black: king g8, rook h8 already next to it; the e8 rook doesn't carry a right
+        let board = board.make_move_new(ChessMove::new(Square::H1, Square::H2, None));
+        let mv = ChessMove::new_castling(Square::G8, Square::H8);
+        assert!(board.legal(mv));
+        let new_board = board.make_move_new(mv);
+        assert_eq!(new_board.piece_on(Square::G8), Some(Piece::King));
+        assert_eq!(new_board.piece_on(Square::F8), Some(Piece::Rook));
     }
 
     #[test]
     fn test_en_passant_capture() {
-        init();
         // White pawn on e5, Black pawn just moved d7-d5
         let fen = "rnbqkbnr/ppp1pppp/8/3pP3/8/8/PPPP1PPP/RNBQKBNR w KQkq d6 0 1";
         let board = Board::from_str(fen).unwrap();
         let e5 = Square::make_square(Rank::from_index(4), File::from_index(4));
         let d6 = Square::make_square(Rank::from_index(5), File::from_index(3));
-        let mv = ChessMove::new(e5, d6, None);
+        let mv = board.normalize_move(ChessMove::new(e5, d6, None));
+        assert_eq!(mv, ChessMove::new_en_passant(e5, d6));
         let new_board = board.make_move_new(mv);
         assert_eq!(new_board.piece_on(d6), Some(Piece::Pawn));
         assert_eq!(new_board.color_on(d6), Some(Color::White));
@@ -659,9 +1609,32 @@ mod tests {
         assert_eq!(new_board.piece_on(d5), None); // captured pawn removed
     }
 
+    #[test]
+    fn test_move_classification() {
+        let board = Board::from_str("r3k2r/8/8/3pP3/8/8/8/R3K2Q w Qkq d6 0 1").unwrap();
+        let ep = ChessMove::new(Square::E5, Square::D6, None);
+        assert!(board.is_en_passant(ep));
+        assert!(board.is_capture(ep));
+        assert_eq!(board.captured_piece(ep), Some(Piece::Pawn));
+
+        // A queen landing on the ep square is not en passant
+        let queen_to_d5 = ChessMove::new(Square::H1, Square::D5, None);
+        assert!(!board.is_en_passant(queen_to_d5));
+        assert_eq!(board.captured_piece(queen_to_d5), Some(Piece::Pawn));
+        let queen_takes_rook = ChessMove::new(Square::H1, Square::H8, None);
+        assert_eq!(board.captured_piece(queen_takes_rook), Some(Piece::Rook));
+
+        let castle = ChessMove::new(Square::E1, Square::A1, None);
+        assert!(board.is_castling(castle));
+        assert!(!board.is_capture(castle));
+        assert_eq!(board.captured_piece(castle), None);
+
+        let quiet = ChessMove::new(Square::E5, Square::E6, None);
+        assert!(!board.is_capture(quiet) && !board.is_en_passant(quiet) && !board.is_castling(quiet));
+    }
+
     #[test]
     fn test_promotion() {
-        init();
         let fen = "8/P7/8/8/8/8/8/K6k w - - 0 1";
         let board = Board::from_str(fen).unwrap();
         let a7 = Square::make_square(Rank::from_index(6), File::from_index(0));
@@ -673,7 +1646,6 @@ mod tests {
 
     #[test]
     fn test_null_move() {
-        init();
         let board = Board::default();
         let null = board.null_move();
         assert!(null.is_some());
@@ -683,7 +1655,6 @@ mod tests {
 
     #[test]
     fn test_null_move_in_check() {
-        init();
         // White king in check
         let fen = "rnbqkbnr/pppp1ppp/4p3/8/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3";
         let board = Board::from_str(fen).unwrap();
@@ -692,7 +1663,6 @@ mod tests {
 
     #[test]
     fn test_hash_changes_on_move() {
-        init();
         let board = Board::default();
         let e2 = Square::make_square(Rank::from_index(1), File::from_index(4));
         let e4 = Square::make_square(Rank::from_index(3), File::from_index(4));
@@ -702,7 +1672,6 @@ mod tests {
 
     #[test]
     fn test_hash_consistency() {
-        init();
         // Hash computed incrementally should match hash computed from scratch
         let board = Board::default();
         let e2 = Square::make_square(Rank::from_index(1), File::from_index(4));
@@ -711,14 +1680,70 @@ mod tests {
         let expected_hash = new_board.compute_hash();
         assert_eq!(new_board.get_hash(), expected_hash, "Incremental hash should match recomputed hash");
     }
+
+    #[test]
+    fn test_psq_accumulators_consistent() {
+        // Walk every legal move (captures, castling, promotions, EP) two plies deep and
+        // compare the incremental accumulators against a from-scratch recomputation
+        for fen in [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "rnbqkbnr/ppp1pppp/8/3pP3/8/8/PPPP1PPP/RNBQKBNR w KQkq d6 0 1",
+            "n1n5/PPPk4/8/8/8/8/4Kppp/5N1N b - - 0 1",
+        ] {
+            let board = Board::from_str(fen).unwrap();
+            for mv in MoveGen::new_legal(&board) {
+                let child = board.make_move_new(mv);
+                assert_eq!((child.psq_mg, child.psq_eg, child.phase), child.compute_psq(), "after {}", mv);
+                for reply in MoveGen::new_legal(&child) {
+                    let grandchild = child.make_move_new(reply);
+                    assert_eq!(
+                        (grandchild.psq_mg, grandchild.psq_eg, grandchild.phase),
+                        grandchild.compute_psq(),
+                        "after {} {}", mv, reply
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_attackers_to() {
+        // e4 attacked by white pawn d3, white knight f2, black pawn d5 and black rook e8 (down
+        // the open e-file); the b7 bishop is blocked by its own pawn on d5
+        let board = Board::from_str("4r1k1/1b6/8/3p4/8/3P4/5N2/4K3 w - - 0 1").unwrap();
+        let e4 = Square::E4;
+        let attackers = board.attackers_to(e4, board.combined());
+        let expected = [Square::D3, Square::F2, Square::D5, Square::E8].iter().fold(EMPTY, |bb, &sq| bb | BitBoard::from_square(sq));
+        assert_eq!(attackers, expected);
+
+        // Removing the d5 pawn from occupancy reveals the b7 bishop x-ray
+        let xray = board.attackers_to(e4, board.combined() ^ BitBoard::from_square(Square::D5));
+        assert!(!(xray & BitBoard::from_square(Square::B7)).is_empty());
+    }
 }
 // Board is a `Copy` type. Instead of a 64-element array of pieces, use bitboards: 6 `BitBoard`s
 // for piece types and 2 for colors. To find on what's on a square, check which bitboards have that
 // bit set. Slightly slower for single-square queries, but much faster for pattern matching.
 
-// CASTLING_MASK -> instead of checking "did king or rook move" w/ compex conditionals, can use
-// 64-element lookup table. When ANY piece moves from or to square X, AND the castling rights with
-// `CASTLING_MASK[X]`. Most entries are 0xFF (no change), but king and rook starting squares have
-// specific bits cleared. This also handles case where rook is captured (destination square clears
-// the opponent's castling rights).
-
+// Castling (Chess960-ready) -> rights are tied to the rook's actual start square in
+// `castle_rooks`, not to fixed corners. A king move clears both of its rights; any move from or to
+// a castling rook's square clears that right (which also covers the rook being captured). Castling
+// moves are encoded internally as king-takes-own-rook, since in Chess960 the king's destination can
+// be its own start square or the rook's. `move_to_uci` / `normalize_move` translate to and from
+// the classical e1g1 notation at the UCI boundary.
+
+// `make_move_new` is the search's hot path and assumes a move from movegen, so it checks nothing.
+// `try_make_move` is the boundary version: an empty or enemy source square is reported separately
+// from an illegal move, since the first usually means the caller has the wrong position.
+
+// FEN parsing is strict by default, since a FEN from our own output or a test should be complete.
+// Lenient mode is for outside input (EPD suites, GUIs that send four fields): it only fills in
+// what can be defaulted safely. Unparseable placement, castling and kings are errors either way.
+//
+// `from_diagram` builds a FEN from the grid and hands it to the strict parser, so a diagram gets
+// the same validation as any other position. Annotation lines default to the least-assuming
+// state (White, no castling, no en passant) rather than guessing rights from where the rooks are.
+//
+// `see` returns the exchange's value rather than answering "is it at least X": callers compare
+// against depth-dependent thresholds, and tests and tools can show the number. It uses the MVV
+// victim values so ordering and pruning agree on what a piece is worth.
@@ -1,19 +1,31 @@
-use std::fmt;
+use alloc::string::{String, ToString};
+use core::fmt;
+use core::str::FromStr;
+
+use thiserror::Error;
 
 use super::piece::Piece;
-use super::square::Square;
+use super::square::{File, Rank, Square};
 
 /// Chess move encoded compactly in a u16.
 ///
-/// Layout: `src(6) | dst(6) | promo(2) | is_promo(1) | reserved(1)`
+/// Layout: `src(6) | dst(6) | promo(2) | kind(2)`
 ///   - bits 0..5:  source square (0-63)
 ///   - bits 6..11: destination square (0-63)
 ///   - bits 12..13: promotion piece (0=Knight, 1=Bishop, 2=Rook, 3=Queen)
-///   - bit 14: is_promotion flag
-///   - bit 15: reserved
+///   - bits 14..15: move kind (0=normal, 1=promotion, 2=en passant, 3=castling)
+///
+/// Castling is encoded as king-takes-own-rook. Move generation sets the en passant and castling
+/// kinds; moves built from squares alone (UCI input, `new`) are normal until
+/// `Board::normalize_move` classifies them.
 #[derive(Clone, Copy, PartialEq, Eq, Hash)]
 pub struct ChessMove(u16);
 
+const KIND_SHIFT: u16 = 14;
+const PROMOTION: u16 = 1 << KIND_SHIFT;
+const EN_PASSANT: u16 = 2 << KIND_SHIFT;
+const CASTLING: u16 = 3 << KIND_SHIFT;
+
 impl ChessMove {
     #[inline]
     pub fn new(src: Square, dst: Square, promotion: Option<Piece>) -> Self {
@@ -27,11 +39,35 @@ impl ChessMove {
                 _ => 3, // default to queen for invalid promo pieces
             };
             bits |= code << 12;
-            bits |= 1 << 14; // is_promotion flag
+            bits |= PROMOTION;
         }
         ChessMove(bits)
     }
 
+    /// Pawn capture onto the en passant square
+    #[inline]
+    pub fn new_en_passant(src: Square, dst: Square) -> Self {
+        ChessMove(Self::new(src, dst, None).0 | EN_PASSANT)
+    }
+
+    /// Castling, given as the king's square and its own rook's square
+    #[inline]
+    pub fn new_castling(king: Square, rook: Square) -> Self {
+        ChessMove(Self::new(king, rook, None).0 | CASTLING)
+    }
+
+    /// rebuild a move from its raw 16-bit encoding (e.g. from a TT entry)
+    #[inline]
+    pub fn from_raw(bits: u16) -> Self {
+        ChessMove(bits)
+    }
+
+    /// raw 16-bit encoding
+    #[inline]
+    pub fn to_raw(self) -> u16 {
+        self.0
+    }
+
     #[inline]
     pub fn get_source(self) -> Square {
         Square::new((self.0 & 0x3F) as u8)
@@ -44,7 +80,7 @@ impl ChessMove {
 
     #[inline]
     pub fn get_promotion(self) -> Option<Piece> {
-        if self.0 & (1 << 14) == 0 {
+        if self.0 & CASTLING != PROMOTION {
             None
         } else {
             Some(match (self.0 >> 12) & 3 {
@@ -55,6 +91,63 @@ impl ChessMove {
             })
         }
     }
+
+    #[inline]
+    pub fn is_en_passant(self) -> bool {
+        self.0 & CASTLING == EN_PASSANT
+    }
+
+    #[inline]
+    pub fn is_castling(self) -> bool {
+        self.0 & CASTLING == CASTLING
+    }
+}
+
+/// Why a string isn't a UCI move
+#[derive(Clone, PartialEq, Eq, Debug, Error)]
+pub enum ParseMoveError {
+    #[error("Invalid UCI move {0}: expected 4 or 5 characters")]
+    Length(String),
+    #[error("Invalid square in UCI move {0}")]
+    BadSquare(String),
+    #[error("Invalid promotion piece {0}")]
+    BadPromotion(char),
+}
+
+impl ChessMove {
+    /// Parse UCI long algebraic notation ("e2e4", "a7a8q") without a board. The move is not
+    /// checked for legality, and castling stays in whatever form was written. `str::parse`
+    /// does the same and says what was wrong.
+    pub fn from_uci(s: &str) -> Option<ChessMove> {
+        s.parse().ok()
+    }
+}
+
+impl FromStr for ChessMove {
+    type Err = ParseMoveError;
+
+    fn from_str(s: &str) -> Result<Self, ParseMoveError> {
+        let bytes = s.as_bytes();
+        if bytes.len() != 4 && bytes.len() != 5 {
+            return Err(ParseMoveError::Length(s.to_string()));
+        }
+        let square = |file: u8, rank: u8| {
+            let (f, r) = (file.wrapping_sub(b'a'), rank.wrapping_sub(b'1'));
+            (f < 8 && r < 8).then(|| Square::make_square(Rank::from_index(r as usize), File::from_index(f as usize)))
+        };
+        let (Some(src), Some(dst)) = (square(bytes[0], bytes[1]), square(bytes[2], bytes[3])) else {
+            return Err(ParseMoveError::BadSquare(s.to_string()));
+        };
+        let promo = match bytes.get(4) {
+            None => None,
+            Some(b'q') => Some(Piece::Queen),
+            Some(b'r') => Some(Piece::Rook),
+            Some(b'b') => Some(Piece::Bishop),
+            Some(b'n') => Some(Piece::Knight),
+            Some(&c) => return Err(ParseMoveError::BadPromotion(c as char)),
+        };
+        Ok(ChessMove::new(src, dst, promo))
+    }
 }
 
 impl fmt::Display for ChessMove {
@@ -76,14 +169,20 @@ impl fmt::Display for ChessMove {
 
 impl fmt::Debug for ChessMove {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "ChessMove({})", self)
+        let kind = if self.is_castling() {
+            ", castling"
+        } else if self.is_en_passant() {
+            ", en passant"
+        } else {
+            ""
+        };
+        write!(f, "ChessMove({}{})", self, kind)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::board::square::{File, Rank};
 
     #[test]
     fn test_basic_move() {
@@ -107,8 +206,8 @@ mod tests {
 
     #[test]
     fn test_all_promotions() {
-        let src = Square::new(48); // a7
-        let dst = Square::new(56); // a8
+        let src = Square::A7;
+        let dst = Square::A8;
         for piece in [Piece::Knight, Piece::Bishop, Piece::Rook, Piece::Queen] {
             let mv = ChessMove::new(src, dst, Some(piece));
             assert_eq!(mv.get_promotion(), Some(piece));
@@ -127,11 +226,42 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_special_move_kinds() {
+        let ep = ChessMove::new_en_passant(Square::E5, Square::D6);
+        assert!(ep.is_en_passant() && !ep.is_castling());
+        assert_eq!(ep.get_promotion(), None);
+        assert_eq!((ep.get_source(), ep.get_dest()), (Square::E5, Square::D6));
+        assert_ne!(ep, ChessMove::new(Square::E5, Square::D6, None));
+
+        let castle = ChessMove::new_castling(Square::E1, Square::H1);
+        assert!(castle.is_castling() && !castle.is_en_passant());
+        assert_eq!(castle.get_promotion(), None);
+        assert_eq!(castle.to_string(), "e1h1");
+        assert_eq!(ChessMove::from_raw(castle.to_raw()), castle);
+
+        let promo = ChessMove::new(Square::A7, Square::A8, Some(Piece::Knight));
+        assert!(!promo.is_castling() && !promo.is_en_passant());
+    }
+
+    #[test]
+    fn test_from_uci() {
+        assert_eq!(ChessMove::from_uci("e2e4"), Some(ChessMove::new(Square::E2, Square::E4, None)));
+        assert_eq!("a7a8q".parse::<ChessMove>().unwrap().get_promotion(), Some(Piece::Queen));
+        assert_eq!("a7a8n".parse::<ChessMove>().unwrap().to_string(), "a7a8n");
+        for bad in ["", "e2", "e2e", "e2e9", "i2e4", "a7a8k", "e2e4qq", "0000"] {
+            assert!(bad.parse::<ChessMove>().is_err(), "{}", bad);
+        }
+        assert_eq!("e2e4qq".parse::<ChessMove>(), Err(ParseMoveError::Length("e2e4qq".to_string())));
+        assert_eq!("e2e9".parse::<ChessMove>(), Err(ParseMoveError::BadSquare("e2e9".to_string())));
+        assert_eq!("a7a8k".parse::<ChessMove>(), Err(ParseMoveError::BadPromotion('k')));
+    }
+
     #[test]
     fn test_equality() {
-        let a = ChessMove::new(Square::new(12), Square::new(28), None);
-        let b = ChessMove::new(Square::new(12), Square::new(28), None);
-        let c = ChessMove::new(Square::new(12), Square::new(20), None);
+        let a = ChessMove::new(Square::E2, Square::E4, None);
+        let b = ChessMove::new(Square::E2, Square::E4, None);
+        let c = ChessMove::new(Square::E2, Square::E3, None);
         assert_eq!(a, b);
         assert_ne!(a, c);
     }
@@ -32,9 +32,51 @@ pub static ROOK_BITS: [u8; 64] = [
     12, 11, 11, 11, 11, 11, 11, 12,
 ];
 
+/// bishop magic multipliers, indexed by square (found offline with `find_magic`)
+pub static BISHOP_MAGICS: [u64; 64] = [
+    0x004002042C102041, 0x0002104200810109, 0x0141412201824004, 0x04082080208C0000,
+    0x2004042040181000, 0x4003090840800138, 0x0027008210410511, 0x0602440404010461,
+    0x0060040890240680, 0x0200115011084082, 0x0800C10401005104, 0x0042044502000010,
+    0x0100040420402008, 0xC140808230414100, 0x2040007402201000, 0x8006008200900402,
+    0x504000A004440088, 0x2820900801010200, 0x0004000804240110, 0x222800040218A211,
+    0x0811020820080009, 0x4802000101008220, 0x0209000A88095040, 0x8082008316411400,
+    0x0220202308020410, 0x801030110A160612, 0x2800300082048601, 0x0010802008020120,
+    0x0044840150802002, 0x0000802206021000, 0x8002205000841000, 0x8020550002411800,
+    0x040120088010100A, 0x0021100281300480, 0x1180208808100420, 0x0044400821020200,
+    0x200A0084000A0220, 0x0001100080610042, 0x1004409200040100, 0x0102040023404200,
+    0x88010188200040A0, 0x0100480804008800, 0x01200A0090080200, 0x000E004010480200,
+    0x184222200A000100, 0x0208095801210200, 0x2210010101004420, 0x0022044910211A02,
+    0x0104050111110000, 0x002200A208020080, 0x0004044200900000, 0x2061140042020320,
+    0x0000948821070012, 0x0000080308420020, 0x4008101102240405, 0x0010220851002000,
+    0x3100840452022040, 0x0000108044022004, 0x0412080042080404, 0x0023088010840401,
+    0x0009108010020880, 0x0320000810018202, 0x0708600304480088, 0x2490045000802100,
+];
+
+/// rook magic multipliers, indexed by square (found offline with `find_magic`)
+pub static ROOK_MAGICS: [u64; 64] = [
+    0x0100102100408000, 0x0840004010002000, 0x0200200A00401080, 0x8B00100100040820,
+    0x4A00141E00982010, 0x0B00020900080C00, 0x2580010002000080, 0x018008D100022080,
+    0x0120800028401080, 0x8000C00050002002, 0x1000802000801000, 0x8001000810010021,
+    0x0000808008000400, 0x0001800201800400, 0x0004808011000200, 0x2812000100804402,
+    0x8480004000402002, 0x0030134020004000, 0x8400808020001004, 0x2411010008201005,
+    0x9404008080040800, 0x0112808004000200, 0x2001040008702241, 0x0252020000804104,
+    0x6080004140022000, 0x0020200080804000, 0x0200802200104A00, 0x4100080080801000,
+    0x0000080080040080, 0x8000040080020080, 0x020100210002005C, 0x00088C020014A441,
+    0x0204400284800020, 0x0010201000404000, 0x0048200041001504, 0x00B2841000800800,
+    0x0060110005004800, 0x0000104008010420, 0x1481000405000A00, 0x0040004882001504,
+    0x1000209040008008, 0x0000402010014000, 0x0002001040820028, 0x4A01001000090021,
+    0x0211001008010004, 0x0119000400090052, 0x0A00040200010100, 0x0015012080420004,
+    0x0880004000200040, 0x1400810040002100, 0x0501004810200100, 0x4908800800100080,
+    0x1020208100401002, 0x0001000208040100, 0x1101800100020080, 0x0120800041002080,
+    0x00160100C060D086, 0x08C1002204428016, 0x0004C02012020882, 0x004A700020C80D01,
+    0x0441001004020801, 0x401D000400020801, 0x8C5A011002080084, 0x102004050C204482,
+];
+
 /// simple xorshift64 PRNG for magic number generation
+#[cfg(test)]
 pub struct Rng(pub u64);
 
+#[cfg(test)]
 impl Rng {
     pub fn next(&mut self) -> u64 {
         self.0 ^= self.0 << 13;
@@ -51,6 +93,7 @@ impl Rng {
 
 /// find a magic number for given square, mask, bits, and slow attack generator
 /// returns magic number that produces a collision-free hash
+#[cfg(test)]
 pub fn find_magic(
     mask: u64,
     bits: u8,
@@ -122,7 +165,9 @@ pub fn find_magic(
 // (blockers). There are ~12 relevant bits in occupancy mask, giving 2^12 = 4096 possible occupancy
 // patterns. Magic number constant, when multiplied by occupancy and right-shifted, maps each
 // pattern to unique index in compact table. These magic numbers found w/ trial error w/ PRNG.
-// xorshift64 PRNG to generate candidate magic numbers and then test for collisions
+// xorshift64 PRNG to generate candidate magic numbers and then test for collisions. The search now
+// runs offline: BISHOP_MAGICS / ROOK_MAGICS hold its output for the fixed seed, and a test reruns it
+// to check the tables still match, so startup only fills the attack tables
 
 // Carry-Rippler trick:`occ = (occ - mask) & mask` enumerates all subsets of `mask`. This is a 
 // well-known bit manipulation technique — it "carries" through the bits of the mask, generating 
@@ -1,3 +1,5 @@
+#[cfg(feature = "arbitrary")]
+mod arbitrary_impls;
 mod attacks;
 mod bitboard;
 #[allow(clippy::module_inception)]
@@ -6,21 +8,24 @@ mod chessmove;
 mod magic;
 mod movegen;
 mod piece;
+mod polyglot;
+#[cfg(feature = "serde")]
+mod serde_impls;
 mod square;
 mod zobrist;
 
-#[allow(unused_imports)]
+#[cfg(feature = "arbitrary")]
+pub use arbitrary_impls::FenString;
+pub use attacks::{bishop_attacks, king_attacks, knight_attacks, pawn_attacks, pext_enabled, queen_attacks, rook_attacks};
 pub use bitboard::{BitBoard, EMPTY};
-#[allow(unused_imports)]
-pub use board::{Board, BoardStatus};
-pub use chessmove::ChessMove;
-#[allow(unused_imports)]
+pub use board::{Board, BoardStatus, MoveError, ParseFenError};
+pub use chessmove::{ChessMove, ParseMoveError};
 pub use movegen::MoveGen;
 pub use piece::{Color, Piece};
-#[allow(unused_imports)]
 pub use square::{File, Rank, Square, ALL_SQUARES};
 
-/// Init attack tables and Zobrist keys. Must be called before any board operations.
+/// Build the slider attack tables up front. Optional: they are built lazily on first use, this
+/// just moves that cost out of the first search. (The Zobrist keys are a compile-time table.)
 pub fn init() {
     attacks::init_attacks();
     zobrist::init_zobrist();
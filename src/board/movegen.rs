@@ -1,43 +1,70 @@
+use arrayvec::ArrayVec;
+
 use super::attacks::{knight_attacks, king_attacks, pawn_attacks, bishop_attacks, rook_attacks};
 use super::bitboard::{BitBoard, EMPTY};
 use super::board::Board;
 use super::chessmove::ChessMove;
 use super::piece::{Color, Piece};
-use super::square::{Square, Rank, File};
+use super::square::{Square, File};
+
+/// More moves than any position has (the most known is 218), pseudo-legal ones included
+const MAX_MOVES: usize = 256;
+
+type MoveList = ArrayVec<ChessMove, MAX_MOVES>;
 
-/// Legal move generator with consuming multi-pass iteration
+/// Legal move generator with consuming multi-pass iteration. Moves live in a fixed-size
+/// array, so generating them never allocates.
 pub struct MoveGen {
-    moves: Vec<ChessMove>,
-    consumed: Vec<bool>,
+    moves: MoveList,
+    /// Bit `i` set once `moves[i]` has been yielded
+    consumed: [u64; MAX_MOVES / 64],
     index: usize,
     mask: BitBoard,
+    source_mask: BitBoard,
+    /// Side to move's pieces by type, for `set_piece_mask`
+    our_pieces: [BitBoard; 6],
+}
+
+/// Which subset of moves to generate
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum GenType {
+    All,
+    /// Captures (including en passant) and all promotions
+    Captures,
+    /// Everything else: non-promoting pushes, non-captures, castling
+    Quiets,
 }
 
 impl MoveGen {
     /// generate all legal moves for position
     pub fn new_legal(board: &Board) -> Self {
-        let pseudo = generate_pseudo_legal(board);
-
-        // filter for legality: make each move and check if our king is safe
-        let mut legal_moves = Vec::with_capacity(pseudo.len());
-        for mv in &pseudo {
-            let new_board = board.make_move_new(*mv);
-            // After make_move_new, side_to_move has flipped. Check if PREVIOUS
-            // side's king is attacked — but compute_checkers already checks current
-            //side's king. Since we flipped, we need to verify opponent (i.e., 
-            //side that just moved) is not in check. Do this by checking if side 
-            //that just moved left their king in check.
-            if !is_king_attacked(&new_board, board.side_to_move()) {
-                legal_moves.push(*mv);
-            }
-        }
+        Self::from_pseudo(board, generate_pseudo_legal(board, GenType::All))
+    }
+
+    /// generate only legal captures (including en passant) and promotions
+    pub fn new_captures(board: &Board) -> Self {
+        Self::from_pseudo(board, generate_pseudo_legal(board, GenType::Captures))
+    }
 
-        let len = legal_moves.len();
+    /// generate only legal quiet moves (no captures, no promotions; castling included).
+    /// Together with `new_captures` this covers exactly the moves of `new_legal`.
+    pub fn new_quiets(board: &Board) -> Self {
+        Self::from_pseudo(board, generate_pseudo_legal(board, GenType::Quiets))
+    }
+
+    fn from_pseudo(board: &Board, mut moves: MoveList) -> Self {
+        // filter for legality: make each move and check that the side that just moved
+        // didn't leave its own king in check
+        moves.retain(|mv| !is_king_attacked(&board.make_move_new(*mv), board.side_to_move()));
+
+        let us = board.color_combined(board.side_to_move());
         MoveGen {
-            moves: legal_moves,
-            consumed: vec![false; len],
+            moves,
+            consumed: [0; MAX_MOVES / 64],
             index: 0,
             mask: !EMPTY, // all squares by default
+            source_mask: !EMPTY,
+            our_pieces: Piece::ALL.map(|p| board.pieces(p) & us),
         }
     }
 
@@ -48,6 +75,25 @@ impl MoveGen {
         self.mask = mask;
         self.index = 0;
     }
+
+    /// Like `set_iterator_mask`, but on the source square. Both masks apply together.
+    pub fn set_source_mask(&mut self, mask: BitBoard) {
+        self.source_mask = mask;
+        self.index = 0;
+    }
+
+    /// Only yield moves of one piece type (a source mask of that piece's squares)
+    pub fn set_piece_mask(&mut self, piece: Piece) {
+        self.set_source_mask(self.our_pieces[piece.to_index()]);
+    }
+
+    /// Whether the move at `i` is still to be yielded under the current masks
+    fn pending(&self, i: usize) -> bool {
+        let mv = self.moves[i];
+        self.consumed[i / 64] & (1 << (i % 64)) == 0
+            && !(BitBoard::from_square(mv.get_dest()) & self.mask).is_empty()
+            && !(BitBoard::from_square(mv.get_source()) & self.source_mask).is_empty()
+    }
 }
 
 impl Iterator for MoveGen {
@@ -58,78 +104,52 @@ impl Iterator for MoveGen {
             let i = self.index;
             self.index += 1;
 
-            if self.consumed[i] {
+            if !self.pending(i) {
                 continue;
             }
 
-            let mv = self.moves[i];
-            let dest_bb = BitBoard::from_square(mv.get_dest());
-            if (dest_bb & self.mask).is_empty() {
-                continue;
-            }
-
-            self.consumed[i] = true;
-            return Some(mv);
+            self.consumed[i / 64] |= 1 << (i % 64);
+            return Some(self.moves[i]);
         }
         None
     }
-}
 
-/// Check if given color's king is attacked in position.
-fn is_king_attacked(board: &Board, color: Color) -> bool {
-    let king_bb = board.pieces(Piece::King) & board.color_combined(color);
-    if king_bb.is_empty() {
-        return false;
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = (self.index..self.moves.len()).filter(|&i| self.pending(i)).count();
+        (len, Some(len))
     }
-    let king_sq = Square::new(king_bb.0.trailing_zeros() as u8);
-    let occupied = board.combined();
-    let enemy = board.color_combined(!color);
+}
 
-    // Check all piece types
-    if !(knight_attacks(king_sq) & board.pieces(Piece::Knight) & enemy).is_empty() {
-        return true;
-    }
-    if !(pawn_attacks(color, king_sq) & board.pieces(Piece::Pawn) & enemy).is_empty() {
-        return true;
-    }
-    if !(bishop_attacks(king_sq, occupied)
-        & (board.pieces(Piece::Bishop) | board.pieces(Piece::Queen))
-        & enemy)
-        .is_empty()
-    {
-        return true;
-    }
-    if !(rook_attacks(king_sq, occupied)
-        & (board.pieces(Piece::Rook) | board.pieces(Piece::Queen))
-        & enemy)
-        .is_empty()
-    {
-        return true;
-    }
-    if !(king_attacks(king_sq) & board.pieces(Piece::King) & enemy).is_empty() {
-        return true;
-    }
+/// `len()` counts the moves still to come under the current masks
+impl ExactSizeIterator for MoveGen {}
 
-    false
+/// Check if given color's king is attacked in position.
+fn is_king_attacked(board: &Board, color: Color) -> bool {
+    is_square_attacked(board, board.king_square(color), color)
 }
 
-/// Generate all pseudo-legal moves (piece rules only, ignoring pins/check).
-fn generate_pseudo_legal(board: &Board) -> Vec<ChessMove> {
-    let mut moves = Vec::with_capacity(64);
+/// Generate pseudo-legal moves of the given kind (piece rules only, ignoring pins/check).
+fn generate_pseudo_legal(board: &Board, kind: GenType) -> MoveList {
+    let mut moves = MoveList::new();
     let us = board.side_to_move();
     let them = !us;
     let our_pieces = board.color_combined(us);
     let their_pieces = board.color_combined(them);
     let occupied = board.combined();
     let empty = !occupied;
+    let targets = match kind {
+        GenType::All => !our_pieces,
+        GenType::Captures => their_pieces,
+        GenType::Quiets => empty,
+    };
 
     // Pawn moves
-    generate_pawn_moves(board, us, our_pieces, their_pieces, occupied, empty, &mut moves);
+    generate_pawn_moves(board, us, their_pieces, empty, kind, &mut moves);
 
     // Knight moves
     let knights = board.pieces(Piece::Knight) & our_pieces;
     for sq in knights.iter() {
-        let attacks = knight_attacks(sq) & !our_pieces;
+        let attacks = knight_attacks(sq) & targets;
         for dst in attacks.iter() {
             moves.push(ChessMove::new(sq, dst, None));
         }
@@ -138,7 +158,7 @@ fn generate_pseudo_legal(board: &Board) -> Vec<ChessMove> {
     // Bishop moves
     let bishops = board.pieces(Piece::Bishop) & our_pieces;
     for sq in bishops.iter() {
-        let attacks = bishop_attacks(sq, occupied) & !our_pieces;
+        let attacks = bishop_attacks(sq, occupied) & targets;
         for dst in attacks.iter() {
             moves.push(ChessMove::new(sq, dst, None));
         }
@@ -147,7 +167,7 @@ fn generate_pseudo_legal(board: &Board) -> Vec<ChessMove> {
     // Rook moves
     let rooks = board.pieces(Piece::Rook) & our_pieces;
     for sq in rooks.iter() {
-        let attacks = rook_attacks(sq, occupied) & !our_pieces;
+        let attacks = rook_attacks(sq, occupied) & targets;
         for dst in attacks.iter() {
             moves.push(ChessMove::new(sq, dst, None));
         }
@@ -156,22 +176,20 @@ fn generate_pseudo_legal(board: &Board) -> Vec<ChessMove> {
     // Queen moves
     let queens = board.pieces(Piece::Queen) & our_pieces;
     for sq in queens.iter() {
-        let attacks = (bishop_attacks(sq, occupied) | rook_attacks(sq, occupied)) & !our_pieces;
+        let attacks = (bishop_attacks(sq, occupied) | rook_attacks(sq, occupied)) & targets;
         for dst in attacks.iter() {
             moves.push(ChessMove::new(sq, dst, None));
         }
     }
 
     // King moves (non-castling)
-    let king_bb = board.pieces(Piece::King) & our_pieces;
-    if !king_bb.is_empty() {
-        let king_sq = Square::new(king_bb.0.trailing_zeros() as u8);
-        let attacks = king_attacks(king_sq) & !our_pieces;
-        for dst in attacks.iter() {
-            moves.push(ChessMove::new(king_sq, dst, None));
-        }
+    let king_sq = board.king_square(us);
+    for dst in (king_attacks(king_sq) & targets).iter() {
+        moves.push(ChessMove::new(king_sq, dst, None));
+    }
 
-        // Castling
+    // Castling
+    if kind != GenType::Captures {
         generate_castling(board, king_sq, us, occupied, &mut moves);
     }
 
@@ -181,49 +199,47 @@ fn generate_pseudo_legal(board: &Board) -> Vec<ChessMove> {
 fn generate_pawn_moves(
     board: &Board,
     us: Color,
-    our_pieces: BitBoard,
     their_pieces: BitBoard,
-    _occupied: BitBoard,
     empty: BitBoard,
-    moves: &mut Vec<ChessMove>,
+    kind: GenType,
+    moves: &mut MoveList,
 ) {
-    let pawns = board.pieces(Piece::Pawn) & our_pieces;
+    let pawns = board.pieces(Piece::Pawn) & board.color_combined(us);
+    let quiets = kind != GenType::Captures;
+    let captures = kind != GenType::Quiets;
     let promo_rank = if us == Color::White { 7usize } else { 0usize };
 
-    let (push_dir, start_rank, _double_rank): (i8, usize, usize) = if us == Color::White {
-        (8, 1, 3)
-    } else {
-        (-8, 6, 4)
-    };
+    let start_rank = if us == Color::White { 1 } else { 6 };
 
     for sq in pawns.iter() {
-        let sq_idx = sq.to_index() as i8;
-
         // Single push
-        let push_sq_idx = sq_idx + push_dir;
-        if (0..64).contains(&push_sq_idx) {
-            let push_sq = Square::new(push_sq_idx as u8);
-            if !(BitBoard::from_square(push_sq) & empty).is_empty() {
-                if push_sq.rank().to_index() == promo_rank {
-                    // Promotion: generate all 4 promo moves
+        if let Some(push_sq) = sq.forward(us)
+            && !(BitBoard::from_square(push_sq) & empty).is_empty()
+        {
+            if push_sq.rank().to_index() == promo_rank {
+                // Promotion: generate all 4 promo moves
+                if captures {
                     for p in [Piece::Queen, Piece::Rook, Piece::Bishop, Piece::Knight] {
                         moves.push(ChessMove::new(sq, push_sq, Some(p)));
                     }
-                } else {
-                    moves.push(ChessMove::new(sq, push_sq, None));
-
-                    // Double push (only if single push was possible)
-                    if sq.rank().to_index() == start_rank {
-                        let double_idx = sq_idx + push_dir * 2;
-                        let double_sq = Square::new(double_idx as u8);
-                        if !(BitBoard::from_square(double_sq) & empty).is_empty() {
-                            moves.push(ChessMove::new(sq, double_sq, None));
-                        }
+                }
+            } else if quiets {
+                moves.push(ChessMove::new(sq, push_sq, None));
+
+                // Double push (only if single push was possible)
+                if sq.rank().to_index() == start_rank {
+                    let double_sq = push_sq.forward(us).expect("start rank is not the last rank");
+                    if !(BitBoard::from_square(double_sq) & empty).is_empty() {
+                        moves.push(ChessMove::new(sq, double_sq, None));
                     }
                 }
             }
         }
 
+        if !captures {
+            continue;
+        }
+
         // Captures (using pawn attack table)
         let attacks = pawn_attacks(us, sq) & their_pieces;
         for dst in attacks.iter() {
@@ -240,7 +256,7 @@ fn generate_pawn_moves(
         if let Some(ep_sq) = board.en_passant() {
             let ep_attacks = pawn_attacks(us, sq) & BitBoard::from_square(ep_sq);
             if !ep_attacks.is_empty() {
-                moves.push(ChessMove::new(sq, ep_sq, None));
+                moves.push(ChessMove::new_en_passant(sq, ep_sq));
             }
         }
     }
@@ -251,82 +267,52 @@ fn generate_castling(
     king_sq: Square,
     us: Color,
     occupied: BitBoard,
-    moves: &mut Vec<ChessMove>,
+    moves: &mut MoveList,
 ) {
     use super::board::{WK, WQ, BK, BQ};
     let rights = board.castling_rights();
+    if rights == 0 || is_king_attacked(board, us) {
+        return;
+    }
 
-    let (ks_right, qs_right, rank) = if us == Color::White {
-        (WK, WQ, 0usize)
-    } else {
-        (BK, BQ, 7usize)
-    };
+    let (ks_right, qs_right) = if us == Color::White { (WK, WQ) } else { (BK, BQ) };
+    let rank = king_sq.rank();
 
-    // Kingside
-    if rights & ks_right != 0 {
-        let f_sq = Square::make_square(Rank::from_index(rank), File::from_index(5));
-        let g_sq = Square::make_square(Rank::from_index(rank), File::from_index(6));
-
-        // Squares between king and rook must be empty
-        if (BitBoard::from_square(f_sq) | BitBoard::from_square(g_sq)) & occupied == EMPTY {
-            // King must not pass through or land on attacked square, and not be in check
-            if !is_king_attacked(board, us)
-                && !is_square_attacked(board, f_sq, us)
-                && !is_square_attacked(board, g_sq, us)
-            {
-                moves.push(ChessMove::new(king_sq, g_sq, None));
-            }
+    for (right, king_file, rook_file) in [(ks_right, 6, 5), (qs_right, 2, 3)] {
+        if rights & right == 0 {
+            continue;
+        }
+        let rook_sq = board.castle_rook(right);
+        let king_to = Square::make_square(rank, File::from_index(king_file));
+        let rook_to = Square::make_square(rank, File::from_index(rook_file));
+
+        // Everything the king and rook cross (or land on) must be empty, ignoring the two
+        // castling pieces themselves
+        let movers = BitBoard::from_square(king_sq) | BitBoard::from_square(rook_sq);
+        let path = rank_span(king_sq, king_to) | rank_span(rook_sq, rook_to);
+        if path & occupied & !movers != EMPTY {
+            continue;
         }
-    }
-
-    // Queenside
-    if rights & qs_right != 0 {
-        let d_sq = Square::make_square(Rank::from_index(rank), File::from_index(3));
-        let c_sq = Square::make_square(Rank::from_index(rank), File::from_index(2));
-        let b_sq = Square::make_square(Rank::from_index(rank), File::from_index(1));
 
-        // Squares between king and rook must be empty
-        if (BitBoard::from_square(d_sq) | BitBoard::from_square(c_sq) | BitBoard::from_square(b_sq))
-            & occupied
-            == EMPTY
-            && !is_king_attacked(board, us)
-            && !is_square_attacked(board, d_sq, us)
-            && !is_square_attacked(board, c_sq, us)
-        {
-            moves.push(ChessMove::new(king_sq, c_sq, None));
+        // King must not pass through or land on an attacked square
+        if rank_span(king_sq, king_to).iter().any(|sq| is_square_attacked(board, sq, us)) {
+            continue;
         }
+
+        // Encoded as king takes own rook
+        moves.push(ChessMove::new_castling(king_sq, rook_sq));
     }
 }
 
+/// All squares from `a` to `b` inclusive (same rank)
+fn rank_span(a: Square, b: Square) -> BitBoard {
+    let (lo, hi) = (a.to_index().min(b.to_index()), a.to_index().max(b.to_index()));
+    (lo..=hi).fold(EMPTY, |bb, i| bb | BitBoard::from_square(Square::new(i as u8)))
+}
+
 /// Check if specific square is attacked by opponent of `color`
 fn is_square_attacked(board: &Board, sq: Square, color: Color) -> bool {
-    let enemy = board.color_combined(!color);
-    let occupied = board.combined();
-
-    if !(knight_attacks(sq) & board.pieces(Piece::Knight) & enemy).is_empty() {
-        return true;
-    }
-    if !(pawn_attacks(color, sq) & board.pieces(Piece::Pawn) & enemy).is_empty() {
-        return true;
-    }
-    if !(bishop_attacks(sq, occupied)
-        & (board.pieces(Piece::Bishop) | board.pieces(Piece::Queen))
-        & enemy)
-        .is_empty()
-    {
-        return true;
-    }
-    if !(rook_attacks(sq, occupied)
-        & (board.pieces(Piece::Rook) | board.pieces(Piece::Queen))
-        & enemy)
-        .is_empty()
-    {
-        return true;
-    }
-    if !(king_attacks(sq) & board.pieces(Piece::King) & enemy).is_empty() {
-        return true;
-    }
-    false
+    !(board.attackers_to(sq, board.combined()) & board.color_combined(!color)).is_empty()
 }
 
 #[cfg(test)]
@@ -334,9 +320,6 @@ mod tests {
     use super::*;
     use std::str::FromStr;
 
-    fn init() {
-        super::super::init();
-    }
 
     fn perft(board: &Board, depth: u32) -> u64 {
         if depth == 0 {
@@ -353,7 +336,6 @@ mod tests {
 
     #[test]
     fn test_startpos_moves() {
-        init();
         let board = Board::default();
         let moves: Vec<_> = MoveGen::new_legal(&board).collect();
         assert_eq!(moves.len(), 20, "Starting position should have 20 legal moves, got {}", moves.len());
@@ -361,77 +343,73 @@ mod tests {
 
     #[test]
     fn test_perft_depth1() {
-        init();
         let board = Board::default();
         assert_eq!(perft(&board, 1), 20);
     }
 
     #[test]
     fn test_perft_depth2() {
-        init();
         let board = Board::default();
         assert_eq!(perft(&board, 2), 400);
     }
 
     #[test]
     fn test_perft_depth3() {
-        init();
         let board = Board::default();
         assert_eq!(perft(&board, 3), 8902);
     }
 
     #[test]
     fn test_perft_depth4() {
-        init();
         let board = Board::default();
         assert_eq!(perft(&board, 4), 197_281);
     }
 
     #[test]
     fn test_kiwipete_depth1() {
-        init();
         let board = Board::from_str("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1").unwrap();
         assert_eq!(perft(&board, 1), 48);
     }
 
     #[test]
     fn test_kiwipete_depth2() {
-        init();
         let board = Board::from_str("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1").unwrap();
         assert_eq!(perft(&board, 2), 2039);
     }
 
     #[test]
     fn test_kiwipete_depth3() {
-        init();
         let board = Board::from_str("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1").unwrap();
         assert_eq!(perft(&board, 3), 97_862);
     }
 
     #[test]
     fn test_position3_depth1() {
-        init();
         let board = Board::from_str("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1").unwrap();
         assert_eq!(perft(&board, 1), 14);
     }
 
     #[test]
     fn test_position3_depth2() {
-        init();
         let board = Board::from_str("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1").unwrap();
         assert_eq!(perft(&board, 2), 191);
     }
 
     #[test]
     fn test_position3_depth3() {
-        init();
         let board = Board::from_str("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1").unwrap();
         assert_eq!(perft(&board, 3), 2812);
     }
 
+    #[test]
+    fn test_most_moves_fit() {
+        // The position with the most legal moves known
+        let board = Board::from_str("R6R/3Q4/1Q4Q1/4Q3/2Q4Q/Q4Q2/pp1Q4/kBNN1KB1 w - - 0 1").unwrap();
+        assert_eq!(perft(&board, 1), 218);
+    }
+
     #[test]
     fn test_iterator_mask() {
-        init();
         let board = Board::default();
         let mut mg = MoveGen::new_legal(&board);
 
@@ -447,9 +425,77 @@ mod tests {
         assert_eq!(remaining.len(), 20);
     }
 
+    #[test]
+    fn test_len_and_source_masks() {
+        let board = Board::from_str("r3k2r/1P6/8/3N1N2/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+        let mut mg = MoveGen::new_legal(&board);
+        let total = mg.len();
+        assert_eq!(total, MoveGen::new_legal(&board).count());
+
+        // Knights reaching e3: the SAN disambiguation query
+        mg.set_piece_mask(Piece::Knight);
+        mg.set_iterator_mask(BitBoard::from_square(Square::E3));
+        assert_eq!(mg.len(), 2);
+        mg.set_source_mask(BitBoard::from_square(Square::D5));
+        assert_eq!(mg.len(), 1);
+        assert_eq!(mg.next(), Some(ChessMove::new(Square::D5, Square::E3, None)));
+        assert_eq!(mg.len(), 0);
+
+        // Reset both masks: everything but the consumed move is left
+        mg.set_source_mask(!EMPTY);
+        mg.set_iterator_mask(!EMPTY);
+        assert_eq!(mg.len(), total - 1);
+        mg.next();
+        assert_eq!(mg.len(), total - 2);
+    }
+
+    #[test]
+    fn test_new_captures_matches_filtered_legal() {
+        for fen in [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "rnbqkbnr/ppp1pppp/8/3pP3/8/8/PPPP1PPP/RNBQKBNR w KQkq d6 0 1",
+            "n1n5/PPPk4/8/8/8/8/4Kppp/5N1N b - - 0 1",
+        ] {
+            let board = Board::from_str(fen).unwrap();
+            let them = board.color_combined(!board.side_to_move());
+            let mut expected: Vec<ChessMove> = MoveGen::new_legal(&board)
+                .filter(|mv| {
+                    !(BitBoard::from_square(mv.get_dest()) & them).is_empty()
+                        || mv.get_promotion().is_some()
+                        || (board.en_passant() == Some(mv.get_dest())
+                            && board.piece_on(mv.get_source()) == Some(Piece::Pawn))
+                })
+                .collect();
+            let mut captures: Vec<ChessMove> = MoveGen::new_captures(&board).collect();
+            expected.sort_by_key(|m| m.to_string());
+            captures.sort_by_key(|m| m.to_string());
+            assert_eq!(captures, expected, "{}", fen);
+        }
+    }
+
+    #[test]
+    fn test_captures_and_quiets_partition_legal() {
+        for fen in [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "rnbqkbnr/ppp1pppp/8/3pP3/8/8/PPPP1PPP/RNBQKBNR w KQkq d6 0 1",
+            "n1n5/PPPk4/8/8/8/8/4Kppp/5N1N b - - 0 1",
+        ] {
+            let board = Board::from_str(fen).unwrap();
+            let quiets: Vec<ChessMove> = MoveGen::new_quiets(&board).collect();
+            let them = board.color_combined(!board.side_to_move());
+            assert!(quiets.iter().all(|mv| (BitBoard::from_square(mv.get_dest()) & them).is_empty()
+                && mv.get_promotion().is_none()));
+
+            let mut all: Vec<ChessMove> = MoveGen::new_legal(&board).collect();
+            let mut split: Vec<ChessMove> = MoveGen::new_captures(&board).chain(quiets).collect();
+            all.sort_by_key(|m| m.to_string());
+            split.sort_by_key(|m| m.to_string());
+            assert_eq!(split, all, "{}", fen);
+        }
+    }
+
     #[test]
     fn test_no_duplicate_moves() {
-        init();
         let board = Board::from_str("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1").unwrap();
         let mut mg = MoveGen::new_legal(&board);
 
@@ -476,7 +522,9 @@ mod tests {
 
 // Pseudo-legal + filter approach. Generate moves that obey piece movmenet rules but might leave
 // the king in check (Pseudo-legal). Then filter... for each candidate move, make it on a copy of
-// the board and check if king is attacked. A bit slower than pin-aware generation, but simple
+// the board and check if king is attacked. A bit slower than pin-aware generation, but simple.
+// The filter runs in place on a 256-slot ArrayVec, and consumed moves are a bitset, so a MoveGen
+// at every search node costs no heap allocation.
 
 // Multi-pass iteration -> `MoceGen` struct supports 3-pass iteration w/ `set_iterator_mask()`.
 // Lets move ordering request captures first -> EP -> quite moves, w/o generating them separately
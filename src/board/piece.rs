@@ -1,7 +1,8 @@
-use std::ops::Not;
+use core::ops::Not;
 
 // chess piece type (pawn, knight, bishop, rook, queen, king)
 #[derive(Clone, Copy, PartialEq, Eq, Debug, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(rename_all = "lowercase"))]
 pub enum Piece {
     Pawn,
     Knight,
@@ -30,6 +31,7 @@ impl Piece {
 
 // color: white or black
 #[derive(Clone, Copy, PartialEq, Eq, Debug, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(rename_all = "lowercase"))]
 pub enum Color {
     White,
     Black
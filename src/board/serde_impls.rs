@@ -0,0 +1,123 @@
+use std::fmt;
+use std::str::FromStr;
+
+use serde::de::{self, Visitor};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use super::board::Board;
+use super::chessmove::ChessMove;
+use super::square::{File, Rank, Square};
+
+/// Deserialize any `FromStr` type from a string, naming what was expected in errors
+struct StrVisitor<T>(&'static str, std::marker::PhantomData<T>);
+
+impl<T: FromStr<Err: fmt::Display>> Visitor<'_> for StrVisitor<T> {
+    type Value = T;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.0)
+    }
+
+    fn visit_str<E: de::Error>(self, s: &str) -> Result<T, E> {
+        s.parse().map_err(E::custom)
+    }
+}
+
+fn deserialize_str<'de, D: Deserializer<'de>, T: FromStr<Err: fmt::Display>>(d: D, expecting: &'static str) -> Result<T, D::Error> {
+    d.deserialize_str(StrVisitor(expecting, std::marker::PhantomData))
+}
+
+/// Board as its FEN string
+impl Serialize for Board {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        s.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Board {
+    fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        deserialize_str(d, "a FEN string")
+    }
+}
+
+/// ChessMove as UCI notation ("e2e4", "a7a8q"). The text has no move kind, so a castling or
+/// en passant move comes back as a normal one: `Game::make_move` and `Board::try_make_move`
+/// classify it again, anything else has to go through `Board::normalize_move`.
+impl Serialize for ChessMove {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        s.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for ChessMove {
+    fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        deserialize_str(d, "a UCI move string")
+    }
+}
+
+/// Square as its name ("e4")
+impl Serialize for Square {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        s.collect_str(self)
+    }
+}
+
+/// Private wrapper so Square's string parsing stays a serde detail
+struct SquareName(Square);
+
+impl FromStr for SquareName {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s.as_bytes() {
+            &[f @ b'a'..=b'h', r @ b'1'..=b'8'] => Ok(SquareName(Square::make_square(
+                Rank::from_index((r - b'1') as usize),
+                File::from_index((f - b'a') as usize),
+            ))),
+            _ => Err(format!("Invalid square: {}", s)),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Square {
+    fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        deserialize_str::<D, SquareName>(d, "a square name like e4").map(|s| s.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::{Color, Piece};
+
+    #[test]
+    fn test_roundtrip() {
+        let board = Board::from_str("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1").unwrap();
+        let json = serde_json::to_string(&board).unwrap();
+        assert_eq!(json, format!("\"{}\"", board));
+        assert_eq!(serde_json::from_str::<Board>(&json).unwrap(), board);
+
+        let mv = ChessMove::from_uci("a7a8q").unwrap();
+        assert_eq!(serde_json::to_string(&mv).unwrap(), "\"a7a8q\"");
+        assert_eq!(serde_json::from_str::<ChessMove>("\"a7a8q\"").unwrap(), mv);
+
+        assert_eq!(serde_json::to_string(&Square::E4).unwrap(), "\"e4\"");
+        assert_eq!(serde_json::from_str::<Square>("\"e4\"").unwrap(), Square::E4);
+
+        assert_eq!(serde_json::to_string(&(Piece::Knight, Color::Black)).unwrap(), "[\"knight\",\"black\"]");
+        assert_eq!(serde_json::from_str::<Color>("\"white\"").unwrap(), Color::White);
+    }
+
+    #[test]
+    fn test_rejects_bad_input() {
+        assert!(serde_json::from_str::<Board>("\"not a fen\"").is_err());
+        assert!(serde_json::from_str::<ChessMove>("\"e2\"").is_err());
+        assert!(serde_json::from_str::<Square>("\"i9\"").is_err());
+        assert!(serde_json::from_str::<Board>("42").is_err());
+    }
+}
+
+// serde support (optional `serde` feature): positions and moves go over the wire in the notations
+// chess tooling already speaks, FEN for boards and UCI for moves, rather than as the internal
+// bitboards / packed u16. That keeps the JSON readable and stable if the internal layout changes.
+// Piece and Color just derive, as lowercase names.
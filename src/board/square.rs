@@ -1,4 +1,6 @@
-use std::fmt;
+use core::fmt;
+
+use super::piece::Color;
 
 /// a square on the chess board, 0..63 (A1=0, H8=63).
 #[derive(Clone, Copy, PartialEq, Eq, Debug, Hash)]
@@ -6,7 +8,7 @@ pub struct Square(u8);
 
 impl Square {
     #[inline]
-    pub fn new(index: u8) -> Self {
+    pub const fn new(index: u8) -> Self {
         debug_assert!(index < 64);
         Square(index)
     }
@@ -31,6 +33,59 @@ impl Square {
         File(self.0 & 7)
     }
 
+    /// Square `dr` ranks and `df` files away, or None if that's off the board
+    #[inline]
+    pub fn offset(self, dr: i8, df: i8) -> Option<Square> {
+        let r = (self.0 >> 3) as i8 + dr;
+        let f = (self.0 & 7) as i8 + df;
+        ((0..8).contains(&r) && (0..8).contains(&f)).then(|| Square((r * 8 + f) as u8))
+    }
+
+    /// One rank towards rank 8
+    #[inline]
+    pub fn up(self) -> Option<Square> {
+        self.offset(1, 0)
+    }
+
+    /// One rank towards rank 1
+    #[inline]
+    pub fn down(self) -> Option<Square> {
+        self.offset(-1, 0)
+    }
+
+    /// One rank in `color`'s pawn direction
+    #[inline]
+    pub fn forward(self, color: Color) -> Option<Square> {
+        if color == Color::White { self.up() } else { self.down() }
+    }
+
+    /// King-move (Chebyshev) distance
+    #[inline]
+    pub fn distance(self, other: Square) -> u8 {
+        self.file_distance(other).max(self.rank_distance(other))
+    }
+
+    #[inline]
+    pub fn file_distance(self, other: Square) -> u8 {
+        (self.0 & 7).abs_diff(other.0 & 7)
+    }
+
+    #[inline]
+    pub fn rank_distance(self, other: Square) -> u8 {
+        (self.0 >> 3).abs_diff(other.0 >> 3)
+    }
+}
+
+#[rustfmt::skip]
+impl Square {
+    pub const A1: Square = Square(0); pub const B1: Square = Square(1); pub const C1: Square = Square(2); pub const D1: Square = Square(3); pub const E1: Square = Square(4); pub const F1: Square = Square(5); pub const G1: Square = Square(6); pub const H1: Square = Square(7);
+    pub const A2: Square = Square(8); pub const B2: Square = Square(9); pub const C2: Square = Square(10); pub const D2: Square = Square(11); pub const E2: Square = Square(12); pub const F2: Square = Square(13); pub const G2: Square = Square(14); pub const H2: Square = Square(15);
+    pub const A3: Square = Square(16); pub const B3: Square = Square(17); pub const C3: Square = Square(18); pub const D3: Square = Square(19); pub const E3: Square = Square(20); pub const F3: Square = Square(21); pub const G3: Square = Square(22); pub const H3: Square = Square(23);
+    pub const A4: Square = Square(24); pub const B4: Square = Square(25); pub const C4: Square = Square(26); pub const D4: Square = Square(27); pub const E4: Square = Square(28); pub const F4: Square = Square(29); pub const G4: Square = Square(30); pub const H4: Square = Square(31);
+    pub const A5: Square = Square(32); pub const B5: Square = Square(33); pub const C5: Square = Square(34); pub const D5: Square = Square(35); pub const E5: Square = Square(36); pub const F5: Square = Square(37); pub const G5: Square = Square(38); pub const H5: Square = Square(39);
+    pub const A6: Square = Square(40); pub const B6: Square = Square(41); pub const C6: Square = Square(42); pub const D6: Square = Square(43); pub const E6: Square = Square(44); pub const F6: Square = Square(45); pub const G6: Square = Square(46); pub const H6: Square = Square(47);
+    pub const A7: Square = Square(48); pub const B7: Square = Square(49); pub const C7: Square = Square(50); pub const D7: Square = Square(51); pub const E7: Square = Square(52); pub const F7: Square = Square(53); pub const G7: Square = Square(54); pub const H7: Square = Square(55);
+    pub const A8: Square = Square(56); pub const B8: Square = Square(57); pub const C8: Square = Square(58); pub const D8: Square = Square(59); pub const E8: Square = Square(60); pub const F8: Square = Square(61); pub const G8: Square = Square(62); pub const H8: Square = Square(63);
 }
 
 impl fmt::Display for Square {
@@ -90,6 +145,30 @@ pub const ALL_SQUARES: [Square; 64] = {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_named_squares() {
+        assert_eq!(Square::A1.to_index(), 0);
+        assert_eq!(Square::E4.to_index(), 28);
+        assert_eq!(Square::H8.to_index(), 63);
+        assert_eq!(Square::E4.to_string(), "e4");
+    }
+
+    #[test]
+    fn test_square_arithmetic() {
+        assert_eq!(Square::E4.up(), Some(Square::E5));
+        assert_eq!(Square::E4.down(), Some(Square::E3));
+        assert_eq!(Square::E8.up(), None);
+        assert_eq!(Square::A1.down(), None);
+        assert_eq!(Square::E2.forward(Color::White), Some(Square::E3));
+        assert_eq!(Square::E7.forward(Color::Black), Some(Square::E6));
+        assert_eq!(Square::B1.offset(2, 1), Some(Square::C3));
+        assert_eq!(Square::H4.offset(0, 1), None); // no wrap onto the a-file
+        assert_eq!(Square::A1.distance(Square::H8), 7);
+        assert_eq!(Square::E4.distance(Square::F6), 2);
+        assert_eq!(Square::B2.file_distance(Square::G3), 5);
+        assert_eq!(Square::B2.rank_distance(Square::G3), 1);
+    }
+
     #[test]
     fn test_a1_is_zero() {
         let sq = Square::make_square(Rank::from_index(0), File::from_index(0));
@@ -1,5 +1,3 @@
-use std::sync::LazyLock;
-
 struct ZobristKeys {
     /// Zobrist keys: [piece_type][color][square]
     piece: [[[u64; 64]; 2]; 6],
@@ -11,39 +9,51 @@ struct ZobristKeys {
     ep: [u64; 8],
 }
 
-static KEYS: LazyLock<ZobristKeys> = LazyLock::new(|| {
-    let mut rng = XorShift64(0x3243F6A8885A308D); // fixed seed
-
-    let mut piece = [[[0u64; 64]; 2]; 6];
-    for piece_keys in &mut piece {
-        for color_keys in piece_keys {
-            for key in color_keys {
-                *key = rng.next();
+/// Generated at compile time, so there is nothing to initialize
+static KEYS: ZobristKeys = ZobristKeys::generate();
+
+impl ZobristKeys {
+    const fn generate() -> Self {
+        let mut rng = XorShift64(0x3243F6A8885A308D); // fixed seed
+
+        let mut piece = [[[0u64; 64]; 2]; 6];
+        let mut p = 0;
+        while p < 6 {
+            let mut c = 0;
+            while c < 2 {
+                let mut sq = 0;
+                while sq < 64 {
+                    piece[p][c][sq] = rng.next();
+                    sq += 1;
+                }
+                c += 1;
             }
+            p += 1;
         }
-    }
 
-    let side = rng.next();
+        let side = rng.next();
 
-    let mut castling = [0u64; 16];
-    for key in &mut castling {
-        *key = rng.next();
-    }
-
-    let mut ep = [0u64; 8];
-    for key in &mut ep {
-        *key = rng.next();
-    }
+        let mut castling = [0u64; 16];
+        let mut i = 0;
+        while i < 16 {
+            castling[i] = rng.next();
+            i += 1;
+        }
 
-    ZobristKeys { piece, side, castling, ep }
-});
+        let mut ep = [0u64; 8];
+        let mut i = 0;
+        while i < 8 {
+            ep[i] = rng.next();
+            i += 1;
+        }
 
-/// Force-init all Zobrist keys. Can be called at startup, but keys are also
-/// lazily initialized on first access.
-pub fn init_zobrist() {
-    LazyLock::force(&KEYS);
+        ZobristKeys { piece, side, castling, ep }
+    }
 }
 
+/// Kept for callers of `board::init`: the keys are a compile-time table now
+pub fn init_zobrist() {}
+
 #[inline]
 pub fn piece_key(piece: usize, color: usize, sq: usize) -> u64 {
     KEYS.piece[piece][color][sq]
@@ -68,7 +78,7 @@ pub fn ep_key(file: usize) -> u64 {
 struct XorShift64(u64);
 
 impl XorShift64 {
-    fn next(&mut self) -> u64 {
+    const fn next(&mut self) -> u64 {
         self.0 ^= self.0 << 13;
         self.0 ^= self.0 >> 7;
         self.0 ^= self.0 << 17;
@@ -82,7 +92,6 @@ mod tests {
 
     #[test]
     fn test_keys_nonzero() {
-        init_zobrist();
         // spot check that keys are non-zero
         assert_ne!(piece_key(0, 0, 0), 0);
         assert_ne!(side_key(), 0);
@@ -92,7 +101,6 @@ mod tests {
 
     #[test]
     fn test_keys_unique() {
-        init_zobrist();
         // check that different piece/square combos produce different keys
         let k1 = piece_key(0, 0, 0); // White pawn on A1
         let k2 = piece_key(0, 0, 1); // White pawn on B1
@@ -105,7 +113,6 @@ mod tests {
 
     #[test]
     fn test_xor_cancellation() {
-        init_zobrist();
         // XORing a key twice should cancel out
         let mut hash = 0u64;
         let key = piece_key(3, 0, 28);
@@ -125,5 +132,8 @@ mod tests {
 // 4. XOR is its own inverse: `hash ^= key; hash ^= key;` restores original hash
 // This all gives O(1) hash updates per move instead of O(# of pieces)
 
+// The keys are built by a const fn rather than on first use: no lock to check on every hash
+// update, and nothing std-only for a no_std build.
+
 // Fixed seed b/c deterministic init means every run of engine produces same Zobrist keys. This
 // makes debugging reproducible and ensures consistent transposition table
@@ -0,0 +1,354 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use thiserror::Error;
+
+use crate::board::{Board, ChessMove, Color};
+use crate::pgn::PgnGame;
+
+/// Bytes per book entry: key (8), move (2), weight (2), games (4), big-endian
+const ENTRY_SIZE: usize = 16;
+
+/// Book Variety at which moves are picked in proportion to their weight
+pub const DEFAULT_BOOK_VARIETY: u8 = 50;
+pub const MAX_BOOK_VARIETY: u8 = 100;
+
+/// Books the engine holds at once (BookFile, BookFile2, ...), probed in that order
+pub const MAX_BOOKS: usize = 3;
+
+/// Why a book couldn't be read or written
+#[derive(Debug, Error)]
+pub enum BookError {
+    #[error("cannot read {}: {source}", path.display())]
+    Read { path: PathBuf, source: std::io::Error },
+    #[error("cannot write {}: {source}", path.display())]
+    Write { path: PathBuf, source: std::io::Error },
+    /// Not a whole number of entries, so not a book (or a truncated one)
+    #[error("book size {0} is not a multiple of {ENTRY_SIZE}")]
+    BadSize(usize),
+}
+
+/// One book move with its weight (higher = play more often)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BookEntry {
+    /// `Board::get_hash` of the position the move is played from
+    pub key: u64,
+    pub mv: ChessMove,
+    pub weight: u16,
+    /// Games in the source database that played this move here
+    pub games: u32,
+}
+
+/// An opening book in ferrite's native format: 16-byte entries sorted by position key, laid
+/// out like Polyglot's but keyed by `Board::get_hash` with moves in `ChessMove` encoding.
+/// Keys follow the engine's Zobrist tables, so a book is tied to the engine version that
+/// built it.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Book {
+    entries: Vec<BookEntry>,
+}
+
+impl Book {
+    /// Entries sorted by key, then best weight first
+    pub fn from_entries(mut entries: Vec<BookEntry>) -> Self {
+        entries.sort_by(|a, b| a.key.cmp(&b.key).then(b.weight.cmp(&a.weight)).then(a.mv.to_raw().cmp(&b.mv.to_raw())));
+        Book { entries }
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Book, BookError> {
+        let path = path.as_ref();
+        let bytes = fs::read(path).map_err(|source| BookError::Read { path: path.to_path_buf(), source })?;
+        Book::from_bytes(&bytes)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), BookError> {
+        let path = path.as_ref();
+        fs::write(path, self.to_bytes()).map_err(|source| BookError::Write { path: path.to_path_buf(), source })
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Book, BookError> {
+        if !bytes.len().is_multiple_of(ENTRY_SIZE) {
+            return Err(BookError::BadSize(bytes.len()));
+        }
+        let entries = bytes
+            .chunks_exact(ENTRY_SIZE)
+            .map(|e| BookEntry {
+                key: u64::from_be_bytes(e[0..8].try_into().unwrap()),
+                mv: ChessMove::from_raw(u16::from_be_bytes([e[8], e[9]])),
+                weight: u16::from_be_bytes([e[10], e[11]]),
+                games: u32::from_be_bytes(e[12..16].try_into().unwrap()),
+            })
+            .collect();
+        Ok(Book::from_entries(entries))
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.entries.len() * ENTRY_SIZE);
+        for entry in &self.entries {
+            bytes.extend_from_slice(&entry.key.to_be_bytes());
+            bytes.extend_from_slice(&entry.mv.to_raw().to_be_bytes());
+            bytes.extend_from_slice(&entry.weight.to_be_bytes());
+            bytes.extend_from_slice(&entry.games.to_be_bytes());
+        }
+        bytes
+    }
+
+    pub fn entries(&self) -> &[BookEntry] {
+        &self.entries
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Book moves for `board`, best weight first; only moves legal in `board` are returned,
+    /// which guards against hash collisions
+    pub fn probe(&self, board: &Board) -> Vec<BookEntry> {
+        let key = board.get_hash();
+        let start = self.entries.partition_point(|e| e.key < key);
+        self.entries[start..]
+            .iter()
+            .take_while(|e| e.key == key)
+            .filter(|e| board.legal(e.mv))
+            .copied()
+            .collect()
+    }
+
+    /// A book move for `board`, chosen with probability proportional to its weight by
+    /// `random` (any value, e.g. from an RNG)
+    pub fn pick(&self, board: &Board, random: u64) -> Option<ChessMove> {
+        let entries = self.probe(board);
+        let total: u64 = entries.iter().map(|e| e.weight as u64).sum();
+        if total == 0 {
+            return None;
+        }
+        let mut target = random % total;
+        entries.into_iter().find_map(|e| match target.checked_sub(e.weight as u64) {
+            Some(rest) => {
+                target = rest;
+                None
+            }
+            None => Some(e.mv),
+        })
+    }
+
+    /// `pick` with the weights sharpened or flattened by `variety` (Book Variety): 0 always
+    /// plays the heaviest move, `DEFAULT_BOOK_VARIETY` picks in proportion to weight, and
+    /// higher values raise each weight to the power `DEFAULT_BOOK_VARIETY / variety`, evening
+    /// out the odds (at 100, a move with 4 times the weight is played twice as often)
+    pub fn pick_with_variety(&self, board: &Board, random: u64, variety: u8) -> Option<ChessMove> {
+        match variety {
+            DEFAULT_BOOK_VARIETY => return self.pick(board, random),
+            0 => return self.probe(board).into_iter().find(|e| e.weight > 0).map(|e| e.mv),
+            _ => {}
+        }
+        let exponent = DEFAULT_BOOK_VARIETY as f64 / variety as f64;
+        let entries: Vec<(ChessMove, f64)> =
+            self.probe(board).into_iter().filter(|e| e.weight > 0).map(|e| (e.mv, (e.weight as f64).powf(exponent))).collect();
+        let total: f64 = entries.iter().map(|&(_, w)| w).sum();
+        // The top 53 bits as a fraction in [0, 1)
+        let mut target = (random >> 11) as f64 / (1u64 << 53) as f64 * total;
+        for &(mv, weight) in &entries {
+            if target < weight {
+                return Some(mv);
+            }
+            target -= weight;
+        }
+        entries.last().map(|&(mv, _)| mv)
+    }
+}
+
+/// Per-move tallies from the side to move's point of view
+#[derive(Debug, Clone, Copy, Default)]
+struct MoveStats {
+    games: u32,
+    wins: u32,
+    draws: u32,
+}
+
+/// Collects moves from PGN games into a `Book`. A move's weight is its score in the games that
+/// played it (2 per win, 1 per draw for the side that moved), so popular moves that also do
+/// well come first.
+#[derive(Debug)]
+pub struct BookBuilder {
+    /// Only the first this many plies of each game are recorded
+    pub max_ply: usize,
+    /// Moves played in fewer games than this are left out
+    pub min_games: u32,
+    stats: HashMap<(u64, ChessMove), MoveStats>,
+    games: usize,
+}
+
+impl Default for BookBuilder {
+    fn default() -> Self {
+        BookBuilder::new(24, 3)
+    }
+}
+
+impl BookBuilder {
+    pub fn new(max_ply: usize, min_games: u32) -> Self {
+        BookBuilder { max_ply, min_games, stats: HashMap::new(), games: 0 }
+    }
+
+    /// Record the opening of one game; games without a decisive or drawn result count toward
+    /// frequency only
+    pub fn add_game(&mut self, game: &PgnGame) {
+        let winner = match game.result.as_str() {
+            "1-0" => Some(Some(Color::White)),
+            "0-1" => Some(Some(Color::Black)),
+            "1/2-1/2" => Some(None),
+            _ => None,
+        };
+        for (board, &mv) in game.positions().zip(&game.moves).take(self.max_ply) {
+            let stats = self.stats.entry((board.get_hash(), mv)).or_default();
+            stats.games += 1;
+            match winner {
+                Some(Some(color)) if color == board.side_to_move() => stats.wins += 1,
+                Some(None) => stats.draws += 1,
+                _ => {}
+            }
+        }
+        self.games += 1;
+    }
+
+    /// Games added so far
+    pub fn games(&self) -> usize {
+        self.games
+    }
+
+    /// Entries for every move seen in at least `min_games` games that scored at least a draw
+    /// once, with weights scaled down if needed to fit in 16 bits
+    pub fn build(&self) -> Book {
+        let kept: Vec<(&(u64, ChessMove), u64)> = self
+            .stats
+            .iter()
+            .filter(|(_, s)| s.games >= self.min_games)
+            .map(|(key, s)| (key, 2 * s.wins as u64 + s.draws as u64))
+            .filter(|&(_, points)| points > 0)
+            .collect();
+        let max_points = kept.iter().map(|&(_, p)| p).max().unwrap_or(0);
+        let scale = max_points.div_ceil(u16::MAX as u64).max(1);
+
+        Book::from_entries(
+            kept.into_iter()
+                .map(|(&(key, mv), points)| BookEntry {
+                    key,
+                    mv,
+                    weight: (points / scale).max(1) as u16,
+                    games: self.stats[&(key, mv)].games,
+                })
+                .collect(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pgn::PgnReader;
+
+    const GAMES: &str = "\
+[Result \"1-0\"]
+
+1. e4 e5 2. Nf3 1-0
+
+[Result \"1/2-1/2\"]
+
+1. e4 c5 2. Nf3 1/2-1/2
+
+[Result \"0-1\"]
+
+1. d4 d5 0-1
+
+[Result \"1-0\"]
+
+1. e4 e5 2. Bc4 1-0
+";
+
+    fn builder(max_ply: usize, min_games: u32) -> BookBuilder {
+        let mut builder = BookBuilder::new(max_ply, min_games);
+        for game in PgnReader::new(GAMES.as_bytes()) {
+            builder.add_game(&game.unwrap());
+        }
+        builder
+    }
+
+    #[test]
+    fn test_build_and_probe() {
+        let book = builder(24, 1).build();
+        let start = Board::default();
+        let moves: Vec<(String, u16, u32)> =
+            book.probe(&start).iter().map(|e| (e.mv.to_string(), e.weight, e.games)).collect();
+        // e4: two wins and a draw for White; d4 lost its only game and is left out
+        assert_eq!(moves, vec![("e2e4".to_string(), 5, 3)]);
+
+        // Black's e5 lost both games, c5 drew
+        let after_e4 = start.make_move_new(ChessMove::from_uci("e2e4").unwrap());
+        let replies: Vec<String> = book.probe(&after_e4).iter().map(|e| e.mv.to_string()).collect();
+        assert_eq!(replies, vec!["c7c5"]);
+    }
+
+    #[test]
+    fn test_pick_by_weight() {
+        let start = Board::default();
+        let [e4, d4] = ["e2e4", "d2d4"].map(|m| ChessMove::from_uci(m).unwrap());
+        let entry = |mv, weight| BookEntry { key: start.get_hash(), mv, weight, games: 1 };
+        let book = Book::from_entries(vec![entry(e4, 3), entry(d4, 1)]);
+        let picks: Vec<ChessMove> = (0..4).map(|r| book.pick(&start, r).unwrap()).collect();
+        assert_eq!(picks, [e4, e4, e4, d4]);
+        assert_eq!(book.pick(&start, 7), Some(d4));
+
+        let after_e4 = start.make_move_new(e4);
+        assert_eq!(book.pick(&after_e4, 0), None);
+        assert_eq!(Book::from_entries(vec![entry(e4, 0)]).pick(&start, 0), None);
+
+        // Book Variety 0 only plays the heaviest move; higher values even the odds out
+        let top = |r: u64| r << 11;
+        let fraction = |variety, picks: u64| {
+            let d4s = (0..picks).filter(|&i| book.pick_with_variety(&start, top(i * (1 << 53) / picks), variety) == Some(d4)).count();
+            d4s as f64 / picks as f64
+        };
+        assert_eq!(fraction(0, 100), 0.0);
+        assert_eq!(book.pick_with_variety(&start, 3, DEFAULT_BOOK_VARIETY), Some(d4));
+        let even = fraction(MAX_BOOK_VARIETY, 1000);
+        assert!((even - 1.0 / (1.0 + 3f64.sqrt())).abs() < 0.01, "{}", even);
+        assert_eq!(book.pick_with_variety(&after_e4, 0, MAX_BOOK_VARIETY), None);
+    }
+
+    #[test]
+    fn test_filters() {
+        // Only the first ply, and only moves from at least 2 games
+        let book = builder(1, 2).build();
+        assert_eq!(book.len(), 1);
+        assert_eq!(book.entries()[0].mv.to_string(), "e2e4");
+        assert_eq!(builder(1, 2).games(), 4);
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let book = builder(24, 1).build();
+        let bytes = book.to_bytes();
+        assert_eq!(bytes.len(), book.len() * ENTRY_SIZE);
+        assert_eq!(Book::from_bytes(&bytes).unwrap(), book);
+
+        let path = std::env::temp_dir().join(format!("ferrite-book-{}.bin", std::process::id()));
+        book.save(&path).unwrap();
+        assert_eq!(Book::load(&path).unwrap(), book);
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(matches!(Book::from_bytes(&bytes[..ENTRY_SIZE + 1]), Err(BookError::BadSize(17))));
+        assert!(matches!(Book::load("/nonexistent/book.bin"), Err(BookError::Read { .. })));
+    }
+}
+
+// Books are built in memory from (position hash, move) tallies and written as one sorted array
+// of fixed-size records, so probing a loaded book is a binary search with no index. The record
+// layout mirrors Polyglot's, which keeps the format easy to inspect and convert, but the keys
+// are the engine's own Zobrist hashes: cheaper than a second hashing scheme, at the cost of
+// rebuilding books if the Zobrist tables ever change. Weights favour moves that score well in
+// the games that played them; a move that only ever lost gets no entry at all.
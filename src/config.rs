@@ -0,0 +1,105 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use thiserror::Error;
+
+/// Why a config file couldn't be loaded
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    #[error("cannot read {}: {source}", path.display())]
+    Read { path: PathBuf, source: std::io::Error },
+    /// A line that is neither `name = value`, a `[section]`, a comment nor blank
+    #[error("line {line}: expected `name = value`, got `{text}`")]
+    Syntax { line: usize, text: String },
+}
+
+/// Engine defaults as UCI `(name, value)` option pairs, in file order, so a later line wins
+/// over an earlier one. Names aren't checked here; they fail like an unknown `setoption`
+/// when applied.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Config {
+    pub options: Vec<(String, String)>,
+}
+
+impl Config {
+    /// Parse `name = value` lines, e.g. `Hash = 256` or `"Skill Level" = 10`. `#` and `;`
+    /// start comments, section headers are ignored and quotes around names and values are
+    /// dropped, so a flat TOML or INI file reads the same.
+    pub fn parse(text: &str) -> Result<Config, ConfigError> {
+        let mut options = Vec::new();
+        for (i, line) in text.lines().enumerate() {
+            let line = strip_comment(line).trim();
+            if line.is_empty() || (line.starts_with('[') && line.ends_with(']')) {
+                continue;
+            }
+            let syntax = || ConfigError::Syntax { line: i + 1, text: line.to_string() };
+            let (name, value) = line.split_once('=').ok_or_else(syntax)?;
+            let name = unquote(name.trim());
+            if name.is_empty() {
+                return Err(syntax());
+            }
+            options.push((name.to_string(), unquote(value.trim()).to_string()));
+        }
+        Ok(Config { options })
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Config, ConfigError> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path).map_err(|source| ConfigError::Read { path: path.to_path_buf(), source })?;
+        Config::parse(&text)
+    }
+}
+
+/// The line up to its first `#` or `;` outside quotes
+fn strip_comment(line: &str) -> &str {
+    let mut quoted = false;
+    for (i, c) in line.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            '#' | ';' if !quoted => return &line[..i],
+            _ => {}
+        }
+    }
+    line
+}
+
+fn unquote(s: &str) -> &str {
+    s.strip_prefix('"').and_then(|s| s.strip_suffix('"')).unwrap_or(s)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_config() {
+        let text = "\
+# engine defaults
+[engine]
+Hash = 256
+\"Skill Level\" = 10   ; weaker for the club
+SyzygyPath = \"/data/tb#5\"
+EvalFile=
+";
+        let config = Config::parse(text).unwrap();
+        let pairs: Vec<(&str, &str)> = config.options.iter().map(|(n, v)| (n.as_str(), v.as_str())).collect();
+        assert_eq!(pairs, [("Hash", "256"), ("Skill Level", "10"), ("SyzygyPath", "/data/tb#5"), ("EvalFile", "")]);
+        assert_eq!(Config::parse("").unwrap(), Config::default());
+    }
+
+    #[test]
+    fn test_config_errors() {
+        match Config::parse("Hash = 64\nThreads 4\n") {
+            Err(ConfigError::Syntax { line, text }) => assert_eq!((line, text.as_str()), (2, "Threads 4")),
+            other => panic!("expected a syntax error, got {:?}", other),
+        }
+        assert!(Config::parse("= 4").is_err());
+        assert!(matches!(Config::load("/nonexistent/ferrite.toml"), Err(ConfigError::Read { .. })));
+    }
+}
+
+// The config file is a list of UCI options rather than its own schema: every setting already
+// has a UCI name, parser and error message in `engine::apply_option`, and a new option (or a
+// tuning parameter in a `tune` build) works in the file with no change here. The accepted
+// syntax is the flat subset TOML and INI share, parsed by hand to avoid a dependency for
+// twenty lines of key-value pairs.
@@ -0,0 +1,244 @@
+use std::io::{self, BufRead, Write};
+use std::path::Path;
+use std::str::FromStr;
+
+use super::TrainingRecord;
+use crate::board::{Board, Color, Piece, ALL_SQUARES};
+
+/// Bytes per record in bullet's `ChessBoard` format
+pub const BULLET_RECORD_SIZE: usize = 32;
+
+/// How training records are stored on disk
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DataFormat {
+    /// `fen | score | result` lines
+    Text,
+    /// bullet's 32-byte `ChessBoard` records
+    Bullet,
+}
+
+impl DataFormat {
+    /// Bullet for `.bin` and `.data` files, text for anything else
+    pub fn from_path(path: impl AsRef<Path>) -> DataFormat {
+        match path.as_ref().extension().and_then(|e| e.to_str()) {
+            Some("bin" | "data") => DataFormat::Bullet,
+            _ => DataFormat::Text,
+        }
+    }
+
+    pub fn write(self, out: &mut impl Write, record: &TrainingRecord) -> io::Result<()> {
+        match self {
+            DataFormat::Text => writeln!(out, "{}", record),
+            DataFormat::Bullet => out.write_all(&record.to_bullet()),
+        }
+    }
+}
+
+/// Parses the `Display` form; results may also be written `1`, `1/2`, `0` or in brackets
+impl FromStr for TrainingRecord {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        let fields: Vec<&str> = s.split('|').map(str::trim).collect();
+        let [fen, score, result] = fields[..] else {
+            return Err(format!("expected `fen | score | result`: {}", s));
+        };
+        let board = Board::from_str(fen)?;
+        let score = score.parse().map_err(|_| format!("invalid score {}", score))?;
+        let result = match result.trim_start_matches('[').trim_end_matches(']') {
+            "1.0" | "1" => 1.0,
+            "0.5" | "1/2" => 0.5,
+            "0.0" | "0" => 0.0,
+            other => return Err(format!("invalid result {}", other)),
+        };
+        Ok(TrainingRecord { board, score, result })
+    }
+}
+
+impl TrainingRecord {
+    /// bullet's `ChessBoard`: occupancy (u64), a 4-bit piece code per occupied square in square
+    /// order, then score (i16), result (0/1/2), both king squares and 3 spare bytes, all
+    /// little-endian. The board, score and result are from the side to move's point of view,
+    /// so a Black-to-move position is stored mirrored with colours swapped.
+    pub fn to_bullet(&self) -> [u8; BULLET_RECORD_SIZE] {
+        let flip = self.board.side_to_move() == Color::Black;
+        let (us, score, result) = if flip {
+            (Color::Black, -self.score, 1.0 - self.result)
+        } else {
+            (Color::White, self.score, self.result)
+        };
+
+        // (square, code) from the side to move's view; code = 8 for the opponent | piece index
+        let mut pieces: Vec<(usize, u8)> = ALL_SQUARES
+            .iter()
+            .filter_map(|&sq| {
+                let piece = self.board.piece_on(sq)?;
+                let theirs = self.board.color_on(sq)? != us;
+                let square = if flip { sq.to_index() ^ 56 } else { sq.to_index() };
+                Some((square, (theirs as u8) << 3 | piece.to_index() as u8))
+            })
+            .collect();
+        pieces.sort_unstable();
+
+        let mut bytes = [0u8; BULLET_RECORD_SIZE];
+        let occupancy = pieces.iter().fold(0u64, |occ, &(sq, _)| occ | 1 << sq);
+        bytes[0..8].copy_from_slice(&occupancy.to_le_bytes());
+        for (i, &(square, code)) in pieces.iter().enumerate().take(32) {
+            bytes[8 + i / 2] |= code << (4 * (i & 1));
+            match code {
+                5 => bytes[27] = square as u8,
+                13 => bytes[28] = square as u8 ^ 56,
+                _ => {}
+            }
+        }
+        let score = score.clamp(i16::MIN as i32, i16::MAX as i32) as i16;
+        bytes[24..26].copy_from_slice(&score.to_le_bytes());
+        bytes[26] = (2.0 * result) as u8;
+        bytes
+    }
+
+    /// Read a bullet record. bullet keeps neither the side to move nor castling and en passant
+    /// rights, so the board comes back as White to move from the original mover's view, without
+    /// those rights; score and result follow it.
+    pub fn from_bullet(bytes: &[u8; BULLET_RECORD_SIZE]) -> Result<TrainingRecord, String> {
+        let mut occupancy = u64::from_le_bytes(bytes[0..8].try_into().unwrap());
+        let mut squares = [None; 64];
+        let mut i = 0;
+        while occupancy != 0 {
+            let square = occupancy.trailing_zeros() as usize;
+            occupancy &= occupancy - 1;
+            let code = (bytes[8 + i / 2] >> (4 * (i & 1))) & 0xF;
+            let piece = match code & 7 {
+                0 => Piece::Pawn,
+                1 => Piece::Knight,
+                2 => Piece::Bishop,
+                3 => Piece::Rook,
+                4 => Piece::Queen,
+                5 => Piece::King,
+                _ => return Err(format!("invalid piece code {}", code)),
+            };
+            let color = if code & 8 == 0 { Color::White } else { Color::Black };
+            squares[square] = Some((piece, color));
+            i += 1;
+        }
+
+        let mut placement = String::new();
+        for rank in (0..8).rev() {
+            let mut empty = 0;
+            for file in 0..8 {
+                match squares[rank * 8 + file] {
+                    None => empty += 1,
+                    Some((piece, color)) => {
+                        if empty > 0 {
+                            placement.push_str(&empty.to_string());
+                            empty = 0;
+                        }
+                        let c = b"pnbrqk"[piece.to_index()] as char;
+                        placement.push(if color == Color::White { c.to_ascii_uppercase() } else { c });
+                    }
+                }
+            }
+            if empty > 0 {
+                placement.push_str(&empty.to_string());
+            }
+            if rank > 0 {
+                placement.push('/');
+            }
+        }
+        let board = Board::from_str(&format!("{} w - - 0 1", placement))?;
+        let score = i16::from_le_bytes([bytes[24], bytes[25]]) as i32;
+        let result = match bytes[26] {
+            0..=2 => bytes[26] as f32 / 2.0,
+            other => return Err(format!("invalid result byte {}", other)),
+        };
+        Ok(TrainingRecord { board, score, result })
+    }
+}
+
+/// Records from `input` in `format`, in order
+pub fn read_records(mut input: impl BufRead, format: DataFormat) -> impl Iterator<Item = Result<TrainingRecord, String>> {
+    let mut lines = 0;
+    std::iter::from_fn(move || match format {
+        DataFormat::Text => loop {
+            let mut line = String::new();
+            lines += 1;
+            match input.read_line(&mut line) {
+                Ok(0) => return None,
+                Ok(_) if line.trim().is_empty() => continue,
+                Ok(_) => return Some(line.trim().parse().map_err(|e| format!("line {}: {}", lines, e))),
+                Err(e) => return Some(Err(e.to_string())),
+            }
+        },
+        DataFormat::Bullet => {
+            let mut bytes = [0u8; BULLET_RECORD_SIZE];
+            match input.read_exact(&mut bytes) {
+                Ok(()) => Some(TrainingRecord::from_bullet(&bytes)),
+                Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => None,
+                Err(e) => Some(Err(e.to_string())),
+            }
+        }
+    })
+}
+
+/// Copy every record from `input` to `output`, changing format. Returns the records written.
+pub fn convert(input: impl BufRead, from: DataFormat, output: &mut impl Write, to: DataFormat) -> Result<usize, String> {
+    let mut count = 0;
+    for record in read_records(input, from) {
+        to.write(output, &record?).map_err(|e| e.to_string())?;
+        count += 1;
+    }
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bullet_layout() {
+        // Start position: 32 pieces, white king on e1 (4), black king on e8 (60 ^ 56 = 4)
+        let record = TrainingRecord { board: Board::default(), score: 25, result: 1.0 };
+        let bytes = record.to_bullet();
+        assert_eq!(u64::from_le_bytes(bytes[0..8].try_into().unwrap()), 0xFFFF_0000_0000_FFFF);
+        // a1 rook (3), b1 knight (1) share the first byte
+        assert_eq!(bytes[8], 3 | 1 << 4);
+        assert_eq!(i16::from_le_bytes([bytes[24], bytes[25]]), 25);
+        assert_eq!(&bytes[26..29], &[2, 4, 4]);
+
+        // Black to move: stored from Black's side, so score and result flip
+        let record: TrainingRecord = "4k3/8/8/8/8/8/4P3/4K3 b - - 0 1 | 100 | 1.0".parse().unwrap();
+        let bytes = record.to_bullet();
+        assert_eq!(i16::from_le_bytes([bytes[24], bytes[25]]), -100);
+        assert_eq!(bytes[26], 0);
+        let back = TrainingRecord::from_bullet(&bytes).unwrap();
+        assert_eq!(back.board.to_string(), "4k3/4p3/8/8/8/8/8/4K3 w - - 0 1");
+        assert_eq!((back.score, back.result), (-100, 0.0));
+    }
+
+    #[test]
+    fn test_convert_round_trip() {
+        let text = "\
+rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1 | 30 | 0.5
+r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4 | -12 | [0.0]
+";
+        let mut binary = Vec::new();
+        assert_eq!(convert(text.as_bytes(), DataFormat::Text, &mut binary, DataFormat::Bullet), Ok(2));
+        assert_eq!(binary.len(), 2 * BULLET_RECORD_SIZE);
+
+        let mut text_again = Vec::new();
+        convert(&binary[..], DataFormat::Bullet, &mut text_again, DataFormat::Text).unwrap();
+        let mut binary_again = Vec::new();
+        convert(&text_again[..], DataFormat::Text, &mut binary_again, DataFormat::Bullet).unwrap();
+        assert_eq!(binary_again, binary);
+
+        assert!(convert("8/8 | x | 1".as_bytes(), DataFormat::Text, &mut Vec::new(), DataFormat::Bullet).is_err());
+        assert_eq!(DataFormat::from_path("train.bin"), DataFormat::Bullet);
+        assert_eq!(DataFormat::from_path("train.txt"), DataFormat::Text);
+    }
+}
+
+// The binary format is bullet's `ChessBoard` byte for byte, so datagen output feeds straight
+// into bullet (and tools that read its format) without a conversion pass. It is built by hand
+// rather than through the bulletformat crate to keep the engine free of trainer dependencies.
+// Records are written from the side to move's point of view, as bullet expects; the text form
+// keeps White's point of view, which is what most other tools read.
@@ -0,0 +1,200 @@
+mod format;
+
+use std::fmt;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::mpsc;
+use std::thread;
+
+use crate::board::{Board, BoardStatus, Color, MoveGen};
+use crate::game::Game;
+use crate::search::{self, SearchState, SearchTables};
+use crate::selfplay::{next_random, tablebase_result, Adjudication, Adjudicator};
+use crate::syzygy::SyzygyProber;
+use crate::types::{Score, SearchLimits, SCORE_MATE};
+
+pub use format::{convert, read_records, DataFormat, BULLET_RECORD_SIZE};
+
+/// One training position
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TrainingRecord {
+    pub board: Board,
+    /// Search score from White's point of view (centipawns)
+    pub score: Score,
+    /// Game result from White's point of view: 1.0 win, 0.5 draw, 0.0 loss
+    pub result: f32,
+}
+
+/// `<fen> | <score> | <result>`, the text format bullet and most trainers read
+impl fmt::Display for TrainingRecord {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} | {} | {:.1}", self.board, self.score, self.result)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DatagenConfig {
+    pub games: u32,
+    /// Games played at once, one search per thread
+    pub threads: usize,
+    /// Per-move search limit; a node limit keeps games fast and their cost even
+    pub limits: SearchLimits,
+    /// Uniformly random legal moves from the start position before the engine takes over
+    pub random_plies: usize,
+    /// Chance that a quiet position (not in check, best move not a capture, no mate score)
+    /// is recorded
+    pub sample_rate: f64,
+    /// Seed for the random plies and sampling; game `i` always gets the same sequence
+    pub seed: u64,
+    pub adjudication: Adjudication,
+    /// Transposition table per thread, in MB
+    pub hash_mb: usize,
+}
+
+impl Default for DatagenConfig {
+    fn default() -> Self {
+        DatagenConfig {
+            games: 100,
+            threads: thread::available_parallelism().map_or(1, |n| n.get()),
+            limits: SearchLimits { nodes: Some(5000), ..Default::default() },
+            random_plies: 8,
+            sample_rate: 0.5,
+            seed: 1,
+            adjudication: Adjudication::default(),
+            hash_mb: 16,
+        }
+    }
+}
+
+/// Play `config.games` games on `config.threads` threads and pass each game's records to
+/// `on_game` (on the calling thread) as games finish
+pub fn run(config: &DatagenConfig, mut on_game: impl FnMut(&[TrainingRecord])) {
+    let next_game = AtomicU32::new(0);
+    let tablebase = config.adjudication.syzygy_path.as_deref().and_then(SyzygyProber::new);
+    let (records_tx, records_rx) = mpsc::channel();
+
+    thread::scope(|scope| {
+        for _ in 0..config.threads.max(1) {
+            let records_tx = records_tx.clone();
+            let next_game = &next_game;
+            let tablebase = tablebase.as_ref();
+            scope.spawn(move || {
+                let mut state = SearchState::with_tables(SearchTables::new(config.hash_mb));
+                state.silent = true;
+                loop {
+                    let index = next_game.fetch_add(1, Ordering::Relaxed);
+                    if index >= config.games || records_tx.send(play_game(config, index, &mut state, tablebase)).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+        drop(records_tx);
+        for records in records_rx {
+            on_game(&records);
+        }
+    });
+}
+
+/// One game from a randomized opening, returning the sampled positions labelled with its result
+fn play_game(config: &DatagenConfig, index: u32, state: &mut SearchState, tablebase: Option<&SyzygyProber>) -> Vec<TrainingRecord> {
+    let mut rng = (config.seed ^ (index as u64 + 1).wrapping_mul(0x9E37_79B9_7F4A_7C15)) | 1;
+    state.tables.tt_mut().clear();
+    state.tables.eval_cache.clear();
+
+    let mut game = Game::default();
+    for _ in 0..config.random_plies {
+        let moves: Vec<_> = MoveGen::new_legal(game.board()).collect();
+        if moves.is_empty() {
+            break;
+        }
+        let mv = moves[(next_random(&mut rng) % moves.len() as u64) as usize];
+        game.make_move(mv).expect("generated moves are legal");
+    }
+
+    let mut samples: Vec<(Board, Score)> = Vec::new();
+    let mut adjudicator = Adjudicator::default();
+    let result = loop {
+        let board = *game.board();
+        if board.status() == BoardStatus::Checkmate {
+            break if board.side_to_move() == Color::White { "0-1" } else { "1-0" };
+        }
+        if game.draw_reason().is_some() || game.moves().len() >= config.adjudication.max_plies {
+            break "1/2-1/2";
+        }
+        if let Some(result) = tablebase.and_then(|tb| tablebase_result(tb, &board)) {
+            break result;
+        }
+
+        let side = board.side_to_move();
+        state.reset();
+        state.position_history = game.repetition_history();
+        let result = search::search(&board, state, &config.limits);
+        let mv = result.best_move.expect("a position that is not mate or stalemate has a move");
+
+        let white_score = if side == Color::White { result.score } else { -result.score };
+        let quiet = board.checkers().0 == 0 && !board.is_capture(mv) && result.score.abs() < SCORE_MATE - 100;
+        if quiet && unit_random(&mut rng) < config.sample_rate {
+            samples.push((board, white_score));
+        }
+
+        game.make_move(mv).expect("search returns legal moves");
+        if let Some(result) = adjudicator.update(&config.adjudication, game.moves().len(), white_score) {
+            break result;
+        }
+    };
+
+    let result = match result {
+        "1-0" => 1.0,
+        "0-1" => 0.0,
+        _ => 0.5,
+    };
+    samples.into_iter().map(|(board, score)| TrainingRecord { board, score, result }).collect()
+}
+
+/// Uniform in [0, 1)
+fn unit_random(rng: &mut u64) -> f64 {
+    (next_random(rng) >> 11) as f64 / (1u64 << 53) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_record_format() {
+        let record = TrainingRecord { board: Board::default(), score: -35, result: 0.5 };
+        assert_eq!(record.to_string(), "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1 | -35 | 0.5");
+    }
+
+    #[test]
+    fn test_datagen_games() {
+        let config = DatagenConfig {
+            games: 3,
+            threads: 2,
+            limits: SearchLimits { nodes: Some(300), ..Default::default() },
+            sample_rate: 1.0,
+            adjudication: Adjudication { max_plies: 40, ..Default::default() },
+            ..Default::default()
+        };
+        let mut games = Vec::new();
+        run(&config, |records| games.push(records.to_vec()));
+        assert_eq!(games.len(), 3);
+
+        for records in &games {
+            assert!(!records.is_empty());
+            // Every record of a game carries that game's result
+            assert!(records.iter().all(|r| r.result == records[0].result));
+            for record in records {
+                assert_eq!(record.board.checkers().0, 0);
+                assert!(Board::from_str(&record.board.to_string()).is_ok());
+            }
+        }
+    }
+}
+
+// Data generation plays its own games instead of going through `Engine`: each thread owns a
+// SearchState and searches directly, so there is no channel hop per move and thousands of short
+// games stay cheap. Only quiet positions are kept, since trainers learn the static eval and a
+// capture or check in hand makes the search score a poor label for it. Randomness is derived
+// from the seed and the game index, so a game's opening doesn't depend on which thread plays it.
@@ -0,0 +1,344 @@
+use alloc::vec::Vec;
+
+use crate::board::{pawn_attacks, BitBoard, Board, Color, Piece, Square};
+
+use crate::pst::MG_PIECE_VALUE;
+use crate::types::Score;
+
+/// Base score for a theoretically won endgame. Well above any normal eval, well below mate scores
+pub const KNOWN_WIN: Score = 10_000;
+
+/// Dark squares (a1, c1, ...) used to match bishops to their mating corner
+const DARK_SQUARES: BitBoard = BitBoard(0xAA55_AA55_AA55_AA55);
+
+/// Piece counts per color: [color][Pawn, Knight, Bishop, Rook, Queen]
+type MaterialKey = [[u32; 5]; 2];
+
+const BARE: [u32; 5] = [0, 0, 0, 0, 0];
+const KQ: [u32; 5] = [0, 0, 0, 0, 1];
+const KR: [u32; 5] = [0, 0, 0, 1, 0];
+const KBN: [u32; 5] = [0, 1, 1, 0, 0];
+
+fn material_key(board: &Board) -> MaterialKey {
+    let mut key = [[0u32; 5]; 2];
+    for color in [Color::White, Color::Black] {
+        let ours = board.color_combined(color);
+        for (i, piece) in [Piece::Pawn, Piece::Knight, Piece::Bishop, Piece::Rook, Piece::Queen]
+            .into_iter()
+            .enumerate()
+        {
+            key[color.to_index()][i] = (board.pieces(piece) & ours).popcnt();
+        }
+    }
+    key
+}
+
+/// Evaluate a recognized endgame. Returns a white-relative score that overrides the generic
+/// evaluation, or None if the material signature isn't one we know.
+pub fn evaluate(board: &Board) -> Option<Score> {
+    // Recognizers only apply once the board is nearly empty
+    if board.combined().popcnt() > 5 {
+        return None;
+    }
+
+    let key = material_key(board);
+    for strong in [Color::White, Color::Black] {
+        let weak = !strong;
+        let ours = key[strong.to_index()];
+        let theirs = key[weak.to_index()];
+
+        let score = if theirs == BARE && (ours == KQ || ours == KR) {
+            Some(king_and_major_vs_king(board, strong))
+        } else if theirs == BARE && ours == KBN {
+            Some(kbn_vs_k(board, strong))
+        } else if theirs == BARE && ours[0] > 0 && ours[1] == 0 && ours[3] == 0 && ours[4] == 0 && ours[2] <= 1 {
+            wrong_rook_pawn(board, strong)
+        } else {
+            None
+        };
+
+        if let Some(s) = score {
+            return Some(if strong == Color::White { s } else { -s });
+        }
+    }
+    None
+}
+
+/// KQ vs K / KR vs K: drive the lone king to the edge and bring our king closer
+fn king_and_major_vs_king(board: &Board, strong: Color) -> Score {
+    let strong_king = board.king_square(strong);
+    let weak_king = board.king_square(!strong);
+    let material = non_king_material(board, strong);
+
+    KNOWN_WIN + material + 20 * center_distance(weak_king) + 10 * (7 - distance(strong_king, weak_king))
+}
+
+/// KBN vs K: the lone king must be driven to a corner matching the bishop's square color
+fn kbn_vs_k(board: &Board, strong: Color) -> Score {
+    let strong_king = board.king_square(strong);
+    let weak_king = board.king_square(!strong);
+    let bishops = board.pieces(Piece::Bishop) & board.color_combined(strong);
+    let corners = if (bishops & DARK_SQUARES).is_empty() {
+        [Square::new(7), Square::new(56)] // h1, a8 (light)
+    } else {
+        [Square::new(0), Square::new(63)] // a1, h8 (dark)
+    };
+    let corner_dist = corners.iter().map(|&c| manhattan(weak_king, c)).min().unwrap_or(0);
+    let material = non_king_material(board, strong);
+
+    KNOWN_WIN + material + 20 * (14 - corner_dist) + 10 * (7 - distance(strong_king, weak_king))
+}
+
+/// K + rook pawn(s) (optionally with the wrong bishop) vs K: drawn if the lone king reaches the
+/// promotion corner and the bishop can't drive it out
+fn wrong_rook_pawn(board: &Board, strong: Color) -> Option<Score> {
+    let pawns = board.pieces(Piece::Pawn) & board.color_combined(strong);
+    let files: Vec<usize> = pawns.iter().map(|sq| sq.file().to_index()).collect();
+    let file = files[0];
+    if (file != 0 && file != 7) || files.iter().any(|&f| f != file) {
+        return None;
+    }
+
+    let promo_rank = if strong == Color::White { 7 } else { 0 };
+    let promo_sq = Square::new((promo_rank * 8 + file) as u8);
+
+    let bishops = board.pieces(Piece::Bishop) & board.color_combined(strong);
+    if !bishops.is_empty() {
+        let promo_dark = !(BitBoard::from_square(promo_sq) & DARK_SQUARES).is_empty();
+        let bishop_dark = !(bishops & DARK_SQUARES).is_empty();
+        if promo_dark == bishop_dark {
+            return None; // right bishop, generic eval handles it
+        }
+    }
+
+    let weak_king = board.king_square(!strong);
+    if distance(weak_king, promo_sq) <= 1 {
+        Some(0)
+    } else {
+        None
+    }
+}
+
+/// Material edge (midgame values) needed before mop-up kicks in
+const MOP_UP_MARGIN: Score = 400;
+/// Mop-up only applies once at most this many pawns remain on the board
+const MOP_UP_MAX_PAWNS: u32 = 2;
+
+/// Mop-up term for winning endgames: reward pushing the losing king to the edge and bringing
+/// the winning king closer. Returns a white-relative endgame bonus (0 if not applicable).
+pub fn mop_up(board: &Board) -> Score {
+    if board.pieces(Piece::Pawn).popcnt() > MOP_UP_MAX_PAWNS {
+        return 0;
+    }
+
+    let diff = non_king_material(board, Color::White) - non_king_material(board, Color::Black);
+    if diff.abs() < MOP_UP_MARGIN {
+        return 0;
+    }
+
+    let strong = if diff > 0 { Color::White } else { Color::Black };
+    let strong_king = board.king_square(strong);
+    let weak_king = board.king_square(!strong);
+    let bonus = 10 * center_distance(weak_king) + 4 * (14 - manhattan(strong_king, weak_king));
+
+    if strong == Color::White { bonus } else { -bonus }
+}
+
+/// Pawn endings: a passed pawn the defending king can't catch is nearly a queen
+const UNSTOPPABLE_PASSER: Score = 700;
+/// Pawn endings: a passed pawn defended by a pawn, which the enemy king can never take
+const PROTECTED_PASSER: Score = 120;
+
+/// Pawn-race knowledge for king and pawn endings: a bonus for a passed pawn outside the
+/// defending king's square (counting whose move it is), unless both sides have one, and for
+/// protected passed pawns. Returns a white-relative endgame bonus (0 with pieces on the board).
+pub fn pawn_race(board: &Board) -> Score {
+    let pawns = board.pieces(Piece::Pawn);
+    if board.combined() != pawns | board.pieces(Piece::King) || pawns.is_empty() {
+        return 0;
+    }
+
+    let mut unstoppable = [false; 2];
+    let mut protected = [0; 2];
+    for color in [Color::White, Color::Black] {
+        let ours = pawns & board.color_combined(color);
+        let theirs = pawns & board.color_combined(!color);
+        for sq in ours.iter().filter(|&sq| is_passed(sq, color, theirs)) {
+            if !(pawn_attacks(!color, sq) & ours).is_empty() {
+                protected[color.to_index()] += PROTECTED_PASSER;
+            }
+            unstoppable[color.to_index()] |= outside_square(board, sq, color);
+        }
+    }
+
+    let mut bonus = protected[0] - protected[1];
+    // Two unstoppable pawns make a queen race, which is the search's to judge
+    if unstoppable[0] != unstoppable[1] {
+        bonus += if unstoppable[0] { UNSTOPPABLE_PASSER } else { -UNSTOPPABLE_PASSER };
+    }
+    bonus
+}
+
+/// No enemy pawn ahead of `sq` on its own or a neighbouring file
+fn is_passed(sq: Square, color: Color, their_pawns: BitBoard) -> bool {
+    !their_pawns.iter().any(|p| {
+        let (rank, theirs) = (sq.rank().to_index(), p.rank().to_index());
+        let ahead = if color == Color::White { theirs > rank } else { theirs < rank };
+        ahead && p.file_distance(sq) <= 1
+    })
+}
+
+/// Rule of the square: `color`'s pawn on `sq` promotes before the enemy king reaches the
+/// promotion square. A piece on the pawn's path (its own king, say) costs it tempi, so the
+/// rule is only applied to a free path.
+fn outside_square(board: &Board, sq: Square, color: Color) -> bool {
+    let rank = sq.rank().to_index();
+    let promo_rank = if color == Color::White { 7 } else { 0 };
+    let promo_sq = Square::new((promo_rank * 8 + sq.file().to_index()) as u8);
+    let mut path = sq.forward(color);
+    while let Some(step) = path {
+        if board.piece_on(step).is_some() {
+            return false;
+        }
+        path = step.forward(color);
+    }
+
+    // From its starting rank the double step saves a move
+    let moves = (rank.abs_diff(promo_rank) as Score).min(5);
+    let tempo = (board.side_to_move() != color) as Score;
+    distance(board.king_square(!color), promo_sq) - tempo > moves
+}
+
+fn non_king_material(board: &Board, color: Color) -> Score {
+    let ours = board.color_combined(color);
+    [Piece::Pawn, Piece::Knight, Piece::Bishop, Piece::Rook, Piece::Queen]
+        .iter()
+        .map(|&p| (board.pieces(p) & ours).popcnt() as Score * MG_PIECE_VALUE[p.to_index()])
+        .sum()
+}
+
+/// Chebyshev (king-move) distance
+pub fn distance(a: Square, b: Square) -> Score {
+    a.distance(b) as Score
+}
+
+fn manhattan(a: Square, b: Square) -> Score {
+    (a.rank_distance(b) + a.file_distance(b)) as Score
+}
+
+/// Manhattan distance from the four center squares: 0 in the center, 6 in a corner
+pub fn center_distance(sq: Square) -> Score {
+    let r = sq.rank().to_index() as Score;
+    let f = sq.file().to_index() as Score;
+    (3 - r).max(r - 4) + (3 - f).max(f - 4)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_kq_vs_k_recognized() {
+        let board = Board::from_str("8/8/8/4k3/8/8/8/3QK3 w - - 0 1").unwrap();
+        let score = evaluate(&board).unwrap();
+        assert!(score > KNOWN_WIN);
+    }
+
+    #[test]
+    fn test_kr_vs_k_prefers_edge() {
+        let center = Board::from_str("8/8/8/4k3/8/8/8/R3K3 w - - 0 1").unwrap();
+        let edge = Board::from_str("4k3/8/8/8/8/8/8/R3K3 w - - 0 1").unwrap();
+        assert!(evaluate(&edge).unwrap() > evaluate(&center).unwrap());
+    }
+
+    #[test]
+    fn test_kr_vs_k_black_strong() {
+        let board = Board::from_str("r3k3/8/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        assert!(evaluate(&board).unwrap() < -KNOWN_WIN);
+    }
+
+    #[test]
+    fn test_kbn_drives_to_bishop_corner() {
+        // Dark-squared bishop on c1: a1/h8 are the mating corners
+        let right = Board::from_str("8/8/8/8/8/8/8/k1BNK3 w - - 0 1").unwrap();
+        let wrong = Board::from_str("k7/8/8/8/8/8/8/2BNK3 w - - 0 1").unwrap();
+        assert!(evaluate(&right).unwrap() > KNOWN_WIN);
+        assert!(evaluate(&right).unwrap() > evaluate(&wrong).unwrap());
+    }
+
+    #[test]
+    fn test_wrong_rook_pawn_draw() {
+        // White h-pawn + light-squared bishop (f1), promotion square h8 is dark, black king on h8
+        let board = Board::from_str("7k/8/8/8/8/8/7P/4KB2 w - - 0 1").unwrap();
+        assert_eq!(evaluate(&board), Some(0));
+
+        // Right bishop (dark, c1): not recognized as a draw
+        let board = Board::from_str("7k/8/8/8/8/8/7P/2B1K3 w - - 0 1").unwrap();
+        assert_eq!(evaluate(&board), None);
+    }
+
+    #[test]
+    fn test_rook_pawn_king_in_corner() {
+        let board = Board::from_str("k7/8/8/8/8/8/P7/4K3 w - - 0 1").unwrap();
+        assert_eq!(evaluate(&board), Some(0));
+    }
+
+    #[test]
+    fn test_mop_up_rewards_cornered_king() {
+        // KRR vs KN: white is winning and wants the black king on the edge
+        let center = Board::from_str("8/8/8/3kn3/8/8/8/RR2K3 w - - 0 1").unwrap();
+        let edge = Board::from_str("3kn3/8/8/8/8/8/8/RR2K3 w - - 0 1").unwrap();
+        assert!(mop_up(&edge) > mop_up(&center));
+        assert!(mop_up(&center) > 0);
+    }
+
+    #[test]
+    fn test_mop_up_inactive_when_balanced() {
+        assert_eq!(mop_up(&Board::default()), 0);
+        let board = Board::from_str("3rk3/8/8/8/8/8/8/3RK3 w - - 0 1").unwrap();
+        assert_eq!(mop_up(&board), 0);
+    }
+
+    #[test]
+    fn test_pawn_race_rule_of_the_square() {
+        // h5 needs three moves, the king on d6 four to reach h8: whoever moves decides
+        let board = Board::from_str("8/8/3k4/7P/8/8/8/K7 w - - 0 1").unwrap();
+        assert_eq!(pawn_race(&board), UNSTOPPABLE_PASSER);
+        let board = Board::from_str("8/8/3k4/7P/8/8/8/K7 b - - 0 1").unwrap();
+        assert_eq!(pawn_race(&board), 0);
+        // The double step: b2 is five moves from b8, one less than the king on h2
+        let board = Board::from_str("8/8/8/8/8/8/1P5k/K7 w - - 0 1").unwrap();
+        assert_eq!(pawn_race(&board), UNSTOPPABLE_PASSER);
+        // Both sides queen: no bonus
+        let board = Board::from_str("8/8/3k4/7P/p7/8/8/7K w - - 0 1").unwrap();
+        assert_eq!(pawn_race(&board), 0);
+        // Only for pawn endings
+        let board = Board::from_str("8/8/3k4/7P/8/8/8/K6n w - - 0 1").unwrap();
+        assert_eq!(pawn_race(&board), 0);
+    }
+
+    #[test]
+    fn test_pawn_race_protected_passer() {
+        // g5 is defended by f4 and the black king is inside both pawns' squares
+        let board = Board::from_str("8/8/3k4/6P1/5P2/8/8/K7 b - - 0 1").unwrap();
+        assert_eq!(pawn_race(&board), PROTECTED_PASSER);
+        assert_eq!(pawn_race(&board.mirror()), -PROTECTED_PASSER);
+    }
+
+    #[test]
+    fn test_unrecognized_material() {
+        assert_eq!(evaluate(&Board::default()), None);
+    }
+}
+
+// Endgame recognizers: for a few material signatures the generic PST eval has no idea how to make
+// progress (KR vs K just shuffles, KBN vs K needs the right corner) or badly overrates a dead draw
+// (wrong-colored bishop with a rook pawn). `evaluate` matches on the per-color piece counts and
+// returns a hand-written score instead. KNOWN_WIN keeps these scores far above normal evals but
+// below the mate band, so the search still prefers actual mates.
+// In a king and pawn ending the search can miss a pawn race by a move or two, and then the PST
+// eval sees one pawn where there is nearly a queen. The rule of the square settles a free passer
+// against a lone king exactly, with the side to move as the tempo; when both sides have one the
+// race goes to the search rather than a guess about who queens with check. A protected passer
+// ties the defending king to it for good, so it gets a smaller bonus of its own.
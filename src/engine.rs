@@ -0,0 +1,748 @@
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use thiserror::Error;
+
+use crate::book::{Book, BookError, MAX_BOOKS, MAX_BOOK_VARIETY};
+use crate::evaluation::{default_network, EvalMode, Style};
+use crate::game::Game;
+use crate::handicap;
+use crate::nnue::Network;
+use crate::numa::{self, NumaPolicy};
+use crate::opponent::{MAX_OPPONENT_CONTEMPT, MAX_RATING};
+use crate::output;
+use crate::search::{self, SearchBackend, SearchState, MAX_CONTEMPT, MAX_MIN_THINK_MS, MAX_MULTI_PV, MAX_NODES_TIME, MAX_SKILL_LEVEL, MAX_SLOW_MOVER, MIN_SLOW_MOVER, MAX_THREADS, MAX_VARIETY_MARGIN, MAX_VARIETY_PLIES};
+use crate::syzygy::SyzygyLoadError;
+#[cfg(feature = "tune")]
+use crate::tune;
+use crate::types::{EngineConfig, Score, SearchInfo, SearchLimits, SearchResult};
+
+/// What the engine reports back while and after searching
+#[derive(Debug, Clone)]
+pub enum EngineEvent {
+    /// One completed iteration of the current search
+    Info(SearchInfo),
+    /// The search finished or was stopped; `result.best_move` is None only without legal moves
+    BestMove(SearchResult),
+    /// A note about an option change, e.g. a network that failed to load
+    Message(String),
+}
+
+/// Why an option change was refused; the option keeps its previous value
+#[derive(Debug, Error)]
+pub enum OptionError {
+    #[error("unknown option {0}")]
+    Unknown(String),
+    #[error("invalid {name} value '{value}', expected {expected}")]
+    InvalidValue { name: String, value: String, expected: &'static str },
+    #[error("failed to load SyzygyPath: {0}")]
+    Syzygy(#[from] SyzygyLoadError),
+    #[error("failed to load EvalFile: {0}")]
+    EvalFile(String),
+    #[error("failed to load BookFile: {0}")]
+    Book(#[from] BookError),
+    #[error("failed to open Log File: {0}")]
+    LogFile(#[from] std::io::Error),
+}
+
+/// `info string` lines about the engine's state (see `Engine::report`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Report {
+    /// Diagnostics of the last search (`SearchStats::report`)
+    Stats,
+    /// The current position's legal moves in search order, with their static evals
+    Moves,
+    /// What the TT and the tablebases hold for the current position
+    Probe,
+}
+
+enum Command {
+    Position(Game),
+    NewGame,
+    SetOption(String, String),
+    Debug(bool),
+    Go(SearchLimits, Arc<AtomicBool>),
+    Report(Report, Sender<Vec<String>>),
+    /// Answered once everything queued before it is done
+    Sync(Sender<()>),
+}
+
+/// Receives engine events on the worker thread (see `Engine::with_handler`)
+pub type EventHandler = Box<dyn FnMut(EngineEvent) + Send>;
+
+/// The engine without the UCI text layer: a worker thread that owns the search state (TT, eval
+/// cache, options) and the current game. Commands are queued and handled in order; events
+/// arrive on `events()`, or go to a handler. Dropping the engine stops any search and joins
+/// the worker.
+pub struct Engine {
+    commands: Option<Sender<Command>>,
+    events: Receiver<EngineEvent>,
+    /// Stop flag of the most recent `go`
+    stop: Arc<AtomicBool>,
+    /// Searches sent and not yet answered with a `BestMove`
+    searches: Arc<AtomicUsize>,
+    /// The options as of the last command handled
+    config: Arc<Mutex<EngineConfig>>,
+    worker: Option<thread::JoinHandle<()>>,
+}
+
+impl Default for Engine {
+    fn default() -> Self {
+        Engine::new()
+    }
+}
+
+impl Engine {
+    pub fn new() -> Self {
+        let (event_tx, events) = mpsc::channel();
+        let mut engine = Engine::with_handler(move |event| {
+            let _ = event_tx.send(event);
+        });
+        engine.events = events;
+        engine
+    }
+
+    /// Call `handler` with each event, on the worker thread, instead of queueing it for
+    /// `events()` (which then stays empty)
+    pub fn with_handler(handler: impl FnMut(EngineEvent) + Send + 'static) -> Self {
+        Engine::spawn(Box::new(handler), true)
+    }
+
+    /// Like `with_handler`, but the search prints its iterations as UCI `info` lines (and
+    /// background messages as `info string` lines) itself; `handler` gets the rest
+    pub fn printing(handler: impl FnMut(EngineEvent) + Send + 'static) -> Self {
+        Engine::spawn(Box::new(handler), false)
+    }
+
+    fn spawn(handler: EventHandler, sinks: bool) -> Self {
+        let (commands, command_rx) = mpsc::channel();
+        let searches = Arc::new(AtomicUsize::new(0));
+        let config = Arc::new(Mutex::new(EngineConfig::default()));
+        let worker = {
+            let (searches, config) = (searches.clone(), config.clone());
+            thread::spawn(move || run_worker(command_rx, handler, sinks, searches, config))
+        };
+        Engine {
+            commands: Some(commands),
+            events: mpsc::channel().1,
+            stop: Arc::new(AtomicBool::new(false)),
+            searches,
+            config,
+            worker: Some(worker),
+        }
+    }
+
+    /// Search from `game`'s current position, with its earlier positions as repetition history
+    pub fn set_position(&self, game: Game) {
+        self.send(Command::Position(game));
+    }
+
+    /// Forget the game: back to the start (or odds) position, clears the eval cache, and the TT
+    /// in deterministic mode
+    pub fn new_game(&self) {
+        self.send(Command::NewGame);
+    }
+
+    /// Set an option by its UCI name (Hash, SyzygyPath, EvalFile, EvalMode, Skill Level, ...)
+    pub fn set_option(&self, name: &str, value: &str) {
+        self.send(Command::SetOption(name.to_string(), value.to_string()));
+    }
+
+    /// UCI `debug`: time manager decisions and refutations as `info string` lines, from the
+    /// next search on
+    pub fn set_debug(&self, on: bool) {
+        self.send(Command::Debug(on));
+    }
+
+    /// Start searching the current position. Info events follow, then exactly one `BestMove`;
+    /// an infinite search sends it only once stopped.
+    pub fn go(&mut self, limits: SearchLimits) {
+        self.stop = Arc::new(AtomicBool::new(false));
+        self.searches.fetch_add(1, Ordering::SeqCst);
+        self.send(Command::Go(limits, self.stop.clone()));
+    }
+
+    /// Stop the current search; its `BestMove` is still sent
+    pub fn stop(&self) {
+        self.stop.store(true, Ordering::SeqCst);
+    }
+
+    /// The current search's stop flag: setting it stops the search as `stop` does, from any
+    /// thread (a signal handler, say)
+    pub fn stop_flag(&self) -> Arc<AtomicBool> {
+        self.stop.clone()
+    }
+
+    /// Whether a search was started and hasn't sent its `BestMove` yet
+    pub fn searching(&self) -> bool {
+        self.searches.load(Ordering::SeqCst) > 0
+    }
+
+    /// The options as of the last command the worker handled; `wait` first for the ones
+    /// still queued
+    pub fn config(&self) -> EngineConfig {
+        self.config.lock().expect("engine worker panicked").clone()
+    }
+
+    /// Block until every command sent so far is handled, a pending search included, which an
+    /// infinite search never is without `stop`
+    pub fn wait(&self) {
+        let (done, finished) = mpsc::channel();
+        self.send(Command::Sync(done));
+        let _ = finished.recv();
+    }
+
+    /// Lines of `report`, once the commands sent before it (a search too) are handled
+    pub fn report(&self, report: Report) -> Vec<String> {
+        let (lines, received) = mpsc::channel();
+        self.send(Command::Report(report, lines));
+        received.recv().unwrap_or_default()
+    }
+
+    pub fn events(&self) -> &Receiver<EngineEvent> {
+        &self.events
+    }
+
+    /// Block until the pending search finishes, skipping other events
+    pub fn wait_best_move(&self) -> Option<SearchResult> {
+        self.events.iter().find_map(|event| match event {
+            EngineEvent::BestMove(result) => Some(result),
+            _ => None,
+        })
+    }
+
+    fn send(&self, command: Command) {
+        if let Some(commands) = &self.commands {
+            // The worker only exits once the sender is dropped
+            let _ = commands.send(command);
+        }
+    }
+}
+
+impl Drop for Engine {
+    fn drop(&mut self) {
+        self.stop();
+        self.commands = None;
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+}
+
+fn run_worker(commands: Receiver<Command>, handler: EventHandler, sinks: bool, searches: Arc<AtomicUsize>, shared_config: Arc<Mutex<EngineConfig>>) {
+    let mut game = Game::default();
+    let mut config = EngineConfig::default();
+    let mut state = SearchState::new();
+    // Shared with the search's info sink, which has to be 'static
+    let handler = Arc::new(Mutex::new(handler));
+    let emit = move |event: EngineEvent| (handler.lock().expect("event handler panicked"))(event);
+    if sinks {
+        let info_emit = emit.clone();
+        state.info_sink = Some(Box::new(move |info: &SearchInfo| info_emit(EngineEvent::Info(info.clone()))));
+        let message_emit = emit.clone();
+        state.message_sink = Some(Arc::new(move |message| message_emit(EngineEvent::Message(message))));
+    }
+
+    for command in commands {
+        match command {
+            Command::Position(g) => game = g,
+            Command::NewGame => {
+                game = Game::new(config.start_position());
+                state.new_game();
+            }
+            Command::SetOption(name, value) => {
+                match apply_option(&name, &value, &mut config, &mut state) {
+                    Ok(Some(message)) => emit(EngineEvent::Message(message)),
+                    Ok(None) => {}
+                    Err(e) => emit(EngineEvent::Message(e.to_string())),
+                }
+                *shared_config.lock().expect("engine config poisoned") = config.clone();
+            }
+            Command::Debug(on) => state.debug = on,
+            Command::Go(limits, stop) => {
+                let board = *game.board();
+                state.reset();
+                state.stop = stop;
+                state.position_history = game.repetition_history();
+                let result = search::search_guarded(&board, &mut state, &limits).unwrap_or_else(|failure| {
+                    emit(EngineEvent::Message(format!("search panicked: {}; playing a fallback move", failure.message)));
+                    failure.fallback
+                });
+                // An infinite search answers only after stop, however early it ran out of depth
+                // (or found a mate)
+                while limits.infinite && !state.is_stopped() {
+                    thread::sleep(Duration::from_millis(1));
+                }
+                emit(EngineEvent::BestMove(result));
+                searches.fetch_sub(1, Ordering::SeqCst);
+            }
+            Command::Report(report, lines) => {
+                let board = *game.board();
+                let _ = lines.send(match report {
+                    Report::Stats => state.stats.report(),
+                    Report::Moves => state.move_report(&board),
+                    Report::Probe => state.probe_report(&board),
+                });
+            }
+            Command::Sync(done) => {
+                let _ = done.send(());
+            }
+        }
+    }
+}
+
+/// The rating advantage and Book Variety the search uses, from UCI_RatingAdv and Book Variety
+/// and what UCI_Opponent says: without a rating advantage from the GUI, Opponent Contempt percent
+/// of the gap between Engine Rating and the opponent's rating goes to DynamicContempt, and a
+/// weaker opponent gets at least Opponent Book Variety
+fn apply_opponent(config: &EngineConfig, state: &mut SearchState) {
+    let opponent = config.opponent.as_ref();
+    state.rating_adv = match opponent.and_then(|o| o.rating_advantage(config.engine_rating)) {
+        Some(advantage) if config.rating_adv == 0 => advantage * config.opponent_contempt as i32 / 100,
+        _ => config.rating_adv,
+    };
+    state.book_variety = match opponent {
+        Some(o) if o.is_weaker(config.engine_rating) => config.book_variety.max(config.opponent_book_variety),
+        _ => config.book_variety,
+    };
+}
+
+/// `BookFile` / `Book Depth` (lowercase) set the first book, `BookFile2` / `Book Depth 2` the
+/// second and so on: whether the option is a file, and the book's index
+fn book_option(option: &str) -> Option<(bool, usize)> {
+    let (file, number) = match option.strip_prefix("bookfile") {
+        Some(number) => (true, number),
+        None => (false, option.strip_prefix("book depth")?),
+    };
+    let slot = match number.trim() {
+        "" => 0,
+        n => n.parse::<usize>().ok().filter(|n| (2..=MAX_BOOKS).contains(n))? - 1,
+    };
+    Some((file, slot))
+}
+
+/// Apply an option by its UCI name (case-insensitive) to the config and the search state.
+/// Returns a note worth showing the user, if any (e.g. a value that was clamped), or why the
+/// change was refused.
+pub fn apply_option(name: &str, value: &str, config: &mut EngineConfig, state: &mut SearchState) -> Result<Option<String>, OptionError> {
+    let invalid = |expected| OptionError::InvalidValue { name: name.to_string(), value: value.to_string(), expected };
+    match name.to_lowercase().as_str() {
+        "hash" => {
+            let mb = value.parse::<usize>().map_err(|_| invalid("a size in MB"))?;
+            config.hash_mb = mb.clamp(1, 4096);
+            state.resize_tt(config.hash_mb);
+            if config.hash_mb != mb {
+                return Ok(Some(format!("Hash {} MB is out of range, using {} MB", mb, config.hash_mb)));
+            }
+        }
+        "syzygypath" => {
+            if value.is_empty() || value == "<empty>" {
+                config.syzygy_path = None;
+                state.syzygy = None;
+            } else {
+                state.load_syzygy(value)?;
+                config.syzygy_path = Some(value.to_string());
+            }
+        }
+        "searchstats" => {
+            config.search_stats = value.eq_ignore_ascii_case("true");
+        }
+        "searchnullmove" | "searchlmr" | "searchaspiration" | "searchextensions" | "searchsee" | "searchfutility" => {
+            let on = value.eq_ignore_ascii_case("true");
+            let params = &mut config.search_params;
+            match name.to_lowercase().as_str() {
+                "searchnullmove" => params.null_move = on,
+                "searchlmr" => params.lmr = on,
+                "searchaspiration" => params.aspiration = on,
+                "searchextensions" => params.extensions = on,
+                "searchsee" => params.see_pruning = on,
+                _ => params.futility = on,
+            }
+            state.params = *params;
+        }
+        "searchbackend" => {
+            let backend = SearchBackend::parse(value).ok_or_else(|| invalid("alphabeta or mcts"))?;
+            config.search_backend = backend;
+            state.backend = backend;
+        }
+        "uci_chess960" => {
+            config.chess960 = value.eq_ignore_ascii_case("true");
+        }
+        "uci_showwdl" => {
+            config.show_wdl = value.eq_ignore_ascii_case("true");
+            state.show_wdl = config.show_wdl;
+        }
+        "uci_showrefutations" => {
+            config.show_refutations = value.eq_ignore_ascii_case("true");
+            state.show_refutations = config.show_refutations;
+        }
+        "multipv" => {
+            let lines = value.parse::<usize>().map_err(|_| invalid("a number of lines"))?;
+            config.multi_pv = lines.clamp(1, MAX_MULTI_PV);
+            state.multi_pv = config.multi_pv;
+            if config.multi_pv != lines {
+                return Ok(Some(format!("MultiPV {} is out of range, using {}", lines, config.multi_pv)));
+            }
+        }
+        "openingvariety" => {
+            let plies = value.parse::<u8>().map_err(|_| invalid("a number of plies"))?;
+            config.variety_plies = plies.min(MAX_VARIETY_PLIES);
+            state.variety_plies = config.variety_plies;
+        }
+        "openingvarietymargin" => {
+            let margin = value.parse::<Score>().map_err(|_| invalid("centipawns"))?;
+            config.variety_margin = margin.clamp(0, MAX_VARIETY_MARGIN);
+            state.variety_margin = config.variety_margin;
+        }
+        "contempt" => {
+            let contempt = value.parse::<Score>().map_err(|_| invalid("centipawns"))?;
+            config.contempt = contempt.clamp(-MAX_CONTEMPT, MAX_CONTEMPT);
+            state.contempt = config.contempt;
+            if config.contempt != contempt {
+                return Ok(Some(format!("Contempt {} is out of range, using {}", contempt, config.contempt)));
+            }
+        }
+        "dynamiccontempt" => {
+            config.dynamic_contempt = value.eq_ignore_ascii_case("true");
+            state.dynamic_contempt = config.dynamic_contempt;
+        }
+        "uci_ratingadv" => {
+            config.rating_adv = value.parse::<i32>().map_err(|_| invalid("a rating difference"))?;
+            apply_opponent(config, state);
+        }
+        "uci_opponent" => {
+            config.opponent = match value {
+                "" | "<empty>" => None,
+                _ => Some(value.parse().map_err(|_| invalid("<title> <rating> <computer|human> <name>"))?),
+            };
+            apply_opponent(config, state);
+        }
+        "engine rating" => {
+            let rating = value.parse::<u32>().map_err(|_| invalid("a rating"))?;
+            config.engine_rating = rating.min(MAX_RATING);
+            apply_opponent(config, state);
+        }
+        "opponent contempt" => {
+            let percent = value.parse::<u32>().map_err(|_| invalid("a percentage"))?;
+            config.opponent_contempt = percent.min(MAX_OPPONENT_CONTEMPT);
+            apply_opponent(config, state);
+        }
+        "opponent book variety" => {
+            let variety = value.parse::<u8>().map_err(|_| invalid("a value from 0 to 100"))?;
+            config.opponent_book_variety = variety.min(MAX_BOOK_VARIETY);
+            apply_opponent(config, state);
+        }
+        "minimum thinking time" => {
+            let ms = value.parse::<u64>().map_err(|_| invalid("milliseconds"))?;
+            config.min_think_ms = ms.min(MAX_MIN_THINK_MS);
+            state.min_think_ms = config.min_think_ms;
+            if config.min_think_ms != ms {
+                return Ok(Some(format!("Minimum Thinking Time {} ms is out of range, using {} ms", ms, config.min_think_ms)));
+            }
+        }
+        "slow mover" => {
+            let percent = value.parse::<u32>().map_err(|_| invalid("a percentage"))?;
+            config.slow_mover = percent.clamp(MIN_SLOW_MOVER, MAX_SLOW_MOVER);
+            state.slow_mover = config.slow_mover;
+            if config.slow_mover != percent {
+                return Ok(Some(format!("Slow Mover {} is out of range, using {}", percent, config.slow_mover)));
+            }
+        }
+        "nodestime" => {
+            let nodes = value.parse::<u64>().map_err(|_| invalid("nodes per millisecond"))?;
+            config.nodes_time = nodes.min(MAX_NODES_TIME);
+            state.nodes_time = config.nodes_time;
+        }
+        "outputformat" => {
+            config.json_output = match value.to_lowercase().as_str() {
+                "uci" => false,
+                "json" => true,
+                _ => return Err(invalid("uci or json")),
+            };
+            state.json_output = config.json_output;
+        }
+        "odds" => {
+            config.odds = match value.to_lowercase().as_str() {
+                "none" => None,
+                other => Some(other.parse().map_err(|_| invalid("none, pawn, knight, rook or queen"))?),
+            };
+        }
+        "oddsside" => {
+            config.odds_side = handicap::parse_side(value).ok_or_else(|| invalid("white or black"))?;
+        }
+        "skill level" => {
+            let level = value.parse::<u8>().map_err(|_| invalid("a level from 0 to 20"))?;
+            config.skill_level = level.min(MAX_SKILL_LEVEL);
+            state.skill_level = config.skill_level;
+        }
+        "evalmode" => {
+            let mode = EvalMode::parse(value).ok_or_else(|| invalid("nnue, hybrid or classical"))?;
+            config.eval_mode = mode;
+            state.tables.eval_cache.set_mode(mode);
+        }
+        "style" => {
+            let style = Style::parse(value).ok_or_else(|| invalid("solid, normal or aggressive"))?;
+            config.style = style;
+            state.tables.eval_cache.set_style(style);
+        }
+        "evalfile" => {
+            if value.is_empty() || value == "<empty>" {
+                config.eval_file = None;
+                state.tables.eval_cache.set_network(default_network());
+            } else {
+                let net = Network::load(value).map_err(OptionError::EvalFile)?;
+                let message = format!("loaded network {} ({} hidden)", value, net.hidden_size());
+                config.eval_file = Some(value.to_string());
+                state.tables.eval_cache.set_network(Some(Arc::new(net)));
+                return Ok(Some(message));
+            }
+        }
+        option if option.starts_with("bookfile") || option.starts_with("book depth") => {
+            let Some((file, slot)) = book_option(option) else {
+                return Err(OptionError::Unknown(name.to_string()));
+            };
+            if !file {
+                config.book_depths[slot] = value.parse::<u8>().map_err(|_| invalid("a number of plies up to 255"))?;
+                state.book_depths[slot] = config.book_depths[slot];
+            } else if value.is_empty() || value == "<empty>" {
+                config.book_files[slot] = None;
+                state.books[slot] = None;
+            } else {
+                let book = Book::load(value)?;
+                let message = format!("loaded book {} ({} entries)", value, book.len());
+                config.book_files[slot] = Some(value.to_string());
+                state.books[slot] = Some(book);
+                return Ok(Some(message));
+            }
+        }
+        "book variety" => {
+            let variety = value.parse::<u8>().map_err(|_| invalid("a value from 0 to 100"))?;
+            config.book_variety = variety.min(MAX_BOOK_VARIETY);
+            apply_opponent(config, state);
+        }
+        "deterministic" => {
+            config.deterministic = value.eq_ignore_ascii_case("true");
+            state.set_deterministic(config.deterministic);
+        }
+        "threads" => {
+            let threads = value.parse::<usize>().map_err(|_| invalid("a number of threads"))?;
+            config.threads = threads.clamp(1, MAX_THREADS);
+            state.threads = config.threads;
+            if config.threads != threads {
+                return Ok(Some(format!("Threads {} is out of range, using {}", threads, config.threads)));
+            }
+        }
+        "numa policy" => {
+            config.numa_policy = NumaPolicy::parse(value).ok_or_else(|| invalid("auto or none"))?;
+            numa::set_policy(config.numa_policy);
+        }
+        "log file" => {
+            let path = Some(value).filter(|v| !v.is_empty() && *v != "<empty>");
+            output::set_log_file(path)?;
+            config.log_file = path.map(str::to_string);
+        }
+        #[cfg(feature = "tune")]
+        other => match tune::find(other) {
+            Some(param) => param.set(value.parse::<i32>().map_err(|_| invalid("an integer"))?),
+            None => return Err(OptionError::Unknown(name.to_string())),
+        },
+        #[cfg(not(feature = "tune"))]
+        _ => return Err(OptionError::Unknown(name.to_string())),
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::search::{SearchParams, MAX_BOOK_DEPTH};
+    use std::str::FromStr;
+
+    #[test]
+    fn test_go_reports_info_then_best_move() {
+        let mut engine = Engine::new();
+        // Mate in one: Qh5xf7#
+        let game = Game::from_str("r1bqkb1r/pppp1ppp/2n2n2/4p2Q/2B1P3/8/PPPP1PPP/RNB1K1NR w KQkq - 4 4").unwrap();
+        engine.set_position(game.clone());
+        engine.go(SearchLimits::depth(3));
+
+        let mut infos = 0;
+        let result = loop {
+            match engine.events().recv().unwrap() {
+                EngineEvent::Info(info) => {
+                    assert_eq!(info.depth as usize, infos + 1);
+                    infos += 1;
+                }
+                EngineEvent::BestMove(result) => break result,
+                EngineEvent::Message(m) => panic!("unexpected message {}", m),
+            }
+        };
+        assert!(infos >= 1);
+        assert_eq!(result.best_move.unwrap().to_string(), "h5f7");
+        assert!(game.board().legal(result.best_move.unwrap()));
+    }
+
+    #[test]
+    fn test_stop_and_options() {
+        let mut engine = Engine::new();
+        engine.set_option("Hash", "8");
+        engine.set_option("EvalFile", "/nonexistent.nnue");
+        engine.go(SearchLimits { infinite: true, ..Default::default() });
+        engine.stop();
+        assert!(engine.wait_best_move().is_some());
+
+        // The failed load was reported before the search result
+        let mut engine = Engine::new();
+        engine.set_option("EvalFile", "/nonexistent.nnue");
+        engine.go(SearchLimits::depth(1));
+        assert!(matches!(engine.events().recv().unwrap(), EngineEvent::Message(m) if m.contains("failed to load")));
+        assert!(engine.wait_best_move().is_some());
+    }
+
+    #[test]
+    fn test_wait_config_and_reports() {
+        let mut engine = Engine::printing(|_| {});
+        engine.set_option("Hash", "8");
+        engine.set_option("SearchStats", "true");
+        engine.wait();
+        let config = engine.config();
+        assert_eq!((config.hash_mb, config.search_stats), (8, true));
+
+        engine.go(SearchLimits { infinite: true, ..Default::default() });
+        assert!(engine.searching());
+        engine.stop();
+        // Queued behind the search, so the stats are that search's
+        let stats = engine.report(Report::Stats);
+        assert!(!engine.searching());
+        assert!(stats[0].starts_with("info string stats"));
+        assert!(engine.report(Report::Moves)[0].starts_with("info string moves 20 legal"));
+        assert!(engine.report(Report::Probe)[0].starts_with("info string probe tt"));
+    }
+
+    #[test]
+    fn test_option_diagnostics() {
+        let mut config = EngineConfig::default();
+        let mut state = SearchState::new();
+        let mut apply = |name: &str, value: &str| apply_option(name, value, &mut config, &mut state);
+
+        assert!(apply("Hash", "lots").unwrap_err().to_string().contains("invalid Hash"));
+        assert!(apply("Hash", "0").unwrap().unwrap().contains("using 1 MB"));
+        assert!(apply("Hash", "16").unwrap().is_none());
+        let err = apply("SyzygyPath", "/nonexistent/tb").unwrap_err();
+        assert!(matches!(err, OptionError::Syzygy(SyzygyLoadError::NotADirectory(_))), "{}", err);
+        assert!(matches!(apply("EvalMode", "magic"), Err(OptionError::InvalidValue { expected: "nnue, hybrid or classical", .. })));
+        assert!(matches!(apply("Aggression", "10"), Err(OptionError::Unknown(name)) if name == "Aggression"));
+        assert!(apply("Contempt", "250").unwrap().unwrap().contains("using 100"));
+        assert!(apply("UCI_RatingAdv", "-150").unwrap().is_none());
+        assert!(apply("Minimum Thinking Time", "-5").is_err());
+        assert!(apply("Minimum Thinking Time", "9000").unwrap().unwrap().contains("using 5000 ms"));
+        assert!(apply("Slow Mover", "5").unwrap().unwrap().contains("using 10"));
+        assert!(apply("Slow Mover", "150").unwrap().is_none());
+        assert!(apply("nodestime", "fast").is_err());
+        assert!(apply("SearchLMR", "false").unwrap().is_none());
+        assert!(apply("searchextensions", "FALSE").unwrap().is_none());
+        assert!(apply("SearchSEE", "false").unwrap().is_none());
+        assert!(apply("SearchFutility", "false").unwrap().is_none());
+        assert!(apply("SearchBackend", "MCTS").unwrap().is_none());
+        assert!(apply("Style", "Aggressive").unwrap().is_none());
+        assert!(matches!(apply("Style", "reckless"), Err(OptionError::InvalidValue { expected: "solid, normal or aggressive", .. })));
+        assert!(matches!(apply("SearchBackend", "random"), Err(OptionError::InvalidValue { expected: "alphabeta or mcts", .. })));
+        assert!(apply("nodestime", "1000000").unwrap().is_none());
+        assert!(matches!(apply("BookFile", "/nonexistent/book.bin"), Err(OptionError::Book(BookError::Read { .. }))));
+        assert!(apply("BookFile", "<empty>").unwrap().is_none());
+        assert!(apply("Book Depth", "300").is_err());
+        assert!(apply("Book Depth", "12").unwrap().is_none());
+        assert!(apply("Book Depth 3", "4").unwrap().is_none());
+        assert!(matches!(apply("Book Depth 9", "4"), Err(OptionError::Unknown(_))));
+        assert!(matches!(apply("BookFile2", "/nonexistent/book.bin"), Err(OptionError::Book(_))));
+        assert!(apply("BookFile2", "<empty>").unwrap().is_none());
+        assert!(apply("book variety", "150").unwrap().is_none());
+        assert!(apply("OutputFormat", "xml").unwrap_err().to_string().contains("expected uci or json"));
+        assert!(apply("OutputFormat", "JSON").unwrap().is_none());
+        assert!(apply("OpeningVariety", "8").unwrap().is_none());
+        assert!(apply("OpeningVarietyMargin", "many").is_err());
+        assert!(apply("OpeningVarietyMargin", "500").unwrap().is_none());
+        assert!(apply("Threads", "0").unwrap().unwrap().contains("using 1"));
+        assert!(apply("Threads", "4").unwrap().is_none());
+        // Valid policies are process-wide, so the numa tests try those
+        assert!(matches!(apply("NUMA Policy", "interleave"), Err(OptionError::InvalidValue { expected: "auto or none", .. })));
+        assert!(apply("Odds", "bishop").unwrap_err().to_string().contains("expected none, pawn"));
+        assert!(apply("Odds", "Knight").unwrap().is_none());
+        assert!(apply("OddsSide", "black").unwrap().is_none());
+        assert_eq!(config.start_position().to_string(), "r1bqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+        assert_eq!((config.hash_mb, config.syzygy_path, config.book_files), (16, None, Default::default()));
+        assert!(config.json_output && state.json_output);
+        assert_eq!((state.variety_plies, state.variety_margin), (8, MAX_VARIETY_MARGIN));
+        assert_eq!((state.contempt, state.rating_adv), (MAX_CONTEMPT, -150));
+        assert_eq!((state.book_depths, state.book_variety), ([12, MAX_BOOK_DEPTH, 4], MAX_BOOK_VARIETY));
+        assert_eq!((state.min_think_ms, state.slow_mover, state.nodes_time), (MAX_MIN_THINK_MS, 150, MAX_NODES_TIME));
+        assert_eq!(state.params, SearchParams { lmr: false, extensions: false, see_pruning: false, futility: false, ..Default::default() });
+        assert_eq!((config.threads, state.threads), (4, 4));
+    }
+
+    #[test]
+    fn test_failed_syzygy_path_keeps_tables() {
+        use crate::board::Board;
+        use crate::syzygy::tests::{table_dir, write_wdl, LOSS, WIN};
+
+        let dir = table_dir("engine-option");
+        write_wdl(&dir, "KRvK", [WIN, LOSS]);
+        let path = dir.to_str().unwrap().to_string();
+        let mut config = EngineConfig::default();
+        let mut state = SearchState::new();
+        state.silent = true;
+        assert!(apply_option("SyzygyPath", &path, &mut config, &mut state).unwrap().is_none());
+
+        // A mistyped path is reported, and the tables loaded before stay in use
+        let err = apply_option("SyzygyPath", "/nonexistent/tb", &mut config, &mut state).unwrap_err();
+        assert!(matches!(err, OptionError::Syzygy(SyzygyLoadError::NotADirectory(_))), "{}", err);
+        assert_eq!(config.syzygy_path.as_deref(), Some(path.as_str()));
+        let prober = state.syzygy.as_ref().expect("earlier tables kept");
+        while prober.get().is_none() {
+            thread::sleep(Duration::from_millis(1));
+        }
+        let board = Board::from_str("4k3/8/8/8/8/8/8/R3K3 w - - 0 1").unwrap();
+        assert!(prober.get().unwrap().probe_wdl(&board).is_some());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_opponent_options() {
+        let mut config = EngineConfig::default();
+        let mut state = SearchState::new();
+        let mut apply = |name: &str, value: &str| {
+            apply_option(name, value, &mut config, &mut state).unwrap();
+            (state.rating_adv, state.book_variety)
+        };
+
+        assert_eq!(apply("Book Variety", "20"), (0, 20));
+        // 400 Elo below the default Engine Rating: all of it goes to contempt, and more variety
+        assert_eq!(apply("UCI_Opponent", "none 2400 human Joe Bloggs"), (400, 75));
+        assert_eq!(apply("Opponent Contempt", "50"), (200, 75));
+        assert_eq!(apply("Opponent Book Variety", "10"), (200, 20));
+        assert_eq!(apply("Opponent Book Variety", "90"), (200, 90));
+        // The GUI's own rating advantage wins
+        assert_eq!(apply("UCI_RatingAdv", "-100"), (-100, 90));
+        assert_eq!(apply("UCI_RatingAdv", "0"), (200, 90));
+        // A stronger engine: draws are welcome, and the book stays as it is
+        assert_eq!(apply("UCI_Opponent", "none 3000 computer Stockfish"), (-100, 20));
+        assert_eq!(apply("Engine Rating", "5000"), (500, 90));
+        assert_eq!(apply("UCI_Opponent", "<empty>"), (0, 20));
+        assert!(apply_option("UCI_Opponent", "GM lots human Someone", &mut config, &mut state).is_err());
+        assert_eq!((config.opponent, config.engine_rating), (None, MAX_RATING));
+    }
+}
+
+// Engine is the UCI loop minus the text: the same SearchState lives on a worker thread for the
+// engine's whole life, so the TT and eval cache persist between searches without being moved
+// around. Commands go through a channel and are handled in order; `stop` is the one exception,
+// since the worker is busy searching when it matters, so it flips the search's atomic flag
+// directly. Each `go` gets a fresh flag, which means a stop sent just after a `go` can't be
+// cleared by the worker picking the search up late.
+
+// `apply_option` separates refusals (`OptionError`, the old value stays) from notes about a
+// change that did happen, such as a clamped Hash size or the network that was loaded. The worker
+// and the UCI loop print both the same way; library users can tell them apart.
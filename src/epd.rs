@@ -0,0 +1,226 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::board::{Board, ChessMove};
+use crate::pgn::{move_to_san, parse_san};
+use crate::types::Score;
+
+/// An EPD operation: an opcode such as `bm` or `id` and its operands, without quotes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Operation {
+    pub opcode: String,
+    pub operands: Vec<String>,
+}
+
+/// One line of an EPD file: a position and its operations, in file order
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EpdRecord {
+    pub board: Board,
+    pub operations: Vec<Operation>,
+}
+
+impl EpdRecord {
+    pub fn new(board: Board) -> Self {
+        EpdRecord { board, operations: Vec::new() }
+    }
+
+    /// The operands of `opcode`, if the record has it
+    pub fn operands(&self, opcode: &str) -> Option<&[String]> {
+        self.operations.iter().find(|op| op.opcode == opcode).map(|op| op.operands.as_slice())
+    }
+
+    /// Replace the operands of `opcode`, or append it
+    pub fn set(&mut self, opcode: &str, operands: Vec<String>) {
+        match self.operations.iter_mut().find(|op| op.opcode == opcode) {
+            Some(op) => op.operands = operands,
+            None => self.operations.push(Operation { opcode: opcode.to_string(), operands }),
+        }
+    }
+
+    pub fn remove(&mut self, opcode: &str) {
+        self.operations.retain(|op| op.opcode != opcode);
+    }
+
+    /// `bm`: the best moves, resolved from SAN (empty without a `bm`)
+    pub fn best_moves(&self) -> Result<Vec<ChessMove>, String> {
+        self.moves("bm")
+    }
+
+    /// `am`: the moves to avoid, resolved from SAN (empty without an `am`)
+    pub fn avoid_moves(&self) -> Result<Vec<ChessMove>, String> {
+        self.moves("am")
+    }
+
+    /// `ce`: centipawn evaluation from the side to move's view
+    pub fn centipawn_eval(&self) -> Option<Score> {
+        self.number("ce")
+    }
+
+    /// `acd`: analysis depth in plies
+    pub fn analysis_depth(&self) -> Option<u32> {
+        self.number("acd")
+    }
+
+    /// `dm`: direct mate in this many moves
+    pub fn direct_mate(&self) -> Option<u32> {
+        self.number("dm")
+    }
+
+    pub fn id(&self) -> Option<&str> {
+        self.operands("id")?.first().map(String::as_str)
+    }
+
+    /// Write `moves` as the SAN operands of `opcode` (`bm`, `am`, `pv`, ...)
+    pub fn set_moves(&mut self, opcode: &str, moves: &[ChessMove]) {
+        let operands = moves.iter().map(|&mv| move_to_san(&self.board, mv)).collect();
+        self.set(opcode, operands);
+    }
+
+    fn moves(&self, opcode: &str) -> Result<Vec<ChessMove>, String> {
+        let operands = self.operands(opcode).unwrap_or_default();
+        operands.iter().map(|san| parse_san(&self.board, san)).collect()
+    }
+
+    fn number<T: FromStr>(&self, opcode: &str) -> Option<T> {
+        self.operands(opcode)?.first()?.parse().ok()
+    }
+}
+
+/// The four position fields, optionally followed by the FEN move counters, then operations
+/// `opcode operand ...;` with string operands in double quotes. The final `;` may be missing,
+/// and `hmvc` / `fmvn` operations set the move counters.
+impl FromStr for EpdRecord {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        let mut rest = s.trim();
+        let mut fields = Vec::with_capacity(6);
+        while fields.len() < 6 {
+            let (word, tail) = split_word(rest);
+            let counter = fields.len() >= 4;
+            if word.is_empty() || (counter && word.parse::<u16>().is_err()) {
+                break;
+            }
+            fields.push(word);
+            rest = tail;
+        }
+        if fields.len() < 4 {
+            return Err(format!("EPD needs four position fields: {}", s));
+        }
+
+        let mut operations: Vec<Operation> = Vec::new();
+        loop {
+            rest = rest.trim_start();
+            if rest.is_empty() {
+                break;
+            }
+            let end = rest.find(|ch: char| ch.is_whitespace() || ch == ';').unwrap_or(rest.len());
+            let opcode = &rest[..end];
+            if !opcode.starts_with(|ch: char| ch.is_ascii_alphabetic()) || !opcode.chars().all(|ch| ch.is_ascii_alphanumeric() || ch == '_') {
+                return Err(format!("invalid EPD opcode {:?}", opcode));
+            }
+            rest = &rest[end..];
+
+            let mut operands = Vec::new();
+            loop {
+                rest = rest.trim_start();
+                if let Some(tail) = rest.strip_prefix(';') {
+                    rest = tail;
+                    break;
+                } else if rest.is_empty() {
+                    break;
+                } else if let Some(quoted) = rest.strip_prefix('"') {
+                    let close = quoted.find('"').ok_or_else(|| format!("unterminated string in {} operation", opcode))?;
+                    operands.push(quoted[..close].to_string());
+                    rest = &quoted[close + 1..];
+                } else {
+                    let end = rest.find(|ch: char| ch.is_whitespace() || ch == ';').unwrap_or(rest.len());
+                    operands.push(rest[..end].to_string());
+                    rest = &rest[end..];
+                }
+            }
+            operations.push(Operation { opcode: opcode.to_string(), operands });
+        }
+
+        let mut record = EpdRecord { board: Board::default(), operations };
+        let halfmove = record.number::<u16>("hmvc").or_else(|| fields.get(4).and_then(|f| f.parse().ok())).unwrap_or(0);
+        let fullmove = record.number::<u16>("fmvn").or_else(|| fields.get(5).and_then(|f| f.parse().ok())).unwrap_or(1);
+        record.board = Board::from_fen_lenient(&format!("{} {} {}", fields[..4].join(" "), halfmove, fullmove))?;
+        Ok(record)
+    }
+}
+
+/// The position's four fields and every operation, each ending in `;`. Operands of `id` and
+/// the comments `c0`..`c9` are always quoted, others only when they need it.
+impl fmt::Display for EpdRecord {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let fen = self.board.to_string();
+        let position: Vec<&str> = fen.split_whitespace().take(4).collect();
+        write!(f, "{}", position.join(" "))?;
+        for op in &self.operations {
+            write!(f, " {}", op.opcode)?;
+            let string_opcode = op.opcode == "id" || (op.opcode.len() == 2 && op.opcode.starts_with('c') && op.opcode.ends_with(|ch: char| ch.is_ascii_digit()));
+            for operand in &op.operands {
+                if string_opcode || operand.is_empty() || operand.contains(|ch: char| ch.is_whitespace() || ch == ';' || ch == '"') {
+                    write!(f, " \"{}\"", operand)?;
+                } else {
+                    write!(f, " {}", operand)?;
+                }
+            }
+            write!(f, ";")?;
+        }
+        Ok(())
+    }
+}
+
+/// The first whitespace-separated word of `s` and the text after it
+fn split_word(s: &str) -> (&str, &str) {
+    let s = s.trim_start();
+    let end = s.find(char::is_whitespace).unwrap_or(s.len());
+    s.split_at(end)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_write() {
+        let line = "1k1r4/pp1b1R2/3q2pp/4p3/2B5/4Q3/PPP2B2/2K5 b - - bm Qd1+; id \"BK.01\";";
+        let record: EpdRecord = line.parse().unwrap();
+        assert_eq!(record.board, Board::from_fen_lenient("1k1r4/pp1b1R2/3q2pp/4p3/2B5/4Q3/PPP2B2/2K5 b - -").unwrap());
+        assert_eq!(record.operands("bm"), Some(&["Qd1+".to_string()][..]));
+        assert_eq!(record.best_moves().unwrap(), vec![ChessMove::from_uci("d6d1").unwrap()]);
+        assert_eq!(record.avoid_moves().unwrap(), Vec::new());
+        assert_eq!(record.id(), Some("BK.01"));
+        assert_eq!(record.to_string(), line);
+
+        let mut record: EpdRecord = "4k3/8/8/8/8/8/4P3/4K3 w - - 5 40 c0 \"two words\"; dm 3;ce -12; acd 14".parse().unwrap();
+        assert_eq!(record.board.to_string(), "4k3/8/8/8/8/8/4P3/4K3 w - - 5 40");
+        assert_eq!((record.direct_mate(), record.centipawn_eval(), record.analysis_depth()), (Some(3), Some(-12), Some(14)));
+        record.set_moves("am", &[ChessMove::from_uci("e2e4").unwrap(), ChessMove::from_uci("e1d1").unwrap()]);
+        record.set("ce", vec!["7".to_string()]);
+        record.remove("dm");
+        assert_eq!(record.to_string(), "4k3/8/8/8/8/8/4P3/4K3 w - - c0 \"two words\"; ce 7; acd 14; am e4 Kd1;");
+        assert_eq!(record.to_string().parse::<EpdRecord>().unwrap().operations, record.operations);
+
+        let record: EpdRecord = "4k3/8/8/8/8/8/4P3/4K3 b - - hmvc 9; fmvn 12;".parse().unwrap();
+        assert_eq!(record.board.to_string(), "4k3/8/8/8/8/8/4P3/4K3 b - - 9 12");
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!("4k3/8/8/8/8/8/4P3/4K3 w -".parse::<EpdRecord>().is_err());
+        assert!("4k3/8/8/8/8/8/4P3/4K3 w - - id \"open".parse::<EpdRecord>().is_err());
+        assert!("4k3/8/8/8/8/8/4P3/4K3 w - - 7up 1;".parse::<EpdRecord>().is_err());
+        let record: EpdRecord = "4k3/8/8/8/8/8/4P3/4K3 w - - bm Qd1;".parse().unwrap();
+        assert!(record.best_moves().is_err());
+    }
+}
+
+// EPD is the format test suites, puzzle files and tuning sets share, so records keep every
+// operation (in order, unknown opcodes included) and only interpret the standard ones on request:
+// a tool that rewrites one operand writes the rest back as it found them. Moves stay SAN text
+// until asked for, since resolving them needs the position and a malformed `bm` shouldn't stop a
+// file of positions from loading. Move counters are not part of EPD, so they are read when a
+// line is really a FEN with operations or carries `hmvc` / `fmvn`, and never written.
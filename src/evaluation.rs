@@ -1,6 +1,19 @@
-use crate::board::{Board, Color, Piece, ALL_SQUARES};
+use crate::board::{
+    bishop_attacks, king_attacks, knight_attacks, pawn_attacks, queen_attacks, rook_attacks, BitBoard, Board,
+    Color, Piece, EMPTY,
+};
 
-use crate::pst::{self, EG_TABLE, MG_TABLE, MG_PIECE_VALUE, EG_PIECE_VALUE, PHASE_WEIGHT, TOTAL_PHASE};
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+
+use crate::endgame;
+use crate::nnue::Network;
+use crate::pst::{
+    self, CONNECTED_PAWN_EG, CONNECTED_PAWN_MG, KING_DANGER_DIVISOR, KING_DANGER_MAX,
+    KING_ZONE_ATTACK_WEIGHT, MG_PIECE_VALUE, MOBILITY_BASE, MOBILITY_EG, MOBILITY_MG,
+    PAWN_SHELTER_MG, PAWN_STORM_MG, PHALANX_PAWN_EG, PHALANX_PAWN_MG, ROOK_SEVENTH_EG, ROOK_SEVENTH_MG, TOTAL_PHASE,
+    VIRTUAL_QUEEN_WEIGHT,
+};
 use crate::types::Score;
 
 /// Maps a Piece to our PST index (0-5)
@@ -15,70 +28,655 @@ fn piece_index(piece: Piece) -> usize {
     }
 }
 
-/// Converts a Square (A1=0, H8=63) to our PST index
-/// PeSTO tables are stored with a8=0, h1=63 (rank 8 first, rank 1 last)
-/// For White: need to flip rank → index = sq ^ 56 (maps rank 1→8, 2→7, etc.)
-/// For Black: use square index directly (already mirrors White's perspective)
-fn pst_index_white(sq: crate::board::Square) -> usize {
-    sq.to_index() ^ 56
+/// Evaluates board position using PeSTO tapered evaluation
+/// Returns score from perspective of side to move
+pub fn evaluate(board: &Board) -> Score {
+    evaluate_inner(board, None).0
 }
 
-fn pst_index_black(sq: crate::board::Square) -> usize {
-    sq.to_index()
+/// Lazy evaluation: if everything but mobility already puts the score outside the (alpha, beta)
+/// window by more than mobility could make up, return that bound without computing mobility.
+/// Returns (score, exact); a non-exact score is only good as a bound for this window.
+pub fn evaluate_lazy(board: &Board, alpha: Score, beta: Score) -> (Score, bool) {
+    evaluate_inner(board, Some((alpha, beta)))
 }
 
-/// Evaluates board position using PeSTO tapered evaluation
-/// Returns score from perspective of side to move
-pub fn evaluate(board: &Board) -> Score {
-    let mut mg_score: [Score; 2] = [0, 0]; // [white, black]
-    let mut eg_score: [Score; 2] = [0, 0];
-    let mut phase: i32 = 0;
-
-    for sq in ALL_SQUARES {
-        if let Some(piece) = board.piece_on(sq) {
-            let color = board.color_on(sq).unwrap();
-            let idx = piece_index(piece);
-            let side = color.to_index(); // White=0, Black=1
-
-            // Material value
-            mg_score[side] += MG_PIECE_VALUE[idx];
-            eg_score[side] += EG_PIECE_VALUE[idx];
-
-            // Positional value from PST
-            let pst_idx = if color == Color::White {
-                pst_index_white(sq)
-            } else {
-                pst_index_black(sq)
+fn evaluate_inner(board: &Board, window: Option<(Score, Score)>) -> (Score, bool) {
+    let stm = |score: Score| if board.side_to_move() == Color::White { score } else { -score };
+
+    // Known endgames override the generic evaluation
+    if let Some(score) = endgame::evaluate(board) {
+        return (stm(score), true);
+    }
+
+    // Material + PST come from the board's incremental accumulators (white-relative)
+    let (psq_mg, psq_eg) = board.psq_score();
+    let phase = game_phase(board);
+
+    let mut mg_score: [Score; 2] = [psq_mg, 0]; // [white, black]
+    let mut eg_score: [Score; 2] = [psq_eg + endgame::pawn_race(board), 0];
+
+    // Rooks on the seventh, pawn structure, pawn shelter and storms, king danger
+    for color in [Color::White, Color::Black] {
+        let (mg, eg) = rook_on_seventh(board, color);
+        mg_score[color.to_index()] += mg;
+        eg_score[color.to_index()] += eg;
+
+        let (mg, eg) = pawn_structure(board, color);
+        mg_score[color.to_index()] += mg;
+        eg_score[color.to_index()] += eg;
+
+        mg_score[color.to_index()] += pawn_shelter(board, color);
+        mg_score[color.to_index()] += pawn_storm(board, color);
+        mg_score[color.to_index()] -= king_danger_penalty(king_danger(board, color));
+    }
+    eg_score[0] += endgame::mop_up(board);
+    let scaled = |mg: Score, eg: Score| {
+        let score = taper(mg, eg, phase);
+        score * scale_factor(board, score) / SCALE_NORMAL
+    };
+
+    // Mobility is all that's left, and no more than `mobility_bound` either way. The scaled
+    // score only grows with the unscaled one, so the bounds hold after scaling too.
+    if let Some((alpha, beta)) = window {
+        let (mg, eg) = (mg_score[0] - mg_score[1], eg_score[0] - eg_score[1]);
+        let (bound_mg, bound_eg) = mobility_bound(board);
+        let (low, high) = (scaled(mg - bound_mg, eg - bound_eg), scaled(mg + bound_mg, eg + bound_eg));
+        let (low, high) = if board.side_to_move() == Color::White { (low, high) } else { (-high, -low) };
+        if low >= beta {
+            return (low, false);
+        }
+        if high <= alpha {
+            return (high, false);
+        }
+    }
+
+    // Mobility for minor and major pieces
+    for color in [Color::White, Color::Black] {
+        let (mg, eg) = mobility(board, color);
+        mg_score[color.to_index()] += mg;
+        eg_score[color.to_index()] += eg;
+    }
+
+    // Compute scores relative to white, scaling down drawish material constellations, and
+    // return from side-to-move perspective
+    (stm(scaled(mg_score[0] - mg_score[1], eg_score[0] - eg_score[1])), true)
+}
+
+/// Game phase, 0 (bare kings and pawns) ..= TOTAL_PHASE (all pieces on the board).
+/// Promotions can push the raw material count higher, so it is clamped.
+pub fn game_phase(board: &Board) -> i32 {
+    board.material_phase().min(TOTAL_PHASE)
+}
+
+/// Game phase normalized to 0.0 (endgame) ..= 1.0 (opening)
+pub fn game_phase_normalized(board: &Board) -> f32 {
+    game_phase(board) as f32 / TOTAL_PHASE as f32
+}
+
+/// Tapered eval: blend mg and eg based on phase
+/// phase = TOTAL_PHASE means full midgame, phase = 0 means full endgame
+#[inline]
+fn taper(mg: Score, eg: Score, phase: i32) -> Score {
+    (mg * phase + eg * (TOTAL_PHASE - phase)) / TOTAL_PHASE
+}
+
+/// Squares attacked by pawns of given color
+fn pawn_attack_span(board: &Board, color: Color) -> BitBoard {
+    let mut attacked = EMPTY;
+    for sq in (board.pieces(Piece::Pawn) & board.color_combined(color)).iter() {
+        attacked |= pawn_attacks(color, sq);
+    }
+    attacked
+}
+
+/// Mobility (mg, eg) for color: squares each knight/bishop/rook/queen attacks that are
+/// neither occupied by own pieces nor covered by enemy pawns
+fn mobility(board: &Board, color: Color) -> (Score, Score) {
+    let occupied = board.combined();
+    let safe = !(board.color_combined(color) | pawn_attack_span(board, !color));
+    let ours = board.color_combined(color);
+
+    let mut mg = 0;
+    let mut eg = 0;
+    for piece in [Piece::Knight, Piece::Bishop, Piece::Rook, Piece::Queen] {
+        let idx = piece_index(piece);
+        for sq in (board.pieces(piece) & ours).iter() {
+            let attacks = match piece {
+                Piece::Knight => knight_attacks(sq),
+                Piece::Bishop => bishop_attacks(sq, occupied),
+                Piece::Rook => rook_attacks(sq, occupied),
+                _ => queen_attacks(sq, occupied),
             };
+            let count = (attacks & safe).popcnt() as i32 - MOBILITY_BASE[idx];
+            mg += count * MOBILITY_MG[idx];
+            eg += count * MOBILITY_EG[idx];
+        }
+    }
+    (mg, eg)
+}
+
+/// Safe squares each piece type can reach at most (knight, bishop, rook, queen; pawns and
+/// kings have no mobility term)
+const MOBILITY_MAX: [i32; 6] = [0, 8, 13, 14, 27, 0];
+
+/// Largest (mg, eg) swing the mobility of both sides' pieces can add to the white-relative score
+/// either way: every piece at its fewest or most safe squares
+fn mobility_bound(board: &Board) -> (Score, Score) {
+    let mut mg = 0;
+    let mut eg = 0;
+    for piece in [Piece::Knight, Piece::Bishop, Piece::Rook, Piece::Queen] {
+        let idx = piece_index(piece);
+        let count = board.pieces(piece).popcnt() as i32;
+        let swing = MOBILITY_BASE[idx].max(MOBILITY_MAX[idx] - MOBILITY_BASE[idx]);
+        mg += count * swing * MOBILITY_MG[idx];
+        eg += count * swing * MOBILITY_EG[idx];
+    }
+    (mg, eg)
+}
+
+/// Bitboard of all squares on rank index r (0 = rank 1)
+#[inline]
+fn rank_bb(r: usize) -> BitBoard {
+    BitBoard(0xFFu64 << (8 * r))
+}
+
+/// Bonus for rooks on the opponent's second rank, awarded only when it bites: the enemy king
+/// is confined to its back rank or there are enemy pawns to attack on that rank
+fn rook_on_seventh(board: &Board, color: Color) -> (Score, Score) {
+    let (seventh, eighth) = if color == Color::White { (6, 7) } else { (1, 0) };
+    let them = board.color_combined(!color);
+    let rooks = board.pieces(Piece::Rook) & board.color_combined(color) & rank_bb(seventh);
+    if rooks.is_empty() {
+        return (0, 0);
+    }
+
+    let king_on_back = !(board.pieces(Piece::King) & them & rank_bb(eighth)).is_empty();
+    let pawns_on_seventh = !(board.pieces(Piece::Pawn) & them & rank_bb(seventh)).is_empty();
+    if !king_on_back && !pawns_on_seventh {
+        return (0, 0);
+    }
+
+    let n = rooks.popcnt() as Score;
+    (n * ROOK_SEVENTH_MG, n * ROOK_SEVENTH_EG)
+}
+
+const FILE_A: BitBoard = BitBoard(0x0101_0101_0101_0101);
+const FILE_H: BitBoard = BitBoard(0x8080_8080_8080_8080);
 
-            mg_score[side] += MG_TABLE[idx][pst_idx];
-            eg_score[side] += EG_TABLE[idx][pst_idx];
+/// Pawn structure (mg, eg) for color: connected pawns (defended by a friendly pawn) and
+/// phalanx pawns (a friendly pawn beside them on the same rank), scaled by relative rank
+fn pawn_structure(board: &Board, color: Color) -> (Score, Score) {
+    let pawns = board.pieces(Piece::Pawn) & board.color_combined(color);
+    let supported = pawns & pawn_attack_span(board, color);
+    let beside = BitBoard((pawns & !FILE_H).0 << 1) | BitBoard((pawns & !FILE_A).0 >> 1);
+    let phalanx = pawns & beside;
 
-            // Accumulate game phase
-            phase += PHASE_WEIGHT[idx];
+    let mut mg = 0;
+    let mut eg = 0;
+    for sq in (supported | phalanx).iter() {
+        let rank = sq.rank().to_index();
+        let rel = if color == Color::White { rank } else { 7 - rank };
+        let bb = BitBoard::from_square(sq);
+        if !(supported & bb).is_empty() {
+            mg += CONNECTED_PAWN_MG[rel];
+            eg += CONNECTED_PAWN_EG[rel];
         }
+        if !(phalanx & bb).is_empty() {
+            mg += PHALANX_PAWN_MG[rel];
+            eg += PHALANX_PAWN_EG[rel];
+        }
+    }
+    (mg, eg)
+}
+
+/// Midgame pawn shelter for color's king while it is on its first two ranks: each of color's pawns
+/// on the king's wing (files a-c with the king on a-d, f-h with it on e-h) scores by relative rank
+fn pawn_shelter(board: &Board, color: Color) -> Score {
+    let king = board.king_square(color);
+    let king_rank = king.rank().to_index();
+    if (if color == Color::White { king_rank } else { 7 - king_rank }) >= 2 {
+        return 0; // no shelter left to keep
+    }
+    // Files f-h or a-c on every rank
+    let wing = if king.file().to_index() >= 4 { FILE_A.0 * 0xE0 } else { FILE_A.0 * 0x07 };
+    let ours = board.pieces(Piece::Pawn) & board.color_combined(color) & BitBoard(wing);
+    ours.iter()
+        .map(|sq| {
+            let rank = sq.rank().to_index();
+            PAWN_SHELTER_MG[if color == Color::White { rank } else { 7 - rank }]
+        })
+        .sum()
+}
+
+/// Midgame bonus for color's pawns storming the enemy king: when that king has castled to a wing
+/// (files a-c or f-h), the most advanced pawn on each of the king's file and its neighbours scores
+/// by relative rank. Pawns already blocked by an enemy pawn count half, since they can't open a file.
+fn pawn_storm(board: &Board, color: Color) -> Score {
+    let them = board.color_combined(!color);
+    let king_file = board.king_square(!color).file().to_index();
+    if (3..=4).contains(&king_file) {
+        return 0; // uncastled king in the center
     }
 
-    // Clamp phase to TOTAL_PHASE (shouldn't exceed, but be safe)
-    if phase > TOTAL_PHASE {
-        phase = TOTAL_PHASE;
+    let ours = board.pieces(Piece::Pawn) & board.color_combined(color);
+    let their_pawns = board.pieces(Piece::Pawn) & them;
+    let mut bonus = 0;
+    for file in king_file.saturating_sub(1)..=(king_file + 1).min(7) {
+        let on_file = ours & BitBoard(FILE_A.0 << file);
+        if on_file.is_empty() {
+            continue;
+        }
+        // Most advanced pawn from color's point of view
+        let sq = if color == Color::White { 63 - on_file.0.leading_zeros() } else { on_file.0.trailing_zeros() };
+        let rel = if color == Color::White { sq / 8 } else { 7 - sq / 8 } as usize;
+        let front = if color == Color::White { sq + 8 } else { sq.wrapping_sub(8) };
+        let blocked = front < 64 && their_pawns.0 & (1u64 << front) != 0;
+        bonus += if blocked { PAWN_STORM_MG[rel] / 2 } else { PAWN_STORM_MG[rel] };
     }
+    bonus
+}
 
-    // Compute scores relative to white
-    let mg = mg_score[0] - mg_score[1];
-    let eg = eg_score[0] - eg_score[1];
+/// King-danger index for color's king: weighted enemy attacks on the king zone (king square and
+/// its neighbours) plus the "virtual mobility" of an imaginary queen on the king square. The
+/// latter counts the lines leading to the king that aren't blocked by our own pieces, a cheap
+/// measure of how exposed it is even before enemy pieces line up.
+pub fn king_danger(board: &Board, color: Color) -> i32 {
+    let ksq = board.king_square(color);
+    let king_bb = BitBoard::from_square(ksq);
+    let occupied = board.combined();
+    let zone = king_attacks(ksq) | king_bb;
+    let them = board.color_combined(!color);
 
-    // Tapered eval: blend mg and eg based on phase
-    // phase = TOTAL_PHASE means full midgame, phase = 0 means full endgame
-    let mg_phase = phase;
-    let eg_phase = TOTAL_PHASE - phase;
-    let score = (mg * mg_phase + eg * eg_phase) / TOTAL_PHASE;
+    let mut danger = 0;
+    for piece in [Piece::Knight, Piece::Bishop, Piece::Rook, Piece::Queen] {
+        for sq in (board.pieces(piece) & them).iter() {
+            let attacks = match piece {
+                Piece::Knight => knight_attacks(sq),
+                Piece::Bishop => bishop_attacks(sq, occupied),
+                Piece::Rook => rook_attacks(sq, occupied),
+                _ => queen_attacks(sq, occupied),
+            };
+            danger += (attacks & zone).popcnt() as i32 * KING_ZONE_ATTACK_WEIGHT[piece_index(piece)];
+        }
+    }
 
-    // Return from side-to-move perspective
-    if board.side_to_move() == Color::White {
-        score
+    let virtual_queen = queen_attacks(ksq, occupied) & !board.color_combined(color);
+    danger + virtual_queen.popcnt() as i32 * VIRTUAL_QUEEN_WEIGHT
+}
+
+/// Midgame penalty for a king-danger index: grows quadratically so several attackers together
+/// hurt far more than one
+fn king_danger_penalty(danger: i32) -> Score {
+    (danger * danger / KING_DANGER_DIVISOR).min(KING_DANGER_MAX)
+}
+
+/// Full-strength scale factor; `scale_factor` returns 0..=SCALE_NORMAL
+const SCALE_NORMAL: Score = 64;
+
+/// Light squares (b1, a2, ...) for opposite-colored bishop detection
+const LIGHT_SQUARES: BitBoard = BitBoard(0x55AA_55AA_55AA_55AA);
+
+/// Non-pawn material of color (midgame values, king excluded)
+fn non_pawn_material(board: &Board, color: Color) -> Score {
+    let ours = board.color_combined(color);
+    [Piece::Knight, Piece::Bishop, Piece::Rook, Piece::Queen]
+        .iter()
+        .map(|&p| (board.pieces(p) & ours).popcnt() as Score * MG_PIECE_VALUE[piece_index(p)])
+        .sum()
+}
+
+/// Scale factor (out of SCALE_NORMAL) for the final score of a white-relative eval.
+/// Recognizes material constellations that are hard or impossible to win even with an edge:
+/// - pawnless endings where the stronger side is up at most a minor (KR vs KB, KRN vs KR, KB vs K)
+/// - opposite-colored bishops, especially with only pawns left
+fn scale_factor(board: &Board, score: Score) -> Score {
+    let strong = if score >= 0 { Color::White } else { Color::Black };
+    let weak = !strong;
+    let pawns = board.pieces(Piece::Pawn);
+    let strong_pawns = (pawns & board.color_combined(strong)).popcnt();
+    let npm_strong = non_pawn_material(board, strong);
+    let npm_weak = non_pawn_material(board, weak);
+    let bishop_value = MG_PIECE_VALUE[pst::BISHOP];
+    let rook_value = MG_PIECE_VALUE[pst::ROOK];
+
+    // Pawnless: no real winning edge unless up more than a minor piece
+    if strong_pawns == 0 && npm_strong - npm_weak <= bishop_value {
+        return if npm_strong < rook_value { 0 } else { 4 };
+    }
+
+    // Opposite-colored bishops: one bishop each, on different square colors
+    let white_bishops = board.pieces(Piece::Bishop) & board.color_combined(Color::White);
+    let black_bishops = board.pieces(Piece::Bishop) & board.color_combined(Color::Black);
+    if white_bishops.popcnt() == 1 && black_bishops.popcnt() == 1 {
+        let white_light = !(white_bishops & LIGHT_SQUARES).is_empty();
+        let black_light = !(black_bishops & LIGHT_SQUARES).is_empty();
+        if white_light != black_light {
+            // Only bishops and pawns left: very drawish
+            return if npm_strong == bishop_value && npm_weak == bishop_value { 24 } else { 48 };
+        }
+    }
+
+    SCALE_NORMAL
+}
+
+/// Network used when no EvalFile is given: the embedded one if compiled in, else none (HCE)
+pub fn default_network() -> Option<Arc<Network>> {
+    #[cfg(feature = "embedded-net")]
+    {
+        Some(Arc::new(Network::embedded()))
+    }
+    #[cfg(not(feature = "embedded-net"))]
+    {
+        None
+    }
+}
+
+/// Which evaluator the eval cache uses when a network is loaded
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EvalMode {
+    /// Always use the network
+    Nnue,
+    /// Network, but fall back to the handcrafted eval where it is unreliable
+    Hybrid,
+    /// Handcrafted eval only, even if a network is loaded
+    Classical,
+}
+
+impl EvalMode {
+    /// Parse a UCI combo value (case-insensitive)
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "nnue" => Some(Self::Nnue),
+            "hybrid" => Some(Self::Hybrid),
+            "classical" => Some(Self::Classical),
+            _ => None,
+        }
+    }
+}
+
+/// Style: how the engine likes to play, as a few evaluation weights and contempt shifted
+/// together (see `StyleWeights`)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Style {
+    /// Keener on trades, less on attacking the king, and happier with a draw
+    Solid,
+    /// The evaluation as tuned
+    #[default]
+    Normal,
+    /// Attacks the king, takes space, keeps pieces on and avoids draws
+    Aggressive,
+}
+
+impl Style {
+    /// Parse a UCI combo value (case-insensitive)
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "solid" => Some(Self::Solid),
+            "normal" => Some(Self::Normal),
+            "aggressive" => Some(Self::Aggressive),
+            _ => None,
+        }
+    }
+
+    pub fn weights(self) -> StyleWeights {
+        match self {
+            Style::Solid => StyleWeights { king_attack: 85, space: 0, trade: 5, contempt: -10 },
+            Style::Normal => StyleWeights { king_attack: 100, space: 0, trade: 0, contempt: 0 },
+            Style::Aggressive => StyleWeights { king_attack: 130, space: 3, trade: -5, contempt: 20 },
+        }
+    }
+}
+
+/// What a Style changes, all for the side the engine plays: the opponent's position is
+/// evaluated as tuned
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StyleWeights {
+    /// Percent of the king-danger penalty counted for the opponent's king
+    pub king_attack: i32,
+    /// Midgame centipawns per safe square behind the center (see `space`)
+    pub space: Score,
+    /// Centipawns per non-pawn piece traded off, by either side; negative to keep pieces on
+    pub trade: Score,
+    /// Centipawns added to Contempt
+    pub contempt: Score,
+}
+
+/// XORed into a position's hash while a Style other than Normal is played (Solid, Aggressive),
+/// for White and for Black: its shifted evaluations and the search scores built on them are
+/// cached under keys of their own, so no other style's or side's entry answers for them
+const STYLE_KEYS: [[u64; 2]; 2] = [[0x9E37_79B9_7F4A_7C15, 0xBF58_476D_1CE4_E5B9], [0x94D0_49BB_1331_11EB, 0xD6E8_FEB8_6659_FD93]];
+
+/// Knights, bishops, rooks and queens in the start position
+const START_PIECES: i32 = 14;
+
+/// Files c-f
+const CENTER_FILES: BitBoard = BitBoard(0x3C3C_3C3C_3C3C_3C3C);
+
+/// Space for color: squares of files c-f on its second to fourth ranks that don't hold its
+/// pawns and aren't covered by enemy pawns, the ones behind its pawns counting twice
+fn space(board: &Board, color: Color) -> i32 {
+    let pawns = board.pieces(Piece::Pawn) & board.color_combined(color);
+    let (area, behind) = if color == Color::White {
+        (rank_bb(1) | rank_bb(2) | rank_bb(3), BitBoard(pawns.0 >> 8 | pawns.0 >> 16 | pawns.0 >> 24))
     } else {
-        -score
+        (rank_bb(6) | rank_bb(5) | rank_bb(4), BitBoard(pawns.0 << 8 | pawns.0 << 16 | pawns.0 << 24))
+    };
+    let safe = area & CENTER_FILES & !pawns & !pawn_attack_span(board, !color);
+    (safe.popcnt() + (safe & behind).popcnt()) as i32
+}
+
+/// White-relative shift of the evaluation by `weights` for `side`: the change in the
+/// opponent's king-danger penalty, space (both midgame only) and the trade bonus
+fn style_score(board: &Board, weights: &StyleWeights, side: Color) -> Score {
+    let mut mg = 0;
+    if weights.king_attack != 100 {
+        mg += king_danger_penalty(king_danger(board, !side)) * (weights.king_attack - 100) / 100;
+    }
+    if weights.space != 0 {
+        mg += weights.space * space(board, side);
+    }
+    let pieces = (board.pieces(Piece::Knight) | board.pieces(Piece::Bishop) | board.pieces(Piece::Rook) | board.pieces(Piece::Queen)).popcnt() as i32;
+    let score = taper(mg, 0, game_phase(board)) + weights.trade * (START_PIECES - pieces).max(0);
+    if side == Color::White { score } else { -score }
+}
+
+/// Hybrid mode: material + PST imbalance beyond which the position is left to the classical eval
+/// (the net has seen few such positions and the outcome is clear anyway)
+const HYBRID_IMBALANCE: Score = 1000;
+/// Hybrid mode: net outputs this close to zero defer to the classical eval's finer judgement
+const HYBRID_NEAR_ZERO: Score = 10;
+
+/// Number of slots in the per-thread eval cache (power of 2).
+const EVAL_CACHE_SIZE: usize = 1 << 16;
+
+/// An eval cache's network, mode and style without its entries (see `EvalCache::settings`)
+#[cfg(feature = "std")]
+#[derive(Clone)]
+pub(crate) struct EvalSettings {
+    network: Option<Arc<Network>>,
+    mode: EvalMode,
+    style: Style,
+    style_side: Color,
+}
+
+/// Small direct-mapped cache of static evaluations keyed by the full Zobrist hash.
+/// Each search thread owns one, so no synchronization is needed.
+/// When a network is set, cached evals come from NNUE instead of the handcrafted eval.
+pub struct EvalCache {
+    keys: Vec<u64>,
+    scores: Vec<Score>,
+    network: Option<Arc<Network>>,
+    mode: EvalMode,
+    style: Style,
+    /// The side the style is played for: the side to move at the root of the current search
+    style_side: Color,
+    /// `STYLE_KEYS` entry of the style and side, 0 for Normal (see `style_key`)
+    style_key: u64,
+}
+
+impl Default for EvalCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EvalCache {
+    pub fn new() -> Self {
+        Self {
+            keys: vec![0; EVAL_CACHE_SIZE],
+            scores: vec![0; EVAL_CACHE_SIZE],
+            network: default_network(),
+            mode: EvalMode::Nnue,
+            style: Style::Normal,
+            style_side: Color::White,
+            style_key: 0,
+        }
+    }
+
+    /// Choose how a loaded network is used. Clears the cache.
+    pub fn set_mode(&mut self, mode: EvalMode) {
+        self.mode = mode;
+        self.clear();
+    }
+
+    /// Choose the playing style. Entries cached under another style stay for when it is back.
+    pub fn set_style(&mut self, style: Style) {
+        self.style = style;
+        self.style_key = self.key_for(self.style_side);
+    }
+
+    pub fn style(&self) -> Style {
+        self.style
+    }
+
+    /// Play the style for `side` from now on. Its evaluations for either side stay cached, so
+    /// analysis that switches sides on every search loses nothing.
+    pub fn set_style_side(&mut self, side: Color) {
+        self.style_side = side;
+        self.style_key = self.key_for(side);
+    }
+
+    fn key_for(&self, side: Color) -> u64 {
+        match self.style {
+            Style::Normal => 0,
+            Style::Solid => STYLE_KEYS[0][side.to_index()],
+            Style::Aggressive => STYLE_KEYS[1][side.to_index()],
+        }
+    }
+
+    /// What to XOR into a position's hash to key anything that depends on the evaluation, such
+    /// as TT entries: the style shifts it for one side only, so the same position scores
+    /// differently with a style played for White, for Black, or not at all
+    pub fn style_key(&self) -> u64 {
+        self.style_key
+    }
+
+    /// Switch the evaluator (None = handcrafted eval). Clears the cache.
+    pub fn set_network(&mut self, network: Option<Arc<Network>>) {
+        self.network = network;
+        self.clear();
+    }
+
+    /// The active NNUE network, if any
+    pub fn network(&self) -> Option<&Arc<Network>> {
+        self.network.as_ref()
+    }
+
+    /// What this cache evaluates with, to set up another the same way (see `follow`)
+    #[cfg(feature = "std")]
+    pub(crate) fn settings(&self) -> EvalSettings {
+        EvalSettings { network: self.network.clone(), mode: self.mode, style: self.style, style_side: self.style_side }
+    }
+
+    /// Evaluate as `settings` say from now on. The cached scores stay if the network and mode
+    /// are the ones they were computed with.
+    #[cfg(feature = "std")]
+    pub(crate) fn follow(&mut self, settings: &EvalSettings) {
+        let same_network = match (&self.network, &settings.network) {
+            (Some(ours), Some(theirs)) => Arc::ptr_eq(ours, theirs),
+            (ours, theirs) => ours.is_none() && theirs.is_none(),
+        };
+        if !same_network || self.mode != settings.mode {
+            self.network = settings.network.clone();
+            self.mode = settings.mode;
+            self.clear();
+        }
+        self.style = settings.style;
+        self.set_style_side(settings.style_side);
+    }
+
+    /// The evaluation with the style's shift, which moves the lazy window along with it
+    #[inline]
+    fn compute(&self, board: &Board, window: Option<(Score, Score)>) -> (Score, bool) {
+        if self.style == Style::Normal {
+            return self.compute_tuned(board, window);
+        }
+        let shift = style_score(board, &self.style.weights(), self.style_side);
+        let shift = if board.side_to_move() == Color::White { shift } else { -shift };
+        let (score, exact) = self.compute_tuned(board, window.map(|(alpha, beta)| (alpha - shift, beta - shift)));
+        (score + shift, exact)
+    }
+
+    /// The evaluation before the style's shift
+    #[inline]
+    fn compute_tuned(&self, board: &Board, window: Option<(Score, Score)>) -> (Score, bool) {
+        let classical = || match window {
+            Some((alpha, beta)) => evaluate_lazy(board, alpha, beta),
+            None => (evaluate(board), true),
+        };
+
+        let Some(net) = &self.network else {
+            return classical();
+        };
+        match self.mode {
+            EvalMode::Nnue => (net.evaluate(board), true),
+            EvalMode::Classical => classical(),
+            EvalMode::Hybrid => {
+                let (mg, eg) = board.psq_score();
+                if taper(mg, eg, game_phase(board)).abs() > HYBRID_IMBALANCE {
+                    return classical();
+                }
+                let score = net.evaluate(board);
+                if score.abs() <= HYBRID_NEAR_ZERO { classical() } else { (score, true) }
+            }
+        }
+    }
+
+    /// Return the cached static eval for board, computing and storing it on a miss.
+    #[inline]
+    pub fn evaluate(&mut self, board: &Board) -> Score {
+        let hash = board.get_hash() ^ self.style_key;
+        let idx = hash as usize & (EVAL_CACHE_SIZE - 1);
+        if self.keys[idx] == hash {
+            return self.scores[idx];
+        }
+        let (score, _) = self.compute(board, None);
+        self.keys[idx] = hash;
+        self.scores[idx] = score;
+        score
+    }
+
+    /// Like `evaluate`, but may return a lazy bound when the estimate is far outside
+    /// (alpha, beta). Only exact scores are cached.
+    #[inline]
+    pub fn evaluate_lazy(&mut self, board: &Board, alpha: Score, beta: Score) -> Score {
+        let hash = board.get_hash() ^ self.style_key;
+        let idx = hash as usize & (EVAL_CACHE_SIZE - 1);
+        if self.keys[idx] == hash {
+            return self.scores[idx];
+        }
+        let (score, exact) = self.compute(board, Some((alpha, beta)));
+        if exact {
+            self.keys[idx] = hash;
+            self.scores[idx] = score;
+        }
+        score
+    }
+
+    /// Clear all entries.
+    pub fn clear(&mut self) {
+        self.keys.fill(0);
     }
 }
 
@@ -123,6 +721,19 @@ mod tests {
         assert!(score.abs() < 50, "Symmetric position should be near 0, got {}", score);
     }
 
+    #[test]
+    fn test_mirror_symmetry() {
+        // Color-swapped, rank-flipped positions must score the same for the side to move
+        for fen in [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            "r1bq1rk1/pp2ppbp/2np1np1/8/3NP3/2N1BP2/PPPQ2PP/R3KB1R w KQ - 0 1",
+        ] {
+            let board = Board::from_str(fen).unwrap();
+            assert_eq!(evaluate(&board), evaluate(&board.mirror()), "{}", fen);
+        }
+    }
+
     #[test]
     fn test_endgame_phase() {
         // King + pawn endgame: should heavily weight endgame tables
@@ -131,6 +742,294 @@ mod tests {
         // White has a pawn advantage, score should be positive
         assert!(score > 0, "White with extra pawn should be positive, got {}", score);
     }
+
+    #[test]
+    fn test_game_phase() {
+        assert_eq!(game_phase(&Board::default()), TOTAL_PHASE);
+        assert_eq!(game_phase_normalized(&Board::default()), 1.0);
+
+        let kp = Board::from_str("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1").unwrap();
+        assert_eq!(game_phase(&kp), 0);
+
+        // Three queens each: raw count exceeds TOTAL_PHASE
+        let queens = Board::from_str("qqqqkqqq/pppppppp/8/8/8/8/PPPPPPPP/QQQQKQQQ w - - 0 1").unwrap();
+        assert_eq!(game_phase(&queens), TOTAL_PHASE);
+    }
+
+    #[test]
+    fn test_mobility_symmetric_startpos() {
+        let board = Board::default();
+        assert_eq!(mobility(&board, Color::White), mobility(&board, Color::Black));
+    }
+
+    #[test]
+    fn test_mobility_centralized_knight() {
+        // Knight on d4 should be more mobile than knight on a1
+        let center = Board::from_str("4k3/8/8/8/3N4/8/8/4K3 w - - 0 1").unwrap();
+        let corner = Board::from_str("4k3/8/8/8/8/8/8/N3K3 w - - 0 1").unwrap();
+        assert!(mobility(&center, Color::White).0 > mobility(&corner, Color::White).0);
+    }
+
+    #[test]
+    fn test_mobility_excludes_pawn_covered_squares() {
+        // Black pawns on c6/e6 cover d5 and b5/f5, shrinking the d4 knight's safe squares
+        let free = Board::from_str("4k3/8/8/8/3N4/8/8/4K3 w - - 0 1").unwrap();
+        let covered = Board::from_str("4k3/8/2p1p3/8/3N4/8/8/4K3 w - - 0 1").unwrap();
+        assert!(mobility(&covered, Color::White).0 < mobility(&free, Color::White).0);
+    }
+
+    #[test]
+    fn test_rook_on_seventh() {
+        // White rook on d7, black king on g8: bonus applies
+        let board = Board::from_str("6k1/3R4/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        assert_eq!(rook_on_seventh(&board, Color::White), (ROOK_SEVENTH_MG, ROOK_SEVENTH_EG));
+
+        // Black king off the back rank and no pawns on the seventh: no bonus
+        let board = Board::from_str("8/3R4/6k1/8/8/8/8/4K3 w - - 0 1").unwrap();
+        assert_eq!(rook_on_seventh(&board, Color::White), (0, 0));
+
+        // Black rook on e2 with white pawns on the second rank
+        let board = Board::from_str("4k3/8/8/8/8/5K2/P3r3/8 b - - 0 1").unwrap();
+        assert_eq!(rook_on_seventh(&board, Color::Black), (ROOK_SEVENTH_MG, ROOK_SEVENTH_EG));
+    }
+
+    #[test]
+    fn test_pawn_structure_connected() {
+        // d4 defended by c3 vs. split pawns on b3/d4
+        let chain = Board::from_str("4k3/8/8/8/3P4/2P5/8/4K3 w - - 0 1").unwrap();
+        let split = Board::from_str("4k3/8/8/8/3P4/1P6/8/4K3 w - - 0 1").unwrap();
+        assert!(pawn_structure(&chain, Color::White).0 > 0);
+        assert_eq!(pawn_structure(&split, Color::White), (0, 0));
+    }
+
+    #[test]
+    fn test_pawn_structure_phalanx_scales_with_rank() {
+        let low = Board::from_str("4k3/8/8/8/8/3PP3/8/4K3 w - - 0 1").unwrap();
+        let high = Board::from_str("4k3/8/3PP3/8/8/8/8/4K3 w - - 0 1").unwrap();
+        assert!(pawn_structure(&high, Color::White).1 > pawn_structure(&low, Color::White).1);
+
+        // Mirrored for black: d6/e6 is black's third rank
+        let black = Board::from_str("4k3/8/3pp3/8/8/8/8/4K3 w - - 0 1").unwrap();
+        assert_eq!(pawn_structure(&black, Color::Black), pawn_structure(&low, Color::White));
+    }
+
+    #[test]
+    fn test_pawn_structure_no_wraparound() {
+        // h3 and a4 are not neighbours
+        let board = Board::from_str("4k3/8/8/8/P7/7P/8/4K3 w - - 0 1").unwrap();
+        assert_eq!(pawn_structure(&board, Color::White), (0, 0));
+    }
+
+    #[test]
+    fn test_pawn_shelter() {
+        // Same f2/g2/h2 pawns: they shelter a king castled short, not one castled long
+        let short = Board::from_str("4k3/8/8/8/8/8/5PPP/6K1 w - - 0 1").unwrap();
+        let long = Board::from_str("4k3/8/8/8/8/8/5PPP/1K6 w - - 0 1").unwrap();
+        assert_eq!(pawn_shelter(&short, Color::White), 3 * PAWN_SHELTER_MG[1]);
+        assert_eq!(pawn_shelter(&long, Color::White), 0);
+        // Pawns that walked away from the king count against it
+        let advanced = Board::from_str("4k3/8/8/5PPP/8/8/8/6K1 w - - 0 1").unwrap();
+        assert!(pawn_shelter(&advanced, Color::White) < 0);
+
+        // Black's shelter mirrors White's
+        let black = Board::from_str("6k1/5ppp/8/8/8/8/8/1K6 w - - 0 1").unwrap();
+        assert_eq!(pawn_shelter(&black, Color::Black), pawn_shelter(&short, Color::White));
+
+        // Off its first two ranks the king has no shelter, either wing
+        let off_home = Board::from_str("4k3/8/8/8/8/6K1/5PPP/8 w - - 0 1").unwrap();
+        assert_eq!(pawn_shelter(&off_home, Color::White), 0);
+    }
+
+    #[test]
+    fn test_pawn_storm_against_castled_king() {
+        // Black king castled short; white g/h pawns advanced to g5/h5
+        let storm = Board::from_str("5rk1/5ppp/8/6PP/8/8/5P2/2K5 w - - 0 1").unwrap();
+        let home = Board::from_str("5rk1/5ppp/8/8/8/8/5PPP/2K5 w - - 0 1").unwrap();
+        assert!(pawn_storm(&storm, Color::White) > pawn_storm(&home, Color::White));
+    }
+
+    #[test]
+    fn test_pawn_storm_ignores_central_king() {
+        let board = Board::from_str("4k3/3ppp2/8/3PPP2/8/8/8/4K3 w - - 0 1").unwrap();
+        assert_eq!(pawn_storm(&board, Color::White), 0);
+    }
+
+    #[test]
+    fn test_pawn_storm_blocked_counts_half() {
+        let free = Board::from_str("6k1/8/8/8/6P1/8/8/2K5 w - - 0 1").unwrap();
+        let blocked = Board::from_str("6k1/8/8/6p1/6P1/8/8/2K5 w - - 0 1").unwrap();
+        assert_eq!(pawn_storm(&blocked, Color::White), pawn_storm(&free, Color::White) / 2);
+    }
+
+    #[test]
+    fn test_king_danger_virtual_queen() {
+        // Sheltered king vs. the same king with its pawn cover gone
+        let sheltered = Board::from_str("4k3/8/8/8/8/8/5PPP/6K1 w - - 0 1").unwrap();
+        let exposed = Board::from_str("4k3/8/8/8/8/8/8/6K1 w - - 0 1").unwrap();
+        assert!(king_danger(&exposed, Color::White) > king_danger(&sheltered, Color::White));
+        assert_eq!(king_danger(&Board::default(), Color::White), 0);
+    }
+
+    #[test]
+    fn test_king_danger_zone_attacks() {
+        // Black queen on h4 eyes f2/g3 around the white king
+        let quiet = Board::from_str("4k3/8/8/8/8/8/5PPP/5RK1 w - - 0 1").unwrap();
+        let attacked = Board::from_str("4k3/8/8/8/7q/8/5PP1/5RK1 w - - 0 1").unwrap();
+        assert!(king_danger(&attacked, Color::White) > king_danger(&quiet, Color::White));
+        assert!(king_danger_penalty(1000) <= KING_DANGER_MAX);
+    }
+
+    #[test]
+    fn test_scale_bare_minor_is_draw() {
+        // KB vs K cannot be won
+        let board = Board::from_str("4k3/8/8/8/8/8/8/2B1K3 w - - 0 1").unwrap();
+        assert_eq!(scale_factor(&board, 300), 0);
+        assert_eq!(evaluate(&board), 0);
+    }
+
+    #[test]
+    fn test_scale_rook_minor_vs_rook() {
+        let board = Board::from_str("3rk3/8/8/8/8/8/8/2BRK3 w - - 0 1").unwrap();
+        assert!(scale_factor(&board, 300) < SCALE_NORMAL / 4);
+    }
+
+    #[test]
+    fn test_scale_opposite_bishops() {
+        // White bishop on c1 (dark), black bishop on c8 (light), extra white pawn
+        let board = Board::from_str("2b1k3/p7/8/8/8/8/PP6/2B1K3 w - - 0 1").unwrap();
+        assert!(scale_factor(&board, 100) < SCALE_NORMAL);
+
+        // Same-colored bishops: no scaling
+        let board = Board::from_str("1b2k3/p7/8/8/8/8/PP6/2B1K3 w - - 0 1").unwrap();
+        assert_eq!(scale_factor(&board, 100), SCALE_NORMAL);
+    }
+
+    #[test]
+    fn test_lazy_eval_inside_window_is_exact() {
+        let board = Board::default();
+        let (score, exact) = evaluate_lazy(&board, -100, 100);
+        assert!(exact);
+        assert_eq!(score, evaluate(&board));
+    }
+
+    #[test]
+    fn test_lazy_eval_outside_window() {
+        // White up a queen, window near zero: lazy path should bail out with a bound above beta
+        let board = Board::from_str("rnb1kbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1")
+            .unwrap();
+        let (score, exact) = evaluate_lazy(&board, -50, 50);
+        assert!(!exact);
+        assert!(score >= 50);
+    }
+
+    #[test]
+    fn test_lazy_eval_bounds_hold() {
+        // Exposed kings, where king danger alone is worth hundreds, and a queen-up position
+        for fen in [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "r1b2rk1/pp3p1p/2n3pQ/3pq3/8/2PB1N2/P4PPP/R4RK1 b - - 0 1",
+            "6k1/5p1p/4qBpQ/8/8/8/5PPP/6K1 w - - 0 1",
+            "rnb1kbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+        ] {
+            let board = Board::from_str(fen).unwrap();
+            for style in [Style::Normal, Style::Aggressive] {
+                let mut cache = EvalCache::new();
+                cache.set_network(None);
+                cache.set_style(style);
+                let full = cache.compute(&board, None).0;
+                for alpha in (-2000..2000).step_by(50) {
+                    let (score, exact) = cache.compute(&board, Some((alpha, alpha + 1)));
+                    if exact {
+                        assert_eq!(score, full, "{}", fen);
+                    } else if score > alpha {
+                        assert!(full >= score, "{} lazy lower bound {} above {}", fen, score, full);
+                    } else {
+                        assert!(full <= score, "{} lazy upper bound {} below {}", fen, score, full);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Net that always answers the same score (only output bias set)
+    fn constant_net(cp: i32) -> Arc<Network> {
+        let hidden = 1;
+        let mut bytes = b"FRNN".to_vec();
+        bytes.extend_from_slice(&1u32.to_le_bytes());
+        bytes.extend_from_slice(&(hidden as u32).to_le_bytes());
+        bytes.extend(std::iter::repeat_n(0u8, 2 * (768 * hidden + 3 * hidden)));
+        bytes.extend_from_slice(&(cp * 255 * 64 / 400).to_le_bytes());
+        Arc::new(Network::from_bytes(&bytes).unwrap())
+    }
+
+    #[test]
+    fn test_eval_mode_hybrid() {
+        let balanced = Board::from_str("r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4")
+            .unwrap();
+        let crushing = Board::from_str("4k3/8/8/8/8/8/8/QQQQK3 w - - 0 1").unwrap();
+        let mut cache = EvalCache::new();
+
+        cache.set_network(Some(constant_net(50)));
+        assert_eq!(cache.evaluate(&balanced), 50);
+        assert_eq!(cache.evaluate(&crushing), 50);
+
+        cache.set_mode(EvalMode::Hybrid);
+        assert_eq!(cache.evaluate(&balanced), 50);
+        assert_eq!(cache.evaluate(&crushing), evaluate(&crushing));
+
+        cache.set_network(Some(constant_net(0)));
+        assert_eq!(cache.evaluate(&balanced), evaluate(&balanced));
+
+        cache.set_mode(EvalMode::Classical);
+        assert_eq!(cache.evaluate(&crushing), evaluate(&crushing));
+        assert_eq!(EvalMode::parse("HYBRID"), Some(EvalMode::Hybrid));
+    }
+
+    #[test]
+    fn test_style() {
+        let start = Board::default();
+        let pawn_ending = Board::from_str("4k3/4p3/8/8/8/8/4P3/4K3 w - - 0 1").unwrap();
+        let shift = |board: &Board, style: Style, side: Color| style_score(board, &style.weights(), side);
+        assert_eq!((space(&start, Color::White), space(&start, Color::Black)), (8, 8));
+        assert_eq!(shift(&start, Style::Normal, Color::White), 0);
+        // Space counts in the midgame, trades in any phase
+        assert_eq!(shift(&start, Style::Aggressive, Color::White), 24);
+        assert_eq!(shift(&start, Style::Aggressive, Color::Black), -24);
+        assert_eq!(shift(&pawn_ending, Style::Solid, Color::White), 70);
+        assert_eq!(shift(&pawn_ending, Style::Aggressive, Color::Black), 70);
+
+        // The cache shifts whichever evaluator it uses, for the side it was told
+        let mut cache = EvalCache::new();
+        cache.set_style(Style::Aggressive);
+        cache.set_style_side(Color::White);
+        assert_eq!(cache.evaluate(&pawn_ending), evaluate(&pawn_ending) - 70);
+        cache.set_style_side(Color::Black);
+        assert_eq!(cache.evaluate(&pawn_ending), evaluate(&pawn_ending) + 70);
+        // Both sides' entries stay cached under their own keys
+        let white_key = pawn_ending.get_hash() ^ STYLE_KEYS[1][Color::White.to_index()];
+        assert!(cache.keys.contains(&white_key) && cache.keys.contains(&(pawn_ending.get_hash() ^ cache.style_key())));
+        cache.set_style_side(Color::White);
+        assert_eq!(cache.evaluate(&pawn_ending), evaluate(&pawn_ending) - 70);
+        cache.set_style_side(Color::Black);
+        cache.set_network(Some(constant_net(50)));
+        assert_eq!(cache.evaluate(&pawn_ending), 120);
+        cache.set_style(Style::Normal);
+        assert_eq!(cache.evaluate(&pawn_ending), 50);
+        assert_eq!(Style::parse("SOLID"), Some(Style::Solid));
+    }
+
+    #[test]
+    fn test_eval_cache_matches_evaluate() {
+        let board = Board::from_str("r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4")
+            .unwrap();
+        let mut cache = EvalCache::new();
+        // The handcrafted eval, even when the embedded-net feature sets a network by default
+        cache.set_network(None);
+        let first = cache.evaluate(&board);
+        let second = cache.evaluate(&board);
+        assert_eq!(first, evaluate(&board));
+        assert_eq!(second, first);
+    }
 }
 // tapered evals -> compute separate midgame and endgame scores, blend them based on how much
 // material is left ("game phase")
@@ -139,3 +1038,7 @@ mod tests {
 // Phase decreases toward 0 as pieces traded. Score blends: `(mg_score * phase + eg_score *
 // (24-phase)) / 24` -> king safe in midgame, but active in endgame
 
+// A Style is applied by the eval cache rather than inside the terms: that way it shifts the
+// network's output as much as the handcrafted eval's, and `evaluate` stays the tuned, symmetric
+// function the tests and tools expect. The shift only favours the engine's own side, since a
+// symmetric change would also make it fear the very attacks it is meant to seek.
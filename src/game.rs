@@ -0,0 +1,232 @@
+use std::str::FromStr;
+
+use crate::board::{Board, BoardStatus, ChessMove, Piece};
+
+/// Why a game is (or can be claimed) drawn
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DrawReason {
+    Stalemate,
+    ThreefoldRepetition,
+    FiftyMoves,
+    InsufficientMaterial,
+}
+
+/// A game: the starting position plus every move played, with undo and draw claims
+#[derive(Debug, Clone)]
+pub struct Game {
+    /// Position after each move; `positions[0]` is the start
+    positions: Vec<Board>,
+    moves: Vec<ChessMove>,
+}
+
+impl Default for Game {
+    fn default() -> Self {
+        Game::new(Board::default())
+    }
+}
+
+impl FromStr for Game {
+    type Err = String;
+
+    fn from_str(fen: &str) -> Result<Self, String> {
+        Ok(Game::new(Board::from_str(fen)?))
+    }
+}
+
+impl Game {
+    pub fn new(start: Board) -> Self {
+        Game { positions: vec![start], moves: Vec::new() }
+    }
+
+    /// Current position
+    pub fn board(&self) -> &Board {
+        self.positions.last().expect("game always has a start position")
+    }
+
+    pub fn start_position(&self) -> &Board {
+        &self.positions[0]
+    }
+
+    /// Moves played so far, in order
+    pub fn moves(&self) -> &[ChessMove] {
+        &self.moves
+    }
+
+    /// Play a move, rejecting it if it is illegal in the current position. Moves built from
+    /// squares alone (`ChessMove::new`, UCI, serde) are normalized first, so castling and en
+    /// passant work.
+    pub fn make_move(&mut self, mv: ChessMove) -> Result<(), String> {
+        let board = self.board();
+        let mv = board.normalize_move(mv);
+        if !board.legal(mv) {
+            return Err(format!("Illegal move {} in {}", board.move_to_uci(mv), board));
+        }
+        let next = board.make_move_new(mv);
+        self.positions.push(next);
+        self.moves.push(mv);
+        Ok(())
+    }
+
+    /// Take back the last move, returning it (None at the start position)
+    pub fn undo(&mut self) -> Option<ChessMove> {
+        let mv = self.moves.pop()?;
+        self.positions.pop();
+        Some(mv)
+    }
+
+    /// Hashes of every position so far, oldest first
+    pub fn hashes(&self) -> Vec<u64> {
+        self.positions.iter().map(|b| b.get_hash()).collect()
+    }
+
+    /// Hashes of the positions since the last capture or pawn move, oldest first and ending with
+    /// the current one: the only ones that can still repeat, so the search's repetition history
+    pub fn repetition_history(&self) -> Vec<u64> {
+        let window = self.board().halfmove_clock() as usize + 1;
+        let start = self.positions.len().saturating_sub(window);
+        self.positions[start..].iter().map(|b| b.get_hash()).collect()
+    }
+
+    /// How many times the current position has occurred, counting this one. Only positions
+    /// since the last capture or pawn move can repeat.
+    pub fn repetition_count(&self) -> usize {
+        let hash = self.board().get_hash();
+        let window = self.board().halfmove_clock() as usize + 1;
+        self.positions.iter().rev().take(window).filter(|b| b.get_hash() == hash).count()
+    }
+
+    pub fn is_threefold_repetition(&self) -> bool {
+        self.repetition_count() >= 3
+    }
+
+    /// 100 plies without a capture or pawn move (mate on the 100th ply still wins)
+    pub fn is_fifty_moves(&self) -> bool {
+        self.board().halfmove_clock() >= 100 && self.board().status() != BoardStatus::Checkmate
+    }
+
+    /// The draw that applies to the current position, if any: stalemate, then the claimable
+    /// draws (threefold, fifty moves), then dead positions with too little material to mate
+    pub fn draw_reason(&self) -> Option<DrawReason> {
+        if self.board().status() == BoardStatus::Stalemate {
+            Some(DrawReason::Stalemate)
+        } else if self.is_threefold_repetition() {
+            Some(DrawReason::ThreefoldRepetition)
+        } else if self.is_fifty_moves() {
+            Some(DrawReason::FiftyMoves)
+        } else if insufficient_material(self.board()) {
+            Some(DrawReason::InsufficientMaterial)
+        } else {
+            None
+        }
+    }
+}
+
+/// Neither side can possibly mate: bare kings, a single minor piece, or only bishops that
+/// all stand on the same square color
+pub fn insufficient_material(board: &Board) -> bool {
+    let heavy = board.pieces(Piece::Pawn) | board.pieces(Piece::Rook) | board.pieces(Piece::Queen);
+    if !heavy.is_empty() {
+        return false;
+    }
+    let knights = board.pieces(Piece::Knight);
+    let bishops = board.pieces(Piece::Bishop);
+    let minors = (knights | bishops).popcnt();
+    if minors <= 1 {
+        return true;
+    }
+    // Several bishops (of either color) on squares of one color can never give mate
+    const LIGHT_SQUARES: u64 = 0x55AA_55AA_55AA_55AA;
+    knights.is_empty() && (bishops.0 & LIGHT_SQUARES == 0 || bishops.0 & !LIGHT_SQUARES == 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::{Color, Square};
+
+    fn play(game: &mut Game, moves: &str) {
+        for mv in moves.split_whitespace() {
+            game.make_move(ChessMove::from_uci(mv).unwrap()).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_make_move_and_undo() {
+        let mut game = Game::default();
+        play(&mut game, "e2e4 e7e5");
+        assert_eq!(game.moves().len(), 2);
+        assert_eq!(game.board().side_to_move(), Color::White);
+        assert!(game.make_move(ChessMove::from_uci("e4e5").unwrap()).is_err());
+
+        assert_eq!(game.undo(), ChessMove::from_uci("e7e5"));
+        assert_eq!(game.undo(), ChessMove::from_uci("e2e4"));
+        assert_eq!(game.undo(), None);
+        assert_eq!(game.board(), game.start_position());
+    }
+
+    #[test]
+    fn test_castling_and_en_passant_from_squares() {
+        let mut game = Game::default();
+        play(&mut game, "e2e4 a7a6 e4e5 d7d5 e5d6 a6a5 g1f3 a5a4 f1e2 a4a3 e1g1");
+        let board = game.board();
+        assert_eq!(board.to_string(), "rnbqkbnr/1pp1pppp/3P4/8/8/p4N2/PPPPBPPP/RNBQ1RK1 b kq - 1 6");
+        assert!(game.moves()[4].is_en_passant() && game.moves()[10].is_castling());
+        assert_eq!(Game::default().make_move(ChessMove::new(Square::E2, Square::E5, None)).map_err(|_| ()), Err(()));
+    }
+
+    #[test]
+    fn test_threefold_repetition() {
+        let mut game = Game::default();
+        play(&mut game, "g1f3 g8f6 f3g1 f6g8");
+        assert_eq!(game.repetition_count(), 2);
+        assert!(!game.is_threefold_repetition());
+        play(&mut game, "g1f3 g8f6 f3g1 f6g8");
+        assert!(game.is_threefold_repetition());
+        assert_eq!(game.draw_reason(), Some(DrawReason::ThreefoldRepetition));
+        assert_eq!(game.hashes().len(), 9);
+        assert_eq!(game.repetition_history(), game.hashes());
+
+        // A pawn move cuts the history to the position after it
+        play(&mut game, "e2e4 g8f6");
+        assert_eq!(game.repetition_history(), game.hashes()[9..]);
+        // So does a halfmove clock from a FEN
+        let game: Game = "4k3/8/8/8/8/8/8/R3K3 w - - 7 80".parse().unwrap();
+        assert_eq!(game.repetition_history().len(), 1);
+    }
+
+    #[test]
+    fn test_fifty_moves() {
+        let mut game: Game = "4k3/8/8/8/8/8/8/R3K3 w - - 99 80".parse().unwrap();
+        assert!(!game.is_fifty_moves());
+        play(&mut game, "a1a2");
+        assert!(game.is_fifty_moves());
+        assert_eq!(game.draw_reason(), Some(DrawReason::FiftyMoves));
+    }
+
+    #[test]
+    fn test_stalemate() {
+        let game: Game = "7k/5Q2/6K1/8/8/8/8/8 b - - 0 1".parse().unwrap();
+        assert_eq!(game.draw_reason(), Some(DrawReason::Stalemate));
+    }
+
+    #[test]
+    fn test_insufficient_material() {
+        for (fen, dead) in [
+            ("4k3/8/8/8/8/8/8/4K3 w - - 0 1", true),
+            ("4k3/8/8/8/8/8/8/4KN2 w - - 0 1", true),
+            ("4k3/8/8/8/8/8/8/2B1K3 w - - 0 1", true),
+            ("2b1k3/8/8/8/8/8/8/3BK3 w - - 0 1", true),   // c8 and d1 are both light squares
+            ("3bk3/8/8/8/8/8/8/3BK3 w - - 0 1", false),   // opposite-colored bishops
+            ("4k3/8/8/8/8/8/8/3NKN2 w - - 0 1", false),
+            ("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1", false),
+        ] {
+            assert_eq!(insufficient_material(&Board::from_str(fen).unwrap()), dead, "{}", fen);
+        }
+    }
+}
+
+// Game keeps the full stack of positions rather than just hashes: undo is a pop, and the current
+// board is always the top of the stack, so nothing has to be replayed. The repetition window is
+// bounded by the halfmove clock because a capture or pawn move makes every earlier position
+// unreachable, and the search is only handed that window, so a long game costs it nothing. Draw reasons are reported, not enforced: the UCI layer plays on, and a GUI decides
+// whether to claim.
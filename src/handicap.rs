@@ -0,0 +1,96 @@
+use std::str::FromStr;
+
+use crate::board::{Board, Color, Square};
+
+/// Material one side gives up before the game: the f-pawn, the queen's knight, the queen's rook
+/// or the queen, as in the classical odds games
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Odds {
+    Pawn,
+    Knight,
+    Rook,
+    Queen,
+}
+
+impl Odds {
+    pub fn name(self) -> &'static str {
+        match self {
+            Odds::Pawn => "pawn",
+            Odds::Knight => "knight",
+            Odds::Rook => "rook",
+            Odds::Queen => "queen",
+        }
+    }
+
+    /// Where the removed piece starts for `giver`
+    pub fn square(self, giver: Color) -> Square {
+        // f2, b1, a1 and d1, mirrored to the eighth rank for Black
+        let index = match self {
+            Odds::Pawn => 13,
+            Odds::Knight => 1,
+            Odds::Rook => 0,
+            Odds::Queen => 3,
+        };
+        Square::new(if giver == Color::White { index } else { index ^ 56 })
+    }
+}
+
+/// `pawn`, `knight`, `rook` or `queen`, any case
+impl FromStr for Odds {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s.to_lowercase().as_str() {
+            "pawn" => Ok(Odds::Pawn),
+            "knight" => Ok(Odds::Knight),
+            "rook" => Ok(Odds::Rook),
+            "queen" => Ok(Odds::Queen),
+            _ => Err(format!("Invalid odds: {} (expected pawn, knight, rook or queen)", s)),
+        }
+    }
+}
+
+/// The standard start position with `giver` playing without the odds piece
+pub fn odds_position(odds: Option<Odds>, giver: Color) -> Board {
+    let board = Board::default();
+    match odds {
+        Some(odds) => board.without_piece(odds.square(giver)).expect("odds pieces are on their start squares"),
+        None => board,
+    }
+}
+
+/// `white` or `black`, any case
+pub fn parse_side(s: &str) -> Option<Color> {
+    match s.to_lowercase().as_str() {
+        "white" => Some(Color::White),
+        "black" => Some(Color::Black),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_odds_positions() {
+        assert_eq!(odds_position(None, Color::White), Board::default());
+        let fens = [
+            (Odds::Pawn, Color::White, "rnbqkbnr/pppppppp/8/8/8/8/PPPPP1PP/RNBQKBNR w KQkq - 0 1"),
+            (Odds::Knight, Color::White, "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/R1BQKBNR w KQkq - 0 1"),
+            (Odds::Rook, Color::Black, "1nbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQk - 0 1"),
+            (Odds::Queen, Color::Black, "rnb1kbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"),
+        ];
+        for (odds, giver, fen) in fens {
+            assert_eq!(odds_position(Some(odds), giver).to_string(), fen);
+            assert_eq!(odds.name().parse(), Ok(odds));
+        }
+        assert!("bishop".parse::<Odds>().is_err());
+        assert_eq!(parse_side("Black"), Some(Color::Black));
+    }
+}
+
+// Odds are a start position, not a search setting: the search plays whatever it is given, so
+// a handicap only has to change what `startpos` and a new game mean. The removed pieces are the
+// traditional ones (queen's-side pieces, so the giver keeps short castling), which keeps the
+// positions recognisable and lets an opening book built from odds games with makebook match them.
@@ -0,0 +1,156 @@
+use crate::board::{Board, ChessMove};
+use crate::search::pv_to_uci;
+use crate::types::{Score, ScoreBound, SearchInfo, SearchResult, SCORE_MATE};
+use crate::wdl::Wdl;
+
+/// A search update as one JSON line, e.g.
+/// `{"type":"info","depth":7,"multipv":1,"score":{"cp":43},"nodes":71823,"time":1235,"nps":58156,"pv":["g1f3","d7d5"]}`.
+/// `bound` ("lower" or "upper") is added for an aspiration fail, `wdl` as `[win,draw,loss]`
+/// permille when given.
+pub fn info_json(board: &Board, info: &SearchInfo, wdl: Option<Wdl>) -> String {
+    let mut fields = vec![
+        r#""type":"info""#.to_string(),
+        format!(r#""depth":{}"#, info.depth),
+        format!(r#""multipv":{}"#, info.multipv),
+        score_json(info.score),
+    ];
+    match info.bound {
+        ScoreBound::Exact => {}
+        ScoreBound::Lower => fields.push(r#""bound":"lower""#.to_string()),
+        ScoreBound::Upper => fields.push(r#""bound":"upper""#.to_string()),
+    }
+    if let Some(Wdl { win, draw, loss }) = wdl {
+        fields.push(format!(r#""wdl":[{},{},{}]"#, win, draw, loss));
+    }
+    fields.extend([
+        format!(r#""nodes":{}"#, info.nodes),
+        format!(r#""time":{}"#, info.time_ms),
+        format!(r#""nps":{}"#, info.nps),
+        format!(r#""pv":{}"#, pv_json(board, &info.pv)),
+    ]);
+    format!("{{{}}}", fields.join(","))
+}
+
+/// A finished search as one JSON line: `{"type":"bestmove","bestmove":"g1f3",...}` with the
+/// fields of `SearchResult`; `bestmove` is null without legal moves, `partialdepth` only
+/// appears when an iteration was cut short, and `visits` lists the root moves of an MCTS
+/// search as `{"move":"e2e4","visits":412,"score":{"cp":31},"prior":0.214}`
+pub fn result_json(board: &Board, result: &SearchResult) -> String {
+    let best_move = result.best_move.map_or("null".to_string(), |mv| format!(r#""{}""#, board.move_to_uci(mv)));
+    let mut fields = vec![
+        r#""type":"bestmove""#.to_string(),
+        format!(r#""bestmove":{}"#, best_move),
+        format!(r#""depth":{}"#, result.depth),
+    ];
+    if let Some(depth) = result.partial_depth {
+        fields.push(format!(r#""partialdepth":{}"#, depth));
+    }
+    fields.extend([
+        format!(r#""seldepth":{}"#, result.seldepth),
+        score_json(result.score),
+        format!(r#""nodes":{}"#, result.nodes),
+        format!(r#""time":{}"#, result.time_ms),
+        format!(r#""nps":{}"#, result.nps),
+        format!(r#""hashfull":{}"#, result.hashfull),
+        format!(r#""tbhits":{}"#, result.tb_hits),
+        format!(r#""pv":{}"#, pv_json(board, &result.pv)),
+    ]);
+    if !result.stats.root_visits.is_empty() {
+        let visits: Vec<String> = result
+            .stats
+            .root_visits
+            .iter()
+            .map(|v| format!(r#"{{"move":"{}","visits":{},{},"prior":{:.3}}}"#, board.move_to_uci(v.mv), v.visits, score_json(v.score), v.prior))
+            .collect();
+        fields.push(format!(r#""visits":[{}]"#, visits.join(",")));
+    }
+    format!("{{{}}}", fields.join(","))
+}
+
+/// `"score":{"cp":N}`, or `"score":{"mate":N}` in moves, negative when getting mated
+fn score_json(score: Score) -> String {
+    if score.abs() > SCORE_MATE - 100 {
+        let moves = (SCORE_MATE - score.abs() + 1) / 2;
+        format!(r#""score":{{"mate":{}}}"#, if score > 0 { moves } else { -moves })
+    } else {
+        format!(r#""score":{{"cp":{}}}"#, score)
+    }
+}
+
+/// The PV as an array of UCI move strings, which need no escaping
+fn pv_json(board: &Board, pv: &[ChessMove]) -> String {
+    let moves: Vec<String> = pv_to_uci(board, pv).split_whitespace().map(|mv| format!(r#""{}""#, mv)).collect();
+    format!("[{}]", moves.join(","))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{RootVisits, SearchStats};
+    use serde_json::{json, Value};
+
+    fn e2e4_e7e5() -> Vec<ChessMove> {
+        ["e2e4", "e7e5"].iter().map(|m| ChessMove::from_uci(m).unwrap()).collect()
+    }
+
+    #[test]
+    fn test_info_json() {
+        let board = Board::default();
+        let info = SearchInfo { depth: 3, multipv: 1, score: 25, bound: ScoreBound::Lower, nodes: 1000, time_ms: 4, nps: 250_000, pv: e2e4_e7e5() };
+        let value: Value = serde_json::from_str(&info_json(&board, &info, None)).unwrap();
+        assert_eq!(
+            value,
+            json!({"type": "info", "depth": 3, "multipv": 1, "score": {"cp": 25}, "bound": "lower", "nodes": 1000, "time": 4, "nps": 250000, "pv": ["e2e4", "e7e5"]})
+        );
+
+        let mated = SearchInfo { score: -(SCORE_MATE - 4), bound: ScoreBound::Exact, pv: Vec::new(), ..info };
+        let wdl = Wdl { win: 0, draw: 0, loss: 1000 };
+        let value: Value = serde_json::from_str(&info_json(&board, &mated, Some(wdl))).unwrap();
+        assert_eq!(value["score"], json!({"mate": -2}));
+        assert_eq!(value["wdl"], json!([0, 0, 1000]));
+        assert_eq!(value["pv"], json!([]));
+        assert!(value.get("bound").is_none());
+    }
+
+    #[test]
+    fn test_result_json() {
+        let board = Board::default();
+        let pv = e2e4_e7e5();
+        let result = SearchResult {
+            best_move: Some(pv[0]),
+            score: SCORE_MATE - 1,
+            depth: 5,
+            partial_depth: None,
+            seldepth: 9,
+            nodes: 5000,
+            time_ms: 20,
+            nps: 250_000,
+            hashfull: 3,
+            tb_hits: 0,
+            pv,
+            stats: Box::default(),
+        };
+        let value: Value = serde_json::from_str(&result_json(&board, &result)).unwrap();
+        assert_eq!(value["type"], "bestmove");
+        assert_eq!(value["bestmove"], "e2e4");
+        assert_eq!(value["score"], json!({"mate": 1}));
+        assert_eq!((value["seldepth"].as_u64(), value["hashfull"].as_u64()), (Some(9), Some(3)));
+        assert!(value.get("partialdepth").is_none() && value.get("visits").is_none());
+
+        let visits = RootVisits { mv: result.pv[0], visits: 40, score: 31, prior: 0.25 };
+        let stats = SearchStats { root_visits: vec![visits], ..Default::default() };
+        let mcts = SearchResult { stats: Box::new(stats), ..result.clone() };
+        let value: Value = serde_json::from_str(&result_json(&board, &mcts)).unwrap();
+        assert_eq!(value["visits"], json!([{"move": "e2e4", "visits": 40, "score": {"cp": 31}, "prior": 0.25}]));
+
+        let none = SearchResult { best_move: None, partial_depth: Some(6), pv: Vec::new(), ..result };
+        let value: Value = serde_json::from_str(&result_json(&board, &none)).unwrap();
+        assert!(value["bestmove"].is_null());
+        assert_eq!((value["depth"].as_u64(), value["partialdepth"].as_u64()), (Some(5), Some(6)));
+    }
+}
+
+// JSON is written by hand rather than through serde: the output is a handful of numbers and UCI
+// move strings, none of which need escaping, and the JSON mode has to work in builds without
+// the `serde` feature. Field names follow the UCI keywords (`time`, `nps`, `tbhits`) so a
+// script can switch formats without relearning the vocabulary.
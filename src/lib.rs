@@ -1,9 +1,108 @@
+//! Ferrite: a UCI chess engine, also usable as a library.
+//!
+//! The common entry points are re-exported at the crate root: [`Board`] and [`MoveGen`] for
+//! positions and legal moves, [`Game`] for a move history with draw detection, [`search()`]
+//! and [`evaluate`] for analysis, [`Engine`] to run searches on a worker thread and receive
+//! events over a channel, and [`uci_loop`] to run the engine over stdin/stdout.
+//! Fallible calls return error enums ([`ParseFenError`], [`ParseMoveError`], [`MoveError`],
+//! [`BookError`], [`SyzygyLoadError`], [`OptionError`], [`ConfigError`]) that implement `std::error::Error`.
+//! The modules below hold the rest; search/eval tables and tuning parameters stay private.
+//!
+//! Without the default `std` feature the crate is `no_std` (it still needs `alloc`) and keeps
+//! only the board, move generation, evaluation and the plain data types, for embedded targets.
+
+// Unit tests get std either way, so they run (and can use it) without the std feature too
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+#[macro_use]
+extern crate alloc;
+
+#[cfg(feature = "tokio")]
+pub mod async_engine;
+#[cfg(feature = "std")]
+pub mod annotate;
 pub mod board;
+#[cfg(feature = "std")]
+pub mod book;
+#[cfg(feature = "std")]
+pub mod config;
+#[cfg(feature = "std")]
+pub mod datagen;
+#[cfg(feature = "std")]
+pub mod engine;
+#[cfg(feature = "std")]
+pub mod epd;
 pub mod evaluation;
+#[cfg(feature = "std")]
+pub mod game;
+#[cfg(feature = "std")]
+pub mod handicap;
+#[cfg(feature = "std")]
+pub mod json;
 pub mod movegen;
-pub mod pst;
+pub mod nnue;
+#[cfg(feature = "std")]
+pub mod numa;
+#[cfg(feature = "std")]
+pub mod mcts;
+#[cfg(feature = "std")]
+pub mod opponent;
+#[cfg(feature = "std")]
+pub mod perft;
+#[cfg(feature = "std")]
+pub mod pgn;
+#[cfg(feature = "std")]
+pub mod puzzles;
+#[cfg(feature = "std")]
 pub mod search;
+#[cfg(feature = "std")]
+pub mod selfplay;
+#[cfg(all(feature = "std", not(target_arch = "wasm32")))]
+pub mod signals;
+#[cfg(feature = "std")]
 pub mod syzygy;
-pub mod time;
+#[cfg(feature = "std")]
+pub mod trace;
+#[cfg(feature = "std")]
 pub mod tt;
 pub mod types;
+#[cfg(feature = "std")]
+pub mod uci;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+#[cfg(feature = "std")]
+pub mod wdl;
+
+mod endgame;
+#[cfg(feature = "std")]
+mod output;
+mod pst;
+#[cfg(feature = "std")]
+mod smp;
+#[cfg(feature = "std")]
+mod time;
+mod tune;
+
+pub use board::{Board, BoardStatus, ChessMove, Color, MoveError, MoveGen, ParseFenError, ParseMoveError, Piece, Square};
+#[cfg(feature = "std")]
+pub use book::BookError;
+#[cfg(feature = "std")]
+pub use config::{Config, ConfigError};
+#[cfg(feature = "tokio")]
+pub use async_engine::AsyncEngine;
+#[cfg(feature = "std")]
+pub use engine::{Engine, EngineEvent, OptionError};
+pub use evaluation::evaluate;
+#[cfg(feature = "std")]
+pub use game::{DrawReason, Game};
+#[cfg(feature = "std")]
+pub use pgn::{PgnGame, PgnReader};
+#[cfg(feature = "std")]
+pub use search::{search, SearchState, SearchTables};
+#[cfg(feature = "std")]
+pub use syzygy::SyzygyLoadError;
+pub use types::{Score, ScoreBound, SearchInfo, SearchLimits, SearchResult, SearchStats};
+#[cfg(feature = "std")]
+pub use uci::run as uci_loop;
+#[cfg(feature = "wasm")]
+pub use wasm::WasmEngine;
@@ -1,15 +1,949 @@
-mod board;
-mod evaluation;
-mod movegen;
-mod pst;
-mod search;
-mod syzygy;
-mod time;
-mod tt;
-mod types;
-mod uci;
+use std::fs;
+use std::io::{BufReader, BufWriter, Write};
+use std::process;
+use std::sync::atomic::Ordering;
+use std::str::FromStr;
+use std::time::Instant;
+
+use ferrite::annotate::{self, AnnotateConfig};
+use ferrite::board::{self, Board};
+use ferrite::book::BookBuilder;
+use ferrite::datagen::{self, DataFormat, DatagenConfig};
+use ferrite::epd::EpdRecord;
+use ferrite::handicap::{self, Odds};
+use ferrite::nnue::FloatNetwork;
+use ferrite::puzzles::{self, PuzzleConfig};
+use ferrite::search::{format_score, pv_to_uci};
+use ferrite::selfplay::{self, SelfplayConfig, TimeControl};
+use ferrite::{
+    json, perft, pgn, signals, uci, Color, Config, Engine, EngineEvent, Game, PgnReader, Score, ScoreBound, SearchInfo,
+    SearchLimits, SearchResult,
+};
+
+const USAGE: &str = "\
+usage: ferrite [options] [command]
+
+commands:
+  uci (default)                 speak UCI on stdin/stdout
+  bench [depth] [threads] [hash]
+                                search the bench positions, print total nodes and nps
+  perft <depth> [fen]           divided perft of the start position or <fen>
+  perftsuite <file>             check the `FEN; depth; nodes` lines of <file>, printing the
+                                divide of every mismatch (--threads, default: all cores)
+  analyze <fen> [--depth N | --movetime MS] [--multipv K] [--mcts]
+                                search one position, printing each iteration, then the
+                                evaluation, the top K lines in SAN and search stats;
+                                --mcts ranks the moves by Monte-Carlo tree search visits
+  annotate <pgn> [--depth N | --movetime MS]
+                                search every position of each game and write the PGN
+                                back with eval comments and ?!/?/?? NAGs on bad moves
+  puzzles <pgn> [--depth N | --movetime MS]
+                                positions in the games where a mistake left one winning
+                                reply, as EPD lines (bm, pv, ce, id) on stdout
+  selfplay [--games N] [--depth N | --movetime MS | --tc SECS+INC]
+           [--openings FILE] [--random-plies N] [--seed N]
+                                play the engine against itself, writing PGN to stdout
+  makebook <out> <pgn>... [--max-ply N] [--min-games N]
+                                build an opening book from PGN files
+  datagen <out> [--games N] [--nodes N | --depth N] [--random-plies N]
+          [--sample-rate F] [--seed N]
+                                self-play training data as `fen | score | result` lines, or
+                                bullet binary records if <out> ends in .bin or .data; one
+                                game per thread (--threads, default: all cores)
+  convert <in> <out>            convert training data between text and bullet binary
+  quantize <checkpoint> <out> [--verify FILE] [--tolerance CP]
+                                quantize a float (768 -> H)x2 -> 1 checkpoint to a net file,
+                                if its mean eval error on the bench positions and the
+                                positions in FILE stays within CP (default 10)
+
+options:
+  --config <file>               engine defaults as `UCI option = value` lines (TOML or INI);
+                                the flags below and `setoption` override them
+  --hash <MB>                   transposition table size
+  --threads <N>                 search threads, sharing the TT (Lazy SMP)
+  --syzygy-path <path>          Syzygy tablebase directory (also --syzygy)
+  --eval-file <file>            NNUE network file
+  --book <file>                 opening book (see makebook) to play from in UCI mode
+  --odds <piece>                start games without a pawn, knight, rook or queen
+  --odds-side <white|black>     the side that gives the odds (default white)
+  --skill <0-20>                cap playing strength (Skill Level)
+  --variety <plies>             vary the first plies of each game (OpeningVariety)
+  --json                        info and bestmove as JSON lines (UCI mode and analyze)";
+
+const DEFAULT_BENCH_DEPTH: u8 = 7;
+const DEFAULT_ANALYZE_DEPTH: u8 = 12;
+/// Root moves `analyze --mcts` lists, most visited first
+const MCTS_SHOWN_MOVES: usize = 8;
+const DEFAULT_BOOK_MAX_PLY: usize = 24;
+const DEFAULT_BOOK_MIN_GAMES: u32 = 3;
+const DEFAULT_QUANTIZE_TOLERANCE: f32 = 10.0;
+
+/// Middlegame, endgame and tactical positions searched by `bench`
+const BENCH_FENS: &[&str] = &[
+    "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+    "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 10",
+    "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 11",
+    "4rrk1/pp1n3p/3q2pQ/2p1pb2/2PP4/2P3N1/P2B2PP/4RRK1 b - - 7 19",
+    "rq3rk1/ppp2ppp/1bnpb3/3N2B1/3NP3/7P/PPPQ1PP1/2KR3R w - - 7 14",
+    "r1bq1r1k/1pp1n1pp/1p1p4/4p2Q/4Pp2/1BNP4/PPP2PPP/3R1RK1 w - - 2 14",
+    "r3r1k1/2p2ppp/p1p1bn2/8/1q2P3/2NPQN2/PPP3PP/R4RK1 b - - 2 15",
+    "6k1/6p1/6Pp/ppp5/3pn2P/1P3K2/1PP2P2/3N4 b - - 0 1",
+    "3b4/5kp1/1p1p1p1p/pP1PpP1P/P1P1P3/3KN3/8/8 w - - 0 1",
+    "8/8/8/8/5kp1/P7/8/1K1N4 w - - 0 1",
+];
+
+#[derive(Debug, PartialEq)]
+enum Command {
+    Help,
+    Uci,
+    Bench { depth: u8 },
+    Perft { depth: u32, fen: Option<String> },
+    PerftSuite { file: String },
+    Analyze { fen: String, limits: SearchLimits, multipv: usize, mcts: bool },
+    Annotate { pgn_file: String, config: AnnotateConfig },
+    Puzzles { pgn_file: String, config: PuzzleConfig },
+    Selfplay { config: SelfplayConfig, openings_file: Option<String> },
+    MakeBook { output: String, pgn_files: Vec<String>, max_ply: usize, min_games: u32 },
+    Datagen { output: String, config: DatagenConfig },
+    Convert { input: String, output: String },
+    Quantize { checkpoint: String, output: String, verify_file: Option<String>, tolerance: f32 },
+}
+
+/// Engine options given on the command line, applied before any command runs
+#[derive(Debug, Default, PartialEq)]
+struct Options {
+    config_file: Option<String>,
+    /// Options from the config file, applied before the flags
+    config: Config,
+    hash_mb: Option<usize>,
+    threads: Option<usize>,
+    syzygy_path: Option<String>,
+    eval_file: Option<String>,
+    book_file: Option<String>,
+    odds: Option<Odds>,
+    odds_side: Option<Color>,
+    skill: Option<u8>,
+    variety: Option<u8>,
+    json: bool,
+}
+
+impl Options {
+    /// As UCI `(name, value)` pairs
+    fn uci_options(&self) -> Vec<(String, String)> {
+        let mut options = self.config.options.clone();
+        if let Some(mb) = self.hash_mb {
+            options.push(("Hash".to_string(), mb.to_string()));
+        }
+        if let Some(n) = self.threads {
+            options.push(("Threads".to_string(), n.to_string()));
+        }
+        if let Some(path) = &self.syzygy_path {
+            options.push(("SyzygyPath".to_string(), path.clone()));
+        }
+        if let Some(path) = &self.eval_file {
+            options.push(("EvalFile".to_string(), path.clone()));
+        }
+        if let Some(path) = &self.book_file {
+            options.push(("BookFile".to_string(), path.clone()));
+        }
+        if let Some(odds) = self.odds {
+            options.push(("Odds".to_string(), odds.name().to_string()));
+        }
+        if let Some(side) = self.odds_side {
+            let name = if side == Color::White { "white" } else { "black" };
+            options.push(("OddsSide".to_string(), name.to_string()));
+        }
+        if let Some(level) = self.skill {
+            options.push(("Skill Level".to_string(), level.to_string()));
+        }
+        if let Some(plies) = self.variety {
+            options.push(("OpeningVariety".to_string(), plies.to_string()));
+        }
+        if self.json {
+            options.push(("OutputFormat".to_string(), "json".to_string()));
+        }
+        options
+    }
+
+    /// The options for the offline commands. The book is left out: bench, analyze, annotate, puzzles and selfplay
+    /// measure the search.
+    fn search_options(&self) -> Vec<(String, String)> {
+        self.uci_options().into_iter().filter(|(name, _)| !name.to_lowercase().starts_with("bookfile")).collect()
+    }
+
+    fn engine(&self) -> Engine {
+        let engine = Engine::new();
+        for (name, value) in self.search_options() {
+            engine.set_option(&name, &value);
+        }
+        engine
+    }
+}
 
 fn main() {
-    board::init();
-    uci::run();
+    #[cfg(feature = "tracing")]
+    ferrite::trace::init();
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let (command, mut options) = match parse_args(&args) {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, USAGE);
+            process::exit(2);
+        }
+    };
+    if let Some(path) = &options.config_file {
+        options.config = Config::load(path).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            process::exit(2);
+        });
+    }
+
+    board::init(); // warm the lazy tables before the GUI starts the clock
+    match command {
+        Command::Help => println!("{}", USAGE),
+        Command::Uci => {
+            let uci_options = options.uci_options();
+            let pairs: Vec<(&str, &str)> = uci_options.iter().map(|(n, v)| (n.as_str(), v.as_str())).collect();
+            uci::run_with_options(&pairs);
+        }
+        Command::Bench { depth } => bench(&options, depth),
+        Command::Perft { depth, fen } => {
+            let board = parse_fen(fen.as_deref());
+            perft::run(&board, depth, true);
+        }
+        Command::PerftSuite { file } => perft_suite(&file, options.threads),
+        Command::Analyze { fen, limits, multipv, mcts } => analyze(&options, &parse_fen(Some(&fen)), &limits, multipv, mcts),
+        Command::Annotate { pgn_file, config } => annotate_games(&options, &pgn_file, &config),
+        Command::Puzzles { pgn_file, config } => find_puzzles(&options, &pgn_file, &config),
+        Command::Selfplay { config, openings_file } => selfplay(&options, config, openings_file.as_deref()),
+        Command::MakeBook { output, pgn_files, max_ply, min_games } => make_book(&output, &pgn_files, max_ply, min_games),
+        Command::Datagen { output, mut config } => {
+            config.adjudication.syzygy_path = options.syzygy_path.clone();
+            generate_data(&output, &config)
+        }
+        Command::Convert { input, output } => convert_data(&input, &output),
+        Command::Quantize { checkpoint, output, verify_file, tolerance } => {
+            quantize(&checkpoint, &output, verify_file.as_deref(), tolerance)
+        }
+    }
+}
+
+fn parse_args(args: &[String]) -> Result<(Command, Options), String> {
+    let mut options = Options::default();
+    let mut depth: Option<String> = None;
+    let mut games: Option<String> = None;
+    let mut movetime: Option<String> = None;
+    let mut tc: Option<String> = None;
+    let mut openings_file: Option<String> = None;
+    let mut random_plies: Option<String> = None;
+    let mut seed: Option<String> = None;
+    let mut max_ply: Option<String> = None;
+    let mut min_games: Option<String> = None;
+    let mut nodes: Option<String> = None;
+    let mut sample_rate: Option<String> = None;
+    let mut verify_file: Option<String> = None;
+    let mut tolerance: Option<String> = None;
+    let mut multipv: Option<String> = None;
+    let mut mcts = false;
+    let mut positional: Vec<&str> = Vec::new();
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        let mut value = || iter.next().cloned().ok_or_else(|| format!("{} needs a value", arg));
+        match arg.as_str() {
+            "--config" => options.config_file = Some(value()?),
+            "--hash" => options.hash_mb = Some(parse_number(&value()?, arg)?),
+            "--threads" => options.threads = Some(parse_number(&value()?, arg)?),
+            "--syzygy" | "--syzygy-path" => options.syzygy_path = Some(value()?),
+            "--eval-file" => options.eval_file = Some(value()?),
+            "--book" => options.book_file = Some(value()?),
+            "--odds" => options.odds = Some(value()?.parse()?),
+            "--odds-side" => {
+                let side = value()?;
+                options.odds_side = Some(handicap::parse_side(&side).ok_or_else(|| format!("invalid --odds-side value {}", side))?);
+            }
+            "--skill" => options.skill = Some(parse_number(&value()?, arg)?),
+            "--variety" => options.variety = Some(parse_number(&value()?, arg)?),
+            "--json" => options.json = true,
+            "--depth" => depth = Some(value()?),
+            "--games" => games = Some(value()?),
+            "--movetime" => movetime = Some(value()?),
+            "--tc" => tc = Some(value()?),
+            "--openings" => openings_file = Some(value()?),
+            "--random-plies" => random_plies = Some(value()?),
+            "--seed" => seed = Some(value()?),
+            "--max-ply" => max_ply = Some(value()?),
+            "--min-games" => min_games = Some(value()?),
+            "--nodes" => nodes = Some(value()?),
+            "--sample-rate" => sample_rate = Some(value()?),
+            "--verify" => verify_file = Some(value()?),
+            "--tolerance" => tolerance = Some(value()?),
+            "--multipv" => multipv = Some(value()?),
+            "--mcts" => mcts = true,
+            "-h" | "--help" => return Ok((Command::Help, options)),
+            flag if flag.starts_with("--") => return Err(format!("unknown option {}", flag)),
+            _ => positional.push(arg),
+        }
+    }
+
+    let (name, rest) = positional.split_first().map_or(("uci", &[][..]), |(n, r)| (*n, r));
+    let command = match name {
+        "uci" => Command::Uci,
+        "bench" => {
+            // OpenBench runs `bench <depth> <threads> <hash>`
+            let [depth_arg, threads, hash] = [rest.first(), rest.get(1), rest.get(2)];
+            if rest.len() > 3 {
+                return Err("bench takes at most a depth, a thread count and a hash size".to_string());
+            }
+            if let Some(n) = threads {
+                options.threads = Some(parse_number(n, "threads")?);
+            }
+            if let Some(mb) = hash {
+                options.hash_mb = Some(parse_number(mb, "hash")?);
+            }
+            let depth = depth.as_deref().or(depth_arg.copied());
+            Command::Bench { depth: depth.map_or(Ok(DEFAULT_BENCH_DEPTH), |d| parse_number(d, "depth"))? }
+        }
+        "perft" => {
+            let (depth, fen) = rest.split_first().ok_or("perft needs a depth")?;
+            let fen = (!fen.is_empty()).then(|| fen.join(" "));
+            Command::Perft { depth: parse_number(depth, "depth")?, fen }
+        }
+        "perftsuite" => {
+            let [file] = rest else {
+                return Err("perftsuite needs one suite file".to_string());
+            };
+            Command::PerftSuite { file: file.to_string() }
+        }
+        "analyze" => {
+            if rest.is_empty() {
+                return Err("analyze needs a FEN".to_string());
+            }
+            let limits = match (depth, movetime) {
+                (Some(d), None) => SearchLimits::depth(parse_number(&d, "--depth")?),
+                (None, Some(ms)) => SearchLimits { movetime: Some(parse_number(&ms, "--movetime")?), ..Default::default() },
+                (None, None) => SearchLimits::depth(DEFAULT_ANALYZE_DEPTH),
+                _ => return Err("use only one of --depth and --movetime".to_string()),
+            };
+            let multipv = multipv.map_or(Ok(1), |k| parse_number(&k, "--multipv"))?;
+            if multipv == 0 {
+                return Err("--multipv needs at least one line".to_string());
+            }
+            Command::Analyze { fen: rest.join(" "), limits, multipv, mcts }
+        }
+        "annotate" => {
+            let [pgn_file] = rest else {
+                return Err("annotate needs one PGN file".to_string());
+            };
+            let mut config = AnnotateConfig::default();
+            config.limits = match (depth, movetime) {
+                (Some(d), None) => SearchLimits::depth(parse_number(&d, "--depth")?),
+                (None, Some(ms)) => SearchLimits { movetime: Some(parse_number(&ms, "--movetime")?), ..Default::default() },
+                (None, None) => config.limits,
+                _ => return Err("use only one of --depth and --movetime".to_string()),
+            };
+            Command::Annotate { pgn_file: pgn_file.to_string(), config }
+        }
+        "puzzles" => {
+            let [pgn_file] = rest else {
+                return Err("puzzles needs one PGN file".to_string());
+            };
+            let mut config = PuzzleConfig::default();
+            config.limits = match (depth, movetime) {
+                (Some(d), None) => SearchLimits::depth(parse_number(&d, "--depth")?),
+                (None, Some(ms)) => SearchLimits { movetime: Some(parse_number(&ms, "--movetime")?), ..Default::default() },
+                (None, None) => config.limits,
+                _ => return Err("use only one of --depth and --movetime".to_string()),
+            };
+            Command::Puzzles { pgn_file: pgn_file.to_string(), config }
+        }
+        "selfplay" => {
+            let mut config = SelfplayConfig::default();
+            if let Some(g) = games {
+                config.games = parse_number(&g, "--games")?;
+            }
+            config.time_control = match (depth, movetime, tc) {
+                (Some(d), None, None) => TimeControl::Depth(parse_number(&d, "--depth")?),
+                (None, Some(ms), None) => TimeControl::MoveTime(parse_number(&ms, "--movetime")?),
+                (None, None, Some(tc)) => tc.parse()?,
+                (None, None, None) => config.time_control,
+                _ => return Err("use only one of --depth, --movetime and --tc".to_string()),
+            };
+            if let Some(n) = random_plies {
+                config.random_plies = parse_number(&n, "--random-plies")?;
+            }
+            if let Some(n) = seed {
+                config.seed = parse_number(&n, "--seed")?;
+            }
+            Command::Selfplay { config, openings_file }
+        }
+        "makebook" => {
+            let [output, pgn_files @ ..] = rest else {
+                return Err("makebook needs an output file".to_string());
+            };
+            if pgn_files.is_empty() {
+                return Err("makebook needs at least one PGN file".to_string());
+            }
+            Command::MakeBook {
+                output: output.to_string(),
+                pgn_files: pgn_files.iter().map(|f| f.to_string()).collect(),
+                max_ply: max_ply.map_or(Ok(DEFAULT_BOOK_MAX_PLY), |n| parse_number(&n, "--max-ply"))?,
+                min_games: min_games.map_or(Ok(DEFAULT_BOOK_MIN_GAMES), |n| parse_number(&n, "--min-games"))?,
+            }
+        }
+        "datagen" => {
+            let [output] = rest else {
+                return Err("datagen needs one output file".to_string());
+            };
+            let mut config = DatagenConfig::default();
+            if let Some(g) = games {
+                config.games = parse_number(&g, "--games")?;
+            }
+            config.limits = match (depth, nodes) {
+                (Some(d), None) => SearchLimits::depth(parse_number(&d, "--depth")?),
+                (None, Some(n)) => SearchLimits { nodes: Some(parse_number(&n, "--nodes")?), ..Default::default() },
+                (None, None) => config.limits,
+                _ => return Err("use only one of --depth and --nodes".to_string()),
+            };
+            if let Some(n) = random_plies {
+                config.random_plies = parse_number(&n, "--random-plies")?;
+            }
+            if let Some(rate) = sample_rate {
+                config.sample_rate = parse_number::<f64>(&rate, "--sample-rate")?;
+                if !(0.0..=1.0).contains(&config.sample_rate) {
+                    return Err(format!("--sample-rate {} is not between 0 and 1", rate));
+                }
+            }
+            if let Some(n) = seed {
+                config.seed = parse_number(&n, "--seed")?;
+            }
+            if let Some(n) = options.threads {
+                config.threads = n;
+            }
+            if let Some(mb) = options.hash_mb {
+                config.hash_mb = mb;
+            }
+            Command::Datagen { output: output.to_string(), config }
+        }
+        "convert" => {
+            let [input, output] = rest else {
+                return Err("convert needs an input and an output file".to_string());
+            };
+            Command::Convert { input: input.to_string(), output: output.to_string() }
+        }
+        "quantize" => {
+            let [checkpoint, output] = rest else {
+                return Err("quantize needs a checkpoint and an output file".to_string());
+            };
+            let tolerance = tolerance.map_or(Ok(DEFAULT_QUANTIZE_TOLERANCE), |t| parse_number(&t, "--tolerance"))?;
+            Command::Quantize { checkpoint: checkpoint.to_string(), output: output.to_string(), verify_file, tolerance }
+        }
+        other => return Err(format!("unknown command {}", other)),
+    };
+    Ok((command, options))
+}
+
+fn parse_number<T: FromStr>(value: &str, what: &str) -> Result<T, String> {
+    value.parse().map_err(|_| format!("invalid {} value {}", what, value))
+}
+
+/// The start position, or `fen`; exits on an invalid FEN
+fn parse_fen(fen: Option<&str>) -> Board {
+    match fen.map(Board::from_str) {
+        None => Board::default(),
+        Some(Ok(board)) => board,
+        Some(Err(e)) => {
+            eprintln!("invalid FEN: {}", e);
+            process::exit(2);
+        }
+    }
+}
+
+/// Exits with 1 if any count is off
+fn perft_suite(path: &str, threads: Option<usize>) {
+    let cases = fs::read_to_string(path).map_err(|e| e.to_string()).and_then(|text| perft::parse_suite(&text)).unwrap_or_else(|e| {
+        eprintln!("{}: {}", path, e);
+        process::exit(2);
+    });
+    let threads = threads.unwrap_or_else(|| std::thread::available_parallelism().map_or(1, |n| n.get()));
+    if perft::run_suite(&cases, threads) > 0 {
+        process::exit(1);
+    }
+}
+
+/// Ends with the `<nodes> nodes <nps> nps` line OpenBench reads; the node count is its
+/// signature for the build, so it must not depend on timing or earlier searches
+fn bench(options: &Options, depth: u8) {
+    let mut engine = options.engine();
+    let start = Instant::now();
+    let mut nodes = 0;
+    for (i, fen) in BENCH_FENS.iter().enumerate() {
+        let position_nodes = bench_position(&mut engine, fen, depth);
+        println!("Position {}/{}: {} nodes", i + 1, BENCH_FENS.len(), position_nodes);
+        nodes += position_nodes;
+    }
+    let ms = start.elapsed().as_millis().max(1) as u64;
+    let nps = nodes * 1_000_000 / (start.elapsed().as_micros() as u64).max(1);
+    println!();
+    println!("Total time (ms) : {}", ms);
+    println!("Nodes searched  : {}", nodes);
+    println!("Nodes/second    : {}", nps);
+    println!("{} nodes {} nps", nodes, nps);
+}
+
+/// Nodes to search `fen` to `depth` from a fresh game
+fn bench_position(engine: &mut Engine, fen: &str, depth: u8) -> u64 {
+    engine.set_option("Deterministic", "true");
+    engine.new_game();
+    engine.set_position(Game::from_str(fen).expect("bench FENs are valid"));
+    engine.go(SearchLimits::depth(depth));
+    engine.wait_best_move().expect("engine worker exited").nodes
+}
+
+fn analyze(options: &Options, board: &Board, limits: &SearchLimits, multipv: usize, mcts: bool) {
+    let mut engine = options.engine();
+    engine.set_option("MultiPV", &multipv.to_string());
+    if mcts {
+        engine.set_option("SearchBackend", "mcts");
+    }
+    engine.set_position(Game::new(*board));
+    engine.go(limits.clone());
+    // Ctrl-C ends the analysis with the best move so far
+    let stop = engine.stop_flag();
+    signals::on_interrupt(move || stop.store(true, Ordering::SeqCst));
+    // The lines of the deepest iteration so far, by multipv index
+    let mut lines: Vec<SearchInfo> = Vec::new();
+    for event in engine.events() {
+        match event {
+            EngineEvent::Info(info) if options.json => println!("{}", json::info_json(board, &info, None)),
+            EngineEvent::BestMove(result) if options.json => {
+                println!("{}", json::result_json(board, &result));
+                break;
+            }
+            EngineEvent::Info(info) => {
+                let bound = match info.bound {
+                    ScoreBound::Exact => "",
+                    ScoreBound::Lower => " lowerbound",
+                    ScoreBound::Upper => " upperbound",
+                };
+                let index = if multipv > 1 { format!(" multipv {}", info.multipv) } else { String::new() };
+                println!(
+                    "depth {}{} {}{} nodes {} time {} nps {} pv {}",
+                    info.depth, index, format_score(info.score), bound, info.nodes, info.time_ms, info.nps,
+                    pv_to_uci(board, &info.pv)
+                );
+                if info.bound == ScoreBound::Exact {
+                    if lines.first().is_some_and(|line| line.depth < info.depth) {
+                        lines.clear();
+                    }
+                    lines.truncate(info.multipv - 1);
+                    lines.push(info);
+                }
+            }
+            EngineEvent::Message(message) => println!("{}", message),
+            EngineEvent::BestMove(result) => {
+                print_analysis(board, &lines, &result);
+                match result.best_move {
+                    Some(mv) => println!("bestmove {}", board.move_to_uci(mv)),
+                    None => println!("bestmove (none)"),
+                }
+                break;
+            }
+        }
+    }
+    signals::clear();
 }
+
+/// The summary after `analyze`'s iterations: evaluation, top lines in SAN, the root moves by
+/// visits after an MCTS search, search stats
+fn print_analysis(board: &Board, lines: &[SearchInfo], result: &SearchResult) {
+    println!();
+    if result.best_move.is_none() {
+        println!("No legal moves");
+        return;
+    }
+    println!("Evaluation : {} (White's view, depth {})", format_eval(board, result.score), result.depth);
+    for line in lines {
+        let label = format!("Line {}", line.multipv);
+        println!("{:<10} : {:>6}  {}", label, format_eval(board, line.score), pgn::line_to_san(board, &line.pv));
+    }
+    let total: u32 = result.stats.root_visits.iter().map(|v| v.visits).sum();
+    for visits in result.stats.root_visits.iter().take(MCTS_SHOWN_MOVES) {
+        println!(
+            "{:<10} : {:>6}  {:>5.1}% of {} visits, prior {:.1}%",
+            pgn::move_to_san(board, visits.mv), format_eval(board, visits.score),
+            visits.visits as f64 * 100.0 / total.max(1) as f64, total, visits.prior * 100.0
+        );
+    }
+    println!(
+        "Search     : seldepth {}, {} nodes in {} ms ({} nps), hashfull {}, tbhits {}",
+        result.seldepth, result.nodes, result.time_ms, result.nps, result.hashfull, result.tb_hits
+    );
+    let stats = &result.stats;
+    let branching = stats.branching_factor().map_or("n/a".to_string(), |factor| format!("{:.2}", factor));
+    println!(
+        "Stats      : qsearch {:.1}% of nodes, tt hits {:.1}%, branching factor {}",
+        stats.qsearch_share(), stats.tt_hit_rate(), branching
+    );
+}
+
+/// A score for people: pawns from White's point of view (`+0.43`), or `#3` / `#-3` for mates
+fn format_eval(board: &Board, score: Score) -> String {
+    annotate::format_eval(if board.side_to_move() == Color::White { score } else { -score })
+}
+
+/// Annotated games to stdout, progress to stderr. Unreadable games are skipped.
+fn annotate_games(options: &Options, path: &str, config: &AnnotateConfig) {
+    let file = fs::File::open(path).unwrap_or_else(|e| {
+        eprintln!("cannot read {}: {}", path, e);
+        process::exit(2);
+    });
+    let mut engine = options.engine();
+    let (mut games, mut skipped) = (0, 0);
+    for game in PgnReader::new(BufReader::new(file)) {
+        match game {
+            Ok(game) => {
+                let annotated = annotate::annotate_game(&mut engine, &game, config);
+                let flagged = annotated.annotations.iter().filter(|a| a.nag.is_some()).count();
+                println!("{}", annotated);
+                games += 1;
+                eprintln!("game {}: {} moves, {} flagged", games, game.moves.len(), flagged);
+            }
+            Err(e) => {
+                eprintln!("skipping unreadable game: {}", e);
+                skipped += 1;
+            }
+        }
+    }
+    eprintln!("{} games annotated ({} unreadable skipped)", games, skipped);
+}
+
+/// Puzzles as EPD lines to stdout, progress to stderr. Ids name the game and the ply.
+fn find_puzzles(options: &Options, path: &str, config: &PuzzleConfig) {
+    let file = fs::File::open(path).unwrap_or_else(|e| {
+        eprintln!("cannot read {}: {}", path, e);
+        process::exit(2);
+    });
+    let mut engine = options.engine();
+    let (mut games, mut found, mut skipped) = (0, 0, 0);
+    for game in PgnReader::new(BufReader::new(file)) {
+        match game {
+            Ok(game) => {
+                games += 1;
+                let puzzles = puzzles::find_puzzles(&mut engine, &game, config);
+                for puzzle in &puzzles {
+                    println!("{}", puzzle.to_epd(&format!("game {} ply {}", games, puzzle.ply)));
+                }
+                found += puzzles.len();
+                eprintln!("game {}: {} moves, {} puzzles", games, game.moves.len(), puzzles.len());
+            }
+            Err(e) => {
+                eprintln!("skipping unreadable game: {}", e);
+                skipped += 1;
+            }
+        }
+    }
+    eprintln!("{} puzzles from {} games ({} unreadable skipped)", found, games, skipped);
+}
+
+fn selfplay(options: &Options, mut config: SelfplayConfig, openings_file: Option<&str>) {
+    if let Some(path) = openings_file {
+        config.openings = read_openings(path).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            process::exit(2);
+        });
+    }
+    if config.openings.is_empty() && options.odds.is_some() {
+        config.openings = vec![handicap::odds_position(options.odds, options.odds_side.unwrap_or(Color::White))];
+    }
+    config.options = options.search_options();
+    config.adjudication.syzygy_path = options.syzygy_path.clone();
+
+    let mut score = [0u32; 3]; // White wins, Black wins, draws
+    selfplay::run(&config, |game| {
+        println!("{}", game);
+        match game.result.as_str() {
+            "1-0" => score[0] += 1,
+            "0-1" => score[1] += 1,
+            _ => score[2] += 1,
+        }
+    });
+    eprintln!("White wins {}, Black wins {}, draws {}", score[0], score[1], score[2]);
+}
+
+fn make_book(output: &str, pgn_files: &[String], max_ply: usize, min_games: u32) {
+    let mut builder = BookBuilder::new(max_ply, min_games);
+    let mut skipped = 0;
+    for path in pgn_files {
+        let file = fs::File::open(path).unwrap_or_else(|e| {
+            eprintln!("cannot read {}: {}", path, e);
+            process::exit(2);
+        });
+        for game in PgnReader::new(BufReader::new(file)) {
+            match game {
+                Ok(game) => builder.add_game(&game),
+                Err(_) => skipped += 1,
+            }
+        }
+    }
+
+    let book = builder.build();
+    if let Err(e) = book.save(output) {
+        eprintln!("{}", e);
+        process::exit(1);
+    }
+    eprintln!("{} games ({} unreadable skipped), {} book entries written to {}", builder.games(), skipped, book.len(), output);
+}
+
+fn generate_data(output: &str, config: &DatagenConfig) {
+    let file = fs::File::create(output).unwrap_or_else(|e| {
+        eprintln!("cannot create {}: {}", output, e);
+        process::exit(2);
+    });
+    let mut out = BufWriter::new(file);
+    let format = DataFormat::from_path(output);
+    let start = Instant::now();
+    let (mut games, mut positions) = (0, 0);
+    datagen::run(config, |records| {
+        for record in records {
+            format.write(&mut out, record).unwrap_or_else(|e| {
+                eprintln!("cannot write {}: {}", output, e);
+                process::exit(1);
+            });
+        }
+        games += 1;
+        positions += records.len();
+        if games % 100 == 0 {
+            let secs = start.elapsed().as_secs_f64().max(0.001);
+            eprintln!("{} games, {} positions ({:.0} positions/s)", games, positions, positions as f64 / secs);
+        }
+    });
+    if let Err(e) = out.flush() {
+        eprintln!("cannot write {}: {}", output, e);
+        process::exit(1);
+    }
+    eprintln!("{} games, {} positions written to {}", games, positions, output);
+}
+
+/// Formats follow the file extensions (see `DataFormat::from_path`)
+fn convert_data(input: &str, output: &str) {
+    let (from, to) = (DataFormat::from_path(input), DataFormat::from_path(output));
+    if from == to {
+        eprintln!("{} and {} have the same format", input, output);
+        process::exit(2);
+    }
+    let reader = fs::File::open(input).map(BufReader::new).unwrap_or_else(|e| {
+        eprintln!("cannot read {}: {}", input, e);
+        process::exit(2);
+    });
+    let mut out = fs::File::create(output).map(BufWriter::new).unwrap_or_else(|e| {
+        eprintln!("cannot create {}: {}", output, e);
+        process::exit(2);
+    });
+    match datagen::convert(reader, from, &mut out, to).and_then(|n| out.flush().map(|_| n).map_err(|e| e.to_string())) {
+        Ok(n) => eprintln!("{} records converted to {}", n, output),
+        Err(e) => {
+            eprintln!("{}: {}", input, e);
+            process::exit(1);
+        }
+    }
+}
+
+/// Quantize `checkpoint` and write it to `output` unless the quantized evals drift too far
+fn quantize(checkpoint: &str, output: &str, verify_file: Option<&str>, tolerance: f32) {
+    let exit = |e: String| -> ! {
+        eprintln!("{}", e);
+        process::exit(1);
+    };
+    let float = FloatNetwork::load(checkpoint).unwrap_or_else(|e| exit(format!("{}: {}", checkpoint, e)));
+    let net = float.quantize().unwrap_or_else(|e| exit(format!("{}: {}", checkpoint, e)));
+
+    let mut boards: Vec<Board> = BENCH_FENS.iter().map(|fen| Board::from_str(fen).expect("bench FENs are valid")).collect();
+    if let Some(path) = verify_file {
+        let reader = fs::File::open(path).map(BufReader::new).unwrap_or_else(|e| exit(format!("cannot read {}: {}", path, e)));
+        for record in datagen::read_records(reader, DataFormat::from_path(path)) {
+            boards.push(record.unwrap_or_else(|e| exit(format!("{}: {}", path, e))).board);
+        }
+    }
+    let (max, mean) = float.quantization_error(&net, &boards);
+    eprintln!("hidden size {}, {} positions: mean error {:.2} cp, max {:.2} cp", net.hidden_size(), boards.len(), mean, max);
+    if mean > tolerance {
+        exit(format!("mean error is over the {} cp tolerance; {} not written", tolerance, output));
+    }
+    fs::write(output, net.to_bytes()).unwrap_or_else(|e| exit(format!("cannot write {}: {}", output, e)));
+    eprintln!("wrote {}", output);
+}
+
+/// One FEN (or EPD, whose first four fields are the position) per line; `#` starts a comment
+fn read_openings(path: &str) -> Result<Vec<Board>, String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("cannot read {}: {}", path, e))?;
+    let mut openings = Vec::new();
+    for line in text.lines().map(str::trim).filter(|l| !l.is_empty() && !l.starts_with('#')) {
+        let record: EpdRecord = line.parse().map_err(|e| format!("{}: bad opening {}: {}", path, line, e))?;
+        openings.push(record.board);
+    }
+    if openings.is_empty() {
+        return Err(format!("{}: no openings", path));
+    }
+    Ok(openings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &str) -> Result<(Command, Options), String> {
+        let args: Vec<String> = args.split_whitespace().map(String::from).collect();
+        parse_args(&args)
+    }
+
+    #[test]
+    fn test_parse_commands() {
+        assert_eq!(parse("").unwrap().0, Command::Uci);
+        assert_eq!(parse("bench --help").unwrap().0, Command::Help);
+        assert_eq!(parse("bench").unwrap().0, Command::Bench { depth: DEFAULT_BENCH_DEPTH });
+        assert_eq!(parse("bench 5").unwrap().0, Command::Bench { depth: 5 });
+        let (command, options) = parse("bench 13 1 16").unwrap();
+        assert_eq!(command, Command::Bench { depth: 13 });
+        assert_eq!((options.threads, options.hash_mb), (Some(1), Some(16)));
+        assert!(parse("bench 13 1 16 1").is_err());
+        assert_eq!(parse("perft 3").unwrap().0, Command::Perft { depth: 3, fen: None });
+        assert_eq!(parse("perftsuite suite.txt").unwrap().0, Command::PerftSuite { file: "suite.txt".to_string() });
+        assert_eq!(
+            parse("perft 2 8/8/8/8/8/8/8/K1k5 w - - 0 1").unwrap().0,
+            Command::Perft { depth: 2, fen: Some("8/8/8/8/8/8/8/K1k5 w - - 0 1".to_string()) }
+        );
+        assert_eq!(
+            parse("analyze --depth 9 8/8/8/8/8/8/8/K1k5 w - - 0 1").unwrap().0,
+            Command::Analyze { fen: "8/8/8/8/8/8/8/K1k5 w - - 0 1".to_string(), limits: SearchLimits::depth(9), multipv: 1, mcts: false }
+        );
+        assert_eq!(
+            parse("analyze 8/8/8/8/8/8/8/K1k5 w - - 0 1 --movetime 500 --multipv 3 --mcts").unwrap().0,
+            Command::Analyze {
+                fen: "8/8/8/8/8/8/8/K1k5 w - - 0 1".to_string(),
+                limits: SearchLimits { movetime: Some(500), ..Default::default() },
+                multipv: 3,
+                mcts: true,
+            }
+        );
+        assert_eq!(
+            parse("annotate games.pgn --depth 8").unwrap().0,
+            Command::Annotate {
+                pgn_file: "games.pgn".to_string(),
+                config: AnnotateConfig { limits: SearchLimits::depth(8), ..Default::default() },
+            }
+        );
+        assert_eq!(
+            parse("puzzles games.pgn --movetime 200").unwrap().0,
+            Command::Puzzles {
+                pgn_file: "games.pgn".to_string(),
+                config: PuzzleConfig { limits: SearchLimits { movetime: Some(200), ..Default::default() }, ..Default::default() },
+            }
+        );
+        assert!(parse("annotate").is_err());
+        assert!(parse("annotate games.pgn --depth 8 --movetime 100").is_err());
+        assert_eq!(
+            parse("makebook book.bin a.pgn b.pgn --max-ply 16").unwrap().0,
+            Command::MakeBook {
+                output: "book.bin".to_string(),
+                pgn_files: vec!["a.pgn".to_string(), "b.pgn".to_string()],
+                max_ply: 16,
+                min_games: DEFAULT_BOOK_MIN_GAMES,
+            }
+        );
+        assert_eq!(
+            parse("datagen data.txt --nodes 2000 --threads 3 --sample-rate 0.25").unwrap().0,
+            Command::Datagen {
+                output: "data.txt".to_string(),
+                config: DatagenConfig {
+                    threads: 3,
+                    limits: SearchLimits { nodes: Some(2000), ..Default::default() },
+                    sample_rate: 0.25,
+                    ..Default::default()
+                },
+            }
+        );
+        assert_eq!(
+            parse("selfplay --games 4 --tc 5+0.05 --openings book.epd").unwrap().0,
+            Command::Selfplay {
+                config: SelfplayConfig {
+                    games: 4,
+                    time_control: TimeControl::Clock { base_ms: 5000, inc_ms: 50 },
+                    ..Default::default()
+                },
+                openings_file: Some("book.epd".to_string()),
+            }
+        );
+    }
+
+    #[test]
+    fn test_bench_is_deterministic() {
+        let mut engine = Options::default().engine();
+        let first: Vec<u64> = BENCH_FENS[..3].iter().map(|fen| bench_position(&mut engine, fen, 4)).collect();
+        // Same counts again from a used engine, in reverse order
+        let mut second: Vec<u64> = BENCH_FENS[..3].iter().rev().map(|fen| bench_position(&mut engine, fen, 4)).collect();
+        second.reverse();
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn test_parse_options_and_errors() {
+        let (command, options) = parse("--hash 256 bench --threads 2 --syzygy /tb").unwrap();
+        assert_eq!(command, Command::Bench { depth: DEFAULT_BENCH_DEPTH });
+        assert_eq!(
+            options,
+            Options { hash_mb: Some(256), threads: Some(2), syzygy_path: Some("/tb".to_string()), ..Default::default() }
+        );
+        assert_eq!(options.uci_options()[0], ("Hash".to_string(), "256".to_string()));
+        let (_, options) = parse("--syzygy-path /tb --eval-file net.nnue --book book.bin").unwrap();
+        let names: Vec<String> = options.uci_options().into_iter().map(|(n, v)| format!("{}={}", n, v)).collect();
+        assert_eq!(names, ["SyzygyPath=/tb", "EvalFile=net.nnue", "BookFile=book.bin"]);
+        assert_eq!(options.search_options().len(), 2);
+        let (command, options) = parse("analyze --json 8/8/8/8/8/8/8/K1k5 w - - 0 1").unwrap();
+        assert!(matches!(command, Command::Analyze { .. }) && options.json);
+        assert_eq!(options.uci_options(), [("OutputFormat".to_string(), "json".to_string())]);
+        let (_, options) = parse("--odds Knight --odds-side black --skill 5 --variety 8").unwrap();
+        let names: Vec<String> = options.uci_options().into_iter().map(|(n, v)| format!("{}={}", n, v)).collect();
+        assert_eq!(names, ["Odds=knight", "OddsSide=black", "Skill Level=5", "OpeningVariety=8"]);
+        assert!(parse("--odds bishop").is_err());
+        assert!(parse("--odds-side red").is_err());
+
+        // Config file options come first, so the flags override them
+        let (_, mut options) = parse("--config engine.toml --hash 32").unwrap();
+        assert_eq!(options.config_file.as_deref(), Some("engine.toml"));
+        options.config = Config::parse("Hash = 512\nUCI_ShowWDL = true").unwrap();
+        let names: Vec<String> = options.uci_options().into_iter().map(|(n, v)| format!("{}={}", n, v)).collect();
+        assert_eq!(names, ["Hash=512", "UCI_ShowWDL=true", "Hash=32"]);
+
+        assert!(parse("--hash").is_err());
+        assert!(parse("--hash lots").is_err());
+        assert!(parse("--verbose").is_err());
+        assert!(parse("perft").is_err());
+        assert!(parse("perftsuite").is_err());
+        assert!(parse("analyze").is_err());
+        assert!(parse("analyze --depth 4 --movetime 100 8/8/8/8/8/8/8/K1k5 w - - 0 1").is_err());
+        assert!(parse("analyze --multipv 0 8/8/8/8/8/8/8/K1k5 w - - 0 1").is_err());
+        assert!(parse("play").is_err());
+        assert!(parse("selfplay --depth 4 --movetime 100").is_err());
+        assert!(parse("makebook book.bin").is_err());
+        assert!(parse("datagen").is_err());
+        assert!(parse("convert data.txt").is_err());
+        assert_eq!(
+            parse("quantize raw.bin net.nnue --tolerance 2.5").unwrap().0,
+            Command::Quantize {
+                checkpoint: "raw.bin".to_string(),
+                output: "net.nnue".to_string(),
+                verify_file: None,
+                tolerance: 2.5
+            }
+        );
+        assert!(parse("quantize raw.bin").is_err());
+        assert!(parse("datagen out.txt --sample-rate 2").is_err());
+        assert!(parse("datagen out.txt --depth 4 --nodes 1000").is_err());
+    }
+}
+
+// The binary is a thin layer over the library: every subcommand drives an `Engine` (or, for
+// `uci`, the UCI loop) with the command-line options applied as if set over UCI, so a flag
+// behaves exactly like the matching option. Arguments are parsed by hand like UCI commands;
+// flags may appear anywhere, and a FEN may be passed either quoted or as separate words.
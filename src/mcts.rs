@@ -0,0 +1,332 @@
+use crate::board::{Board, ChessMove, MoveGen};
+use crate::game::insufficient_material;
+use crate::search::{progress, quiescence, report_info, SearchState};
+use crate::time;
+use crate::types::{RootVisits, Score, ScoreBound, SearchInfo, SearchResult, MAX_PLY, SCORE_INFINITY, SCORE_MATE};
+
+/// PUCT exploration constant: how far a move's prior and few visits count against its value
+const CPUCT: f64 = 1.5;
+
+/// Values are `tanh(centipawns / VALUE_SCALE)`: +300 is worth about three quarters of a win
+const VALUE_SCALE: f64 = 300.0;
+
+/// Softmax temperature of the priors, in centipawns: a move this much worse than another is
+/// tried e times less often before visits take over
+const PRIOR_TEMPERATURE: f64 = 100.0;
+
+/// Centipawns below the best sibling so far at which a child's quiescence search only proves
+/// that it is worse: its prior is negligible by then
+const PRIOR_WINDOW: Score = 400;
+
+/// Playouts of a search to depth N: `PLAYOUTS_PER_DEPTH << N`, the shift capped at
+/// `MAX_PLAYOUT_SHIFT`
+const PLAYOUTS_PER_DEPTH: u64 = 2;
+const MAX_PLAYOUT_SHIFT: u8 = 20;
+
+/// Largest tree, in nodes (about 64 MB); a full tree ends the search
+const MAX_TREE_NODES: usize = 1 << 21;
+
+/// Playouts before the first info line; later lines follow whenever the count doubles
+const FIRST_REPORT: u64 = 64;
+
+/// A position in the tree. Values are from the point of view of the side that moved into it,
+/// in -1 (lost) to 1 (won).
+struct Node {
+    /// The move into this position (none at the root)
+    mv: Option<ChessMove>,
+    prior: f32,
+    /// Value of the quiescence search run when the parent was expanded, counted as one visit
+    estimate: f64,
+    visits: u32,
+    value_sum: f64,
+    /// Children are `first_child..first_child + children` in the tree
+    first_child: u32,
+    children: u16,
+    expanded: bool,
+    /// Mate or a draw: `estimate` is exact and the position is never expanded
+    terminal: bool,
+}
+
+impl Node {
+    fn new(mv: Option<ChessMove>, prior: f32, estimate: f64) -> Self {
+        Node { mv, prior, estimate, visits: 0, value_sum: 0.0, first_child: 0, children: 0, expanded: false, terminal: false }
+    }
+
+    /// Mean value, the estimate included
+    fn q(&self) -> f64 {
+        (self.estimate + self.value_sum) / (self.visits + 1) as f64
+    }
+
+    fn child_range(&self) -> std::ops::Range<usize> {
+        self.first_child as usize..self.first_child as usize + self.children as usize
+    }
+}
+
+/// The search tree, its nodes in one vector with the root first
+struct Tree {
+    nodes: Vec<Node>,
+}
+
+impl Tree {
+    /// Children of `index`, most visited first (better mean value first among equals)
+    fn ranked_children(&self, index: usize) -> Vec<usize> {
+        let mut children: Vec<usize> = self.nodes[index].child_range().collect();
+        children.sort_by(|&a, &b| {
+            let (a, b) = (&self.nodes[a], &self.nodes[b]);
+            b.visits.cmp(&a.visits).then(b.q().total_cmp(&a.q()))
+        });
+        children
+    }
+
+    /// The most visited line from `index`, starting with the move into it
+    fn line(&self, mut index: usize) -> Vec<ChessMove> {
+        let mut line: Vec<ChessMove> = self.nodes[index].mv.into_iter().collect();
+        while let Some(&child) = self.ranked_children(index).first() {
+            if self.nodes[child].visits == 0 {
+                break;
+            }
+            line.extend(self.nodes[child].mv);
+            index = child;
+        }
+        line
+    }
+
+    /// The score of a root child for the side to move at the root: a mate it delivers, or its
+    /// mean value in centipawns
+    fn score(&self, child: usize) -> Score {
+        let node = &self.nodes[child];
+        if node.terminal && node.estimate >= 1.0 {
+            SCORE_MATE - 1
+        } else {
+            to_score(node.q())
+        }
+    }
+
+    /// The child of `index` with the best PUCT score: its mean value plus an exploration
+    /// bonus that grows with its prior and shrinks with its visits
+    fn select(&self, index: usize) -> usize {
+        let parent = &self.nodes[index];
+        let exploration = CPUCT * ((parent.visits + 1) as f64).sqrt();
+        let puct = |child: usize| {
+            let node = &self.nodes[child];
+            node.q() + exploration * node.prior as f64 / (node.visits + 1) as f64
+        };
+        parent.child_range().max_by(|&a, &b| puct(a).total_cmp(&puct(b))).expect("expanded nodes have children")
+    }
+
+    /// Add a visit worth `value` to the side to move at the end of `path` to every node on it
+    fn backup(&mut self, path: &[usize], mut value: f64) {
+        for &index in path.iter().rev() {
+            value = -value;
+            let node = &mut self.nodes[index];
+            node.visits += 1;
+            node.value_sum += value;
+        }
+    }
+}
+
+/// Monte-Carlo tree search from `board` within the limits `search` applied to `state`: the
+/// clock, the node limit and `stop`, or else `PLAYOUTS_PER_DEPTH << max_depth` playouts.
+/// Info lines report the most visited root moves (MultiPV of them) with their lines, and the
+/// result's stats rank every root move by visits (`SearchStats::root_visits`).
+pub fn search(board: &Board, state: &mut SearchState, max_depth: u8) -> SearchResult {
+    let max_playouts = PLAYOUTS_PER_DEPTH << max_depth.min(MAX_PLAYOUT_SHIFT);
+    let mut tree = Tree { nodes: vec![Node::new(None, 1.0, 0.0)] };
+    let mut history = state.position_history.clone();
+    let root_history = history.len();
+    let mut playouts = 0;
+    let mut reported = 0;
+
+    while playouts < max_playouts && !state.is_stopped() {
+        history.truncate(root_history);
+        let mut path = vec![0];
+        let mut position = *board;
+        while tree.nodes[path[path.len() - 1]].expanded {
+            let child = tree.select(path[path.len() - 1]);
+            position = position.make_move_new(tree.nodes[child].mv.expect("children have moves"));
+            history.push(position.get_hash());
+            path.push(child);
+        }
+        let leaf = path[path.len() - 1];
+        let value = if tree.nodes[leaf].terminal {
+            -tree.nodes[leaf].estimate
+        } else if leaf != 0 && is_draw(&position, &history) {
+            tree.nodes[leaf].terminal = true;
+            tree.nodes[leaf].estimate = 0.0;
+            0.0
+        } else if path.len() >= MAX_PLY {
+            to_value(state.tables.eval_cache.evaluate(&position))
+        } else {
+            match expand(&mut tree, leaf, &position, path.len(), state) {
+                Some(value) => value,
+                None => break,
+            }
+        };
+        tree.backup(&path, value);
+        playouts += 1;
+        if tree.nodes[0].terminal {
+            break;
+        }
+        if playouts >= FIRST_REPORT && playouts >= 2 * reported {
+            report_lines(board, state, &tree);
+            reported = playouts;
+        }
+    }
+    if playouts != reported && tree.nodes[0].expanded {
+        report_lines(board, state, &tree);
+    }
+
+    let ranked = tree.ranked_children(0);
+    let best = ranked.first().copied();
+    let pv = best.map_or_else(Vec::new, |child| tree.line(child));
+    let score = match best {
+        Some(child) => tree.score(child),
+        None if board.checkers().0 != 0 => -SCORE_MATE,
+        None => 0,
+    };
+    state.stats.root_visits = ranked
+        .iter()
+        .map(|&child| {
+            let node = &tree.nodes[child];
+            RootVisits { mv: node.mv.expect("children have moves"), visits: node.visits, score: tree.score(child), prior: node.prior }
+        })
+        .collect();
+
+    state.wait_min_time();
+    state.stats.nodes = state.nodes;
+    SearchResult {
+        best_move: pv.first().copied(),
+        score,
+        depth: pv.len() as u8,
+        partial_depth: None,
+        seldepth: state.seldepth,
+        nodes: state.nodes,
+        time_ms: state.start_time.elapsed_ms().max(1),
+        nps: time::nps(state.nodes, state.start_time.elapsed_us()),
+        hashfull: state.tables.tt.hashfull(),
+        tb_hits: 0,
+        pv,
+        stats: Box::new(state.stats.clone()),
+    }
+}
+
+/// Give `leaf` its children, each with a quiescence search as estimate and a softmax of those
+/// as prior (a move `PRIOR_WINDOW` below the best only gets a bound). Returns the leaf's value for its side to move: the best child's estimate, or
+/// the result of a mate or stalemate. None when the search was stopped or the tree is full.
+fn expand(tree: &mut Tree, leaf: usize, board: &Board, ply: usize, state: &mut SearchState) -> Option<f64> {
+    let moves: Vec<ChessMove> = MoveGen::new_legal(board).filter(|&mv| leaf != 0 || !state.skips_root_move(mv)).collect();
+    if moves.is_empty() {
+        let value = if board.checkers().0 != 0 { -1.0 } else { 0.0 };
+        tree.nodes[leaf].terminal = true;
+        tree.nodes[leaf].estimate = -value;
+        return Some(value);
+    }
+    if tree.nodes.len() + moves.len() > MAX_TREE_NODES {
+        return None;
+    }
+    let mut scores = Vec::with_capacity(moves.len());
+    let mut best = -SCORE_INFINITY;
+    for &mv in &moves {
+        let floor = (best - PRIOR_WINDOW).max(-SCORE_INFINITY);
+        let score = -quiescence(&board.make_move_new(mv), state, ply, -SCORE_INFINITY, -floor);
+        if state.is_stopped() {
+            return None;
+        }
+        best = best.max(score);
+        scores.push(score);
+    }
+
+    let weights: Vec<f64> = scores.iter().map(|&score| ((score - best) as f64 / PRIOR_TEMPERATURE).exp()).collect();
+    let total: f64 = weights.iter().sum();
+    let first_child = tree.nodes.len();
+    for ((&mv, &score), weight) in moves.iter().zip(&scores).zip(weights) {
+        tree.nodes.push(Node::new(Some(mv), (weight / total) as f32, to_value(score)));
+    }
+    let node = &mut tree.nodes[leaf];
+    node.first_child = first_child as u32;
+    node.children = moves.len() as u16;
+    node.expanded = true;
+    Some(to_value(best))
+}
+
+/// Repetition (of a position since the last capture or pawn move, `history` ending with this
+/// one), the fifty-move rule or too little material to mate
+fn is_draw(board: &Board, history: &[u64]) -> bool {
+    let hash = board.get_hash();
+    let repeated = (4..=board.halfmove_clock() as usize)
+        .step_by(2)
+        .any(|back| history.len().checked_sub(1 + back).is_some_and(|i| history[i] == hash));
+    repeated || board.halfmove_clock() >= 100 || insufficient_material(board)
+}
+
+/// Report the most visited root moves, as many as MultiPV asks for
+fn report_lines(board: &Board, state: &mut SearchState, tree: &Tree) {
+    for (multipv, child) in tree.ranked_children(0).into_iter().take(state.multi_pv).enumerate() {
+        let line = tree.line(child);
+        let info = progress(state, line.len() as u8, tree.score(child), ScoreBound::Exact, state.nodes, line);
+        report_info(board, state, SearchInfo { multipv: multipv + 1, ..info });
+    }
+}
+
+fn to_value(score: Score) -> f64 {
+    (score as f64 / VALUE_SCALE).tanh()
+}
+
+/// Centipawns of a mean value, short of mate scores
+fn to_score(value: f64) -> Score {
+    (value.clamp(-0.999, 0.999).atanh() * VALUE_SCALE).round() as Score
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+    use crate::search::{self, SearchBackend};
+    use crate::types::SearchLimits;
+
+    fn mcts(fen: &str, depth: u8) -> SearchResult {
+        let mut state = SearchState::new();
+        state.silent = true;
+        state.backend = SearchBackend::Mcts;
+        search::search(&Board::from_str(fen).unwrap(), &mut state, &SearchLimits::depth(depth))
+    }
+
+    #[test]
+    fn test_mcts_finds_mate_and_wins_material() {
+        let result = mcts("6k1/5ppp/8/8/8/8/5PPP/R5K1 w - - 0 1", 6);
+        assert_eq!(result.best_move, ChessMove::from_uci("a1a8"));
+        assert_eq!(result.score, SCORE_MATE - 1);
+
+        // The queen hangs to the knight
+        let result = mcts("4k3/8/8/3q4/8/4N3/4P3/4K3 w - - 0 1", 8);
+        assert_eq!(result.best_move, ChessMove::from_uci("e3d5"));
+        assert!(result.score > 300, "{}", result.score);
+    }
+
+    #[test]
+    fn test_mcts_root_visits() {
+        let result = mcts("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", 6);
+        let visits = &result.stats.root_visits;
+        assert_eq!(visits.len(), 20);
+        assert_eq!(visits.iter().map(|v| v.visits).sum::<u32>() + 1, (PLAYOUTS_PER_DEPTH << 6) as u32);
+        assert!(visits.windows(2).all(|pair| pair[0].visits >= pair[1].visits));
+        assert!((visits.iter().map(|v| v.prior).sum::<f32>() - 1.0).abs() < 1e-3);
+        assert_eq!(Some(visits[0].mv), result.best_move);
+        assert_eq!(result.pv.len(), result.depth as usize);
+
+        // No legal moves: nothing to rank
+        let mated = mcts("R5k1/5ppp/8/8/8/8/5PPP/6K1 b - - 0 1", 4);
+        assert_eq!((mated.best_move, mated.score, mated.stats.root_visits.len()), (None, -SCORE_MATE, 0));
+    }
+}
+
+// A second search for analysis rather than play: the alpha-beta search says which move is best
+// and by how much, while a tree search also says how much attention each move deserved, which is
+// easier to read and a useful check on what the alpha-beta search dismissed. There is no policy
+// network, so a node's children are each scored with a quiescence search when it is expanded:
+// the softmax of those scores is the prior and each score seeds its child's value, so an
+// unvisited move is neither optimistic nor pessimistic and a hanging piece is seen at once.
+// Values back up as means rather than minimax, which is what makes the ranking by visits
+// meaningful. Only the root move that delivers mate gets a mate score; everything else is
+// a mean value mapped back to centipawns.
@@ -1,5 +1,5 @@
 use arrayvec::ArrayVec;
-use crate::board::{BitBoard, Board, ChessMove, MoveGen, Piece, EMPTY};
+use crate::board::{Board, ChessMove, MoveGen, Piece};
 
 use crate::pst::MVV_VALUE;
 use crate::types::Score;
@@ -21,53 +21,32 @@ pub fn order_moves(
 ) -> ArrayVec<ScoredMove, 256> {
     let mut scored: ArrayVec<ScoredMove, 256> = ArrayVec::new();
 
-    let mut movegen = MoveGen::new_legal(board);
-
-    // First pass: captures (pieces on enemy squares)
-    let targets = board.color_combined(!board.side_to_move());
-    movegen.set_iterator_mask(targets);
-
-    for mv in &mut movegen {
+    // First stage: captures (including en passant) and promotions
+    for mv in MoveGen::new_captures(board) {
         let score = if Some(mv) == hash_move {
             100_000
-        } else {
-            let mut s: Score = 10_000; // Base capture bonus (above all quiet moves)
-            if let Some(victim) = board.piece_on(mv.get_dest()) {
-                let victim_idx = piece_to_index(victim);
-                let attacker = board.piece_on(mv.get_source()).unwrap();
-                let attacker_idx = piece_to_index(attacker);
-                // MVV-LVA: high victim value ...  low attacker index = good capture
-                s += MVV_VALUE[victim_idx] * 10 - attacker_idx as Score;
-            }
+        } else if let Some(victim) = board.captured_piece(mv) {
+            let victim_idx = piece_to_index(victim);
+            let attacker = board.piece_on(mv.get_source()).unwrap();
+            let attacker_idx = piece_to_index(attacker);
+            // Base capture bonus (above all quiet moves)
+            // MVV-LVA: high victim value ...  low attacker index = good capture
+            let mut s: Score = 10_000 + MVV_VALUE[victim_idx] * 10 - attacker_idx as Score;
             if mv.get_promotion().is_some() {
                 s += 9000;
             }
             s
+        } else {
+            // Quiet promotion
+            9000
         };
         scored.push(ScoredMove { mv, score });
     }
 
-    // Second pass: en passant captures (destination square is empty, so missed above)
-    if let Some(ep_sq) = board.en_passant() {
-        movegen.set_iterator_mask(BitBoard::from_square(ep_sq));
-        for mv in &mut movegen {
-            let score = if Some(mv) == hash_move {
-                100_000
-            } else {
-                // Pawn captures pawn via en passant
-                10_000 + MVV_VALUE[0] * 10
-            };
-            scored.push(ScoredMove { mv, score });
-        }
-    }
-
-    // Third pass: quiet moves
-    movegen.set_iterator_mask(!EMPTY);
-    for mv in &mut movegen {
+    // Second stage: quiet moves
+    for mv in MoveGen::new_quiets(board) {
         let score = if Some(mv) == hash_move {
             100_000
-        } else if mv.get_promotion().is_some() {
-            9000
         } else if Some(mv) == killers[0] {
             // First killer move: just below captures
             8000
@@ -87,21 +66,17 @@ pub fn order_moves(
         scored.push(ScoredMove { mv, score });
     }
 
-    scored.sort_unstable_by(|a, b| b.score.cmp(&a.score));
+    scored.sort_unstable_by_key(|m| core::cmp::Reverse(m.score));
     scored
 }
 
-/// Generates only capture moves for quiescence search, ordered by MVV-LVA.
+/// Generates only captures and promotions for quiescence search, ordered by MVV-LVA.
 pub fn order_captures(board: &Board) -> ArrayVec<ScoredMove, 256> {
     let mut scored: ArrayVec<ScoredMove, 256> = ArrayVec::new();
 
-    let mut movegen = MoveGen::new_legal(board);
-    let targets = board.color_combined(!board.side_to_move());
-    movegen.set_iterator_mask(targets);
-
-    for mv in &mut movegen {
+    for mv in MoveGen::new_captures(board) {
         let mut score: Score = 0;
-        if let Some(victim) = board.piece_on(mv.get_dest()) {
+        if let Some(victim) = board.captured_piece(mv) {
             let victim_idx = piece_to_index(victim);
             let attacker = board.piece_on(mv.get_source()).unwrap();
             let attacker_idx = piece_to_index(attacker);
@@ -113,17 +88,7 @@ pub fn order_captures(board: &Board) -> ArrayVec<ScoredMove, 256> {
         scored.push(ScoredMove { mv, score });
     }
 
-    // En passant captures (destination square is empty, so missed above)
-    if let Some(ep_sq) = board.en_passant() {
-        movegen.set_iterator_mask(BitBoard::from_square(ep_sq));
-        for mv in &mut movegen {
-            // Pawn captures pawn via en passant
-            let score = MVV_VALUE[0] * 10;
-            scored.push(ScoredMove { mv, score });
-        }
-    }
-
-    scored.sort_unstable_by(|a, b| b.score.cmp(&a.score));
+    scored.sort_unstable_by_key(|m| core::cmp::Reverse(m.score));
     scored
 }
 
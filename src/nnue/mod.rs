@@ -0,0 +1,409 @@
+#[cfg(feature = "std")]
+mod quantize;
+
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use std::fs;
+
+use crate::board::{Board, Color, Piece};
+use crate::types::Score;
+
+#[cfg(feature = "std")]
+pub use quantize::FloatNetwork;
+
+/// File magic for ferrite networks
+const MAGIC: &[u8; 4] = b"FRNN";
+const VERSION: u32 = 1;
+
+/// Number of input features: 2 (own/their) x 6 piece types x 64 squares
+pub const INPUTS: usize = 768;
+/// Feature transformer activation clip (CReLU upper bound)
+pub const QA: i32 = 255;
+/// Output layer weight quantization
+pub const QB: i32 = 64;
+/// Centipawn scale of the network output
+pub const EVAL_SCALE: i32 = 400;
+
+/// Largest hidden layer we accept from a file (guards against garbage headers)
+const MAX_HIDDEN: usize = 4096;
+
+/// Network embedded into the binary with the `embedded-net` feature
+#[cfg(feature = "embedded-net")]
+static EMBEDDED_NET: &[u8] = include_bytes!("../../nets/default.nnue");
+
+/// A (768 -> H)x2 -> 1 perspective network with i16 quantized weights.
+///
+/// File layout (little endian):
+///   - magic `FRNN`, u32 version, u32 hidden size H
+///   - i16 feature weights [INPUTS][H]
+///   - i16 feature biases [H]
+///   - i16 output weights [2][H] (side to move first, then opponent)
+///   - i32 output bias
+pub struct Network {
+    hidden: usize,
+    ft_weights: Vec<i16>,
+    ft_bias: Vec<i16>,
+    out_weights: Vec<i16>,
+    out_bias: i32,
+}
+
+impl Network {
+    /// Parse a network from its serialized bytes
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        let mut reader = Reader { bytes, pos: 0 };
+
+        if reader.take(4)? != MAGIC {
+            return Err("not a ferrite network (bad magic)".to_string());
+        }
+        let version = reader.u32()?;
+        if version != VERSION {
+            return Err(format!("unsupported network version {}", version));
+        }
+        let hidden = reader.u32()? as usize;
+        if hidden == 0 || hidden > MAX_HIDDEN {
+            return Err(format!("invalid hidden layer size {}", hidden));
+        }
+
+        let ft_weights = reader.i16s(INPUTS * hidden)?;
+        let ft_bias = reader.i16s(hidden)?;
+        let out_weights = reader.i16s(2 * hidden)?;
+        let out_bias = reader.u32()? as i32;
+
+        if reader.pos != bytes.len() {
+            return Err(format!("{} trailing bytes after network", bytes.len() - reader.pos));
+        }
+
+        Ok(Self { hidden, ft_weights, ft_bias, out_weights, out_bias })
+    }
+
+    /// Load a network from a file on disk
+    #[cfg(feature = "std")]
+    pub fn load(path: &str) -> Result<Self, String> {
+        let bytes = fs::read(path).map_err(|e| format!("cannot read {}: {}", path, e))?;
+        Self::from_bytes(&bytes)
+    }
+
+    /// The default network compiled into the binary
+    #[cfg(feature = "embedded-net")]
+    pub fn embedded() -> Self {
+        Self::from_bytes(EMBEDDED_NET).expect("embedded network is invalid")
+    }
+
+    /// Serialize to the on-disk format
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(12 + 2 * (self.ft_weights.len() + 3 * self.hidden) + 4);
+        out.extend_from_slice(MAGIC);
+        out.extend_from_slice(&VERSION.to_le_bytes());
+        out.extend_from_slice(&(self.hidden as u32).to_le_bytes());
+        for w in self.ft_weights.iter().chain(&self.ft_bias).chain(&self.out_weights) {
+            out.extend_from_slice(&w.to_le_bytes());
+        }
+        out.extend_from_slice(&self.out_bias.to_le_bytes());
+        out
+    }
+
+    pub fn hidden_size(&self) -> usize {
+        self.hidden
+    }
+
+    /// Evaluate board from the side to move's perspective
+    pub fn evaluate(&self, board: &Board) -> Score {
+        let h = self.hidden;
+        let mut white = self.ft_bias.clone();
+        let mut black = self.ft_bias.clone();
+
+        for color in [Color::White, Color::Black] {
+            for piece in Piece::ALL {
+                let bb = board.pieces(piece) & board.color_combined(color);
+                for sq in bb.iter() {
+                    let (wf, bf) = feature_indices(piece, color, sq.to_index());
+                    add_weights(&mut white, &self.ft_weights[wf * h..(wf + 1) * h]);
+                    add_weights(&mut black, &self.ft_weights[bf * h..(bf + 1) * h]);
+                }
+            }
+        }
+
+        let (us, them) = if board.side_to_move() == Color::White {
+            (&white, &black)
+        } else {
+            (&black, &white)
+        };
+
+        let sum = crelu_dot(us, &self.out_weights[..h]) + crelu_dot(them, &self.out_weights[h..]);
+        ((sum as i64 + self.out_bias as i64) * EVAL_SCALE as i64 / (QA * QB) as i64) as Score
+    }
+}
+
+/// Feature index of a piece from White's and Black's perspective.
+/// Each perspective sees its own pieces as "own" (0) and flips ranks for Black.
+#[inline]
+fn feature_indices(piece: Piece, color: Color, sq: usize) -> (usize, usize) {
+    let p = piece.to_index();
+    let (w_rel, b_rel) = if color == Color::White { (0, 1) } else { (1, 0) };
+    ((w_rel * 6 + p) * 64 + sq, (b_rel * 6 + p) * 64 + (sq ^ 56))
+}
+
+/// Whether the CPU has AVX2: detected at runtime with std, else whether the build targets it
+#[cfg(target_arch = "x86_64")]
+#[inline]
+fn has_avx2() -> bool {
+    #[cfg(feature = "std")]
+    {
+        is_x86_feature_detected!("avx2")
+    }
+    #[cfg(not(feature = "std"))]
+    {
+        cfg!(target_feature = "avx2")
+    }
+}
+
+#[inline]
+fn add_weights(acc: &mut [i16], weights: &[i16]) {
+    #[cfg(target_arch = "x86_64")]
+    if has_avx2() {
+        // SAFETY: AVX2 support was just checked
+        return unsafe { simd::add_weights_avx2(acc, weights) };
+    }
+    #[cfg(target_arch = "aarch64")]
+    {
+        // SAFETY: NEON is mandatory on aarch64
+        return unsafe { simd::add_weights_neon(acc, weights) };
+    }
+    #[allow(unreachable_code)]
+    add_weights_scalar(acc, weights)
+}
+
+#[inline]
+fn crelu_dot(acc: &[i16], weights: &[i16]) -> i32 {
+    #[cfg(target_arch = "x86_64")]
+    if has_avx2() {
+        // SAFETY: AVX2 support was just checked
+        return unsafe { simd::crelu_dot_avx2(acc, weights) };
+    }
+    #[cfg(target_arch = "aarch64")]
+    {
+        // SAFETY: NEON is mandatory on aarch64
+        return unsafe { simd::crelu_dot_neon(acc, weights) };
+    }
+    #[allow(unreachable_code)]
+    crelu_dot_scalar(acc, weights)
+}
+
+#[inline]
+fn add_weights_scalar(acc: &mut [i16], weights: &[i16]) {
+    for (a, &w) in acc.iter_mut().zip(weights) {
+        *a = a.wrapping_add(w);
+    }
+}
+
+#[inline]
+fn crelu_dot_scalar(acc: &[i16], weights: &[i16]) -> i32 {
+    acc.iter()
+        .zip(weights)
+        .map(|(&a, &w)| (a as i32).clamp(0, QA) * w as i32)
+        .sum()
+}
+
+/// Vectorized kernels. Each handles full 16-lane (AVX2) or 8-lane (NEON) chunks and finishes
+/// the tail with the scalar code, so any hidden size works.
+mod simd {
+    #[cfg(target_arch = "aarch64")]
+    use core::arch::aarch64::*;
+    #[cfg(target_arch = "x86_64")]
+    use core::arch::x86_64::*;
+
+    #[allow(unused_imports)]
+    use super::{add_weights_scalar, crelu_dot_scalar, QA};
+
+    #[cfg(target_arch = "x86_64")]
+    #[target_feature(enable = "avx2")]
+    pub unsafe fn add_weights_avx2(acc: &mut [i16], weights: &[i16]) {
+        let n = acc.len().min(weights.len());
+        let chunks = n / 16;
+        for i in 0..chunks {
+            // SAFETY: i * 16 + 16 <= n for both slices; unaligned loads/stores
+            unsafe {
+                let a = acc.as_mut_ptr().add(i * 16) as *mut __m256i;
+                let w = weights.as_ptr().add(i * 16) as *const __m256i;
+                _mm256_storeu_si256(a, _mm256_add_epi16(_mm256_loadu_si256(a), _mm256_loadu_si256(w)));
+            }
+        }
+        add_weights_scalar(&mut acc[chunks * 16..n], &weights[chunks * 16..n]);
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    #[target_feature(enable = "avx2")]
+    pub unsafe fn crelu_dot_avx2(acc: &[i16], weights: &[i16]) -> i32 {
+        let n = acc.len().min(weights.len());
+        let chunks = n / 16;
+        let zero = _mm256_setzero_si256();
+        let qa = _mm256_set1_epi16(QA as i16);
+        let mut sum = _mm256_setzero_si256();
+        for i in 0..chunks {
+            // SAFETY: i * 16 + 16 <= n for both slices; unaligned loads
+            let (a, w) = unsafe {
+                (
+                    _mm256_loadu_si256(acc.as_ptr().add(i * 16) as *const __m256i),
+                    _mm256_loadu_si256(weights.as_ptr().add(i * 16) as *const __m256i),
+                )
+            };
+            let clipped = _mm256_min_epi16(_mm256_max_epi16(a, zero), qa);
+            // Pairwise i16*i16 -> i32 products summed into 8 lanes
+            sum = _mm256_add_epi32(sum, _mm256_madd_epi16(clipped, w));
+        }
+        let lo = _mm256_castsi256_si128(sum);
+        let hi = _mm256_extracti128_si256(sum, 1);
+        let s = _mm_add_epi32(lo, hi);
+        let s = _mm_add_epi32(s, _mm_shuffle_epi32(s, 0b01_00_11_10));
+        let s = _mm_add_epi32(s, _mm_shuffle_epi32(s, 0b10_11_00_01));
+        _mm_cvtsi128_si32(s) + crelu_dot_scalar(&acc[chunks * 16..n], &weights[chunks * 16..n])
+    }
+
+    #[cfg(target_arch = "aarch64")]
+    #[target_feature(enable = "neon")]
+    pub unsafe fn add_weights_neon(acc: &mut [i16], weights: &[i16]) {
+        let n = acc.len().min(weights.len());
+        let chunks = n / 8;
+        for i in 0..chunks {
+            // SAFETY: i * 8 + 8 <= n for both slices
+            unsafe {
+                let a = acc.as_mut_ptr().add(i * 8);
+                let w = weights.as_ptr().add(i * 8);
+                vst1q_s16(a, vaddq_s16(vld1q_s16(a), vld1q_s16(w)));
+            }
+        }
+        add_weights_scalar(&mut acc[chunks * 8..n], &weights[chunks * 8..n]);
+    }
+
+    #[cfg(target_arch = "aarch64")]
+    #[target_feature(enable = "neon")]
+    pub unsafe fn crelu_dot_neon(acc: &[i16], weights: &[i16]) -> i32 {
+        let n = acc.len().min(weights.len());
+        let chunks = n / 8;
+        let zero = vdupq_n_s16(0);
+        let qa = vdupq_n_s16(QA as i16);
+        let mut sum = vdupq_n_s32(0);
+        for i in 0..chunks {
+            // SAFETY: i * 8 + 8 <= n for both slices
+            let (a, w) = unsafe { (vld1q_s16(acc.as_ptr().add(i * 8)), vld1q_s16(weights.as_ptr().add(i * 8))) };
+            let clipped = vminq_s16(vmaxq_s16(a, zero), qa);
+            sum = vmlal_s16(sum, vget_low_s16(clipped), vget_low_s16(w));
+            sum = vmlal_high_s16(sum, clipped, w);
+        }
+        vaddvq_s32(sum) + crelu_dot_scalar(&acc[chunks * 8..n], &weights[chunks * 8..n])
+    }
+}
+
+/// Minimal little-endian cursor over the network bytes
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], String> {
+        if self.pos + n > self.bytes.len() {
+            return Err("network file is truncated".to_string());
+        }
+        let slice = &self.bytes[self.pos..self.pos + n];
+        self.pos += n;
+        Ok(slice)
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        let b = self.take(4)?;
+        Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    fn i16s(&mut self, n: usize) -> Result<Vec<i16>, String> {
+        let b = self.take(n * 2)?;
+        Ok(b.chunks_exact(2).map(|c| i16::from_le_bytes([c[0], c[1]])).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    /// Tiny hand-built net: one neuron counting (own queens - their queens)
+    fn queen_counter() -> Network {
+        let hidden = 2;
+        let mut ft_weights = vec![0i16; INPUTS * hidden];
+        for sq in 0..64 {
+            ft_weights[(4 * 64 + sq) * hidden] = 100; // own queen -> neuron 0
+            ft_weights[((6 + 4) * 64 + sq) * hidden + 1] = 100; // their queen -> neuron 1
+        }
+        Network {
+            hidden,
+            ft_weights,
+            ft_bias: vec![0; hidden],
+            out_weights: vec![QB as i16, 0, 0, QB as i16 / 2],
+            out_bias: 0,
+        }
+    }
+
+    #[test]
+    fn test_roundtrip_bytes() {
+        let net = queen_counter();
+        let parsed = Network::from_bytes(&net.to_bytes()).unwrap();
+        assert_eq!(parsed.hidden_size(), 2);
+        assert_eq!(parsed.ft_weights, net.ft_weights);
+        assert_eq!(parsed.out_weights, net.out_weights);
+    }
+
+    #[test]
+    fn test_rejects_bad_files() {
+        assert!(Network::from_bytes(b"nope").is_err());
+        let mut bytes = queen_counter().to_bytes();
+        bytes.pop();
+        assert!(Network::from_bytes(&bytes).is_err());
+        #[cfg(feature = "std")]
+        assert!(Network::load("/nonexistent/net.nnue").is_err());
+    }
+
+    #[test]
+    fn test_perspective() {
+        let net = queen_counter();
+        let board = Board::default();
+        // Symmetric: own queen feeds out weight QB, their queen feeds the "them" half
+        let white = net.evaluate(&board);
+        let black = net.evaluate(&Board::from_str("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR b KQkq - 0 1").unwrap());
+        assert_eq!(white, black);
+        assert!(white > 0);
+    }
+
+    #[test]
+    fn test_simd_matches_scalar() {
+        // 37 lanes: exercises full vector chunks plus a scalar tail
+        let acc: Vec<i16> = (0..37).map(|i| (i * 97 % 600) as i16 - 200).collect();
+        let weights: Vec<i16> = (0..37).map(|i| (i * 31 % 130) as i16 - 64).collect();
+        assert_eq!(crelu_dot(&acc, &weights), crelu_dot_scalar(&acc, &weights));
+
+        let mut fast = acc.clone();
+        let mut slow = acc.clone();
+        add_weights(&mut fast, &weights);
+        add_weights_scalar(&mut slow, &weights);
+        assert_eq!(fast, slow);
+    }
+
+    #[cfg(feature = "embedded-net")]
+    #[test]
+    fn test_embedded_net_loads() {
+        let net = Network::embedded();
+        assert!(net.evaluate(&Board::default()).abs() < 100);
+    }
+}
+
+// NNUE (efficiently updatable neural network) eval: each perspective (white/black) has an
+// accumulator = bias + sum of feature-transformer rows for every piece on the board. The output
+// layer applies CReLU (clamp to 0..QA) and dots with the output weights, side-to-move half first.
+// Accumulators are rebuilt per evaluation for now; pieces * H i16 adds is cheap next to search.
+// Both hot loops (accumulator adds, clipped dot product) dispatch to AVX2 when the CPU has it
+// (checked at runtime, the result is cached by std) or NEON on aarch64, else plain scalar code.
+
+// nets/default.nnue is a PeSTO-equivalent network: the average of the midgame and endgame
+// material + PST values folded into the feature transformer, with staggered biases so the sum of
+// clipped neurons stays linear over +-40 pawns. It lets the `embedded-net` build run standalone
+// until a trained net replaces it.
@@ -0,0 +1,215 @@
+use std::fs;
+
+use super::{feature_indices, Network, EVAL_SCALE, INPUTS, QA, QB};
+use crate::board::{Board, Color, Piece};
+
+/// Floats in a checkpoint of hidden size H: feature weights and biases, output weights and bias
+fn checkpoint_len(hidden: usize) -> usize {
+    (INPUTS + 3) * hidden + 1
+}
+
+/// The float network a trainer produces, before quantization. Same architecture and parameter
+/// order as `Network`: f32 feature weights [INPUTS][H], feature biases [H], output weights
+/// [2][H] and the output bias, all little-endian with no header. This is bullet's unquantised
+/// `raw.bin` for a `(768 -> H)x2 -> 1` CReLU net.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FloatNetwork {
+    hidden: usize,
+    ft_weights: Vec<f32>,
+    ft_bias: Vec<f32>,
+    out_weights: Vec<f32>,
+    out_bias: f32,
+}
+
+impl FloatNetwork {
+    /// Parse a checkpoint; the hidden size follows from its length
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        if !bytes.len().is_multiple_of(4) {
+            return Err(format!("checkpoint size {} is not a whole number of floats", bytes.len()));
+        }
+        let floats: Vec<f32> = bytes.chunks_exact(4).map(|c| f32::from_le_bytes([c[0], c[1], c[2], c[3]])).collect();
+        let hidden = floats.len().saturating_sub(1) / (INPUTS + 3);
+        if hidden == 0 || checkpoint_len(hidden) != floats.len() {
+            return Err(format!("{} floats is not a (768 -> H)x2 -> 1 network", floats.len()));
+        }
+        if let Some(i) = floats.iter().position(|f| !f.is_finite()) {
+            return Err(format!("parameter {} is {}", i, floats[i]));
+        }
+
+        let (ft_weights, rest) = floats.split_at(INPUTS * hidden);
+        let (ft_bias, rest) = rest.split_at(hidden);
+        let (out_weights, rest) = rest.split_at(2 * hidden);
+        Ok(FloatNetwork {
+            hidden,
+            ft_weights: ft_weights.to_vec(),
+            ft_bias: ft_bias.to_vec(),
+            out_weights: out_weights.to_vec(),
+            out_bias: rest[0],
+        })
+    }
+
+    /// Load a checkpoint from a file on disk
+    pub fn load(path: &str) -> Result<Self, String> {
+        let bytes = fs::read(path).map_err(|e| format!("cannot read {}: {}", path, e))?;
+        Self::from_bytes(&bytes)
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let params = self.ft_weights.iter().chain(&self.ft_bias).chain(&self.out_weights).chain([&self.out_bias]);
+        params.flat_map(|f| f.to_le_bytes()).collect()
+    }
+
+    pub fn hidden_size(&self) -> usize {
+        self.hidden
+    }
+
+    /// Round to the engine's format: feature layer scaled by QA, output weights by QB and the
+    /// output bias by QA * QB. Fails on a weight that doesn't fit its integer type.
+    pub fn quantize(&self) -> Result<Network, String> {
+        Ok(Network {
+            hidden: self.hidden,
+            ft_weights: quantize_i16(&self.ft_weights, QA, "feature weight")?,
+            ft_bias: quantize_i16(&self.ft_bias, QA, "feature bias")?,
+            out_weights: quantize_i16(&self.out_weights, QB, "output weight")?,
+            out_bias: {
+                let bias = (self.out_bias as f64 * (QA * QB) as f64).round();
+                if bias.abs() > i32::MAX as f64 {
+                    return Err(format!("output bias {} is out of range", self.out_bias));
+                }
+                bias as i32
+            },
+        })
+    }
+
+    /// Evaluate board from the side to move's perspective in centipawns, as `Network::evaluate`
+    /// does, but in floating point
+    pub fn evaluate(&self, board: &Board) -> f32 {
+        let h = self.hidden;
+        let mut white = self.ft_bias.clone();
+        let mut black = self.ft_bias.clone();
+
+        for color in [Color::White, Color::Black] {
+            for piece in Piece::ALL {
+                let bb = board.pieces(piece) & board.color_combined(color);
+                for sq in bb.iter() {
+                    let (wf, bf) = feature_indices(piece, color, sq.to_index());
+                    white.iter_mut().zip(&self.ft_weights[wf * h..]).for_each(|(a, w)| *a += w);
+                    black.iter_mut().zip(&self.ft_weights[bf * h..]).for_each(|(a, w)| *a += w);
+                }
+            }
+        }
+
+        let (us, them) = if board.side_to_move() == Color::White { (&white, &black) } else { (&black, &white) };
+        let dot = |acc: &[f32], weights: &[f32]| acc.iter().zip(weights).map(|(a, w)| a.clamp(0.0, 1.0) * w).sum::<f32>();
+        (dot(us, &self.out_weights[..h]) + dot(them, &self.out_weights[h..]) + self.out_bias) * EVAL_SCALE as f32
+    }
+
+    /// Largest and mean difference in centipawns between `net` and this network over `boards`
+    pub fn quantization_error<'a>(&self, net: &Network, boards: impl IntoIterator<Item = &'a Board>) -> (f32, f32) {
+        let (mut max, mut total, mut count) = (0.0f32, 0.0f32, 0);
+        for board in boards {
+            let error = (net.evaluate(board) as f32 - self.evaluate(board)).abs();
+            max = max.max(error);
+            total += error;
+            count += 1;
+        }
+        (max, if count == 0 { 0.0 } else { total / count as f32 })
+    }
+}
+
+fn quantize_i16(values: &[f32], scale: i32, what: &str) -> Result<Vec<i16>, String> {
+    values
+        .iter()
+        .map(|&v| {
+            let q = (v * scale as f32).round();
+            if q < i16::MIN as f32 || q > i16::MAX as f32 {
+                return Err(format!("{} {} is out of range after scaling by {}", what, v, scale));
+            }
+            Ok(q as i16)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    /// Small pseudo-random float network with weights in a trained net's usual ranges
+    fn random_net(hidden: usize) -> FloatNetwork {
+        let mut seed = 0x9E37_79B9_7F4A_7C15u64;
+        let mut next = |scale: f32| {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            ((seed >> 40) as f32 / (1u64 << 24) as f32 - 0.5) * 2.0 * scale
+        };
+        let floats: Vec<f32> = (0..checkpoint_len(hidden))
+            .map(|i| if i < (INPUTS + 1) * hidden { next(0.3) } else { next(1.0) })
+            .collect();
+        FloatNetwork::from_bytes(&floats.iter().flat_map(|f| f.to_le_bytes()).collect::<Vec<_>>()).unwrap()
+    }
+
+    #[test]
+    fn test_checkpoint_parsing() {
+        let net = random_net(8);
+        assert_eq!(net.hidden_size(), 8);
+        assert_eq!(FloatNetwork::from_bytes(&net.to_bytes()).unwrap(), net);
+
+        let mut bytes = net.to_bytes();
+        bytes.truncate(bytes.len() - 4);
+        assert!(FloatNetwork::from_bytes(&bytes).is_err());
+        assert!(FloatNetwork::from_bytes(&[0; 6]).is_err());
+        let mut bytes = net.to_bytes();
+        bytes[..4].copy_from_slice(&f32::NAN.to_le_bytes());
+        assert!(FloatNetwork::from_bytes(&bytes).is_err());
+    }
+
+    #[test]
+    fn test_quantized_matches_float() {
+        let float = random_net(32);
+        let net = float.quantize().unwrap();
+        // The quantized file loads like any other network
+        let net = Network::from_bytes(&net.to_bytes()).unwrap();
+        assert_eq!(net.hidden_size(), 32);
+
+        let boards: Vec<Board> = [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 b - - 0 1",
+        ]
+        .iter()
+        .map(|fen| Board::from_str(fen).unwrap())
+        .collect();
+        // Rounding error only: a few centipawns on evals of hundreds
+        let (max, mean) = float.quantization_error(&net, &boards);
+        assert!(mean <= max && max < 25.0, "max error {} mean {}", max, mean);
+        assert!(boards.iter().any(|b| float.evaluate(b).abs() > 10.0 * max));
+
+        // Weights already on the integer grid only lose the final division's remainder
+        let exact = FloatNetwork {
+            ft_weights: net.ft_weights.iter().map(|&w| w as f32 / QA as f32).collect(),
+            ft_bias: net.ft_bias.iter().map(|&w| w as f32 / QA as f32).collect(),
+            out_weights: net.out_weights.iter().map(|&w| w as f32 / QB as f32).collect(),
+            out_bias: net.out_bias as f32 / (QA * QB) as f32,
+            ..float
+        };
+        assert_eq!(exact.quantize().unwrap().to_bytes(), net.to_bytes());
+        assert!(exact.quantization_error(&net, &boards).0 <= 1.0);
+    }
+
+    #[test]
+    fn test_quantize_rejects_overflow() {
+        let mut float = random_net(4);
+        float.out_weights[3] = 1000.0;
+        let err = float.quantize().err().unwrap();
+        assert!(err.contains("output weight"), "{}", err);
+    }
+}
+
+// Quantization uses the same scales as bullet's examples for this architecture (QA = 255 for the
+// feature layer, QB = 64 for the output), so its checkpoints need no conversion beyond rounding.
+// Values that don't fit are an error rather than clamped: a saturated weight quietly
+// changes the eval, and it usually means the trainer's weight clipping was off. The float
+// evaluation keeps the engine's feature indexing so the only difference it can measure is
+// rounding.
@@ -0,0 +1,151 @@
+//! NUMA placement for search threads: the machine's nodes and their CPUs, and pinning a
+//! thread to one of them so that the memory it allocates (and first touches) is local to it
+
+use std::path::Path;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::OnceLock;
+
+/// NUMA Policy: where search threads (and the parts of the TT they fill) are placed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum NumaPolicy {
+    /// Spread threads over the nodes in turn, each pinned to its node's CPUs, when the machine
+    /// has more than one node
+    #[default]
+    Auto,
+    /// Leave placement to the operating system
+    None,
+}
+
+impl NumaPolicy {
+    /// Parse a UCI combo value (case-insensitive)
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "auto" => Some(Self::Auto),
+            "none" => Some(Self::None),
+            _ => None,
+        }
+    }
+}
+
+/// Thread placement is process-wide, so the policy is too
+static POLICY: AtomicU8 = AtomicU8::new(0);
+
+/// Set the policy for threads started from now on
+pub fn set_policy(policy: NumaPolicy) {
+    POLICY.store(policy as u8, Ordering::Relaxed);
+}
+
+pub fn policy() -> NumaPolicy {
+    match POLICY.load(Ordering::Relaxed) {
+        0 => NumaPolicy::Auto,
+        _ => NumaPolicy::None,
+    }
+}
+
+/// The CPUs of each NUMA node with any, read from sysfs once. Empty where there is no sysfs.
+pub fn nodes() -> &'static [Vec<usize>] {
+    static NODES: OnceLock<Vec<Vec<usize>>> = OnceLock::new();
+    NODES.get_or_init(|| read_nodes(Path::new("/sys/devices/system/node")))
+}
+
+/// Nodes that threads are spread over under the current policy: 1 when placement is left to
+/// the operating system
+pub fn nodes_in_use() -> usize {
+    match policy() {
+        NumaPolicy::Auto if nodes().len() > 1 => nodes().len(),
+        _ => 1,
+    }
+}
+
+/// Pin the calling thread to the CPUs of node `index % nodes_in_use()`, so threads numbered in
+/// turn are spread evenly. Does nothing (and returns false) with a single node in use or
+/// where threads can't be pinned.
+pub fn bind_thread(index: usize) -> bool {
+    let count = nodes_in_use();
+    count > 1 && set_affinity(&nodes()[index % count])
+}
+
+#[cfg(target_os = "linux")]
+fn set_affinity(cpus: &[usize]) -> bool {
+    // SAFETY: a zeroed cpu_set_t is the empty set, CPU_SET stays within it, and
+    // sched_setaffinity only reads it
+    unsafe {
+        let mut set: libc::cpu_set_t = std::mem::zeroed();
+        for &cpu in cpus.iter().filter(|&&cpu| cpu < libc::CPU_SETSIZE as usize) {
+            libc::CPU_SET(cpu, &mut set);
+        }
+        libc::sched_setaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &set) == 0
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn set_affinity(_cpus: &[usize]) -> bool {
+    false
+}
+
+/// The CPUs of each `node<N>` directory under `root`, in node order, leaving out nodes
+/// without CPUs (memory-only nodes)
+fn read_nodes(root: &Path) -> Vec<Vec<usize>> {
+    let Ok(dir) = std::fs::read_dir(root) else {
+        return Vec::new();
+    };
+    let mut nodes: Vec<(usize, Vec<usize>)> = dir
+        .flatten()
+        .filter_map(|entry| {
+            let id = entry.file_name().to_str()?.strip_prefix("node")?.parse().ok()?;
+            let cpus = parse_cpu_list(&std::fs::read_to_string(entry.path().join("cpulist")).ok()?);
+            Some((id, cpus)).filter(|(_, cpus)| !cpus.is_empty())
+        })
+        .collect();
+    nodes.sort();
+    nodes.into_iter().map(|(_, cpus)| cpus).collect()
+}
+
+/// A kernel CPU list such as `0-3,8-11`
+fn parse_cpu_list(list: &str) -> Vec<usize> {
+    list.trim()
+        .split(',')
+        .filter_map(|range| match range.split_once('-') {
+            Some((first, last)) => Some(first.parse().ok()?..=last.parse().ok()?),
+            None => range.parse().ok().map(|cpu| cpu..=cpu),
+        })
+        .flatten()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_topology() {
+        assert_eq!(parse_cpu_list("0-3,8-11\n"), vec![0, 1, 2, 3, 8, 9, 10, 11]);
+        assert_eq!(parse_cpu_list("5"), vec![5]);
+        assert!(parse_cpu_list("\n").is_empty());
+
+        // Nodes in numeric order (node10 after node2), memory-only nodes left out
+        let root = std::env::temp_dir().join(format!("ferrite-numa-{}", std::process::id()));
+        for (node, cpus) in [("node0", "0-1"), ("node2", "4,6"), ("node10", "2-3"), ("node3", ""), ("possible", "")] {
+            std::fs::create_dir_all(root.join(node)).unwrap();
+            std::fs::write(root.join(node).join("cpulist"), format!("{}\n", cpus)).unwrap();
+        }
+        assert_eq!(read_nodes(&root), vec![vec![0, 1], vec![4, 6], vec![2, 3]]);
+        std::fs::remove_dir_all(&root).unwrap();
+        assert!(read_nodes(&root).is_empty());
+
+        assert_eq!(NumaPolicy::parse("NONE"), Some(NumaPolicy::None));
+        assert_eq!(NumaPolicy::parse("interleave"), None);
+        // However many nodes this machine has, one is all that's used without a policy
+        set_policy(NumaPolicy::None);
+        assert_eq!((policy(), nodes_in_use()), (NumaPolicy::None, 1));
+        assert!(!std::thread::spawn(|| bind_thread(1)).join().unwrap());
+        set_policy(NumaPolicy::Auto);
+        assert_eq!(nodes_in_use(), nodes().len().max(1));
+    }
+}
+
+// Placement follows Linux's first-touch rule: a page lands on the node of the thread that first
+// writes it. So each search helper pins itself to a node before it builds its search state
+// (history, killers, eval cache), and the TT is filled by threads pinned to the nodes in turn,
+// which interleaves it in large stripes. The main search thread is the caller's and is left
+// where it is. On a single node (or another OS) nothing is pinned and the scheduler decides.
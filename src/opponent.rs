@@ -0,0 +1,101 @@
+use std::str::FromStr;
+
+/// Engine Rating: the engine's own rating, which an opponent's is compared with
+pub const DEFAULT_ENGINE_RATING: u32 = 2800;
+pub const MAX_RATING: u32 = 4000;
+/// Opponent Contempt: percent of the rating gap to the opponent handed to DynamicContempt
+pub const DEFAULT_OPPONENT_CONTEMPT: u32 = 100;
+pub const MAX_OPPONENT_CONTEMPT: u32 = 200;
+/// Opponent Book Variety: the least Book Variety used against a weaker opponent
+pub const DEFAULT_OPPONENT_BOOK_VARIETY: u8 = 75;
+
+/// Ratings assumed for titled players whose rating the GUI doesn't know
+const TITLE_RATINGS: [(&str, u32); 8] =
+    [("GM", 2500), ("IM", 2400), ("WGM", 2300), ("FM", 2300), ("WIM", 2200), ("CM", 2200), ("WFM", 2100), ("WCM", 2000)];
+
+/// The opponent as the GUI describes it in `UCI_Opponent`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Opponent {
+    /// FIDE title (GM, IM, ...), if any
+    pub title: Option<String>,
+    pub rating: Option<u32>,
+    pub computer: bool,
+    pub name: String,
+}
+
+impl Opponent {
+    /// The rating, or else the one the title suggests
+    pub fn rating_estimate(&self) -> Option<u32> {
+        self.rating.or_else(|| {
+            let title = self.title.as_deref()?;
+            TITLE_RATINGS.iter().find(|(known, _)| known.eq_ignore_ascii_case(title)).map(|&(_, rating)| rating)
+        })
+    }
+
+    /// `engine_rating` minus the opponent's (estimated) rating, if it has one
+    pub fn rating_advantage(&self, engine_rating: u32) -> Option<i32> {
+        self.rating_estimate().map(|rating| engine_rating as i32 - rating as i32)
+    }
+
+    /// Rated below `engine_rating`, or a human without a rating or title
+    pub fn is_weaker(&self, engine_rating: u32) -> bool {
+        match self.rating_advantage(engine_rating) {
+            Some(advantage) => advantage > 0,
+            None => !self.computer,
+        }
+    }
+}
+
+/// `<title> <rating> <computer|human> <name>`, with `none` for an unknown title or rating:
+/// `GM 2800 human Gary Kasparov`, `none none computer Shredder`
+impl FromStr for Opponent {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        let words: Vec<&str> = s.split_whitespace().collect();
+        let [title, rating, kind, name @ ..] = &words[..] else {
+            return Err(format!("Invalid opponent: {} (expected <title> <rating> <computer|human> <name>)", s));
+        };
+        fn known(word: &str) -> Option<&str> {
+            Some(word).filter(|w| !w.eq_ignore_ascii_case("none"))
+        }
+        let rating = match known(rating) {
+            Some(rating) => Some(rating.parse().map_err(|_| format!("Invalid opponent rating: {}", rating))?),
+            None => None,
+        };
+        let computer = match kind.to_lowercase().as_str() {
+            "computer" => true,
+            "human" => false,
+            _ => return Err(format!("Invalid opponent type: {} (expected computer or human)", kind)),
+        };
+        Ok(Opponent { title: known(title).map(str::to_string), rating, computer, name: name.join(" ") })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_opponent() {
+        let gm: Opponent = "GM 2800 human Gary Kasparov".parse().unwrap();
+        assert_eq!(gm, Opponent { title: Some("GM".to_string()), rating: Some(2800), computer: false, name: "Gary Kasparov".to_string() });
+        assert_eq!((gm.rating_advantage(DEFAULT_ENGINE_RATING), gm.is_weaker(DEFAULT_ENGINE_RATING)), (Some(0), false));
+
+        let engine: Opponent = "none none computer Shredder".parse().unwrap();
+        assert_eq!((engine.title.as_deref(), engine.rating_estimate(), engine.is_weaker(3000)), (None, None, false));
+        let human: Opponent = "none none human".parse().unwrap();
+        assert!(human.is_weaker(DEFAULT_ENGINE_RATING) && human.name.is_empty());
+        let im: Opponent = "im none human Someone".parse().unwrap();
+        assert_eq!(im.rating_advantage(2500), Some(100));
+
+        assert!("GM 2800 Kasparov".parse::<Opponent>().is_err());
+        assert!("GM strong human Kasparov".parse::<Opponent>().is_err());
+        assert!("GM".parse::<Opponent>().is_err());
+    }
+}
+
+// UCI_Opponent says who the engine is playing, which matters for the settings that trade
+// soundness for winning chances. The GUI's UCI_RatingAdv is the more direct signal, so the
+// opponent's rating only stands in when that is missing; a title without a rating gets a
+// conservative estimate, and an unrated human counts as weaker while an unrated engine doesn't.
@@ -0,0 +1,91 @@
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::trace::trace_event;
+
+/// Where protocol I/O is teed to (UCI `Log File`), if anywhere
+static LOG_FILE: Mutex<Option<File>> = Mutex::new(None);
+
+/// Print a line to the GUI (stdout), copying it to the log file if one is open
+macro_rules! send {
+    ($($arg:tt)*) => {
+        $crate::output::send_line(&format!($($arg)*))
+    };
+}
+pub(crate) use send;
+
+pub(crate) fn send_line(line: &str) {
+    trace_event!(TRACE, line, "uci output");
+    println!("{}", line);
+    log("<<", line);
+}
+
+/// Copy a line received from the GUI to the log file
+pub(crate) fn log_input(line: &str) {
+    log(">>", line);
+}
+
+/// Append protocol I/O to `path` from now on, or stop logging with None
+pub(crate) fn set_log_file(path: Option<&str>) -> io::Result<()> {
+    let file = path.map(|p| OpenOptions::new().create(true).append(true).open(p)).transpose()?;
+    *LOG_FILE.lock().unwrap_or_else(|e| e.into_inner()) = file;
+    Ok(())
+}
+
+fn log(direction: &str, line: &str) {
+    let mut log_file = LOG_FILE.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(file) = log_file.as_mut() {
+        // A full disk shouldn't take the engine down with it
+        let _ = writeln!(file, "{} {} {}", timestamp(), direction, line);
+    }
+}
+
+/// Push out whatever stdout and the log file still hold, e.g. before exiting on a signal.
+/// Waits for a line being logged on another thread to be finished first.
+pub(crate) fn flush() {
+    let _ = io::stdout().flush();
+    if let Some(file) = LOG_FILE.lock().unwrap_or_else(|e| e.into_inner()).as_mut() {
+        let _ = file.sync_data();
+    }
+}
+
+/// UTC time of day, `HH:MM:SS.mmm`
+fn timestamp() -> String {
+    let ms = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_millis()) % 86_400_000;
+    format!("{:02}:{:02}:{:02}.{:03}", ms / 3_600_000, ms / 60_000 % 60, ms / 1000 % 60, ms % 1000)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_log_file_tees_io() {
+        let path = std::env::temp_dir().join(format!("ferrite-log-{}.txt", std::process::id()));
+        let path = path.to_str().unwrap();
+        set_log_file(Some(path)).unwrap();
+        log_input("isready");
+        send!("readyok");
+        set_log_file(None).unwrap();
+        send!("not logged");
+
+        let text = std::fs::read_to_string(path).unwrap();
+        std::fs::remove_file(path).unwrap();
+        // Tests running in parallel may log lines of their own in between
+        let input = text.lines().position(|l| l.ends_with(" >> isready")).unwrap();
+        let output = text.lines().position(|l| l.ends_with(" << readyok")).unwrap();
+        assert!(input < output);
+        assert!(!text.contains("not logged"));
+        assert_eq!(text.find(' '), Some("00:00:00.000".len()));
+
+        assert!(set_log_file(Some("/nonexistent/dir/log.txt")).is_err());
+    }
+}
+
+// All protocol output goes through `send!` so the Log File option sees exactly what the GUI
+// sees. The log is process-wide, like stdout itself: searches print from the worker thread
+// while the command loop prints from the main one, and both need to land in the same file in
+// order. Timestamps are time of day only, which is what matters when lining a log up with a
+// GUI's own.
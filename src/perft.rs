@@ -0,0 +1,224 @@
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Instant;
+
+use crate::board::{Board, ChessMove, MoveGen};
+use crate::output::send;
+use crate::time;
+
+/// The standard perft positions from the Chess Programming Wiki, by the names `position` takes
+/// for them (`pos2` is Kiwipete)
+pub const TEST_POSITIONS: [(&str, &str); 6] = [
+    ("startpos", "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"),
+    ("kiwipete", "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1"),
+    ("pos3", "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1"),
+    ("pos4", "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1"),
+    ("pos5", "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8"),
+    ("pos6", "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10"),
+];
+
+/// The test position called `name` (any case; `pos2` is an alias of `kiwipete`), or None
+pub fn named_position(name: &str) -> Option<Board> {
+    let name = match name.to_lowercase().as_str() {
+        "pos2" => "kiwipete".to_string(),
+        other => other.to_string(),
+    };
+    TEST_POSITIONS
+        .iter()
+        .find(|(known, _)| *known == name)
+        .map(|(_, fen)| Board::from_str(fen).expect("test positions are valid FENs"))
+}
+
+/// Count leaf nodes of the legal move tree to `depth`
+pub fn perft(board: &Board, depth: u32) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+    let moves = MoveGen::new_legal(board);
+    if depth == 1 {
+        return moves.len() as u64;
+    }
+    moves.map(|mv| perft(&board.make_move_new(mv), depth - 1)).sum()
+}
+
+/// Per-root-move node counts at `depth`, in generation order
+pub fn perft_divide(board: &Board, depth: u32) -> Vec<(ChessMove, u64)> {
+    if depth == 0 {
+        return Vec::new();
+    }
+    MoveGen::new_legal(board)
+        .map(|mv| (mv, perft(&board.make_move_new(mv), depth - 1)))
+        .collect()
+}
+
+/// Run perft and print the result. With `divide`, print one `move: nodes` line per root move
+/// first (the format other engines use, so outputs can be diffed directly).
+pub fn run(board: &Board, depth: u32, divide: bool) -> u64 {
+    let start = Instant::now();
+    let total = if divide {
+        let counts = perft_divide(board, depth);
+        for (mv, nodes) in &counts {
+            send!("{}: {}", board.move_to_uci(*mv), nodes);
+        }
+        send!("");
+        counts.iter().map(|(_, n)| n).sum()
+    } else {
+        perft(board, depth)
+    };
+    let ms = start.elapsed().as_millis().max(1) as u64;
+    send!("Nodes searched: {}", total);
+    send!("Time: {} ms ({} nps)", ms, time::nps(total, start.elapsed().as_micros() as u64));
+    total
+}
+
+/// One record of a perft suite: the node count expected from `board` at `depth`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PerftCase {
+    pub board: Board,
+    pub depth: u32,
+    pub nodes: u64,
+}
+
+/// `FEN; depth; expected_nodes` lines; blank lines and `#` comments are skipped
+pub fn parse_suite(text: &str) -> Result<Vec<PerftCase>, String> {
+    let mut cases = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let fields: Vec<&str> = line.split(';').map(str::trim).collect();
+        let [fen, depth, nodes] = fields[..] else {
+            return Err(format!("line {}: expected `FEN; depth; nodes`", i + 1));
+        };
+        let board = Board::from_str(fen).map_err(|e| format!("line {}: {}", i + 1, e))?;
+        let depth = depth.parse().map_err(|_| format!("line {}: invalid depth {}", i + 1, depth))?;
+        let nodes = nodes.parse().map_err(|_| format!("line {}: invalid node count {}", i + 1, nodes))?;
+        cases.push(PerftCase { board, depth, nodes });
+    }
+    Ok(cases)
+}
+
+/// Node counts of `cases`, in order, computed on up to `threads` threads
+pub fn suite_counts(cases: &[PerftCase], threads: usize) -> Vec<u64> {
+    let next = AtomicUsize::new(0);
+    let counts = Mutex::new(vec![0; cases.len()]);
+    thread::scope(|scope| {
+        for _ in 0..threads.clamp(1, cases.len().max(1)) {
+            scope.spawn(|| {
+                loop {
+                    let i = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(case) = cases.get(i) else { break };
+                    let nodes = perft(&case.board, case.depth);
+                    counts.lock().expect("perft workers don't panic")[i] = nodes;
+                }
+            });
+        }
+    });
+    counts.into_inner().expect("perft workers don't panic")
+}
+
+/// Run a suite and print one line per case, with the divide of every mismatch. Returns the
+/// number of mismatches.
+pub fn run_suite(cases: &[PerftCase], threads: usize) -> usize {
+    let start = Instant::now();
+    let counts = suite_counts(cases, threads);
+    let mut failures = 0;
+    for (case, &nodes) in cases.iter().zip(&counts) {
+        if nodes == case.nodes {
+            send!("ok    {} depth {}: {}", case.board, case.depth, nodes);
+            continue;
+        }
+        failures += 1;
+        send!("FAIL  {} depth {}: {} (expected {})", case.board, case.depth, nodes, case.nodes);
+        for (mv, nodes) in perft_divide(&case.board, case.depth) {
+            send!("  {}: {}", case.board.move_to_uci(mv), nodes);
+        }
+    }
+    let total: u64 = counts.iter().sum();
+    send!("");
+    send!("{} of {} passed, {} nodes in {} ms", cases.len() - failures, cases.len(), total, start.elapsed().as_millis());
+    failures
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_perft_startpos() {
+        let board = Board::default();
+        assert_eq!(perft(&board, 0), 1);
+        assert_eq!(perft(&board, 3), 8902);
+    }
+
+    #[test]
+    fn test_divide_sums_to_perft() {
+        let board = Board::from_str("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1").unwrap();
+        let divide = perft_divide(&board, 2);
+        assert_eq!(divide.len(), 48);
+        assert_eq!(divide.iter().map(|(_, n)| n).sum::<u64>(), 2039);
+
+        // e1g1 (castling) has 43 replies in kiwipete
+        let castle = divide.iter().find(|(mv, _)| board.move_to_uci(*mv) == "e1g1").unwrap();
+        assert_eq!(castle.1, 43);
+    }
+
+    #[test]
+    fn test_named_positions() {
+        // Depth 2 totals from the wiki's table
+        let expected = [("startpos", 400), ("kiwipete", 2039), ("pos3", 191), ("pos4", 264), ("pos5", 1486), ("pos6", 2079)];
+        for (name, nodes) in expected {
+            assert_eq!(perft(&named_position(name).unwrap(), 2), nodes, "{}", name);
+        }
+        assert_eq!(named_position("POS2"), named_position("kiwipete"));
+        assert_eq!(named_position("pos7"), None);
+    }
+
+    #[test]
+    fn test_parse_suite() {
+        let cases = parse_suite("# comment\n\n8/8/8/8/8/8/8/K1k5 w - - 0 1; 2; 9\n").unwrap();
+        assert_eq!(cases, vec![PerftCase { board: Board::from_str("8/8/8/8/8/8/8/K1k5 w - - 0 1").unwrap(), depth: 2, nodes: 9 }]);
+        assert!(parse_suite("8/8/8/8/8/8/8/K1k5 w - - 0 1; 2").unwrap_err().starts_with("line 1:"));
+        assert!(parse_suite("\n8/8/8/8/8/8/8/K1k5 w - - 0 1; two; 9").unwrap_err().starts_with("line 2:"));
+        assert!(parse_suite("8/8/8/8/8/8/8/K1k5 w - -; 1; x").is_err());
+        assert!(parse_suite("not a fen; 1; 1").is_err());
+    }
+
+    #[test]
+    fn test_suite_counts() {
+        // The shipped suite parses; its cheap cases pass, on several threads or one
+        let cases: Vec<PerftCase> = parse_suite(include_str!("../tests/perft.txt")).unwrap().into_iter().filter(|case| case.nodes < 30_000).collect();
+        assert!(cases.len() >= 2);
+        let expected: Vec<u64> = cases.iter().map(|case| case.nodes).collect();
+        assert_eq!(suite_counts(&cases, 4), expected);
+        assert_eq!(suite_counts(&cases, 1), expected);
+        assert_eq!(suite_counts(&[], 4), Vec::<u64>::new());
+
+        let wrong = PerftCase { nodes: 401, ..parse_suite(&format!("{}; 2; 400", TEST_POSITIONS[0].1)).unwrap().remove(0) };
+        assert_eq!(run_suite(&[wrong], 2), 1);
+    }
+
+    #[test]
+    fn test_perft_chess960() {
+        // Position 1 of the Chess960 perft suite (castling rights HFhf)
+        let board = Board::from_str("bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w KQkq - 2 9").unwrap();
+        assert!(board.is_chess960());
+        assert_eq!(perft(&board, 1), 21);
+        assert_eq!(perft(&board, 2), 528);
+        assert_eq!(perft(&board, 3), 12189);
+    }
+}
+
+// perft ("performance test") walks the legal move tree and counts leaves; comparing the totals to
+// published numbers validates move generation. When a count is off, `divide` breaks the total down
+// per root move so it can be diffed against a reference engine's divide output, then repeated one
+// level deeper from the offending move until the bad position is isolated.
+// The wiki's positions are also what bug reports tend to start from, so `position kiwipete` takes
+// them by name instead of making everyone paste the FEN.
+// A suite file makes the whole check one command: `perftsuite` runs every record (in parallel,
+// since the cases are independent) and prints the divide of each mismatch right away, which is
+// the first thing anyone debugging it would ask for. The counts are collected before anything is
+// printed, so the output keeps the file's order however the threads finish.
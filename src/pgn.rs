@@ -0,0 +1,589 @@
+use std::fmt;
+use std::io::BufRead;
+use std::str::FromStr;
+
+use crate::board::{BitBoard, Board, BoardStatus, ChessMove, Color, File, MoveGen, Piece, Rank, Square};
+use crate::game::Game;
+
+/// One game read from a PGN file: its tag pairs, start position and mainline moves.
+/// Comments, NAGs and variations are skipped.
+#[derive(Debug, Clone)]
+pub struct PgnGame {
+    /// Tag pairs in file order, e.g. `("White", "Carlsen")`
+    pub tags: Vec<(String, String)>,
+    /// Starting position (the `FEN` tag if present, else the standard start)
+    pub start: Board,
+    pub moves: Vec<ChessMove>,
+    /// Game termination marker: "1-0", "0-1", "1/2-1/2" or "*"
+    pub result: String,
+    /// Notes written after each move, by move index; empty for a game read from a file
+    pub annotations: Vec<Annotation>,
+}
+
+/// What is written after a move: a NAG such as `$2` (mistake) and/or a `{comment}`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Annotation {
+    pub nag: Option<u8>,
+    pub comment: Option<String>,
+}
+
+impl PgnGame {
+    /// Value of a tag, by exact name
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags.iter().find(|(k, _)| k == name).map(|(_, v)| v.as_str())
+    }
+
+    /// Every position of the mainline, from the start position to the final one
+    pub fn positions(&self) -> impl Iterator<Item = Board> + '_ {
+        let mut board = Some(self.start);
+        let mut moves = self.moves.iter();
+        std::iter::from_fn(move || {
+            let current = board?;
+            board = moves.next().map(|&mv| current.make_move_new(mv));
+            Some(current)
+        })
+    }
+
+    /// Replay the mainline into a `Game` (moves were checked for legality while parsing)
+    pub fn to_game(&self) -> Game {
+        let mut game = Game::new(self.start);
+        for &mv in &self.moves {
+            game.make_move(mv).expect("PGN moves are legal");
+        }
+        game
+    }
+
+    /// Parse the text of a single game (tag section plus movetext)
+    pub fn parse(text: &str) -> Result<PgnGame, String> {
+        let mut tags = Vec::new();
+        let mut movetext = String::new();
+        for line in text.lines() {
+            let line = line.trim();
+            if line.starts_with('%') {
+                continue; // escape line
+            }
+            if movetext.is_empty() && line.starts_with('[') {
+                tags.push(parse_tag(line)?);
+            } else {
+                movetext.push_str(line);
+                movetext.push('\n');
+            }
+        }
+
+        let tag = |name: &str| tags.iter().find(|(k, _)| k == name).map(|(_, v)| v.as_str());
+        let mut start = match tag("FEN") {
+            Some(fen) => Board::from_str(fen)?,
+            None => Board::default(),
+        };
+        if tag("Variant").is_some_and(|v| v.eq_ignore_ascii_case("chess960") || v.eq_ignore_ascii_case("fischerandom")) {
+            start.set_chess960(true);
+        }
+
+        let mut board = start;
+        let mut moves = Vec::new();
+        let mut result = String::from("*");
+        for token in movetext_tokens(&movetext) {
+            if matches!(token, "1-0" | "0-1" | "1/2-1/2" | "*") {
+                result = token.to_string();
+                break;
+            }
+            let mv = parse_san(&board, token).map_err(|e| format!("{} (move {})", e, moves.len() / 2 + 1))?;
+            board = board.make_move_new(mv);
+            moves.push(mv);
+        }
+
+        // Prefer the Result tag when the movetext was cut off without a marker
+        if result == "*" && let Some(tagged) = tag("Result") {
+            result = tagged.to_string();
+        }
+
+        Ok(PgnGame { tags, start, moves, result, annotations: Vec::new() })
+    }
+}
+
+/// Movetext lines are wrapped before this many columns, as the PGN standard asks
+const MAX_LINE: usize = 79;
+
+/// Writes the game as PGN: the tags in order, then the movetext in SAN wrapped at 79 columns
+impl fmt::Display for PgnGame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (name, value) in &self.tags {
+            writeln!(f, "[{} \"{}\"]", name, value.replace('\\', "\\\\").replace('"', "\\\""))?;
+        }
+        writeln!(f)?;
+
+        let mut tokens = san_tokens(&self.start, &self.moves, &self.annotations);
+        tokens.push(self.result.clone());
+
+        let mut line_len = 0;
+        for token in tokens {
+            if line_len > 0 && line_len + 1 + token.len() > MAX_LINE {
+                writeln!(f)?;
+                line_len = 0;
+            }
+            if line_len > 0 {
+                write!(f, " ")?;
+                line_len += 1;
+            }
+            write!(f, "{}", token)?;
+            line_len += token.len();
+        }
+        writeln!(f)
+    }
+}
+
+/// Moves from `board` as SAN with move numbers, e.g. `1. e4 e5 2. Nf3` or `3... Nc6 4. Bb5`
+pub fn line_to_san(board: &Board, moves: &[ChessMove]) -> String {
+    san_tokens(board, moves, &[]).join(" ")
+}
+
+/// Move numbers, SAN moves and annotations as separate tokens (a comment one per word). Black's
+/// move gets a `N...` number when it comes first or follows a comment.
+fn san_tokens(start: &Board, moves: &[ChessMove], annotations: &[Annotation]) -> Vec<String> {
+    let mut tokens = Vec::with_capacity(moves.len() * 3 / 2 + 1);
+    let mut board = *start;
+    let mut commented = false;
+    for (i, &mv) in moves.iter().enumerate() {
+        if board.side_to_move() == Color::White {
+            tokens.push(format!("{}.", board.fullmove_number()));
+        } else if i == 0 || commented {
+            tokens.push(format!("{}...", board.fullmove_number()));
+        }
+        tokens.push(move_to_san(&board, mv));
+        board = board.make_move_new(mv);
+
+        let annotation = annotations.get(i);
+        if let Some(nag) = annotation.and_then(|a| a.nag) {
+            tokens.push(format!("${}", nag));
+        }
+        commented = false;
+        if let Some(comment) = annotation.and_then(|a| a.comment.as_deref()) {
+            // A brace would end the comment early
+            let words: Vec<&str> = comment.split_whitespace().collect();
+            let last = words.len().saturating_sub(1);
+            for (j, word) in words.iter().enumerate() {
+                let word = word.replace('}', ")");
+                let open = if j == 0 { "{" } else { "" };
+                let close = if j == last { "}" } else { "" };
+                tokens.push(format!("{}{}{}", open, word, close));
+            }
+            commented = !words.is_empty();
+        }
+    }
+    tokens
+}
+
+/// Standard algebraic notation for a legal move: minimal disambiguation, `x` for captures,
+/// `=Q` for promotions, `O-O`/`O-O-O` for castling and a `+` or `#` suffix
+pub fn move_to_san(board: &Board, mv: ChessMove) -> String {
+    let src = mv.get_source();
+    let dst = mv.get_dest();
+    let piece = board.piece_on(src).expect("move_to_san needs a legal move");
+
+    let mut san = if board.is_castling(mv) {
+        // Internally the king captures its own rook
+        let rook = if mv.is_castling() { dst } else { board.normalize_move(mv).get_dest() };
+        if rook.file().to_index() > src.file().to_index() { "O-O".to_string() } else { "O-O-O".to_string() }
+    } else {
+        let mut san = String::new();
+        if piece == Piece::Pawn {
+            if board.is_capture(mv) {
+                san.push(file_char(src));
+            }
+        } else {
+            san.push(piece_char(piece));
+            let mut rivals = MoveGen::new_legal(board);
+            rivals.set_piece_mask(piece);
+            rivals.set_iterator_mask(BitBoard::from_square(dst));
+            let rivals: Vec<Square> = rivals.map(|m| m.get_source()).filter(|&s| s != src).collect();
+            if !rivals.is_empty() {
+                if rivals.iter().all(|s| s.file() != src.file()) {
+                    san.push(file_char(src));
+                } else if rivals.iter().all(|s| s.rank() != src.rank()) {
+                    san.push(rank_char(src));
+                } else {
+                    san.push(file_char(src));
+                    san.push(rank_char(src));
+                }
+            }
+        }
+        if board.is_capture(mv) {
+            san.push('x');
+        }
+        san.push(file_char(dst));
+        san.push(rank_char(dst));
+        if let Some(promotion) = mv.get_promotion() {
+            san.push('=');
+            san.push(piece_char(promotion));
+        }
+        san
+    };
+
+    let after = board.make_move_new(mv);
+    if after.status() == BoardStatus::Checkmate {
+        san.push('#');
+    } else if after.checkers().0 != 0 {
+        san.push('+');
+    }
+    san
+}
+
+fn piece_char(piece: Piece) -> char {
+    match piece {
+        Piece::Pawn => 'P',
+        Piece::Knight => 'N',
+        Piece::Bishop => 'B',
+        Piece::Rook => 'R',
+        Piece::Queen => 'Q',
+        Piece::King => 'K',
+    }
+}
+
+fn file_char(sq: Square) -> char {
+    (b'a' + sq.file().to_index() as u8) as char
+}
+
+fn rank_char(sq: Square) -> char {
+    (b'1' + sq.rank().to_index() as u8) as char
+}
+
+/// `[Name "value"]`, with `\"` and `\\` escapes inside the value
+fn parse_tag(line: &str) -> Result<(String, String), String> {
+    let inner = line
+        .strip_prefix('[')
+        .and_then(|l| l.strip_suffix(']'))
+        .ok_or_else(|| format!("Malformed tag: {}", line))?;
+    let (name, rest) = inner.split_once(char::is_whitespace).ok_or_else(|| format!("Malformed tag: {}", line))?;
+    let quoted = rest
+        .trim()
+        .strip_prefix('"')
+        .and_then(|r| r.strip_suffix('"'))
+        .ok_or_else(|| format!("Malformed tag value: {}", line))?;
+
+    let mut value = String::with_capacity(quoted.len());
+    let mut chars = quoted.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' && let Some(escaped) = chars.next() {
+            value.push(escaped);
+        } else {
+            value.push(c);
+        }
+    }
+    Ok((name.to_string(), value))
+}
+
+/// Split movetext into mainline SAN tokens and the result marker, dropping move numbers,
+/// `{...}` and `;` comments, `$n` NAGs and parenthesized variations (which may nest)
+fn movetext_tokens(text: &str) -> Vec<&str> {
+    let bytes = text.as_bytes();
+    let mut tokens = Vec::new();
+    let mut depth = 0usize;
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'{' => {
+                i = text[i..].find('}').map_or(bytes.len(), |end| i + end + 1);
+            }
+            b';' => {
+                i = text[i..].find('\n').map_or(bytes.len(), |end| i + end + 1);
+            }
+            b'(' => {
+                depth += 1;
+                i += 1;
+            }
+            b')' => {
+                depth = depth.saturating_sub(1);
+                i += 1;
+            }
+            c if c.is_ascii_whitespace() => i += 1,
+            _ => {
+                let start = i;
+                while i < bytes.len() && !bytes[i].is_ascii_whitespace() && !b"{;()".contains(&bytes[i]) {
+                    i += 1;
+                }
+                let token = &text[start..i];
+                if depth > 0 || token.starts_with('$') {
+                    continue;
+                }
+                if matches!(token, "1-0" | "0-1" | "1/2-1/2" | "*") {
+                    tokens.push(token);
+                    continue;
+                }
+                // "12." / "12..." move numbers, possibly glued to the move ("12.Nf3")
+                let san = token.trim_start_matches(|c: char| c.is_ascii_digit());
+                let san = if san.starts_with('.') { san.trim_start_matches('.') } else { token };
+                if !san.is_empty() {
+                    tokens.push(san);
+                }
+            }
+        }
+    }
+    tokens
+}
+
+/// Resolve a SAN move ("Nbd7", "exd6", "e8=Q+", "O-O") against the legal moves of `board`.
+/// Accepts the common sloppy forms: missing `=` before a promotion piece, `0-0` castling,
+/// trailing `!?` annotations and superfluous disambiguation.
+pub fn parse_san(board: &Board, san: &str) -> Result<ChessMove, String> {
+    let text = san.trim_end_matches(['+', '#', '!', '?']);
+
+    if matches!(text, "O-O" | "0-0" | "O-O-O" | "0-0-0") {
+        let kingside = text.len() == 3;
+        return MoveGen::new_legal(board)
+            .find(|&mv| {
+                board.is_castling(mv)
+                    && (mv.get_dest().file().to_index() > mv.get_source().file().to_index()) == kingside
+            })
+            .ok_or_else(|| format!("Illegal castling {} in {}", san, board));
+    }
+
+    let bytes = text.as_bytes();
+    let (piece, mut rest) = match bytes.first() {
+        Some(b'N') => (Piece::Knight, &bytes[1..]),
+        Some(b'B') => (Piece::Bishop, &bytes[1..]),
+        Some(b'R') => (Piece::Rook, &bytes[1..]),
+        Some(b'Q') => (Piece::Queen, &bytes[1..]),
+        Some(b'K') => (Piece::King, &bytes[1..]),
+        _ => (Piece::Pawn, bytes),
+    };
+
+    let mut promotion = None;
+    if piece == Piece::Pawn && let [head @ .., last] = rest {
+        let promo = match last.to_ascii_uppercase() {
+            b'N' => Some(Piece::Knight),
+            b'B' => Some(Piece::Bishop),
+            b'R' => Some(Piece::Rook),
+            b'Q' => Some(Piece::Queen),
+            _ => None,
+        };
+        if promo.is_some() && head.last().is_some_and(|c| c.is_ascii_digit() || *c == b'=') {
+            promotion = promo;
+            rest = head.strip_suffix(b"=").unwrap_or(head);
+        }
+    }
+
+    let invalid = || format!("Invalid SAN move: {}", san);
+    let [from @ .., dst_file, dst_rank] = rest else {
+        return Err(invalid());
+    };
+    let (dst_file, dst_rank) = (dst_file.wrapping_sub(b'a'), dst_rank.wrapping_sub(b'1'));
+    if dst_file >= 8 || dst_rank >= 8 {
+        return Err(invalid());
+    }
+
+    // Whatever is left is disambiguation plus capture/long-algebraic separators
+    let mut from_file = None;
+    let mut from_rank = None;
+    for &c in from {
+        match c {
+            b'a'..=b'h' => from_file = Some(c - b'a'),
+            b'1'..=b'8' => from_rank = Some(c - b'1'),
+            b'x' | b':' | b'-' => {}
+            _ => return Err(invalid()),
+        }
+    }
+
+    let dst = Square::make_square(Rank::from_index(dst_rank as usize), File::from_index(dst_file as usize));
+    let mut moves = MoveGen::new_legal(board);
+    moves.set_piece_mask(piece);
+    moves.set_iterator_mask(BitBoard::from_square(dst));
+    let mut candidates = moves.filter(|&mv| {
+        let src = mv.get_source();
+        !mv.is_castling()
+            && mv.get_promotion() == promotion
+            && from_file.is_none_or(|f| src.file().to_index() == f as usize)
+            && from_rank.is_none_or(|r| src.rank().to_index() == r as usize)
+    });
+    match (candidates.next(), candidates.next()) {
+        (Some(mv), None) => Ok(mv),
+        (None, _) => Err(format!("Illegal move {} in {}", san, board)),
+        (Some(_), Some(_)) => Err(format!("Ambiguous move {} in {}", san, board)),
+    }
+}
+
+/// Streams games out of a PGN source one at a time, so multi-gigabyte databases never
+/// have to be held in memory. A game that fails to parse yields an `Err` and reading
+/// continues with the next one.
+pub struct PgnReader<R: BufRead> {
+    reader: R,
+    /// First tag line of the next game, read while looking for the end of the previous one
+    pending: Option<String>,
+    done: bool,
+}
+
+impl<R: BufRead> PgnReader<R> {
+    pub fn new(reader: R) -> Self {
+        PgnReader { reader, pending: None, done: false }
+    }
+}
+
+impl<R: BufRead> Iterator for PgnReader<R> {
+    type Item = Result<PgnGame, String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let mut text = self.pending.take().unwrap_or_default();
+        let mut in_movetext = false;
+        loop {
+            let mut line = String::new();
+            match self.reader.read_line(&mut line) {
+                Ok(0) => {
+                    self.done = true;
+                    break;
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    self.done = true;
+                    return Some(Err(format!("PGN read error: {}", e)));
+                }
+            }
+            let trimmed = line.trim();
+            if trimmed.starts_with('[') && in_movetext {
+                self.pending = Some(line);
+                break;
+            }
+            if !trimmed.is_empty() && !trimmed.starts_with('[') && !trimmed.starts_with('%') {
+                in_movetext = true;
+            }
+            text.push_str(&line);
+        }
+
+        if text.trim().is_empty() {
+            return None;
+        }
+        Some(PgnGame::parse(&text))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TWO_GAMES: &str = r#"[Event "Test \"quoted\""]
+[White "A"]
+[Black "B"]
+[Result "1-0"]
+
+1. e4 e5 2. Nf3 {main line} Nc6 (2... d6 3. d4 (3. Bc4) exd4) 3. Bb5 $1 a6
+; rest of line is a comment
+4. Ba4 Nf6 5. O-O 1-0
+
+[Event "Second"]
+[FEN "4k3/P7/8/8/8/8/8/4K3 w - - 0 1"]
+[SetUp "1"]
+
+1.a8=Q+ Kd7 *
+"#;
+
+    #[test]
+    fn test_read_games() {
+        let games: Vec<PgnGame> = PgnReader::new(TWO_GAMES.as_bytes()).map(Result::unwrap).collect();
+        assert_eq!(games.len(), 2);
+
+        let first = &games[0];
+        assert_eq!(first.tag("Event"), Some("Test \"quoted\""));
+        assert_eq!(first.tag("White"), Some("A"));
+        assert_eq!(first.result, "1-0");
+        assert_eq!(first.moves.len(), 9);
+        assert_eq!(first.moves[6], ChessMove::from_uci("b5a4").unwrap());
+        assert!(first.positions().last().unwrap().to_string().starts_with("r1bqkb1r/1ppp1ppp/p1n2n2/4p3/B3P3/5N2/PPPP1PPP/RNBQ1RK1 b kq"));
+        assert_eq!(first.positions().count(), 10);
+
+        let second = &games[1];
+        assert_eq!(second.start.to_string(), "4k3/P7/8/8/8/8/8/4K3 w - - 0 1");
+        assert_eq!(second.moves[0], ChessMove::new(Square::A7, Square::A8, Some(Piece::Queen)));
+        assert_eq!(second.result, "*");
+        assert_eq!(second.to_game().moves().len(), 2);
+    }
+
+    #[test]
+    fn test_parse_san_forms() {
+        let board = Board::from_str("r3k2r/1P6/8/3N1N2/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+        // Two knights can reach e3: file disambiguation is required
+        assert!(parse_san(&board, "Ne3").unwrap_err().contains("Ambiguous"));
+        assert_eq!(parse_san(&board, "Nde3").unwrap(), ChessMove::new(Square::D5, Square::E3, None));
+        assert_eq!(parse_san(&board, "Nf5e3").unwrap(), ChessMove::new(Square::F5, Square::E3, None));
+        assert_eq!(parse_san(&board, "bxa8=N").unwrap(), ChessMove::new(Square::B7, Square::A8, Some(Piece::Knight)));
+        assert_eq!(parse_san(&board, "b8Q!?").unwrap(), ChessMove::new(Square::B7, Square::B8, Some(Piece::Queen)));
+        assert_eq!(parse_san(&board, "O-O").unwrap(), ChessMove::new_castling(Square::E1, Square::H1));
+        assert_eq!(parse_san(&board, "0-0-0+").unwrap(), ChessMove::new_castling(Square::E1, Square::A1));
+        assert!(parse_san(&board, "Ke3").is_err());
+        assert!(parse_san(&board, "Zz9").is_err());
+    }
+
+    #[test]
+    fn test_move_to_san() {
+        let board = Board::from_str("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1").unwrap();
+        let san = |uci: &str| move_to_san(&board, board.normalize_move(ChessMove::from_uci(uci).unwrap()));
+        assert_eq!(san("e1g1"), "O-O");
+        assert_eq!(san("e1c1"), "O-O-O");
+        assert_eq!(san("d5e6"), "dxe6");
+        assert_eq!(san("e5f7"), "Nxf7");
+        assert_eq!(san("f3f6"), "Qxf6");
+
+        let twins = Board::from_str("4k3/8/8/R7/8/5N2/8/RN2K3 w - - 0 1").unwrap();
+        assert_eq!(move_to_san(&twins, ChessMove::from_uci("b1d2").unwrap()), "Nbd2");
+        assert_eq!(move_to_san(&twins, ChessMove::from_uci("a5a3").unwrap()), "R5a3");
+
+        // Every legal move survives a round trip through SAN
+        for board in [board, Board::default(), Board::from_str("4k3/1P6/8/8/8/8/8/R3K2R w KQ - 0 1").unwrap()] {
+            for mv in MoveGen::new_legal(&board) {
+                assert_eq!(parse_san(&board, &move_to_san(&board, mv)), Ok(mv));
+            }
+        }
+
+        let mate = Board::from_str("r1bqkb1r/pppp1ppp/2n2n2/4p2Q/2B1P3/8/PPPP1PPP/RNB1K1NR w KQkq - 4 4").unwrap();
+        assert_eq!(move_to_san(&mate, ChessMove::from_uci("h5f7").unwrap()), "Qxf7#");
+
+        let line: Vec<ChessMove> = ["h5f7", "e8f7"].iter().map(|m| ChessMove::from_uci(m).unwrap()).collect();
+        assert_eq!(line_to_san(&mate, &line[..1]), "4. Qxf7#");
+        let after = mate.make_move_new(ChessMove::from_uci("a2a3").unwrap());
+        assert_eq!(line_to_san(&after, &[ChessMove::from_uci("f6e4").unwrap()]), "4... Nxe4");
+        assert_eq!(line_to_san(&mate, &[]), "");
+    }
+
+    #[test]
+    fn test_write_and_reread() {
+        let games: Vec<PgnGame> = PgnReader::new(TWO_GAMES.as_bytes()).map(Result::unwrap).collect();
+        for game in &games {
+            let text = game.to_string();
+            assert!(text.lines().all(|l| l.len() <= MAX_LINE));
+            let reread = PgnGame::parse(&text).unwrap();
+            assert_eq!(reread.tags, game.tags);
+            assert_eq!(reread.moves, game.moves);
+            assert_eq!(reread.result, game.result);
+        }
+        assert!(games[0].to_string().ends_with("\n\n1. e4 e5 2. Nf3 Nc6 3. Bb5 a6 4. Ba4 Nf6 5. O-O 1-0\n"));
+        assert!(games[1].to_string().ends_with("1. a8=Q+ Kd7 *\n"));
+
+        // Annotations go after their move, and a comment on White's move renumbers Black's
+        let mut game = games[0].clone();
+        game.moves.truncate(3);
+        game.annotations = vec![
+            Annotation { nag: None, comment: Some("+0.30".to_string()) },
+            Annotation { nag: Some(2), comment: None },
+            Annotation { nag: Some(4), comment: Some("-3.10; best Nc3".to_string()) },
+        ];
+        let text = game.to_string();
+        assert!(text.ends_with("\n\n1. e4 {+0.30} 1... e5 $2 2. Nf3 $4 {-3.10; best Nc3} 1-0\n"), "{}", text);
+        assert_eq!(PgnGame::parse(&text).unwrap().moves, game.moves);
+    }
+
+    #[test]
+    fn test_bad_game_does_not_stop_reader() {
+        let pgn = "[Event \"bad\"]\n\n1. e4 e4 *\n\n[Event \"good\"]\n\n1. d4 *\n";
+        let results: Vec<_> = PgnReader::new(pgn.as_bytes()).collect();
+        assert_eq!(results.len(), 2);
+        assert!(results[0].as_ref().unwrap_err().contains("Illegal move e4"));
+        assert_eq!(results[1].as_ref().unwrap().moves.len(), 1);
+    }
+}
+
+// PGN import: the reader buffers one game's text at a time (a new tag section after movetext
+// ends the previous game) and then parses it, so one malformed game costs only that game. Only
+// the mainline is kept: variations, comments and NAGs are recognized just well enough to skip,
+// which is all book building and training-data extraction need. SAN is resolved by matching
+// against the legal move list rather than decoding squares directly, which handles disambiguation
+// and castling in both standard and Chess960 positions for free.
@@ -18,9 +18,51 @@ pub const EG_PIECE_VALUE: [Score; 6] = [94, 281, 297, 512, 936, 24000];
 pub const PHASE_WEIGHT: [i32; 6] = [0, 1, 1, 2, 4, 0];
 pub const TOTAL_PHASE: i32 = 24; // 0*16 + 1*4 + 1*4 + 2*4 + 4*2
 
+// Mobility bonus per safe attacked square, indexed by piece (pawns/kings unused)
+pub const MOBILITY_MG: [Score; 6] = [0, 4, 5, 2, 1, 0];
+pub const MOBILITY_EG: [Score; 6] = [0, 4, 5, 4, 2, 0];
+// Typical square count per piece; mobility is scored relative to this so the average piece nets ~0
+pub const MOBILITY_BASE: [i32; 6] = [0, 4, 7, 7, 14, 0];
+
+// Rook on the opponent's second rank (enemy king on back rank or enemy pawns on that rank)
+pub const ROOK_SEVENTH_MG: Score = 20;
+pub const ROOK_SEVENTH_EG: Score = 40;
+
+// Connected (pawn-defended) and phalanx (side-by-side) pawn bonuses, indexed by relative rank
+pub const CONNECTED_PAWN_MG: [Score; 8] = [0, 0, 6, 8, 12, 24, 40, 0];
+pub const CONNECTED_PAWN_EG: [Score; 8] = [0, 0, 4, 6, 12, 30, 55, 0];
+pub const PHALANX_PAWN_MG: [Score; 8] = [0, 3, 5, 8, 14, 28, 45, 0];
+pub const PHALANX_PAWN_EG: [Score; 8] = [0, 1, 3, 6, 14, 32, 60, 0];
+
+// Pawn storm against a castled enemy king, by relative rank of our most advanced pawn per file
+pub const PAWN_STORM_MG: [Score; 8] = [0, 0, 0, 6, 16, 28, 12, 0];
+
+// Pawn shelter for a king still on its first two ranks, by relative rank of each pawn on the
+// king's wing: pawns in front of the king are worth more, pawns that have walked away from it less
+pub const PAWN_SHELTER_MG: [Score; 8] = [0, 12, 6, 0, -6, -6, 0, 0];
+
+// King danger: index weight per enemy attack on the king zone, indexed by attacking piece,
+// and per square an imaginary queen on the king square could reach
+pub const KING_ZONE_ATTACK_WEIGHT: [i32; 6] = [0, 2, 2, 3, 5, 0];
+pub const VIRTUAL_QUEEN_WEIGHT: i32 = 1;
+// Penalty = danger^2 / KING_DANGER_DIVISOR, capped at KING_DANGER_MAX (midgame only)
+pub const KING_DANGER_DIVISOR: i32 = 16;
+pub const KING_DANGER_MAX: Score = 500;
+
 // MVV-LVA victim values for capture ordering
 pub const MVV_VALUE: [Score; 6] = [100, 300, 310, 500, 900, 20000];
 
+/// Material + PST contribution (mg, eg) of one piece from White's perspective (Black negated).
+/// PeSTO tables are a8=0, so White reads `sq ^ 56` and Black reads `sq` directly.
+#[inline]
+pub fn psq_value(piece: usize, color: usize, sq: usize) -> (Score, Score) {
+    let (idx, sign) = if color == 0 { (sq ^ 56, 1) } else { (sq, -1) };
+    (
+        sign * (MG_PIECE_VALUE[piece] + MG_TABLE[piece][idx]),
+        sign * (EG_PIECE_VALUE[piece] + EG_TABLE[piece][idx]),
+    )
+}
+
 // Midgame piece-square tables (from White's perspective, a8=index 0, h1=index 63)
 // Source: PeSTO's Evaluation Function (https://www.chessprogramming.org/PeSTO%27s_Evaluation_Function)
 
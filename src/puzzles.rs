@@ -0,0 +1,159 @@
+use crate::board::{Board, BoardStatus, ChessMove};
+use crate::engine::{Engine, EngineEvent};
+use crate::game::Game;
+use crate::pgn::{move_to_san, PgnGame};
+use crate::types::{Score, ScoreBound, SearchInfo, SearchLimits};
+
+/// Scores are clamped to this before comparing, so every mate counts the same
+const SCORE_CLAMP: Score = 1000;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PuzzleConfig {
+    /// Search for every position of the game
+    pub limits: SearchLimits,
+    /// Centipawns the previous move must have handed to the side to move
+    pub min_swing: Score,
+    /// Centipawns the best move must be ahead of the second best, so the solution is unique
+    pub min_gap: Score,
+    /// The solver's score after the best move
+    pub min_score: Score,
+    /// Longest solution kept from the best line; always odd, ending on the solver's move
+    pub max_solution_plies: usize,
+}
+
+impl Default for PuzzleConfig {
+    fn default() -> Self {
+        PuzzleConfig { limits: SearchLimits::depth(10), min_swing: 200, min_gap: 150, min_score: 150, max_solution_plies: 5 }
+    }
+}
+
+/// A position where the side to move has one clearly best continuation
+#[derive(Debug, Clone, PartialEq)]
+pub struct Puzzle {
+    pub board: Board,
+    /// The best line, starting with the solver's move
+    pub solution: Vec<ChessMove>,
+    /// The solver's score, in centipawns or as a mate score
+    pub score: Score,
+    /// Moves of the game played before the position
+    pub ply: usize,
+}
+
+impl Puzzle {
+    /// An EPD record: `<position> bm <move>; pv "<line>"; ce <score>; id "<id>";`, moves in SAN
+    pub fn to_epd(&self, id: &str) -> String {
+        let fen = self.board.to_string();
+        let position: Vec<&str> = fen.split_whitespace().take(4).collect();
+        let mut board = self.board;
+        let line: Vec<String> = self
+            .solution
+            .iter()
+            .map(|&mv| {
+                let san = move_to_san(&board, mv);
+                board = board.make_move_new(mv);
+                san
+            })
+            .collect();
+        format!("{} bm {}; pv \"{}\"; ce {}; id \"{}\";", position.join(" "), line[0], line.join(" "), self.score, id)
+    }
+}
+
+/// The positions of `game` where the move just played gave the side to move a winning chance
+/// with a single good reply. Every position is searched with `config.limits` and two lines, so
+/// this sets the engine's `MultiPV` option to 2.
+pub fn find_puzzles(engine: &mut Engine, game: &PgnGame, config: &PuzzleConfig) -> Vec<Puzzle> {
+    engine.new_game();
+    engine.set_option("MultiPV", "2");
+    let mut history = Game::new(game.start);
+    let mut puzzles = Vec::new();
+    // The side to move's score before the last move, from the previous search
+    let mut previous: Option<Score> = None;
+    for ply in 0..=game.moves.len() {
+        let board = *history.board();
+        let lines = if board.status() == BoardStatus::Ongoing && history.draw_reason().is_none() {
+            search_lines(engine, &history, &config.limits)
+        } else {
+            Vec::new()
+        };
+
+        let best = lines.first().map(|line| line.score.clamp(-SCORE_CLAMP, SCORE_CLAMP));
+        if let (Some(before), Some(best), Some(second)) = (previous, best, lines.get(1)) {
+            // `before` is the opponent's score: what they threw away is what we gained
+            let swing = best + before;
+            let gap = best - second.score.clamp(-SCORE_CLAMP, SCORE_CLAMP);
+            if swing >= config.min_swing && gap >= config.min_gap && best >= config.min_score {
+                let mut solution = lines[0].pv.clone();
+                solution.truncate(config.max_solution_plies.max(1));
+                if solution.len() % 2 == 0 {
+                    solution.pop();
+                }
+                puzzles.push(Puzzle { board, solution, score: lines[0].score, ply });
+            }
+        }
+
+        previous = best;
+        if let Some(&mv) = game.moves.get(ply) {
+            history.make_move(mv).expect("PGN moves are legal");
+        }
+    }
+    puzzles
+}
+
+/// The exact lines of the deepest iteration that reported two of them (one if there is a
+/// single legal move), best first
+fn search_lines(engine: &mut Engine, game: &Game, limits: &SearchLimits) -> Vec<SearchInfo> {
+    engine.set_position(game.clone());
+    engine.go(limits.clone());
+    let (mut lines, mut complete): (Vec<SearchInfo>, Vec<SearchInfo>) = (Vec::new(), Vec::new());
+    for event in engine.events() {
+        match event {
+            EngineEvent::Info(info) if info.bound == ScoreBound::Exact => {
+                if lines.first().is_some_and(|line| line.depth < info.depth) {
+                    lines.clear();
+                }
+                lines.truncate(info.multipv - 1);
+                lines.push(info);
+                if lines.len() == 2 {
+                    complete = lines.clone();
+                }
+            }
+            EngineEvent::BestMove(result) => {
+                if complete.is_empty() && result.best_move.is_some() {
+                    // A single legal move: no second line is ever reported
+                    complete = lines;
+                }
+                break;
+            }
+            _ => {}
+        }
+    }
+    complete
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_mate_puzzle() {
+        let game = PgnGame::parse("1. e4 e5 2. Qh5 Nc6 3. Bc4 Nf6 *").unwrap();
+        let mut engine = Engine::new();
+        let config = PuzzleConfig { limits: SearchLimits::depth(4), ..Default::default() };
+        let puzzles = find_puzzles(&mut engine, &game, &config);
+
+        let puzzle = puzzles.iter().find(|p| p.ply == 6).expect("3...Nf6 allows mate");
+        assert_eq!(puzzle.solution.len(), 1);
+        assert_eq!(
+            puzzle.to_epd("test"),
+            format!("r1bqkb1r/pppp1ppp/2n2n2/4p2Q/2B1P3/8/PPPP1PPP/RNB1K1NR w KQkq - bm Qxf7#; pv \"Qxf7#\"; ce {}; id \"test\";", puzzle.score)
+        );
+        // The opening moves hand nobody anything
+        assert!(puzzles.iter().all(|p| p.ply >= 5));
+    }
+}
+
+// A puzzle needs two things from one search: the move before it must have been a mistake (the
+// swing, from consecutive positions' scores) and only one move may punish it (the gap between
+// the first and second MultiPV lines). Searching each position once serves both, since a
+// position's score is also the "before" of the next one. Records are EPD with standard opcodes,
+// the format the BK suite already reads, so a puzzle file doubles as a test suite.
@@ -1,109 +1,852 @@
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
-use std::time::Instant;
 
-use crate::board::{Board, BoardStatus, ChessMove, Piece};
+use crate::board::{Board, ChessMove, Color, MoveGen, Piece, Square};
+use crate::book::{Book, DEFAULT_BOOK_VARIETY, MAX_BOOKS};
 
-use crate::evaluation::evaluate;
+use crate::evaluation::{game_phase, EvalCache, EvalSettings};
+use crate::json;
+use crate::mcts;
 use crate::movegen::{order_captures, order_moves};
-use crate::syzygy::SyzygyProber;
+use crate::numa;
+use crate::output::send;
+use crate::pgn::move_to_san;
+use crate::pst::TOTAL_PHASE;
+use crate::smp::HelperPool;
+use crate::syzygy::{self, SharedProber, SyzygyLoadError, SyzygyProber};
 use crate::tt::{TTFlag, TranspositionTable};
-use crate::types::{Score, SearchResult, SCORE_INFINITY, SCORE_MATE, MAX_PLY, DEFAULT_HASH_MB, HISTORY_MAX};
+use crate::time::{self, ClockFn, Timer};
+use crate::trace::{trace_event, trace_span};
+use crate::tune;
+use crate::wdl;
+use crate::types::{IterationStats, Score, ScoreBound, SearchInfo, SearchLimits, SearchResult, SearchStats, CUTOFF_BUCKETS, SCORE_INFINITY, SCORE_MATE, MAX_PLY, DEFAULT_DEPTH, DEFAULT_HASH_MB, HISTORY_MAX};
+
+/// Skill Level at which play is unhandicapped (the UCI option's maximum)
+pub const MAX_SKILL_LEVEL: u8 = 20;
+
+/// Most search threads the Threads option allows
+pub const MAX_THREADS: usize = 256;
+
+/// Most lines the MultiPV option can ask for
+pub const MAX_MULTI_PV: usize = 64;
+
+/// Root moves a handicapped search chooses between (best first)
+const SKILL_CANDIDATES: usize = 4;
+
+/// Book Depth's maximum and default: the book is used for as long as it has moves
+pub const MAX_BOOK_DEPTH: u8 = 255;
+
+/// Most plies the OpeningVariety option can randomize
+pub const MAX_VARIETY_PLIES: u8 = 40;
+
+/// Largest OpeningVarietyMargin, in centipawns
+pub const MAX_VARIETY_MARGIN: Score = 100;
+
+/// Largest Contempt, in centipawns, and the cap on the dynamic value
+pub const MAX_CONTEMPT: Score = 100;
+
+/// DynamicContempt: Elo of UCI_RatingAdv per centipawn of contempt, and the most it adds
+const RATING_PER_CONTEMPT: i32 = 20;
+const MAX_RATING_CONTEMPT: Score = 20;
+
+/// DynamicContempt: centipawns added when the opponent's clock is empty and ours isn't
+const CLOCK_CONTEMPT: Score = 10;
+
+/// Largest Minimum Thinking Time, in milliseconds
+pub const MAX_MIN_THINK_MS: u64 = 5000;
+
+/// Largest nodestime, in nodes per millisecond
+pub const MAX_NODES_TIME: u64 = 10_000;
+
+/// Slow Mover's default and range: percent of the normal share of the clock per move
+pub const DEFAULT_SLOW_MOVER: u32 = 100;
+pub const MIN_SLOW_MOVER: u32 = 10;
+pub const MAX_SLOW_MOVER: u32 = 1000;
+
+/// Depth inside the search is counted in quarter plies, so extensions and reductions can be
+/// fractions of a ply
+const ONE_PLY: i32 = 4;
+
+/// Extensions, in quarter plies: half a ply per recapture, so a long exchange costs a little
+/// depth, and a full ply per passed-pawn push
+const RECAPTURE_EXTENSION: i32 = ONE_PLY / 2;
+const PASSED_PAWN_EXTENSION: i32 = ONE_PLY;
+
+/// Depth searched for a forced move under a time limit: enough for a PV to ponder on
+const SINGLE_REPLY_DEPTH: u8 = 4;
+
+/// Passed-pawn extension: most game phase (see `game_phase`) that counts as an endgame
+const PASSED_PAWN_MAX_PHASE: i32 = 8;
+
+/// Skill Level seed in deterministic mode
+const DETERMINISTIC_SEED: u64 = 0x9E37_79B9_7F4A_7C15;
+
+/// Iterations before this depth search with a full window: early scores are too unsettled
+const ASPIRATION_MIN_DEPTH: u8 = 4;
+
+impl SearchStats {
+    fn record_cutoff(&mut self, move_num: usize) {
+        self.cutoff_index[move_num.min(CUTOFF_BUCKETS - 1)] += 1;
+    }
+
+    /// Beta cutoffs: nodes that failed high on one of their moves
+    pub fn cut_nodes(&self) -> u64 {
+        self.cutoff_index.iter().sum()
+    }
+
+    /// Percentage of the nodes searched in quiescence
+    pub fn qsearch_share(&self) -> f64 {
+        percent(self.qnodes, self.nodes)
+    }
+
+    /// Percentage of TT probes that found an entry
+    pub fn tt_hit_rate(&self) -> f64 {
+        percent(self.tt_hits, self.tt_probes)
+    }
+
+    /// Effective branching factor: how many times more nodes each iteration took than the one
+    /// before, on (geometric) average from depth 2 on. None before two iterations have completed.
+    pub fn branching_factor(&self) -> Option<f64> {
+        let (first, last) = (self.iterations.first()?, self.iterations.last()?);
+        let steps = last.depth.checked_sub(first.depth).filter(|&steps| steps > 0)?;
+        Some((last.nodes.max(1) as f64 / first.nodes.max(1) as f64).powf(1.0 / steps as f64))
+    }
+
+    /// Format stats as `info string` lines
+    pub fn report(&self) -> Vec<String> {
+        let total_cutoffs = self.cut_nodes();
+        let dist: Vec<String> = self
+            .cutoff_index
+            .iter()
+            .enumerate()
+            .map(|(i, &n)| {
+                let label = if i == CUTOFF_BUCKETS - 1 { format!("{}+", i + 1) } else { (i + 1).to_string() };
+                format!("{}:{:.1}%", label, percent(n, total_cutoffs))
+            })
+            .collect();
+        let time_to_depth: Vec<String> = self.iterations.iter().map(|it| format!("{}:{}ms", it.depth, it.time_ms)).collect();
+
+        vec![
+            format!("info string stats cutoffs {} by move index {}", total_cutoffs, dist.join(" ")),
+            format!(
+                "info string stats tt probes {} hits {} ({:.1}%)",
+                self.tt_probes, self.tt_hits, self.tt_hit_rate()
+            ),
+            format!(
+                "info string stats nullmove tries {} cutoffs {} ({:.1}%)",
+                self.null_tries, self.null_cutoffs, percent(self.null_cutoffs, self.null_tries)
+            ),
+            format!(
+                "info string stats lmr searches {} researches {} ({:.1}%)",
+                self.lmr_searches, self.lmr_researches, percent(self.lmr_researches, self.lmr_searches)
+            ),
+            format!(
+                "info string stats pvs searches {} researches {} ({:.1}%)",
+                self.pvs_searches, self.pvs_researches, percent(self.pvs_researches, self.pvs_searches)
+            ),
+            format!(
+                "info string stats tb probes {} hits {} ({:.1}%)",
+                self.tb_probes, self.tb_hits, percent(self.tb_hits, self.tb_probes)
+            ),
+            format!(
+                "info string stats extensions recapture {} passed-pawn {}",
+                self.recapture_extensions, self.passed_pawn_extensions
+            ),
+            format!("info string stats see prunes {}", self.see_prunes),
+            format!("info string stats futility prunes {} razor cuts {}", self.futility_prunes, self.razor_cuts),
+            format!(
+                "info string stats nodes {} qsearch {} ({:.1}%) pv {} cut {} all {}",
+                self.nodes, self.qnodes, self.qsearch_share(), self.pv_nodes, total_cutoffs, self.all_nodes
+            ),
+            match self.branching_factor() {
+                Some(factor) => format!("info string stats branching factor {:.2}", factor),
+                None => "info string stats branching factor n/a".to_string(),
+            },
+            format!("info string stats time to depth {}", if time_to_depth.is_empty() { "n/a".to_string() } else { time_to_depth.join(" ") }),
+        ]
+    }
+}
+
+fn percent(part: u64, total: u64) -> f64 {
+    if total == 0 { 0.0 } else { part as f64 * 100.0 / total as f64 }
+}
+
+/// Search heuristics that can be switched off at runtime, so a regression can be traced to
+/// one of them without rebuilding. All on by default.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SearchParams {
+    pub null_move: bool,
+    pub lmr: bool,
+    pub aspiration: bool,
+    /// Recapture and passed-pawn extensions
+    pub extensions: bool,
+    /// Skipping shallow moves that lose material by static exchange
+    pub see_pruning: bool,
+    /// Reverse futility pruning, razoring and futility pruning of quiet moves
+    pub futility: bool,
+}
+
+impl Default for SearchParams {
+    fn default() -> Self {
+        SearchParams { null_move: true, lmr: true, aspiration: true, extensions: true, see_pruning: true, futility: true }
+    }
+}
+
+/// SearchBackend: how `search` picks a move
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SearchBackend {
+    /// Iterative deepening alpha-beta
+    #[default]
+    AlphaBeta,
+    /// Monte-Carlo tree search ranking moves by visits (see `mcts`)
+    Mcts,
+}
+
+impl SearchBackend {
+    /// Parse a UCI combo value (case-insensitive)
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "alphabeta" => Some(Self::AlphaBeta),
+            "mcts" => Some(Self::Mcts),
+            _ => None,
+        }
+    }
+}
+
+/// Triangular PV table: `lines[ply]` is the best line found so far from the node at `ply`,
+/// rebuilt from the child's line whenever a move raises alpha
+struct PvTable {
+    lines: Vec<Vec<ChessMove>>,
+}
+
+impl PvTable {
+    fn new() -> Self {
+        PvTable { lines: vec![Vec::new(); MAX_PLY + 1] }
+    }
+
+    fn clear(&mut self, ply: usize) {
+        if let Some(line) = self.lines.get_mut(ply) {
+            line.clear();
+        }
+    }
+
+    /// `mv` followed by the line just found at `ply + 1`
+    fn update(&mut self, ply: usize, mv: ChessMove) {
+        if ply >= MAX_PLY {
+            return;
+        }
+        let (head, tail) = self.lines.split_at_mut(ply + 1);
+        let line = &mut head[ply];
+        line.clear();
+        line.push(mv);
+        line.extend_from_slice(&tail[0]);
+    }
+
+    fn line(&self, ply: usize) -> &[ChessMove] {
+        &self.lines[ply]
+    }
+}
+
+/// Callback for per-iteration search progress (see `SearchState::info_sink`)
+pub type InfoSink = Box<dyn FnMut(&SearchInfo) + Send>;
+
+/// Callback for messages from background work, such as tablebase loading (see
+/// `SearchState::message_sink`)
+pub type MessageSink = Arc<dyn Fn(String) + Send + Sync>;
+
+/// What one search leaves for the next: built once at the configured Hash size and kept for
+/// the engine's lifetime. `SearchState::reset` only ages it, so it never reallocates.
+pub struct SearchTables {
+    /// Shared with the helper threads while a search runs, and only then (see `tt_mut`)
+    pub tt: Arc<TranspositionTable>,
+    pub eval_cache: EvalCache,
+    /// Quiet move scores by piece and destination, halved between searches
+    pub history: [[Score; 64]; 6],
+}
+
+impl Default for SearchTables {
+    fn default() -> Self {
+        Self::new(DEFAULT_HASH_MB)
+    }
+}
+
+impl SearchTables {
+    pub fn new(hash_mb: usize) -> Self {
+        SearchTables { tt: Arc::new(TranspositionTable::new(hash_mb)), eval_cache: EvalCache::new(), history: [[0; 64]; 6] }
+    }
+
+    /// The TT to clear, resize or age. Helper threads hold it only during a search, so
+    /// between searches it is this table's alone.
+    pub fn tt_mut(&mut self) -> &mut TranspositionTable {
+        Arc::get_mut(&mut self.tt).expect("the TT is only shared while a search runs")
+    }
+
+    /// `board`'s TT key: its hash with the style's key, since the scores follow the style's
+    /// shifted evaluation (see `EvalCache::style_key`)
+    pub fn tt_hash(&self, board: &Board) -> u64 {
+        board.get_hash() ^ self.eval_cache.style_key()
+    }
+}
 
 /// Mutable search state shared across recursion
 pub struct SearchState {
+    /// Nodes searched by this thread (see `total_nodes` for the helpers' too)
     pub nodes: u64,
-    pub start_time: Instant,
+    /// Deepest ply reached this search, quiescence included (UCI `seldepth`)
+    pub seldepth: u8,
+    pub start_time: Timer,
+    /// Millisecond clock for time limits where `std::time::Instant` is unavailable (wasm32)
+    pub clock: Option<ClockFn>,
     pub stop: Arc<AtomicBool>,
     pub time_limit_ms: u64,
+    /// Minimum Thinking Time: a timed search takes at least this many milliseconds, as far as
+    /// the clock allows
+    pub min_think_ms: u64,
+    /// Slow Mover: percent of the normal time allocation used per move under a clock
+    pub slow_mover: u32,
+    /// nodestime: nodes per millisecond the clock is counted in (0 = real time). A search under
+    /// wtime/btime then spends nodes rather than milliseconds.
+    pub nodes_time: u64,
+    /// Whether this search's time limits are counted in nodes (see `nodes_time`)
+    node_clock: bool,
+    /// This search's share of `min_think_ms` (0 when it has no time limit)
+    min_time_ms: u64,
+    /// Stop once this many nodes have been searched (0 = no limit); checked at every node
+    pub node_limit: u64,
+    /// Ignore time limits and seed Skill Level's randomness with a constant, so the same
+    /// commands give the same search, node for node
+    pub deterministic: bool,
     pub killers: [[Option<ChessMove>; 2]; MAX_PLY],
-    pub history: [[Score; 64]; 6],
-    pub tt: TranspositionTable,
-    pub syzygy: Option<SyzygyProber>,
+    pub tables: SearchTables,
+    /// Threads: search threads, this one included. The others are Lazy SMP helpers that
+    /// search the same position and share the TT (see `smp`).
+    pub threads: usize,
+    /// The helper threads, started by the first search that has a use for them
+    helpers: Option<HelperPool>,
+    /// Nodes the helpers have searched so far this search, added in batches as they go. On a
+    /// helper, the main state's counter.
+    helper_nodes: Arc<AtomicU64>,
+    /// 0 on the main search thread, 1 and up on helpers
+    thread_index: usize,
+    /// Tablebases from SyzygyPath; not probed while they are still loading
+    pub syzygy: Option<SharedProber>,
+    /// Opening books from BookFile, BookFile2, ...: a position in one of them gets a book move
+    /// without a search, from the first book that has the position
+    pub books: [Option<Book>; MAX_BOOKS],
+    /// Book Depth, Book Depth 2, ...: plies from the start of the game each book is used for
+    pub book_depths: [u8; MAX_BOOKS],
+    /// Book Variety: 0 plays the heaviest book move, higher values pick more evenly (see
+    /// `Book::pick_with_variety`)
+    pub book_variety: u8,
     pub root_best_move: Option<ChessMove>,
+    /// Hashes of the game's positions since the last capture or pawn move, oldest first, ending
+    /// with the root (`Game::repetition_history`). The search drops anything older.
     pub position_history: Vec<u64>,
     pub silent: bool,
+    /// Receives each completed iteration instead of it being printed as a UCI `info` line
+    pub info_sink: Option<InfoSink>,
+    /// Receives background messages instead of them being printed as `info string` lines
+    pub message_sink: Option<MessageSink>,
+    pub show_wdl: bool,
+    /// UCI_ShowRefutations (or `debug on`): after each iteration, print an `info refutation`
+    /// line for every root move that was refuted by a reply the search played out
+    pub show_refutations: bool,
+    /// OutputFormat json: print info lines as JSON (see `json::info_json`)
+    pub json_output: bool,
+    /// UCI `debug on`: print time manager decisions as `info string debug` lines
+    pub debug: bool,
+    pub stats: SearchStats,
+    /// 0-20: below `MAX_SKILL_LEVEL` the search is shallower and plays a randomly chosen
+    /// near-best root move
+    pub skill_level: u8,
+    /// Heuristics switched on (see `SearchParams`)
+    pub params: SearchParams,
+    pub backend: SearchBackend,
+    /// MultiPV: best lines reported per iteration, each searched with the moves starting the
+    /// better ones excluded. Only the first decides the move played.
+    pub multi_pv: usize,
+    /// OpeningVariety: for this many plies of the game, play a random root move among those
+    /// within `variety_margin` centipawns of the best
+    pub variety_plies: u8,
+    pub variety_margin: Score,
+    /// Contempt: centipawns a draw is worth below zero to the side to move at the root;
+    /// negative to steer towards draws
+    pub contempt: Score,
+    /// DynamicContempt: adjust contempt each search for the material left, the clocks and
+    /// `rating_adv` (see `search_contempt`)
+    pub dynamic_contempt: bool,
+    /// UCI_RatingAdv: the engine's rating minus the opponent's
+    pub rating_adv: i32,
+    /// This search's contempt, from `search_contempt`
+    draw_contempt: Score,
+    /// Root moves that failed low this iteration, each followed by the reply that refuted it
+    refutations: Vec<Vec<ChessMove>>,
+    /// Root moves skipped by the next search (finds the 2nd, 3rd, ... best move for Skill Level)
+    excluded_root_moves: Vec<ChessMove>,
+    /// The only root moves searched, from `SearchLimits::searchmoves` (all if empty)
+    search_moves: Vec<ChessMove>,
+    pv: PvTable,
+    /// Hash of the position at each ply of the current line, the root at 0
+    line_hashes: [u64; MAX_PLY + 1],
+    /// Where the move into each ply of the current line captured, if it was a capture
+    line_captures: [Option<Square>; MAX_PLY + 1],
+    /// Extensions taken by the current line on the way to each ply, in quarter plies
+    line_extensions: [i32; MAX_PLY + 1],
+    /// Most a line may be extended in total: the depth of the root search it belongs to
+    extension_budget: i32,
+    rng: u64,
+}
+
+impl Default for SearchState {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl SearchState {
     pub fn new() -> Self {
+        Self::with_tables(SearchTables::default())
+    }
+
+    /// A search state around tables built (and sized) beforehand. Everything else is small, so
+    /// this allocates next to nothing.
+    pub fn with_tables(tables: SearchTables) -> Self {
         Self {
             nodes: 0,
-            start_time: Instant::now(),
+            seldepth: 0,
+            start_time: Timer::start(None),
+            clock: None,
             stop: Arc::new(AtomicBool::new(false)),
             time_limit_ms: 0,
+            min_think_ms: 0,
+            slow_mover: DEFAULT_SLOW_MOVER,
+            nodes_time: 0,
+            node_clock: false,
+            min_time_ms: 0,
+            node_limit: 0,
+            deterministic: false,
             killers: [[None; 2]; MAX_PLY],
-            history: [[0; 64]; 6],
-            tt: TranspositionTable::new(DEFAULT_HASH_MB),
+            tables,
+            threads: 1,
+            helpers: None,
+            helper_nodes: Arc::new(AtomicU64::new(0)),
+            thread_index: 0,
             syzygy: None,
+            books: Default::default(),
+            book_depths: [MAX_BOOK_DEPTH; MAX_BOOKS],
+            book_variety: DEFAULT_BOOK_VARIETY,
             root_best_move: None,
             position_history: Vec::new(),
             silent: false,
+            info_sink: None,
+            message_sink: None,
+            show_wdl: false,
+            show_refutations: false,
+            json_output: false,
+            debug: false,
+            stats: SearchStats::default(),
+            skill_level: MAX_SKILL_LEVEL,
+            params: SearchParams::default(),
+            backend: SearchBackend::AlphaBeta,
+            multi_pv: 1,
+            variety_plies: 0,
+            variety_margin: 20,
+            contempt: 0,
+            dynamic_contempt: true,
+            rating_adv: 0,
+            draw_contempt: 0,
+            refutations: Vec::new(),
+            excluded_root_moves: Vec::new(),
+            search_moves: Vec::new(),
+            pv: PvTable::new(),
+            line_hashes: [0; MAX_PLY + 1],
+            line_captures: [None; MAX_PLY + 1],
+            line_extensions: [0; MAX_PLY + 1],
+            extension_budget: 0,
+            rng: seed_rng(),
         }
     }
 
+    /// The state of helper thread `index`, built on that thread so its tables are allocated
+    /// there. It borrows the main state's TT for each search (see `help`), so its own is a
+    /// token one.
+    pub(crate) fn helper(index: usize) -> Self {
+        let tables = SearchTables { tt: Arc::new(TranspositionTable::new(0)), eval_cache: EvalCache::new(), history: [[0; 64]; 6] };
+        SearchState { thread_index: index, silent: true, ..Self::with_tables(tables) }
+    }
+
+    /// Prepare for the next search: per-search counters start over, while the tables are only
+    /// aged. Allocation-free, so a state is best reused rather than rebuilt.
     pub fn reset(&mut self) {
         self.nodes = 0;
+        self.seldepth = 0;
         self.stop.store(false, Ordering::SeqCst);
         self.killers = [[None; 2]; MAX_PLY];
-        self.history = [[0; 64]; 6];
-        self.start_time = Instant::now();
-        self.tt.new_search();
+        self.tables.history.iter_mut().flatten().for_each(|h| *h /= 2);
+        self.start_time = Timer::start(self.clock);
+        self.tables.tt_mut().new_search();
         self.root_best_move = None;
+        self.stats = SearchStats::default();
+    }
+
+    /// Set the time and node limits for a search by `side`
+    fn apply_limits(&mut self, limits: &SearchLimits, board: &Board) {
+        let side = board.side_to_move();
+        let allocated = if self.deterministic { 0 } else { limits.compute_time_ms(side, self.slow_mover) };
+        self.node_clock = self.nodes_time > 0 && allocated > 0 && limits.movetime.is_none();
+        // Minimum Thinking Time raises the allocation, never past what the clock can spare;
+        // a clock counted in nodes has no use for waiting
+        self.min_time_ms = if allocated > 0 && !self.node_clock { self.min_think_ms.min(limits.max_time_ms(side)) } else { 0 };
+        self.time_limit_ms = allocated.max(self.min_time_ms);
+        self.node_limit = limits.nodes.unwrap_or(0);
+        if self.node_clock {
+            // The hard limit becomes a node limit, which is exact
+            let budget = self.time_limit_ms * self.nodes_time;
+            self.node_limit = if self.node_limit > 0 { self.node_limit.min(budget) } else { budget };
+        }
+        self.search_moves = limits.searchmoves.iter().map(|&mv| board.normalize_move(mv)).filter(|&mv| board.legal(mv)).collect();
+        trace_event!(
+            DEBUG,
+            allocated,
+            min_time_ms = self.min_time_ms,
+            time_limit_ms = self.time_limit_ms,
+            node_limit = self.node_limit,
+            node_clock = self.node_clock,
+            "limits"
+        );
+    }
+
+    /// Contempt for a search from `board`: the Contempt option plus the Style's, as is, or with
+    /// DynamicContempt raised against a weaker opponent and when ahead on the clock, then scaled
+    /// by the material left, so that it is gone by the time only kings and pawns remain
+    fn search_contempt(&self, board: &Board, limits: &SearchLimits) -> Score {
+        let contempt = (self.contempt + self.tables.eval_cache.style().weights().contempt).clamp(-MAX_CONTEMPT, MAX_CONTEMPT);
+        if !self.dynamic_contempt {
+            return contempt;
+        }
+        let mut contempt = contempt + (self.rating_adv / RATING_PER_CONTEMPT).clamp(-MAX_RATING_CONTEMPT, MAX_RATING_CONTEMPT);
+        let (ours, theirs) = match board.side_to_move() {
+            Color::White => (limits.wtime, limits.btime),
+            Color::Black => (limits.btime, limits.wtime),
+        };
+        if let (Some(ours), Some(theirs)) = (ours, theirs)
+            && ours.max(theirs) > 0
+        {
+            let lead = (ours as i64 - theirs as i64) * CLOCK_CONTEMPT as i64 / ours.max(theirs) as i64;
+            contempt += lead as Score;
+        }
+        (contempt * game_phase(board) / TOTAL_PHASE).clamp(-MAX_CONTEMPT, MAX_CONTEMPT)
+    }
+
+    /// The score of a drawn position `ply` plies from the root, for its side to move
+    fn draw_score(&self, ply: usize) -> Score {
+        if ply.is_multiple_of(2) { -self.draw_contempt } else { self.draw_contempt }
     }
 
+    /// Whether the root move `mv` is left out of this search
+    pub(crate) fn skips_root_move(&self, mv: ChessMove) -> bool {
+        self.excluded_root_moves.contains(&mv) || (!self.search_moves.is_empty() && !self.search_moves.contains(&mv))
+    }
+
+    /// Turn deterministic mode on or off; turning it on restarts Skill Level's random sequence
+    pub fn set_deterministic(&mut self, on: bool) {
+        self.deterministic = on;
+        if on {
+            self.seed_skill(DETERMINISTIC_SEED);
+        }
+    }
+
+    /// Make the state fit to search again after a search panicked part way: per-search state
+    /// starts over and the tables are cleared, since a half-finished node may have left them
+    /// wrong. Options are kept.
+    pub fn recover(&mut self) {
+        self.finish_helpers();
+        self.reset();
+        self.tables.tt_mut().clear();
+        self.tables.eval_cache.clear();
+        self.tables.history = [[0; 64]; 6];
+        self.excluded_root_moves.clear();
+        self.refutations.clear();
+    }
+
+    /// `info string` lines listing the legal moves of `board` in the order a search of it would
+    /// try them: UCI and SAN, the ordering score (from the TT's move, the root killers and the
+    /// history table as they stand) and the change in static eval for the mover, in centipawns
+    pub fn move_report(&mut self, board: &Board) -> Vec<String> {
+        let hash_move = self.tables.tt.probe(self.tables.tt_hash(board), 0).and_then(|entry| entry.best_move());
+        let moves = order_moves(board, hash_move, &self.killers[0], &self.tables.history, 0);
+        let before = self.tables.eval_cache.evaluate(board);
+        let mut lines = vec![format!("info string moves {} legal, static eval {}", moves.len(), before)];
+        for scored in &moves {
+            let after = -self.tables.eval_cache.evaluate(&board.make_move_new(scored.mv));
+            lines.push(format!(
+                "info string move {} {} order {} eval {:+}",
+                board.move_to_uci(scored.mv),
+                move_to_san(board, scored.mv),
+                scored.score,
+                after - before
+            ));
+        }
+        lines
+    }
+
+    /// `info string` lines with what the TT and the tablebases hold for `board`: the TT entry's
+    /// depth (in plies), score, bound, move and age (in searches), and the Syzygy WDL and DTZ
+    pub fn probe_report(&self, board: &Board) -> Vec<String> {
+        let tt = &self.tables.tt;
+        let tt_line = match tt.probe(self.tables.tt_hash(board), 0) {
+            Some(entry) => {
+                let bound = match entry.flag() {
+                    TTFlag::Exact => "exact",
+                    TTFlag::LowerBound => "lower",
+                    TTFlag::UpperBound => "upper",
+                };
+                let mv = entry.best_move().map_or("none".to_string(), |mv| board.move_to_uci(mv));
+                format!(
+                    "info string probe tt depth {} score {} bound {} move {} age {}",
+                    entry.depth() as f64 / ONE_PLY as f64,
+                    entry.score(),
+                    bound,
+                    mv,
+                    tt.searches_since(&entry)
+                )
+            }
+            None => "info string probe tt none".to_string(),
+        };
+        let tb_line = match self.syzygy.as_ref().and_then(SharedProber::get) {
+            None => "info string probe tb not loaded".to_string(),
+            Some(syzygy) => match syzygy.probe_wdl(board) {
+                None => "info string probe tb none".to_string(),
+                Some(score) => {
+                    let wdl = match score {
+                        s if s > 100 => "win",
+                        s if s > 0 => "cursed-win",
+                        0 => "draw",
+                        s if s >= -100 => "blessed-loss",
+                        _ => "loss",
+                    };
+                    let dtz = syzygy.probe_dtz(board).map_or("none".to_string(), |dtz| dtz.to_string());
+                    format!("info string probe tb wdl {} dtz {}", wdl, dtz)
+                }
+            },
+        };
+        vec![tt_line, tb_line]
+    }
+
+    /// Forget the previous game: history and eval cache start empty. The TT is only cleared in
+    /// deterministic mode, where a search mustn't depend on earlier ones; otherwise its entries
+    /// stay until overwritten, which their older generation allows at once.
+    pub fn new_game(&mut self) {
+        if self.deterministic {
+            self.tables.tt_mut().clear();
+        }
+        self.tables.eval_cache.clear();
+        self.tables.history = [[0; 64]; 6];
+    }
+
+    /// Resize the TT, keeping what it has learned so far
     pub fn resize_tt(&mut self, mb: usize) {
-        self.tt = TranspositionTable::new(mb);
+        self.tables.tt_mut().resize(mb);
+    }
+
+    /// Find the tablebases in `path` and open them on a background thread, which reports the
+    /// tables found when done; searches don't probe them until then. Fails if the directory
+    /// has no tables at all, keeping the tables loaded before.
+    pub fn load_syzygy(&mut self, path: &str) -> Result<(), SyzygyLoadError> {
+        let prober = SyzygyProber::open(path)?;
+        let summary = format!("in {} ({})", path, prober.summary());
+        let sink = self.message_sink.clone();
+        let silent = self.silent;
+        self.syzygy = Some(SharedProber::load(prober, move |report| {
+            let message = format!("{} {}", report, summary);
+            match sink {
+                Some(sink) => sink(message),
+                None if !silent => send!("info string {}", message),
+                None => {}
+            }
+        }));
+        Ok(())
     }
 
-    pub fn load_syzygy(&mut self, path: &str) {
-        self.syzygy = SyzygyProber::new(path);
+    /// Time this search has used as the clock counts it: real time, or nodes under nodestime
+    fn elapsed_ms(&self) -> u64 {
+        if self.node_clock { self.nodes / self.nodes_time } else { self.start_time.elapsed_ms() }
     }
 
     fn check_time(&self) {
         if self.time_limit_ms > 0 {
-            let elapsed = self.start_time.elapsed().as_millis() as u64;
+            let elapsed = self.elapsed_ms();
             if elapsed >= self.time_limit_ms {
                 self.stop.store(true, Ordering::Relaxed);
             }
         }
     }
 
-    fn is_stopped(&self) -> bool {
-        self.stop.load(Ordering::Relaxed)
+    /// Time after which no new iteration is started (0 = no limit)
+    pub fn soft_time_limit_ms(&self) -> u64 {
+        self.time_limit_ms * tune::SOFT_TIME_PERCENT.get() as u64 / 100
     }
-}
 
-/// Extract principal variation by following TT hash move chain
-fn extract_pv(board: &Board, tt: &TranspositionTable, max_moves: usize) -> Vec<ChessMove> {
-    let mut pv = Vec::new();
-    let mut current_board = *board;
-    let mut seen: Vec<u64> = Vec::new();
+    /// Soft limit once the root score has fallen `score_drop` centipawns since the previous
+    /// iteration: a big enough drop ("panic") doubles it, never past the hard limit
+    pub fn soft_limit_after_drop_ms(&self, score_drop: Score) -> u64 {
+        if score_drop >= tune::PANIC_MARGIN.get() {
+            (self.soft_time_limit_ms() * 2).min(self.time_limit_ms)
+        } else {
+            self.soft_time_limit_ms()
+        }
+    }
 
-    for _ in 0..max_moves {
-        let hash = current_board.get_hash();
-        if seen.contains(&hash) {
-            break;
+    /// Hold the answer until the search has taken `min_time_ms`, or is stopped. Browsers have
+    /// no thread to block, so on wasm32 a search that finishes early answers early.
+    pub(crate) fn wait_min_time(&self) {
+        #[cfg(not(target_arch = "wasm32"))]
+        while !self.is_stopped() && self.start_time.elapsed_ms() < self.min_time_ms {
+            std::thread::sleep(std::time::Duration::from_millis(1));
         }
-        seen.push(hash);
+    }
 
-        if let Some(entry) = tt.probe(hash, 0) {
-            if let Some(mv) = entry.best_move {
-                if current_board.legal(mv) {
-                    pv.push(mv);
-                    current_board = current_board.make_move_new(mv);
-                } else {
-                    break;
-                }
-            } else {
-                break;
+    fn debug_log(&self, message: &str) {
+        trace_event!(DEBUG, "{}", message);
+        if self.debug {
+            send!("info string debug {}", message);
+        }
+    }
+
+    /// Count a node, stopping the search at the node limit or (every 2048 nodes) the time limit.
+    /// Nodes entered after the limit return at once and aren't counted. Helpers pass on their
+    /// count every 2048 nodes too.
+    fn count_node(&mut self) {
+        if self.node_limit > 0 && self.nodes >= self.node_limit {
+            return;
+        }
+        self.nodes += 1;
+        if self.nodes & 2047 == 0 {
+            self.check_time();
+            if self.thread_index > 0 {
+                self.helper_nodes.fetch_add(2048, Ordering::Relaxed);
             }
-        } else {
-            break;
         }
+        if self.node_limit > 0 && self.nodes >= self.node_limit {
+            self.stop.store(true, Ordering::Relaxed);
+        }
+    }
+
+    /// Whether the position at `ply` of the current line appeared before, in the line or in the
+    /// game. Only positions with the same side to move since the last capture or pawn move can
+    /// match, so this looks back every second ply as far as the halfmove clock.
+    fn is_repetition(&self, board: &Board, ply: usize) -> bool {
+        let hash = self.line_hashes[ply];
+        let game = &self.position_history;
+        (4..=board.halfmove_clock() as usize).step_by(2).any(|back| match ply.checked_sub(back) {
+            Some(earlier) => self.line_hashes[earlier] == hash,
+            // Past the root, whose hash ends the game history
+            None => game.len().checked_sub(1 + back - ply).is_some_and(|i| game[i] == hash),
+        })
+    }
+
+    pub(crate) fn is_stopped(&self) -> bool {
+        self.stop.load(Ordering::Relaxed)
+    }
+
+    /// Nodes searched this search by all threads, the helpers' as of their last batch
+    pub fn total_nodes(&self) -> u64 {
+        self.nodes + self.helper_nodes.load(Ordering::Relaxed)
+    }
+
+    /// Lazy SMP: set the helper threads searching `board` up to `max_depth`, alongside this
+    /// thread. Searches with a node limit (nodes, nodestime) or in deterministic mode stay on
+    /// this thread, which keeps their node counts exact and repeatable.
+    fn start_helpers(&mut self, board: &Board, max_depth: u8) {
+        let count = self.threads.clamp(1, MAX_THREADS) - 1;
+        if count == 0 || self.deterministic || self.node_limit > 0 || cfg!(target_arch = "wasm32") {
+            return;
+        }
+        let mut pool = match self.helpers.take() {
+            Some(pool) if pool.fits(count) => pool,
+            _ => HelperPool::new(count),
+        };
+        let search = HelperSearch {
+            board: *board,
+            max_depth,
+            tt: Arc::clone(&self.tables.tt),
+            eval: self.tables.eval_cache.settings(),
+            params: self.params,
+            draw_contempt: self.draw_contempt,
+            position_history: self.position_history.clone(),
+            search_moves: self.search_moves.clone(),
+            syzygy: self.syzygy.clone(),
+            nodes: Arc::clone(&self.helper_nodes),
+        };
+        pool.start(|_, stop| {
+            let search = search.clone();
+            Box::new(move |helper: &mut SearchState| help(helper, search, stop))
+        });
+        self.debug_log(&format!("{} helper threads on {} NUMA nodes", count, numa::nodes_in_use()));
+        self.helpers = Some(pool);
+    }
+
+    /// Stop the helper threads and wait for them, so that the TT is this state's alone again.
+    /// A pool that lost a thread to a panic is dropped, to be started afresh.
+    fn finish_helpers(&mut self) {
+        if let Some(pool) = self.helpers.as_mut()
+            && !pool.finish()
+        {
+            self.helpers = None;
+        }
+    }
+
+    /// Reseed the Skill Level move picker (for reproducible weak play)
+    pub fn seed_skill(&mut self, seed: u64) {
+        self.rng = seed | 1;
+    }
+
+    /// A book move for `board` from the first book that has one and that the game is still
+    /// under the Book Depth of, weighted by the book and Book Variety and drawn from Skill
+    /// Level's random sequence
+    fn book_move(&mut self, board: &Board) -> Option<ChessMove> {
+        let ply = game_ply(board);
+        let in_use: Vec<usize> = (0..MAX_BOOKS).filter(|&i| self.books[i].is_some() && ply < self.book_depths[i] as u32).collect();
+        if in_use.is_empty() {
+            return None;
+        }
+        let random = self.next_random();
+        in_use.into_iter().find_map(|i| self.books[i].as_ref()?.pick_with_variety(board, random, self.book_variety))
+    }
+
+    /// xorshift64
+    fn next_random(&mut self) -> u64 {
+        self.rng ^= self.rng << 13;
+        self.rng ^= self.rng >> 7;
+        self.rng ^= self.rng << 17;
+        self.rng
     }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn seed_rng() -> u64 {
+    // No system time here; embedders reseed with seed_skill
+    0x9E37_79B9_7F4A_7C15
+}
 
-    pv
+#[cfg(not(target_arch = "wasm32"))]
+fn seed_rng() -> u64 {
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |d| d.as_nanos() as u64);
+    nanos | 1
+}
+
+/// PV as a space-separated UCI string (castling notation depends on each position)
+pub fn pv_to_uci(board: &Board, pv: &[ChessMove]) -> String {
+    let mut current_board = *board;
+    let mut out = Vec::with_capacity(pv.len());
+    for &mv in pv {
+        out.push(current_board.move_to_uci(mv));
+        current_board = current_board.make_move_new(mv);
+    }
+    out.join(" ")
 }
 
 /// Format a score for UCI output (centipawns or mate-in-N).
@@ -121,21 +864,105 @@ pub fn format_score(score: Score) -> String {
     }
 }
 
-/// Iterative deepening search. Returns best move found
-pub fn search(board: &Board, state: &mut SearchState, max_depth: u8) -> SearchResult {
+/// Iterative deepening search within `limits`. Returns best move found
+pub fn search(board: &Board, state: &mut SearchState, limits: &SearchLimits) -> SearchResult {
     let mut best_move: Option<ChessMove> = None;
     let mut best_score: Score = -SCORE_INFINITY;
     let mut completed_depth: u8 = 0;
-    let mut total_nodes: u64 = 0;
+    let mut partial_depth = None;
+    let _search = trace_span!(DEBUG, "search", fen = %board);
+    state.nodes = 0;
+    state.helper_nodes.store(0, Ordering::Relaxed);
+    state.seldepth = 0;
+    state.apply_limits(limits, board);
+    state.draw_contempt = state.search_contempt(board, limits);
+    state.tables.eval_cache.set_style_side(board.side_to_move());
+    // Book moves are for play: analysis, mate searches and searchmoves get a real search
+    if !limits.infinite && limits.mate.is_none() && state.search_moves.is_empty() && let Some(mv) = state.book_move(board) {
+        state.debug_log(&format!("book move {}", board.move_to_uci(mv)));
+        state.wait_min_time();
+        return unsearched_result(state, Some(mv));
+    }
+    // Deterministic mode ignores the clock, so a search only the clock would end gets the default depth
+    let clock_only = state.deterministic && !limits.infinite && limits.nodes.is_none() && limits.mate.is_none();
+    let max_depth = limits.depth.unwrap_or(if clock_only { DEFAULT_DEPTH } else { limits.default_depth() });
+    if state.node_clock {
+        state.debug_log(&format!(
+            "time limit {}ms, soft limit {}ms, counted at {} nodes/ms",
+            state.time_limit_ms, state.soft_time_limit_ms(), state.nodes_time
+        ));
+    } else if state.time_limit_ms > 0 {
+        state.debug_log(&format!("time limit {}ms, soft limit {}ms", state.time_limit_ms, state.soft_time_limit_ms()));
+    } else {
+        state.debug_log(&format!("no time limit, max depth {}", max_depth));
+    }
+    // Repetition lookups past the root expect the game history to end with it
+    if state.position_history.last() != Some(&board.get_hash()) {
+        state.position_history.push(board.get_hash());
+    }
+    // and to reach back no further than the root's last irreversible move
+    let window = board.halfmove_clock() as usize + 1;
+    if state.position_history.len() > window {
+        state.position_history.drain(..state.position_history.len() - window);
+    }
+    // The tree search shares the limits and game history set up so far
+    if state.backend == SearchBackend::Mcts {
+        return mcts::search(board, state, max_depth);
+    }
+    // PV of the last completed iteration, shown while a failed-low re-search has none
+    let mut pv: Vec<ChessMove> = Vec::new();
+
+    // Weaker levels also look less far ahead
+    let handicapped = state.skill_level < MAX_SKILL_LEVEL;
+    let max_depth = if handicapped { max_depth.min(state.skill_level + 1) } else { max_depth };
+    // A single legal move needs no thinking time, only a line to ponder on
+    let single_reply = state.time_limit_ms > 0 && !limits.infinite && MoveGen::new_legal(board).len() == 1;
+    if single_reply {
+        state.debug_log(&format!("single legal move, searching to depth {} only", SINGLE_REPLY_DEPTH));
+    }
+    let max_depth = if single_reply { max_depth.min(SINGLE_REPLY_DEPTH) } else { max_depth };
+    state.start_helpers(board, max_depth);
 
     for depth in 1..=max_depth {
-        state.nodes = 0;
-        state.root_best_move = None;
-        let score = negamax(board, state, depth, 0, -SCORE_INFINITY, SCORE_INFINITY, true);
-
-        total_nodes += state.nodes;
+        let _iteration = trace_span!(DEBUG, "iteration", depth);
+        let iteration_start_nodes = state.nodes;
+        // Aspiration window around the last score, widened on the failing side until the
+        // score lands inside it. Mate scores swing too far for a window to help.
+        let mut window = tune::ASPIRATION_WINDOW.get();
+        let (mut alpha, mut beta) = if state.params.aspiration && depth >= ASPIRATION_MIN_DEPTH && best_score.abs() < SCORE_MATE - 100 {
+            (best_score - window, best_score + window)
+        } else {
+            (-SCORE_INFINITY, SCORE_INFINITY)
+        };
+        let score = loop {
+            state.root_best_move = None;
+            let score = negamax(board, state, depth as i32 * ONE_PLY, 0, alpha, beta, true);
+
+            let bound = if state.is_stopped() || (alpha < score && score < beta) {
+                break score;
+            } else if score <= alpha {
+                ScoreBound::Upper
+            } else {
+                ScoreBound::Lower
+            };
+            // Show the trend now rather than a frozen score for the whole re-search. A fail
+            // high has a new best line; a fail low found nothing better than the old one.
+            trace_event!(TRACE, score, ?bound, alpha, beta, "aspiration window failed");
+            let line = if bound == ScoreBound::Lower { state.pv.line(0).to_vec() } else { pv.clone() };
+            report_info(board, state, progress(state, depth, score, bound, state.total_nodes(), line));
+            window *= 2;
+            match bound {
+                ScoreBound::Upper => alpha = (score - window).max(-SCORE_INFINITY),
+                _ => beta = (score + window).min(SCORE_INFINITY),
+            }
+        };
 
         if state.is_stopped() {
+            state.debug_log(&format!(
+                "depth {} interrupted after {}ms (limit {}ms)",
+                depth, state.elapsed_ms(), state.time_limit_ms
+            ));
+            partial_depth = Some(depth);
             // Interrupted — only use partial result if we have nothing from a complete iteration
             if best_move.is_none() {
                 best_move = state.root_best_move;
@@ -144,30 +971,40 @@ pub fn search(board: &Board, state: &mut SearchState, max_depth: u8) -> SearchRe
         }
 
         completed_depth = depth;
+        state.stats.iterations.push(IterationStats {
+            depth,
+            nodes: state.nodes - iteration_start_nodes,
+            time_ms: state.elapsed_ms(),
+        });
+        let score_drop = if depth > 1 { best_score - score } else { 0 };
         best_score = score;
         if let Some(mv) = state.root_best_move {
             best_move = Some(mv);
         }
-
-        let elapsed_ms = state.start_time.elapsed().as_millis().max(1) as u64;
-        let nps = total_nodes * 1000 / elapsed_ms;
-
-        // Extract PV from TT chain
-        let pv = extract_pv(board, &state.tt, depth as usize);
-        let pv_str: String = pv.iter().map(|m| m.to_string()).collect::<Vec<_>>().join(" ");
-
-        if !state.silent {
-            let score_str = format_score(best_score);
-            println!(
-                "info depth {} {} nodes {} time {} nps {} pv {}",
-                depth, score_str, total_nodes, elapsed_ms, nps, pv_str
-            );
+        pv = state.pv.line(0).to_vec();
+        trace_event!(DEBUG, score, nodes = state.total_nodes(), seldepth = state.seldepth, elapsed_ms = state.elapsed_ms(), "iteration complete");
+        report_info(board, state, progress(state, depth, best_score, ScoreBound::Exact, state.total_nodes(), pv.clone()));
+        report_refutations(board, state);
+        if state.multi_pv > 1 && let Some(mv) = best_move {
+            report_other_lines(board, state, depth, mv);
         }
 
-        // Soft time limit: don't start next iteration if >50% of time used
+        // Soft time limit: don't start next iteration if >50% (by default) of time used, or up
+        // to the hard limit while panicking over a score that just dropped
         if state.time_limit_ms > 0 {
-            let elapsed = state.start_time.elapsed().as_millis() as u64;
-            if elapsed > state.time_limit_ms / 2 {
+            let elapsed = state.elapsed_ms();
+            let soft_limit = state.soft_limit_after_drop_ms(score_drop).max(state.min_time_ms);
+            if soft_limit > state.soft_time_limit_ms() && elapsed > state.soft_time_limit_ms() {
+                state.debug_log(&format!(
+                    "panic: score dropped {}cp at depth {}, soft limit extended to {}ms",
+                    score_drop, depth, soft_limit
+                ));
+            }
+            if elapsed > soft_limit {
+                state.debug_log(&format!(
+                    "not starting depth {}: {}ms used, soft limit {}ms",
+                    depth + 1, elapsed, soft_limit
+                ));
                 break;
             }
         }
@@ -177,78 +1014,400 @@ pub fn search(board: &Board, state: &mut SearchState, max_depth: u8) -> SearchRe
             break;
         }
     }
+    state.finish_helpers();
 
+    // Stopped before the first root move was searched: any legal move beats `bestmove 0000`
+    if best_move.is_none() {
+        best_move = state.search_moves.first().copied().or_else(|| MoveGen::new_legal(board).next());
+    }
+
+    if handicapped && completed_depth > 0 && let Some(best) = best_move {
+        let (mv, score) = pick_skill_move(board, state, completed_depth, (best, best_score));
+        best_move = Some(mv);
+        best_score = score;
+    } else if completed_depth > 0
+        && !limits.infinite
+        && limits.mate.is_none()
+        && game_ply(board) < state.variety_plies as u32
+        && let Some(best) = best_move
+    {
+        let (mv, score) = pick_variety_move(board, state, completed_depth, (best, best_score));
+        best_move = Some(mv);
+        best_score = score;
+    }
+    // The PV starts with the move played: Skill Level's or OpeningVariety's pick, or the
+    // fallback, brings its own
+    if pv.first() != best_move.as_ref() {
+        pv = best_move.into_iter().collect();
+    }
+
+    // A forced mate, a depth limit or a single legal move can end the search in no time
+    state.wait_min_time();
+    let time_ms = state.start_time.elapsed_ms().max(1);
+    state.stats.nodes = state.nodes;
     SearchResult {
         best_move,
         score: best_score,
         depth: completed_depth,
-        nodes: total_nodes,
+        partial_depth,
+        seldepth: state.seldepth,
+        nodes: state.total_nodes(),
+        time_ms,
+        nps: time::nps(state.total_nodes(), state.start_time.elapsed_us()),
+        hashfull: state.tables.tt.hashfull(),
+        tb_hits: state.stats.tb_hits,
+        pv,
+        stats: Box::new(state.stats.clone()),
+    }
+}
+
+/// A result for a move chosen without searching
+fn unsearched_result(state: &SearchState, best_move: Option<ChessMove>) -> SearchResult {
+    SearchResult {
+        best_move,
+        score: 0,
+        depth: 0,
+        partial_depth: None,
+        seldepth: 0,
+        nodes: 0,
+        time_ms: state.start_time.elapsed_ms().max(1),
+        nps: 0,
+        hashfull: state.tables.tt.hashfull(),
+        tb_hits: 0,
+        pv: best_move.into_iter().collect(),
+        stats: Box::default(),
+    }
+}
+
+/// A search that panicked, caught by `search_guarded`
+#[derive(Debug)]
+pub struct SearchPanic {
+    /// The panic's message
+    pub message: String,
+    /// A legal move to play instead (none without one), found without a search
+    pub fallback: SearchResult,
+}
+
+/// `search`, with a panic caught instead of taking the caller down with it, so a GUI still gets
+/// a `bestmove`. The state is recovered for the next search (see `SearchState::recover`).
+pub fn search_guarded(board: &Board, state: &mut SearchState, limits: &SearchLimits) -> Result<SearchResult, SearchPanic> {
+    panic::catch_unwind(AssertUnwindSafe(|| search(board, state, limits))).map_err(|payload| {
+        let message = payload
+            .downcast_ref::<&str>()
+            .map(|m| m.to_string())
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "unknown panic".to_string());
+        trace_event!(ERROR, message = %message, "search panicked");
+        state.recover();
+        let fallback = limits.searchmoves.iter().map(|&mv| board.normalize_move(mv)).find(|&mv| board.legal(mv));
+        SearchPanic { message, fallback: unsearched_result(state, fallback.or_else(|| MoveGen::new_legal(board).next())) }
+    })
+}
+
+/// A search update timed now
+pub(crate) fn progress(state: &SearchState, depth: u8, score: Score, bound: ScoreBound, nodes: u64, pv: Vec<ChessMove>) -> SearchInfo {
+    let time_ms = state.start_time.elapsed_ms().max(1);
+    SearchInfo { depth, multipv: 1, score, bound, nodes, time_ms, nps: time::nps(nodes, state.start_time.elapsed_us()), pv }
+}
+
+/// Pass a search update to the info sink, or print it as a UCI info line
+pub(crate) fn report_info(board: &Board, state: &mut SearchState, info: SearchInfo) {
+    if let Some(sink) = state.info_sink.as_mut() {
+        sink(&info);
+    } else if !state.silent && state.json_output {
+        send!("{}", json::info_json(board, &info, state.show_wdl.then(|| wdl::wdl(info.score, board))));
+    } else if !state.silent {
+        let SearchInfo { depth, multipv, score, bound, nodes, time_ms: elapsed_ms, nps, pv } = info;
+        let mut score_str = format_score(score);
+        match bound {
+            ScoreBound::Exact => {}
+            ScoreBound::Lower => score_str.push_str(" lowerbound"),
+            ScoreBound::Upper => score_str.push_str(" upperbound"),
+        }
+        if state.show_wdl {
+            score_str = format!("{} {}", score_str, wdl::wdl(score, board));
+        }
+        let multipv = if state.multi_pv > 1 { format!(" multipv {}", multipv) } else { String::new() };
+        send!(
+            "info depth {}{} {} nodes {} time {} nps {} pv {}",
+            depth, multipv, score_str, nodes, elapsed_ms, nps, pv_to_uci(board, &pv)
+        );
+    }
+}
+
+/// Print the refuted root moves of the iteration just completed as `info refutation` lines
+fn report_refutations(board: &Board, state: &mut SearchState) {
+    let refutations = std::mem::take(&mut state.refutations);
+    if state.silent || state.info_sink.is_some() || state.json_output {
+        return;
+    }
+    for line in refutations {
+        send!("info refutation {}", pv_to_uci(board, &line));
+    }
+}
+
+/// MultiPV: report lines 2 and on of a completed iteration, each the best line left once the
+/// root moves of the lines before it are excluded. Stops early when out of moves or time.
+fn report_other_lines(board: &Board, state: &mut SearchState, depth: u8, best: ChessMove) {
+    state.excluded_root_moves = vec![best];
+    for multipv in 2..=state.multi_pv {
+        state.root_best_move = None;
+        let score = negamax(board, state, depth as i32 * ONE_PLY, 0, -SCORE_INFINITY, SCORE_INFINITY, true);
+        let Some(mv) = state.root_best_move.filter(|_| !state.is_stopped()) else { break };
+        let line = state.pv.line(0).to_vec();
+        report_info(board, state, SearchInfo { multipv, ..progress(state, depth, score, ScoreBound::Exact, state.total_nodes(), line) });
+        state.excluded_root_moves.push(mv);
+    }
+    state.excluded_root_moves.clear();
+}
+
+/// Skill Level: find the next few best root moves by re-searching with the better ones
+/// excluded, then pick one with Stockfish's formula. Each candidate gets a random push scaled by
+/// the level's weakness plus a share of how far it trails the best, so low levels often play the
+/// second or third choice while a clearly losing move still rarely wins the draw.
+/// Returns the chosen move and its score.
+fn pick_skill_move(board: &Board, state: &mut SearchState, depth: u8, best: (ChessMove, Score)) -> (ChessMove, Score) {
+    let mut candidates = vec![best];
+    while candidates.len() < SKILL_CANDIDATES {
+        state.excluded_root_moves = candidates.iter().map(|&(mv, _)| mv).collect();
+        state.root_best_move = None;
+        let score = negamax(board, state, depth as i32 * ONE_PLY, 0, -SCORE_INFINITY, SCORE_INFINITY, true);
+        match state.root_best_move {
+            Some(mv) if !state.is_stopped() => candidates.push((mv, score)),
+            _ => break,
+        }
+    }
+    state.excluded_root_moves.clear();
+
+    let weakness = 120 - 2 * state.skill_level as Score;
+    let top = best.1;
+    let delta = (top - candidates.last().map_or(top, |c| c.1)).min(100);
+    let mut choice = best;
+    let mut max_score = -SCORE_INFINITY;
+    for &(mv, score) in &candidates {
+        let noise = (state.next_random() % weakness as u64) as Score;
+        let push = (weakness * (top - score) + delta * noise) / 128;
+        if score + push >= max_score {
+            max_score = score + push;
+            choice = (mv, score);
+        }
+    }
+    (choice.0, choice.1)
+}
+
+/// OpeningVariety: a uniformly random pick among the root moves that score within
+/// `variety_margin` of the best, found by re-searching with the better ones excluded.
+/// Returns the chosen move and its score.
+fn pick_variety_move(board: &Board, state: &mut SearchState, depth: u8, best: (ChessMove, Score)) -> (ChessMove, Score) {
+    let mut candidates = vec![best];
+    // Mates aren't played for variety
+    while best.1.abs() < SCORE_MATE - 100 && candidates.len() < SKILL_CANDIDATES {
+        state.excluded_root_moves = candidates.iter().map(|&(mv, _)| mv).collect();
+        state.root_best_move = None;
+        let floor = best.1 - state.variety_margin;
+        // Only whether the next move reaches the margin matters, so the window starts just below it
+        let score = negamax(board, state, depth as i32 * ONE_PLY, 0, floor - 1, SCORE_INFINITY, true);
+        match state.root_best_move {
+            Some(mv) if !state.is_stopped() && score >= floor => candidates.push((mv, score)),
+            _ => break,
+        }
     }
+    state.excluded_root_moves.clear();
+    candidates[(state.next_random() % candidates.len() as u64) as usize]
+}
+
+/// What a helper thread takes from the main search state for one search (see
+/// `SearchState::start_helpers`)
+#[derive(Clone)]
+struct HelperSearch {
+    board: Board,
+    max_depth: u8,
+    tt: Arc<TranspositionTable>,
+    eval: EvalSettings,
+    params: SearchParams,
+    draw_contempt: Score,
+    position_history: Vec<u64>,
+    search_moves: Vec<ChessMove>,
+    syzygy: Option<SharedProber>,
+    nodes: Arc<AtomicU64>,
+}
+
+/// A helper's search: the main thread's iterations without the aspiration windows and
+/// reporting, until `stop` is set. Odd helpers start a ply deeper, so the threads spread over
+/// two depths. The shared TT is given back when done.
+fn help(state: &mut SearchState, search: HelperSearch, stop: Arc<AtomicBool>) {
+    let own_tt = std::mem::replace(&mut state.tables.tt, search.tt);
+    state.tables.eval_cache.follow(&search.eval);
+    state.params = search.params;
+    state.draw_contempt = search.draw_contempt;
+    state.position_history = search.position_history;
+    state.search_moves = search.search_moves;
+    state.syzygy = search.syzygy;
+    state.helper_nodes = search.nodes;
+    state.stop = stop;
+    state.nodes = 0;
+    state.seldepth = 0;
+    state.killers = [[None; 2]; MAX_PLY];
+    state.tables.history.iter_mut().flatten().for_each(|h| *h /= 2);
+    state.stats = SearchStats::default();
+
+    for depth in (1 + (state.thread_index % 2) as u8)..=search.max_depth {
+        negamax(&search.board, state, depth as i32 * ONE_PLY, 0, -SCORE_INFINITY, SCORE_INFINITY, true);
+        if state.is_stopped() {
+            break;
+        }
+    }
+    // The nodes since the last batch of 2048
+    state.helper_nodes.fetch_add(state.nodes & 2047, Ordering::Relaxed);
+    state.tables.tt = own_tt;
+}
+
+/// Whether `mv` pushes a pawn to its 6th or 7th rank with no enemy pawn ahead of it on its own
+/// or a neighbouring file
+fn is_passed_pawn_push(board: &Board, mv: ChessMove) -> bool {
+    let to = mv.get_dest();
+    let us = board.side_to_move();
+    let rank = match us {
+        Color::White => to.rank().to_index(),
+        Color::Black => 7 - to.rank().to_index(),
+    };
+    if board.piece_on(mv.get_source()) != Some(Piece::Pawn) || !(5..=6).contains(&rank) {
+        return false;
+    }
+    let their_pawns = board.pieces(Piece::Pawn) & board.color_combined(!us);
+    !their_pawns.iter().any(|sq| {
+        let ahead = match us {
+            Color::White => sq.rank().to_index() > to.rank().to_index(),
+            Color::Black => sq.rank().to_index() < to.rank().to_index(),
+        };
+        ahead && sq.file_distance(to) <= 1
+    })
+}
+
+/// Plies played since the start of the game, from the FEN move counters
+fn game_ply(board: &Board) -> u32 {
+    (board.fullmove_number() as u32).saturating_sub(1) * 2 + (board.side_to_move() == Color::Black) as u32
 }
 
-/// Negamax with alpha-beta pruning, TT, NMP, and LMR.
+/// Negamax with alpha-beta pruning, TT, NMP, and LMR. `depth` is in quarter plies (`ONE_PLY`).
 fn negamax(
     board: &Board,
     state: &mut SearchState,
-    depth: u8,
+    depth: i32,
     ply: usize,
     mut alpha: Score,
     beta: Score,
     can_null: bool,
 ) -> Score {
-    // Time check every 2048 nodes
-    state.nodes += 1;
-    if state.nodes & 2047 == 0 {
-        state.check_time();
-    }
+    state.count_node();
+    state.seldepth = state.seldepth.max(ply as u8);
+    // Whatever this node returns, it hasn't found a line yet
+    state.pv.clear(ply);
     if state.is_stopped() {
         return 0;
     }
 
-    // Terminal node checks
-    match board.status() {
-        BoardStatus::Checkmate => return -SCORE_MATE + ply as Score,
-        BoardStatus::Stalemate => return 0,
-        _ => {}
-    }
-
     // Draw detection: repetition
     let hash = board.get_hash();
-    if ply > 0 && state.position_history.contains(&hash) {
-        return 0;
+    if ply <= MAX_PLY {
+        state.line_hashes[ply] = hash;
+        if ply > 0 && state.is_repetition(board, ply) {
+            return state.draw_score(ply);
+        }
+    }
+
+    // Leaf node: switch to quiescence search (which finds mates, as it searches every
+    // evasion when in check)
+    if depth <= 0 {
+        return quiescence_node(board, state, ply, alpha, beta);
     }
 
-    // Leaf node: switch to quiescence search
-    if depth == 0 {
-        return quiescence(board, state, ply, alpha, beta);
+    // PV nodes (an open window) must produce their line, so they take no shortcuts
+    let pv_node = beta - alpha > 1;
+    if pv_node {
+        state.stats.pv_nodes += 1;
     }
 
     // TT probe
     let mut hash_move: Option<ChessMove> = None;
 
-    if let Some(entry) = state.tt.probe(hash, ply) {
-        hash_move = entry.best_move;
-        if entry.depth >= depth
-            && let Some(score) = TranspositionTable::retrieve_score(entry, ply, alpha, beta)
+    state.stats.tt_probes += 1;
+    let tt_hash = state.tables.tt_hash(board);
+    if let Some(entry) = state.tables.tt.probe(tt_hash, ply) {
+        state.stats.tt_hits += 1;
+        hash_move = entry.best_move();
+        // Never cut at the root (the search needs a root move, not just a score) or at other
+        // PV nodes, where a stored score would end the PV early or disagree with it
+        if ply > 0
+            && !pv_node
+            && entry.depth() as i32 >= depth
+            && let Some(score) = TranspositionTable::retrieve_score(&entry, ply, alpha, beta)
         {
             return score;
         }
     }
 
+    // Moves are generated once, and double as the checkmate/stalemate test: before the
+    // tablebases (which would call a mate a plain loss) and null move pruning (which would
+    // pass in stalemate)
+    let in_check = board.checkers().0 != 0;
+    let moves = order_moves(board, hash_move, &state.killers[ply], &state.tables.history, ply);
+    if moves.is_empty() {
+        return if in_check { -SCORE_MATE + ply as Score } else { state.draw_score(ply) };
+    }
+
     // Syzygy tablebase probe (only at non-root with <= 5 pieces)
     if ply > 0
-        && let Some(ref syzygy) = state.syzygy
-        && let Some(score) = syzygy.probe_wdl(board)
+        && let Some(syzygy) = state.syzygy.as_ref().and_then(SharedProber::get)
+        && board.combined().popcnt() <= syzygy::MAX_PIECES
     {
-        return score;
+        state.stats.tb_probes += 1;
+        if let Some(score) = syzygy.probe_wdl(board) {
+            state.stats.tb_hits += 1;
+            return score;
+        }
     }
 
-    let in_check = board.checkers().0 != 0;
+    // Static eval for the pruning decisions below, through the eval cache: a transposition
+    // reaches the same position without evaluating it again
+    let static_eval = if in_check { -SCORE_INFINITY } else { state.tables.eval_cache.evaluate(board) };
+    let plies = (depth + ONE_PLY - 1) / ONE_PLY;
+    let prunable = ply > 0 && !pv_node && !in_check && state.params.futility;
+    let mate_window = beta.abs() >= SCORE_MATE - MAX_PLY as Score;
+
+    // Reverse futility pruning: near the horizon, a static eval that beats beta by more than
+    // the remaining depth could lose is taken as a cutoff
+    if prunable
+        && !mate_window
+        && plies <= tune::FUTILITY_DEPTH.get()
+        && static_eval - tune::FUTILITY_MARGIN.get() * plies >= beta
+    {
+        state.stats.futility_prunes += 1;
+        return static_eval;
+    }
+
+    // Razoring: a static eval far below alpha drops straight into quiescence, which only has
+    // to confirm that no capture saves the node
+    if prunable && plies <= tune::RAZOR_DEPTH.get() && static_eval + tune::RAZOR_MARGIN.get() * plies < alpha {
+        let score = quiescence_node(board, state, ply, alpha, beta);
+        if score < alpha {
+            state.stats.razor_cuts += 1;
+            return score;
+        }
+    }
 
     // Null move pruning:
     // "If I skip my turn and still beat beta, my real position must be even better."
-    // Conditions: not in check, depth >= 3, not consecutive null moves, has non-pawn material
-    if can_null && !in_check && depth >= 3 && ply > 0 {
+    // Conditions: not a PV node, not in check, depth >= 3, a static eval at beta or above,
+    // not consecutive null moves, has non-pawn material
+    if can_null
+        && state.params.null_move
+        && !pv_node
+        && !in_check
+        && depth >= tune::NMP_MIN_DEPTH.get() * ONE_PLY
+        && ply > 0
+        && static_eval >= beta
+    {
         // Skip NMP in zugzwang-prone positions (side has only pawns + king)
         let our_pieces = board.color_combined(board.side_to_move());
         let pawns_and_king = board.pieces(Piece::Pawn) | board.pieces(Piece::King);
@@ -257,68 +1416,159 @@ fn negamax(
         if has_non_pawn_material
             && let Some(null_board) = board.null_move()
         {
-            state.position_history.push(hash);
-            let score = -negamax(&null_board, state, depth - 3, ply + 1, -beta, -beta + 1, false);
-            state.position_history.pop();
+            state.stats.null_tries += 1;
+            if ply < MAX_PLY {
+                state.line_captures[ply + 1] = None;
+                state.line_extensions[ply + 1] = state.line_extensions[ply];
+            }
+            let null_depth = depth - tune::NMP_REDUCTION.get() * ONE_PLY;
+            let score = -negamax(&null_board, state, null_depth, ply + 1, -beta, -beta + 1, false);
 
             if state.is_stopped() {
                 return 0;
             }
             if score >= beta {
+                state.stats.null_cutoffs += 1;
                 return beta;
             }
         }
     }
 
-    let moves = order_moves(board, hash_move, &state.killers[ply], &state.history, ply);
-
-    if moves.is_empty() {
-        return 0;
-    }
-
-    // Push current position for repetition detection in child nodes
-    state.position_history.push(hash);
-
     let mut best_score = -SCORE_INFINITY;
     let mut best_move: Option<ChessMove> = None;
     let original_alpha = alpha;
 
+    let excluding = ply == 0 && !(state.excluded_root_moves.is_empty() && state.search_moves.is_empty());
+    let endgame = game_phase(board) <= PASSED_PAWN_MAX_PHASE;
+    if ply == 0 {
+        state.line_extensions[0] = 0;
+        state.extension_budget = depth;
+    }
+    let collect_refutations = ply == 0 && (state.show_refutations || state.debug);
+    if collect_refutations {
+        state.refutations.clear();
+    }
+    let budget = if state.params.extensions { state.extension_budget - state.line_extensions[ply] } else { 0 };
+    // Moves searched so far, the first of which gets the full window
+    let mut searched = 0;
+
     for (move_num, scored_move) in moves.iter().enumerate() {
+        if excluding && state.skips_root_move(scored_move.mv) {
+            continue;
+        }
         let new_board = board.make_move_new(scored_move.mv);
-        let is_capture = board.piece_on(scored_move.mv.get_dest()).is_some()
-            || board.en_passant() == Some(scored_move.mv.get_dest());
+        let is_capture = board.is_capture(scored_move.mv);
         let gives_check = new_board.checkers().0 != 0;
 
-        let score;
+        // SEE pruning: near the horizon, skip quiet moves that put a piece where it can be won
+        // and captures that lose more than the remaining depth could make up. Never the first
+        // move, so the node always has a score.
+        if state.params.see_pruning
+            && ply > 0
+            && !pv_node
+            && !in_check
+            && !gives_check
+            && best_score > -SCORE_MATE + MAX_PLY as Score
+            && depth <= tune::SEE_PRUNE_DEPTH.get() * ONE_PLY
+        {
+            let threshold = if is_capture {
+                -tune::SEE_CAPTURE_MARGIN.get() * plies * plies
+            } else {
+                -tune::SEE_QUIET_MARGIN.get() * plies
+            };
+            if board.see(scored_move.mv) < threshold {
+                state.stats.see_prunes += 1;
+                continue;
+            }
+        }
+
+        // Futility pruning: a quiet move can't lift a static eval this far below alpha back up
+        // to it within the remaining depth
+        if prunable
+            && move_num > 0
+            && !is_capture
+            && !gives_check
+            && scored_move.mv.get_promotion().is_none()
+            && plies <= tune::FUTILITY_DEPTH.get()
+            && static_eval + tune::FUTILITY_MARGIN.get() * plies <= alpha
+        {
+            state.stats.futility_prunes += 1;
+            continue;
+        }
+
+        let to = scored_move.mv.get_dest();
+        // Recapture extension: an exchange on one square is played out before the horizon;
+        // passed-pawn extension: so is a promotion race. A line spends at most its budget.
+        let extension = if budget <= 0 {
+            0
+        } else if is_capture && state.line_captures[ply] == Some(to) {
+            state.stats.recapture_extensions += 1;
+            RECAPTURE_EXTENSION.min(budget)
+        } else if endgame && is_passed_pawn_push(board, scored_move.mv) {
+            state.stats.passed_pawn_extensions += 1;
+            PASSED_PAWN_EXTENSION.min(budget)
+        } else {
+            0
+        };
+        if ply < MAX_PLY {
+            state.line_captures[ply + 1] = is_capture.then_some(to);
+            state.line_extensions[ply + 1] = state.line_extensions[ply] + extension;
+        }
 
         // LMR: reduce depth for late quiet moves
         // "Moves ordered late are likely bad... search them shallowly first."
-        let do_lmr = move_num >= 3
-            && depth >= 3
+        let do_lmr = state.params.lmr
+            && move_num as i32 >= tune::LMR_MIN_MOVES.get()
+            && depth >= tune::LMR_MIN_DEPTH.get() * ONE_PLY
             && !is_capture
             && !in_check
             && !gives_check
+            && extension == 0
             && Some(scored_move.mv) != state.killers[ply][0]
             && Some(scored_move.mv) != state.killers[ply][1];
 
-        if do_lmr {
-            // Reduced depth search with null window
-            let reduced = -negamax(&new_board, state, depth - 2, ply + 1, -alpha - 1, -alpha, true);
-            if reduced > alpha {
-                // Re-search at full depth
-                score = -negamax(&new_board, state, depth - 1, ply + 1, -beta, -alpha, true);
-            } else {
-                score = reduced;
-            }
+        let full_depth = depth - ONE_PLY + extension;
+        let score = if searched == 0 {
+            -negamax(&new_board, state, full_depth, ply + 1, -beta, -alpha, true)
         } else {
-            score = -negamax(&new_board, state, depth - 1, ply + 1, -beta, -alpha, true);
-        }
+            // Without LMR the move goes straight to the null-window search below
+            let mut score = alpha + 1;
+            if do_lmr {
+                // Reduced depth search with null window
+                state.stats.lmr_searches += 1;
+                let reduced_depth = depth - ONE_PLY - tune::LMR_REDUCTION.get() * ONE_PLY;
+                score = -negamax(&new_board, state, reduced_depth, ply + 1, -alpha - 1, -alpha, true);
+                if score > alpha {
+                    state.stats.lmr_researches += 1;
+                }
+            }
+            // PVS: once the first move has set alpha, the rest only have to be shown no better
+            // than it, which a null window does cheaply. One that beats alpha inside the window
+            // is searched again with all of it for its exact score.
+            if score > alpha {
+                state.stats.pvs_searches += 1;
+                score = -negamax(&new_board, state, full_depth, ply + 1, -alpha - 1, -alpha, true);
+                if score > alpha && score < beta {
+                    state.stats.pvs_researches += 1;
+                    score = -negamax(&new_board, state, full_depth, ply + 1, -beta, -alpha, true);
+                }
+            }
+            score
+        };
+        searched += 1;
 
         if state.is_stopped() {
-            state.position_history.pop();
             return best_score;
         }
 
+        // Failing low, the move's reply raised the child's alpha, so the child's line starts
+        // with the refutation
+        if collect_refutations && score <= alpha && !state.pv.line(1).is_empty() {
+            let mut line = vec![scored_move.mv];
+            line.extend_from_slice(state.pv.line(1));
+            state.refutations.push(line);
+        }
+
         if score > best_score {
             best_score = score;
             best_move = Some(scored_move.mv);
@@ -330,10 +1580,13 @@ fn negamax(
 
         if score > alpha {
             alpha = score;
+            state.pv.update(ply, scored_move.mv);
         }
 
         // Beta cutoff
         if alpha >= beta {
+            state.stats.record_cutoff(move_num);
+
             // Update killer moves and history for quiet moves that cause cutoffs
             if !is_capture && ply < MAX_PLY {
                 // Shift killer: slot 1 = old slot 0
@@ -344,9 +1597,10 @@ fn negamax(
                 if let Some(piece) = board.piece_on(scored_move.mv.get_source()) {
                     let pi = piece_to_index(piece);
                     let to = scored_move.mv.get_dest().to_index();
-                    state.history[pi][to] += (depth as Score) * (depth as Score);
-                    if state.history[pi][to] > HISTORY_MAX {
-                        state.history[pi][to] = HISTORY_MAX;
+                    let plies = depth / ONE_PLY;
+                    state.tables.history[pi][to] += plies * plies;
+                    if state.tables.history[pi][to] > HISTORY_MAX {
+                        state.tables.history[pi][to] = HISTORY_MAX;
                     }
                 }
             }
@@ -354,7 +1608,10 @@ fn negamax(
         }
     }
 
-    state.position_history.pop();
+    // The root result with moves left out isn't the position's true value: keep it out of the TT
+    if excluding {
+        return best_score;
+    }
 
     // Store in TT
     let flag = if best_score >= beta {
@@ -364,24 +1621,44 @@ fn negamax(
     } else {
         TTFlag::Exact
     };
+    if flag == TTFlag::UpperBound {
+        state.stats.all_nodes += 1;
+    }
 
-    state.tt.store(hash, depth, best_score, flag, best_move, ply);
+    // Quarter plies fit a byte up to 63 plies; deeper entries are all as good as exact
+    state.tables.tt.store(tt_hash, depth.clamp(0, u8::MAX as i32) as u8, best_score, flag, best_move, ply);
 
     best_score
 }
 
 /// Quiescence search — explores captures (and all moves when in check).
-fn quiescence(
+pub(crate) fn quiescence(
     board: &Board,
     state: &mut SearchState,
     ply: usize,
-    mut alpha: Score,
+    alpha: Score,
     beta: Score,
 ) -> Score {
-    state.nodes += 1;
+    state.count_node();
+    if state.is_stopped() {
+        return 0;
+    }
+    quiescence_node(board, state, ply, alpha, beta)
+}
 
+/// Quiescence search of a node that has already been counted: a depth-0 `negamax` node hands
+/// over here, so it isn't counted twice
+fn quiescence_node(
+    board: &Board,
+    state: &mut SearchState,
+    ply: usize,
+    mut alpha: Score,
+    beta: Score,
+) -> Score {
+    state.stats.qnodes += 1;
+    state.seldepth = state.seldepth.max(ply as u8);
     if ply >= MAX_PLY {
-        return evaluate(board);
+        return state.tables.eval_cache.evaluate(board);
     }
 
     let in_check = board.checkers().0 != 0;
@@ -390,7 +1667,7 @@ fn quiescence(
         // In check: must search ALL legal moves — standing pat is illegal
         let mut best_score: Score = -SCORE_INFINITY;
         let killers = state.killers[ply];
-        let moves = order_moves(board, None, &killers, &state.history, ply);
+        let moves = order_moves(board, None, &killers, &state.tables.history, ply);
 
         if moves.is_empty() {
             // In check with no legal moves = checkmate
@@ -420,7 +1697,7 @@ fn quiescence(
     }
 
     // Not in check: normal quiescence with stand-pat
-    let stand_pat = evaluate(board);
+    let stand_pat = state.tables.eval_cache.evaluate_lazy(board, alpha, beta);
     let mut best_score = stand_pat;
 
     if stand_pat >= beta {
@@ -470,12 +1747,14 @@ fn piece_to_index(piece: Piece) -> usize {
 mod tests {
     use super::*;
     use std::str::FromStr;
+    use crate::evaluation::Style;
+    use crate::types::TIMED_MAX_DEPTH;
 
     #[test]
     fn test_search_finds_move() {
         let board = Board::default();
         let mut state = SearchState::new();
-        let result = search(&board, &mut state, 3);
+        let result = search(&board, &mut state, &SearchLimits::depth(3));
         assert!(result.best_move.is_some());
     }
 
@@ -484,7 +1763,7 @@ mod tests {
         let board = Board::from_str("r1bqkb1r/pppp1ppp/2n2n2/4p2Q/2B1P3/8/PPPP1PPP/RNB1K1NR w KQkq - 4 4")
             .unwrap();
         let mut state = SearchState::new();
-        let result = search(&board, &mut state, 2);
+        let result = search(&board, &mut state, &SearchLimits::depth(2));
         let best = result.best_move.unwrap();
         assert_eq!(best.to_string(), "h5f7", "Expected Qxf7# but got {}", best);
         assert!(result.score > SCORE_MATE - 100, "Mate score should be near SCORE_MATE, got {}", result.score);
@@ -494,7 +1773,7 @@ mod tests {
     fn test_search_avoids_giving_material() {
         let board = Board::default();
         let mut state = SearchState::new();
-        let result = search(&board, &mut state, 4);
+        let result = search(&board, &mut state, &SearchLimits::depth(4));
         assert!(result.best_move.is_some());
         assert!(result.nodes > 0);
         assert_eq!(result.depth, 4, "Should complete all requested depths from startpos");
@@ -506,10 +1785,20 @@ mod tests {
         let board = Board::from_str("rnbqkbnr/pppp1ppp/4p3/8/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3")
             .unwrap();
         let mut state = SearchState::new();
-        let score = negamax(&board, &mut state, 1, 0, -SCORE_INFINITY, SCORE_INFINITY, true);
+        let score = negamax(&board, &mut state, ONE_PLY, 0, -SCORE_INFINITY, SCORE_INFINITY, true);
         assert!(score < -SCORE_MATE + 200, "Checkmate score should be very negative, got {}", score);
     }
 
+    #[test]
+    fn test_stalemate_found_before_pruning() {
+        // Black's pinned bishop can't move and neither can the king: no null move may stand in
+        // for a move here, however low beta is
+        let board = Board::from_str("kb5R/8/1PK5/8/8/8/8/8 b - - 0 1").unwrap();
+        let mut state = SearchState::new();
+        let score = negamax(&board, &mut state, 4 * ONE_PLY, 1, -SCORE_INFINITY, -SCORE_MATE + 1000, true);
+        assert_eq!(score, 0);
+    }
+
     #[test]
     fn test_tt_reduces_nodes() {
         // Search same position twice. second search should be faster due to TT
@@ -517,12 +1806,12 @@ mod tests {
         let mut state = SearchState::new();
 
         // First search
-        search(&board, &mut state, 4);
+        search(&board, &mut state, &SearchLimits::depth(4));
         let nodes_first = state.nodes;
 
         // Second search (TT populated)
         state.reset();
-        search(&board, &mut state, 4);
+        search(&board, &mut state, &SearchLimits::depth(4));
         let nodes_second = state.nodes;
 
         // Second search should use fewer nodes (TT hits)
@@ -532,15 +1821,135 @@ mod tests {
     }
 
     #[test]
-    fn test_draw_detection_repetition() {
-        // Set up a position and add same hash to position_history
+    fn test_lazy_smp() {
         let board = Board::default();
         let mut state = SearchState::new();
-        // Simulate a repetition by adding current hash
+        state.threads = 3;
+        state.reset();
+        let result = search(&board, &mut state, &SearchLimits::depth(6));
+        assert_eq!(result.depth, 6);
+        // Every helper searched at least its root, and its nodes are counted in
+        assert!(result.nodes >= state.nodes + 2, "{} nodes, {} on the main thread", result.nodes, state.nodes);
+        // The helpers have given the TT back and wait for the next search
+        assert_eq!(Arc::strong_count(&state.tables.tt), 1);
+        assert!(state.helpers.as_ref().is_some_and(|pool| pool.fits(2)));
+        state.resize_tt(2);
+
+        // Node limits stay exact on one thread
+        state.reset();
+        let result = search(&board, &mut state, &SearchLimits { nodes: Some(5000), ..Default::default() });
+        assert_eq!((result.nodes, state.nodes), (5000, 5000));
+
+        // A search that panics stops its helpers, and the next one starts them again
+        state.reset();
+        state.info_sink = Some(Box::new(|_: &SearchInfo| panic!("sink failed")));
+        assert!(search_guarded(&board, &mut state, &SearchLimits::depth(3)).is_err());
+        assert_eq!(Arc::strong_count(&state.tables.tt), 1);
+        state.info_sink = None;
+        state.reset();
+        assert_eq!(search_guarded(&board, &mut state, &SearchLimits::depth(4)).unwrap().depth, 4);
+    }
+
+    #[test]
+    fn test_draw_detection_repetition() {
+        // Both knights out and back: the position 4 plies into the line is the root's
+        let mut board = Board::default();
+        let mut state = SearchState::new();
         state.position_history.push(board.get_hash());
+        for (ply, uci) in ["g1f3", "g8f6", "f3g1", "f6g8"].iter().enumerate() {
+            state.line_hashes[ply] = board.get_hash();
+            board = board.make_move_new(ChessMove::from_uci(uci).unwrap());
+        }
         // At ply > 0, negamax should detect repetition and return 0
-        let score = negamax(&board, &mut state, 3, 1, -SCORE_INFINITY, SCORE_INFINITY, true);
+        let score = negamax(&board, &mut state, 3 * ONE_PLY, 4, -SCORE_INFINITY, SCORE_INFINITY, true);
         assert_eq!(score, 0, "Repeated position should return 0 (draw), got {}", score);
+
+        // With contempt the draw is worth less to the root's side, and more to the other
+        state.draw_contempt = 30;
+        assert_eq!(negamax(&board, &mut state, 3 * ONE_PLY, 4, -SCORE_INFINITY, SCORE_INFINITY, true), -30);
+        assert_eq!(state.draw_score(3), 30);
+    }
+
+    #[test]
+    fn test_dynamic_contempt() {
+        let mut state = SearchState::new();
+        let start = Board::default();
+        let pawn_ending = Board::from_str("4k3/4p3/8/8/8/8/4P3/4K3 w - - 0 1").unwrap();
+        let limits = SearchLimits::default();
+        assert_eq!(state.search_contempt(&start, &limits), 0);
+
+        state.contempt = 24;
+        assert_eq!(state.search_contempt(&start, &limits), 24);
+        // Gone in a pawn ending, so a won one isn't thrown away dodging repetitions
+        assert_eq!(state.search_contempt(&pawn_ending, &limits), 0);
+        // More against a weaker opponent, and when ahead on the clock
+        state.rating_adv = 200;
+        assert_eq!(state.search_contempt(&start, &limits), 34);
+        let clocks = SearchLimits { wtime: Some(60_000), btime: Some(30_000), ..Default::default() };
+        assert_eq!(state.search_contempt(&start, &clocks), 39);
+        assert_eq!(state.search_contempt(&start.null_move().unwrap(), &clocks), 29);
+
+        state.dynamic_contempt = false;
+        assert_eq!(state.search_contempt(&pawn_ending, &clocks), 24);
+        // Style shifts it before anything else
+        state.tables.eval_cache.set_style(Style::Aggressive);
+        assert_eq!(state.search_contempt(&pawn_ending, &clocks), 44);
+        state.dynamic_contempt = true;
+        assert_eq!(state.search_contempt(&start, &limits), 54);
+    }
+
+    #[test]
+    fn test_style_keys_tt_entries() {
+        let board = Board::default();
+        let mut state = SearchState::new();
+        state.silent = true;
+        search(&board, &mut state, &SearchLimits::depth(3));
+        let plain = state.tables.tt_hash(&board);
+        assert_eq!(plain, board.get_hash());
+
+        // A style keys the scores it shifted apart from the plain ones, and each side's apart
+        state.tables.eval_cache.set_style(Style::Aggressive);
+        assert!(state.tables.tt.probe(state.tables.tt_hash(&board), 0).is_none());
+        search(&board, &mut state, &SearchLimits::depth(3));
+        let white = state.tables.tt_hash(&board);
+        state.tables.eval_cache.set_style_side(Color::Black);
+        let black = state.tables.tt_hash(&board);
+        assert!(plain != white && white != black && black != plain);
+        assert!(state.tables.tt.probe(black, 0).is_none());
+        // Nothing is cleared: the other entries are still there when their style is back
+        assert!(state.tables.tt.probe(white, 0).is_some() && state.tables.tt.probe(plain, 0).is_some());
+    }
+
+    #[test]
+    fn test_repetition_within_halfmove_clock() {
+        // The halfmove clock is 0, so nothing before this position can match it
+        let board = Board::default();
+        let hash = board.get_hash();
+        let mut state = SearchState::new();
+        state.position_history = vec![hash, 1, 2, 3];
+        state.line_hashes[..2].copy_from_slice(&[3, hash]);
+        assert!(!state.is_repetition(&board, 1));
+
+        // Found in the game before the root and in the line itself
+        let board = Board::from_str("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 6 4").unwrap();
+        let hash = board.get_hash();
+        state.position_history = vec![hash, 1, 2, 3, 4];
+        state.line_hashes[..3].copy_from_slice(&[4, 5, hash]);
+        assert!(state.is_repetition(&board, 2));
+        state.position_history = vec![1, 2, 3, 4];
+        state.line_hashes[..6].copy_from_slice(&[4, 5, 6, 7, 8, hash]);
+        assert!(!state.is_repetition(&board, 5));
+        state.line_hashes[1] = hash;
+        assert!(state.is_repetition(&board, 5));
+        // Positions with the other side to move never count
+        state.line_hashes[1] = 5;
+        state.line_hashes[2] = hash;
+        assert!(!state.is_repetition(&board, 5));
+
+        // A search keeps only the window that can still repeat, root included
+        state.position_history = (1..=20).collect();
+        search(&board, &mut state, &SearchLimits::depth(1));
+        assert_eq!(state.position_history, [15, 16, 17, 18, 19, 20, hash]);
     }
 
     #[test]
@@ -554,25 +1963,552 @@ mod tests {
         assert!(score < -SCORE_MATE + 200, "Checkmate in qsearch should return mate score, got {}", score);
     }
 
+    #[test]
+    fn test_quiescence_node_counting() {
+        // A quiet leaf is one node, whether negamax or quiescence is asked
+        let quiet = Board::from_str("4k3/p7/8/8/8/8/7P/4K3 w - - 0 1").unwrap();
+        let mut state = SearchState::new();
+        negamax(&quiet, &mut state, 0, 1, -SCORE_INFINITY, SCORE_INFINITY, true);
+        assert_eq!(state.nodes, 1);
+        quiescence(&quiet, &mut state, 1, -SCORE_INFINITY, SCORE_INFINITY);
+        assert_eq!(state.nodes, 2);
+
+        // Limits are checked at every quiescence node, not just in negamax
+        let busy = Board::from_str("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1").unwrap();
+        let mut state = SearchState::new();
+        state.node_limit = 10;
+        quiescence(&busy, &mut state, 0, -SCORE_INFINITY, SCORE_INFINITY);
+        assert_eq!(state.nodes, 10);
+        assert!(state.is_stopped());
+    }
+
     #[test]
     fn test_stop_preserves_best_move() {
         // Search with a tight time limit so it stops during deeper iterations
         let board = Board::default();
         let mut state = SearchState::new();
-        state.time_limit_ms = 1; // 1ms — will stop almost immediately
-        state.start_time = Instant::now();
-        let result = search(&board, &mut state, 20);
+        // 1ms — will stop almost immediately
+        let result = search(&board, &mut state, &SearchLimits { depth: Some(20), movetime: Some(1), ..Default::default() });
         // Should still have found a move from depth 1 or partial search
         assert!(result.best_move.is_some(), "Should find a move even when stopped early");
+
+        // Stopped before it started, e.g. by a `position` right after `go`
+        state.reset();
+        state.stop.store(true, Ordering::SeqCst);
+        let result = search(&board, &mut state, &SearchLimits::depth(20));
+        assert!(board.legal(result.best_move.unwrap()));
+    }
+
+    #[test]
+    fn test_aspiration_bounds_precede_exact_info() {
+        let board = Board::from_str("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1").unwrap();
+        let infos = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let mut state = SearchState::new();
+        let sink = infos.clone();
+        state.info_sink = Some(Box::new(move |info: &SearchInfo| sink.lock().unwrap().push((info.depth, info.bound))));
+        search(&board, &mut state, &SearchLimits::depth(6));
+
+        // One exact line per depth; a bound line is always followed by more of the same depth
+        let infos = infos.lock().unwrap();
+        let exact: Vec<u8> = infos.iter().filter(|i| i.1 == ScoreBound::Exact).map(|i| i.0).collect();
+        assert_eq!(exact, (1..=6).collect::<Vec<u8>>());
+        for pair in infos.windows(2) {
+            if pair[0].1 != ScoreBound::Exact {
+                assert_eq!(pair[0].0, pair[1].0);
+                assert!(pair[0].0 >= ASPIRATION_MIN_DEPTH);
+            }
+        }
     }
 
     #[test]
-    fn test_pv_extraction() {
+    fn test_node_limit_is_exact() {
         let board = Board::default();
         let mut state = SearchState::new();
-        search(&board, &mut state, 4);
-        let pv = extract_pv(&board, &state.tt, 4);
-        assert!(!pv.is_empty(), "PV should contain at least one move after search");
+        state.reset();
+        let result = search(&board, &mut state, &SearchLimits { nodes: Some(5000), ..Default::default() });
+        assert_eq!(result.nodes, 5000);
+        assert!(result.best_move.is_some());
+        // The depth is the last finished iteration, not the one the limit cut short
+        assert_eq!(result.partial_depth, Some(result.depth + 1));
+        state.reset();
+        assert_eq!(search(&board, &mut state, &SearchLimits::depth(3)).partial_depth, None);
+    }
+
+    #[test]
+    fn test_deterministic_mode() {
+        let board = Board::default();
+        // A 1ms clock would stop the search at once; deterministic mode ignores it
+        let limits = SearchLimits { depth: Some(4), wtime: Some(1), btime: Some(1), ..Default::default() };
+        let run = || {
+            let mut state = SearchState::new();
+            state.set_deterministic(true);
+            state.skill_level = 10;
+            state.reset();
+            let result = search(&board, &mut state, &limits);
+            (result.depth, result.nodes, result.best_move)
+        };
+        let first = run();
+        assert_eq!(first.0, 4);
+        assert_eq!(run(), first);
+    }
+
+    #[test]
+    fn test_reused_state_matches_fresh() {
+        let board = Board::default();
+        let fresh = || {
+            let mut state = SearchState::with_tables(SearchTables::new(1));
+            state.set_deterministic(true);
+            state
+        };
+        let mut state = fresh();
+        let first = search(&board, &mut state, &SearchLimits::depth(5)).nodes;
+        assert!(state.tables.history.iter().flatten().any(|&h| h > 1));
+
+        // Between searches the history is only aged
+        let history = state.tables.history;
+        state.reset();
+        assert!(state.tables.history.iter().flatten().zip(history.iter().flatten()).all(|(&h, &old)| h == old / 2));
+
+        // A new game in deterministic mode searches exactly like a new state
+        state.new_game();
+        state.reset();
+        assert_eq!(search(&board, &mut state, &SearchLimits::depth(5)).nodes, first);
+        assert_eq!(search(&board, &mut fresh(), &SearchLimits::depth(5)).nodes, first);
+    }
+
+    #[test]
+    fn test_searchmoves_and_mate_limits() {
+        // Qxf7# is the obvious move; with it left out the search has to pick another
+        let board = Board::from_str("r1bqkb1r/pppp1ppp/2n2n2/4p2Q/2B1P3/8/PPPP1PPP/RNB1K1NR w KQkq - 4 4").unwrap();
+        let only = |moves: &[&str]| SearchLimits {
+            depth: Some(3),
+            searchmoves: moves.iter().map(|m| ChessMove::from_uci(m).unwrap()).collect(),
+            ..Default::default()
+        };
+        let mut state = SearchState::new();
+        assert_eq!(search(&board, &mut state, &only(&[])).best_move.unwrap().to_string(), "h5f7");
+        let result = search(&board, &mut state, &only(&["b1c3", "h5h4"]));
+        assert!(["b1c3", "h5h4"].contains(&result.best_move.unwrap().to_string().as_str()));
+        assert!(result.score < SCORE_MATE - 100);
+        // Moves that aren't legal here are dropped, and with none left every move counts
+        assert_eq!(search(&board, &mut state, &only(&["e2e5"])).best_move.unwrap().to_string(), "h5f7");
+
+        // `mate 1` needs a single ply, and every limit but depth leaves the depth open
+        let mate = SearchLimits { mate: Some(1), ..Default::default() };
+        assert_eq!(mate.default_depth(), 1);
+        let result = search(&board, &mut state, &mate);
+        assert_eq!((result.depth, result.score), (1, SCORE_MATE - 1));
+        assert_eq!(SearchLimits { wtime: Some(1000), ..Default::default() }.default_depth(), TIMED_MAX_DEPTH);
+        assert_eq!(SearchLimits::default().default_depth(), DEFAULT_DEPTH);
+    }
+
+    #[test]
+    fn test_panic_time() {
+        let mut state = SearchState::new();
+        state.time_limit_ms = 1000;
+        let soft = state.soft_time_limit_ms();
+        assert_eq!(state.soft_limit_after_drop_ms(0), soft);
+        assert_eq!(state.soft_limit_after_drop_ms(-200), soft, "a rising score needs no extra time");
+        let panic = state.soft_limit_after_drop_ms(200);
+        assert!(panic > soft && panic <= state.time_limit_ms);
+    }
+
+    #[test]
+    fn test_nodes_time() {
+        let board = Board::default();
+        let run = |limits: &SearchLimits| {
+            let mut state = SearchState::new();
+            state.nodes_time = 10;
+            search(&board, &mut state, limits).nodes
+        };
+        // 30s over the default 30 moves is 1s, so 10,000 nodes; the soft limit may stop sooner
+        let clock = SearchLimits { wtime: Some(30_000), btime: Some(30_000), ..Default::default() };
+        let nodes = run(&clock);
+        assert!(nodes > 0 && nodes <= 10_000, "{} nodes", nodes);
+        // Nodes, unlike milliseconds, give the same search every time
+        assert_eq!(run(&clock), nodes);
+        // A tighter go nodes still wins
+        assert_eq!(run(&SearchLimits { nodes: Some(500), ..clock.clone() }), 500);
+
+        // movetime stays in real time
+        let mut state = SearchState::new();
+        state.nodes_time = 10;
+        state.apply_limits(&SearchLimits { movetime: Some(100), ..Default::default() }, &board);
+        assert_eq!((state.node_clock, state.node_limit), (false, 0));
+        state.apply_limits(&clock, &board);
+        assert_eq!((state.node_clock, state.node_limit), (true, 10_000));
+    }
+
+    #[test]
+    fn test_move_report() {
+        let board = Board::from_str("rnbqkbnr/ppp1pppp/8/3p4/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 2").unwrap();
+        let mut state = SearchState::new();
+        let lines = state.move_report(&board);
+        assert_eq!(lines.len(), 1 + 31);
+        assert!(lines[0].starts_with("info string moves 31 legal, static eval "), "{}", lines[0]);
+        // The only capture comes first and wins a pawn's worth of eval
+        let capture: Vec<&str> = lines[1].split_whitespace().collect();
+        assert_eq!(&capture[..6], ["info", "string", "move", "e4d5", "exd5", "order"]);
+        assert!(capture[8].parse::<Score>().unwrap() > 50, "{}", lines[1]);
+
+        // After a search, its best move leads the list
+        let best = search(&board, &mut state, &SearchLimits::depth(4)).best_move.unwrap();
+        assert!(state.move_report(&board)[1].contains(&format!(" {} ", board.move_to_uci(best))));
+    }
+
+    #[test]
+    fn test_probe_report() {
+        let board = Board::default();
+        let mut state = SearchState::new();
+        assert_eq!(state.probe_report(&board), ["info string probe tt none", "info string probe tb not loaded"]);
+
+        let result = search(&board, &mut state, &SearchLimits::depth(3));
+        let report = state.probe_report(&board);
+        let expected = format!(
+            "info string probe tt depth 3 score {} bound exact move {} age 0",
+            result.score,
+            board.move_to_uci(result.best_move.unwrap())
+        );
+        assert_eq!(report[0], expected);
+    }
+
+    #[test]
+    fn test_search_panic_is_caught() {
+        let board = Board::default();
+        let mut state = SearchState::new();
+        state.info_sink = Some(Box::new(|_: &SearchInfo| panic!("sink failed")));
+        let failure = search_guarded(&board, &mut state, &SearchLimits::depth(3)).unwrap_err();
+        assert_eq!(failure.message, "sink failed");
+        assert!(board.legal(failure.fallback.best_move.unwrap()));
+        assert_eq!(failure.fallback.pv, vec![failure.fallback.best_move.unwrap()]);
+
+        // searchmoves are honoured by the fallback too, and the state searches again
+        let only = SearchLimits { searchmoves: vec![ChessMove::from_uci("h2h3").unwrap()], ..SearchLimits::depth(3) };
+        assert_eq!(search_guarded(&board, &mut state, &only).unwrap_err().fallback.best_move.unwrap().to_string(), "h2h3");
+        state.info_sink = None;
+        let result = search_guarded(&board, &mut state, &SearchLimits::depth(3)).unwrap();
+        assert_eq!(result.depth, 3);
+    }
+
+    #[test]
+    fn test_single_reply_answers_at_once() {
+        // Black's king has one square
+        let board = Board::from_str("7k/R7/5K2/8/8/8/8/8 b - - 0 1").unwrap();
+        assert_eq!(MoveGen::new_legal(&board).len(), 1);
+        let mut state = SearchState::new();
+        let result = search(&board, &mut state, &SearchLimits { movetime: Some(10_000), ..Default::default() });
+        assert_eq!(result.best_move.unwrap().to_string(), "h8g8");
+        assert!(result.depth <= SINGLE_REPLY_DEPTH && result.time_ms < 5000);
+        assert!(result.pv.len() >= 2, "a reply to ponder on");
+        // Asked for a depth, the search goes to it
+        state.reset();
+        assert_eq!(search(&board, &mut state, &SearchLimits::depth(6)).depth, 6);
+    }
+
+    #[test]
+    fn test_minimum_thinking_time() {
+        let board = Board::from_str("r1bqkb1r/pppp1ppp/2n2n2/4p2Q/2B1P3/8/PPPP1PPP/RNB1K1NR w KQkq - 4 4").unwrap();
+        let mut state = SearchState::new();
+        state.min_think_ms = 100;
+        // The mate ends the search at once, but the answer waits
+        let result = search(&board, &mut state, &SearchLimits { movetime: Some(5000), ..Default::default() });
+        assert_eq!(result.best_move.unwrap().to_string(), "h5f7");
+        assert!(result.time_ms >= 100, "answered after {}ms", result.time_ms);
+
+        // Never more than the clock can spare, and depth-limited searches don't wait
+        state.apply_limits(&SearchLimits { wtime: Some(50), ..Default::default() }, &board);
+        assert_eq!((state.min_time_ms, state.time_limit_ms), (40, 40));
+        state.apply_limits(&SearchLimits::depth(2), &board);
+        assert_eq!((state.min_time_ms, state.time_limit_ms), (0, 0));
+    }
+
+    #[test]
+    fn test_pv_is_searched_line() {
+        let board = Board::default();
+        let pvs = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let mut state = SearchState::new();
+        let sink = pvs.clone();
+        state.info_sink = Some(Box::new(move |info: &SearchInfo| sink.lock().unwrap().push(info.pv.clone())));
+        let result = search(&board, &mut state, &SearchLimits::depth(5));
+
+        let pv = pvs.lock().unwrap().last().unwrap().clone();
+        assert_eq!(pv.first().copied(), result.best_move);
+        assert!(pv.len() >= 3, "PV should run well past the root move, got {}", pv.len());
+        // The result carries the same line and the rest of the final info line
+        assert_eq!(result.pv, pv);
+        assert!(result.seldepth >= result.depth);
+        assert!(result.time_ms > 0 && result.nps > 0);
+        assert!(result.hashfull <= 1000);
+        assert_eq!(result.tb_hits, 0);
+        let mut position = board;
+        for mv in pv {
+            assert!(position.legal(mv));
+            position = position.make_move_new(mv);
+        }
+    }
+
+    #[test]
+    fn test_pv_nodes_skip_tt_cutoffs() {
+        let board = Board::default().make_move_new(ChessMove::from_uci("e2e4").unwrap());
+        let mut state = SearchState::new();
+        state.tables.tt.store(board.get_hash(), u8::MAX, 500, TTFlag::Exact, None, 1);
+        // A null window takes the stored score; an open one searches, and finds a line
+        assert_eq!(negamax(&board, &mut state, 2 * ONE_PLY, 1, -1, 0, true), 500);
+        let score = negamax(&board, &mut state, 2 * ONE_PLY, 1, -SCORE_INFINITY, SCORE_INFINITY, true);
+        assert!(score.abs() < 200, "searched score {}", score);
+        assert_eq!(state.pv.line(1).len(), 2);
+    }
+
+    #[test]
+    fn test_multi_pv() {
+        let board = Board::default();
+        let infos = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let mut state = SearchState::new();
+        state.multi_pv = 3;
+        let sink = infos.clone();
+        state.info_sink = Some(Box::new(move |info: &SearchInfo| sink.lock().unwrap().push(info.clone())));
+        let result = search(&board, &mut state, &SearchLimits::depth(4));
+
+        let infos = infos.lock().unwrap();
+        let last: Vec<&SearchInfo> = infos.iter().filter(|i| i.depth == 4 && i.bound == ScoreBound::Exact).collect();
+        assert_eq!(last.iter().map(|i| i.multipv).collect::<Vec<_>>(), [1, 2, 3]);
+        assert_eq!(last[0].pv.first().copied(), result.best_move);
+        let mut first_moves: Vec<u16> = last.iter().map(|i| i.pv[0].to_raw()).collect();
+        first_moves.sort();
+        first_moves.dedup();
+        assert_eq!(first_moves.len(), 3);
+        assert!(state.excluded_root_moves.is_empty());
+
+        // More lines than legal moves: one line per move
+        let board = Board::from_str("7k/8/8/8/8/8/8/K7 w - - 0 1").unwrap();
+        let sink = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let lines = sink.clone();
+        state.multi_pv = 10;
+        state.info_sink = Some(Box::new(move |info: &SearchInfo| lines.lock().unwrap().push((info.depth, info.multipv))));
+        search(&board, &mut state, &SearchLimits::depth(2));
+        assert_eq!(sink.lock().unwrap().iter().filter(|(depth, _)| *depth == 2).count(), 3);
+    }
+
+    #[test]
+    fn test_book_moves() {
+        use crate::book::BookEntry;
+        let board = Board::default();
+        let e4 = ChessMove::from_uci("e2e4").unwrap();
+        let mut state = SearchState::new();
+        state.books[0] = Some(Book::from_entries(vec![BookEntry { key: board.get_hash(), mv: e4, weight: 2, games: 1 }]));
+
+        let result = search(&board, &mut state, &SearchLimits::depth(4));
+        assert_eq!((result.best_move, result.nodes, result.pv), (Some(e4), 0, vec![e4]));
+        // Restricted root moves, and positions out of book, are searched
+        let d4 = ChessMove::from_uci("d2d4").unwrap();
+        let result = search(&board, &mut state, &SearchLimits { searchmoves: vec![d4], ..SearchLimits::depth(2) });
+        assert_eq!(result.best_move, Some(d4));
+        let after_e4 = board.make_move_new(e4);
+        assert!(search(&after_e4, &mut state, &SearchLimits::depth(2)).nodes > 0);
+        // Past Book Depth the book is left alone
+        state.book_depths[0] = 0;
+        assert!(search(&board, &mut state, &SearchLimits::depth(2)).nodes > 0);
+
+        // Books are tried in order: the first one that is in use and has the position decides
+        let c4 = ChessMove::from_uci("c2c4").unwrap();
+        state.books[2] = Some(Book::from_entries(vec![
+            BookEntry { key: board.get_hash(), mv: c4, weight: 1, games: 1 },
+            BookEntry { key: after_e4.get_hash(), mv: ChessMove::from_uci("c7c5").unwrap(), weight: 1, games: 1 },
+        ]));
+        assert_eq!(search(&board, &mut state, &SearchLimits::depth(2)).best_move, Some(c4));
+        state.book_depths[0] = MAX_BOOK_DEPTH;
+        assert_eq!(search(&board, &mut state, &SearchLimits::depth(2)).best_move, Some(e4));
+        let result = search(&after_e4, &mut state, &SearchLimits::depth(2));
+        assert_eq!((result.best_move, result.nodes), (ChessMove::from_uci("c7c5"), 0));
+    }
+
+    #[test]
+    fn test_search_params_switch_heuristics_off() {
+        let board = Board::from_str("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1").unwrap();
+        let mut state = SearchState::new();
+        let bounds = Arc::new(std::sync::Mutex::new(0));
+        let sink = bounds.clone();
+        state.info_sink = Some(Box::new(move |info: &SearchInfo| *sink.lock().unwrap() += (info.bound != ScoreBound::Exact) as u32));
+        state.params = SearchParams { null_move: false, lmr: false, aspiration: false, extensions: false, see_pruning: false, futility: false };
+        let result = search(&board, &mut state, &SearchLimits::depth(4));
+        assert!(board.legal(result.best_move.unwrap()));
+        let stats = state.stats;
+        assert_eq!((stats.null_tries, stats.lmr_searches), (0, 0));
+        assert_eq!((stats.recapture_extensions, stats.passed_pawn_extensions, stats.see_prunes), (0, 0, 0));
+        assert_eq!((stats.futility_prunes, stats.razor_cuts), (0, 0));
+        assert_eq!(*bounds.lock().unwrap(), 0, "no aspiration window to fail");
+    }
+
+    #[test]
+    fn test_refutations() {
+        let board = Board::from_str("r1bqkb1r/pppp1ppp/2n2n2/4p2Q/2B1P3/8/PPPP1PPP/RNB1K1NR b KQkq - 5 4").unwrap();
+        let mut state = SearchState::new();
+        state.silent = true;
+        state.show_refutations = true;
+        state.params.aspiration = false;
+        // A reply cut by its static eval on the null window has no line to show
+        state.params.futility = false;
+        // Every move but the defences of f7 loses to Qxf7# or Bxf7+
+        let iteration = |state: &mut SearchState| {
+            state.reset();
+            negamax(&board, state, 2 * ONE_PLY, 0, -SCORE_INFINITY, SCORE_INFINITY, true);
+            std::mem::take(&mut state.refutations)
+        };
+        let refutations = iteration(&mut state);
+        assert!(!refutations.is_empty());
+        // A refutation is the first reply good enough to cut, not necessarily the mate
+        assert!(refutations.iter().all(|line| line.len() >= 2));
+        for line in &refutations {
+            assert_ne!(Some(line[0]), state.root_best_move);
+            let mut position = board;
+            for &mv in line {
+                assert!(position.legal(mv));
+                position = position.make_move_new(mv);
+            }
+        }
+        state.show_refutations = false;
+        assert!(iteration(&mut state).is_empty());
+    }
+
+    #[test]
+    fn test_stats_collected() {
+        let board = Board::default();
+        let mut state = SearchState::new();
+        // The counts below are the handcrafted eval's, with or without the embedded-net feature
+        state.tables.eval_cache.set_network(None);
+        let result = search(&board, &mut state, &SearchLimits::depth(4));
+        let stats = state.stats;
+        assert!(stats.tt_probes > 0);
+        assert!(stats.tt_hits <= stats.tt_probes);
+        assert!(stats.cutoff_index.iter().sum::<u64>() > 0);
+        assert!(stats.lmr_researches <= stats.lmr_searches);
+        assert!(stats.pvs_searches > 0 && stats.pvs_researches < stats.pvs_searches);
+        // Moves after the first get a null window, so few interior nodes are PV nodes
+        assert!(stats.pv_nodes * 10 < stats.nodes - stats.qnodes);
+        // No tablebases loaded
+        assert_eq!(stats.tb_probes, 0);
+        assert!(stats.recapture_extensions > 0);
+        assert_eq!(stats.passed_pawn_extensions, 0, "no endgame yet");
+        assert_eq!(stats.report().len(), 12);
+
+        // Telemetry, also returned with the result
+        assert_eq!(stats.nodes, result.nodes);
+        assert!(stats.qnodes > 0 && stats.qnodes < stats.nodes);
+        assert!(stats.pv_nodes > 0 && stats.all_nodes > 0);
+        assert!(stats.qsearch_share() > 0.0 && stats.tt_hit_rate() > 0.0);
+        let depths: Vec<u8> = stats.iterations.iter().map(|it| it.depth).collect();
+        assert_eq!(depths, [1, 2, 3, 4]);
+        assert_eq!(stats.iterations.iter().map(|it| it.nodes).sum::<u64>(), stats.nodes);
+        assert!(stats.iterations.windows(2).all(|w| w[0].time_ms <= w[1].time_ms));
+        assert!(stats.branching_factor().is_some_and(|factor| factor > 1.0));
+        assert_eq!(result.stats.iterations, stats.iterations);
+        assert_eq!(SearchStats::default().branching_factor(), None);
+    }
+
+    #[test]
+    fn test_see_pruning() {
+        let board = Board::from_str("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1").unwrap();
+        let mut state = SearchState::new();
+        let result = search(&board, &mut state, &SearchLimits::depth(5));
+        assert!(board.legal(result.best_move.unwrap()));
+        assert!(state.stats.see_prunes > 0);
+
+        // A winning capture is never the move pruned away
+        let board = Board::from_str("4k3/8/2n5/3q4/8/8/3R4/4K3 w - - 0 1").unwrap();
+        let mut state = SearchState::new();
+        let result = search(&board, &mut state, &SearchLimits::depth(5));
+        assert_eq!(result.best_move, Some(ChessMove::from_uci("d2d5").unwrap()));
+    }
+
+    #[test]
+    fn test_futility_pruning() {
+        let board = Board::from_str("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1").unwrap();
+        let mut state = SearchState::new();
+        let result = search(&board, &mut state, &SearchLimits::depth(6));
+        assert!(board.legal(result.best_move.unwrap()));
+        assert!(state.stats.futility_prunes > 0 && state.stats.razor_cuts > 0);
+
+        // A queen up, the quiet moves next to the mating one are futile, never the mate itself
+        let board = Board::from_str("6k1/5ppp/8/8/8/8/5PPP/3Q2K1 w - - 0 1").unwrap();
+        let mut state = SearchState::new();
+        let result = search(&board, &mut state, &SearchLimits::depth(4));
+        assert_eq!(result.best_move, Some(ChessMove::from_uci("d1d8").unwrap()));
+    }
+
+    #[test]
+    fn test_passed_pawn_push_extension() {
+        let board = Board::from_str("8/5k2/8/1P6/8/6p1/2K5/8 w - - 0 1").unwrap();
+        let push = |uci: &str| is_passed_pawn_push(&board, board.normalize_move(ChessMove::from_uci(uci).unwrap()));
+        assert!(push("b5b6"));
+        assert!(!push("c2c3"), "not a pawn");
+        let black = Board::from_str("8/5k2/8/1P6/8/6p1/2K5/8 b - - 0 1").unwrap();
+        assert!(is_passed_pawn_push(&black, ChessMove::from_uci("g3g2").unwrap()));
+        // An enemy pawn ahead on a neighbouring file stops it counting
+        let blocked = Board::from_str("8/2p2k2/8/1P6/8/8/2K5/8 w - - 0 1").unwrap();
+        assert!(!is_passed_pawn_push(&blocked, ChessMove::from_uci("b5b6").unwrap()));
+
+        let mut state = SearchState::new();
+        search(&board, &mut state, &SearchLimits::depth(4));
+        assert!(state.stats.passed_pawn_extensions > 0);
+        // No line extends past the budget, however many pushes it has
+        assert_eq!(state.extension_budget, 4 * ONE_PLY);
+        assert!(state.line_extensions.iter().all(|&e| e <= state.extension_budget));
+        let mut state = SearchState::new();
+        search(&board, &mut state, &SearchLimits::depth(1));
+        assert!(state.line_extensions.iter().all(|&e| e <= ONE_PLY));
+    }
+
+    #[test]
+    fn test_skill_level() {
+        let board = Board::default();
+        let legal: Vec<ChessMove> = MoveGen::new_legal(&board).collect();
+
+        // Level 0 searches one ply and varies between the top few moves
+        let mut state = SearchState::new();
+        state.skill_level = 0;
+        let mut picks = Vec::new();
+        for seed in 1..=20 {
+            state.seed_skill(seed);
+            state.reset();
+            let result = search(&board, &mut state, &SearchLimits::depth(6));
+            assert_eq!(result.depth, 1);
+            let mv = result.best_move.unwrap();
+            assert!(legal.contains(&mv));
+            assert_eq!(result.pv.first(), Some(&mv));
+            picks.push(mv);
+        }
+        assert!(picks.iter().any(|&mv| mv != picks[0]), "level 0 always played {}", picks[0]);
+        assert!(state.excluded_root_moves.is_empty());
+
+        // OpeningVariety varies full-strength play within its margin, and only early in the game
+        let mut state = SearchState::new();
+        state.variety_plies = 4;
+        state.variety_margin = 30;
+        let mut picks = Vec::new();
+        for seed in 1..=12 {
+            state.seed_skill(seed);
+            state.reset();
+            let result = search(&board, &mut state, &SearchLimits::depth(4));
+            assert_eq!(result.pv.first(), result.best_move.as_ref());
+            picks.push(result.best_move.unwrap());
+        }
+        assert!(picks.iter().any(|&mv| mv != picks[0]), "variety always played {}", picks[0]);
+        let late = Board::from_str("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 3").unwrap();
+        let mut late_picks = Vec::new();
+        for seed in 1..=4 {
+            state.seed_skill(seed);
+            state.reset();
+            late_picks.push(search(&late, &mut state, &SearchLimits::depth(4)).best_move.unwrap());
+        }
+        assert!(late_picks.iter().all(|&mv| mv == late_picks[0]));
+
+        // A mate is so far ahead of the alternatives that even level 0 plays it
+        let board = Board::from_str("r1bqkb1r/pppp1ppp/2n2n2/4p2Q/2B1P3/8/PPPP1PPP/RNB1K1NR w KQkq - 4 4").unwrap();
+        for seed in 1..=5 {
+            state.seed_skill(seed);
+            state.reset();
+            assert_eq!(search(&board, &mut state, &SearchLimits::depth(6)).best_move.unwrap().to_string(), "h5f7");
+        }
     }
 
     #[test]
@@ -603,3 +2539,31 @@ mod tests {
 // they look promising, research at full depth
 
 // Quiescence search: at leaf nodes, don't just eval, search all captures to avoid "horizon effect"
+
+// PV: collected during search in a triangular table (each node's line is its best move plus the
+// child's line) rather than by walking the TT afterwards, so overwritten or colliding entries
+// can't cut the reported line short
+
+// Repetition: a capture or pawn move can never be undone, so only the last halfmove-clock plies
+// can repeat, and only every second one has the same side to move. The line's hashes sit in a
+// ply-indexed array and older ones in the game history, so a long game costs nothing per node.
+
+// Contempt: a draw scores -contempt for the side to move at the root and +contempt for the other,
+// decided once per search from the root position. Scaling it by the material left means it fades
+// as the game simplifies, where a misjudged "avoid the draw" costs the most; the clock and
+// rating terms are small nudges on top, not a second evaluation.
+
+// Panics: a bug in one search shouldn't leave a GUI waiting forever for a bestmove, so the front
+// ends go through search_guarded and always answer. The tables may hold whatever the panicking
+// search half-wrote, so they are cleared rather than trusted; the options stay, since they were
+// set by the user and not by the search. wasm32 aborts on panic, so there is nothing to catch there.
+// SEE pruning: a move that loses material by static exchange near the horizon rarely gets it back
+// before the search ends, so it is skipped rather than searched to find that out. Captures get
+// a quadratic margin (a sacrifice with several plies left may well be a combination), quiet moves
+// a linear one; checks are exempt, since SEE can't see what a check forces.
+// Telemetry: the counters live in SearchStats and travel with the SearchResult (boxed, since
+// results sit in events and Results that shouldn't grow by a few hundred bytes), so a library
+// user or a regression script reads the same numbers `SearchStats` prints. Node types follow the
+// usual classification: PV nodes by their window, cut nodes by their cutoff, all nodes by a
+// fail low. The branching factor compares iterations rather than plies, which is what a
+// regression in pruning or move ordering actually changes.
@@ -0,0 +1,392 @@
+use std::str::FromStr;
+use std::time::Instant;
+
+use crate::board::{Board, BoardStatus, Color, MoveGen};
+use crate::engine::Engine;
+use crate::game::Game;
+use crate::pgn::PgnGame;
+use crate::syzygy::SyzygyProber;
+use crate::types::{Score, SearchLimits, TIMED_MAX_DEPTH};
+
+/// How long each move may take
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeControl {
+    Depth(u8),
+    MoveTime(u64),
+    /// Per-side clock in milliseconds, with an increment after each move. Running out loses.
+    Clock { base_ms: u64, inc_ms: u64 },
+}
+
+/// Clock in seconds as `base+inc` or `base` (e.g. `10+0.1`), the format of the PGN TimeControl tag
+impl FromStr for TimeControl {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        let (base, inc) = s.split_once('+').unwrap_or((s, "0"));
+        let ms = |secs: &str| {
+            secs.parse::<f64>()
+                .ok()
+                .filter(|v| v.is_finite() && *v >= 0.0)
+                .map(|v| (v * 1000.0).round() as u64)
+                .ok_or_else(|| format!("Invalid time control: {}", s))
+        };
+        Ok(TimeControl::Clock { base_ms: ms(base)?, inc_ms: ms(inc)? })
+    }
+}
+
+impl TimeControl {
+    /// Value of the PGN TimeControl tag
+    fn pgn_tag(&self) -> String {
+        match *self {
+            TimeControl::Depth(_) => "-".to_string(),
+            TimeControl::MoveTime(ms) => format!("{}/move", seconds(ms)),
+            TimeControl::Clock { base_ms, inc_ms: 0 } => seconds(base_ms),
+            TimeControl::Clock { base_ms, inc_ms } => format!("{}+{}", seconds(base_ms), seconds(inc_ms)),
+        }
+    }
+}
+
+fn seconds(ms: u64) -> String {
+    // f64 Display drops a zero fraction: 10000 -> "10", 100 -> "0.1"
+    format!("{}", ms as f64 / 1000.0)
+}
+
+/// When to end a game early on the engines' own scores. A score counts for a ply when the
+/// side that just searched reports it; a streak breaks as soon as one ply falls outside.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Adjudication {
+    /// Win for the side that is at least this many centipawns ahead for `resign_plies` plies
+    pub resign_score: Option<Score>,
+    pub resign_plies: usize,
+    /// Draw once every score stays within this many centipawns of zero for `draw_plies` plies,
+    /// counted from ply `draw_after_ply`
+    pub draw_score: Option<Score>,
+    pub draw_plies: usize,
+    pub draw_after_ply: usize,
+    /// Draw any game still going after this many plies
+    pub max_plies: usize,
+    /// Syzygy tables that end a game with their result as soon as it reaches one of their
+    /// positions (see `tablebase_result`)
+    pub syzygy_path: Option<String>,
+}
+
+impl Default for Adjudication {
+    fn default() -> Self {
+        Adjudication {
+            resign_score: Some(1000),
+            resign_plies: 6,
+            draw_score: Some(10),
+            draw_plies: 16,
+            draw_after_ply: 80,
+            max_plies: 500,
+            syzygy_path: None,
+        }
+    }
+}
+
+/// Running score streaks for `Adjudication`
+#[derive(Debug, Default)]
+pub(crate) struct Adjudicator {
+    /// Positive while White is winning, negative while Black is
+    resign_streak: i32,
+    draw_streak: usize,
+}
+
+impl Adjudicator {
+    /// Record White's score from the search behind the move that made the game `plies` long.
+    /// Returns the result once the rules call the game.
+    pub(crate) fn update(&mut self, rules: &Adjudication, plies: usize, white_score: Score) -> Option<&'static str> {
+        if let Some(threshold) = rules.resign_score {
+            self.resign_streak = match white_score {
+                s if s >= threshold => self.resign_streak.max(0) + 1,
+                s if s <= -threshold => self.resign_streak.min(0) - 1,
+                _ => 0,
+            };
+            if self.resign_streak.unsigned_abs() as usize >= rules.resign_plies {
+                return Some(if self.resign_streak > 0 { "1-0" } else { "0-1" });
+            }
+        }
+        if let Some(margin) = rules.draw_score {
+            let in_window = plies > rules.draw_after_ply && white_score.abs() <= margin;
+            self.draw_streak = if in_window { self.draw_streak + 1 } else { 0 };
+            if self.draw_streak >= rules.draw_plies {
+                return Some("1/2-1/2");
+            }
+        }
+        None
+    }
+}
+
+/// The game result the tablebases give `board`, if they have it. A win the fifty-move rule would
+/// stop first (a cursed win, or the next capture or pawn move too far off for the halfmove
+/// clock) is a draw.
+pub(crate) fn tablebase_result(prober: &SyzygyProber, board: &Board) -> Option<&'static str> {
+    let score = prober.probe_wdl(board)?;
+    let too_slow = prober.probe_dtz(board).is_some_and(|dtz| dtz.unsigned_abs() + board.halfmove_clock() as u32 > 100);
+    let winner = match score {
+        s if s > 100 && !too_slow => Some(board.side_to_move()),
+        s if s < -100 && !too_slow => Some(!board.side_to_move()),
+        _ => None,
+    };
+    Some(match winner {
+        Some(Color::White) => "1-0",
+        Some(Color::Black) => "0-1",
+        None => "1/2-1/2",
+    })
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SelfplayConfig {
+    pub games: u32,
+    pub time_control: TimeControl,
+    /// Start positions, used in turn; the standard start position if empty
+    pub openings: Vec<Board>,
+    /// Uniformly random legal moves played from the opening before the engines take over
+    pub random_plies: usize,
+    /// Seed for the random plies, so a run can be repeated
+    pub seed: u64,
+    pub adjudication: Adjudication,
+    /// UCI `(name, value)` options given to both engines
+    pub options: Vec<(String, String)>,
+}
+
+impl Default for SelfplayConfig {
+    fn default() -> Self {
+        SelfplayConfig {
+            games: 1,
+            time_control: TimeControl::Depth(6),
+            openings: Vec::new(),
+            random_plies: 0,
+            seed: 1,
+            adjudication: Adjudication::default(),
+            options: Vec::new(),
+        }
+    }
+}
+
+/// Play `config.games` games of the engine against itself and pass each one to `on_game` as it
+/// finishes. Each side has its own engine, so the two never share a transposition table.
+pub fn run(config: &SelfplayConfig, mut on_game: impl FnMut(&PgnGame)) {
+    let mut engines = [Engine::new(), Engine::new()];
+    for engine in &engines {
+        for (name, value) in &config.options {
+            engine.set_option(name, value);
+        }
+    }
+    let mut rng = config.seed | 1;
+    let tablebase = config.adjudication.syzygy_path.as_deref().and_then(SyzygyProber::new);
+
+    for round in 1..=config.games {
+        let opening = match config.openings.len() {
+            0 => Board::default(),
+            n => config.openings[(round as usize - 1) % n],
+        };
+        for engine in &engines {
+            engine.new_game();
+        }
+        let game = play_game(config, opening, &mut rng, &mut engines, round, tablebase.as_ref());
+        on_game(&game);
+    }
+}
+
+/// One game from `opening`: random plies, then engine moves until the rules or adjudication end it
+fn play_game(
+    config: &SelfplayConfig,
+    opening: Board,
+    rng: &mut u64,
+    engines: &mut [Engine; 2],
+    round: u32,
+    tablebase: Option<&SyzygyProber>,
+) -> PgnGame {
+    let adjudication = &config.adjudication;
+    let mut game = Game::new(opening);
+    for _ in 0..config.random_plies {
+        let moves: Vec<_> = MoveGen::new_legal(game.board()).collect();
+        if moves.is_empty() {
+            break;
+        }
+        let mv = moves[(next_random(rng) % moves.len() as u64) as usize];
+        game.make_move(mv).expect("generated moves are legal");
+    }
+
+    let mut clocks = match config.time_control {
+        TimeControl::Clock { base_ms, .. } => [base_ms; 2],
+        _ => [0; 2],
+    };
+    let mut adjudicator = Adjudicator::default();
+
+    let (result, termination) = loop {
+        let board = *game.board();
+        if board.status() == BoardStatus::Checkmate {
+            break (if board.side_to_move() == Color::White { "0-1" } else { "1-0" }, "normal");
+        }
+        if game.draw_reason().is_some() {
+            break ("1/2-1/2", "normal");
+        }
+        if game.moves().len() >= adjudication.max_plies {
+            break ("1/2-1/2", "adjudication");
+        }
+        if let Some(result) = tablebase.and_then(|tb| tablebase_result(tb, &board)) {
+            break (result, "adjudication");
+        }
+
+        let side = board.side_to_move();
+        let limits = match config.time_control {
+            TimeControl::Depth(depth) => SearchLimits::depth(depth),
+            TimeControl::MoveTime(ms) => SearchLimits { depth: Some(TIMED_MAX_DEPTH), movetime: Some(ms), ..Default::default() },
+            TimeControl::Clock { inc_ms, .. } => SearchLimits {
+                depth: Some(TIMED_MAX_DEPTH),
+                wtime: Some(clocks[Color::White.to_index()]),
+                btime: Some(clocks[Color::Black.to_index()]),
+                winc: Some(inc_ms),
+                binc: Some(inc_ms),
+                ..Default::default()
+            },
+        };
+
+        let engine = &mut engines[side.to_index()];
+        engine.set_position(game.clone());
+        let start = Instant::now();
+        engine.go(limits);
+        let result = engine.wait_best_move().expect("engine worker exited");
+        let mv = result.best_move.expect("a position that is not mate or stalemate has a move");
+
+        if let TimeControl::Clock { inc_ms, .. } = config.time_control {
+            let clock = &mut clocks[side.to_index()];
+            let used = start.elapsed().as_millis() as u64;
+            if used > *clock {
+                break (if side == Color::White { "0-1" } else { "1-0" }, "time forfeit");
+            }
+            *clock = *clock - used + inc_ms;
+        }
+        game.make_move(mv).expect("engine plays legal moves");
+
+        let white_score = if side == Color::White { result.score } else { -result.score };
+        if let Some(result) = adjudicator.update(adjudication, game.moves().len(), white_score) {
+            break (result, "adjudication");
+        }
+    };
+
+    let mut tags = vec![
+        ("Event".to_string(), "ferrite self-play".to_string()),
+        ("Site".to_string(), "?".to_string()),
+        ("Date".to_string(), "????.??.??".to_string()),
+        ("Round".to_string(), round.to_string()),
+        ("White".to_string(), "ferrite".to_string()),
+        ("Black".to_string(), "ferrite".to_string()),
+        ("Result".to_string(), result.to_string()),
+    ];
+    if opening != Board::default() {
+        tags.push(("SetUp".to_string(), "1".to_string()));
+        tags.push(("FEN".to_string(), opening.to_string()));
+    }
+    tags.push(("TimeControl".to_string(), config.time_control.pgn_tag()));
+    tags.push(("Termination".to_string(), termination.to_string()));
+    tags.push(("PlyCount".to_string(), game.moves().len().to_string()));
+
+    PgnGame { tags, start: opening, moves: game.moves().to_vec(), result: result.to_string(), annotations: Vec::new() }
+}
+
+/// xorshift64
+pub(crate) fn next_random(state: &mut u64) -> u64 {
+    *state ^= *state << 13;
+    *state ^= *state >> 7;
+    *state ^= *state << 17;
+    *state
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_time_control_parse() {
+        assert_eq!("10+0.1".parse(), Ok(TimeControl::Clock { base_ms: 10_000, inc_ms: 100 }));
+        assert_eq!("60".parse(), Ok(TimeControl::Clock { base_ms: 60_000, inc_ms: 0 }));
+        assert!("fast".parse::<TimeControl>().is_err());
+        assert!("-1+0".parse::<TimeControl>().is_err());
+        assert_eq!(TimeControl::Clock { base_ms: 10_000, inc_ms: 100 }.pgn_tag(), "10+0.1");
+    }
+
+    #[test]
+    fn test_games_are_valid_pgn() {
+        let config = SelfplayConfig {
+            games: 2,
+            time_control: TimeControl::Depth(2),
+            random_plies: 4,
+            adjudication: Adjudication { max_plies: 12, ..Default::default() },
+            ..Default::default()
+        };
+        let mut games = Vec::new();
+        run(&config, |game| games.push(game.clone()));
+        assert_eq!(games.len(), 2);
+
+        for game in &games {
+            let reread = PgnGame::parse(&game.to_string()).unwrap();
+            assert_eq!(reread.moves, game.moves);
+            assert!(game.moves.len() <= 12);
+            assert_eq!(game.tag("Result"), Some(game.result.as_str()));
+        }
+        // Random plies give the two games different openings
+        assert_ne!(games[0].moves[..4], games[1].moves[..4]);
+    }
+
+    #[test]
+    fn test_adjudication_and_openings() {
+        // White is a queen up: the resign rule ends it long before mate
+        let opening = Board::from_str("4k3/8/8/8/8/8/3QK3/8 w - - 0 1").unwrap();
+        let config = SelfplayConfig {
+            time_control: TimeControl::Depth(2),
+            openings: vec![opening],
+            adjudication: Adjudication { resign_plies: 2, ..Default::default() },
+            // The resign scores are the handcrafted eval's, with or without the embedded-net feature
+            options: vec![("EvalMode".to_string(), "Classical".to_string())],
+            ..Default::default()
+        };
+        let mut games = Vec::new();
+        run(&config, |game| games.push(game.clone()));
+        let game = &games[0];
+        assert_eq!(game.result, "1-0");
+        assert_eq!(game.tag("Termination"), Some("adjudication"));
+        assert_eq!(game.tag("FEN"), Some(opening.to_string().as_str()));
+        assert_eq!(game.moves.len(), 2);
+    }
+
+    #[test]
+    fn test_tablebase_adjudication() {
+        use crate::syzygy::tests::{table_dir, write_wdl, DRAW, LOSS, WIN};
+
+        let dir = table_dir("adjudication");
+        write_wdl(&dir, "KRvK", [WIN, LOSS]);
+        write_wdl(&dir, "KNvK", [DRAW, DRAW]);
+        let prober = SyzygyProber::open(dir.to_str().unwrap()).unwrap();
+        let result = |fen: &str| tablebase_result(&prober, &Board::from_str(fen).unwrap());
+        assert_eq!(result("4k3/8/8/8/8/8/8/R3K3 b - - 0 1"), Some("1-0"));
+        assert_eq!(result("r3k3/8/8/8/8/8/8/4K3 w - - 0 1"), Some("0-1"));
+        assert_eq!(result("4k3/8/8/8/8/8/8/N3K3 w - - 0 1"), Some("1/2-1/2"));
+        assert_eq!(result("4k3/8/8/8/8/8/8/Q3K3 w - - 0 1"), None);
+
+        // The game ends on reaching the table, before anyone searches
+        let opening = Board::from_str("4k3/8/8/8/8/8/8/R3K3 w - - 0 1").unwrap();
+        let config = SelfplayConfig {
+            time_control: TimeControl::Depth(2),
+            openings: vec![opening],
+            adjudication: Adjudication { syzygy_path: Some(dir.to_str().unwrap().to_string()), ..Default::default() },
+            ..Default::default()
+        };
+        let mut games = Vec::new();
+        run(&config, |game| games.push(game.clone()));
+        assert_eq!((games[0].result.as_str(), games[0].moves.len()), ("1-0", 0));
+        assert_eq!(games[0].tag("Termination"), Some("adjudication"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
+
+// Self-play drives two `Engine`s through a `Game`, so repetition history reaches the search and
+// the rules (mate, stalemate, threefold, fifty moves, dead positions) end games exactly as in
+// the library. Adjudication only looks at the scores the engines report anyway: a resign streak
+// is kept signed so a swing from one side winning to the other restarts it. Tablebases are the
+// exception, since they are exact: a game that reaches one of their positions is over, and
+// playing it out would only cost time and risk a misplayed ending. Opening variety
+// comes from an openings list plus seeded random plies, which keeps runs reproducible for
+// regression testing; the random moves are part of the recorded game.
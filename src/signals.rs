@@ -0,0 +1,77 @@
+use std::process;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, Once};
+
+use crate::output;
+
+/// Exit status after a second interrupt, as a shell reports a process killed by SIGINT
+const INTERRUPTED_EXIT_CODE: i32 = 130;
+
+type Action = Box<dyn Fn() + Send>;
+
+static ACTION: Mutex<Option<Action>> = Mutex::new(None);
+static PENDING: AtomicBool = AtomicBool::new(false);
+static INSTALL: Once = Once::new();
+
+/// Run `action` on Ctrl-C or SIGTERM instead of dying mid-write: it should stop the search so
+/// the engine can answer and exit by itself. A second signal before `clear` exits at once, with
+/// output flushed. Replaces any earlier action.
+pub fn on_interrupt(action: impl Fn() + Send + 'static) {
+    INSTALL.call_once(|| {
+        // Failing to install (another handler got there first) leaves the default behaviour
+        let _ = ctrlc::set_handler(handle_signal);
+    });
+    PENDING.store(false, Ordering::SeqCst);
+    *ACTION.lock().unwrap_or_else(|e| e.into_inner()) = Some(Box::new(action));
+}
+
+/// Drop the action: a signal exits straight away again
+pub fn clear() {
+    *ACTION.lock().unwrap_or_else(|e| e.into_inner()) = None;
+}
+
+fn handle_signal() {
+    let action = ACTION.lock().unwrap_or_else(|e| e.into_inner());
+    match action.as_ref() {
+        Some(action) if !PENDING.swap(true, Ordering::SeqCst) => action(),
+        _ => {
+            output::flush();
+            process::exit(INTERRUPTED_EXIT_CODE);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_interrupt_runs_action() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = calls.clone();
+        on_interrupt(move || {
+            counter.fetch_add(1, Ordering::SeqCst);
+        });
+        handle_signal();
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert!(PENDING.load(Ordering::SeqCst));
+
+        // A new action starts over; a second signal now would exit the process
+        let counter = calls.clone();
+        on_interrupt(move || {
+            counter.fetch_add(10, Ordering::SeqCst);
+        });
+        assert!(!PENDING.load(Ordering::SeqCst));
+        handle_signal();
+        assert_eq!(calls.load(Ordering::SeqCst), 11);
+        clear();
+        assert!(ACTION.lock().unwrap().is_none());
+    }
+}
+
+// Signals only ever set a flag or send a message here; ctrlc runs the handler on a thread of
+// its own, so it may lock and allocate, but the real work (printing bestmove, joining the
+// worker) stays with whoever owns the engine. The handler is installed on first use rather than
+// at startup, so commands that don't ask for it keep the default die-on-Ctrl-C behaviour.
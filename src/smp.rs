@@ -0,0 +1,104 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+
+use crate::numa::{self, NumaPolicy};
+use crate::search::SearchState;
+
+/// One search for a helper, run on the helper's own state
+pub(crate) type Task = Box<dyn FnOnce(&mut SearchState) + Send>;
+
+struct Helper {
+    tasks: Sender<Task>,
+    done: Receiver<()>,
+    thread: JoinHandle<()>,
+}
+
+/// The Lazy SMP helper threads of a search state (see `SearchState::threads`). Each thread
+/// lives as long as the pool and owns a search state of its own, built on the thread after it
+/// is pinned to its NUMA node, so its history, killers and eval cache are local to it.
+pub(crate) struct HelperPool {
+    helpers: Vec<Helper>,
+    /// The policy the threads were placed under
+    policy: NumaPolicy,
+    /// Ends the helpers' searches
+    stop: Arc<AtomicBool>,
+    /// Whether tasks were started and not yet waited for
+    running: bool,
+}
+
+impl HelperPool {
+    /// `count` helper threads, numbered from 1 since the main search thread is 0
+    pub(crate) fn new(count: usize) -> Self {
+        let helpers = (1..=count)
+            .map(|index| {
+                let (tasks, queue) = mpsc::channel::<Task>();
+                let (finished, done) = mpsc::channel();
+                let thread = thread::Builder::new()
+                    .name(format!("search-{}", index))
+                    .spawn(move || {
+                        numa::bind_thread(index);
+                        let mut state = SearchState::helper(index);
+                        for task in queue {
+                            task(&mut state);
+                            if finished.send(()).is_err() {
+                                break;
+                            }
+                        }
+                    })
+                    .expect("failed to spawn a search thread");
+                Helper { tasks, done, thread }
+            })
+            .collect();
+        HelperPool { helpers, policy: numa::policy(), stop: Arc::new(AtomicBool::new(false)), running: false }
+    }
+
+    /// Whether the pool has `count` helpers, placed under the current NUMA policy
+    pub(crate) fn fits(&self, count: usize) -> bool {
+        self.helpers.len() == count && self.policy == numa::policy()
+    }
+
+    /// Start `task(index, stop)` on each helper, where `stop` is the flag that `finish` sets
+    pub(crate) fn start(&mut self, task: impl Fn(usize, Arc<AtomicBool>) -> Task) {
+        self.stop.store(false, Ordering::SeqCst);
+        for (i, helper) in self.helpers.iter().enumerate() {
+            // A helper whose thread is gone is found out by `finish`
+            let _ = helper.tasks.send(task(i + 1, Arc::clone(&self.stop)));
+        }
+        self.running = true;
+    }
+
+    /// Stop the helpers and wait until each has finished its task. False if a helper's thread
+    /// has died (its search panicked), which leaves the pool to be replaced.
+    pub(crate) fn finish(&mut self) -> bool {
+        if !std::mem::take(&mut self.running) {
+            return true;
+        }
+        self.stop.store(true, Ordering::SeqCst);
+        let mut alive = true;
+        for helper in &self.helpers {
+            // Wait for every helper, even after finding a dead one
+            alive &= helper.done.recv().is_ok();
+        }
+        alive
+    }
+}
+
+impl Drop for HelperPool {
+    fn drop(&mut self) {
+        self.finish();
+        for Helper { tasks, thread, .. } in self.helpers.drain(..) {
+            // Closing the queue ends the thread
+            drop(tasks);
+            let _ = thread.join();
+        }
+    }
+}
+
+// Lazy SMP has no split points or work sharing: every thread searches the whole tree from the
+// root, and they help one another only through the TT, whose entries are single atomic words.
+// Helpers iterate from a ply apart so they don't all search the same depth, and whatever they
+// store orders and cuts the main thread's search, which alone reports and picks the move.
+// The threads are kept between searches rather than spawned for each, so that each one's
+// search state stays warm, and stays on the node it was first built on.
@@ -0,0 +1,526 @@
+mod table;
+
+use std::collections::HashMap;
+use std::ops::Neg;
+use std::path::PathBuf;
+use std::sync::{Arc, OnceLock};
+use std::thread;
+
+use thiserror::Error;
+
+use table::{Material, Metric, Table};
+
+use crate::board::{Board, BoardStatus, MoveGen, Piece};
+use crate::types::Score;
+
+/// Largest position (kings included) the probe looks up
+pub const MAX_PIECES: u32 = 5;
+
+/// Outcome with best play. Cursed wins and blessed losses are wins and losses that the
+/// fifty-move rule turns into draws.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Wdl {
+    Loss,
+    BlessedLoss,
+    Draw,
+    CursedWin,
+    Win,
+}
+
+impl Neg for Wdl {
+    type Output = Wdl;
+
+    fn neg(self) -> Wdl {
+        match self {
+            Wdl::Loss => Wdl::Win,
+            Wdl::BlessedLoss => Wdl::CursedWin,
+            Wdl::Draw => Wdl::Draw,
+            Wdl::CursedWin => Wdl::BlessedLoss,
+            Wdl::Win => Wdl::Loss,
+        }
+    }
+}
+
+/// A table file found in the directory, opened on its first probe
+struct LazyTable {
+    path: PathBuf,
+    metric: Metric,
+    table: OnceLock<Result<Table, String>>,
+}
+
+impl LazyTable {
+    fn get(&self, material: Material) -> Result<&Table, String> {
+        self.table
+            .get_or_init(|| Table::open(&self.path, material, self.metric))
+            .as_ref()
+            .map_err(|e| format!("{}: {}", self.path.display(), e))
+    }
+}
+
+/// Why `SyzygyProber::open` found nothing to probe
+#[derive(Debug, Error)]
+pub enum SyzygyLoadError {
+    #[error("{0} is not a directory")]
+    NotADirectory(String),
+    #[error("cannot read {path}: {source}")]
+    Read { path: String, source: std::io::Error },
+    #[error("no Syzygy WDL tables (.rtbw) in {0}")]
+    NoTables(String),
+    #[error("tablebases are not supported on wasm32")]
+    Unsupported,
+}
+
+pub struct SyzygyProber {
+    wdl: HashMap<Material, LazyTable>,
+    dtz: HashMap<Material, LazyTable>,
+    /// WDL tables found, indexed by piece count (kings included)
+    wdl_tables: [usize; 8],
+}
+
+impl SyzygyProber {
+    /// Create a new prober by loading tablebases from given directory
+    /// Returns None if path doesn't exist or contains no valid tables
+    pub fn new(path: &str) -> Option<Self> {
+        Self::open(path).ok()
+    }
+
+    /// Like `new`, but says why nothing was loaded
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn open(path: &str) -> Result<Self, SyzygyLoadError> {
+        if !std::path::Path::new(path).is_dir() {
+            return Err(SyzygyLoadError::NotADirectory(path.to_string()));
+        }
+
+        let mut prober = SyzygyProber { wdl: HashMap::new(), dtz: HashMap::new(), wdl_tables: [0; 8] };
+        for entry in std::fs::read_dir(path).map_err(|source| SyzygyLoadError::Read { path: path.to_string(), source })?.flatten() {
+            let file = entry.path();
+            let (tables, metric) = match file.extension().and_then(|ext| ext.to_str()) {
+                Some("rtbw") => (&mut prober.wdl, Metric::Wdl),
+                Some("rtbz") => (&mut prober.dtz, Metric::Dtz),
+                _ => continue,
+            };
+            // Tables are named with the stronger side first (KQvKR), and every table file
+            // is 16 bytes past a multiple of 64
+            let Some(material) = file.file_stem().and_then(|s| s.to_str()).and_then(Material::from_name) else {
+                continue;
+            };
+            if material != material.normalized() || entry.metadata().map_or(true, |m| m.len() % 64 != 16) {
+                continue;
+            }
+            if metric == Metric::Wdl {
+                prober.wdl_tables[material.count()] += 1;
+            }
+            tables.insert(material, LazyTable { path: file, metric, table: OnceLock::new() });
+        }
+        if prober.wdl.is_empty() {
+            return Err(SyzygyLoadError::NoTables(path.to_string()));
+        }
+
+        Ok(prober)
+    }
+
+    /// No filesystem on wasm32, so never any tables
+    #[cfg(target_arch = "wasm32")]
+    pub fn open(_path: &str) -> Result<Self, SyzygyLoadError> {
+        Err(SyzygyLoadError::Unsupported)
+    }
+
+    /// WDL tables found per piece count, e.g. `3-piece: 5, 4-piece: 30`
+    pub fn summary(&self) -> String {
+        let counts: Vec<String> = self
+            .wdl_tables
+            .iter()
+            .enumerate()
+            .filter(|&(_, &n)| n > 0)
+            .map(|(pieces, n)| format!("{}-piece: {}", pieces, n))
+            .collect();
+        let mut summary = counts.join(", ");
+        if self.wdl_tables[MAX_PIECES as usize + 1..].iter().any(|&n| n > 0) {
+            summary.push_str(&format!(" (only up to {} pieces are probed)", MAX_PIECES));
+        }
+        summary
+    }
+
+    /// Open every table now rather than on its first probe. Reports the tables that opened,
+    /// e.g. `found 145 WDL / 145 DTZ tables`, and the first that didn't.
+    pub fn open_all(&self) -> String {
+        let failures = |tables: &HashMap<Material, LazyTable>| -> Vec<String> {
+            tables.iter().filter_map(|(&material, table)| table.get(material).err()).collect()
+        };
+        let (wdl_failed, dtz_failed) = (failures(&self.wdl), failures(&self.dtz));
+        let (wdl, dtz) = (self.wdl.len() - wdl_failed.len(), self.dtz.len() - dtz_failed.len());
+        let failed: Vec<String> = wdl_failed.into_iter().chain(dtz_failed).collect();
+        let mut report = format!("found {} WDL / {} DTZ tables", wdl, dtz);
+        if let Some(first) = failed.first() {
+            report.push_str(&format!(", {} failed to open ({})", failed.len(), first));
+        }
+        report
+    }
+
+    /// Probe WDL for a position given as a `crate::board::Board`.
+    /// Returns a score: positive for win, negative for loss, 0 for draw.
+    /// Only valid for positions with 5 or fewer pieces and no castling rights.
+    pub fn probe_wdl(&self, board: &Board) -> Option<Score> {
+        if !Self::probeable(board) {
+            return None;
+        }
+        self.probe(board).ok().map(|(wdl, _)| wdl_to_score(wdl))
+    }
+
+    /// Plies to the next capture or pawn move with best play, positive when the side to move
+    /// wins and 0 for a draw. Past 100 plies (or -100) the fifty-move rule draws the game
+    /// first. Tables that store full moves make this one ply too long at times.
+    /// Needs the DTZ (.rtbz) tables as well as the WDL ones.
+    pub fn probe_dtz(&self, board: &Board) -> Option<i32> {
+        if !Self::probeable(board) {
+            return None;
+        }
+        self.dtz(board).ok()
+    }
+
+    fn probeable(board: &Board) -> bool {
+        board.combined().popcnt() <= MAX_PIECES && board.castling_rights() == 0
+    }
+
+    /// WDL of `board`, and whether the best move is a capture or pawn move. Tables may store
+    /// any value for a position where a capture does at least as well, so captures
+    /// (en passant included) are searched first.
+    fn probe(&self, board: &Board) -> Result<(Wdl, bool), String> {
+        let moves: Vec<_> = MoveGen::new_legal(board).collect();
+        let mut best_capture = Wdl::Loss;
+        let mut best_ep = Wdl::Loss;
+        for &mv in moves.iter().filter(|&&mv| board.is_capture(mv)) {
+            let v = -self.probe_captures(&board.make_move_new(mv), Wdl::Loss, -best_capture)?;
+            if v == Wdl::Win {
+                return Ok((v, true));
+            }
+            if board.is_en_passant(mv) {
+                best_ep = best_ep.max(v);
+            } else {
+                best_capture = best_capture.max(v);
+            }
+        }
+
+        // Tables ignore en passant rights, so only trust them against the other captures
+        let v = self.probe_table(board)?;
+        if best_ep > v.max(best_capture) {
+            return Ok((best_ep, true));
+        }
+        best_capture = best_capture.max(best_ep);
+        if best_capture >= v {
+            return Ok((best_capture, best_capture > Wdl::Draw));
+        }
+        // Stalemate but for en passant: the capture is forced
+        if v == Wdl::Draw && !moves.is_empty() && moves.iter().all(|&mv| board.is_en_passant(mv)) {
+            return Ok((best_ep, true));
+        }
+        Ok((v, false))
+    }
+
+    /// Alpha-beta over captures only, for positions without en passant rights
+    fn probe_captures(&self, board: &Board, mut alpha: Wdl, beta: Wdl) -> Result<Wdl, String> {
+        for mv in MoveGen::new_captures(board).filter(|&mv| board.is_capture(mv)) {
+            let v = -self.probe_captures(&board.make_move_new(mv), -beta, -alpha)?;
+            if v >= beta {
+                return Ok(v);
+            }
+            alpha = alpha.max(v);
+        }
+        Ok(alpha.max(self.probe_table(board)?))
+    }
+
+    fn probe_table(&self, board: &Board) -> Result<Wdl, String> {
+        if board.combined() == board.pieces(Piece::King) {
+            return Ok(Wdl::Draw);
+        }
+        let material = Material::from_board(board).normalized();
+        let table = self.wdl.get(&material).ok_or_else(|| format!("no WDL table for {}", material))?;
+        table.get(material)?.probe_wdl(board)
+    }
+
+    fn dtz(&self, board: &Board) -> Result<i32, String> {
+        let (wdl, zeroing) = self.probe(board)?;
+        let before_zeroing = match wdl {
+            Wdl::Loss => -1,
+            Wdl::BlessedLoss => -101,
+            Wdl::Draw => return Ok(0),
+            Wdl::CursedWin => 101,
+            Wdl::Win => 1,
+        };
+        if zeroing {
+            return Ok(before_zeroing);
+        }
+
+        // A winning pawn push is the best move too (captures were covered by `probe`)
+        if wdl > Wdl::Draw {
+            for mv in MoveGen::new_legal(board) {
+                if board.piece_on(mv.get_source()) == Some(Piece::Pawn)
+                    && !board.is_capture(mv)
+                    && -self.probe(&board.make_move_new(mv))?.0 == wdl
+                {
+                    return Ok(before_zeroing);
+                }
+            }
+        }
+
+        let material = Material::from_board(board).normalized();
+        let table = self.dtz.get(&material).ok_or_else(|| format!("no DTZ table for {}", material))?;
+        if let Some((plies, _rounded)) = table.get(material)?.probe_dtz(board, wdl)? {
+            return Ok(before_zeroing + before_zeroing.signum() * plies as i32);
+        }
+
+        // The table only has the other side to move: search one ply
+        let mut best = if wdl > Wdl::Draw { None } else { Some(before_zeroing) };
+        for mv in MoveGen::new_legal(board) {
+            if board.piece_on(mv.get_source()) == Some(Piece::Pawn) || board.is_capture(mv) {
+                continue;
+            }
+            let after = board.make_move_new(mv);
+            let v = -self.dtz(&after)?;
+            if v == 1 && after.status() == BoardStatus::Checkmate {
+                best = Some(1);
+            } else if v.signum() == before_zeroing.signum() {
+                let v = v + v.signum();
+                best = Some(best.map_or(v, |best| best.min(v)));
+            }
+        }
+        best.ok_or_else(|| format!("no DTZ for {}", board))
+    }
+}
+
+/// A prober whose tables are opened on a background thread. Clones share it; `get` is None
+/// until the thread has finished, so searches meanwhile go without tablebases.
+#[derive(Clone, Default)]
+pub struct SharedProber(Arc<OnceLock<SyzygyProber>>);
+
+impl SharedProber {
+    /// Open every table of `prober` on a new thread, then make it available and pass
+    /// `on_done` the `open_all` report
+    pub fn load(prober: SyzygyProber, on_done: impl FnOnce(String) + Send + 'static) -> Self {
+        let shared = SharedProber::default();
+        let loaded = shared.clone();
+        thread::spawn(move || {
+            let report = prober.open_all();
+            let _ = loaded.0.set(prober);
+            on_done(report);
+        });
+        shared
+    }
+
+    pub fn get(&self) -> Option<&SyzygyProber> {
+        self.0.get()
+    }
+}
+
+fn wdl_to_score(wdl: Wdl) -> Score {
+    match wdl {
+        Wdl::Win => 20_000,
+        Wdl::CursedWin => 100,
+        Wdl::Draw => 0,
+        Wdl::BlessedLoss => -100,
+        Wdl::Loss => -20_000,
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    #[test]
+    fn test_syzygy_prober_invalid_path() {
+        let prober = SyzygyProber::new("/nonexistent/path");
+        assert!(prober.is_none());
+    }
+
+    #[test]
+    fn test_syzygy_open_reports_why() {
+        let error = |path| SyzygyProber::open(path).err().unwrap();
+        assert!(matches!(error("/nonexistent/path"), SyzygyLoadError::NotADirectory(_)));
+        assert!(error("/nonexistent/path").to_string().contains("not a directory"));
+        // A directory without tables
+        assert!(matches!(error("src"), SyzygyLoadError::NoTables(_)));
+    }
+
+    use super::table::tests::write_table;
+    use std::path::Path;
+    use std::str::FromStr;
+
+    pub(crate) const WIN: u16 = 4;
+    pub(crate) const DRAW: u16 = 2;
+    pub(crate) const LOSS: u16 = 0;
+
+    /// Fresh directory for one test's tables
+    pub(crate) fn table_dir(test: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("ferrite-syzygy-{}-{}", test, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// WDL table with one value for each side to move (White first, as the stronger side)
+    pub(crate) fn write_wdl(dir: &Path, name: &str, values: [u16; 2]) {
+        write_table(&dir.join(format!("{}.rtbw", name)), name, Metric::Wdl, 0, |side, _, _| values[side]);
+    }
+
+    fn probe(prober: &SyzygyProber, fen: &str) -> (Option<Score>, Option<i32>) {
+        let board = Board::from_str(fen).unwrap();
+        (prober.probe_wdl(&board), prober.probe_dtz(&board))
+    }
+
+    #[test]
+    fn test_probe_resolves_captures() {
+        let dir = table_dir("captures");
+        write_wdl(&dir, "KRvKN", [DRAW, DRAW]);
+        write_wdl(&dir, "KNvK", [DRAW, DRAW]);
+        write_wdl(&dir, "KRvK", [WIN, LOSS]);
+        // White to move is 5 plies from zeroing everywhere; Black to move isn't stored
+        write_table(&dir.join("KRvK.rtbz"), "KRvK", Metric::Dtz, table::FLAG_WIN_PLIES, |_, _, _| 5);
+        let prober = SyzygyProber::open(dir.to_str().unwrap()).unwrap();
+
+        // Taking the knight wins, and is itself the zeroing move
+        assert_eq!(probe(&prober, "4k3/8/8/8/8/8/1n6/1R2K3 w - - 0 1"), (Some(20_000), Some(1)));
+        assert_eq!(probe(&prober, "4k3/8/8/8/8/8/1n6/1R2K3 b - - 0 1"), (Some(0), Some(0)));
+        assert_eq!(probe(&prober, "4k3/8/8/8/8/8/8/R3K3 w - - 0 1"), (Some(20_000), Some(6)));
+        // From the other side a ply further, found by searching Black's moves
+        assert_eq!(probe(&prober, "4k3/8/8/8/8/8/8/R3K3 b - - 0 1"), (Some(-20_000), Some(-7)));
+        // Black is the stronger side here: the board is flipped to find KRvK
+        assert_eq!(probe(&prober, "r3k3/8/8/8/8/8/8/4K3 b - - 0 1"), (Some(20_000), Some(6)));
+
+        assert_eq!(probe(&prober, "4k3/8/8/8/8/8/8/4K3 w - - 0 1"), (Some(0), Some(0)));
+        assert_eq!(probe(&prober, "4k3/8/8/8/8/8/8/Q3K3 w - - 0 1"), (None, None));
+        assert_eq!(probe(&prober, "4k3/8/8/8/8/8/8/R3K3 w Q - 0 1"), (None, None));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_probe_en_passant() {
+        let dir = table_dir("ep");
+        write_wdl(&dir, "KPvKP", [DRAW, DRAW]);
+        write_wdl(&dir, "KPvK", [WIN, LOSS]);
+        let prober = SyzygyProber::open(dir.to_str().unwrap()).unwrap();
+
+        // Tables ignore en passant rights, so only the capture finds the win
+        assert_eq!(prober.probe_wdl(&Board::from_str("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1").unwrap()), Some(20_000));
+        assert_eq!(prober.probe_wdl(&Board::from_str("4k3/8/8/3pP3/8/8/8/4K3 w - - 0 1").unwrap()), Some(0));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_probe_symmetries() {
+        let dir = table_dir("symmetries");
+        for name in ["KRvKN", "KRvK", "KNvK", "KPvKP", "KPvK"] {
+            // Any values will do, as long as they vary with the index
+            write_table(&dir.join(format!("{}.rtbw", name)), name, Metric::Wdl, 0, |side, file, idx| {
+                ((idx ^ ((side as u64) << 20) ^ ((file as u64) << 24)).wrapping_mul(0x9E37_79B9_7F4A_7C15) >> 59) as u16 % 5
+            });
+        }
+        let prober = SyzygyProber::open(dir.to_str().unwrap()).unwrap();
+
+        let mut values = Vec::new();
+        for fen in [
+            "8/8/3k4/8/2n5/8/5R2/1K6 w - - 0 1",
+            "8/8/3k4/8/2n5/8/5R2/1K6 b - - 0 1",
+            "k7/8/8/3n4/8/8/8/1R4K1 w - - 0 1",
+            "7k/6n1/8/8/3R4/8/1K6/8 b - - 0 1",
+            "8/1k6/8/8/8/8/6K1/3R2n1 w - - 0 1",
+            "8/4k3/8/2p5/8/8/1P6/6K1 w - - 0 1",
+            "8/2k5/3p4/8/8/5P2/8/K7 b - - 0 1",
+            "8/8/8/8/1k6/8/P6p/K7 w - - 0 1",
+        ] {
+            let board = Board::from_str(fen).unwrap();
+            let value = prober.probe_wdl(&board);
+            assert!(value.is_some(), "{}", fen);
+            for other in [board.flip_horizontal(), board.mirror(), board.mirror().flip_horizontal()] {
+                assert_eq!(prober.probe_wdl(&other), value, "{} vs {}", fen, other);
+            }
+            values.push(value);
+        }
+        values.dedup();
+        assert!(values.len() > 2);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_background_loading() {
+        let dir = table_dir("background");
+        write_wdl(&dir, "KRvK", [WIN, LOSS]);
+        write_table(&dir.join("KRvK.rtbz"), "KRvK", Metric::Dtz, table::FLAG_WIN_PLIES, |_, _, _| 5);
+        // Named and sized like a table, but not one
+        std::fs::write(dir.join("KQvK.rtbw"), [0; 80]).unwrap();
+        let prober = SyzygyProber::open(dir.to_str().unwrap()).unwrap();
+
+        let (done, report) = std::sync::mpsc::channel();
+        let shared = SharedProber::load(prober, move |report| done.send(report).unwrap());
+        let report = report.recv().unwrap();
+        assert!(report.starts_with("found 1 WDL / 1 DTZ tables, 1 failed to open"), "{}", report);
+        assert!(report.contains("KQvK.rtbw"), "{}", report);
+        let prober = shared.get().expect("loaded before reporting");
+        assert_eq!(probe(prober, "4k3/8/8/8/8/8/8/R3K3 w - - 0 1"), (Some(20_000), Some(6)));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    /// Real tables from `SYZYGY_PATH`, which must be set and hold tables
+    fn real_tables() -> SyzygyProber {
+        let path = std::env::var("SYZYGY_PATH").expect("SYZYGY_PATH must name a directory of Syzygy tables");
+        SyzygyProber::open(&path).unwrap_or_else(|err| panic!("SYZYGY_PATH={}: {}", path, err))
+    }
+
+    /// Known answers from the published 3-4-5 piece tables: the tests above only read tables
+    /// written by our own encoder, so these catch a misreading of the real format. Needs the
+    /// full set (WDL and DTZ), which isn't in the repository, so it only runs with `--ignored`.
+    #[test]
+    #[ignore = "needs Syzygy tables in SYZYGY_PATH"]
+    fn test_probe_real_tables() {
+        let prober = real_tables();
+        for (fen, wdl, dtz) in [
+            // Mate in one is one ply to zeroing
+            ("k7/8/1K6/8/8/8/7Q/8 w - - 0 1", 20_000, Some(1)),
+            ("k7/8/1K6/8/8/8/8/7R w - - 0 1", 20_000, Some(1)),
+            // Promoting is the zeroing move
+            ("8/4P3/8/8/8/8/k7/4K3 w - - 0 1", 20_000, Some(1)),
+            // Opposition: the side to move loses it
+            ("4k3/8/4K3/4P3/8/8/8/8 b - - 0 1", -20_000, None),
+            ("4k3/8/4K3/4P3/8/8/8/8 w - - 0 1", 0, Some(0)),
+            // Kxe2, found by the capture search
+            ("8/8/8/8/8/8/3kP3/7K b - - 0 1", 0, Some(0)),
+            // Rook pawn with the king in the corner
+            ("k7/8/K7/P7/8/8/8/8 w - - 0 1", 0, Some(0)),
+            ("8/8/8/4k3/8/8/8/KN6 w - - 0 1", 0, Some(0)),
+            ("8/8/8/4k3/8/8/8/KB6 w - - 0 1", 0, Some(0)),
+            ("8/8/8/4k3/8/8/8/KNN5 w - - 0 1", 0, Some(0)),
+            ("8/8/8/4k3/8/8/8/KBN5 w - - 0 1", 20_000, None),
+            // Lucena
+            ("1K1k4/1P6/8/8/8/8/r7/2R5 w - - 0 1", 20_000, None),
+        ] {
+            assert_eq!(probe(&prober, fen).0, Some(wdl), "WDL of {}", fen);
+            if let Some(dtz) = dtz {
+                assert_eq!(probe(&prober, fen).1, Some(dtz), "DTZ of {}", fen);
+            }
+        }
+
+        // DTZ agrees with WDL and stays within the longest published values: KQvK mates in
+        // at most 10 moves and KBNvK in 33, with no zeroing move on the way
+        let (wdl, dtz) = probe(&prober, "8/8/8/4k3/8/8/8/4K2Q b - - 0 1");
+        assert_eq!(wdl, Some(-20_000));
+        assert!(dtz.is_some_and(|dtz| (-20..0).contains(&dtz)), "{:?}", dtz);
+        let (wdl, dtz) = probe(&prober, "8/8/8/4k3/8/8/8/KBN5 w - - 0 1");
+        assert_eq!(wdl, Some(20_000));
+        assert!(dtz.is_some_and(|dtz| (1..=66).contains(&dtz)), "{:?}", dtz);
+    }
+
+    #[test]
+    fn test_syzygy_prober_too_many_pieces() {
+        let dir = table_dir("too-many");
+        write_wdl(&dir, "KRvK", [WIN, LOSS]);
+        let prober = SyzygyProber::open(dir.to_str().unwrap()).unwrap();
+        assert!(prober.probe_wdl(&crate::board::Board::default()).is_none());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
+
+// The reader follows the Syzygy format directly: each table file is opened on its first probe
+// (or all at once by `SharedProber`, off the UCI thread, since a full 7-piece set has
+// thousands of files) and read with positioned reads, so search threads share one handle without locking and
+// without mapping multi-gigabyte files. Positions are encoded from our own bitboards (a1 = 0,
+// as in the format), and probing resolves captures with our move generator before trusting a
+// table value, since tables store arbitrary values where a capture is at least as good.
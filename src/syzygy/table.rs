@@ -0,0 +1,1382 @@
+use std::fs::File;
+use std::io;
+use std::path::Path;
+
+use super::Wdl;
+use crate::board::{Board, Color, Piece};
+
+/// First bytes of WDL (.rtbw) and DTZ (.rtbz) files
+const WDL_MAGIC: [u8; 4] = [0x71, 0xe8, 0x23, 0x5d];
+const DTZ_MAGIC: [u8; 4] = [0xd7, 0x66, 0x0c, 0xa5];
+
+/// Most pieces a Syzygy table can hold
+const TB_PIECES: usize = 7;
+
+/// Largest compressed block we accept
+const MAX_BLOCK_SIZE: usize = 1024;
+
+/// Layout byte: the table has a subtable per side to move / per leading pawn file
+const LAYOUT_SPLIT: u8 = 1;
+const LAYOUT_HAS_PAWNS: u8 = 2;
+
+/// Subtable flags
+const FLAG_STM: u8 = 1;
+const FLAG_MAPPED: u8 = 2;
+pub(super) const FLAG_WIN_PLIES: u8 = 4;
+const FLAG_LOSS_PLIES: u8 = 8;
+const FLAG_WIDE_DTZ: u8 = 16;
+const FLAG_SINGLE_VALUE: u8 = 128;
+
+/// Piece counts as `[color][piece]`, the key tables are filed under
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(super) struct Material([[u8; 6]; 2]);
+
+impl Material {
+    pub(super) fn from_board(board: &Board) -> Material {
+        let mut counts = [[0; 6]; 2];
+        for color in [Color::White, Color::Black] {
+            for piece in Piece::ALL {
+                counts[color.to_index()][piece.to_index()] = (board.pieces(piece) & board.color_combined(color)).popcnt() as u8;
+            }
+        }
+        Material(counts)
+    }
+
+    fn from_pieces(pieces: &[(Color, Piece)]) -> Material {
+        let mut counts = [[0; 6]; 2];
+        for &(color, piece) in pieces {
+            counts[color.to_index()][piece.to_index()] += 1;
+        }
+        Material(counts)
+    }
+
+    /// From a table name like `KRPvKR`; None unless both sides have a king and at most
+    /// `TB_PIECES` pieces are named
+    #[cfg_attr(target_arch = "wasm32", allow(dead_code))]
+    pub(super) fn from_name(name: &str) -> Option<Material> {
+        let (white, black) = name.split_once('v')?;
+        let mut counts = [[0; 6]; 2];
+        for (side, pieces) in [white, black].into_iter().enumerate() {
+            for c in pieces.chars() {
+                let piece = match c {
+                    'P' => Piece::Pawn,
+                    'N' => Piece::Knight,
+                    'B' => Piece::Bishop,
+                    'R' => Piece::Rook,
+                    'Q' => Piece::Queen,
+                    'K' => Piece::King,
+                    _ => return None,
+                };
+                counts[side][piece.to_index()] += 1;
+            }
+        }
+        let material = Material(counts);
+        let kings = Piece::King.to_index();
+        (counts[0][kings] == 1 && counts[1][kings] == 1 && material.count() <= TB_PIECES).then_some(material)
+    }
+
+    pub(super) fn count(&self) -> usize {
+        self.0.iter().flatten().map(|&n| n as usize).sum()
+    }
+
+    fn has_pawns(&self) -> bool {
+        self.0.iter().any(|side| side[Piece::Pawn.to_index()] > 0)
+    }
+
+    fn both_have_pawns(&self) -> bool {
+        self.0.iter().all(|side| side[Piece::Pawn.to_index()] > 0)
+    }
+
+    fn is_symmetric(&self) -> bool {
+        self.0[0] == self.0[1]
+    }
+
+    /// Piece types (per colour) present exactly once
+    fn unique_pieces(&self) -> usize {
+        self.0.iter().flatten().filter(|&&n| n == 1).count()
+    }
+
+    /// Smallest count above one of any piece type, 0 if none
+    fn min_like_man(&self) -> usize {
+        self.0.iter().flatten().filter(|&&n| n >= 2).min().map_or(0, |&n| n as usize)
+    }
+
+    fn swapped(self) -> Material {
+        Material([self.0[1], self.0[0]])
+    }
+
+    /// Stronger side first, as in table file names: more pieces, then more kings, queens,
+    /// rooks, bishops, knights and pawns
+    pub(super) fn normalized(self) -> Material {
+        let strength = |side: &[u8; 6]| {
+            let total: u8 = side.iter().sum();
+            (total, side[5], side[4], side[3], side[2], side[1], side[0])
+        };
+        if strength(&self.0[1]) > strength(&self.0[0]) { self.swapped() } else { self }
+    }
+}
+
+/// Table name, `KQvKR`
+impl std::fmt::Display for Material {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        for (i, side) in self.0.iter().enumerate() {
+            if i == 1 {
+                write!(f, "v")?;
+            }
+            for (piece, c) in [(5, 'K'), (4, 'Q'), (3, 'R'), (2, 'B'), (1, 'N'), (0, 'P')] {
+                for _ in 0..side[piece] {
+                    write!(f, "{}", c)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+const fn binomial(mut n: u64, k: u64) -> u64 {
+    if k > n {
+        return 0;
+    }
+    if k > n - k {
+        return binomial(n, n - k);
+    }
+    let (mut r, mut d) = (1, 1);
+    while d <= k {
+        r = r * n / d;
+        n -= 1;
+        d += 1;
+    }
+    r
+}
+
+/// Maps squares into the a1-d1-d4 triangle
+#[rustfmt::skip]
+const TRIANGLE: [u64; 64] = [
+    6, 0, 1, 2, 2, 1, 0, 6,
+    0, 7, 3, 4, 4, 3, 7, 0,
+    1, 3, 8, 5, 5, 8, 3, 1,
+    2, 4, 5, 9, 9, 5, 4, 2,
+    2, 4, 5, 9, 9, 5, 4, 2,
+    1, 3, 8, 5, 5, 8, 3, 1,
+    0, 7, 3, 4, 4, 3, 7, 0,
+    6, 0, 1, 2, 2, 1, 0, 6,
+];
+
+/// A square for each `TRIANGLE` value
+const INV_TRIANGLE: [usize; 10] = [1, 2, 3, 10, 11, 19, 0, 9, 18, 27];
+
+/// Maps the b1-h1-h7 triangle to 0..=27
+#[rustfmt::skip]
+const LOWER: [u64; 64] = [
+    28,  0,  1,  2,  3,  4,  5,  6,
+     0, 29,  7,  8,  9, 10, 11, 12,
+     1,  7, 30, 13, 14, 15, 16, 17,
+     2,  8, 13, 31, 18, 19, 20, 21,
+     3,  9, 14, 18, 32, 22, 23, 24,
+     4, 10, 15, 19, 22, 33, 25, 26,
+     5, 11, 16, 20, 23, 25, 34, 27,
+     6, 12, 17, 21, 24, 26, 27, 35,
+];
+
+/// Square order for groups of identical leading pieces
+#[rustfmt::skip]
+const MULT_TWIST: [u64; 64] = [
+    15, 63, 55, 47, 40, 48, 56, 12,
+    62, 11, 39, 31, 24, 32,  8, 57,
+    54, 38,  7, 23, 16,  4, 33, 49,
+    46, 30, 22,  3,  0, 17, 25, 41,
+    45, 29, 21,  2,  1, 18, 26, 42,
+    53, 37,  6, 20, 19,  5, 34, 50,
+    61, 10, 36, 28, 27, 35,  9, 58,
+    14, 60, 52, 44, 43, 51, 59, 13,
+];
+
+/// Squares that can't be indexed
+const Z0: u64 = u64::MAX;
+
+/// The 462 placements of two kings that don't touch, first king in the a1-d1-d4 triangle
+#[rustfmt::skip]
+const KK_IDX: [[u64; 64]; 10] = [[
+     Z0,  Z0,  Z0,   0,   1,   2,   3,   4,
+     Z0,  Z0,  Z0,   5,   6,   7,   8,   9,
+     10,  11,  12,  13,  14,  15,  16,  17,
+     18,  19,  20,  21,  22,  23,  24,  25,
+     26,  27,  28,  29,  30,  31,  32,  33,
+     34,  35,  36,  37,  38,  39,  40,  41,
+     42,  43,  44,  45,  46,  47,  48,  49,
+     50,  51,  52,  53,  54,  55,  56,  57,
+], [
+     58,  Z0,  Z0,  Z0,  59,  60,  61,  62,
+     63,  Z0,  Z0,  Z0,  64,  65,  66,  67,
+     68,  69,  70,  71,  72,  73,  74,  75,
+     76,  77,  78,  79,  80,  81,  82,  83,
+     84,  85,  86,  87,  88,  89,  90,  91,
+     92,  93,  94,  95,  96,  97,  98,  99,
+    100, 101, 102, 103, 104, 105, 106, 107,
+    108, 109, 110, 111, 112, 113, 114, 115,
+], [
+    116, 117,  Z0,  Z0,  Z0, 118, 119, 120,
+    121, 122,  Z0,  Z0,  Z0, 123, 124, 125,
+    126, 127, 128, 129, 130, 131, 132, 133,
+    134, 135, 136, 137, 138, 139, 140, 141,
+    142, 143, 144, 145, 146, 147, 148, 149,
+    150, 151, 152, 153, 154, 155, 156, 157,
+    158, 159, 160, 161, 162, 163, 164, 165,
+    166, 167, 168, 169, 170, 171, 172, 173,
+], [
+    174,  Z0,  Z0,  Z0, 175, 176, 177, 178,
+    179,  Z0,  Z0,  Z0, 180, 181, 182, 183,
+    184,  Z0,  Z0,  Z0, 185, 186, 187, 188,
+    189, 190, 191, 192, 193, 194, 195, 196,
+    197, 198, 199, 200, 201, 202, 203, 204,
+    205, 206, 207, 208, 209, 210, 211, 212,
+    213, 214, 215, 216, 217, 218, 219, 220,
+    221, 222, 223, 224, 225, 226, 227, 228,
+], [
+    229, 230,  Z0,  Z0,  Z0, 231, 232, 233,
+    234, 235,  Z0,  Z0,  Z0, 236, 237, 238,
+    239, 240,  Z0,  Z0,  Z0, 241, 242, 243,
+    244, 245, 246, 247, 248, 249, 250, 251,
+    252, 253, 254, 255, 256, 257, 258, 259,
+    260, 261, 262, 263, 264, 265, 266, 267,
+    268, 269, 270, 271, 272, 273, 274, 275,
+    276, 277, 278, 279, 280, 281, 282, 283,
+], [
+    284, 285, 286, 287, 288, 289, 290, 291,
+    292, 293,  Z0,  Z0,  Z0, 294, 295, 296,
+    297, 298,  Z0,  Z0,  Z0, 299, 300, 301,
+    302, 303,  Z0,  Z0,  Z0, 304, 305, 306,
+    307, 308, 309, 310, 311, 312, 313, 314,
+    315, 316, 317, 318, 319, 320, 321, 322,
+    323, 324, 325, 326, 327, 328, 329, 330,
+    331, 332, 333, 334, 335, 336, 337, 338,
+], [
+     Z0,  Z0, 339, 340, 341, 342, 343, 344,
+     Z0,  Z0, 345, 346, 347, 348, 349, 350,
+     Z0,  Z0, 441, 351, 352, 353, 354, 355,
+     Z0,  Z0,  Z0, 442, 356, 357, 358, 359,
+     Z0,  Z0,  Z0,  Z0, 443, 360, 361, 362,
+     Z0,  Z0,  Z0,  Z0,  Z0, 444, 363, 364,
+     Z0,  Z0,  Z0,  Z0,  Z0,  Z0, 445, 365,
+     Z0,  Z0,  Z0,  Z0,  Z0,  Z0,  Z0, 446,
+], [
+     Z0,  Z0,  Z0, 366, 367, 368, 369, 370,
+     Z0,  Z0,  Z0, 371, 372, 373, 374, 375,
+     Z0,  Z0,  Z0, 376, 377, 378, 379, 380,
+     Z0,  Z0,  Z0, 447, 381, 382, 383, 384,
+     Z0,  Z0,  Z0,  Z0, 448, 385, 386, 387,
+     Z0,  Z0,  Z0,  Z0,  Z0, 449, 388, 389,
+     Z0,  Z0,  Z0,  Z0,  Z0,  Z0, 450, 390,
+     Z0,  Z0,  Z0,  Z0,  Z0,  Z0,  Z0, 451,
+], [
+    452, 391, 392, 393, 394, 395, 396, 397,
+     Z0,  Z0,  Z0,  Z0, 398, 399, 400, 401,
+     Z0,  Z0,  Z0,  Z0, 402, 403, 404, 405,
+     Z0,  Z0,  Z0,  Z0, 406, 407, 408, 409,
+     Z0,  Z0,  Z0,  Z0, 453, 410, 411, 412,
+     Z0,  Z0,  Z0,  Z0,  Z0, 454, 413, 414,
+     Z0,  Z0,  Z0,  Z0,  Z0,  Z0, 455, 415,
+     Z0,  Z0,  Z0,  Z0,  Z0,  Z0,  Z0, 456,
+], [
+    457, 416, 417, 418, 419, 420, 421, 422,
+     Z0, 458, 423, 424, 425, 426, 427, 428,
+     Z0,  Z0,  Z0,  Z0,  Z0, 429, 430, 431,
+     Z0,  Z0,  Z0,  Z0,  Z0, 432, 433, 434,
+     Z0,  Z0,  Z0,  Z0,  Z0, 435, 436, 437,
+     Z0,  Z0,  Z0,  Z0,  Z0, 459, 438, 439,
+     Z0,  Z0,  Z0,  Z0,  Z0,  Z0, 460, 440,
+     Z0,  Z0,  Z0,  Z0,  Z0,  Z0,  Z0, 461,
+]];
+
+/// The 278 placements of a leading pair of identical pieces
+#[rustfmt::skip]
+const PP_IDX: [[u64; 64]; 10] = [[
+      0,  Z0,   1,   2,   3,   4,   5,   6,
+      7,   8,   9,  10,  11,  12,  13,  14,
+     15,  16,  17,  18,  19,  20,  21,  22,
+     23,  24,  25,  26,  27,  28,  29,  30,
+     31,  32,  33,  34,  35,  36,  37,  38,
+     39,  40,  41,  42,  43,  44,  45,  46,
+     Z0,  47,  48,  49,  50,  51,  52,  53,
+     54,  55,  56,  57,  58,  59,  60,  61,
+], [
+     62,  Z0,  Z0,  63,  64,  65,  Z0,  66,
+     Z0,  67,  68,  69,  70,  71,  72,  Z0,
+     73,  74,  75,  76,  77,  78,  79,  80,
+     81,  82,  83,  84,  85,  86,  87,  88,
+     89,  90,  91,  92,  93,  94,  95,  96,
+     Z0,  97,  98,  99, 100, 101, 102, 103,
+     Z0, 104, 105, 106, 107, 108, 109,  Z0,
+    110,  Z0, 111, 112, 113, 114,  Z0, 115,
+], [
+    116,  Z0,  Z0,  Z0, 117,  Z0,  Z0, 118,
+     Z0, 119, 120, 121, 122, 123, 124,  Z0,
+     Z0, 125, 126, 127, 128, 129, 130,  Z0,
+    131, 132, 133, 134, 135, 136, 137, 138,
+     Z0, 139, 140, 141, 142, 143, 144, 145,
+     Z0, 146, 147, 148, 149, 150, 151,  Z0,
+     Z0, 152, 153, 154, 155, 156, 157,  Z0,
+    158,  Z0,  Z0, 159, 160,  Z0,  Z0, 161,
+], [
+    162,  Z0,  Z0,  Z0,  Z0,  Z0,  Z0, 163,
+     Z0, 164,  Z0, 165, 166, 167, 168,  Z0,
+     Z0, 169, 170, 171, 172, 173, 174,  Z0,
+     Z0, 175, 176, 177, 178, 179, 180,  Z0,
+     Z0, 181, 182, 183, 184, 185, 186,  Z0,
+     Z0,  Z0, 187, 188, 189, 190, 191,  Z0,
+     Z0, 192, 193, 194, 195, 196, 197,  Z0,
+    198,  Z0,  Z0,  Z0,  Z0,  Z0,  Z0, 199,
+], [
+    200,  Z0,  Z0,  Z0,  Z0,  Z0,  Z0, 201,
+     Z0, 202,  Z0,  Z0, 203,  Z0, 204,  Z0,
+     Z0,  Z0, 205, 206, 207, 208,  Z0,  Z0,
+     Z0, 209, 210, 211, 212, 213, 214,  Z0,
+     Z0,  Z0, 215, 216, 217, 218, 219,  Z0,
+     Z0,  Z0, 220, 221, 222, 223,  Z0,  Z0,
+     Z0, 224,  Z0, 225, 226,  Z0, 227,  Z0,
+    228,  Z0,  Z0,  Z0,  Z0,  Z0,  Z0, 229,
+], [
+    230,  Z0,  Z0,  Z0,  Z0,  Z0,  Z0, 231,
+     Z0, 232,  Z0,  Z0,  Z0,  Z0, 233,  Z0,
+     Z0,  Z0, 234,  Z0, 235, 236,  Z0,  Z0,
+     Z0,  Z0, 237, 238, 239, 240,  Z0,  Z0,
+     Z0,  Z0,  Z0, 241, 242, 243,  Z0,  Z0,
+     Z0,  Z0, 244, 245, 246, 247,  Z0,  Z0,
+     Z0, 248,  Z0,  Z0,  Z0,  Z0, 249,  Z0,
+    250,  Z0,  Z0,  Z0,  Z0,  Z0,  Z0, 251,
+], [
+     Z0,  Z0,  Z0,  Z0,  Z0,  Z0,  Z0, 259,
+     Z0, 252,  Z0,  Z0,  Z0,  Z0, 260,  Z0,
+     Z0,  Z0, 253,  Z0,  Z0, 261,  Z0,  Z0,
+     Z0,  Z0,  Z0, 254, 262,  Z0,  Z0,  Z0,
+     Z0,  Z0,  Z0,  Z0, 255,  Z0,  Z0,  Z0,
+     Z0,  Z0,  Z0,  Z0,  Z0, 256,  Z0,  Z0,
+     Z0,  Z0,  Z0,  Z0,  Z0,  Z0, 257,  Z0,
+     Z0,  Z0,  Z0,  Z0,  Z0,  Z0,  Z0, 258,
+], [
+     Z0,  Z0,  Z0,  Z0,  Z0,  Z0,  Z0,  Z0,
+     Z0,  Z0,  Z0,  Z0,  Z0,  Z0, 268,  Z0,
+     Z0,  Z0, 263,  Z0,  Z0, 269,  Z0,  Z0,
+     Z0,  Z0,  Z0, 264, 270,  Z0,  Z0,  Z0,
+     Z0,  Z0,  Z0,  Z0, 265,  Z0,  Z0,  Z0,
+     Z0,  Z0,  Z0,  Z0,  Z0, 266,  Z0,  Z0,
+     Z0,  Z0,  Z0,  Z0,  Z0,  Z0, 267,  Z0,
+     Z0,  Z0,  Z0,  Z0,  Z0,  Z0,  Z0,  Z0,
+], [
+     Z0,  Z0,  Z0,  Z0,  Z0,  Z0,  Z0,  Z0,
+     Z0,  Z0,  Z0,  Z0,  Z0,  Z0,  Z0,  Z0,
+     Z0,  Z0,  Z0,  Z0,  Z0, 274,  Z0,  Z0,
+     Z0,  Z0,  Z0, 271, 275,  Z0,  Z0,  Z0,
+     Z0,  Z0,  Z0,  Z0, 272,  Z0,  Z0,  Z0,
+     Z0,  Z0,  Z0,  Z0,  Z0, 273,  Z0,  Z0,
+     Z0,  Z0,  Z0,  Z0,  Z0,  Z0,  Z0,  Z0,
+     Z0,  Z0,  Z0,  Z0,  Z0,  Z0,  Z0,  Z0,
+], [
+     Z0,  Z0,  Z0,  Z0,  Z0,  Z0,  Z0,  Z0,
+     Z0,  Z0,  Z0,  Z0,  Z0,  Z0,  Z0,  Z0,
+     Z0,  Z0,  Z0,  Z0,  Z0,  Z0,  Z0,  Z0,
+     Z0,  Z0,  Z0,  Z0, 277,  Z0,  Z0,  Z0,
+     Z0,  Z0,  Z0,  Z0, 276,  Z0,  Z0,  Z0,
+     Z0,  Z0,  Z0,  Z0,  Z0,  Z0,  Z0,  Z0,
+     Z0,  Z0,  Z0,  Z0,  Z0,  Z0,  Z0,  Z0,
+     Z0,  Z0,  Z0,  Z0,  Z0,  Z0,  Z0,  Z0,
+]];
+
+/// The a7-a5-c5 triangle
+const TEST45: u64 = 0x1_0307_0000_0000;
+
+/// Index tables derived from the ones above
+struct Consts {
+    mult_idx: [[u64; 10]; 5],
+    mult_factor: [u64; 5],
+    map_pawns: [u64; 64],
+    lead_pawn_idx: [[u64; 64]; 6],
+    lead_pawns_size: [[u64; 4]; 6],
+}
+
+const CONSTS: Consts = Consts::new();
+
+impl Consts {
+    const fn new() -> Consts {
+        let mut mult_idx = [[0; 10]; 5];
+        let mut mult_factor = [0; 5];
+        let mut i = 0;
+        while i < 5 {
+            let mut s = 0;
+            let mut j = 0;
+            while j < 10 {
+                mult_idx[i][j] = s;
+                s += if i == 0 { 1 } else { binomial(MULT_TWIST[INV_TRIANGLE[j]], i as u64) };
+                j += 1;
+            }
+            mult_factor[i] = s;
+            i += 1;
+        }
+
+        let mut available_squares = 48;
+        let mut map_pawns = [0; 64];
+        let mut lead_pawn_idx = [[0; 64]; 6];
+        let mut lead_pawns_size = [[0; 4]; 6];
+        let mut lead_pawns = 1;
+        while lead_pawns <= 5 {
+            let mut file = 0;
+            while file < 4 {
+                let mut idx = 0;
+                let mut rank = 1;
+                while rank < 7 {
+                    let sq = file + 8 * rank;
+                    if lead_pawns == 1 {
+                        available_squares -= 1;
+                        map_pawns[sq] = available_squares;
+                        available_squares -= 1;
+                        map_pawns[sq ^ 7] = available_squares;
+                    }
+                    lead_pawn_idx[lead_pawns][sq] = idx;
+                    idx += binomial(map_pawns[sq], lead_pawns as u64 - 1);
+                    rank += 1;
+                }
+                lead_pawns_size[lead_pawns][file] = idx;
+                file += 1;
+            }
+            lead_pawns += 1;
+        }
+
+        Consts { mult_idx, mult_factor, map_pawns, lead_pawn_idx, lead_pawns_size }
+    }
+}
+
+fn file_of(sq: usize) -> usize {
+    sq & 7
+}
+
+fn rank_of(sq: usize) -> usize {
+    sq >> 3
+}
+
+fn flip_vertical(sq: usize) -> usize {
+    sq ^ 56
+}
+
+fn flip_horizontal(sq: usize) -> usize {
+    sq ^ 7
+}
+
+/// Mirror in the a1-h8 diagonal
+fn flip_diagonal(sq: usize) -> usize {
+    ((sq >> 3) | (sq << 3)) & 63
+}
+
+fn offdiag(sq: usize) -> bool {
+    file_of(sq) != rank_of(sq)
+}
+
+/// Which metric a table stores
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Metric {
+    Wdl,
+    Dtz,
+}
+
+/// A table file read with positioned reads, so probes never share a cursor
+struct TableFile(File);
+
+impl TableFile {
+    fn read(&self, buf: &mut [u8], offset: u64) -> Result<(), String> {
+        read_at(&self.0, buf, offset).map_err(|e| format!("read error at {}: {}", offset, e))
+    }
+
+    fn u8(&self, offset: u64) -> Result<u8, String> {
+        let mut buf = [0; 1];
+        self.read(&mut buf, offset)?;
+        Ok(buf[0])
+    }
+
+    fn u16(&self, offset: u64) -> Result<u16, String> {
+        let mut buf = [0; 2];
+        self.read(&mut buf, offset)?;
+        Ok(u16::from_le_bytes(buf))
+    }
+}
+
+#[cfg(unix)]
+fn read_at(file: &File, buf: &mut [u8], offset: u64) -> io::Result<()> {
+    std::os::unix::fs::FileExt::read_exact_at(file, buf, offset)
+}
+
+#[cfg(windows)]
+fn read_at(file: &File, mut buf: &mut [u8], mut offset: u64) -> io::Result<()> {
+    use std::os::windows::fs::FileExt;
+    while !buf.is_empty() {
+        match file.seek_read(buf, offset)? {
+            0 => return Err(io::ErrorKind::UnexpectedEof.into()),
+            n => {
+                buf = &mut buf[n..];
+                offset += n as u64;
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(any(unix, windows)))]
+fn read_at(_file: &File, _buf: &mut [u8], _offset: u64) -> io::Result<()> {
+    Err(io::ErrorKind::Unsupported.into())
+}
+
+/// How a subtable's pieces are split into groups and each group's place in the index
+#[derive(Debug)]
+struct GroupData {
+    pieces: Vec<(Color, Piece)>,
+    lens: Vec<usize>,
+    /// Multiplier of each group's index; the last entry is the subtable size
+    factors: Vec<u64>,
+}
+
+impl GroupData {
+    fn new(pieces: Vec<(Color, Piece)>, order: [u8; 2], file: usize) -> Result<GroupData, String> {
+        let material = Material::from_pieces(&pieces);
+
+        // Pawnless tables lead with three unique pieces if there are any, else the kings or
+        // the smallest group of identical pieces; pawn tables lead with the leading pawns
+        let first_len = if material.has_pawns() {
+            0
+        } else if material.unique_pieces() >= 3 {
+            3
+        } else if material.unique_pieces() == 2 {
+            2
+        } else {
+            material.min_like_man()
+        };
+        let mut lens = Vec::new();
+        if first_len > 0 {
+            lens.push(first_len);
+        }
+        lens.extend(pieces.get(first_len..).ok_or("too few pieces")?.chunk_by(|a, b| a == b).map(<[_]>::len));
+
+        let pp = material.both_have_pawns();
+        let mut factors = vec![0; lens.len() + 1];
+        let mut free_squares = 64 - lens[0] - if pp { lens[1] } else { 0 };
+        let mut next = if pp { 2 } else { 1 };
+        let mut idx = 1u64;
+        let mut k = 0;
+        while next < lens.len() || k == order[0] || k == order[1] {
+            if k == order[0] {
+                factors[0] = idx;
+                idx *= if material.has_pawns() {
+                    CONSTS.lead_pawns_size[lens[0]][file]
+                } else if material.unique_pieces() >= 3 {
+                    31_332
+                } else if material.unique_pieces() == 2 {
+                    462
+                } else if material.min_like_man() == 2 {
+                    278
+                } else {
+                    CONSTS.mult_factor[material.min_like_man() - 1]
+                };
+            } else if k == order[1] {
+                factors[1] = idx;
+                idx *= binomial(48 - lens[0] as u64, lens[1] as u64);
+            } else {
+                factors[next] = idx;
+                idx *= binomial(free_squares as u64, lens[next] as u64);
+                free_squares -= lens[next];
+                next += 1;
+            }
+            k += 1;
+            if k > 15 {
+                return Err("bad piece group order".to_string());
+            }
+        }
+        factors[lens.len()] = idx;
+
+        Ok(GroupData { pieces, lens, factors })
+    }
+}
+
+/// Huffman tree node: a literal value, or a pair of symbols that follow each other
+#[derive(Debug, Clone, Copy, Default)]
+struct Symbol {
+    lr: [u8; 3],
+    /// Values the symbol stands for, minus one
+    len: u8,
+}
+
+impl Symbol {
+    fn left(&self) -> u16 {
+        (u16::from(self.lr[1] & 0xf) << 8) | u16::from(self.lr[0])
+    }
+
+    fn right(&self) -> u16 {
+        (u16::from(self.lr[2]) << 4) | (u16::from(self.lr[1]) >> 4)
+    }
+}
+
+/// DTZ values remapped per WDL outcome, with offsets for win, loss, cursed win, blessed loss
+#[derive(Debug)]
+struct DtzMap {
+    ptr: u64,
+    by_wdl: [u16; 4],
+    wide: bool,
+}
+
+/// One compressed subtable: a side to move (WDL) and/or a leading pawn file
+#[derive(Debug)]
+struct PairsData {
+    flags: u8,
+    groups: GroupData,
+    block_size: u32,
+    /// Values per sparse index entry
+    span: u32,
+    blocks_num: u32,
+    /// Shortest Huffman code in bits; the value itself for single-value subtables
+    min_symlen: u8,
+    /// Offset of the first symbol of each code length
+    lowest_sym: u64,
+    /// Smallest code of each length, left-aligned in 64 bits
+    base: Vec<u64>,
+    symbols: Vec<Symbol>,
+    sparse_index: u64,
+    sparse_index_size: u32,
+    block_lengths: u64,
+    block_length_size: u32,
+    data: u64,
+    dtz_map: Option<DtzMap>,
+}
+
+impl PairsData {
+    /// Parse the subtable header at `ptr`; returns it and where the next header starts
+    fn parse(file: &TableFile, mut ptr: u64, groups: GroupData, metric: Metric) -> Result<(PairsData, u64), String> {
+        let flags = file.u8(ptr)?;
+        let mut data = PairsData {
+            flags,
+            groups,
+            block_size: 0,
+            span: 0,
+            blocks_num: 0,
+            min_symlen: 0,
+            lowest_sym: 0,
+            base: Vec::new(),
+            symbols: Vec::new(),
+            sparse_index: 0,
+            sparse_index_size: 0,
+            block_lengths: 0,
+            block_length_size: 0,
+            data: 0,
+            dtz_map: None,
+        };
+        if flags & FLAG_SINGLE_VALUE != 0 {
+            data.min_symlen = if metric == Metric::Wdl { file.u8(ptr + 1)? } else { 0 };
+            return Ok((data, ptr + 2));
+        }
+
+        let mut header = [0; 10];
+        file.read(&mut header, ptr)?;
+        let tb_size = data.groups.factors[data.groups.lens.len()];
+        data.block_size = 1u32.checked_shl(header[1].into()).filter(|&s| s as usize <= MAX_BLOCK_SIZE).ok_or("bad block size")?;
+        data.span = 1u32.checked_shl(header[2].into()).ok_or("bad span")?;
+        data.sparse_index_size = tb_size.div_ceil(u64::from(data.span)) as u32;
+        data.blocks_num = u32::from_le_bytes([header[4], header[5], header[6], header[7]]);
+        data.block_length_size = data.blocks_num.checked_add(header[3].into()).ok_or("bad block count")?;
+        let (max_symlen, min_symlen) = (header[8], header[9]);
+        if min_symlen == 0 || max_symlen > 32 || min_symlen > max_symlen {
+            return Err("bad symbol lengths".to_string());
+        }
+        data.min_symlen = min_symlen;
+        let h = usize::from(max_symlen - min_symlen + 1);
+        data.lowest_sym = ptr + 10;
+
+        let mut base = vec![0u64; h];
+        for i in (0..h - 1).rev() {
+            let ptr = data.lowest_sym + 2 * i as u64;
+            base[i] = (base[i + 1] + u64::from(file.u16(ptr)?))
+                .checked_sub(u64::from(file.u16(ptr + 2)?))
+                .ok_or("bad Huffman code")?
+                / 2;
+            if base[i] * 2 < base[i + 1] {
+                return Err("bad Huffman code".to_string());
+            }
+        }
+        for (i, b) in base.iter_mut().enumerate() {
+            *b = b.checked_shl(64 - (u32::from(min_symlen) + i as u32)).ok_or("bad Huffman code")?;
+        }
+        data.base = base;
+
+        ptr += 10 + 2 * h as u64;
+        let sym_count = usize::from(file.u16(ptr)?);
+        ptr += 2;
+        let mut raw = vec![0; 3 * sym_count];
+        file.read(&mut raw, ptr)?;
+        data.symbols = raw.chunks_exact(3).map(|lr| Symbol { lr: [lr[0], lr[1], lr[2]], len: 0 }).collect();
+        let mut visited = vec![false; sym_count];
+        for sym in 0..sym_count {
+            symbol_len(&mut data.symbols, &mut visited, sym, 16)?;
+        }
+        ptr += 3 * sym_count as u64 + (sym_count as u64 & 1);
+
+        Ok((data, ptr))
+    }
+}
+
+/// Fill in `symbols[sym].len` (and its children's) from the tree
+fn symbol_len(symbols: &mut [Symbol], visited: &mut [bool], sym: usize, depth: u8) -> Result<(), String> {
+    if *visited.get(sym).ok_or("bad symbol")? {
+        return Ok(());
+    }
+    let symbol = symbols[sym];
+    if symbol.right() != 0xfff {
+        let depth = depth.checked_sub(1).ok_or("Huffman tree too deep")?;
+        let (left, right) = (usize::from(symbol.left()), usize::from(symbol.right()));
+        symbol_len(symbols, visited, left, depth)?;
+        symbol_len(symbols, visited, right, depth)?;
+        symbols[sym].len = symbols[left].len.checked_add(symbols[right].len).and_then(|n| n.checked_add(1)).ok_or("bad symbol")?;
+    }
+    visited[sym] = true;
+    Ok(())
+}
+
+/// An opened WDL or DTZ table
+pub(super) struct Table {
+    file: TableFile,
+    metric: Metric,
+    /// The table's material, stronger side as White
+    material: Material,
+    /// Subtables per leading pawn file (one for pawnless tables), then per side
+    files: Vec<Vec<PairsData>>,
+}
+
+impl Table {
+    pub(super) fn open(path: &Path, material: Material, metric: Metric) -> Result<Table, String> {
+        let file = TableFile(File::open(path).map_err(|e| e.to_string())?);
+        let mut magic = [0; 4];
+        file.read(&mut magic, 0)?;
+        if magic != if metric == Metric::Wdl { WDL_MAGIC } else { DTZ_MAGIC } {
+            return Err("not a Syzygy table (bad magic)".to_string());
+        }
+
+        let layout = file.u8(4)?;
+        let has_pawns = layout & LAYOUT_HAS_PAWNS != 0;
+        if has_pawns != material.has_pawns() || (layout & LAYOUT_SPLIT != 0) == material.is_symmetric() {
+            return Err("table layout does not match its name".to_string());
+        }
+
+        let pp = material.both_have_pawns();
+        let num_files = if has_pawns { 4 } else { 1 };
+        let num_sides = if metric == Metric::Wdl && !material.is_symmetric() { 2 } else { 1 };
+        let count = material.count();
+        let mut ptr = 5;
+
+        let mut groups = Vec::new();
+        for file_index in 0..num_files {
+            let first = file.u8(ptr)?;
+            let second = if pp { file.u8(ptr + 1)? } else { 0xff };
+            let order = [[first & 0xf, second & 0xf], [first >> 4, second >> 4]];
+            ptr += 1 + u64::from(pp);
+
+            let mut sides = Vec::new();
+            for (side, order) in order.into_iter().enumerate().take(num_sides) {
+                let mut bytes = vec![0; count];
+                file.read(&mut bytes, ptr)?;
+                let pieces = bytes
+                    .iter()
+                    .map(|&b| nibble_to_piece(if side == 0 { b & 0xf } else { b >> 4 }))
+                    .collect::<Option<Vec<_>>>()
+                    .ok_or("bad piece in header")?;
+                let key = Material::from_pieces(&pieces);
+                if key != material && key.swapped() != material {
+                    return Err("table pieces do not match its name".to_string());
+                }
+                sides.push(GroupData::new(pieces, order, file_index)?);
+            }
+            ptr += count as u64;
+            groups.push(sides);
+        }
+        ptr += ptr & 1;
+        if (groups[0][0].pieces[0].1 == Piece::Pawn) != has_pawns {
+            return Err("table does not lead with a pawn".to_string());
+        }
+
+        let mut files = Vec::new();
+        for sides in groups {
+            let mut pairs = Vec::new();
+            for side in sides {
+                let (data, next) = PairsData::parse(&file, ptr, side, metric)?;
+                pairs.push(data);
+                ptr = next;
+            }
+            files.push(pairs);
+        }
+
+        if metric == Metric::Dtz {
+            let map_ptr = ptr;
+            for pairs in &mut files {
+                let side = &mut pairs[0];
+                if side.flags & FLAG_MAPPED == 0 {
+                    continue;
+                }
+                let wide = side.flags & FLAG_WIDE_DTZ != 0;
+                let mut by_wdl = [0; 4];
+                for offset in &mut by_wdl {
+                    if wide {
+                        *offset = ((ptr - map_ptr + 2) / 2) as u16;
+                        ptr += u64::from(file.u16(ptr)?) * 2 + 2;
+                    } else {
+                        *offset = (ptr - map_ptr + 1) as u16;
+                        ptr += u64::from(file.u8(ptr)?) + 1;
+                    }
+                }
+                side.dtz_map = Some(DtzMap { ptr: map_ptr, by_wdl, wide });
+            }
+            ptr += ptr & 1;
+        }
+
+        for side in files.iter_mut().flatten() {
+            side.sparse_index = ptr;
+            ptr += u64::from(side.sparse_index_size) * 6;
+        }
+        for side in files.iter_mut().flatten() {
+            side.block_lengths = ptr;
+            ptr += u64::from(side.block_length_size) * 2;
+        }
+        for side in files.iter_mut().flatten() {
+            ptr = (ptr + 0x3f) & !0x3f;
+            side.data = ptr;
+            ptr += u64::from(side.blocks_num) * u64::from(side.block_size);
+        }
+
+        let material = Material::from_pieces(&files[0][0].groups.pieces);
+        Ok(Table { file, metric, material, files })
+    }
+
+    /// WDL of `board` as stored, without resolving captures
+    pub(super) fn probe_wdl(&self, board: &Board) -> Result<Wdl, String> {
+        let (side, idx) = self.encode(board)?.ok_or("WDL tables have both sides")?;
+        Ok(match self.decompress(side, idx)? {
+            0 => Wdl::Loss,
+            1 => Wdl::BlessedLoss,
+            2 => Wdl::Draw,
+            3 => Wdl::CursedWin,
+            4 => Wdl::Win,
+            v => return Err(format!("bad WDL value {}", v)),
+        })
+    }
+
+    /// Stored distance to zeroing in plies for a decisive `wdl`, and whether it was rounded
+    /// to full moves. None when the table only has the other side to move.
+    pub(super) fn probe_dtz(&self, board: &Board, wdl: Wdl) -> Result<Option<(u32, bool)>, String> {
+        let Some((side, idx)) = self.encode(board)? else {
+            return Ok(None);
+        };
+        let mut value = self.decompress(side, idx)?;
+        if let Some(map) = &side.dtz_map {
+            let offset = u64::from(map.by_wdl[match wdl {
+                Wdl::Win => 0,
+                Wdl::Loss => 1,
+                Wdl::CursedWin => 2,
+                _ => 3,
+            }]) + u64::from(value);
+            value = if map.wide { self.file.u16(map.ptr + 2 * offset)? } else { self.file.u8(map.ptr + offset)?.into() };
+        }
+        let plies = match wdl {
+            Wdl::Win => side.flags & FLAG_WIN_PLIES != 0,
+            Wdl::Loss => side.flags & FLAG_LOSS_PLIES != 0,
+            _ => false,
+        };
+        Ok(Some(if plies { (value.into(), false) } else { (2 * u32::from(value), true) }))
+    }
+
+    /// The subtable for `board` and its index there
+    fn encode(&self, board: &Board) -> Result<Option<(&PairsData, u64)>, String> {
+        let material = self.material;
+        let key = Material::from_board(board);
+        if key != material && key.swapped() != material {
+            return Err("position does not match the table".to_string());
+        }
+        // Tables are stored with the stronger side as White: mirror the board when Black is
+        // the stronger side, or to put White to move in symmetric tables
+        let black_to_move = board.side_to_move() == Color::Black;
+        let flip = key != material || (material.is_symmetric() && black_to_move);
+        let bside = black_to_move ^ flip;
+        let relative = |color: Color| if flip { !color } else { color };
+        let mirror = |sq: usize| if flip { flip_vertical(sq) } else { sq };
+
+        let mut squares = Vec::with_capacity(TB_PIECES);
+        let mut used = 0u64;
+        let file_index = if material.has_pawns() {
+            let (color, _) = self.files[0][0].groups.pieces[0];
+            let lead_pawns = (board.pieces(Piece::Pawn) & board.color_combined(relative(color))).0;
+            used |= lead_pawns;
+            squares.extend(bits(lead_pawns).map(mirror));
+            for i in 1..squares.len() {
+                if CONSTS.map_pawns[squares[0]] < CONSTS.map_pawns[squares[i]] {
+                    squares.swap(0, i);
+                }
+            }
+            let file = file_of(squares[0]);
+            if file >= 4 { 7 - file } else { file }
+        } else {
+            0
+        };
+
+        let sides = &self.files[file_index];
+        let side = &sides[if bside { sides.len() - 1 } else { 0 }];
+        // DTZ tables keep one side to move; the caller searches a ply for the other
+        if self.metric == Metric::Dtz
+            && (side.flags & FLAG_STM != 0) != bside
+            && (!material.is_symmetric() || material.has_pawns())
+        {
+            return Ok(None);
+        }
+
+        let lead_count = squares.len();
+        for &(color, piece) in &side.groups.pieces[lead_count..] {
+            let candidates = (board.pieces(piece) & board.color_combined(relative(color))).0 & !used;
+            let sq = bits(candidates).next().ok_or("piece missing from the position")?;
+            squares.push(mirror(sq));
+            used |= 1 << sq;
+        }
+
+        if file_of(squares[0]) >= 4 {
+            squares.iter_mut().for_each(|sq| *sq = flip_horizontal(*sq));
+        }
+
+        let lens = &side.groups.lens;
+        let mut idx = if material.has_pawns() {
+            let mut idx = CONSTS.lead_pawn_idx[lead_count][squares[0]];
+            squares[1..lead_count].sort_unstable_by_key(|&sq| CONSTS.map_pawns[sq]);
+            for (i, &sq) in squares.iter().enumerate().take(lead_count).skip(1) {
+                idx += binomial(CONSTS.map_pawns[sq], i as u64);
+            }
+            idx
+        } else {
+            encode_pawnless(&mut squares, lens[0], material)
+        };
+        idx *= side.groups.factors[0];
+
+        // The remaining groups, each as a combination of the squares earlier groups left free
+        let mut remaining_pawns = material.both_have_pawns();
+        let mut group_start = lens[0];
+        for (next, &len) in lens.iter().enumerate().skip(1) {
+            let (prev, group) = squares.split_at_mut(group_start);
+            let group = &mut group[..len];
+            group.sort_unstable();
+            let mut n = 0;
+            for (i, &sq) in group.iter().enumerate() {
+                let adjust = prev.iter().filter(|&&p| sq > p).count() as u64;
+                n += binomial(sq as u64 - adjust - if remaining_pawns { 8 } else { 0 }, i as u64 + 1);
+            }
+            remaining_pawns = false;
+            idx += n * side.groups.factors[next];
+            group_start += len;
+        }
+
+        Ok(Some((side, idx)))
+    }
+
+    /// Value number `idx` of a subtable
+    fn decompress(&self, d: &PairsData, idx: u64) -> Result<u16, String> {
+        if d.flags & FLAG_SINGLE_VALUE != 0 {
+            return Ok(d.min_symlen.into());
+        }
+
+        // The sparse index gives a block near the value, the block lengths the exact one
+        let main_idx = idx / u64::from(d.span);
+        let mut entry = [0; 6];
+        self.file.read(&mut entry, d.sparse_index + 6 * main_idx)?;
+        let mut block = u32::from_le_bytes([entry[0], entry[1], entry[2], entry[3]]);
+        let mut lit_idx = (idx % u64::from(d.span)) as i64 - i64::from(d.span / 2) + i64::from(u16::from_le_bytes([entry[4], entry[5]]));
+        while lit_idx < 0 {
+            block = block.checked_sub(1).ok_or("bad sparse index")?;
+            lit_idx += i64::from(self.file.u16(d.block_lengths + 2 * u64::from(block))?) + 1;
+        }
+        loop {
+            let len = i64::from(self.file.u16(d.block_lengths + 2 * u64::from(block))?) + 1;
+            if lit_idx < len {
+                break;
+            }
+            lit_idx -= len;
+            block += 1;
+        }
+
+        // Read the block (and 4 bytes for the last refill) and walk its Huffman codes
+        let size = d.block_size as usize;
+        let mut buffer = [0; MAX_BLOCK_SIZE + 4];
+        self.file.read(&mut buffer[..size + 4], d.data + u64::from(block) * u64::from(d.block_size))?;
+        let mut next_word = 8;
+        let mut buf = u64::from_be_bytes(buffer[..8].try_into().expect("8 bytes"));
+        let mut buf_size = 64;
+        let sym = loop {
+            let mut len = 0;
+            while buf < *d.base.get(len).ok_or("bad Huffman code")? {
+                len += 1;
+            }
+            let mut sym = ((buf - d.base[len]) >> (64 - len - usize::from(d.min_symlen))) as u16;
+            sym += self.file.u16(d.lowest_sym + 2 * len as u64)?;
+            let values = i64::from(d.symbols.get(usize::from(sym)).ok_or("bad symbol")?.len) + 1;
+            if lit_idx < values {
+                break sym;
+            }
+            lit_idx -= values;
+            len += usize::from(d.min_symlen);
+            buf <<= len;
+            buf_size -= len;
+            if buf_size <= 32 {
+                let word = buffer.get(next_word..next_word + 4).ok_or("block overrun")?;
+                buf_size += 32;
+                buf |= u64::from(u32::from_be_bytes(word.try_into().expect("4 bytes"))) << (64 - buf_size);
+                next_word += 4;
+            }
+        };
+
+        // Descend the symbol's tree to the literal
+        let mut symbol = d.symbols[usize::from(sym)];
+        while symbol.len > 0 {
+            let left = *d.symbols.get(usize::from(symbol.left())).ok_or("bad symbol")?;
+            if lit_idx < i64::from(left.len) + 1 {
+                symbol = left;
+            } else {
+                lit_idx -= i64::from(left.len) + 1;
+                symbol = *d.symbols.get(usize::from(symbol.right())).ok_or("bad symbol")?;
+            }
+        }
+        Ok(symbol.left())
+    }
+}
+
+/// Index of the leading group of a pawnless table. Normalizes `squares` by the board's
+/// symmetries first, as the remaining groups are indexed relative to it.
+fn encode_pawnless(squares: &mut [usize], lead_len: usize, material: Material) -> u64 {
+    let transform = |squares: &mut [usize], f: fn(usize) -> usize| squares.iter_mut().for_each(|sq| *sq = f(*sq));
+
+    if rank_of(squares[0]) >= 4 {
+        transform(squares, flip_vertical);
+    }
+    for i in 0..lead_len {
+        if !offdiag(squares[i]) {
+            continue;
+        }
+        if rank_of(squares[i]) > file_of(squares[i]) {
+            transform(&mut squares[i..], flip_diagonal);
+        }
+        break;
+    }
+
+    let [s0, s1] = [squares[0], squares[1]];
+    if material.unique_pieces() > 2 {
+        let s2 = squares[2];
+        let adjust1 = u64::from(s1 > s0);
+        let adjust2 = u64::from(s2 > s0) + u64::from(s2 > s1);
+        if offdiag(s0) {
+            TRIANGLE[s0] * 63 * 62 + (s1 as u64 - adjust1) * 62 + (s2 as u64 - adjust2)
+        } else if offdiag(s1) {
+            6 * 63 * 62 + rank_of(s0) as u64 * 28 * 62 + LOWER[s1] * 62 + s2 as u64 - adjust2
+        } else if offdiag(s2) {
+            6 * 63 * 62 + 4 * 28 * 62 + rank_of(s0) as u64 * 7 * 28 + (rank_of(s1) as u64 - adjust1) * 28 + LOWER[s2]
+        } else {
+            6 * 63 * 62
+                + 4 * 28 * 62
+                + 4 * 7 * 28
+                + rank_of(s0) as u64 * 7 * 6
+                + (rank_of(s1) as u64 - adjust1) * 6
+                + (rank_of(s2) as u64 - adjust2)
+        }
+    } else if material.unique_pieces() == 2 {
+        KK_IDX[TRIANGLE[s0] as usize][s1]
+    } else if material.min_like_man() == 2 {
+        if TRIANGLE[squares[0]] > TRIANGLE[squares[1]] {
+            squares.swap(0, 1);
+        }
+        if file_of(squares[0]) >= 4 {
+            transform(squares, flip_horizontal);
+        }
+        if rank_of(squares[0]) >= 4 {
+            transform(squares, flip_vertical);
+        }
+        if rank_of(squares[0]) > file_of(squares[0]) || (!offdiag(squares[0]) && rank_of(squares[1]) > file_of(squares[1])) {
+            transform(squares, flip_diagonal);
+        }
+        if TEST45 & (1 << squares[1]) != 0 && TRIANGLE[squares[0]] == TRIANGLE[squares[1]] {
+            squares.swap(0, 1);
+            transform(squares, |sq| flip_diagonal(flip_vertical(sq)));
+        }
+        PP_IDX[TRIANGLE[squares[0]] as usize][squares[1]]
+    } else {
+        for i in 1..lead_len {
+            if TRIANGLE[squares[0]] > TRIANGLE[squares[i]] {
+                squares.swap(0, i);
+            }
+        }
+        if file_of(squares[0]) >= 4 {
+            transform(squares, flip_horizontal);
+        }
+        if rank_of(squares[0]) >= 4 {
+            transform(squares, flip_vertical);
+        }
+        if rank_of(squares[0]) > file_of(squares[0]) {
+            transform(squares, flip_diagonal);
+        }
+        for i in 1..lead_len {
+            for j in i + 1..lead_len {
+                if MULT_TWIST[squares[i]] > MULT_TWIST[squares[j]] {
+                    squares.swap(i, j);
+                }
+            }
+        }
+        let mut idx = CONSTS.mult_idx[lead_len - 1][TRIANGLE[squares[0]] as usize];
+        for i in 1..lead_len {
+            idx += binomial(MULT_TWIST[squares[i]], i as u64);
+        }
+        idx
+    }
+}
+
+/// Header nibble to piece: low three bits 1 (pawn) to 6 (king), bit 3 for Black
+fn nibble_to_piece(nibble: u8) -> Option<(Color, Piece)> {
+    let color = if nibble & 8 == 0 { Color::White } else { Color::Black };
+    let piece = *Piece::ALL.get(usize::from(nibble & 7).checked_sub(1)?)?;
+    Some((color, piece))
+}
+
+/// Square indices of the set bits, lowest first
+fn bits(mut bb: u64) -> impl Iterator<Item = usize> {
+    std::iter::from_fn(move || {
+        (bb != 0).then(|| {
+            let sq = bb.trailing_zeros() as usize;
+            bb &= bb - 1;
+            sq
+        })
+    })
+}
+
+#[cfg(test)]
+pub(super) mod tests {
+    use super::*;
+
+    /// Write a table for `name` whose subtable `(side, file)` holds `value(side, file, idx)`
+    /// at each index. Subtables with one value are stored as such; the rest use fixed-length
+    /// Huffman codes plus one symbol for a pair of zeros, so the tree descent gets exercised.
+    pub(in crate::syzygy) fn write_table(path: &Path, name: &str, metric: Metric, flags: u8, value: impl Fn(usize, usize, u64) -> u16) {
+        let material = Material::from_name(name).unwrap();
+        let pieces = header_pieces(material);
+        let pp = material.both_have_pawns();
+        let num_files = if material.has_pawns() { 4 } else { 1 };
+        let num_sides = if metric == Metric::Wdl && !material.is_symmetric() { 2 } else { 1 };
+
+        let mut out = if metric == Metric::Wdl { WDL_MAGIC } else { DTZ_MAGIC }.to_vec();
+        out.push(if material.has_pawns() { LAYOUT_HAS_PAWNS } else { 0 } | if material.is_symmetric() { 0 } else { LAYOUT_SPLIT });
+        for _ in 0..num_files {
+            out.push(0x00);
+            if pp {
+                out.push(0x11);
+            }
+            out.extend(pieces.iter().map(|&p| piece_nibble(p) | (piece_nibble(p) << 4)));
+        }
+        if out.len() % 2 == 1 {
+            out.push(0);
+        }
+
+        let mut subtables = Vec::new();
+        for file in 0..num_files {
+            for side in 0..num_sides {
+                let groups = GroupData::new(pieces.clone(), [0, if pp { 1 } else { 0xf }], file).unwrap();
+                let size = groups.factors[groups.lens.len()];
+                let values: Vec<u16> = (0..size).map(|idx| value(side, file, idx)).collect();
+                subtables.push(encode_subtable(&values, metric, flags));
+            }
+        }
+        for subtable in &subtables {
+            out.extend(&subtable.header);
+        }
+        if metric == Metric::Dtz {
+            // Each file's map sends stored value v to 2v + wdl (300v + wdl when wide)
+            for _ in 0..num_files * usize::from(flags & FLAG_MAPPED != 0) {
+                for wdl in 0..4u16 {
+                    let max = 64u16;
+                    if flags & FLAG_WIDE_DTZ != 0 {
+                        out.extend(max.to_le_bytes());
+                        (0..max).for_each(|v| out.extend((300 * v + wdl).to_le_bytes()));
+                    } else {
+                        out.push(max as u8);
+                        out.extend((0..max).map(|v| (2 * v + wdl) as u8));
+                    }
+                }
+            }
+            if out.len() % 2 == 1 {
+                out.push(0);
+            }
+        }
+        for subtable in &subtables {
+            out.extend(&subtable.sparse_index);
+        }
+        for subtable in &subtables {
+            out.extend(&subtable.block_lengths);
+        }
+        for subtable in &subtables {
+            out.resize(out.len().next_multiple_of(64), 0);
+            out.extend(&subtable.data);
+        }
+        // Room for the last block's 4-byte refill; real tables end 16 bytes past a multiple of 64
+        out.resize(out.len().next_multiple_of(64) + 16, 0);
+        std::fs::write(path, out).unwrap();
+    }
+
+    struct Subtable {
+        header: Vec<u8>,
+        sparse_index: Vec<u8>,
+        block_lengths: Vec<u8>,
+        data: Vec<u8>,
+    }
+
+    const BLOCK_BITS: u8 = 6;
+    const SPAN_BITS: u8 = 8;
+
+    fn encode_subtable(values: &[u16], metric: Metric, flags: u8) -> Subtable {
+        if values.iter().all(|&v| v == values[0]) && (metric == Metric::Wdl || values[0] == 0) {
+            return Subtable {
+                header: vec![flags | FLAG_SINGLE_VALUE, values[0] as u8],
+                sparse_index: Vec::new(),
+                block_lengths: Vec::new(),
+                data: Vec::new(),
+            };
+        }
+
+        // Literals 0..=max, then the pair symbol
+        let pair = *values.iter().max().unwrap() + 1;
+        let symlen = 16 - pair.leading_zeros() as usize;
+        let mut symbols = Vec::new();
+        let mut i = 0;
+        while i < values.len() {
+            if values[i] == 0 && values.get(i + 1) == Some(&0) {
+                symbols.push((pair, 2));
+                i += 2;
+            } else {
+                symbols.push((values[i], 1));
+                i += 1;
+            }
+        }
+
+        // Blocks of whole symbols, each block's bits MSB first
+        let block_size = 1 << BLOCK_BITS;
+        let per_block = block_size * 8 / symlen;
+        let (mut data, mut lengths, mut starts) = (Vec::new(), Vec::new(), Vec::new());
+        let mut start = 0u64;
+        for chunk in symbols.chunks(per_block) {
+            let mut block = vec![0u8; block_size];
+            for (n, &(sym, _)) in chunk.iter().enumerate() {
+                for bit in 0..symlen {
+                    if sym >> (symlen - 1 - bit) & 1 != 0 {
+                        let pos = n * symlen + bit;
+                        block[pos / 8] |= 0x80 >> (pos % 8);
+                    }
+                }
+            }
+            let count: u64 = chunk.iter().map(|&(_, n)| n).sum();
+            data.extend(block);
+            lengths.extend((count as u16 - 1).to_le_bytes());
+            starts.push(start);
+            start += count;
+        }
+
+        let span = 1u64 << SPAN_BITS;
+        let mut sparse_index = Vec::new();
+        for main in 0..(values.len() as u64).div_ceil(span) {
+            let anchor = main * span + span / 2;
+            let block = starts.partition_point(|&s| s <= anchor.min(values.len() as u64 - 1)) - 1;
+            sparse_index.extend((block as u32).to_le_bytes());
+            sparse_index.extend(((anchor - starts[block]) as u16).to_le_bytes());
+        }
+
+        let mut header = vec![flags, BLOCK_BITS, SPAN_BITS, 0];
+        header.extend((starts.len() as u32).to_le_bytes());
+        header.extend([symlen as u8, symlen as u8]);
+        header.extend(0u16.to_le_bytes());
+        header.extend((pair + 1).to_le_bytes());
+        for sym in 0..pair {
+            header.extend([sym as u8, (sym >> 8) as u8 | 0xf0, 0xff]);
+        }
+        header.extend([0, 0, 0]);
+        if (pair + 1) % 2 == 1 {
+            header.push(0);
+        }
+        Subtable { header, sparse_index, block_lengths: lengths, data }
+    }
+
+    /// Header piece order: leading pawns (White's if it has any) and the other side's pawns,
+    /// or the first unique pieces for pawnless tables; then the rest, identical pieces together
+    fn header_pieces(material: Material) -> Vec<(Color, Piece)> {
+        let mut counts = material.0;
+        let mut pieces = Vec::new();
+        let mut take = |pieces: &mut Vec<_>, color: Color, piece: Piece, n: u8| {
+            let count = &mut counts[color.to_index()][piece.to_index()];
+            pieces.extend(std::iter::repeat_n((color, piece), n.min(*count) as usize));
+            *count -= n.min(*count);
+        };
+        let all = || [Color::White, Color::Black].into_iter().flat_map(|c| Piece::ALL.into_iter().rev().map(move |p| (c, p)));
+        if material.has_pawns() {
+            let lead = if material.0[0][0] > 0 { Color::White } else { Color::Black };
+            take(&mut pieces, lead, Piece::Pawn, 8);
+            take(&mut pieces, !lead, Piece::Pawn, 8);
+        } else {
+            let lead_len = if material.unique_pieces() >= 3 { 3 } else { 2 };
+            for (color, piece) in all().filter(|&(c, p)| material.0[c.to_index()][p.to_index()] == 1).take(lead_len).collect::<Vec<_>>() {
+                take(&mut pieces, color, piece, 1);
+            }
+        }
+        for (color, piece) in all() {
+            take(&mut pieces, color, piece, 8);
+        }
+        pieces
+    }
+
+    fn piece_nibble((color, piece): (Color, Piece)) -> u8 {
+        piece.to_index() as u8 + 1 + if color == Color::Black { 8 } else { 0 }
+    }
+
+    /// Mostly zeros, so the pair symbol is common
+    fn noisy(side: usize, file: usize, idx: u64) -> u16 {
+        let h = (idx ^ ((side as u64) << 40) ^ ((file as u64) << 48)).wrapping_mul(0x9E37_79B9_7F4A_7C15) >> 40;
+        if h % 8 < 3 { 0 } else { (h % 5) as u16 }
+    }
+
+    #[test]
+    fn test_material_names() {
+        let material = Material::from_name("KRPvKR").unwrap();
+        assert_eq!(material.count(), 5);
+        assert_eq!(material.to_string(), "KRPvKR");
+        assert_eq!(material.swapped().normalized(), material);
+        assert_eq!(Material::from_name("KvKQ").unwrap().normalized().to_string(), "KQvK");
+        assert!(Material::from_name("KQvQ").is_none());
+        assert!(Material::from_name("KXvK").is_none());
+    }
+
+    #[test]
+    fn test_decompress_every_value() {
+        let dir = std::env::temp_dir().join(format!("ferrite-tb-decompress-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        for (name, metric) in [("KRvK", Metric::Wdl), ("KPvK", Metric::Wdl), ("KRvK", Metric::Dtz)] {
+            let path = dir.join(name);
+            write_table(&path, name, metric, 0, noisy);
+            let table = Table::open(&path, Material::from_name(name).unwrap(), metric).unwrap();
+            for (file, sides) in table.files.iter().enumerate() {
+                for (side, subtable) in sides.iter().enumerate() {
+                    let size = subtable.groups.factors[subtable.groups.lens.len()];
+                    for idx in (0..size).step_by(7) {
+                        assert_eq!(table.decompress(subtable, idx).unwrap(), noisy(side, file, idx), "{} {} {} {}", name, file, side, idx);
+                    }
+                }
+            }
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_open_rejects_mismatches() {
+        let dir = std::env::temp_dir().join(format!("ferrite-tb-reject-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("KQvK.rtbw");
+        write_table(&path, "KQvK", Metric::Wdl, 0, |_, _, _| 2);
+        assert!(Table::open(&path, Material::from_name("KQvK").unwrap(), Metric::Wdl).is_ok());
+        assert!(Table::open(&path, Material::from_name("KQvK").unwrap(), Metric::Dtz).err().unwrap().contains("magic"));
+        assert!(Table::open(&path, Material::from_name("KRvK").unwrap(), Metric::Wdl).err().unwrap().contains("name"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
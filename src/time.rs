@@ -1,8 +1,84 @@
-// Time management is integrated directly into search::SearchState (check_time)
-// and uci::GoParams (compute_time_ms). No separate module needed.
-//
-// Design:
-// - Hard limit: search aborts when elapsed >= time_limit_ms (checked every 2048 nodes)
-// - Soft limit: iterative deepening stops if >50% of allocated time used
-// - Allocation: my_time / moves_to_go + 3/4 * increment, capped at 80% of remaining time
+use std::time::Instant;
+
+/// Milliseconds from a monotonic source, e.g. `performance.now()` in a browser
+pub type ClockFn = fn() -> f64;
+
+/// When a search started. Measured with `Instant`, or with a callback clock on targets that
+/// have no `Instant` (wasm32-unknown-unknown, where `Instant::now` panics).
+#[derive(Debug, Clone, Copy)]
+pub enum Timer {
+    Instant(Instant),
+    Clock(ClockFn, f64),
+}
+
+impl Timer {
+    /// Start timing now, with `clock` if given. On wasm32 without a clock, time stands still,
+    /// so time limits never trigger.
+    pub fn start(clock: Option<ClockFn>) -> Self {
+        match clock {
+            Some(clock) => Timer::Clock(clock, clock()),
+            #[cfg(not(target_arch = "wasm32"))]
+            None => Timer::Instant(Instant::now()),
+            #[cfg(target_arch = "wasm32")]
+            None => Timer::Clock(|| 0.0, 0.0),
+        }
+    }
+
+    pub fn elapsed_ms(&self) -> u64 {
+        match *self {
+            Timer::Instant(start) => start.elapsed().as_millis() as u64,
+            Timer::Clock(clock, start) => (clock() - start).max(0.0) as u64,
+        }
+    }
+
+    /// Elapsed microseconds, for rates over searches too short to time in milliseconds
+    pub fn elapsed_us(&self) -> u64 {
+        match *self {
+            Timer::Instant(start) => start.elapsed().as_micros() as u64,
+            Timer::Clock(clock, start) => ((clock() - start) * 1000.0).max(0.0) as u64,
+        }
+    }
+}
 
+/// Nodes per second for `nodes` searched in `elapsed_us` microseconds
+pub fn nps(nodes: u64, elapsed_us: u64) -> u64 {
+    nodes * 1_000_000 / elapsed_us.max(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_callback_clock() {
+        fn fixed() -> f64 {
+            1234.5
+        }
+        let timer = Timer::start(Some(fixed));
+        assert_eq!((timer.elapsed_ms(), timer.elapsed_us()), (0, 0));
+        assert!(Timer::start(None).elapsed_ms() < 1000);
+    }
+
+    #[test]
+    fn test_nps() {
+        // 500 nodes in 250us is 2M nps, not the 500k a rounded-up millisecond would give
+        assert_eq!(nps(500, 250), 2_000_000);
+        assert_eq!(nps(0, 0), 0);
+        assert_eq!(nps(3, 0), 3_000_000);
+    }
+}
+
+// Time management is otherwise integrated directly into search::SearchState (check_time)
+// and types::SearchLimits (compute_time_ms):
+// - Hard limit: search aborts when elapsed >= time_limit_ms (checked every 2048 nodes)
+// - Soft limit: iterative deepening stops if >50% of allocated time used, doubled (up to the
+//   hard limit) after an iteration whose score dropped by the panic margin
+// - Allocation: my_time / moves_to_go (times Slow Mover percent) + 3/4 * increment, capped
+//   at 80% of remaining time
+// - Minimum Thinking Time: raises the allocation (within the same cap) and keeps the soft
+//   limit from ending the search sooner; a search that runs out of depth waits for it
+// - nodestime: a clock-timed search counts its time as nodes / nodestime, and the hard limit
+//   becomes a node limit
+// - Single reply: a timed search with one legal move stops at a shallow depth
+// The clock is a plain fn pointer rather than a boxed closure so Timer stays Copy and the
+// SearchState stays Send; browsers only need `Date.now` or `performance.now`, which need no state.
@@ -0,0 +1,52 @@
+//! Structured diagnostics: with the `tracing` feature, `trace_event!` and `trace_span!` are
+//! `tracing` events and spans; without it they compile to nothing.
+
+/// `trace_event!(DEBUG, field = value, "message")`: a `tracing` event at the given level
+#[cfg(feature = "tracing")]
+macro_rules! trace_event {
+    ($level:ident, $($arg:tt)*) => {
+        tracing::event!(tracing::Level::$level, $($arg)*)
+    };
+}
+#[cfg(not(feature = "tracing"))]
+macro_rules! trace_event {
+    ($level:ident, $($arg:tt)*) => {};
+}
+pub(crate) use trace_event;
+
+/// `let _span = trace_span!(DEBUG, "name", field = value)`: a `tracing` span, entered until the
+/// guard is dropped
+#[cfg(feature = "tracing")]
+macro_rules! trace_span {
+    ($level:ident, $($arg:tt)*) => {
+        tracing::span!(tracing::Level::$level, $($arg)*).entered()
+    };
+}
+#[cfg(not(feature = "tracing"))]
+macro_rules! trace_span {
+    ($level:ident, $($arg:tt)*) => {
+        $crate::trace::NoSpan
+    };
+}
+pub(crate) use trace_span;
+
+/// What `trace_span!` gives without the feature
+#[cfg(not(feature = "tracing"))]
+pub(crate) struct NoSpan;
+
+/// Print the events and spans `RUST_LOG` selects (`ferrite=debug`, `ferrite::search=trace`, ...)
+/// to stderr, leaving stdout to the protocol. Does nothing if a subscriber is already set.
+#[cfg(feature = "tracing")]
+pub fn init() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+        .with_writer(std::io::stderr)
+        .with_ansi(std::io::IsTerminal::is_terminal(&std::io::stderr()))
+        .try_init();
+}
+
+// The macros keep call sites free of cfg attributes: the build without the feature, which is
+// what gets played and benchmarked, sees no code at all, not even a disabled-level check. Spans
+// mark where time goes (a command, a search, an iteration) and events carry the values;
+// everything `debug on` prints is an event too, so a trace has the time management decisions
+// without a GUI attached.
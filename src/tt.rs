@@ -1,4 +1,10 @@
+use std::mem::MaybeUninit;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::thread;
+
 use crate::board::ChessMove;
+use crate::numa;
+use crate::trace::trace_event;
 
 use crate::types::{Score, SCORE_MATE};
 
@@ -9,62 +15,228 @@ pub enum TTFlag {
     UpperBound, // Failed low (score <= alpha)
 }
 
-#[derive(Clone, Copy)]
+/// Packed TT entry (8 bytes).
+///
+/// Layout:
+///   - `key`:  lower 16 bits of the Zobrist hash (upper bits select the slot)
+///   - `mv`:   raw 16-bit move encoding, 0 = no move
+///   - `score`: i16 score (mate scores fit: |score| <= SCORE_INFINITY)
+///   - `depth`: search depth, in the search's quarter plies
+///   - `meta`: bits 0..1 = flag (0 = empty), bits 2..7 = generation (age)
+#[derive(Clone, Copy, Default)]
 pub struct TTEntry {
-    pub key: u64, // Zobrist hash (full, for collision detection)
-    pub depth: u8,
-    pub score: Score,
-    pub flag: TTFlag,
-    pub best_move: Option<ChessMove>,
-    pub age: u8, // Search generation for aging
+    key: u16,
+    mv: u16,
+    score: i16,
+    depth: u8,
+    meta: u8,
 }
 
-impl Default for TTEntry {
-    fn default() -> Self {
-        Self {
-            key: 0,
-            depth: 0,
-            score: 0,
-            flag: TTFlag::Exact,
-            best_move: None,
-            age: 0,
+const FLAG_MASK: u8 = 0b11;
+const AGE_SHIFT: u8 = 2;
+const AGE_MASK: u8 = 0x3F;
+
+// Bit offsets of the fields in a packed entry (`key` is at 0)
+const MOVE_SHIFT: u32 = 16;
+const SCORE_SHIFT: u32 = 32;
+const DEPTH_SHIFT: u32 = 48;
+const META_SHIFT: u32 = 56;
+
+impl TTEntry {
+    #[inline]
+    pub fn depth(&self) -> u8 {
+        self.depth
+    }
+
+    #[inline]
+    pub fn score(&self) -> Score {
+        self.score as Score
+    }
+
+    #[inline]
+    pub fn flag(&self) -> TTFlag {
+        match self.meta & FLAG_MASK {
+            1 => TTFlag::Exact,
+            2 => TTFlag::LowerBound,
+            _ => TTFlag::UpperBound,
+        }
+    }
+
+    #[inline]
+    pub fn best_move(&self) -> Option<ChessMove> {
+        if self.mv == 0 {
+            None
+        } else {
+            Some(ChessMove::from_raw(self.mv))
         }
     }
+
+    #[inline]
+    pub fn age(&self) -> u8 {
+        self.meta >> AGE_SHIFT
+    }
+
+    #[inline]
+    fn is_empty(&self) -> bool {
+        self.meta & FLAG_MASK == 0
+    }
+
+    /// The entry as the one word the table stores, so it is read and written whole
+    #[inline]
+    fn pack(self) -> u64 {
+        self.key as u64
+            | (self.mv as u64) << MOVE_SHIFT
+            | (self.score as u16 as u64) << SCORE_SHIFT
+            | (self.depth as u64) << DEPTH_SHIFT
+            | (self.meta as u64) << META_SHIFT
+    }
+
+    #[inline]
+    fn unpack(word: u64) -> Self {
+        TTEntry {
+            key: word as u16,
+            mv: (word >> MOVE_SHIFT) as u16,
+            score: (word >> SCORE_SHIFT) as u16 as i16,
+            depth: (word >> DEPTH_SHIFT) as u8,
+            meta: (word >> META_SHIFT) as u8,
+        }
+    }
+}
+
+#[inline]
+fn key_slice(hash: u64) -> u16 {
+    hash as u16
+}
+
+#[inline]
+fn flag_bits(flag: TTFlag) -> u8 {
+    match flag {
+        TTFlag::Exact => 1,
+        TTFlag::LowerBound => 2,
+        TTFlag::UpperBound => 3,
+    }
+}
+
+/// Entries below which a table is filled on the calling thread (8 MB): spawning threads
+/// would cost more than it saves
+const PARALLEL_FILL_MIN: usize = 1 << 20;
+
+/// Run `f(offset, part)` over consecutive parts of `slice`, split across the available cores
+/// when it is large. The parts go to the NUMA nodes in turn (see `numa::bind_thread`).
+fn parallel_chunks<T: Send>(slice: &mut [T], f: impl Fn(usize, &mut [T]) + Sync) {
+    let threads = thread::available_parallelism().map_or(1, |n| n.get());
+    if threads == 1 || slice.len() < PARALLEL_FILL_MIN {
+        f(0, slice);
+        return;
+    }
+    let chunk = slice.len().div_ceil(threads);
+    let f = &f;
+    thread::scope(|scope| {
+        for (i, part) in slice.chunks_mut(chunk).enumerate() {
+            scope.spawn(move || {
+                numa::bind_thread(i);
+                f(i * chunk, part)
+            });
+        }
+    });
+}
+
+/// `size` entries with `init(slot)` in each, written by several threads so the pages are also
+/// first touched in parallel, and so spread over the NUMA nodes
+fn build_entries(size: usize, init: impl Fn(usize) -> TTEntry + Sync) -> Vec<AtomicU64> {
+    let mut entries = Vec::with_capacity(size);
+    parallel_chunks(&mut entries.spare_capacity_mut()[..size], |offset, part| {
+        for (i, entry) in part.iter_mut().enumerate() {
+            *entry = MaybeUninit::new(AtomicU64::new(init(offset + i).pack()));
+        }
+    });
+    // SAFETY: the first `size` entries were all just initialized
+    unsafe { entries.set_len(size) };
+    entries
+}
+
+/// Slot of `hash` in a table of `len` entries: `hash * len / 2^64`, which spreads hashes over
+/// any table size without a modulo
+#[inline]
+fn slot(hash: u64, len: usize) -> usize {
+    ((hash as u128 * len as u128) >> 64) as usize
+}
+
+/// Smallest and largest hash that land in `slot` of a table of `len` entries
+fn slot_hashes(slot: usize, len: usize) -> (u64, u64) {
+    let first = |slot: usize| ((slot as u128) << 64).div_ceil(len as u128);
+    (first(slot) as u64, (first(slot + 1) - 1) as u64)
 }
 
+fn entries_for(mb: usize) -> usize {
+    ((mb * 1024 * 1024) / std::mem::size_of::<TTEntry>()).max(1024) // Minimum 1024 entries
+}
+
+/// Entries are single atomic words, so search threads probe and store through a shared
+/// reference without locks (see `SearchTables::tt`)
 pub struct TranspositionTable {
-    entries: Vec<TTEntry>,
-    mask: usize, // size - 1 (for fast modulo)
+    entries: Vec<AtomicU64>,
     generation: u8, // Current search generation
 }
 
 impl TranspositionTable {
     /// Create new TT with given size in megabytes
     pub fn new(mb: usize) -> Self {
-        let entry_size = std::mem::size_of::<TTEntry>();
-        let num_entries = (mb * 1024 * 1024) / entry_size;
-        // Round down to power of 2
-        let size = num_entries.next_power_of_two() / 2;
-        let size = size.max(1024); // Minimum 1024 entries
-
         Self {
-            entries: vec![TTEntry::default(); size],
-            mask: size - 1,
+            entries: build_entries(entries_for(mb), |_| TTEntry::default()),
             generation: 0,
         }
     }
 
+    /// Change the size to `mb`, moving entries into the new table. Where several compete for
+    /// a slot the one that `store` would keep wins.
+    pub fn resize(&mut self, mb: usize) {
+        let (old, size) = (&self.entries, entries_for(mb));
+        if size == old.len() {
+            return;
+        }
+        let old_entry = |slot: usize| TTEntry::unpack(old[slot].load(Ordering::Relaxed));
+        // Only the low bits of a hash are stored, so an entry's new slot is only known to be
+        // one of those its old slot's hashes map to. It goes into each of them: in the wrong
+        // ones its key is as unlikely to match as any other position's.
+        let entries = build_entries(size, |new_slot| {
+            let (first, last) = slot_hashes(new_slot, size);
+            (slot(first, old.len())..=slot(last, old.len()))
+                .map(old_entry)
+                .filter(|e| !e.is_empty())
+                .max_by_key(|e| (e.age() == self.generation, e.depth))
+                .unwrap_or_default()
+        });
+        self.entries = entries;
+        trace_event!(DEBUG, mb, entries = self.entries.len(), "tt resized");
+    }
+
+    /// How many searches ago `entry` was last written (modulo the 6-bit generation)
+    pub fn searches_since(&self, entry: &TTEntry) -> u8 {
+        self.generation.wrapping_sub(entry.age()) & AGE_MASK
+    }
+
     /// Increment generation counter (call at start of each search)
     pub fn new_search(&mut self) {
-        self.generation = self.generation.wrapping_add(1);
+        self.generation = (self.generation + 1) & AGE_MASK;
+        trace_event!(TRACE, generation = self.generation, "tt new search");
+    }
+
+    #[inline]
+    fn index(&self, hash: u64) -> usize {
+        slot(hash, self.entries.len())
+    }
+
+    #[inline]
+    fn entry(&self, idx: usize) -> TTEntry {
+        TTEntry::unpack(self.entries[idx].load(Ordering::Relaxed))
     }
 
     /// Probe TT for given hash
-    pub fn probe(&self, hash: u64, _ply: usize) -> Option<&TTEntry> {
-        let idx = hash as usize & self.mask;
-        let entry = &self.entries[idx];
+    pub fn probe(&self, hash: u64, _ply: usize) -> Option<TTEntry> {
+        let entry = self.entry(self.index(hash));
 
-        if entry.key == hash {
+        if !entry.is_empty() && entry.key == key_slice(hash) {
             Some(entry)
         } else {
             None
@@ -73,7 +245,7 @@ impl TranspositionTable {
 
     /// Retrieve score from TT entry, adjusting mate scores for current ply
     pub fn retrieve_score(entry: &TTEntry, ply: usize, alpha: Score, beta: Score) -> Option<Score> {
-        let mut score = entry.score;
+        let mut score = entry.score();
 
         // Adjust mate scores from storage format (relative to root) to current ply
         if score > SCORE_MATE - 100 {
@@ -82,7 +254,7 @@ impl TranspositionTable {
             score += ply as Score;
         }
 
-        match entry.flag {
+        match entry.flag() {
             TTFlag::Exact => Some(score),
             TTFlag::LowerBound => {
                 if score >= beta { Some(score) } else { None }
@@ -93,9 +265,10 @@ impl TranspositionTable {
         }
     }
 
-    /// Store position in TT
+    /// Store position in TT. Threads storing to the same slot at once each write a whole
+    /// entry, and the last one stays.
     pub fn store(
-        &mut self,
+        &self,
         hash: u64,
         depth: u8,
         mut score: Score,
@@ -103,15 +276,16 @@ impl TranspositionTable {
         best_move: Option<ChessMove>,
         ply: usize,
     ) {
-        let idx = hash as usize & self.mask;
-        let entry = &self.entries[idx];
+        let idx = self.index(hash);
+        let entry = self.entry(idx);
+        let key = key_slice(hash);
 
         // Replacement strategy: depth-preferred with aging
         // Replace if: empty, same position, deeper search, or stale entry
-        let should_replace = entry.key == 0
-            || entry.key == hash
+        let should_replace = entry.is_empty()
+            || entry.key == key
             || depth >= entry.depth
-            || entry.age != self.generation;
+            || entry.age() != self.generation;
 
         if !should_replace {
             return;
@@ -124,22 +298,31 @@ impl TranspositionTable {
             score -= ply as Score;
         }
 
-        self.entries[idx] = TTEntry {
-            key: hash,
+        let entry = TTEntry {
+            key,
+            mv: best_move.map_or(0, ChessMove::to_raw),
+            score: score as i16,
             depth,
-            score,
-            flag,
-            best_move,
-            age: self.generation,
+            meta: flag_bits(flag) | (self.generation << AGE_SHIFT),
         };
+        self.entries[idx].store(entry.pack(), Ordering::Relaxed);
+    }
+
+    /// Permille of a 1000-entry sample written during the current search (UCI `hashfull`)
+    pub fn hashfull(&self) -> u32 {
+        let sample = self.entries.len().min(1000);
+        let used = (0..sample)
+            .map(|idx| self.entry(idx))
+            .filter(|e| !e.is_empty() && e.age() == self.generation)
+            .count();
+        (used * 1000 / sample) as u32
     }
 
-    /// Clear all entries.
+    /// Clear all entries, on several threads for a large table
     pub fn clear(&mut self) {
-        for entry in self.entries.iter_mut() {
-            *entry = TTEntry::default();
-        }
+        parallel_chunks(&mut self.entries, |_, part| part.iter_mut().for_each(|e| *e.get_mut() = 0));
         self.generation = 0;
+        trace_event!(DEBUG, entries = self.entries.len(), "tt cleared");
     }
 }
 
@@ -150,7 +333,7 @@ mod tests {
 
     #[test]
     fn test_tt_store_and_probe() {
-        let mut tt = TranspositionTable::new(1); // 1 MB
+        let tt = TranspositionTable::new(1); // 1 MB
         let hash: u64 = 0x123456789ABCDEF0;
 
         tt.store(hash, 5, 100, TTFlag::Exact, None, 0);
@@ -158,9 +341,83 @@ mod tests {
         let entry = tt.probe(hash, 0);
         assert!(entry.is_some());
         let entry = entry.unwrap();
-        assert_eq!(entry.depth, 5);
-        assert_eq!(entry.score, 100);
-        assert_eq!(entry.flag, TTFlag::Exact);
+        assert_eq!(entry.depth(), 5);
+        assert_eq!(entry.score(), 100);
+        assert_eq!(entry.flag(), TTFlag::Exact);
+    }
+
+    /// A hash that lands in slot `idx` of `tt`
+    fn hash_for_slot(tt: &TranspositionTable, idx: usize) -> u64 {
+        slot_hashes(idx, tt.entries.len()).0
+    }
+
+    #[test]
+    fn test_hashfull() {
+        let mut tt = TranspositionTable::new(1);
+        assert_eq!(tt.hashfull(), 0);
+        for i in 0..100 {
+            tt.store(hash_for_slot(&tt, i), 1, 0, TTFlag::Exact, None, 0);
+        }
+        assert_eq!(tt.hashfull(), 100);
+        // Entries from earlier searches don't count
+        tt.new_search();
+        assert_eq!(tt.hashfull(), 0);
+    }
+
+    #[test]
+    fn test_any_size_and_index() {
+        // 3 MB isn't rounded down to 2 MB
+        let tt = TranspositionTable::new(3);
+        assert_eq!(tt.entries.len(), 3 * 1024 * 1024 / 8);
+        for idx in [0, 1, 12345, tt.entries.len() - 1] {
+            assert_eq!(tt.index(hash_for_slot(&tt, idx)), idx);
+        }
+        assert_eq!(tt.index(u64::MAX), tt.entries.len() - 1);
+
+        // Hashes sharing a slot are told apart by their low bits
+        let tt = TranspositionTable::new(1);
+        let hash = hash_for_slot(&tt, 500);
+        tt.store(hash, 4, 30, TTFlag::Exact, None, 0);
+        assert_eq!(tt.index(hash + 1), 500);
+        assert!(tt.probe(hash + 1, 0).is_none());
+        assert_eq!(tt.probe(hash, 0).unwrap().score(), 30);
+    }
+
+    #[test]
+    fn test_resize_keeps_entries() {
+        let mut tt = TranspositionTable::new(2);
+        // Spread over the table, with varied low bits
+        let hashes: Vec<u64> = (0..1000).map(|i| hash_for_slot(&tt, i * 251) + i as u64 * 7919).collect();
+        for (i, &hash) in hashes.iter().enumerate() {
+            tt.store(hash, (i % 20) as u8, i as Score, TTFlag::LowerBound, None, 0);
+        }
+        let check = |tt: &TranspositionTable| {
+            for (i, &hash) in hashes.iter().enumerate() {
+                let entry = tt.probe(hash, 0).expect("entry survives the resize");
+                assert_eq!((entry.depth(), entry.score(), entry.flag()), ((i % 20) as u8, i as Score, TTFlag::LowerBound));
+            }
+        };
+
+        // Growing to a size that isn't a multiple of the old one, then shrinking below it
+        tt.resize(5);
+        assert_eq!(tt.entries.len(), 5 * 1024 * 1024 / 8);
+        check(&tt);
+        tt.resize(1);
+        check(&tt);
+
+        // Shrinking merges slots: an entry from the current search wins, then the deeper one
+        let mut tt = TranspositionTable::new(2);
+        let hash = |slot: usize| hash_for_slot(&tt, slot) + slot as u64;
+        let (stale, current, shallow, deep) = (hash(12), hash(13), hash(10), hash(11));
+        tt.store(stale, 30, 1, TTFlag::Exact, None, 0);
+        tt.new_search();
+        tt.store(current, 2, 2, TTFlag::Exact, None, 0);
+        tt.store(shallow, 2, 3, TTFlag::Exact, None, 0);
+        tt.store(deep, 9, 4, TTFlag::Exact, None, 0);
+        tt.resize(1);
+        assert_eq!((tt.index(stale), tt.index(shallow)), (tt.index(current), tt.index(deep)));
+        assert!(tt.probe(stale, 0).is_none() && tt.probe(shallow, 0).is_none());
+        assert_eq!((tt.probe(current, 0).unwrap().score(), tt.probe(deep, 0).unwrap().score()), (2, 4));
     }
 
     #[test]
@@ -173,7 +430,7 @@ mod tests {
 
     #[test]
     fn test_mate_score_adjustment() {
-        let mut tt = TranspositionTable::new(1);
+        let tt = TranspositionTable::new(1);
         let hash: u64 = 0xABCDEF;
 
         // Store mate score at ply 3
@@ -182,16 +439,16 @@ mod tests {
 
         // stored score should be adjusted: SCORE_MATE - 3 + 3 = SCORE_MATE
         let entry = tt.probe(hash, 0).unwrap();
-        assert_eq!(entry.score, SCORE_MATE);
+        assert_eq!(entry.score(), SCORE_MATE);
 
         // Retrieve at ply 5 should give SCORE_MATE - 5
-        let retrieved = TranspositionTable::retrieve_score(entry, 5, -SCORE_INFINITY, SCORE_INFINITY);
+        let retrieved = TranspositionTable::retrieve_score(&entry, 5, -SCORE_INFINITY, SCORE_INFINITY);
         assert_eq!(retrieved, Some(SCORE_MATE - 5));
     }
 
     #[test]
     fn test_tt_replacement() {
-        let mut tt = TranspositionTable::new(1);
+        let tt = TranspositionTable::new(1);
         let hash: u64 = 0x12345;
 
         // Store at depth 3
@@ -200,16 +457,87 @@ mod tests {
         tt.store(hash, 6, 75, TTFlag::Exact, None, 0);
 
         let entry = tt.probe(hash, 0).unwrap();
-        assert_eq!(entry.depth, 6);
-        assert_eq!(entry.score, 75);
+        assert_eq!(entry.depth(), 6);
+        assert_eq!(entry.score(), 75);
+    }
+
+    #[test]
+    fn test_parallel_clear() {
+        // Large enough to be filled by several threads
+        let mut tt = TranspositionTable::new(32);
+        assert!(tt.entries.len() > PARALLEL_FILL_MIN);
+        let hashes: Vec<u64> = (0..1000u64).map(|i| i.wrapping_mul(0x9E37_79B9_7F4A_7C15)).collect();
+        assert!(hashes.iter().all(|&h| tt.probe(h, 0).is_none()));
+        for &hash in &hashes {
+            tt.store(hash, 3, 10, TTFlag::Exact, None, 0);
+        }
+        tt.new_search();
+        tt.clear();
+        assert!(hashes.iter().all(|&h| tt.probe(h, 0).is_none()));
+        assert!(tt.entries.iter().all(|e| e.load(Ordering::Relaxed) == 0));
+    }
+
+    #[test]
+    fn test_entry_size() {
+        assert_eq!(std::mem::size_of::<TTEntry>(), 8);
+    }
+
+    #[test]
+    fn test_shared_stores() {
+        // Threads storing to the same slots at once: every entry read back is one thread's whole
+        let tt = TranspositionTable::new(1);
+        let hashes: Vec<u64> = (0..64).map(|i| hash_for_slot(&tt, i)).collect();
+        std::thread::scope(|scope| {
+            for thread in 1..=4u8 {
+                let (tt, hashes) = (&tt, &hashes);
+                scope.spawn(move || {
+                    for _ in 0..1000 {
+                        for &hash in hashes {
+                            let mv = ChessMove::from_raw(thread as u16);
+                            tt.store(hash, thread, -(thread as Score), TTFlag::LowerBound, Some(mv), 0);
+                        }
+                    }
+                });
+            }
+        });
+        for &hash in &hashes {
+            let entry = tt.probe(hash, 0).unwrap();
+            let thread = entry.depth();
+            assert!((1..=4).contains(&thread));
+            assert_eq!((entry.score(), entry.best_move().map(ChessMove::to_raw)), (-(thread as Score), Some(thread as u16)));
+        }
+    }
+
+    #[test]
+    fn test_best_move_roundtrip() {
+        use crate::board::{Piece, Square};
+        let tt = TranspositionTable::new(1);
+        let hash: u64 = 0xFEDC_BA98_7654_3210;
+        let mv = ChessMove::new(Square::new(52), Square::new(60), Some(Piece::Knight));
+
+        tt.store(hash, 4, -SCORE_INFINITY + 1, TTFlag::UpperBound, Some(mv), 0);
+
+        let entry = tt.probe(hash, 0).unwrap();
+        assert_eq!(entry.best_move(), Some(mv));
+        assert_eq!(entry.flag(), TTFlag::UpperBound);
+        assert_eq!(entry.score(), -SCORE_INFINITY + 1);
     }
 }
 
-// TT is hashmap indexed by `zobrist_hash % table_size`. Each entry stores position's hash (for
-// collision detection), search depth, score, bound type (exact/lower/upper) and best move found.
+// TT is hashmap indexed by `zobrist_hash * table_size >> 64` (Lemire's multiply-shift), which maps
+// hashes evenly onto any table size, so `Hash 1536` really gets 1536 MB rather than rounding to a
+// power of two. That index comes from the hash's high bits, so each entry stores the low 16 bits
+// to tell positions sharing a slot apart, plus search depth, score, bound type
+// (exact/lower/upper) and best move found, all packed into 8 bytes so more entries fit per MB.
 // Table uses depth-preferred replacement w/ aging -> deeper searches overwrite shallower ones, and
 // stale entries from prev searches are replaced.
 
+// Allocating and clearing a multi-gigabyte table is memory-bound, so it is split over all cores
+// with scoped threads. A new game doesn't clear at all (see `SearchState::new_game`): entries are
+// facts about positions, not games, and the generation stamp already lets every later search
+// overwrite them. Resizing keeps them too, each new slot taking the best entry among the old
+// slots whose hash ranges overlap its own, so the slots are independent and filled in parallel.
+
 // Mate score adjustment: Mate scores are stored relative to the root (ie. mate in 5 from root) but
 // need to be adjusted to the current ply when probed (ie. mate in 3 from this node). This is done
 // by adding/subtracting ply difference
@@ -0,0 +1,148 @@
+use alloc::string::String;
+#[cfg(feature = "tune")]
+use core::sync::atomic::{AtomicI32, Ordering};
+
+/// A search/eval constant that external tuners (SPSA via OpenBench etc.) can adjust.
+/// Without the `tune` feature `get` always returns the default, so it compiles to a constant.
+#[cfg_attr(not(feature = "tune"), allow(dead_code))]
+pub struct Param {
+    pub name: &'static str,
+    pub default: i32,
+    pub min: i32,
+    pub max: i32,
+    pub step: i32,
+    #[cfg(feature = "tune")]
+    value: AtomicI32,
+}
+
+#[cfg_attr(not(feature = "tune"), allow(dead_code))]
+impl Param {
+    const fn new(name: &'static str, default: i32, min: i32, max: i32, step: i32) -> Self {
+        Self {
+            name,
+            default,
+            min,
+            max,
+            step,
+            #[cfg(feature = "tune")]
+            value: AtomicI32::new(default),
+        }
+    }
+
+    #[inline]
+    pub fn get(&self) -> i32 {
+        #[cfg(feature = "tune")]
+        {
+            self.value.load(Ordering::Relaxed)
+        }
+        #[cfg(not(feature = "tune"))]
+        {
+            self.default
+        }
+    }
+
+    /// Set the value (clamped to min..=max). No-op without the `tune` feature.
+    pub fn set(&self, value: i32) {
+        #[cfg(feature = "tune")]
+        self.value.store(value.clamp(self.min, self.max), Ordering::Relaxed);
+        #[cfg(not(feature = "tune"))]
+        let _ = value;
+    }
+
+    /// UCI option declaration
+    pub fn uci_option(&self) -> String {
+        format!("option name {} type spin default {} min {} max {}", self.name, self.default, self.min, self.max)
+    }
+
+    /// OpenBench SPSA input line: name, type, default, min, max, step, learning rate
+    pub fn spsa_line(&self) -> String {
+        format!("{}, int, {}, {}, {}, {}, 0.002", self.name, self.default, self.min, self.max, self.step)
+    }
+}
+
+/// Null move pruning: minimum remaining depth
+pub static NMP_MIN_DEPTH: Param = Param::new("NmpMinDepth", 3, 1, 8, 1);
+/// Null move pruning: depth reduction R
+pub static NMP_REDUCTION: Param = Param::new("NmpReduction", 3, 1, 6, 1);
+/// LMR: minimum remaining depth
+pub static LMR_MIN_DEPTH: Param = Param::new("LmrMinDepth", 3, 1, 8, 1);
+/// LMR: number of moves searched at full depth before reducing
+pub static LMR_MIN_MOVES: Param = Param::new("LmrMinMoves", 3, 1, 12, 1);
+/// LMR: extra plies of reduction on top of the normal depth - 1
+pub static LMR_REDUCTION: Param = Param::new("LmrReduction", 1, 1, 4, 1);
+/// SEE pruning: deepest remaining depth (plies) at which losing moves are skipped
+pub static SEE_PRUNE_DEPTH: Param = Param::new("SeePruneDepth", 6, 1, 12, 1);
+/// SEE pruning: centipawns per ply a quiet move may lose before it is skipped
+pub static SEE_QUIET_MARGIN: Param = Param::new("SeeQuietMargin", 60, 10, 200, 5);
+/// SEE pruning: centipawns per ply squared a capture may lose before it is skipped
+pub static SEE_CAPTURE_MARGIN: Param = Param::new("SeeCaptureMargin", 20, 5, 100, 5);
+/// Futility pruning: deepest remaining depth (plies) at which the static eval prunes
+pub static FUTILITY_DEPTH: Param = Param::new("FutilityDepth", 3, 1, 8, 1);
+/// Futility pruning: centipawns per remaining ply between the static eval and the window
+pub static FUTILITY_MARGIN: Param = Param::new("FutilityMargin", 120, 40, 400, 10);
+/// Razoring: deepest remaining depth (plies) at which a hopeless node drops into quiescence
+pub static RAZOR_DEPTH: Param = Param::new("RazorDepth", 2, 1, 4, 1);
+/// Razoring: centipawns per remaining ply the static eval must be below alpha
+pub static RAZOR_MARGIN: Param = Param::new("RazorMargin", 300, 100, 800, 25);
+/// Soft time limit: don't start a new iteration past this percent of the allotted time
+pub static SOFT_TIME_PERCENT: Param = Param::new("SoftTimePercent", 50, 20, 90, 5);
+/// Panic time: a root score drop between iterations (centipawns) that extends the soft limit
+pub static PANIC_MARGIN: Param = Param::new("PanicMargin", 40, 10, 200, 5);
+/// Aspiration window: initial half-width around the last iteration's score (centipawns)
+pub static ASPIRATION_WINDOW: Param = Param::new("AspirationWindow", 50, 10, 300, 5);
+
+/// Every tunable parameter, in UCI option order
+#[cfg_attr(not(feature = "tune"), allow(dead_code))]
+pub static PARAMS: [&Param; 15] = [
+    &NMP_MIN_DEPTH,
+    &NMP_REDUCTION,
+    &LMR_MIN_DEPTH,
+    &LMR_MIN_MOVES,
+    &LMR_REDUCTION,
+    &SEE_PRUNE_DEPTH,
+    &SEE_QUIET_MARGIN,
+    &SEE_CAPTURE_MARGIN,
+    &FUTILITY_DEPTH,
+    &FUTILITY_MARGIN,
+    &RAZOR_DEPTH,
+    &RAZOR_MARGIN,
+    &SOFT_TIME_PERCENT,
+    &PANIC_MARGIN,
+    &ASPIRATION_WINDOW,
+];
+
+/// Look up a parameter by (case-insensitive) UCI name
+#[cfg_attr(not(feature = "tune"), allow(dead_code))]
+pub fn find(name: &str) -> Option<&'static Param> {
+    PARAMS.iter().copied().find(|p| p.name.eq_ignore_ascii_case(name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_defaults_in_range() {
+        for p in PARAMS {
+            assert!(p.min <= p.default && p.default <= p.max, "{} default out of range", p.name);
+            assert!(p.step > 0);
+        }
+    }
+
+    #[test]
+    fn test_find_case_insensitive() {
+        assert!(std::ptr::eq(find("nmpreduction").unwrap(), &NMP_REDUCTION));
+        assert!(find("NoSuchParam").is_none());
+    }
+
+    #[test]
+    fn test_option_format() {
+        assert_eq!(NMP_REDUCTION.uci_option(), "option name NmpReduction type spin default 3 min 1 max 6");
+        assert_eq!(NMP_REDUCTION.spsa_line(), "NmpReduction, int, 3, 1, 6, 1, 0.002");
+    }
+}
+
+// Tunable parameters: the magic numbers in search and eval live here as `Param` statics so that
+// SPSA tuning can change them through `setoption` without a rebuild. The registry is only exposed
+// over UCI with `--features tune` (plus a `spsa` command printing OpenBench's input format); normal
+// builds read the const default, so there is no atomic load on the hot path.
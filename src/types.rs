@@ -1,4 +1,21 @@
-use crate::board::ChessMove;
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+
+use crate::board::{ChessMove, Color};
+#[cfg(feature = "std")]
+use crate::board::Board;
+#[cfg(feature = "std")]
+use crate::book::{DEFAULT_BOOK_VARIETY, MAX_BOOKS};
+#[cfg(feature = "std")]
+use crate::evaluation::{EvalMode, Style};
+#[cfg(feature = "std")]
+use crate::handicap::{self, Odds};
+#[cfg(feature = "std")]
+use crate::numa::NumaPolicy;
+#[cfg(feature = "std")]
+use crate::opponent::{Opponent, DEFAULT_ENGINE_RATING, DEFAULT_OPPONENT_BOOK_VARIETY, DEFAULT_OPPONENT_CONTEMPT};
+#[cfg(feature = "std")]
+use crate::search::{SearchBackend, SearchParams, DEFAULT_SLOW_MOVER, MAX_BOOK_DEPTH, MAX_SKILL_LEVEL};
 
 pub type Score = i32;
 
@@ -6,27 +23,310 @@ pub const SCORE_INFINITY: Score = 30_000;
 pub const SCORE_MATE: Score = 29_000;
 pub const MAX_PLY: usize = 128;
 pub const DEFAULT_DEPTH: u8 = 5;
+/// Depth limit for timed searches, high enough that only the clock stops them
+pub const TIMED_MAX_DEPTH: u8 = 64;
 pub const DEFAULT_HASH_MB: usize = 64;
 pub const HISTORY_MAX: Score = 16384;
 
+/// Every UCI option's value, as the front ends keep them
+#[cfg(feature = "std")]
+#[derive(Clone)]
 pub struct EngineConfig {
     pub hash_mb: usize,
     pub syzygy_path: Option<String>,
+    pub search_stats: bool,
+    pub eval_file: Option<String>,
+    /// BookFile, BookFile2, ... and their Book Depth, Book Depth 2, ...
+    pub book_files: [Option<String>; MAX_BOOKS],
+    pub book_depths: [u8; MAX_BOOKS],
+    pub book_variety: u8,
+    pub show_wdl: bool,
+    pub show_refutations: bool,
+    /// OutputFormat json: info and bestmove as JSON lines
+    pub json_output: bool,
+    pub chess960: bool,
+    /// Material `odds_side` gives up in new games and `startpos`
+    pub odds: Option<Odds>,
+    pub odds_side: Color,
+    pub eval_mode: EvalMode,
+    /// Style: evaluation weights and contempt shifted for the side the engine plays
+    pub style: Style,
+    pub skill_level: u8,
+    /// SearchNullMove, SearchLMR, SearchAspiration, SearchExtensions, SearchSEE and SearchFutility
+    pub search_params: SearchParams,
+    pub search_backend: SearchBackend,
+    pub multi_pv: usize,
+    /// OpeningVariety plies and OpeningVarietyMargin centipawns
+    pub variety_plies: u8,
+    pub variety_margin: Score,
+    /// Contempt centipawns, DynamicContempt and UCI_RatingAdv (see `SearchState::contempt`)
+    pub contempt: Score,
+    pub dynamic_contempt: bool,
+    pub rating_adv: i32,
+    /// UCI_Opponent, and how it adjusts contempt and book variety (see `opponent`): Engine
+    /// Rating, Opponent Contempt percent and Opponent Book Variety
+    pub opponent: Option<Opponent>,
+    pub engine_rating: u32,
+    pub opponent_contempt: u32,
+    pub opponent_book_variety: u8,
+    /// Minimum Thinking Time in milliseconds
+    pub min_think_ms: u64,
+    /// Slow Mover percent
+    pub slow_mover: u32,
+    /// nodestime: nodes per millisecond of clock time (0 = real time)
+    pub nodes_time: u64,
+    pub log_file: Option<String>,
+    pub deterministic: bool,
+    /// Threads, the main search thread included, and the NUMA Policy that places them
+    pub threads: usize,
+    pub numa_policy: NumaPolicy,
 }
 
+#[cfg(feature = "std")]
 impl Default for EngineConfig {
     fn default() -> Self {
         Self {
             hash_mb: DEFAULT_HASH_MB,
             syzygy_path: None,
+            search_stats: false,
+            eval_file: None,
+            book_files: Default::default(),
+            book_depths: [MAX_BOOK_DEPTH; MAX_BOOKS],
+            book_variety: DEFAULT_BOOK_VARIETY,
+            show_wdl: false,
+            show_refutations: false,
+            json_output: false,
+            chess960: false,
+            odds: None,
+            odds_side: Color::White,
+            eval_mode: EvalMode::Nnue,
+            style: Style::Normal,
+            skill_level: MAX_SKILL_LEVEL,
+            search_params: SearchParams::default(),
+            search_backend: SearchBackend::AlphaBeta,
+            multi_pv: 1,
+            variety_plies: 0,
+            variety_margin: 20,
+            contempt: 0,
+            dynamic_contempt: true,
+            rating_adv: 0,
+            opponent: None,
+            engine_rating: DEFAULT_ENGINE_RATING,
+            opponent_contempt: DEFAULT_OPPONENT_CONTEMPT,
+            opponent_book_variety: DEFAULT_OPPONENT_BOOK_VARIETY,
+            min_think_ms: 0,
+            slow_mover: DEFAULT_SLOW_MOVER,
+            nodes_time: 0,
+            log_file: None,
+            deterministic: false,
+            threads: 1,
+            numa_policy: NumaPolicy::Auto,
         }
     }
 }
 
+#[cfg(feature = "std")]
+impl EngineConfig {
+    /// Where new games start: the standard position, less the odds piece if Odds is set
+    pub fn start_position(&self) -> Board {
+        handicap::odds_position(self.odds, self.odds_side)
+    }
+}
+
+/// Outcome of a search, with everything a final UCI `info` line shows
+#[derive(Debug, Clone)]
 pub struct SearchResult {
     pub best_move: Option<ChessMove>,
     pub score: Score,
+    /// Last completed iteration
+    pub depth: u8,
+    /// The iteration the search was stopped in, if a stop or limit cut one short
+    pub partial_depth: Option<u8>,
+    pub seldepth: u8,
+    pub nodes: u64,
+    pub time_ms: u64,
+    pub nps: u64,
+    /// Permille of the TT written during this search
+    pub hashfull: u32,
+    pub tb_hits: u64,
+    /// Principal variation, starting with `best_move` (empty without one)
+    pub pv: Vec<ChessMove>,
+    /// Diagnostics of the search (all zero for a book move), boxed to keep results small
+    pub stats: Box<SearchStats>,
+}
+
+/// Number of buckets in the beta-cutoff move index histogram (last bucket = "this index or later")
+pub const CUTOFF_BUCKETS: usize = 8;
+
+/// Search diagnostics collected alongside the node count. Counters are cheap increments so they
+/// are always gathered; reporting is opt-in (`SearchStats` UCI option or the `stats` command).
+#[derive(Clone, Default, Debug)]
+pub struct SearchStats {
+    /// All nodes the main thread searched, once the search is over. Helper threads keep no
+    /// statistics, so everything here is the main thread's.
+    pub nodes: u64,
+    /// Nodes searched in quiescence, depth-0 nodes included
+    pub qnodes: u64,
+    /// Interior nodes searched with an open window
+    pub pv_nodes: u64,
+    /// Interior nodes where no move reached alpha
+    pub all_nodes: u64,
+    /// beta cutoffs by index of the move that caused them
+    pub cutoff_index: [u64; CUTOFF_BUCKETS],
+    pub tt_probes: u64,
+    pub tt_hits: u64,
+    pub null_tries: u64,
+    pub null_cutoffs: u64,
+    pub lmr_searches: u64,
+    pub lmr_researches: u64,
+    /// Moves after the first searched on a null window, and how many beat alpha inside the
+    /// window and had to be searched again with all of it
+    pub pvs_searches: u64,
+    pub pvs_researches: u64,
+    /// Tablebase lookups of positions with few enough pieces, and how many found a result
+    pub tb_probes: u64,
+    pub tb_hits: u64,
+    /// Captures searched a ply deeper for retaking on the square of the capture before them
+    pub recapture_extensions: u64,
+    /// Endgame passed-pawn pushes to the 6th or 7th rank searched a ply deeper
+    pub passed_pawn_extensions: u64,
+    /// Moves skipped at shallow depth for losing material by static exchange
+    pub see_prunes: u64,
+    /// Nodes cut and quiet moves skipped because the static eval was too far from the window
+    pub futility_prunes: u64,
+    /// Nodes near the horizon that quiescence confirmed to be below alpha
+    pub razor_cuts: u64,
+    /// One entry per completed iteration of iterative deepening
+    pub iterations: Vec<IterationStats>,
+    /// SearchBackend mcts: the root moves by visits, most visited first (empty for alpha-beta)
+    pub root_visits: Vec<RootVisits>,
+}
+
+/// A completed iteration: the nodes it took and the time since the search started
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IterationStats {
+    pub depth: u8,
+    pub nodes: u64,
+    pub time_ms: u64,
+}
+
+/// A root move of a Monte-Carlo tree search: how often the search went through it, the score
+/// of its mean value and the share of visits its prior asked for
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RootVisits {
+    pub mv: ChessMove,
+    pub visits: u32,
+    /// Centipawns for the side to move
+    pub score: Score,
+    /// 0 to 1, summing to 1 over the root moves
+    pub prior: f32,
+}
+
+/// Whether a reported score is exact, or only a bound from a root search that failed outside
+/// its aspiration window
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ScoreBound {
+    #[default]
+    Exact,
+    /// Failed high: the score is at least this
+    Lower,
+    /// Failed low: the score is at most this
+    Upper,
+}
+
+/// Progress after one completed iteration of iterative deepening (a UCI `info` line), or after
+/// an aspiration failure, in which case `score` is only a bound and the iteration goes on
+#[derive(Debug, Clone)]
+pub struct SearchInfo {
     pub depth: u8,
+    /// Which line this is with MultiPV, 1 = best
+    pub multipv: usize,
+    pub score: Score,
+    pub bound: ScoreBound,
     pub nodes: u64,
+    pub time_ms: u64,
+    pub nps: u64,
+    pub pv: Vec<ChessMove>,
+}
+
+/// Limits for one search, as given by a UCI `go` command. The search stops at whichever is
+/// reached first; all unset = the default depth.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SearchLimits {
+    pub depth: Option<u8>,
+    pub movetime: Option<u64>,
+    pub wtime: Option<u64>,
+    pub btime: Option<u64>,
+    pub winc: Option<u64>,
+    pub binc: Option<u64>,
+    pub moves_to_go: Option<u64>,
+    /// Stop after exactly this many nodes
+    pub nodes: Option<u64>,
+    /// Look for a mate in this many moves: searched no deeper than such a mate needs
+    pub mate: Option<u8>,
+    pub infinite: bool,
+    /// Only consider these root moves (all legal moves if empty, or if none of these is legal)
+    pub searchmoves: Vec<ChessMove>,
 }
 
+impl SearchLimits {
+    /// Search to a fixed depth
+    pub fn depth(depth: u8) -> Self {
+        SearchLimits { depth: Some(depth), ..Default::default() }
+    }
+
+    /// Depth to search when `depth` isn't given: unbounded when something else ends the search
+    /// (a clock, a node count or `stop`), else deep enough for the mate asked for, else
+    /// `DEFAULT_DEPTH`
+    pub fn default_depth(&self) -> u8 {
+        let timed = self.movetime.is_some() || self.wtime.is_some() || self.btime.is_some();
+        if self.infinite || self.nodes.is_some() || timed {
+            TIMED_MAX_DEPTH
+        } else if let Some(moves) = self.mate {
+            (2 * moves as u16).saturating_sub(1).clamp(1, TIMED_MAX_DEPTH as u16) as u8
+        } else {
+            DEFAULT_DEPTH
+        }
+    }
+
+    /// Compute the time limit for this search in milliseconds. Under a clock, the base share
+    /// of it is scaled by `slow_mover` percent (Slow Mover; 100 = unchanged).
+    pub fn compute_time_ms(&self, side: Color, slow_mover: u32) -> u64 {
+        if self.infinite {
+            return 0;
+        }
+        if let Some(mt) = self.movetime {
+            return mt;
+        }
+
+        let (my_time, my_inc) = if side == Color::White {
+            (self.wtime.unwrap_or(0), self.winc.unwrap_or(0))
+        } else {
+            (self.btime.unwrap_or(0), self.binc.unwrap_or(0))
+        };
+
+        if my_time == 0 {
+            return 0; // No time control = infinite (depth-limited)
+        }
+
+        let moves_left = self.moves_to_go.unwrap_or(30);
+        let base = my_time / moves_left.max(1) * slow_mover as u64 / 100;
+        let inc_bonus = my_inc * 3 / 4;
+        let allocated = base + inc_bonus;
+
+        allocated.min(self.max_time_ms(side))
+    }
+
+    /// The most a search by `side` may take: the move time, or 80% of the remaining clock
+    /// (0 = no time limit)
+    pub fn max_time_ms(&self, side: Color) -> u64 {
+        if self.infinite {
+            return 0;
+        }
+        if let Some(mt) = self.movetime {
+            return mt;
+        }
+        let my_time = if side == Color::White { self.wtime } else { self.btime };
+        my_time.unwrap_or(0) * 4 / 5
+    }
+}
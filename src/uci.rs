@@ -1,34 +1,110 @@
 
 use std::io::{self, BufRead};
-use std::str::FromStr;
-use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::Arc;
+use std::sync::{mpsc, Arc, Mutex};
 use std::thread;
 
-use crate::board::{Board, ChessMove, Color, File, Piece, Rank, Square};
-
-use crate::search::{self, SearchState};
-use crate::types::{EngineConfig, DEFAULT_DEPTH};
+use crate::board::{Board, ChessMove};
+use crate::book;
+use crate::engine::{Engine, EngineEvent, Report};
+use crate::game::Game;
+use crate::json;
+use crate::opponent;
+use crate::output::{self, send};
+use crate::perft;
+use crate::search;
+use crate::trace::trace_span;
+#[cfg(feature = "tune")]
+use crate::tune;
+use crate::types::{EngineConfig, SearchLimits, SearchResult};
 
 pub fn run() {
-    let stdin = io::stdin();
-
-    let mut board = Board::default();
-    let mut config = EngineConfig::default();
-    let mut search_state: Option<SearchState> = Some(SearchState::new());
-    let mut stop_flag: Arc<AtomicBool> = Arc::new(AtomicBool::new(false));
-    let mut search_thread: Option<thread::JoinHandle<SearchState>> = None;
-    let mut position_history: Vec<u64> = Vec::new();
-
-    for line in stdin.lock().lines() {
-        let line = match line {
-            Ok(l) => l,
-            Err(_) => break,
+    run_with_options(&[]);
+}
+
+/// Run the UCI loop with `(name, value)` options applied first, as if sent with `setoption`
+pub fn run_with_options(options: &[(&str, &str)]) {
+    run_loop(read_input(), options);
+    #[cfg(not(target_arch = "wasm32"))]
+    crate::signals::clear();
+    output::flush();
+}
+
+/// What a running search needs to print its result: the position and the options of its `go`
+#[derive(Clone, Copy)]
+struct GoOutput {
+    board: Board,
+    show_stats: bool,
+    json_output: bool,
+    debug: bool,
+}
+
+/// Print the end of a search: its last info line, the stats if asked for, then `bestmove`
+fn print_result(go: &GoOutput, result: &SearchResult) {
+    let board = &go.board;
+    if !go.json_output {
+        send!(
+            "info depth {} seldepth {} {} nodes {} time {} nps {} hashfull {} tbhits {} pv {}",
+            result.depth, result.seldepth, search::format_score(result.score), result.nodes,
+            result.time_ms, result.nps, result.hashfull, result.tb_hits, search::pv_to_uci(board, &result.pv)
+        );
+    }
+    if go.show_stats || go.debug {
+        for line in result.stats.report() {
+            send!("{}", line);
+        }
+    }
+    if go.debug {
+        send!("info string debug tt hashfull {} permille", result.hashfull);
+    }
+
+    if go.json_output {
+        send!("{}", json::result_json(board, result));
+    } else if let Some(m) = result.best_move {
+        send!("bestmove {}", board.move_to_uci(m));
+    } else {
+        send!("bestmove 0000");
+    }
+}
+
+fn run_loop(inputs: mpsc::Receiver<Input>, options: &[(&str, &str)]) {
+    let mut game = Game::default();
+    let mut debug = false;
+    // Set by `go` before the search starts; the worker prints the result with it
+    let output: Arc<Mutex<Option<GoOutput>>> = Arc::new(Mutex::new(None));
+    let mut engine = {
+        let output = output.clone();
+        Engine::printing(move |event| match event {
+            EngineEvent::BestMove(result) => {
+                if let Some(go) = *output.lock().expect("uci output poisoned") {
+                    print_result(&go, &result);
+                }
+            }
+            EngineEvent::Message(message) => send!("info string {}", message),
+            EngineEvent::Info(_) => {}
+        })
+    };
+    for &(name, value) in options {
+        engine.set_option(name, value);
+    }
+    // Whether the last search was `go infinite`, which never ends by itself
+    let mut infinite = false;
+
+    for input in inputs {
+        let line = match input {
+            Input::Line(line) => line,
+            Input::End => break,
+            // Like `quit`: the running search answers with its bestmove first
+            Input::Interrupt => {
+                stop_search(&engine);
+                break;
+            }
         };
         let line = line.trim().to_string();
         if line.is_empty() {
             continue;
         }
+        output::log_input(&line);
+        let _command = trace_span!(DEBUG, "uci", command = %line);
 
         let tokens: Vec<&str> = line.split_whitespace().collect();
         if tokens.is_empty() {
@@ -37,162 +113,222 @@ pub fn run() {
 
         match tokens[0] {
             "uci" => {
-                println!("id name chess-engine");
-                println!("id author yourname");
-                println!("option name Hash type spin default 64 min 1 max 4096");
-                println!("option name SyzygyPath type string default <empty>");
-                println!("uciok");
+                send!("id name chess-engine");
+                send!("id author yourname");
+                send!("option name Hash type spin default 64 min 1 max 4096");
+                send!("option name Threads type spin default 1 min 1 max {}", search::MAX_THREADS);
+                send!("option name NUMA Policy type combo default auto var auto var none");
+                send!("option name SyzygyPath type string default <empty>");
+                send!("option name SearchStats type check default false");
+                for toggle in ["SearchNullMove", "SearchLMR", "SearchAspiration", "SearchExtensions", "SearchSEE", "SearchFutility"] {
+                    send!("option name {} type check default true", toggle);
+                }
+                send!("option name SearchBackend type combo default alphabeta var alphabeta var mcts");
+                send!("option name EvalFile type string default <empty>");
+                for n in 1..=book::MAX_BOOKS {
+                    let (file, depth) = match n {
+                        1 => ("BookFile".to_string(), "Book Depth".to_string()),
+                        n => (format!("BookFile{}", n), format!("Book Depth {}", n)),
+                    };
+                    send!("option name {} type string default <empty>", file);
+                    send!("option name {} type spin default {1} min 0 max {1}", depth, search::MAX_BOOK_DEPTH);
+                }
+                send!("option name Book Variety type spin default {} min 0 max {}", book::DEFAULT_BOOK_VARIETY, book::MAX_BOOK_VARIETY);
+                send!("option name EvalMode type combo default nnue var nnue var hybrid var classical");
+                send!("option name Style type combo default Normal var Solid var Normal var Aggressive");
+                send!("option name Skill Level type spin default 20 min 0 max 20");
+                send!("option name MultiPV type spin default 1 min 1 max {}", search::MAX_MULTI_PV);
+                send!("option name OpeningVariety type spin default 0 min 0 max {}", search::MAX_VARIETY_PLIES);
+                send!("option name OpeningVarietyMargin type spin default 20 min 0 max {}", search::MAX_VARIETY_MARGIN);
+                send!("option name Contempt type spin default 0 min -{0} max {0}", search::MAX_CONTEMPT);
+                send!("option name DynamicContempt type check default true");
+                send!("option name UCI_RatingAdv type spin default 0 min -3000 max 3000");
+                send!("option name UCI_Opponent type string default <empty>");
+                send!("option name Engine Rating type spin default {} min 0 max {}", opponent::DEFAULT_ENGINE_RATING, opponent::MAX_RATING);
+                send!("option name Opponent Contempt type spin default {} min 0 max {}", opponent::DEFAULT_OPPONENT_CONTEMPT, opponent::MAX_OPPONENT_CONTEMPT);
+                send!("option name Opponent Book Variety type spin default {} min 0 max {}", opponent::DEFAULT_OPPONENT_BOOK_VARIETY, book::MAX_BOOK_VARIETY);
+                send!("option name Minimum Thinking Time type spin default 0 min 0 max {}", search::MAX_MIN_THINK_MS);
+                send!("option name Slow Mover type spin default {} min {} max {}", search::DEFAULT_SLOW_MOVER, search::MIN_SLOW_MOVER, search::MAX_SLOW_MOVER);
+                send!("option name nodestime type spin default 0 min 0 max {}", search::MAX_NODES_TIME);
+                send!("option name UCI_ShowWDL type check default false");
+                send!("option name UCI_ShowRefutations type check default false");
+                send!("option name OutputFormat type combo default uci var uci var json");
+                send!("option name UCI_Chess960 type check default false");
+                send!("option name Odds type combo default none var none var pawn var knight var rook var queen");
+                send!("option name OddsSide type combo default white var white var black");
+                send!("option name Deterministic type check default false");
+                send!("option name Log File type string default <empty>");
+                #[cfg(feature = "tune")]
+                for param in tune::PARAMS {
+                    send!("{}", param.uci_option());
+                }
+                send!("uciok");
+            }
+            "debug" => {
+                // Applies from the next search on if one is running
+                debug = tokens.get(1) == Some(&"on");
+                engine.set_debug(debug);
             }
             "isready" => {
-                wait_for_search(&mut search_thread, &mut search_state);
-                println!("readyok");
+                // A running search doesn't stop the engine from answering; anything else
+                // queued (a Hash resize, ucinewgame's cache clear) is finished first
+                if !engine.searching() {
+                    engine.wait();
+                }
+                send!("readyok");
             }
             "ucinewgame" => {
-                wait_for_search(&mut search_thread, &mut search_state);
-                board = Board::default();
-                position_history.clear();
-                if let Some(ref mut ss) = search_state {
-                    ss.tt.clear();
-                }
+                stop_search(&engine);
+                game = Game::new(engine.config().start_position());
+                engine.new_game();
             }
             "position" => {
-                wait_for_search(&mut search_thread, &mut search_state);
-                parse_position(&tokens, &mut board, &mut position_history);
+                // Mid-search: the running search ends with its bestmove first
+                stop_search(&engine);
+                parse_position(&tokens, &mut game, &engine.config());
+                engine.set_position(game.clone());
             }
             "go" => {
-                wait_for_search(&mut search_thread, &mut search_state);
-
-                let go_params = parse_go(&tokens);
-                let max_depth = go_params.depth.unwrap_or(DEFAULT_DEPTH);
-
-                let mut ss = search_state.take().expect("search state missing");
-                ss.reset();
-                ss.time_limit_ms = go_params.compute_time_ms(board.side_to_move());
-                ss.position_history = position_history.clone();
-
-                // Set up shared stop flag
-                let flag = Arc::new(AtomicBool::new(false));
-                stop_flag = flag.clone();
-                ss.stop = flag;
-
-                let board_copy = board;
-
-                search_thread = Some(thread::spawn(move || {
-                    let result = search::search(&board_copy, &mut ss, max_depth);
-
-                    let elapsed_ms = ss.start_time.elapsed().as_millis().max(1) as u64;
-                    let nps = result.nodes * 1000 / elapsed_ms;
-                    let score_str = search::format_score(result.score);
-                    println!(
-                        "info depth {} {} nodes {} time {} nps {}",
-                        result.depth, score_str, result.nodes, elapsed_ms, nps
-                    );
-
-                    if let Some(m) = result.best_move {
-                        println!("bestmove {}", m);
-                    } else {
-                        println!("bestmove 0000");
+                // A second go replaces the running search, which still answers with a bestmove
+                stop_search(&engine);
+
+                // go perft <depth>: divided perft, as Stockfish does
+                if tokens.get(1) == Some(&"perft") {
+                    match tokens.get(2).and_then(|t| t.parse::<u32>().ok()) {
+                        Some(depth) => {
+                            perft::run(game.board(), depth, true);
+                        }
+                        None => send!("info string usage: go perft <depth>"),
                     }
+                    continue;
+                }
 
-                    ss
-                }));
+                let go_params = parse_go(&tokens);
+                let config = engine.config();
+                infinite = go_params.infinite;
+                *output.lock().expect("uci output poisoned") = Some(GoOutput {
+                    board: *game.board(),
+                    show_stats: config.search_stats,
+                    json_output: config.json_output,
+                    debug,
+                });
+                engine.go(go_params);
             }
             "stop" => {
-                stop_flag.store(true, Ordering::SeqCst);
-                wait_for_search(&mut search_thread, &mut search_state);
+                stop_search(&engine);
             }
+            // Queued behind a running search, like any other change to the engine's state
             "setoption" => {
-                wait_for_search(&mut search_thread, &mut search_state);
-                if let Some(ref mut ss) = search_state {
-                    parse_setoption(&tokens, &mut config, ss);
+                if let Some((name, value)) = parse_setoption(&tokens) {
+                    engine.set_option(&name, &value);
                 }
             }
             "quit" => {
-                stop_flag.store(true, Ordering::SeqCst);
-                wait_for_search(&mut search_thread, &mut search_state);
+                stop_search(&engine);
                 break;
             }
+            #[cfg(feature = "tune")]
+            "spsa" => {
+                for param in tune::PARAMS {
+                    send!("{}", param.spsa_line());
+                }
+            }
+            // These need the state to themselves, and a running search (an infinite one above
+            // all) would hold them up until it is stopped
+            "stats" | "moves" | "probe" | "perft" if engine.searching() => {
+                send!("info string busy: stop the search before {}", tokens[0]);
+            }
+            "stats" => {
+                for line in engine.report(Report::Stats) {
+                    send!("{}", line);
+                }
+            }
+            "perft" => {
+                // perft <depth> | perft divide <depth>
+                engine.wait();
+                let divide = tokens.get(1) == Some(&"divide");
+                let depth_token = if divide { tokens.get(2) } else { tokens.get(1) };
+                match depth_token.and_then(|t| t.parse::<u32>().ok()) {
+                    Some(depth) => {
+                        perft::run(game.board(), depth, divide);
+                    }
+                    None => send!("info string usage: perft [divide] <depth>"),
+                }
+            }
+            "moves" => {
+                for line in engine.report(Report::Moves) {
+                    send!("{}", line);
+                }
+            }
+            "probe" => {
+                for line in engine.report(Report::Probe) {
+                    send!("{}", line);
+                }
+            }
+            "flip" => {
+                stop_search(&engine);
+                if flip_side(&mut game) {
+                    engine.set_position(game.clone());
+                } else {
+                    send!("info string cannot flip: the side to move is in check");
+                }
+            }
             "d" | "print" => {
-                println!("{}", board);
+                let unicode = tokens.get(1).is_some_and(|t| t.eq_ignore_ascii_case("unicode"));
+                send!("{}", game.board().diagram(unicode));
             }
             _ => {}
         }
     }
-}
 
-/// Wait for a running search thread to finish and recover the SearchState.
-fn wait_for_search(
-    handle: &mut Option<thread::JoinHandle<SearchState>>,
-    state: &mut Option<SearchState>,
-) {
-    if let Some(h) = handle.take() {
-        match h.join() {
-            Ok(ss) => *state = Some(ss),
-            Err(_) => {
-                // Search thread panicked — create fresh state
-                *state = Some(SearchState::new());
-            }
-        }
+    // Let a search still running at the end of input finish and print its bestmove; dropping
+    // the engine stops an infinite one
+    if !infinite {
+        engine.wait();
     }
 }
 
-/// Parsed `go` command parameters.
-struct GoParams {
-    depth: Option<u8>,
-    movetime: Option<u64>,
-    wtime: Option<u64>,
-    btime: Option<u64>,
-    winc: Option<u64>,
-    binc: Option<u64>,
-    moves_to_go: Option<u64>,
-    infinite: bool,
+/// What the command loop reads
+enum Input {
+    Line(String),
+    /// stdin closed
+    End,
+    /// Ctrl-C or SIGTERM
+    #[cfg_attr(target_arch = "wasm32", allow(dead_code))]
+    Interrupt,
 }
 
-impl GoParams {
-    fn new() -> Self {
-        Self {
-            depth: None,
-            movetime: None,
-            wtime: None,
-            btime: None,
-            winc: None,
-            binc: None,
-            moves_to_go: None,
-            infinite: false,
-        }
+/// Lines from stdin, read on a thread of their own so that a signal can get a word in while
+/// the loop waits for the next command
+fn read_input() -> mpsc::Receiver<Input> {
+    let (inputs, receiver) = mpsc::channel();
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let inputs = inputs.clone();
+        crate::signals::on_interrupt(move || {
+            let _ = inputs.send(Input::Interrupt);
+        });
     }
-
-    /// Compute the time limit for this search in milliseconds.
-    fn compute_time_ms(&self, side: Color) -> u64 {
-        if self.infinite {
-            return 0;
-        }
-        if let Some(mt) = self.movetime {
-            return mt;
-        }
-
-        let (my_time, my_inc) = if side == Color::White {
-            (self.wtime.unwrap_or(0), self.winc.unwrap_or(0))
-        } else {
-            (self.btime.unwrap_or(0), self.binc.unwrap_or(0))
-        };
-
-        if my_time == 0 {
-            return 0; // No time control = infinite (depth-limited)
+    thread::spawn(move || {
+        for line in io::stdin().lock().lines() {
+            let Ok(line) = line else { break };
+            if inputs.send(Input::Line(line)).is_err() {
+                return;
+            }
         }
+        let _ = inputs.send(Input::End);
+    });
+    receiver
+}
 
-        let moves_left = self.moves_to_go.unwrap_or(30);
-        let base = my_time / moves_left.max(1);
-        let inc_bonus = my_inc * 3 / 4;
-        let allocated = base + inc_bonus;
-
-        // Don't use more than 80% of remaining time
-        allocated.min(my_time * 4 / 5)
-    }
+/// Stop the running search, if any, and wait until it has printed its bestmove
+fn stop_search(engine: &Engine) {
+    engine.stop();
+    engine.wait();
 }
 
-fn parse_go(tokens: &[&str]) -> GoParams {
-    let mut params = GoParams::new();
+fn parse_go(tokens: &[&str]) -> SearchLimits {
+    let mut params = SearchLimits::default();
     let mut i = 1;
 
     while i < tokens.len() {
@@ -233,15 +369,34 @@ fn parse_go(tokens: &[&str]) -> GoParams {
                     params.binc = tokens[i].parse().ok();
                 }
             }
+            "nodes" => {
+                i += 1;
+                if i < tokens.len() {
+                    params.nodes = tokens[i].parse().ok();
+                }
+            }
             "movestogo" => {
                 i += 1;
                 if i < tokens.len() {
                     params.moves_to_go = tokens[i].parse().ok();
                 }
             }
+            "mate" => {
+                i += 1;
+                if i < tokens.len() {
+                    params.mate = tokens[i].parse().ok();
+                }
+            }
             "infinite" => {
                 params.infinite = true;
             }
+            // Every move that follows; normalized against the position when the search starts
+            "searchmoves" => {
+                while let Some(mv) = tokens.get(i + 1).and_then(|t| ChessMove::from_uci(t)) {
+                    params.searchmoves.push(mv);
+                    i += 1;
+                }
+            }
             _ => {}
         }
         i += 1;
@@ -250,16 +405,17 @@ fn parse_go(tokens: &[&str]) -> GoParams {
     params
 }
 
-fn parse_position(tokens: &[&str], board: &mut Board, history: &mut Vec<u64>) {
+/// `startpos` is the odds position when the Odds option is set
+fn parse_position(tokens: &[&str], game: &mut Game, config: &EngineConfig) {
     if tokens.len() < 2 {
         return;
     }
 
     let mut idx = 1;
 
-    if tokens[idx] == "startpos" {
-        *board = Board::default();
+    let mut board = if tokens[idx] == "startpos" {
         idx += 1;
+        config.start_position()
     } else if tokens[idx] == "fen" {
         idx += 1;
         // Collect FEN fields up to "moves" keyword or end of tokens
@@ -268,74 +424,51 @@ fn parse_position(tokens: &[&str], board: &mut Board, history: &mut Vec<u64>) {
             fen_parts.push(tokens[idx]);
             idx += 1;
         }
-        if fen_parts.len() >= 4 {
-            let fen_str = fen_parts.join(" ");
-            match Board::from_str(&fen_str) {
-                Ok(b) => *board = b,
-                Err(_) => return,
-            }
-        } else {
+        if fen_parts.len() < 4 {
             return;
         }
+        match Board::from_fen_lenient(&fen_parts.join(" ")) {
+            Ok(b) => b,
+            Err(_) => return,
+        }
+    } else if let Some(board) = perft::named_position(tokens[idx]) {
+        idx += 1;
+        board
     } else {
         return;
-    }
+    };
 
-    // Build position history for draw detection
-    history.clear();
-    history.push(board.get_hash());
+    // Moves arrive (and go out) as king-takes-rook when UCI_Chess960 is on
+    board.set_chess960(config.chess960);
+    *game = Game::new(board);
 
-    // Parse moves
+    // Parse moves (the game's position stack doubles as the repetition history)
     if idx < tokens.len() && tokens[idx] == "moves" {
         idx += 1;
         for &move_str in &tokens[idx..] {
-            if let Some(m) = parse_uci_move(board, move_str) {
-                *board = board.make_move_new(m);
-                history.push(board.get_hash());
+            if let Some(m) = parse_uci_move(game.board(), move_str) {
+                game.make_move(m).expect("parse_uci_move only returns legal moves");
             }
         }
     }
 }
 
-/// Parse a UCI move string directly into squares + optional promotion piece.
-/// Zero heap allocations (no move generation/string comparison).
-fn parse_uci_move(board: &Board, move_str: &str) -> Option<ChessMove> {
-    if move_str.len() < 4 {
-        return None;
-    }
-    let bytes = move_str.as_bytes();
-
-    let src_file = bytes[0].wrapping_sub(b'a');
-    let src_rank = bytes[1].wrapping_sub(b'1');
-    let dst_file = bytes[2].wrapping_sub(b'a');
-    let dst_rank = bytes[3].wrapping_sub(b'1');
-
-    if src_file >= 8 || src_rank >= 8 || dst_file >= 8 || dst_rank >= 8 {
-        return None;
-    }
-
-    let src = Square::make_square(
-        Rank::from_index(src_rank as usize),
-        File::from_index(src_file as usize),
-    );
-    let dst = Square::make_square(
-        Rank::from_index(dst_rank as usize),
-        File::from_index(dst_file as usize),
-    );
-
-    let promo = if move_str.len() >= 5 {
-        match bytes[4] {
-            b'q' => Some(Piece::Queen),
-            b'r' => Some(Piece::Rook),
-            b'b' => Some(Piece::Bishop),
-            b'n' => Some(Piece::Knight),
-            _ => None,
+/// Hand the move to the other side (clearing en passant) as a new game from the flipped
+/// position; false, with `game` unchanged, when the side to move is in check
+fn flip_side(game: &mut Game) -> bool {
+    match game.board().null_move() {
+        Some(board) => {
+            *game = Game::new(board);
+            true
         }
-    } else {
-        None
-    };
+        None => false,
+    }
+}
 
-    let mv = ChessMove::new(src, dst, promo);
+/// Parse a UCI move string and accept it only if it is legal on `board`.
+/// Standard castling notation (e1g1) is mapped onto the internal king-takes-rook move.
+fn parse_uci_move(board: &Board, move_str: &str) -> Option<ChessMove> {
+    let mv = board.normalize_move(ChessMove::from_uci(move_str)?);
     if board.legal(mv) {
         Some(mv)
     } else {
@@ -343,72 +476,94 @@ fn parse_uci_move(board: &Board, move_str: &str) -> Option<ChessMove> {
     }
 }
 
-fn parse_setoption(tokens: &[&str], config: &mut EngineConfig, state: &mut SearchState) {
-    let name_idx = tokens.iter().position(|&t| t == "name");
-    let value_idx = tokens.iter().position(|&t| t == "value");
-
-    if let (Some(ni), Some(vi)) = (name_idx, value_idx) {
-        let name: String = tokens[ni + 1..vi].join(" ");
-        let value: String = tokens[vi + 1..].join(" ");
-
-        match name.to_lowercase().as_str() {
-            "hash" => {
-                if let Ok(mb) = value.parse::<usize>() {
-                    config.hash_mb = mb.clamp(1, 4096);
-                    state.resize_tt(config.hash_mb);
-                }
-            }
-            "syzygypath" => {
-                if value.is_empty() || value == "<empty>" {
-                    config.syzygy_path = None;
-                    state.syzygy = None;
-                } else {
-                    config.syzygy_path = Some(value.clone());
-                    state.load_syzygy(&value);
-                }
-            }
-            _ => {}
-        }
-    }
+/// `setoption name <name> value <value>`: the option's name and value, both of which may
+/// contain spaces
+fn parse_setoption(tokens: &[&str]) -> Option<(String, String)> {
+    let ni = tokens.iter().position(|&t| t == "name")?;
+    let vi = tokens.iter().position(|&t| t == "value")?;
+    Some((tokens.get(ni + 1..vi)?.join(" "), tokens[vi + 1..].join(" ")))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::str::FromStr;
+    use crate::board::{Color, Piece};
+    use crate::evaluation::EvalMode;
+    use crate::engine::apply_option;
+    use crate::handicap::Odds;
+    use crate::search::SearchState;
+
+    /// `setoption` as the engine's worker applies it
+    fn setoption(tokens: &[&str], config: &mut EngineConfig, state: &mut SearchState) {
+        let (name, value) = parse_setoption(tokens).unwrap();
+        let _ = apply_option(&name, &value, config, state);
+    }
 
     #[test]
     fn test_parse_position_startpos() {
-        let mut board = Board::default();
-        let mut history = Vec::new();
+        let mut game = Game::default();
         let tokens = vec!["position", "startpos"];
-        parse_position(&tokens, &mut board, &mut history);
-        assert_eq!(board, Board::default());
-        assert_eq!(history.len(), 1);
+        parse_position(&tokens, &mut game, &EngineConfig::default());
+        assert_eq!(*game.board(), Board::default());
+        assert_eq!(game.hashes().len(), 1);
+
+        // With Odds set, startpos is the odds position
+        let config = EngineConfig { odds: Some(Odds::Queen), ..Default::default() };
+        parse_position(&tokens, &mut game, &config);
+        assert_eq!(game.board().to_string(), "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNB1KBNR w KQkq - 0 1");
     }
 
     #[test]
     fn test_parse_position_startpos_with_moves() {
-        let mut board = Board::default();
-        let mut history = Vec::new();
+        let mut game = Game::default();
         let tokens = vec!["position", "startpos", "moves", "e2e4", "e7e5"];
-        parse_position(&tokens, &mut board, &mut history);
-        assert_ne!(board, Board::default());
+        parse_position(&tokens, &mut game, &EngineConfig::default());
+        assert_ne!(*game.board(), Board::default());
         // startpos + 2 moves = 3 entries in history
-        assert_eq!(history.len(), 3);
+        assert_eq!(game.hashes().len(), 3);
+    }
+
+    #[test]
+    fn test_parse_position_named() {
+        let mut game = Game::default();
+        parse_position(&["position", "kiwipete", "moves", "e1g1"], &mut game, &EngineConfig::default());
+        assert_eq!(game.board().to_string(), "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R4RK1 b kq - 1 1");
+        assert_eq!(game.hashes().len(), 2);
+
+        parse_position(&["position", "Pos3"], &mut game, &EngineConfig::default());
+        assert_eq!(game.board().to_string(), "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1");
+        // An unknown name leaves the position alone
+        parse_position(&["position", "pos9"], &mut game, &EngineConfig::default());
+        assert_eq!(game.board().to_string(), "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1");
+    }
+
+    #[test]
+    fn test_flip_side() {
+        let mut game = Game::default();
+        parse_position(&["position", "startpos", "moves", "e2e4"], &mut game, &EngineConfig::default());
+        assert!(flip_side(&mut game));
+        assert_eq!(game.board().to_string(), "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 1");
+        assert_eq!(game.hashes().len(), 1);
+
+        // No flipping out of check
+        parse_position(&["position", "startpos", "moves", "f2f3", "e7e5", "g2g4", "d8h4"], &mut game, &EngineConfig::default());
+        let before = game.board().to_string();
+        assert!(!flip_side(&mut game));
+        assert_eq!(game.board().to_string(), before);
     }
 
     #[test]
     fn test_parse_position_fen() {
-        let mut board = Board::default();
-        let mut history = Vec::new();
+        let mut game = Game::default();
         let tokens = vec![
             "position", "fen",
             "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR",
             "b", "KQkq", "e3", "0", "1",
         ];
-        parse_position(&tokens, &mut board, &mut history);
-        assert_ne!(board, Board::default());
-        assert_eq!(history.len(), 1);
+        parse_position(&tokens, &mut game, &EngineConfig::default());
+        assert_ne!(*game.board(), Board::default());
+        assert_eq!(game.hashes().len(), 1);
     }
 
     #[test]
@@ -416,6 +571,16 @@ mod tests {
         let tokens = vec!["go", "depth", "6"];
         let params = parse_go(&tokens);
         assert_eq!(params.depth, Some(6));
+        assert_eq!(parse_go(&["go", "nodes", "10000"]).nodes, Some(10000));
+    }
+
+    #[test]
+    fn test_parse_go_mate_and_searchmoves() {
+        let params = parse_go(&["go", "searchmoves", "e2e4", "d2d4", "mate", "3"]);
+        assert_eq!(params.mate, Some(3));
+        let moves: Vec<String> = params.searchmoves.iter().map(|m| m.to_string()).collect();
+        assert_eq!(moves, ["e2e4", "d2d4"]);
+        assert!(parse_go(&["go", "depth", "4"]).searchmoves.is_empty());
     }
 
     #[test]
@@ -430,11 +595,14 @@ mod tests {
 
     #[test]
     fn test_compute_time_ms() {
-        let mut params = GoParams::new();
-        params.wtime = Some(60000);
-        params.winc = Some(1000);
-        let time = params.compute_time_ms(Color::White);
+        let params = SearchLimits { wtime: Some(60000), winc: Some(1000), ..Default::default() };
+        let time = params.compute_time_ms(Color::White, 100);
         assert!(time > 0 && time <= 48000, "Time allocation {} out of range", time);
+        // Slow Mover scales the clock's share, not the increment's, and stays under the cap
+        assert_eq!(params.compute_time_ms(Color::White, 200) - time, 2000);
+        let last_move = SearchLimits { moves_to_go: Some(1), ..params.clone() };
+        assert_eq!(last_move.compute_time_ms(Color::White, 1000), 48000);
+        assert!(params.compute_time_ms(Color::White, 50) < time);
     }
 
     #[test]
@@ -442,10 +610,94 @@ mod tests {
         let mut config = EngineConfig::default();
         let mut state = SearchState::new();
         let tokens = vec!["setoption", "name", "Hash", "value", "128"];
-        parse_setoption(&tokens, &mut config, &mut state);
+        setoption(&tokens, &mut config, &mut state);
         assert_eq!(config.hash_mb, 128);
     }
 
+    #[test]
+    fn test_parse_setoption_skill_level() {
+        let mut config = EngineConfig::default();
+        let mut state = SearchState::new();
+        setoption(&["setoption", "name", "Skill", "Level", "value", "5"], &mut config, &mut state);
+        assert_eq!((config.skill_level, state.skill_level), (5, 5));
+        setoption(&["setoption", "name", "Skill", "Level", "value", "99"], &mut config, &mut state);
+        assert_eq!(state.skill_level, 20);
+    }
+
+    #[test]
+    fn test_parse_setoption_search_stats() {
+        let mut config = EngineConfig::default();
+        let mut state = SearchState::new();
+        let tokens = vec!["setoption", "name", "SearchStats", "value", "true"];
+        setoption(&tokens, &mut config, &mut state);
+        assert!(config.search_stats);
+    }
+
+    #[test]
+    fn test_parse_setoption_show_wdl() {
+        let mut config = EngineConfig::default();
+        let mut state = SearchState::new();
+        let tokens = vec!["setoption", "name", "UCI_ShowWDL", "value", "true"];
+        setoption(&tokens, &mut config, &mut state);
+        assert!(config.show_wdl && state.show_wdl);
+    }
+
+    #[test]
+    fn test_parse_setoption_chess960() {
+        let mut config = EngineConfig::default();
+        let mut state = SearchState::new();
+        let tokens = vec!["setoption", "name", "UCI_Chess960", "value", "true"];
+        setoption(&tokens, &mut config, &mut state);
+        assert!(config.chess960);
+    }
+
+    #[test]
+    fn test_parse_position_chess960_castling() {
+        let mut game = Game::default();
+        // Shredder-FEN castling field; king on b1 castles queenside with the a1 rook
+        let tokens = vec!["position", "fen", "4k3/8/8/8/8/8/8/RK5R", "w", "HA", "-", "0", "1", "moves", "b1a1"];
+        parse_position(&tokens, &mut game, &EngineConfig { chess960: true, ..Default::default() });
+        assert_eq!(game.hashes().len(), 2);
+        assert_eq!(game.board().to_string(), "4k3/8/8/8/8/8/8/2KR3R b - - 1 1");
+
+        // In classical mode with a standard position, castling still prints as e1g1
+        let tokens = vec!["position", "fen", "r3k2r/8/8/8/8/8/8/R3K2R", "w", "KQkq", "-", "0", "1"];
+        parse_position(&tokens, &mut game, &EngineConfig::default());
+        let mv = parse_uci_move(game.board(), "e1g1").unwrap();
+        assert_eq!(game.board().move_to_uci(mv), "e1g1");
+        parse_position(&tokens, &mut game, &EngineConfig { chess960: true, ..Default::default() });
+        assert!(parse_uci_move(game.board(), "e1g1").is_none());
+        let mv = parse_uci_move(game.board(), "e1h1").unwrap();
+        assert_eq!(game.board().move_to_uci(mv), "e1h1");
+    }
+
+    #[test]
+    fn test_parse_setoption_eval_mode() {
+        let mut config = EngineConfig::default();
+        let mut state = SearchState::new();
+        let tokens = vec!["setoption", "name", "EvalMode", "value", "hybrid"];
+        setoption(&tokens, &mut config, &mut state);
+        assert_eq!(config.eval_mode, EvalMode::Hybrid);
+
+        let tokens = vec!["setoption", "name", "EvalMode", "value", "bogus"];
+        setoption(&tokens, &mut config, &mut state);
+        assert_eq!(config.eval_mode, EvalMode::Hybrid);
+    }
+
+    #[test]
+    fn test_parse_setoption_eval_file() {
+        let mut config = EngineConfig::default();
+        let mut state = SearchState::new();
+        let tokens = vec!["setoption", "name", "EvalFile", "value", "/nonexistent/net.nnue"];
+        setoption(&tokens, &mut config, &mut state);
+        assert!(config.eval_file.is_none());
+
+        let tokens = vec!["setoption", "name", "EvalFile", "value", "nets/default.nnue"];
+        setoption(&tokens, &mut config, &mut state);
+        assert_eq!(config.eval_file.as_deref(), Some("nets/default.nnue"));
+        assert!(state.tables.eval_cache.network().is_some());
+    }
+
     #[test]
     fn test_parse_uci_move_basic() {
         let board = Board::default();
@@ -470,15 +722,42 @@ mod tests {
 
     #[test]
     fn test_position_history_tracking() {
-        let mut board = Board::default();
-        let mut history = Vec::new();
+        let mut game = Game::default();
         // Play moves that return to a similar structure
         let tokens = vec!["position", "startpos", "moves", "g1f3", "g8f6", "f3g1", "f6g8"];
-        parse_position(&tokens, &mut board, &mut history);
+        parse_position(&tokens, &mut game, &EngineConfig::default());
         // startpos + 4 moves = 5 entries
-        assert_eq!(history.len(), 5);
+        assert_eq!(game.hashes().len(), 5);
         // First and last positions should have the same hash (repetition)
+        let history = game.hashes();
         assert_eq!(history[0], history[4], "Position after Nf3 Nf6 Ng1 Ng8 should repeat startpos");
+        assert_eq!(game.repetition_history(), history);
+
+        // The search only gets the positions after the last pawn move
+        let tokens = vec!["position", "startpos", "moves", "g1f3", "g8f6", "f3g1", "f6g8", "e2e4", "b8c6"];
+        parse_position(&tokens, &mut game, &EngineConfig::default());
+        assert_eq!(game.repetition_history(), game.hashes()[5..]);
+    }
+
+    #[test]
+    fn test_commands_during_go_infinite() {
+        let (inputs, receiver) = mpsc::channel();
+        for line in ["go infinite", "setoption name Hash value 8", "stats", "moves", "probe", "perft 2", "isready", "stop", "stats"] {
+            inputs.send(Input::Line(line.to_string())).unwrap();
+        }
+        inputs.send(Input::End).unwrap();
+        // None of them may wait for the search, or `stop` is never read
+        let (done, finished) = mpsc::channel();
+        thread::spawn(move || {
+            run_loop(receiver, &[]);
+            let _ = done.send(());
+        });
+        assert!(finished.recv_timeout(std::time::Duration::from_secs(60)).is_ok(), "the loop hung during go infinite");
     }
 }
-// search runs in sep thread with an `Arc<AtomicBool>` stop flag shared w/ main thread.
+// The loop is the text layer over `Engine`: its worker thread owns the SearchState, so the TT and
+// eval cache never leave it, and the search prints its own info lines (`Engine::printing`). The
+// main thread only parses commands and queues them; `stop` flips the search's flag directly.
+// Commands that need the state settled (position, go, ucinewgame) stop the search and `wait`
+// first. `isready` is the exception while a search runs: it is answered straight away, as the
+// UCI spec expects.
@@ -0,0 +1,163 @@
+use std::str::FromStr;
+
+use js_sys::{Function, Object, Reflect};
+use wasm_bindgen::prelude::*;
+
+use crate::board::{Board, ChessMove, MoveGen};
+use crate::engine::apply_option;
+use crate::game::Game;
+use crate::search::{self, pv_to_uci, InfoSink, SearchState};
+use crate::types::{EngineConfig, Score, ScoreBound, SearchInfo, SearchLimits, SCORE_MATE};
+
+/// The engine for JavaScript. Searches run synchronously on the calling thread (run them in a
+/// Web Worker to keep a page responsive) and are bounded by depth and/or move time, measured
+/// with `Date.now()`.
+#[wasm_bindgen]
+pub struct WasmEngine {
+    game: Game,
+    config: EngineConfig,
+    state: SearchState,
+}
+
+impl Default for WasmEngine {
+    fn default() -> Self {
+        WasmEngine::new()
+    }
+}
+
+#[wasm_bindgen]
+impl WasmEngine {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> WasmEngine {
+        let mut state = SearchState::new();
+        state.clock = Some(js_sys::Date::now);
+        state.seed_skill(js_sys::Date::now() as u64);
+        state.silent = true;
+        WasmEngine { game: Game::default(), config: EngineConfig::default(), state }
+    }
+
+    /// Set the position from a FEN (or `"startpos"`, the odds position if Odds is set) and UCI
+    /// moves separated by spaces
+    #[wasm_bindgen(js_name = setPosition)]
+    pub fn set_position(&mut self, fen: &str, moves: &str) -> Result<(), JsError> {
+        let start = if fen == "startpos" { self.config.start_position() } else { Board::from_str(fen).map_err(|e| JsError::new(&e.to_string()))? };
+        let mut game = Game::new(start);
+        for text in moves.split_whitespace() {
+            let mv = ChessMove::from_uci(text).ok_or_else(|| JsError::new(&format!("Invalid move {}", text)))?;
+            let mv = game.board().normalize_move(mv);
+            game.make_move(mv).map_err(|e| JsError::new(&e))?;
+        }
+        self.game = game;
+        Ok(())
+    }
+
+    /// FEN of the current position
+    pub fn fen(&self) -> String {
+        self.game.board().to_string()
+    }
+
+    /// Legal moves of the current position in UCI notation
+    #[wasm_bindgen(js_name = legalMoves)]
+    pub fn legal_moves(&self) -> Vec<String> {
+        let board = self.game.board();
+        MoveGen::new_legal(board).map(|mv| board.move_to_uci(mv)).collect()
+    }
+
+    /// Set an option by its UCI name (Hash, EvalMode, Skill Level, ...). Returns a message
+    /// worth showing, if any.
+    #[wasm_bindgen(js_name = setOption)]
+    pub fn set_option(&mut self, name: &str, value: &str) -> Option<String> {
+        apply_option(name, value, &mut self.config, &mut self.state).unwrap_or_else(|e| Some(e.to_string()))
+    }
+
+    /// Back to the start position (see Odds), with the eval cache cleared (and the TT, in deterministic mode)
+    #[wasm_bindgen(js_name = newGame)]
+    pub fn new_game(&mut self) {
+        self.game = Game::new(self.config.start_position());
+        self.state.new_game();
+    }
+
+    /// Search the current position to `depth` plies and/or for `movetimeMs`. `onInfo` is
+    /// called after each iteration with `{depth, multipv, score | mate, nodes, timeMs, nps, pv}`, and
+    /// with `bound: "lower" | "upper"` added when an aspiration window fails.
+    /// Returns `{bestMove, score | mate, depth, seldepth, nodes, timeMs, nps, hashfull, tbhits, pv}`,
+    /// plus `partialDepth` when the move time cut an iteration short; `bestMove` is null without
+    /// legal moves.
+    pub fn search(&mut self, depth: Option<u8>, movetime_ms: Option<f64>, on_info: Option<Function>) -> JsValue {
+        let board = *self.game.board();
+        let limits = SearchLimits { depth, movetime: movetime_ms.map(|ms| ms as u64), ..Default::default() };
+
+        self.state.reset();
+        self.state.position_history = self.game.repetition_history();
+        self.state.info_sink = on_info.map(|callback| {
+            let callback = JsCallback(callback);
+            Box::new(move |info: &SearchInfo| {
+                // An exception thrown by the callback shouldn't abort the search
+                let _ = callback.0.call1(&JsValue::NULL, &info_object(&board, info));
+            }) as InfoSink
+        });
+        let result = search::search(&board, &mut self.state, &limits);
+        self.state.info_sink = None;
+
+        let best_move = result.best_move.map_or(JsValue::NULL, |mv| board.move_to_uci(mv).into());
+        let object = Object::new();
+        set(&object, "bestMove", best_move);
+        set_score(&object, result.score);
+        set(&object, "depth", result.depth.into());
+        if let Some(depth) = result.partial_depth {
+            set(&object, "partialDepth", depth.into());
+        }
+        set(&object, "seldepth", result.seldepth.into());
+        set(&object, "nodes", (result.nodes as f64).into());
+        set(&object, "timeMs", (result.time_ms as f64).into());
+        set(&object, "nps", (result.nps as f64).into());
+        set(&object, "hashfull", result.hashfull.into());
+        set(&object, "tbhits", (result.tb_hits as f64).into());
+        set(&object, "pv", pv_to_uci(&board, &result.pv).into());
+        object.into()
+    }
+}
+
+/// A JS callback kept in the search's info sink, which has to be Send. wasm32 without the
+/// threads proposal has a single thread, so the callback never actually changes threads.
+struct JsCallback(Function);
+
+// SAFETY: see above; WasmEngine never hands its SearchState to another thread
+unsafe impl Send for JsCallback {}
+
+fn info_object(board: &Board, info: &SearchInfo) -> JsValue {
+    let object = Object::new();
+    set(&object, "depth", info.depth.into());
+    set(&object, "multipv", (info.multipv as u32).into());
+    set_score(&object, info.score);
+    match info.bound {
+        ScoreBound::Exact => {}
+        ScoreBound::Lower => set(&object, "bound", "lower".into()),
+        ScoreBound::Upper => set(&object, "bound", "upper".into()),
+    }
+    set(&object, "nodes", (info.nodes as f64).into());
+    set(&object, "timeMs", (info.time_ms as f64).into());
+    set(&object, "nps", (info.nps as f64).into());
+    set(&object, "pv", pv_to_uci(board, &info.pv).into());
+    object.into()
+}
+
+/// `mate` in moves (negative when getting mated) for mate scores, else `score` in centipawns
+fn set_score(object: &Object, score: Score) {
+    if score.abs() > SCORE_MATE - 100 {
+        let moves = (SCORE_MATE - score.abs() + 1) / 2;
+        set(object, "mate", (if score > 0 { moves } else { -moves }).into());
+    } else {
+        set(object, "score", score.into());
+    }
+}
+
+fn set(object: &Object, key: &str, value: JsValue) {
+    Reflect::set(object, &key.into(), &value).expect("setting a property on a plain object");
+}
+
+// The browser build has no threads, so WasmEngine owns a SearchState directly instead of going
+// through Engine's worker: a search blocks until its depth or move time runs out, and there is no
+// `stop`. Time comes from a callback clock (Date.now) because Instant panics on
+// wasm32-unknown-unknown. Results and info lines are plain JS objects built with Reflect, which
+// keeps the JS side free of generated wrapper classes beyond WasmEngine itself.
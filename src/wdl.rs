@@ -0,0 +1,104 @@
+use crate::board::Board;
+use crate::evaluation::game_phase;
+use crate::pst::TOTAL_PHASE;
+use crate::types::{Score, SCORE_MATE};
+
+/// Win/draw/loss probabilities in permille, from the side to move's perspective
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Wdl {
+    pub win: u32,
+    pub draw: u32,
+    pub loss: u32,
+}
+
+impl core::fmt::Display for Wdl {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        write!(f, "wdl {} {} {}", self.win, self.draw, self.loss)
+    }
+}
+
+/// Logistic model parameters as a function of material phase (0 = pawn endgame, 24 = full board):
+/// `a` is the eval with a 50% win chance, `b` the spread. Fitted to self-play results by
+/// `scripts/fit_wdl.py`: with little material left a given edge is drawn more often, so both
+/// fall as phase grows.
+fn model(phase: i32) -> (f64, f64) {
+    let m = phase.clamp(0, TOTAL_PHASE) as f64;
+    (346.2 - 12.06 * m, 247.8 - 3.15 * m)
+}
+
+/// Probability (permille) that `score` converts to a win
+fn win_rate(score: Score, phase: i32) -> u32 {
+    let (a, b) = model(phase);
+    (1000.0 / (1.0 + ((a - score as f64) / b).exp())).round() as u32
+}
+
+/// Convert a search score into WDL probabilities for the root position
+pub fn wdl(score: Score, board: &Board) -> Wdl {
+    if score.abs() > SCORE_MATE - 100 {
+        return if score > 0 {
+            Wdl { win: 1000, draw: 0, loss: 0 }
+        } else {
+            Wdl { win: 0, draw: 0, loss: 1000 }
+        };
+    }
+
+    let phase = game_phase(board);
+    let win = win_rate(score, phase);
+    // Rounding can push the sum just past 1000 for huge evals
+    let loss = win_rate(-score, phase).min(1000 - win);
+    Wdl { win, draw: 1000 - win - loss, loss }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_sums_to_1000() {
+        let board = Board::default();
+        for score in [-2000, -300, -50, 0, 50, 300, 2000] {
+            let w = wdl(score, &board);
+            assert_eq!(w.win + w.draw + w.loss, 1000);
+        }
+    }
+
+    #[test]
+    fn test_symmetric_and_monotonic() {
+        let board = Board::default();
+        let even = wdl(0, &board);
+        assert_eq!(even.win, even.loss);
+        assert!(even.draw > 100);
+
+        let up = wdl(200, &board);
+        let down = wdl(-200, &board);
+        assert_eq!(up.win, down.loss);
+        assert!(up.win > even.win);
+    }
+
+    #[test]
+    fn test_endgame_is_drawish() {
+        // The same edge is drawn more often with less material on the board, and an even
+        // pawn ending mostly is
+        let opening = Board::default();
+        let endgame = Board::from_str("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1").unwrap();
+        assert!(wdl(150, &endgame).win < wdl(150, &opening).win);
+        assert!(wdl(0, &endgame).draw > 500 && wdl(0, &endgame).draw > wdl(0, &opening).draw);
+    }
+
+    #[test]
+    fn test_mate_scores() {
+        let board = Board::default();
+        assert_eq!(wdl(SCORE_MATE - 5, &board).win, 1000);
+        assert_eq!(wdl(-SCORE_MATE + 5, &board).loss, 1000);
+        assert_eq!(wdl(SCORE_MATE - 5, &board).to_string(), "wdl 1000 0 0");
+    }
+}
+
+// WDL model: UCI_ShowWDL GUIs (and lichess) expect win/draw/loss odds next to the score. We map
+// centipawns through a logistic curve whose midpoint and slope scale with the material left, the
+// same shape as Stockfish's model. The coefficients are a maximum-likelihood fit to 79565
+// positions from `ferrite datagen wdl.txt --games 4000 --nodes 5000 --sample-rate 0.25 --seed
+// 4840`; games that short are decisive more often than real ones, so the opening's draw share
+// comes out low. Refit with `python3 scripts/fit_wdl.py wdl.txt` after eval changes, or on
+// longer games.
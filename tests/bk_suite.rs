@@ -1,57 +1,20 @@
-use std::fs;
-use std::str::FromStr;
-
-use ferrite::board::{Board, BoardStatus, ChessMove, Color, MoveGen, Piece, ALL_SQUARES};
+#![cfg(feature = "std")]
 
-/// Parse an EPD line: "<FEN> bm <move(s)>; id "<name>";"
-fn parse_epd_line(line: &str) -> Option<(String, Vec<String>, String)> {
-    let line = line.trim();
-    if line.is_empty() {
-        return None;
-    }
-
-    // Split on " bm "
-    let bm_idx = line.find(" bm ")?;
-    let fen = line[..bm_idx].to_string();
-    let rest = &line[bm_idx + 4..];
-
-    // Extract best moves (before semicolon)
-    let semi_idx = rest.find(';')?;
-    let moves_str = &rest[..semi_idx];
-    let best_moves: Vec<String> = moves_str.split_whitespace().map(|s| s.to_string()).collect();
-
-    // Extract id
-    let id = if let Some(id_start) = rest.find("id \"") {
-        let id_content = &rest[id_start + 4..];
-        if let Some(id_end) = id_content.find('"') {
-            id_content[..id_end].to_string()
-        } else {
-            "unknown".to_string()
-        }
-    } else {
-        "unknown".to_string()
-    };
+use std::fs;
 
-    Some((fen, best_moves, id))
-}
+use ferrite::board::{BitBoard, Board, BoardStatus, ChessMove, Color, MoveGen, Piece, ALL_SQUARES};
+use ferrite::epd::EpdRecord;
 
 /// Convert a ChessMove to SAN notation.
 fn move_to_san(board: &Board, mv: ChessMove) -> String {
     let piece = board.piece_on(mv.get_source()).unwrap();
-    let is_capture = board.piece_on(mv.get_dest()).is_some()
-        || (piece == Piece::Pawn
-            && mv.get_source().file() != mv.get_dest().file());
-
-    // Castling
-    if piece == Piece::King {
-        let from_file = mv.get_source().file().to_index();
-        let to_file = mv.get_dest().file().to_index();
-        if from_file == 4 && to_file == 6 {
-            return add_check_suffix(board, mv, "O-O".to_string());
-        }
-        if from_file == 4 && to_file == 2 {
-            return add_check_suffix(board, mv, "O-O-O".to_string());
-        }
+    let is_capture = board.is_capture(mv);
+
+    // Castling (encoded as king takes own rook)
+    if board.is_castling(mv) {
+        let kingside = mv.get_dest().file().to_index() > mv.get_source().file().to_index();
+        let san = if kingside { "O-O" } else { "O-O-O" };
+        return add_check_suffix(board, mv, san.to_string());
     }
 
     let mut san = String::new();
@@ -110,19 +73,17 @@ fn disambiguation(board: &Board, mv: ChessMove, piece: Piece) -> String {
     let mut needs_rank = false;
     let mut ambiguous = false;
 
-    let moves = MoveGen::new_legal(board);
+    let mut moves = MoveGen::new_legal(board);
+    moves.set_piece_mask(piece);
+    moves.set_iterator_mask(BitBoard::from_square(mv.get_dest()));
     for other in moves {
         if other == mv { continue; }
-        if board.piece_on(other.get_source()) == Some(piece)
-            && other.get_dest() == mv.get_dest()
-        {
-            ambiguous = true;
-            if other.get_source().file() == mv.get_source().file() {
-                needs_rank = true;
-            }
-            if other.get_source().rank() == mv.get_source().rank() {
-                needs_file = true;
-            }
+        ambiguous = true;
+        if other.get_source().file() == mv.get_source().file() {
+            needs_rank = true;
+        }
+        if other.get_source().rank() == mv.get_source().rank() {
+            needs_file = true;
         }
     }
 
@@ -161,30 +122,28 @@ fn simple_evaluate(board: &Board) -> i32 {
 
 #[test]
 fn test_bk_suite() {
-    ferrite::board::init();
-
     let content = fs::read_to_string("tests/bk.txt").expect("Could not read tests/bk.txt");
     let mut total = 0;
 
-    for line in content.lines() {
-        if let Some((fen, _best_moves, _id)) = parse_epd_line(line) {
-            total += 1;
-            let board = Board::from_str(&fen).expect(&format!("Invalid FEN: {}", fen));
-            let moves = MoveGen::new_legal(&board);
-            let mut best_move = None;
-            let mut best_score = i32::MIN;
-
-            for mv in moves {
-                let new_board = board.make_move_new(mv);
-                let score = -simple_evaluate(&new_board);
-                if score > best_score {
-                    best_score = score;
-                    best_move = Some(mv);
-                }
+    for line in content.lines().filter(|l| !l.trim().is_empty()) {
+        let record: EpdRecord = line.parse().unwrap_or_else(|e| panic!("Invalid EPD {}: {}", line, e));
+        total += 1;
+        let board = record.board;
+        assert!(!record.best_moves().unwrap().is_empty(), "{}", line);
+        let moves = MoveGen::new_legal(&board);
+        let mut best_move = None;
+        let mut best_score = i32::MIN;
+
+        for mv in moves {
+            let new_board = board.make_move_new(mv);
+            let score = -simple_evaluate(&new_board);
+            if score > best_score {
+                best_score = score;
+                best_move = Some(mv);
             }
-
-            assert!(best_move.is_some());
         }
+
+        assert!(best_move.is_some());
     }
 
     assert!(total == 24, "Expected 24 BK positions, got {}", total);
@@ -193,16 +152,15 @@ fn test_bk_suite() {
 #[test]
 fn test_epd_parser() {
     let line = "1k1r4/pp1b1R2/3q2pp/4p3/2B5/4Q3/PPP2B2/2K5 b - - bm Qd1+; id \"BK.01\";";
-    let (fen, moves, id) = parse_epd_line(line).unwrap();
-    assert_eq!(fen, "1k1r4/pp1b1R2/3q2pp/4p3/2B5/4Q3/PPP2B2/2K5 b - -");
-    assert_eq!(moves, vec!["Qd1+"]);
-    assert_eq!(id, "BK.01");
+    let record: EpdRecord = line.parse().unwrap();
+    assert_eq!(record.board, Board::from_fen_lenient("1k1r4/pp1b1R2/3q2pp/4p3/2B5/4Q3/PPP2B2/2K5 b - -").unwrap());
+    assert_eq!(record.operands("bm"), Some(&["Qd1+".to_string()][..]));
+    assert_eq!(record.id(), Some("BK.01"));
 }
 
 #[test]
 fn test_uci_to_san_conversion() {
-    ferrite::board::init();
-    let board = Board::from_str("1k1r4/pp1b1R2/3q2pp/4p3/2B5/4Q3/PPP2B2/2K5 b - -").unwrap();
+    let board = Board::from_fen_lenient("1k1r4/pp1b1R2/3q2pp/4p3/2B5/4Q3/PPP2B2/2K5 b - -").unwrap();
     let moves = MoveGen::new_legal(&board);
     for mv in moves {
         if mv.to_string() == "d6d1" {